@@ -55,6 +55,25 @@ impl Bio {
         Self(inner)
     }
 
+    /// Constructs a new `Bio` to discard `nsectors` sectors starting from `start_sid`.
+    ///
+    /// A discard `Bio` has no memory segments.
+    pub fn new_discard(
+        start_sid: Sid,
+        nsectors: u64,
+        complete_fn: Option<fn(&SubmittedBio)>,
+    ) -> Self {
+        let inner = Arc::new(BioInner {
+            type_: BioType::Discard,
+            sid_range: start_sid..start_sid + nsectors,
+            segments: Vec::new(),
+            complete_fn,
+            status: AtomicU32::new(BioStatus::Init as u32),
+            wait_queue: WaitQueue::new(),
+        });
+        Self(inner)
+    }
+
     /// Returns the type.
     pub fn type_(&self) -> BioType {
         self.0.type_()
@@ -5,7 +5,7 @@ use ostd::mm::{VmIo, VmReader, VmWriter};
 use super::{
     bio::{Bio, BioEnqueueError, BioSegment, BioStatus, BioType, BioWaiter, SubmittedBio},
     id::{Bid, Sid},
-    BlockDevice, BLOCK_SIZE,
+    BlockDevice, BLOCK_SIZE, SECTOR_SIZE,
 };
 use crate::{
     bio::{is_sector_aligned, BioDirection},
@@ -78,6 +78,16 @@ impl dyn BlockDevice {
         bio.submit(self)
     }
 
+    /// Synchronously discards `nblocks` contiguous blocks starting from the `bid`.
+    ///
+    /// Returns `BioStatus::NotSupported` if the device does not support discard.
+    pub fn discard_blocks(&self, bid: Bid, nblocks: usize) -> Result<BioStatus, BioEnqueueError> {
+        let nsectors = (nblocks * (BLOCK_SIZE / SECTOR_SIZE)) as u64;
+        let bio = Bio::new_discard(Sid::from(bid), nsectors, Some(general_complete_fn));
+        let status = bio.submit_and_wait(self)?;
+        Ok(status)
+    }
+
     /// Issues a sync request
     pub fn sync(&self) -> Result<BioStatus, BioEnqueueError> {
         let bio = Bio::new(
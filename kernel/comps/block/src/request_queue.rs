@@ -177,7 +177,8 @@ impl BioRequest {
 
     /// Returns `true` if can merge the `SubmittedBio`, `false` otherwise.
     pub fn can_merge(&self, rq_bio: &SubmittedBio) -> bool {
-        if rq_bio.type_() != self.type_ {
+        // Each discard bio is sent to the device as a separate request
+        if rq_bio.type_() != self.type_ || self.type_ == BioType::Discard {
             return false;
         }
 
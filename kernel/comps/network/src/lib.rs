@@ -69,16 +69,18 @@ pub trait AnyNetworkDevice: Send + Sync + Any + Debug {
 
 pub trait NetDeviceCallback = Fn() + Send + Sync + 'static;
 
+/// Registers a network device.
+///
+/// Each device must have a unique name.
 pub fn register_device(
     name: String,
     device: Arc<SpinLock<dyn AnyNetworkDevice, BottomHalfDisabled>>,
 ) {
-    COMPONENT
-        .get()
-        .unwrap()
-        .network_device_table
-        .lock()
-        .insert(name, NetworkDeviceIrqCallbackSet::new(device));
+    let mut device_table = COMPONENT.get().unwrap().network_device_table.lock();
+    // Devices are never unregistered, so the table length can serve as the registration order.
+    let order = device_table.len();
+    let old_device = device_table.insert(name, NetworkDeviceIrqCallbackSet::new(device, order));
+    assert!(old_device.is_none(), "the device name is not unique");
 }
 
 pub fn get_device(str: &str) -> Option<Arc<SpinLock<dyn AnyNetworkDevice, BottomHalfDisabled>>> {
@@ -161,10 +163,15 @@ pub fn raise_receive_softirq() {
     SoftIrqLine::get(NETWORK_RX_SOFTIRQ_ID).raise();
 }
 
+/// Returns all network devices in the order of registration.
 pub fn all_devices() -> Vec<(String, NetworkDeviceRef)> {
     let network_devs = COMPONENT.get().unwrap().network_device_table.lock();
-    network_devs
-        .iter()
+
+    let mut devices: Vec<_> = network_devs.iter().collect();
+    devices.sort_by_key(|(_, callbacks)| callbacks.order);
+
+    devices
+        .into_iter()
         .map(|(name, callbacks)| (name.clone(), callbacks.device.clone()))
         .collect()
 }
@@ -193,14 +200,17 @@ struct Component {
 /// The send callbacks and recv callbacks for a network device
 struct NetworkDeviceIrqCallbackSet {
     device: NetworkDeviceRef,
+    /// The registration order of the device.
+    order: usize,
     recv_callbacks: NetDeviceCallbackListRef,
     send_callbacks: NetDeviceCallbackListRef,
 }
 
 impl NetworkDeviceIrqCallbackSet {
-    fn new(device: NetworkDeviceRef) -> Self {
+    fn new(device: NetworkDeviceRef, order: usize) -> Self {
         Self {
             device,
+            order,
             recv_callbacks: Arc::new(SpinLock::new(Vec::new())),
             send_callbacks: Arc::new(SpinLock::new(Vec::new())),
         }
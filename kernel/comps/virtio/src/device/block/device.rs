@@ -19,7 +19,7 @@ use aster_block::{
 use id_alloc::IdAlloc;
 use log::{debug, info};
 use ostd::{
-    mm::{DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, VmIo},
+    mm::{DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, VmIo, PAGE_SIZE},
     sync::SpinLock,
     trap::TrapFrame,
     Pod,
@@ -92,7 +92,7 @@ impl BlockDevice {
             BioType::Read => self.device.read(request),
             BioType::Write => self.device.write(request),
             BioType::Flush => self.device.flush(request),
-            BioType::Discard => todo!(),
+            BioType::Discard => self.device.discard(request),
         }
     }
 
@@ -497,6 +497,94 @@ impl DeviceInner {
             return;
         }
     }
+
+    /// Discards sectors on the device, this function is non-blocking.
+    ///
+    /// The bios are completed with `BioStatus::NotSupported` if the device
+    /// doesn't support the `VIRTIO_BLK_F_DISCARD` feature.
+    fn discard(&self, bio_request: BioRequest) {
+        let segments = {
+            let max_sectors = self.config_manager.max_discard_sectors() as u64;
+            let mut segments = Vec::new();
+            let mut sector = bio_request.sid_range().start.to_raw();
+            let end = bio_request.sid_range().end.to_raw();
+            while sector < end {
+                let num_sectors = (end - sector).min(max_sectors);
+                segments.push(DiscardSegment {
+                    sector,
+                    num_sectors: num_sectors as u32,
+                    flags: 0,
+                });
+                sector += num_sectors;
+            }
+            segments
+        };
+        if !self.features.support_discard
+            || segments.len() > self.config_manager.max_discard_seg() as usize
+        {
+            bio_request.bios().for_each(|bio| {
+                bio.complete(BioStatus::NotSupported);
+            });
+            return;
+        }
+
+        let id = self.id_allocator.disable_irq().lock().alloc().unwrap();
+        let req_slice = {
+            let req_slice = DmaStreamSlice::new(&self.block_requests, id * REQ_SIZE, REQ_SIZE);
+            let req = BlockReq {
+                type_: ReqType::Discard as _,
+                reserved: 0,
+                sector: 0,
+            };
+            req_slice.write_val(0, &req).unwrap();
+            req_slice.sync().unwrap();
+            req_slice
+        };
+
+        let resp_slice = {
+            let resp_slice = DmaStreamSlice::new(&self.block_responses, id * RESP_SIZE, RESP_SIZE);
+            resp_slice.write_val(0, &BlockResp::default()).unwrap();
+            resp_slice
+        };
+
+        let payload_len = segments.len() * size_of::<DiscardSegment>();
+        let payload_stream = {
+            let segment = FrameAllocOptions::new()
+                .alloc_segment(payload_len.div_ceil(PAGE_SIZE))
+                .unwrap();
+            DmaStream::map(segment.into(), DmaDirection::ToDevice, false).unwrap()
+        };
+        let payload_slice = DmaStreamSlice::new(&payload_stream, 0, payload_len);
+        for (i, segment) in segments.iter().enumerate() {
+            payload_slice
+                .write_val(i * size_of::<DiscardSegment>(), segment)
+                .unwrap();
+        }
+        payload_slice.sync().unwrap();
+
+        let num_used_descs = 3;
+        loop {
+            let mut queue = self.queue.disable_irq().lock();
+            if num_used_descs > queue.available_desc() {
+                continue;
+            }
+            let token = queue
+                .add_dma_buf(&[&req_slice, &payload_slice], &[&resp_slice])
+                .expect("add queue failed");
+            if queue.should_notify() {
+                queue.notify();
+            }
+
+            // Records the submitted request
+            let submitted_request =
+                SubmittedRequest::with_payload(id as u16, bio_request, payload_stream.clone());
+            self.submitted_requests
+                .disable_irq()
+                .lock()
+                .insert(token, submitted_request);
+            return;
+        }
+    }
 }
 
 /// A submitted bio request for callback.
@@ -504,11 +592,25 @@ impl DeviceInner {
 struct SubmittedRequest {
     id: u16,
     bio_request: BioRequest,
+    /// The extra DMA buffer that the device reads, kept alive until the request completes.
+    _payload: Option<DmaStream>,
 }
 
 impl SubmittedRequest {
     pub fn new(id: u16, bio_request: BioRequest) -> Self {
-        Self { id, bio_request }
+        Self {
+            id,
+            bio_request,
+            _payload: None,
+        }
+    }
+
+    pub fn with_payload(id: u16, bio_request: BioRequest, payload: DmaStream) -> Self {
+        Self {
+            id,
+            bio_request,
+            _payload: Some(payload),
+        }
     }
 }
 
@@ -523,6 +625,15 @@ struct BlockReq {
 
 const REQ_SIZE: usize = size_of::<BlockReq>();
 
+/// A range of sectors to discard, the payload of a VirtIOBlock discard request.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod)]
+struct DiscardSegment {
+    pub sector: u64,
+    pub num_sectors: u32,
+    pub flags: u32,
+}
+
 /// Response of a VirtIOBlock request.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod)]
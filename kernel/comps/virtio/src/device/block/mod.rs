@@ -119,6 +119,7 @@ pub struct VirtioBlockTopology {
 #[repr(C)]
 pub struct VirtioBlockFeature {
     support_flush: bool,
+    support_discard: bool,
 }
 
 impl VirtioBlockConfig {
@@ -185,6 +186,22 @@ impl ConfigManager<VirtioBlockConfig> {
             .unwrap() as usize
     }
 
+    /// Returns the maximum number of sectors in one discard segment.
+    pub(self) fn max_discard_sectors(&self) -> u32 {
+        match self.read_once::<u32>(offset_of!(VirtioBlockConfig, max_discard_sectors)) {
+            Ok(0) | Err(_) => u32::MAX,
+            Ok(max_sectors) => max_sectors,
+        }
+    }
+
+    /// Returns the maximum number of segments in one discard command.
+    pub(self) fn max_discard_seg(&self) -> u32 {
+        match self.read_once::<u32>(offset_of!(VirtioBlockConfig, max_discard_seg)) {
+            Ok(0) | Err(_) => 1,
+            Ok(max_seg) => max_seg,
+        }
+    }
+
     pub(self) fn capacity_sectors(&self) -> usize {
         let cap_low = self
             .read_once::<u32>(offset_of!(VirtioBlockConfig, capacity))
@@ -199,7 +216,12 @@ impl ConfigManager<VirtioBlockConfig> {
 
 impl VirtioBlockFeature {
     pub(self) fn new(transport: &dyn VirtioTransport) -> Self {
-        let support_flush = transport.read_device_features() & BlockFeatures::FLUSH.bits() == 1;
-        VirtioBlockFeature { support_flush }
+        let device_features = transport.read_device_features();
+        let support_flush = device_features & BlockFeatures::FLUSH.bits() == 1;
+        let support_discard = device_features & BlockFeatures::DISCARD.bits() != 0;
+        VirtioBlockFeature {
+            support_flush,
+            support_discard,
+        }
     }
 }
@@ -1,7 +1,20 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use alloc::{format, string::String};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 pub mod config;
 pub mod device;
 pub mod header;
 
+/// The name prefix of virtio network devices.
+///
+/// Each device is named by the prefix and its probing order (e.g., `Virtio-Net0`).
 pub const DEVICE_NAME: &str = "Virtio-Net";
+
+/// Allocates a unique name for a new virtio network device.
+fn alloc_device_name() -> String {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    format!("{}{}", DEVICE_NAME, NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
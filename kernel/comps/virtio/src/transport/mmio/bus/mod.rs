@@ -112,40 +112,100 @@ fn x86_probe() {
 
 #[cfg(target_arch = "riscv64")]
 fn riscv_probe() {
+    use common_device::{mmio_check_magic, mmio_read_device_id, MmioCommonDevice};
+    use log::debug;
     use ostd::{
-        arch::{boot::DEVICE_TREE, kernel::PLIC},
+        arch::{
+            boot::{smp::bsp_hart_id, DEVICE_TREE},
+            kernel::{aplic::SourceMode, APLIC, IMSIC, PLIC},
+        },
         io::IoMem,
         trap::IrqLine,
     };
 
+    // The priority of the IRQs of the virtio-mmio devices. Any non-zero priority works because
+    // the PLIC threshold of each hart is zero.
+    const VIRTIO_MMIO_IRQ_PRIORITY: u32 = 6;
+
     let mut mmio_bus = MMIO_BUS.lock();
 
     let fdt = DEVICE_TREE.get().unwrap();
+
     for node in fdt.all_nodes() {
-        if let Some(compatible) = node.compatible() {
-            if compatible.all().any(|s| s == "virtio,mmio") {
-                use crate::transport::mmio::bus::common_device::MmioCommonDevice;
+        if !node
+            .compatible()
+            .is_some_and(|compatible| compatible.all().any(|s| s == "virtio,mmio"))
+        {
+            continue;
+        }
 
-                let region = node.reg().unwrap().next().unwrap();
-                let irq_num = node.interrupts().unwrap().next().unwrap();
+        // The first cell of the interrupt specifier is the interrupt source number, regardless of
+        // whether the interrupt parent is a PLIC (one cell) or an APLIC (two cells).
+        let (Some(region), Some(irq_num)) = (
+            node.reg().and_then(|mut reg| reg.next()),
+            node.property("interrupts")
+                .and_then(|interrupts| interrupts.value.first_chunk::<4>())
+                .map(|cell| u32::from_be_bytes(*cell)),
+        ) else {
+            debug!(
+                "[Virtio]: Ignore MMIO node {} without MMIO region or IRQ",
+                node.name
+            );
+            continue;
+        };
 
-                let io_mem = IoMem::acquire(
-                    region.starting_address as usize
-                        ..region.starting_address as usize + region.size.unwrap(),
-                )
-                .unwrap();
+        let mmio_base = region.starting_address as usize;
+        let Ok(io_mem) = IoMem::acquire(mmio_base..mmio_base + region.size.unwrap()) else {
+            debug!(
+                "[Virtio]: Ignore MMIO device at {:#x} because the MMIO address is not available",
+                mmio_base
+            );
+            continue;
+        };
 
-                let irq = IrqLine::alloc_specific(irq_num as _).unwrap();
+        // We now check the requirements specified in Virtual I/O Device (VIRTIO) Version 1.3,
+        // Section 4.2.2.2 Driver Requirements: MMIO Device Register Layout.
 
-                PLIC.get().unwrap().set_priority(irq_num as _, 6);
-                PLIC.get().unwrap().enable(0, true, irq_num as _);
+        // "The driver MUST ignore a device with MagicValue which is not 0x74726976, although it
+        // MAY report an error."
+        if !mmio_check_magic(&io_mem) {
+            debug!(
+                "[Virtio]: Ignore MMIO device at {:#x} because the magic number does not match",
+                mmio_base
+            );
+            continue;
+        }
+
+        // "The driver MUST ignore a device with DeviceID 0x0, but MUST NOT report any error."
+        //
+        // QEMU's `virt` machine always describes a fixed number of virtio-mmio transports in the
+        // device tree, and the ones without any device attached have a device ID of zero.
+        match mmio_read_device_id(&io_mem) {
+            Err(_) | Ok(0) => continue,
+            Ok(_) => (),
+        }
 
-                let device = MmioCommonDevice::new(io_mem, irq);
+        let Ok(irq_line) = IrqLine::alloc_specific(irq_num as _) else {
+            debug!(
+                "[Virtio]: Ignore MMIO device at {:#x} because its IRQ line is not available",
+                mmio_base
+            );
+            continue;
+        };
 
-                mmio_bus.register_mmio_device(device);
-            }
+        if let Some(aplic) = APLIC.get() {
+            // The APLIC forwards the interrupts to the IMSIC, which always has an interrupt file
+            // for the BSP. The interrupts of the virtio-mmio devices are level-triggered.
+            let imsic = IMSIC.get().unwrap();
+            let file_index = imsic.file_index(bsp_hart_id()).unwrap();
+            aplic.enable(irq_num, SourceMode::LevelHigh, file_index);
+        } else if let Some(plic) = PLIC.get() {
+            // TODO: Route the IRQs to the hart of the BSP instead of hart 0.
+            plic.set_priority(irq_num as _, VIRTIO_MMIO_IRQ_PRIORITY);
+            plic.enable(0, true, irq_num as _);
         }
-    }
 
-    PLIC.get().unwrap().set_threshold(0, true, 0);
+        let device = MmioCommonDevice::new(io_mem, irq_line);
+        mmio_bus.register_mmio_device(device);
+    }
 }
@@ -4,7 +4,7 @@
 //! init destroy lookup forget getattr setattr readlink mknod mkdir
 //! unlink rmdir symlink rename link open read write flush release
 //! fsync opendir readdir releasedir fsyncdir statfs setxattr getxattr
-//! listxattr removexattr access create getlk ioctl
+//! listxattr removexattr access create getlk ioctl fallocate
 //!
 //! Rust crate `fuser` doesn't have the detailed explantion of these interfaces.
 //! See `fuse_lowlevel_ops` in C FUSE library for details.
@@ -17,7 +17,9 @@
 
 use super::common::{sys_time2second, time_or_now2second, translate_attr, translate_ftype};
 use crate::block_dev::StateBlockDevice;
-use another_ext4::{ErrCode, Ext4, Ext4Error, FileType as Ext4FileType, InodeMode};
+use another_ext4::{
+    ErrCode, Ext4, Ext4Error, FallocFlags, FileType as Ext4FileType, InodeMode,
+};
 use fuser::{
     FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
     ReplyEntry, ReplyOpen, ReplyWrite, Request,
@@ -228,6 +230,28 @@ impl<T: 'static> Filesystem for StateExt4FuseFs<T> {
         }
     }
 
+    fn fallocate(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        length: i64,
+        mode: i32,
+        reply: ReplyEmpty,
+    ) {
+        let Some(flags) = FallocFlags::from_bits(mode as u32) else {
+            return reply.error(ErrCode::ENOTSUP as i32);
+        };
+        match self
+            .fs
+            .fallocate(ino as u32, offset as usize, length as usize, flags)
+        {
+            Ok(_) => reply.ok(),
+            Err(e) => reply.error(e.code() as i32),
+        }
+    }
+
     fn release(
         &mut self,
         _req: &Request<'_>,
@@ -263,7 +287,7 @@ impl<T: 'static> Filesystem for StateExt4FuseFs<T> {
     }
 
     fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
-        match self.fs.unlink(parent as u32, name.to_str().unwrap()) {
+        match self.fs.unlink(parent as u32, name.to_str().unwrap(), true) {
             Ok(_) => reply.ok(),
             Err(e) => reply.error(e.code() as i32),
         }
@@ -290,7 +314,10 @@ impl<T: 'static> Filesystem for StateExt4FuseFs<T> {
                     && self.fs.listdir(des).unwrap().len() <= 2
                 {
                     // Overwrite empty directory
-                    if let Err(e) = self.fs.rmdir(newparent as u32, newname.to_str().unwrap()) {
+                    if let Err(e) = self
+                        .fs
+                        .rmdir(newparent as u32, newname.to_str().unwrap(), true)
+                    {
                         return reply.error(e.code() as i32);
                     }
                 } else {
@@ -391,7 +418,7 @@ impl<T: 'static> Filesystem for StateExt4FuseFs<T> {
     }
 
     fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
-        match self.fs.rmdir(parent as u32, name.to_str().unwrap()) {
+        match self.fs.rmdir(parent as u32, name.to_str().unwrap(), true) {
             Ok(()) => reply.ok(),
             Err(e) => reply.error(e.code() as i32),
         }
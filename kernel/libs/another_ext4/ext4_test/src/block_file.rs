@@ -1,6 +1,7 @@
 use another_ext4::{Block, BlockDevice, BLOCK_SIZE};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug)]
 pub struct BlockFile(File);
@@ -33,3 +34,44 @@ impl BlockDevice for BlockFile {
         let _r = file.write_all(&block.data);
     }
 }
+
+/// A block file that crashes right after a journal commit block is written.
+/// All later writes are dropped, so the committed transaction is never
+/// checkpointed.
+#[derive(Debug)]
+pub struct CrashBlockFile {
+    file: BlockFile,
+    crashed: AtomicBool,
+}
+
+impl CrashBlockFile {
+    /// The magic number and the block type of a journal commit block.
+    const COMMIT_HEADER: [u8; 8] = [0xC0, 0x3B, 0x39, 0x98, 0, 0, 0, 2];
+
+    pub fn new(path: &str) -> Self {
+        Self {
+            file: BlockFile::new(path),
+            crashed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.crashed.load(Ordering::Relaxed)
+    }
+}
+
+impl BlockDevice for CrashBlockFile {
+    fn read_block(&self, block_id: u64) -> Block {
+        self.file.read_block(block_id)
+    }
+
+    fn write_block(&self, block: &Block) {
+        if self.crashed() {
+            return;
+        }
+        self.file.write_block(block);
+        if block.data[..8] == Self::COMMIT_HEADER {
+            self.crashed.store(true, Ordering::Relaxed);
+        }
+    }
+}
@@ -1,5 +1,8 @@
-use another_ext4::{Ext4, InodeMode, EXT4_ROOT_INO};
-use block_file::BlockFile;
+use another_ext4::{
+    BlockDevice, ErrCode, Ext4, FallocFlags, InodeFlags, InodeMode, BLOCK_SIZE, EXT4_ROOT_INO,
+    INODE_BLOCK_SIZE,
+};
+use block_file::{BlockFile, CrashBlockFile};
 use simple_logger::SimpleLogger;
 use std::sync::Arc;
 
@@ -125,6 +128,236 @@ fn xattr_test(ext4: &mut Ext4) {
         .expect_err("getxattr failed");
     let names = ext4.listxattr(file).expect("listxattr failed");
     assert_eq!(names, vec!["user.testtwo"]);
+
+    // Large values do not fit in the inode
+    let large_value = vec![42u8; 1000];
+    ext4.setxattr(file, "trusted.large", &large_value)
+        .expect("setxattr failed");
+    ext4.setxattr(file, "security.testthree", "hello".as_bytes())
+        .expect("setxattr failed");
+    let value = ext4
+        .getxattr(file, "trusted.large")
+        .expect("getxattr failed");
+    assert_eq!(value, large_value);
+    // Replace the old value
+    ext4.setxattr(file, "user.testtwo", "hello again".as_bytes())
+        .expect("setxattr failed");
+    let value = ext4
+        .getxattr(file, "user.testtwo")
+        .expect("getxattr failed");
+    assert_eq!(value, "hello again".as_bytes());
+    let mut names = ext4.listxattr(file).expect("listxattr failed");
+    names.sort();
+    assert_eq!(
+        names,
+        vec!["security.testthree", "trusted.large", "user.testtwo"]
+    );
+    ext4.removexattr(file, "trusted.large")
+        .expect("removexattr failed");
+    ext4.getxattr(file, "trusted.large")
+        .expect_err("getxattr failed");
+}
+
+fn fallocate_test(ext4: &mut Ext4) {
+    let file_mode: InodeMode = InodeMode::FILE | InodeMode::ALL_RWX;
+    let file = ext4
+        .generic_create(ROOT_INO, "f3", file_mode)
+        .expect("Create failed");
+    let mut expected = vec![1u8; 16 * BLOCK_SIZE];
+    ext4.write(file, 0, &expected).expect("write failed");
+
+    // Punch a hole across block boundaries
+    ext4.fallocate(
+        file,
+        BLOCK_SIZE + 100,
+        2 * BLOCK_SIZE,
+        FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+    )
+    .expect("punch hole failed");
+    expected[BLOCK_SIZE + 100..3 * BLOCK_SIZE + 100].fill(0);
+    // Zero a range
+    ext4.fallocate(file, 5 * BLOCK_SIZE - 10, BLOCK_SIZE + 20, FallocFlags::ZERO_RANGE)
+        .expect("zero range failed");
+    expected[5 * BLOCK_SIZE - 10..6 * BLOCK_SIZE + 10].fill(0);
+    // Collapse a range
+    ext4.fallocate(file, 8 * BLOCK_SIZE, 2 * BLOCK_SIZE, FallocFlags::COLLAPSE_RANGE)
+        .expect("collapse range failed");
+    expected.drain(8 * BLOCK_SIZE..10 * BLOCK_SIZE);
+    // Preallocate beyond the end of file
+    ext4.fallocate(file, 0, 32 * BLOCK_SIZE, FallocFlags::KEEP_SIZE)
+        .expect("fallocate failed");
+    assert_eq!(ext4.getattr(file).unwrap().size, expected.len() as u64);
+    ext4.write(file, 20 * BLOCK_SIZE + 1, &[2u8; 10])
+        .expect("write failed");
+    expected.resize(20 * BLOCK_SIZE + 1, 0);
+    expected.extend_from_slice(&[2u8; 10]);
+
+    let mut rbuffer = vec![0xffu8; expected.len() + 10];
+    let rcount = ext4.read(file, 0, &mut rbuffer).expect("read failed");
+    assert_eq!(expected, &rbuffer[..rcount]);
+
+    // Fragment the file to build a multi-level extent tree
+    ext4.fallocate(file, 0, 2000 * BLOCK_SIZE, FallocFlags::empty())
+        .expect("fallocate failed");
+    for i in 0..1000 {
+        ext4.fallocate(
+            file,
+            (2 * i + 1) * BLOCK_SIZE,
+            BLOCK_SIZE,
+            FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+        )
+        .expect("punch hole failed");
+    }
+    ext4.write(file, 1001 * BLOCK_SIZE, &[4u8; 10])
+        .expect("write failed");
+    ext4.fallocate(file, 0, 2000 * BLOCK_SIZE, FallocFlags::empty())
+        .expect("fallocate failed");
+    ext4.write(file, 1999 * BLOCK_SIZE, &[3u8; BLOCK_SIZE])
+        .expect("write failed");
+    let mut rbuffer = vec![0xffu8; BLOCK_SIZE];
+    ext4.read(file, 1999 * BLOCK_SIZE, &mut rbuffer)
+        .expect("read failed");
+    assert_eq!(rbuffer, [3u8; BLOCK_SIZE]);
+    ext4.read(file, 1997 * BLOCK_SIZE, &mut rbuffer)
+        .expect("read failed");
+    assert_eq!(rbuffer, [0u8; BLOCK_SIZE]);
+
+    // Preallocated blocks are contiguous, and extent tree blocks are counted
+    let file = ext4
+        .generic_create(ROOT_INO, "f6", file_mode)
+        .expect("Create failed");
+    ext4.fallocate(file, 0, 64 * BLOCK_SIZE, FallocFlags::empty())
+        .expect("fallocate failed");
+    let extents = ext4.fiemap(file).expect("fiemap failed");
+    assert_eq!(extents.len(), 1);
+    assert_eq!(extents[0].block_count(), 64);
+    for i in 0..16 {
+        ext4.fallocate(
+            file,
+            (4 * i + 1) * BLOCK_SIZE,
+            BLOCK_SIZE,
+            FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+        )
+        .expect("punch hole failed");
+    }
+    assert_eq!(ext4.fiemap(file).expect("fiemap failed").len(), 17);
+    // 48 data blocks and 2 leaf blocks split from the root
+    let sectors_per_block = (BLOCK_SIZE / INODE_BLOCK_SIZE) as u64;
+    assert_eq!(ext4.getattr(file).unwrap().blocks, 50 * sectors_per_block);
+    ext4.fallocate(
+        file,
+        0,
+        64 * BLOCK_SIZE,
+        FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+    )
+    .expect("punch hole failed");
+    assert!(ext4.fiemap(file).expect("fiemap failed").is_empty());
+    assert_eq!(ext4.getattr(file).unwrap().blocks, 0);
+}
+
+fn ioctl_test(ext4: &mut Ext4) {
+    let file_mode: InodeMode = InodeMode::FILE | InodeMode::ALL_RWX;
+    let file = ext4
+        .generic_create(ROOT_INO, "f4", file_mode)
+        .expect("Create failed");
+
+    let flags = ext4.getflags(file).expect("getflags failed");
+    assert!(flags.contains(InodeFlags::EXTENTS));
+    ext4.setflags(file, flags | InodeFlags::APPEND | InodeFlags::NOATIME)
+        .expect("setflags failed");
+    let new_flags = ext4.getflags(file).expect("getflags failed");
+    assert_eq!(new_flags, flags | InodeFlags::APPEND | InodeFlags::NOATIME);
+    ext4.setflags(file, flags - InodeFlags::EXTENTS)
+        .expect_err("clearing extents flag should fail");
+    ext4.setflags(file, flags).expect("setflags failed");
+
+    ext4.write(file, 0, &[1u8; 4 * BLOCK_SIZE])
+        .expect("write failed");
+    ext4.fallocate(
+        file,
+        BLOCK_SIZE,
+        BLOCK_SIZE,
+        FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+    )
+    .expect("punch hole failed");
+    let extents = ext4.fiemap(file).expect("fiemap failed");
+    assert_eq!(extents.len(), 2);
+    assert_eq!(extents[0].start_lblock(), 0);
+    assert_eq!(extents[0].block_count(), 1);
+    assert_eq!(extents[1].start_lblock(), 2);
+    assert_eq!(extents[1].block_count(), 2);
+
+    let mut discarded = Vec::new();
+    let trimmed = ext4
+        .fstrim(0, u64::MAX, 1, |start, len| {
+            discarded.push((start, len));
+            Ok(())
+        })
+        .expect("fstrim failed");
+    assert_eq!(trimmed, discarded.iter().map(|(_, len)| len).sum::<u64>());
+    for ex in extents.iter() {
+        let (start, end) = (ex.start_pblock(), ex.start_pblock() + ex.block_count() as u64);
+        assert!(discarded
+            .iter()
+            .all(|&(s, len)| s + len <= start || s >= end));
+    }
+}
+
+fn checksum_test(ext4: &mut Ext4) {
+    let file = ext4
+        .generic_lookup(ROOT_INO, "d1/d2/d3/d4/f1")
+        .expect("Lookup failed");
+    ext4.flush_all();
+
+    // Reload the filesystem and check the metadata written so far
+    let reloaded = Ext4::load(Arc::new(BlockFile::new("ext4.img"))).expect("reload failed");
+    assert!(!reloaded.read_only());
+    reloaded.getattr(file).expect("getattr failed");
+
+    // Corrupt the superblock checksum
+    let mut sb_block = BlockFile::new("ext4.img").read_block(0);
+    sb_block.data[1024 + 1020] ^= 0xff;
+    BlockFile::new("ext4.img").write_block(&sb_block);
+    let err = Ext4::load(Arc::new(BlockFile::new("ext4.img")))
+        .err()
+        .expect("load should fail");
+    assert_eq!(err.code(), ErrCode::EBADMSG);
+    sb_block.data[1024 + 1020] ^= 0xff;
+    BlockFile::new("ext4.img").write_block(&sb_block);
+}
+
+fn journal_test(ext4: &mut Ext4) {
+    let file_mode: InodeMode = InodeMode::FILE | InodeMode::ALL_RWX;
+    let file = ext4
+        .generic_create(ROOT_INO, "f5", file_mode)
+        .expect("Create failed");
+    ext4.flush_all();
+
+    // Crash after the write is committed to the journal, but before it is
+    // checkpointed to the final locations
+    let device = Arc::new(CrashBlockFile::new("ext4.img"));
+    let crashing = Ext4::load(device.clone()).expect("load failed");
+    let wbuffer = vec![5u8; 3 * BLOCK_SIZE + 10];
+    crashing.write(file, 0, &wbuffer).expect("write failed");
+    assert!(device.crashed());
+    drop(crashing);
+
+    // The superblock must tell other implementations to replay the journal
+    let sb_block = BlockFile::new("ext4.img").read_block(0);
+    let feature_incompat =
+        u32::from_le_bytes(sb_block.data[1024 + 0x60..][..4].try_into().unwrap());
+    assert_ne!(feature_incompat & 0x4, 0, "needs_recovery is not set");
+
+    // Replay the journal on mount
+    let recovered = Ext4::load(Arc::new(BlockFile::new("ext4.img"))).expect("recover failed");
+    assert_eq!(recovered.getattr(file).unwrap().size, wbuffer.len() as u64);
+    let mut rbuffer = vec![0u8; wbuffer.len()];
+    let rcount = recovered.read(file, 0, &mut rbuffer).expect("read failed");
+    assert_eq!(wbuffer, &rbuffer[..rcount]);
+    let sb_block = BlockFile::new("ext4.img").read_block(0);
+    let feature_incompat =
+        u32::from_le_bytes(sb_block.data[1024 + 0x60..][..4].try_into().unwrap());
+    assert_eq!(feature_incompat & 0x4, 0, "needs_recovery is not cleared");
 }
 
 fn main() {
@@ -146,4 +379,12 @@ fn main() {
     println!("remove file test done");
     xattr_test(&mut ext4);
     println!("xattr test done");
+    fallocate_test(&mut ext4);
+    println!("fallocate test done");
+    ioctl_test(&mut ext4);
+    println!("ioctl test done");
+    checksum_test(&mut ext4);
+    println!("checksum test done");
+    journal_test(&mut ext4);
+    println!("journal test done");
 }
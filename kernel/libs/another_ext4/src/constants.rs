@@ -32,10 +32,16 @@ pub const INODE_BLOCK_SIZE: usize = 512;
 /// CRC32 initial value
 pub const CRC32_INIT: u32 = 0xFFFFFFFF;
 
+/// CRC16 initial value
+pub const CRC16_INIT: u16 = 0xFFFF;
+
 /// The value of super block `inode_size` field.
 /// We implement the larger version of inode size for simplicity.
 pub const SB_GOOD_INODE_SIZE: usize = 256;
 
+/// The size of the original ext2 inode, the base of `inode.extra_isize`.
+pub const EXT4_GOOD_OLD_INODE_SIZE: usize = 128;
+
 /// The value of super block `desc_size` field.
 /// We implement the 64-bit block group descriptor for simplicity.
 pub const SB_GOOD_DESC_SIZE: usize = 64;
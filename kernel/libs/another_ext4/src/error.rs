@@ -47,12 +47,16 @@ pub enum ErrCode {
     ENOTEMPTY = 39,
     /// No data available.
     ENODATA = 61,
+    /// Bad message, a metadata checksum mismatches.
+    EBADMSG = 74,
     /// Not supported.
     ENOTSUP = 95,
     /// Link failed.
     ELINKFAIL = 97,
     /// Inode alloc failed.
     EALLOCFAIL = 98,
+    /// Structure needs cleaning, the filesystem is corrupted.
+    EUCLEAN = 117,
 }
 
 /// error used in this crate
@@ -49,29 +49,23 @@ impl Ext4 {
     /// Free an allocated inode and all data blocks allocated for it
     pub(super) fn free_inode(&self, inode: &mut InodeRef) -> Result<()> {
         // Free the data blocks allocated for the inode
-        let pblocks = self.extent_all_data_blocks(inode);
+        let pblocks = self.extent_all_data_blocks(inode)?;
         for pblock in pblocks {
             // Deallocate the block
             self.dealloc_block(inode, pblock)?;
             // Clear the block content
-            self.write_block(&Block::new(pblock, [0; BLOCK_SIZE]));
+            self.write_data_block(&Block::new(pblock, [0; BLOCK_SIZE]));
         }
         // Free extent tree
-        let pblocks = self.extent_all_tree_blocks(inode);
+        let pblocks = self.extent_all_tree_blocks(inode)?;
         for pblock in pblocks {
             // Deallocate the block
             self.dealloc_block(inode, pblock)?;
             // Clear the block content
             self.write_block(&Block::new(pblock, [0; BLOCK_SIZE]));
         }
-        // Free xattr block
-        let xattr_block = inode.inode.xattr_block();
-        if xattr_block != 0 {
-            // Deallocate the block
-            self.dealloc_block(inode, xattr_block)?;
-            // Clear the block content
-            self.write_block(&Block::new(xattr_block, [0; BLOCK_SIZE]));
-        }
+        // Free xattr block, unless it is shared with other inodes
+        self.xattr_release_block(inode)?;
         // Deallocate the inode
         self.dealloc_inode(inode)?;
         Ok(())
@@ -79,9 +73,8 @@ impl Ext4 {
 
     /// Append a data block for an inode, return a pair of (logical block id, physical block id)
     ///
-    /// Only data blocks allocated by `inode_append_block` will be counted in `inode.block_count`.
-    /// Blocks allocated by calling `alloc_block` directly will not be counted, i.e., blocks
-    /// allocated for the inode's extent tree.
+    /// Both the data blocks and the blocks allocated for the inode's extent tree are counted in
+    /// `inode.block_count`, so the new logical block id is derived from `inode.size`.
     ///
     /// Appending a block does not increase `inode.size`, because `inode.size` records the actual
     /// size of the data content, not the number of blocks allocated for it.
@@ -91,64 +84,166 @@ impl Ext4 {
     /// newly created block.
     pub(super) fn inode_append_block(&self, inode: &mut InodeRef) -> Result<(LBlockId, PBlockId)> {
         // The new logical block id
-        let iblock = inode.inode.fs_block_count() as LBlockId;
+        let iblock = inode.inode.size().div_ceil(BLOCK_SIZE as u64) as LBlockId;
         // Check the extent tree to get the physical block id
         let fblock = self.extent_query_or_create(inode, iblock, 1)?;
         // Update block count
-        inode.inode.set_fs_block_count(iblock as u64 + 1);
-        self.write_inode_without_csum(inode);
+        let block_count = inode.inode.fs_block_count() + 1;
+        inode.inode.set_fs_block_count(block_count);
+        self.write_inode_with_csum(inode);
 
         Ok((iblock, fblock))
     }
 
-    /// Allocate a new physical block for an inode, return the physical block number
+    /// Call `discard` on each free extent of block group `bgid` that lies in
+    /// the physical block range `[start, end)` and is at least `min_len` long.
+    /// Return the number of blocks discarded.
+    pub(super) fn trim_group(
+        &self,
+        bgid: BlockGroupId,
+        start: PBlockId,
+        end: PBlockId,
+        min_len: u64,
+        discard: &mut impl FnMut(PBlockId, u64) -> Result<()>,
+    ) -> Result<u64> {
+        let sb = self.read_super_block();
+        let group_start = sb.first_data_block() as PBlockId
+            + bgid as PBlockId * sb.blocks_per_group() as PBlockId;
+        let block_count = sb.block_count_in_group(bgid) as usize;
+        let group_end = group_start + block_count as PBlockId;
+        if group_end <= start || group_start >= end {
+            return Ok(0);
+        }
+        let bg = self.read_block_group(bgid);
+        // Skip the full groups and the groups whose bitmap is not initialized
+        if bg.desc.get_free_blocks_count() == 0 || bg.desc.is_block_uninit() {
+            return Ok(0);
+        }
+
+        let mut bitmap_block = self.read_block(bg.desc.block_bitmap_block());
+        let bitmap = Bitmap::new(&mut bitmap_block.data, block_count);
+        let first = (start.max(group_start) - group_start) as usize;
+        let last = (end.min(group_end) - group_start) as usize;
+        let mut trimmed = 0;
+        let mut bit = first;
+        while let Some(free_start) = bitmap.first_clear_bit(bit, last) {
+            let mut free_end = free_start + 1;
+            while free_end < last && bitmap.is_bit_clear(free_end) {
+                free_end += 1;
+            }
+            let len = (free_end - free_start) as u64;
+            if len >= min_len {
+                discard(group_start + free_start as PBlockId, len)?;
+                trimmed += len;
+            }
+            bit = free_end;
+        }
+        Ok(trimmed)
+    }
+
+    /// Allocate a new physical block for an inode, return the physical block number.
+    ///
+    /// The search starts from the block group of the inode.
     pub(super) fn alloc_block(&self, inode: &mut InodeRef) -> Result<PBlockId> {
-        let mut sb = self.read_super_block();
+        self.alloc_blocks(inode, None, 1).map(|(fblock, _)| fblock)
+    }
 
-        // Calc block group id
-        let inodes_per_group = sb.inodes_per_group();
-        let bgid = ((inode.id - 1) / inodes_per_group) as BlockGroupId;
+    /// Allocate at most `count` contiguous physical blocks for an inode, return the first
+    /// physical block number and the number of blocks allocated.
+    ///
+    /// The search starts from `goal` if specified, so that the new blocks can continue an
+    /// existing extent. Otherwise, the search starts from the block group of the inode.
+    pub(super) fn alloc_blocks(
+        &self,
+        inode: &mut InodeRef,
+        goal: Option<PBlockId>,
+        count: u32,
+    ) -> Result<(PBlockId, u32)> {
+        let mut sb = self.read_super_block();
+        let bg_count = sb.block_group_count();
+        let first_data_block = sb.first_data_block() as PBlockId;
+        let blocks_per_group = sb.blocks_per_group() as PBlockId;
+
+        // Calc block group id and index in block group to start the search
+        let (start_bgid, goal_idx) = match goal {
+            Some(goal) if goal >= first_data_block && goal < sb.block_count() => {
+                let rel_block = goal - first_data_block;
+                (
+                    (rel_block / blocks_per_group) as BlockGroupId,
+                    (rel_block % blocks_per_group) as usize,
+                )
+            }
+            _ => {
+                let inodes_per_group = sb.inodes_per_group();
+                (((inode.id - 1) / inodes_per_group) as BlockGroupId, 0)
+            }
+        };
 
-        // Load block group descriptor
-        let mut bg = self.read_block_group(bgid);
+        for i in 0..bg_count {
+            let bgid = (start_bgid + i) % bg_count;
+            // Load block group descriptor
+            let mut bg = self.read_block_group(bgid);
+            // Skip the full groups and the groups whose bitmap is not initialized
+            if bg.desc.get_free_blocks_count() == 0 || bg.desc.is_block_uninit() {
+                continue;
+            }
 
-        // Load block bitmap
-        let bitmap_block_id = bg.desc.block_bitmap_block();
-        let mut bitmap_block = self.read_block(bitmap_block_id);
-        let mut bitmap = Bitmap::new(&mut bitmap_block.data, 8 * BLOCK_SIZE);
-
-        // Find the first free block
-        let fblock = bitmap
-            .find_and_set_first_clear_bit(0, 8 * BLOCK_SIZE)
-            .ok_or(format_error!(
-                ErrCode::ENOSPC,
-                "No free blocks in block group {}",
-                bgid
-            ))? as PBlockId;
-        // Set block group checksum
-        bg.desc.set_block_bitmap_csum(&sb.uuid(), &bitmap);
-        self.write_block(&bitmap_block);
+            // Load block bitmap
+            let bitmap_block_id = bg.desc.block_bitmap_block();
+            let mut bitmap_block = self.read_block(bitmap_block_id);
+            let block_count = sb.block_count_in_group(bgid) as usize;
+            let mut bitmap = Bitmap::new(&mut bitmap_block.data, block_count);
+
+            // Find the first free block, starting from the goal in its block group
+            let search_start = if i == 0 { goal_idx } else { 0 };
+            let Some(idx_in_bg) = bitmap
+                .first_clear_bit(search_start, block_count)
+                .or_else(|| bitmap.first_clear_bit(0, search_start))
+            else {
+                continue;
+            };
+            // Extend the run of free blocks
+            let mut len = 1;
+            while len < count as usize
+                && idx_in_bg + len < block_count
+                && bitmap.is_bit_clear(idx_in_bg + len)
+            {
+                len += 1;
+            }
+            for bit in idx_in_bg..idx_in_bg + len {
+                bitmap.set_bit(bit);
+            }
+            // Set block group checksum
+            bg.desc.set_block_bitmap_csum(&sb, &bitmap_block.data);
+            self.write_block(&bitmap_block);
 
-        // Update block group counters
-        bg.desc
-            .set_free_blocks_count(bg.desc.get_free_blocks_count() - 1);
-        self.write_block_group_with_csum(&mut bg);
+            // Update block group counters
+            bg.desc
+                .set_free_blocks_count(bg.desc.get_free_blocks_count() - len as u64);
+            self.write_block_group_with_csum(&mut bg);
 
-        // Update superblock counters
-        sb.set_free_blocks_count(sb.free_blocks_count() - 1);
-        self.write_super_block(&sb);
+            // Update superblock counters
+            sb.set_free_blocks_count(sb.free_blocks_count() - len as u64);
+            self.write_super_block(&sb);
 
-        trace!("Alloc block {} ok", fblock);
-        Ok(fblock)
+            // Compute the absolute block number
+            let fblock =
+                first_data_block + bgid as PBlockId * blocks_per_group + idx_in_bg as PBlockId;
+            trace!("Alloc blocks {}+{} ok", fblock, len);
+            return Ok((fblock, len as u32));
+        }
+        return_error!(ErrCode::ENOSPC, "No free blocks");
     }
 
     /// Deallocate a physical block allocated for an inode
-    pub(super) fn dealloc_block(&self, inode: &mut InodeRef, pblock: PBlockId) -> Result<()> {
+    pub(super) fn dealloc_block(&self, _inode: &mut InodeRef, pblock: PBlockId) -> Result<()> {
         let mut sb = self.read_super_block();
 
-        // Calc block group id
-        let inodes_per_group = sb.inodes_per_group();
-        let bgid = ((inode.id - 1) / inodes_per_group) as BlockGroupId;
+        // Calc block group id and index in block group
+        let blocks_per_group = sb.blocks_per_group() as PBlockId;
+        let rel_block = pblock - sb.first_data_block() as PBlockId;
+        let bgid = (rel_block / blocks_per_group) as BlockGroupId;
+        let idx_in_bg = (rel_block % blocks_per_group) as usize;
 
         // Load block group descriptor
         let mut bg = self.read_block_group(bgid);
@@ -156,15 +251,18 @@ impl Ext4 {
         // Load block bitmap
         let bitmap_block_id = bg.desc.block_bitmap_block();
         let mut bitmap_block = self.read_block(bitmap_block_id);
-        let mut bitmap = Bitmap::new(&mut bitmap_block.data, 8 * BLOCK_SIZE);
+        let block_count = sb.block_count_in_group(bgid) as usize;
+        let mut bitmap = Bitmap::new(&mut bitmap_block.data, block_count);
 
         // Free the block
-        if bitmap.is_bit_clear(pblock as usize) {
+        if bitmap.is_bit_clear(idx_in_bg) {
             return_error!(ErrCode::EINVAL, "Block {} is already free", pblock);
         }
-        bitmap.clear_bit(pblock as usize);
+        bitmap.clear_bit(idx_in_bg);
+        // The block content must not be written back by the running transaction
+        self.trans_forget(pblock);
         // Set block group checksum
-        bg.desc.set_block_bitmap_csum(&sb.uuid(), &bitmap);
+        bg.desc.set_block_bitmap_csum(&sb, &bitmap_block.data);
         self.write_block(&bitmap_block);
 
         // Update block group counters
@@ -210,7 +308,7 @@ impl Ext4 {
                         bgid
                     ))? as u32;
             // Update bitmap in disk
-            bg.desc.set_inode_bitmap_csum(&sb.uuid(), &bitmap);
+            bg.desc.set_inode_bitmap_csum(&sb, &bitmap_block.data);
             self.write_block(&bitmap_block);
 
             // Modify block group counters
@@ -267,7 +365,7 @@ impl Ext4 {
         }
         bitmap.clear_bit(idx_in_bg as usize);
         // Update bitmap in disk
-        bg.desc.set_inode_bitmap_csum(&sb.uuid(), &bitmap);
+        bg.desc.set_inode_bitmap_csum(&sb, &bitmap_block.data);
         self.write_block(&bitmap_block);
 
         // Update block group counters
@@ -285,7 +383,7 @@ impl Ext4 {
 
         // Clear inode content
         inode_ref.inode = unsafe { mem::zeroed() };
-        self.write_inode_without_csum(inode_ref);
+        self.write_inode_with_csum(inode_ref);
 
         Ok(())
     }
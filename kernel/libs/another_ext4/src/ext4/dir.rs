@@ -8,7 +8,7 @@ impl Ext4 {
     /// Find a directory entry that matches a given name under a parent directory
     pub(super) fn dir_find_entry(&self, dir: &InodeRef, name: &str) -> Result<InodeId> {
         trace!("Dir find entry: dir {}, name {}", dir.id, name);
-        let total_blocks = dir.inode.fs_block_count() as u32;
+        let total_blocks = dir.inode.size().div_ceil(BLOCK_SIZE as u64) as u32;
         let mut iblock: LBlockId = 0;
         while iblock < total_blocks {
             // Get the fs block id
@@ -43,7 +43,7 @@ impl Ext4 {
             child.id,
             name
         );
-        let total_blocks = dir.inode.fs_block_count() as u32;
+        let total_blocks = dir.inode.size().div_ceil(BLOCK_SIZE as u64) as u32;
         let mut iblock: LBlockId = 0;
         // Try finding a block with enough space
         while iblock < total_blocks {
@@ -54,11 +54,9 @@ impl Ext4 {
             // Try inserting the entry to parent block
             if dir_block.insert(name, child.id, child.inode.file_type()) {
                 // Update checksum
-                dir_block.set_checksum(
-                    &self.read_super_block().uuid(),
-                    dir.id,
-                    dir.inode.generation(),
-                );
+                if let Some(seed) = self.csum_seed() {
+                    dir_block.set_checksum(dir.csum_seed(seed));
+                }
                 // Write the block back to disk
                 self.write_block(dir_block.block());
                 return Ok(());
@@ -76,11 +74,9 @@ impl Ext4 {
         // Write the entry to block
         new_dir_block.init();
         new_dir_block.insert(name, child.id, child.inode.file_type());
-        new_dir_block.set_checksum(
-            &self.read_super_block().uuid(),
-            dir.id,
-            dir.inode.generation(),
-        );
+        if let Some(seed) = self.csum_seed() {
+            new_dir_block.set_checksum(dir.csum_seed(seed));
+        }
         // Write the block back to disk
         self.write_block(new_dir_block.block());
 
@@ -90,7 +86,7 @@ impl Ext4 {
     /// Remove a entry from a directory
     pub(super) fn dir_remove_entry(&self, dir: &InodeRef, name: &str) -> Result<()> {
         trace!("Dir remove entry: dir {}, name {}", dir.id, name);
-        let total_blocks = dir.inode.fs_block_count() as u32;
+        let total_blocks = dir.inode.size().div_ceil(BLOCK_SIZE as u64) as u32;
         // Check each block
         let mut iblock: LBlockId = 0;
         while iblock < total_blocks {
@@ -101,11 +97,9 @@ impl Ext4 {
             // Try removing the entry
             if dir_block.remove(name) {
                 // Update checksum
-                dir_block.set_checksum(
-                    &self.read_super_block().uuid(),
-                    dir.id,
-                    dir.inode.generation(),
-                );
+                if let Some(seed) = self.csum_seed() {
+                    dir_block.set_checksum(dir.csum_seed(seed));
+                }
                 // Write the block back to disk
                 self.write_block(dir_block.block());
                 return Ok(());
@@ -124,7 +118,7 @@ impl Ext4 {
 
     /// Get all entries under a directory
     pub(super) fn dir_list_entries(&self, dir: &InodeRef) -> Vec<DirEntry> {
-        let total_blocks = dir.inode.fs_block_count() as u32;
+        let total_blocks = dir.inode.size().div_ceil(BLOCK_SIZE as u64) as u32;
         let mut entries: Vec<DirEntry> = Vec::new();
         let mut iblock: LBlockId = 0;
         while iblock < total_blocks {
@@ -3,6 +3,7 @@ use crate::constants::*;
 use crate::ext4_defs::*;
 use crate::format_error;
 use crate::prelude::*;
+use crate::return_error;
 use core::cmp::min;
 
 #[derive(Debug)]
@@ -23,92 +24,574 @@ impl ExtentSearchStep {
 }
 
 impl Ext4 {
-    /// Given a logic block id, find the corresponding fs block id.
-    pub(super) fn extent_query(&self, inode_ref: &InodeRef, iblock: LBlockId) -> Result<PBlockId> {
-        let path = self.find_extent(inode_ref, iblock);
+    /// Given a logic block id, find the extent that covers it.
+    pub(super) fn extent_lookup(
+        &self,
+        inode_ref: &InodeRef,
+        iblock: LBlockId,
+    ) -> Result<Option<Extent>> {
+        let path = self.find_extent(inode_ref, iblock)?;
         // Leaf is the last element of the path
         let leaf = path.last().unwrap();
-        if let Ok(index) = leaf.index {
-            // Note: block data must be defined here to keep it alive
-            let block_data: Block;
-            let ex_node = if leaf.pblock != 0 {
-                // Load the extent node
-                block_data = self.read_block(leaf.pblock);
-                // Load the next extent header
-                ExtentNode::from_bytes(&block_data.data)
-            } else {
-                // Root node
-                inode_ref.inode.extent_root()
-            };
-            let ex = ex_node.extent_at(index);
-            Ok(ex.start_pblock() + (iblock - ex.start_lblock()) as PBlockId)
+        let Ok(index) = leaf.index else {
+            return Ok(None);
+        };
+        if leaf.pblock != 0 {
+            // Load the extent node
+            let block_data = self.read_block(leaf.pblock);
+            let ex_node = ExtentNode::from_bytes(&block_data.data);
+            Ok(Some(*ex_node.extent_at(index)))
         } else {
-            Err(format_error!(
+            // Root node
+            Ok(Some(*inode_ref.inode.extent_root().extent_at(index)))
+        }
+    }
+
+    /// Given a logic block id, find the corresponding fs block id.
+    pub(super) fn extent_query(&self, inode_ref: &InodeRef, iblock: LBlockId) -> Result<PBlockId> {
+        self.extent_lookup(inode_ref, iblock)?
+            .map(|ex| ex.pblock_of(iblock))
+            .ok_or(format_error!(
                 ErrCode::ENOENT,
                 "extent_query: inode {} query iblock {} not found",
                 inode_ref.id,
                 iblock
             ))
-        }
     }
 
     /// Given a logic block id, find the corresponding fs block id.
     /// Create a new extent if not found.
+    ///
+    /// The new blocks continue the previous extent physically if possible, so
+    /// that they can be merged into it.
     pub(super) fn extent_query_or_create(
         &self,
         inode_ref: &mut InodeRef,
         iblock: LBlockId,
         block_count: u32,
     ) -> Result<PBlockId> {
-        let path = self.find_extent(inode_ref, iblock);
+        let path = self.find_extent(inode_ref, iblock)?;
         // Leaf is the last element of the path
         let leaf = path.last().unwrap();
-        // Note: block data must be defined here to keep it alive
-        let mut block_data: Block;
-        let ex_node = if leaf.pblock != 0 {
-            block_data = self.read_block(leaf.pblock);
-            ExtentNodeMut::from_bytes(&mut block_data.data)
-        } else {
-            // Root node
-            inode_ref.inode.extent_root_mut()
-        };
+        let extents = self.read_leaf(inode_ref, leaf)?;
         match leaf.index {
             Ok(index) => {
                 // Found, return the corresponding fs block id
-                let ex = ex_node.extent_at(index);
-                Ok(ex.start_pblock() + (iblock - ex.start_lblock()) as PBlockId)
+                Ok(extents[index].pblock_of(iblock))
             }
-            Err(_) => {
+            Err(index) => {
                 // Not found, create a new extent
                 let block_count = min(block_count, MAX_BLOCKS - iblock);
-                // Allocate physical block
-                let fblock = self.alloc_block(inode_ref)?;
-                // Create a new extent
-                let new_ext = Extent::new(iblock, fblock, block_count as u16);
-                // Insert the new extent
-                self.insert_extent(inode_ref, &path, &new_ext)?;
+                let goal = index
+                    .checked_sub(1)
+                    .map(|prev| extents[prev])
+                    .filter(|prev| prev.end_lblock() == iblock)
+                    .map(|prev| prev.pblock_of(iblock - 1) + 1);
+                // Allocate physical blocks
+                let (fblock, count) = self.alloc_blocks(inode_ref, goal, block_count)?;
+                // Create and insert a new extent
+                let new_ext = Extent::new(iblock, fblock, count as u16);
+                self.extent_insert(inode_ref, &new_ext)?;
                 Ok(fblock)
             }
         }
     }
 
     /// Get all data blocks recorded in the extent tree
-    pub(super) fn extent_all_data_blocks(&self, inode_ref: &InodeRef) -> Vec<PBlockId> {
+    pub(super) fn extent_all_data_blocks(&self, inode_ref: &InodeRef) -> Result<Vec<PBlockId>> {
         let mut pblocks = Vec::new();
         let ex_node = inode_ref.inode.extent_root();
-        self.get_all_pblocks_recursive(&ex_node, &mut pblocks);
-        pblocks
+        self.get_all_pblocks_recursive(inode_ref, &ex_node, &mut pblocks)?;
+        Ok(pblocks)
     }
 
     /// Get all physical blocks for saving the extent tree
-    pub(super) fn extent_all_tree_blocks(&self, inode_ref: &InodeRef) -> Vec<PBlockId> {
+    pub(super) fn extent_all_tree_blocks(&self, inode_ref: &InodeRef) -> Result<Vec<PBlockId>> {
         let mut pblocks = Vec::new();
         let ex_node = inode_ref.inode.extent_root();
-        self.get_all_nodes_recursive(&ex_node, &mut pblocks);
-        pblocks
+        self.get_all_nodes_recursive(inode_ref, &ex_node, &mut pblocks)?;
+        Ok(pblocks)
+    }
+
+    /// Get all extents recorded in the extent tree, sorted by logic block id
+    pub(super) fn extent_list(&self, inode_ref: &InodeRef) -> Result<Vec<Extent>> {
+        let mut extents = Vec::new();
+        let ex_node = inode_ref.inode.extent_root();
+        self.get_all_extents_recursive(inode_ref, &ex_node, &mut extents)?;
+        Ok(extents)
+    }
+
+    /// Allocate unwritten extents for the unmapped logic blocks in `[start, end)`.
+    /// Return the number of data blocks allocated.
+    ///
+    /// Each hole is filled with runs of contiguous blocks, which continue the
+    /// previous extent physically if possible. `inode.block_count` is updated
+    /// and the inode is written back.
+    pub(super) fn extent_alloc_range(
+        &self,
+        inode_ref: &mut InodeRef,
+        start: LBlockId,
+        end: LBlockId,
+    ) -> Result<u64> {
+        let holes = self.extent_holes(inode_ref, start, end)?;
+        let required: u64 = holes.iter().map(|(s, e)| (e - s) as u64).sum();
+        if required == 0 {
+            return Ok(0);
+        }
+        if required > self.read_super_block().free_blocks_count() {
+            return_error!(
+                ErrCode::ENOSPC,
+                "No space to allocate {} blocks for inode {}",
+                required,
+                inode_ref.id
+            );
+        }
+        for (hole_start, hole_end) in holes {
+            let mut cursor = hole_start;
+            while cursor < hole_end {
+                let goal = match cursor.checked_sub(1) {
+                    Some(prev) => self
+                        .extent_lookup(inode_ref, prev)?
+                        .map(|ex| ex.pblock_of(prev) + 1),
+                    None => None,
+                };
+                let max_count = min(hole_end - cursor, Extent::UNWRITTEN_MAX_LEN as LBlockId);
+                let (pblock, count) = self.alloc_blocks(inode_ref, goal, max_count)?;
+                let mut ex = Extent::new(cursor, pblock, count as u16);
+                ex.mark_unwritten();
+                self.extent_insert(inode_ref, &ex)?;
+                let block_count = inode_ref.inode.fs_block_count() + count as u64;
+                inode_ref.inode.set_fs_block_count(block_count);
+                cursor += count;
+            }
+        }
+        self.write_inode_with_csum(inode_ref);
+        Ok(required)
+    }
+
+    /// Remove the mapping of logic blocks in `[start, end)` and free the data
+    /// blocks. Return the number of data blocks freed.
+    ///
+    /// `inode.block_count` is updated and the inode is written back.
+    pub(super) fn extent_remove_range(
+        &self,
+        inode_ref: &mut InodeRef,
+        start: LBlockId,
+        end: LBlockId,
+    ) -> Result<u64> {
+        let mut freed = 0;
+        self.extent_update_range(inode_ref, start, end, |inode_ref, extents| {
+            let mut new_extents = Vec::with_capacity(extents.len() + 1);
+            let mut changed = false;
+            for ex in extents.drain(..) {
+                if ex.end_lblock() <= start || ex.start_lblock() >= end {
+                    new_extents.push(ex);
+                    continue;
+                }
+                changed = true;
+                // Keep the parts outside the range
+                if ex.start_lblock() < start {
+                    new_extents.push(ex.sub_extent(ex.start_lblock(), start));
+                }
+                if ex.end_lblock() > end {
+                    new_extents.push(ex.sub_extent(end, ex.end_lblock()));
+                }
+                // Free the blocks inside the range
+                for iblock in ex.start_lblock().max(start)..ex.end_lblock().min(end) {
+                    self.dealloc_block(inode_ref, ex.pblock_of(iblock))?;
+                    freed += 1;
+                }
+            }
+            *extents = new_extents;
+            Ok(changed)
+        })?;
+        if freed == 0 {
+            return Ok(0);
+        }
+        self.extent_shrink_root(inode_ref)?;
+        let block_count = inode_ref.inode.fs_block_count() - freed;
+        inode_ref.inode.set_fs_block_count(block_count);
+        self.write_inode_with_csum(inode_ref);
+        Ok(freed)
+    }
+
+    /// Mark the unwritten extents in logic blocks `[start, end)` as written.
+    pub(super) fn extent_mark_written(
+        &self,
+        inode_ref: &mut InodeRef,
+        start: LBlockId,
+        end: LBlockId,
+    ) -> Result<()> {
+        self.extent_update_range(inode_ref, start, end, |_, extents| {
+            let mut new_extents = Vec::with_capacity(extents.len() + 2);
+            let mut changed = false;
+            for ex in extents.drain(..) {
+                if !ex.is_unwritten() || ex.end_lblock() <= start || ex.start_lblock() >= end {
+                    new_extents.push(ex);
+                    continue;
+                }
+                let (mid_start, mid_end) = (ex.start_lblock().max(start), ex.end_lblock().min(end));
+                if ex.start_lblock() < mid_start {
+                    new_extents.push(ex.sub_extent(ex.start_lblock(), mid_start));
+                }
+                let mut mid = ex.sub_extent(mid_start, mid_end);
+                mid.mark_written();
+                new_extents.push(mid);
+                if ex.end_lblock() > mid_end {
+                    new_extents.push(ex.sub_extent(mid_end, ex.end_lblock()));
+                }
+                changed = true;
+            }
+            *extents = new_extents;
+            Ok(changed)
+        })
+    }
+
+    /// Move the extents starting from logic block `start` backward by `shift`
+    /// blocks. Logic blocks in `[start - shift, start)` must be unmapped.
+    ///
+    /// The extents and the indexes are changed in place.
+    pub(super) fn extent_shift_left(
+        &self,
+        inode_ref: &mut InodeRef,
+        start: LBlockId,
+        shift: LBlockId,
+    ) -> Result<()> {
+        self.shift_node_left(inode_ref, 0, start, shift)?;
+        Ok(())
     }
 
-    fn get_all_pblocks_recursive(&self, ex_node: &ExtentNode, pblocks: &mut Vec<PBlockId>) {
+    /// Move the extents in the node stored in `pblock` (0 for the root node)
+    /// starting from logic block `start` backward by `shift` blocks. Return
+    /// the new start logic block of the node, or `None` if it is empty.
+    fn shift_node_left(
+        &self,
+        inode_ref: &mut InodeRef,
+        pblock: PBlockId,
+        start: LBlockId,
+        shift: LBlockId,
+    ) -> Result<Option<LBlockId>> {
+        let mut block = if pblock != 0 {
+            Some(self.read_extent_block(inode_ref, pblock)?)
+        } else {
+            None
+        };
+        // Find the children that may cover the extents to move
+        let (depth, children) = {
+            let node = match &block {
+                Some(block) => ExtentNode::from_bytes(&block.data),
+                None => inode_ref.inode.extent_root(),
+            };
+            let count = node.header().entries_count() as usize;
+            let children: Vec<(usize, PBlockId)> = if node.header().depth() == 0 {
+                Vec::new()
+            } else {
+                (0..count)
+                    .filter(|&i| {
+                        i + 1 == count || node.extent_index_at(i + 1).start_lblock() > start
+                    })
+                    .map(|i| (i, node.extent_index_at(i).leaf()))
+                    .collect()
+            };
+            (node.header().depth(), children)
+        };
+        let mut child_starts = Vec::with_capacity(children.len());
+        for (i, child) in children {
+            if let Some(child_start) = self.shift_node_left(inode_ref, child, start, shift)? {
+                child_starts.push((i, child_start));
+            }
+        }
+
+        let mut node = match block.as_mut() {
+            Some(block) => ExtentNodeMut::from_bytes(&mut block.data),
+            None => inode_ref.inode.extent_root_mut(),
+        };
+        if depth == 0 {
+            for i in 0..node.header().entries_count() as usize {
+                let ex = node.extent_mut_at(i);
+                if ex.start_lblock() >= start {
+                    ex.set_start_lblock(ex.start_lblock() - shift);
+                }
+            }
+        } else {
+            // The indexes follow the new start of the children
+            for (i, child_start) in child_starts {
+                node.extent_index_mut_at(i).first_block = child_start;
+            }
+        }
+        let node_start =
+            (node.header().entries_count() > 0).then(|| node.extent_index_at(0).start_lblock());
+        match block.as_mut() {
+            Some(block) => self.write_extent_block(inode_ref, block),
+            None => self.write_inode_with_csum(inode_ref),
+        }
+        Ok(node_start)
+    }
+
+    /// Find the unmapped ranges of logic blocks in `[start, end)`.
+    fn extent_holes(
+        &self,
+        inode_ref: &InodeRef,
+        start: LBlockId,
+        end: LBlockId,
+    ) -> Result<Vec<(LBlockId, LBlockId)>> {
+        let mut holes: Vec<(LBlockId, LBlockId)> = Vec::new();
+        let mut push_hole = |hole_start: LBlockId, hole_end: LBlockId| match holes.last_mut() {
+            Some(last) if last.1 == hole_start => last.1 = hole_end,
+            _ => holes.push((hole_start, hole_end)),
+        };
+        let mut cursor = start;
+        while cursor < end {
+            let path = self.find_extent(inode_ref, cursor)?;
+            let next = self.next_leaf_start(inode_ref, &path)?;
+            for ex in self.read_leaf(inode_ref, path.last().unwrap())? {
+                if ex.end_lblock() <= cursor {
+                    continue;
+                }
+                if ex.start_lblock() >= end {
+                    break;
+                }
+                if ex.start_lblock() > cursor {
+                    push_hole(cursor, ex.start_lblock());
+                }
+                cursor = ex.end_lblock();
+            }
+            let leaf_end = next.unwrap_or(end).min(end);
+            if cursor < leaf_end {
+                push_hole(cursor, leaf_end);
+                cursor = leaf_end;
+            }
+            if next.is_none() {
+                break;
+            }
+        }
+        Ok(holes)
+    }
+
+    /// Call `f` on the extents of each leaf that may cover logic blocks in
+    /// `[start, end)`. If `f` returns `true`, the changed extents are written
+    /// back to the leaf in place.
+    fn extent_update_range(
+        &self,
+        inode_ref: &mut InodeRef,
+        start: LBlockId,
+        end: LBlockId,
+        mut f: impl FnMut(&mut InodeRef, &mut Vec<Extent>) -> Result<bool>,
+    ) -> Result<()> {
+        let mut cursor = start;
+        while cursor < end {
+            let path = self.find_extent(inode_ref, cursor)?;
+            // Find the next leaf before the tree is changed
+            let next = self.next_leaf_start(inode_ref, &path)?;
+            let mut extents = self.read_leaf(inode_ref, path.last().unwrap())?;
+            if f(inode_ref, &mut extents)? {
+                self.write_leaf(inode_ref, &path, extents)?;
+            }
+            match next {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the start logic block of the leaf following the leaf at the end
+    /// of `path`, or `None` if it is the last leaf.
+    fn next_leaf_start(
+        &self,
+        inode_ref: &InodeRef,
+        path: &[ExtentSearchStep],
+    ) -> Result<Option<LBlockId>> {
+        for step in path.iter().rev().skip(1) {
+            let index = step.index.unwrap();
+            let block_data: Block;
+            let node = if step.pblock != 0 {
+                block_data = self.read_extent_block(inode_ref, step.pblock)?;
+                ExtentNode::from_bytes(&block_data.data)
+            } else {
+                inode_ref.inode.extent_root()
+            };
+            if index + 1 < node.header().entries_count() as usize {
+                return Ok(Some(node.extent_index_at(index + 1).start_lblock()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the extents of the leaf node at `leaf`.
+    fn read_leaf(&self, inode_ref: &InodeRef, leaf: &ExtentSearchStep) -> Result<Vec<Extent>> {
+        let block_data: Block;
+        let node = if leaf.pblock != 0 {
+            block_data = self.read_extent_block(inode_ref, leaf.pblock)?;
+            ExtentNode::from_bytes(&block_data.data)
+        } else {
+            inode_ref.inode.extent_root()
+        };
+        Ok((0..node.header().entries_count() as usize)
+            .map(|i| *node.extent_at(i))
+            .collect())
+    }
+
+    /// Write `extents` back to the leaf node at the end of `path`, merging the
+    /// adjacent extents if possible.
+    ///
+    /// The indexes on `path` follow the new start of the leaf. An empty leaf is
+    /// removed from the tree, and the extents that do not fit in the leaf are
+    /// inserted by splitting it.
+    fn write_leaf(
+        &self,
+        inode_ref: &mut InodeRef,
+        path: &[ExtentSearchStep],
+        extents: Vec<Extent>,
+    ) -> Result<()> {
+        let mut extents = merge_extents(extents);
+        let leaf = path.last().unwrap();
+        if leaf.pblock != 0 && extents.is_empty() {
+            return self.remove_node(inode_ref, path);
+        }
+
+        let mut block = if leaf.pblock != 0 {
+            Some(self.read_extent_block(inode_ref, leaf.pblock)?)
+        } else {
+            None
+        };
+        let mut leaf_node = match block.as_mut() {
+            Some(block) => ExtentNodeMut::from_bytes(&mut block.data),
+            None => inode_ref.inode.extent_root_mut(),
+        };
+        let max_entries = leaf_node.header().max_entries_count() as usize;
+        let overflow = extents.split_off(extents.len().min(max_entries));
+        for (i, ex) in extents.iter().enumerate() {
+            *leaf_node.extent_mut_at(i) = *ex;
+        }
+        leaf_node
+            .header_mut()
+            .set_entries_count(extents.len() as u16);
+        match block.as_mut() {
+            Some(block) => {
+                self.write_extent_block(inode_ref, block);
+                self.set_index_start(inode_ref, path, extents[0].start_lblock());
+            }
+            None => self.write_inode_with_csum(inode_ref),
+        }
+
+        // Insert the extents that do not fit, which splits the leaf
+        for ex in overflow {
+            let path = self.find_extent(inode_ref, ex.start_lblock())?;
+            self.insert_extent(inode_ref, &path, &ex)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the empty node at the end of `path` from the tree and free its
+    /// block. The parent node is removed as well if it becomes empty.
+    fn remove_node(&self, inode_ref: &mut InodeRef, path: &[ExtentSearchStep]) -> Result<()> {
+        let node = path.last().unwrap();
+        self.dealloc_tree_block(inode_ref, node.pblock)?;
+
+        let parent_path = &path[..path.len() - 1];
+        let parent = parent_path.last().unwrap();
+        let index = parent.index.unwrap();
+        if parent.pblock == 0 {
+            let mut root = inode_ref.inode.extent_root_mut();
+            root.remove_entry(index);
+            if root.header().entries_count() == 0 {
+                // The tree is empty now
+                root.init(0, 0);
+            }
+            self.write_inode_with_csum(inode_ref);
+            return Ok(());
+        }
+
+        let mut parent_block = self.read_extent_block(inode_ref, parent.pblock)?;
+        let mut parent_node = ExtentNodeMut::from_bytes(&mut parent_block.data);
+        parent_node.remove_entry(index);
+        if parent_node.header().entries_count() == 0 {
+            return self.remove_node(inode_ref, parent_path);
+        }
+        let parent_start = parent_node.extent_index_at(0).start_lblock();
+        self.write_extent_block(inode_ref, &mut parent_block);
+        if index == 0 {
+            self.set_index_start(inode_ref, parent_path, parent_start);
+        }
+        Ok(())
+    }
+
+    /// Decrease the depth of the tree while the root node has only one child,
+    /// whose entries fit in the root node. The child block is freed.
+    fn extent_shrink_root(&self, inode_ref: &mut InodeRef) -> Result<()> {
+        loop {
+            let root = inode_ref.inode.extent_root();
+            if root.header().depth() == 0 || root.header().entries_count() != 1 {
+                return Ok(());
+            }
+            let root_max = root.header().max_entries_count();
+            let child = root.extent_index_at(0).leaf();
+            let child_block = self.read_extent_block(inode_ref, child)?;
+            let child_node = ExtentNode::from_bytes(&child_block.data);
+            if child_node.header().entries_count() > root_max {
+                return Ok(());
+            }
+
+            let mut root = inode_ref.inode.extent_root_mut();
+            root.init(child_node.header().depth(), 0);
+            for i in 0..child_node.header().entries_count() as usize {
+                *root.fake_extent_mut_at(i) = *child_node.fake_extent_at(i);
+            }
+            root.header_mut()
+                .set_entries_count(child_node.header().entries_count());
+            self.write_inode_with_csum(inode_ref);
+            self.dealloc_tree_block(inode_ref, child)?;
+        }
+    }
+
+    /// Allocate a block for the extent tree of `inode_ref`. The block is
+    /// counted in `inode.block_count`, but the inode is not written back.
+    fn alloc_tree_block(&self, inode_ref: &mut InodeRef) -> Result<PBlockId> {
+        let pblock = self.alloc_block(inode_ref)?;
+        let block_count = inode_ref.inode.fs_block_count() + 1;
+        inode_ref.inode.set_fs_block_count(block_count);
+        Ok(pblock)
+    }
+
+    /// Free a block of the extent tree of `inode_ref`. The block is no longer
+    /// counted in `inode.block_count`, but the inode is not written back.
+    fn dealloc_tree_block(&self, inode_ref: &mut InodeRef, pblock: PBlockId) -> Result<()> {
+        self.dealloc_block(inode_ref, pblock)?;
+        let block_count = inode_ref.inode.fs_block_count() - 1;
+        inode_ref.inode.set_fs_block_count(block_count);
+        Ok(())
+    }
+
+    fn get_all_extents_recursive(
+        &self,
+        inode_ref: &InodeRef,
+        ex_node: &ExtentNode,
+        extents: &mut Vec<Extent>,
+    ) -> Result<()> {
+        if ex_node.header().depth() == 0 {
+            // Leaf
+            for i in 0..ex_node.header().entries_count() as usize {
+                extents.push(*ex_node.extent_at(i));
+            }
+        } else {
+            // Non-leaf
+            for i in 0..ex_node.header().entries_count() as usize {
+                let ex_idx = ex_node.extent_index_at(i);
+                let child_block = self.read_extent_block(inode_ref, ex_idx.leaf())?;
+                let child_node = ExtentNode::from_bytes(&child_block.data);
+                self.get_all_extents_recursive(inode_ref, &child_node, extents)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_all_pblocks_recursive(
+        &self,
+        inode_ref: &InodeRef,
+        ex_node: &ExtentNode,
+        pblocks: &mut Vec<PBlockId>,
+    ) -> Result<()> {
         if ex_node.header().depth() == 0 {
             // Leaf
             for i in 0..ex_node.header().entries_count() as usize {
@@ -121,28 +604,68 @@ impl Ext4 {
             // Non-leaf
             for i in 0..ex_node.header().entries_count() as usize {
                 let ex_idx = ex_node.extent_index_at(i);
-                let child_block = self.read_block(ex_idx.leaf());
+                let child_block = self.read_extent_block(inode_ref, ex_idx.leaf())?;
                 let child_node = ExtentNode::from_bytes(&child_block.data);
-                self.get_all_pblocks_recursive(&child_node, pblocks);
+                self.get_all_pblocks_recursive(inode_ref, &child_node, pblocks)?;
             }
         }
+        Ok(())
     }
 
-    fn get_all_nodes_recursive(&self, ex_node: &ExtentNode, pblocks: &mut Vec<PBlockId>) {
+    fn get_all_nodes_recursive(
+        &self,
+        inode_ref: &InodeRef,
+        ex_node: &ExtentNode,
+        pblocks: &mut Vec<PBlockId>,
+    ) -> Result<()> {
         if ex_node.header().depth() != 0 {
             // Non-leaf
             for i in 0..ex_node.header().entries_count() as usize {
                 let ex_idx = ex_node.extent_index_at(i);
                 pblocks.push(ex_idx.leaf());
-                let child_block = self.read_block(ex_idx.leaf());
+                let child_block = self.read_extent_block(inode_ref, ex_idx.leaf())?;
                 let child_node = ExtentNode::from_bytes(&child_block.data);
-                self.get_all_nodes_recursive(&child_node, pblocks);
+                self.get_all_nodes_recursive(inode_ref, &child_node, pblocks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an extent tree block of `inode_ref`, checking its header and checksum.
+    fn read_extent_block(&self, inode_ref: &InodeRef, pblock: PBlockId) -> Result<Block> {
+        let block = self.read_block(pblock);
+        let node = ExtentNode::from_bytes(&block.data);
+        if !node.is_valid_block() {
+            return_error!(
+                ErrCode::EUCLEAN,
+                "Inode {} has a corrupted extent block {}",
+                inode_ref.id,
+                pblock
+            );
+        }
+        if let Some(seed) = self.csum_seed() {
+            if !node.verify_checksum(inode_ref.csum_seed(seed)) {
+                return_error!(
+                    ErrCode::EBADMSG,
+                    "Inode {} extent block {} checksum mismatch",
+                    inode_ref.id,
+                    pblock
+                );
             }
         }
+        Ok(block)
+    }
+
+    /// Write an extent tree block of `inode_ref` with checksum.
+    fn write_extent_block(&self, inode_ref: &InodeRef, block: &mut Block) {
+        if let Some(seed) = self.csum_seed() {
+            ExtentNodeMut::from_bytes(&mut block.data).set_checksum(inode_ref.csum_seed(seed));
+        }
+        self.write_block(block);
     }
 
     /// Find the given logic block id in the extent tree, return the search path
-    fn find_extent(&self, inode_ref: &InodeRef, iblock: LBlockId) -> Vec<ExtentSearchStep> {
+    fn find_extent(&self, inode_ref: &InodeRef, iblock: LBlockId) -> Result<Vec<ExtentSearchStep>> {
         let mut path: Vec<ExtentSearchStep> = Vec::new();
         let mut ex_node = inode_ref.inode.extent_root();
         let mut pblock = 0;
@@ -157,7 +680,7 @@ impl Ext4 {
             // Load the next extent node
             let next = ex_idx.leaf();
             // Note: block data cannot be released until the next assigment
-            block_data = self.read_block(next);
+            block_data = self.read_extent_block(inode_ref, next)?;
             // Load the next extent header
             ex_node = ExtentNode::from_bytes(&block_data.data);
             pblock = next;
@@ -166,10 +689,31 @@ impl Ext4 {
         let index = ex_node.search_extent(iblock);
         path.push(ExtentSearchStep::new(pblock, index));
 
-        path
+        Ok(path)
     }
 
-    /// Insert a new extent into the extent tree.
+    /// Insert a new extent into the extent tree. The new extent is merged
+    /// with the adjacent extents in the same leaf if possible.
+    ///
+    /// The logic blocks covered by the new extent must be unmapped.
+    fn extent_insert(&self, inode_ref: &mut InodeRef, new_ext: &Extent) -> Result<()> {
+        let path = self.find_extent(inode_ref, new_ext.start_lblock())?;
+        let leaf = path.last().unwrap();
+        let Err(pos) = leaf.index else {
+            return_error!(
+                ErrCode::EEXIST,
+                "Inode {} logic block {} is already mapped",
+                inode_ref.id,
+                new_ext.start_lblock()
+            );
+        };
+        let mut extents = self.read_leaf(inode_ref, leaf)?;
+        extents.insert(pos, *new_ext);
+        self.write_leaf(inode_ref, &path, extents)
+    }
+
+    /// Insert a new extent into the leaf node at the end of `path` without
+    /// merging, and split the nodes on `path` if they are full.
     fn insert_extent(
         &self,
         inode_ref: &mut InodeRef,
@@ -182,7 +726,7 @@ impl Ext4 {
             let mut leaf_node = inode_ref.inode.extent_root_mut();
             // Insert the extent
             let res = leaf_node.insert_extent(new_ext, leaf.index.unwrap_err());
-            self.write_inode_without_csum(inode_ref);
+            self.write_inode_with_csum(inode_ref);
             // Handle split
             return if let Err(split) = res {
                 self.split_root(inode_ref, &split)
@@ -190,12 +734,16 @@ impl Ext4 {
                 Ok(())
             };
         }
-        // 2. Leaf is not root, load the leaf node
+        // 2. Leaf is not root, the new extent may start before the indexes
+        if leaf.index == Err(0) {
+            self.set_index_start(inode_ref, path, new_ext.start_lblock());
+        }
+        // Load the leaf node
         let mut leaf_block = self.read_block(leaf.pblock);
         let mut leaf_node = ExtentNodeMut::from_bytes(&mut leaf_block.data);
         // Insert the extent
         let res = leaf_node.insert_extent(new_ext, leaf.index.unwrap_err());
-        self.write_block(&leaf_block);
+        self.write_extent_block(inode_ref, &mut leaf_block);
         // Handle split
         if let Err(mut split) = res {
             // Handle split until root
@@ -218,6 +766,41 @@ impl Ext4 {
         }
     }
 
+    /// Set the start logic block of the indexes on `path` that cover the node
+    /// at the end of `path` to `lblock`, when the first entry of the node changes.
+    fn set_index_start(
+        &self,
+        inode_ref: &mut InodeRef,
+        path: &[ExtentSearchStep],
+        lblock: LBlockId,
+    ) {
+        for parent in path.iter().rev().skip(1) {
+            let index = parent.index.unwrap();
+            if parent.pblock == 0 {
+                let mut parent_node = inode_ref.inode.extent_root_mut();
+                let ex_idx = parent_node.extent_index_mut_at(index);
+                if ex_idx.first_block == lblock {
+                    break;
+                }
+                ex_idx.first_block = lblock;
+                self.write_inode_with_csum(inode_ref);
+            } else {
+                let mut parent_block = self.read_block(parent.pblock);
+                let mut parent_node = ExtentNodeMut::from_bytes(&mut parent_block.data);
+                let ex_idx = parent_node.extent_index_mut_at(index);
+                if ex_idx.first_block == lblock {
+                    break;
+                }
+                ex_idx.first_block = lblock;
+                self.write_extent_block(inode_ref, &mut parent_block);
+            }
+            // The upper indexes cover the node unless it is the first one
+            if index != 0 {
+                break;
+            }
+        }
+    }
+
     /// Split an extent node. Given the block id where the parent node is
     /// stored, and the child position that `parent_node.extent_at(child_pos)`
     /// points to the child.
@@ -232,7 +815,7 @@ impl Ext4 {
         child_pos: usize,
         split: &[FakeExtent],
     ) -> core::result::Result<(), Vec<FakeExtent>> {
-        let right_bid = self.alloc_block(inode_ref).unwrap();
+        let right_bid = self.alloc_tree_block(inode_ref).unwrap();
         let mut right_block = self.read_block(right_bid);
         let mut right_node = ExtentNodeMut::from_bytes(&mut right_block.data);
 
@@ -255,19 +838,19 @@ impl Ext4 {
             let mut parent_node = inode_ref.inode.extent_root_mut();
             parent_depth = parent_node.header().depth();
             res = parent_node.insert_extent_index(&extent_index, child_pos + 1);
-            self.write_inode_without_csum(inode_ref);
+            self.write_inode_with_csum(inode_ref);
         } else {
             // Parent is not root
             let mut parent_block = self.read_block(parent_pblock);
             let mut parent_node = ExtentNodeMut::from_bytes(&mut parent_block.data);
             parent_depth = parent_node.header().depth();
             res = parent_node.insert_extent_index(&extent_index, child_pos + 1);
-            self.write_block(&parent_block);
+            self.write_extent_block(inode_ref, &mut parent_block);
         }
 
         // Right node is the child of parent, so its depth is 1 less than parent
         right_node.header_mut().set_depth(parent_depth - 1);
-        self.write_block(&right_block);
+        self.write_extent_block(inode_ref, &mut right_block);
 
         res
     }
@@ -280,8 +863,8 @@ impl Ext4 {
     /// This function will create a new leaf node to store the split part.
     fn split_root(&self, inode_ref: &mut InodeRef, split: &[FakeExtent]) -> Result<()> {
         // Create left and right blocks
-        let l_bid = self.alloc_block(inode_ref)?;
-        let r_bid = self.alloc_block(inode_ref)?;
+        let l_bid = self.alloc_tree_block(inode_ref)?;
+        let r_bid = self.alloc_tree_block(inode_ref)?;
         let mut l_block = self.read_block(l_bid);
         let mut r_block = self.read_block(r_bid);
 
@@ -313,10 +896,26 @@ impl Ext4 {
         *root.extent_index_mut_at(1) = ExtentIndex::new(right.extent_at(0).start_lblock(), r_bid);
 
         // Sync to disk
-        self.write_block(&l_block);
-        self.write_block(&r_block);
-        self.write_inode_without_csum(inode_ref);
+        self.write_extent_block(inode_ref, &mut l_block);
+        self.write_extent_block(inode_ref, &mut r_block);
+        self.write_inode_with_csum(inode_ref);
 
         Ok(())
     }
 }
+
+/// Merge the adjacent extents in `extents`, which must be sorted and
+/// non-overlapping.
+fn merge_extents(extents: Vec<Extent>) -> Vec<Extent> {
+    let mut merged: Vec<Extent> = Vec::with_capacity(extents.len());
+    for ex in extents {
+        if let Some(last) = merged.last_mut() {
+            if Extent::can_append(last, &ex) {
+                last.set_block_count(last.block_count() + ex.block_count());
+                continue;
+            }
+        }
+        merged.push(ex);
+    }
+    merged
+}
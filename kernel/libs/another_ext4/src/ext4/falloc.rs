@@ -0,0 +1,156 @@
+use super::Ext4;
+use crate::constants::*;
+use crate::ext4_defs::*;
+use crate::prelude::*;
+use crate::return_error;
+
+impl Ext4 {
+    /// Allocate unwritten blocks for the byte range `[offset, offset + len)`.
+    /// The file size is extended unless `keep_size` is set.
+    pub(super) fn falloc_allocate(
+        &self,
+        file: &mut InodeRef,
+        offset: usize,
+        len: usize,
+        keep_size: bool,
+    ) -> Result<()> {
+        let end = offset + len;
+        self.extent_alloc_range(
+            file,
+            (offset / BLOCK_SIZE) as LBlockId,
+            end.div_ceil(BLOCK_SIZE) as LBlockId,
+        )?;
+        if !keep_size && end as u64 > file.inode.size() {
+            file.inode.set_size(end as u64);
+        }
+        Ok(())
+    }
+
+    /// Deallocate the byte range `[offset, offset + len)`. The range reads
+    /// as zeros afterwards, the file size is not changed.
+    pub(super) fn falloc_punch_hole(
+        &self,
+        file: &mut InodeRef,
+        offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        let size = file.inode.size() as usize;
+        if offset >= size {
+            return Ok(());
+        }
+        // Blocks beyond the end of file are not touched
+        let end = (offset + len).min(size.next_multiple_of(BLOCK_SIZE));
+        let (start_block, end_block) = self.zero_partial_blocks(file, offset, end)?;
+        if start_block < end_block {
+            self.extent_remove_range(file, start_block, end_block)?;
+        }
+        Ok(())
+    }
+
+    /// Convert the byte range `[offset, offset + len)` to zeros. Blocks in
+    /// the range are replaced by unwritten blocks. The file size is extended
+    /// unless `keep_size` is set.
+    pub(super) fn falloc_zero_range(
+        &self,
+        file: &mut InodeRef,
+        offset: usize,
+        len: usize,
+        keep_size: bool,
+    ) -> Result<()> {
+        let end = offset + len;
+        let (start_block, end_block) = self.zero_partial_blocks(file, offset, end)?;
+        if start_block < end_block {
+            self.extent_remove_range(file, start_block, end_block)?;
+        }
+        self.falloc_allocate(file, offset, len, keep_size)
+    }
+
+    /// Remove the byte range `[offset, offset + len)` and move the following
+    /// data backward. The range must be block aligned and end before the
+    /// end of file.
+    pub(super) fn falloc_collapse_range(
+        &self,
+        file: &mut InodeRef,
+        offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        if offset % BLOCK_SIZE != 0 || len % BLOCK_SIZE != 0 {
+            return_error!(
+                ErrCode::EINVAL,
+                "Collapse range {}+{} is not block aligned",
+                offset,
+                len
+            );
+        }
+        let size = file.inode.size() as usize;
+        if offset + len >= size {
+            return_error!(
+                ErrCode::EINVAL,
+                "Collapse range {}+{} reaches the end of file",
+                offset,
+                len
+            );
+        }
+        let start_block = (offset / BLOCK_SIZE) as LBlockId;
+        let end_block = ((offset + len) / BLOCK_SIZE) as LBlockId;
+        self.extent_remove_range(file, start_block, end_block)?;
+        self.extent_shift_left(file, end_block, end_block - start_block)?;
+        file.inode.set_size((size - len) as u64);
+        Ok(())
+    }
+
+    /// Change the file size. When shrinking, blocks beyond the new end of
+    /// file are freed and the rest of the last block is zeroed. When
+    /// extending, the new range is left as a hole.
+    pub(super) fn truncate(&self, file: &mut InodeRef, size: u64) -> Result<()> {
+        if size < file.inode.size() {
+            let (start_block, _) = self.zero_partial_blocks(
+                file,
+                size as usize,
+                (size as usize).next_multiple_of(BLOCK_SIZE),
+            )?;
+            self.extent_remove_range(file, start_block, MAX_BLOCKS)?;
+        }
+        file.inode.set_size(size);
+        Ok(())
+    }
+
+    /// Zero the partial blocks at both ends of the byte range `[start, end)`.
+    /// Return the range of the blocks that are entirely covered.
+    fn zero_partial_blocks(
+        &self,
+        file: &InodeRef,
+        start: usize,
+        end: usize,
+    ) -> Result<(LBlockId, LBlockId)> {
+        let start_block = start.div_ceil(BLOCK_SIZE);
+        let end_block = end / BLOCK_SIZE;
+        if start_block > end_block {
+            // The range is inside a block
+            self.zero_in_block(file, start, end)?;
+            return Ok((start_block as LBlockId, start_block as LBlockId));
+        }
+        self.zero_in_block(file, start, start_block * BLOCK_SIZE)?;
+        self.zero_in_block(file, end_block * BLOCK_SIZE, end)?;
+        Ok((start_block as LBlockId, end_block as LBlockId))
+    }
+
+    /// Zero the byte range `[start, end)` inside a block. Holes and unwritten
+    /// blocks already read as zeros, so only written blocks are changed.
+    fn zero_in_block(&self, file: &InodeRef, start: usize, end: usize) -> Result<()> {
+        if start >= end {
+            return Ok(());
+        }
+        let iblock = (start / BLOCK_SIZE) as LBlockId;
+        match self.extent_lookup(file, iblock)? {
+            Some(ex) if !ex.is_unwritten() => {
+                let mut block = self.read_block(ex.pblock_of(iblock));
+                let offset = start % BLOCK_SIZE;
+                block.data[offset..offset + end - start].fill(0);
+                self.write_data_block(&block);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
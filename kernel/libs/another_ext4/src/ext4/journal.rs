@@ -1,11 +1,358 @@
+//! Journaling of Ext4 filesystem.
+//!
+//! Metadata updates are grouped into transactions and written to the JBD2
+//! journal before they reach their final locations (ordered mode). Data
+//! blocks are written directly, before the transaction that references
+//! them commits.
+//!
+//! Each transaction is checkpointed right after it commits, so the journal
+//! holds at most one committed transaction at a time. The superblock is
+//! marked as needing recovery while the log holds a transaction.
+//!
+//! All handles join the same running transaction. Like in Linux, a failed
+//! handle is not rolled back, since other handles may have already changed
+//! the same blocks. A transaction that outgrows the log is split, and the
+//! blocks recorded so far are committed before the handles go on.
+
 use super::Ext4;
+use crate::constants::*;
+use crate::ext4_defs::*;
+use crate::jbd2::*;
+use crate::prelude::*;
+use crate::return_error;
+use spin::Mutex;
+
+/// The passes of journal recovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecoveryPass {
+    /// Find the end of the valid log.
+    Scan,
+    /// Collect revoked blocks.
+    Revoke,
+    /// Write journaled blocks to their final locations.
+    Replay,
+}
 
 impl Ext4 {
-    /// start transaction
-    #[allow(unused)]
-    pub(super) fn trans_start(&self) {}
+    /// Load the journal and replay it if the filesystem was not cleanly unmounted.
+    pub(super) fn load_journal(&mut self) -> Result<()> {
+        let sb = self.read_super_block();
+        if !sb.has_journal() || sb.journal_inode_number() == 0 {
+            return Ok(());
+        }
+        let journal_inode = self.read_inode(sb.journal_inode_number())?;
+        if !journal_inode.inode.has_extents() {
+            return_error!(ErrCode::ENOTSUP, "Journal inode does not use extents");
+        }
+        let blocks = self.extent_all_data_blocks(&journal_inode)?;
+        if blocks.is_empty() {
+            return_error!(ErrCode::EINVAL, "Journal inode has no blocks");
+        }
+        // Load and check the journal superblock
+        let jsb: JournalSuperBlock = self.read_block_direct(blocks[0]).read_offset_as(0);
+        if !jsb.check_magic() {
+            return_error!(ErrCode::EINVAL, "Invalid journal magic number");
+        }
+        if jsb.block_size() as usize != BLOCK_SIZE {
+            return_error!(
+                ErrCode::EINVAL,
+                "Invalid journal block size {}",
+                jsb.block_size()
+            );
+        }
+        if jsb.max_len() as usize > blocks.len() || jsb.first() == 0 {
+            return_error!(ErrCode::EINVAL, "Invalid journal length {}", jsb.max_len());
+        }
+        // Fast commit blocks are never replayed, and asynchronous commits rely
+        // on the transaction checksums of `JBD2_FEATURE_COMPAT_CHECKSUM`
+        if jsb.has_incompat(JBD2_FEATURE_INCOMPAT_FAST_COMMIT | JBD2_FEATURE_INCOMPAT_ASYNC_COMMIT)
+        {
+            return_error!(
+                ErrCode::ENOTSUP,
+                "Fast commit and asynchronous commit journals are not supported"
+            );
+        }
+        if jsb.feature_incompat() & !JBD2_KNOWN_INCOMPAT_FEATURES != 0 {
+            return_error!(
+                ErrCode::ENOTSUP,
+                "Unsupported journal features {:#x}",
+                jsb.feature_incompat()
+            );
+        }
+
+        let mut journal = Journal::new(jsb, blocks);
+        if jsb.start() != 0 {
+            info!("Recovering journal from sequence {}", jsb.sequence());
+            self.recover_journal(&mut journal);
+        } else if sb.needs_recovery() {
+            // The journal is clean but the superblock was not updated
+            info!("Journal is clean, no recovery needed");
+        }
+        // The log is empty now
+        self.reset_journal(&mut journal);
+        // The superblock may have been replayed
+        let mut sb = self.read_super_block();
+        if sb.needs_recovery() {
+            sb.set_needs_recovery(false);
+            self.write_super_block(&sb);
+        }
+        self.journal = Some(Mutex::new(journal));
+        Ok(())
+    }
+
+    /// Start a journal handle. All metadata writes until the matching
+    /// `trans_stop` belong to the running transaction.
+    pub(super) fn trans_start(&self) {
+        if let Some(journal) = &self.journal {
+            journal.lock().start_handle();
+        }
+    }
+
+    /// Stop a journal handle. The running transaction is committed when
+    /// its last handle stops.
+    pub(super) fn trans_stop(&self) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let mut journal = journal.lock();
+        if let Some(trans) = journal.stop_handle() {
+            self.commit_transaction(&mut journal, &trans);
+        }
+    }
+
+    /// Run `f` in a journal handle. The changes made by `f` are committed
+    /// even if it fails.
+    pub(super) fn transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.trans_start();
+        let res = f();
+        self.trans_stop();
+        res
+    }
+
+    /// Record a metadata block in the running transaction. Return false if
+    /// there is no running transaction.
+    ///
+    /// If the transaction is full, the blocks recorded so far are committed
+    /// first, so that every transaction fits in the log.
+    pub(super) fn trans_put_block(&self, block: &Block) -> bool {
+        let Some(journal) = &self.journal else {
+            return false;
+        };
+        let mut journal = journal.lock();
+        if journal.is_running_full(block.id) {
+            if let Some(trans) = journal.split_running() {
+                warn!(
+                    "Transaction is too large for the journal, commit {} blocks early",
+                    trans.blocks().len()
+                );
+                self.commit_transaction(&mut journal, &trans);
+            }
+        }
+        journal.put_block(block)
+    }
+
+    /// Drop a freed block from the running transaction.
+    pub(super) fn trans_forget(&self, block_id: PBlockId) {
+        if let Some(journal) = &self.journal {
+            journal.lock().forget_block(block_id);
+        }
+    }
+
+    /// Write a transaction to the journal, then checkpoint it.
+    fn commit_transaction(&self, journal: &mut Journal, trans: &Transaction) {
+        let blocks = trans.blocks();
+        if blocks.is_empty() {
+            return;
+        }
+        debug_assert!(blocks.len() <= journal.max_transaction_blocks());
+
+        let jsb = *journal.superblock();
+        let sequence = journal.sequence();
+        let start = jsb.first();
+        let mut jblock = start;
+        let blocks: Vec<&Block> = blocks.values().collect();
+        // Write descriptor blocks and the metadata blocks they describe
+        for chunk in blocks.chunks(DescriptorBlock::max_tags(&jsb)) {
+            let desc_jblock = jblock;
+            jblock = journal.next_block(jblock);
+            let mut tags = Vec::new();
+            for block in chunk {
+                let mut log_block = Block::new(journal.bmap(jblock), block.data);
+                let mut flags = 0;
+                // Escape the blocks that look like journal metadata
+                if log_block.data[..4] == JBD2_MAGIC.to_be_bytes() {
+                    log_block.data[..4].fill(0);
+                    flags |= JBD2_FLAG_ESCAPE;
+                }
+                let checksum = if jsb.has_csum_v2or3() {
+                    JournalBlockTag::compute_checksum(&jsb, sequence, &log_block.data)
+                } else {
+                    0
+                };
+                tags.push(JournalBlockTag {
+                    block: block.id,
+                    flags,
+                    checksum,
+                });
+                self.write_block_direct(&log_block);
+                jblock = journal.next_block(jblock);
+            }
+            let mut desc =
+                DescriptorBlock::new(Block::new(journal.bmap(desc_jblock), [0; BLOCK_SIZE]));
+            desc.init(sequence);
+            desc.set_tags(&jsb, &tags);
+            let mut desc_block = desc.block();
+            DescriptorBlock::set_tail(&jsb, &mut desc_block);
+            self.write_block_direct(&desc_block);
+        }
+        // Mark the log as non-empty, so that recovery will find the transaction
+        self.mark_needs_recovery(true);
+        journal.superblock_mut().set_start(start);
+        journal.superblock_mut().set_sequence(sequence);
+        self.write_journal_superblock(journal);
+        // Write the commit block, the transaction is durable from now on
+        let mut commit_block = Block::new(journal.bmap(jblock), [0; BLOCK_SIZE]);
+        commit_block.write_offset_as(0, &CommitHeader::new(sequence));
+        CommitHeader::set_checksum(&jsb, &mut commit_block);
+        self.write_block_direct(&commit_block);
+        trace!("Commit transaction {} ok", sequence);
+
+        // Checkpoint: write the metadata blocks to their final locations
+        for block in blocks {
+            self.write_block_direct(block);
+        }
+        journal.set_sequence(sequence.wrapping_add(1));
+        self.reset_journal(journal);
+        self.mark_needs_recovery(false);
+    }
+
+    /// Set or clear the `needs_recovery` flag of the superblock on disk.
+    ///
+    /// The superblock is written directly, because the copy in the running
+    /// transaction must not reach the disk before it commits.
+    fn mark_needs_recovery(&self, needs_recovery: bool) {
+        let mut block = self.read_block_direct(0);
+        let mut sb: SuperBlock = block.read_offset_as(BASE_OFFSET);
+        if sb.needs_recovery() == needs_recovery {
+            return;
+        }
+        sb.set_needs_recovery(needs_recovery);
+        sb.set_checksum();
+        block.write_offset_as(BASE_OFFSET, &sb);
+        self.write_block_direct(&block);
+    }
+
+    /// Mark the journal as empty.
+    fn reset_journal(&self, journal: &mut Journal) {
+        let sequence = journal.sequence();
+        journal.superblock_mut().set_start(0);
+        journal.superblock_mut().set_sequence(sequence);
+        self.write_journal_superblock(journal);
+    }
+
+    /// Write the journal superblock to disk.
+    fn write_journal_superblock(&self, journal: &mut Journal) {
+        journal.superblock_mut().set_checksum();
+        let mut block = self.read_block_direct(journal.bmap(0));
+        block.write_offset_as(0, journal.superblock());
+        self.write_block_direct(&block);
+    }
+
+    /// Replay all committed transactions in the journal.
+    fn recover_journal(&self, journal: &mut Journal) {
+        let mut revoked = BTreeMap::new();
+        let end = self.recovery_pass(journal, RecoveryPass::Scan, 0, &mut revoked);
+        self.recovery_pass(journal, RecoveryPass::Revoke, end, &mut revoked);
+        self.recovery_pass(journal, RecoveryPass::Replay, end, &mut revoked);
+        info!(
+            "Journal recovery done, replayed transactions {}..{}",
+            journal.superblock().sequence(),
+            end
+        );
+        // Skip a sequence number, so that stale blocks from the replayed
+        // transactions can never be mistaken for a new transaction.
+        journal.set_sequence(end.wrapping_add(1));
+    }
 
-    /// stop transaction
-    #[allow(unused)]
-    pub(super) fn trans_abort(&self) {}
+    /// Walk through the log from the journal start. Return the sequence
+    /// number following the last complete transaction.
+    ///
+    /// `end` is the sequence number returned by the scan pass, which is
+    /// ignored in the scan pass itself.
+    fn recovery_pass(
+        &self,
+        journal: &Journal,
+        pass: RecoveryPass,
+        end: u32,
+        revoked: &mut BTreeMap<PBlockId, u32>,
+    ) -> u32 {
+        let jsb = journal.superblock();
+        let mut sequence = jsb.sequence();
+        let mut jblock = jsb.start();
+        loop {
+            if pass != RecoveryPass::Scan && sequence == end {
+                break;
+            }
+            let block = self.read_block_direct(journal.bmap(jblock));
+            jblock = journal.next_block(jblock);
+            let header: JournalHeader = block.read_offset_as(0);
+            if !header.check_magic() || header.sequence() != sequence {
+                break;
+            }
+            match header.block_type() {
+                JBD2_DESCRIPTOR_BLOCK => {
+                    if !DescriptorBlock::verify_tail(jsb, &block) {
+                        warn!("Journal descriptor block checksum mismatch");
+                        break;
+                    }
+                    let tags = DescriptorBlock::new(block).tags(jsb);
+                    for tag in tags {
+                        let log_jblock = jblock;
+                        jblock = journal.next_block(jblock);
+                        if pass != RecoveryPass::Replay {
+                            continue;
+                        }
+                        // Blocks revoked by this or a later transaction are not replayed
+                        if revoked.get(&tag.block).is_some_and(|&rev| rev >= sequence) {
+                            continue;
+                        }
+                        let log_block = self.read_block_direct(journal.bmap(log_jblock));
+                        if jsb.has_csum_v2or3()
+                            && JournalBlockTag::compute_checksum(jsb, sequence, &log_block.data)
+                                != tag.checksum
+                        {
+                            warn!("Journal block {} checksum mismatch, skipped", tag.block);
+                            continue;
+                        }
+                        let mut block = Block::new(tag.block, log_block.data);
+                        if tag.flags & JBD2_FLAG_ESCAPE != 0 {
+                            block.write_offset(0, &JBD2_MAGIC.to_be_bytes());
+                        }
+                        self.write_block_direct(&block);
+                    }
+                }
+                JBD2_COMMIT_BLOCK => {
+                    if pass == RecoveryPass::Scan && !CommitHeader::verify_checksum(jsb, &block) {
+                        warn!("Journal commit block checksum mismatch");
+                        break;
+                    }
+                    sequence = sequence.wrapping_add(1);
+                }
+                JBD2_REVOKE_BLOCK => {
+                    if !DescriptorBlock::verify_tail(jsb, &block) {
+                        warn!("Journal revoke block checksum mismatch");
+                        break;
+                    }
+                    if pass == RecoveryPass::Revoke {
+                        for record in RevokeBlock::new(&block).records(jsb) {
+                            let rev = revoked.entry(record).or_insert(sequence);
+                            *rev = (*rev).max(sequence);
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        sequence
+    }
 }
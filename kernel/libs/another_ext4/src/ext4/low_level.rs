@@ -26,7 +26,7 @@ impl Ext4 {
     ///
     /// `EINVAL` if the inode is invalid (link count == 0).
     pub fn getattr(&self, id: InodeId) -> Result<FileAttr> {
-        let inode = self.read_inode(id);
+        let inode = self.read_inode(id)?;
         if inode.inode.link_count() == 0 {
             return_error!(ErrCode::EINVAL, "Invalid inode {}", id);
         }
@@ -75,41 +75,38 @@ impl Ext4 {
         ctime: Option<u32>,
         crtime: Option<u32>,
     ) -> Result<()> {
-        let mut inode = self.read_inode(id);
-        if inode.inode.mode().bits() == 0 {
-            return_error!(ErrCode::EINVAL, "Invalid inode {}", id);
-        }
-        if let Some(mode) = mode {
-            inode.inode.set_mode(mode);
-        }
-        if let Some(uid) = uid {
-            inode.inode.set_uid(uid);
-        }
-        if let Some(gid) = gid {
-            inode.inode.set_gid(gid);
-        }
-        if let Some(size) = size {
-            // If size increases, allocate new blocks if needed.
-            let required_blocks = (size as usize).div_ceil(INODE_BLOCK_SIZE);
-            for _ in inode.inode.block_count()..required_blocks as u64 {
-                self.inode_append_block(&mut inode)?;
+        self.transaction(|| {
+            let mut inode = self.read_inode(id)?;
+            if inode.inode.mode().bits() == 0 {
+                return_error!(ErrCode::EINVAL, "Invalid inode {}", id);
             }
-            inode.inode.set_size(size);
-        }
-        if let Some(atime) = atime {
-            inode.inode.set_atime(atime);
-        }
-        if let Some(mtime) = mtime {
-            inode.inode.set_mtime(mtime);
-        }
-        if let Some(ctime) = ctime {
-            inode.inode.set_ctime(ctime);
-        }
-        if let Some(crtime) = crtime {
-            inode.inode.set_crtime(crtime);
-        }
-        self.write_inode_with_csum(&mut inode);
-        Ok(())
+            if let Some(mode) = mode {
+                inode.inode.set_mode(mode);
+            }
+            if let Some(uid) = uid {
+                inode.inode.set_uid(uid);
+            }
+            if let Some(gid) = gid {
+                inode.inode.set_gid(gid);
+            }
+            if let Some(size) = size {
+                self.truncate(&mut inode, size)?;
+            }
+            if let Some(atime) = atime {
+                inode.inode.set_atime(atime);
+            }
+            if let Some(mtime) = mtime {
+                inode.inode.set_mtime(mtime);
+            }
+            if let Some(ctime) = ctime {
+                inode.inode.set_ctime(ctime);
+            }
+            if let Some(crtime) = crtime {
+                inode.inode.set_crtime(crtime);
+            }
+            self.write_inode_with_csum(&mut inode);
+            Ok(())
+        })
     }
 
     /// Create a file. This function will not check the existence of
@@ -131,16 +128,18 @@ impl Ext4 {
     /// * `ENOTDIR` - `parent` is not a directory
     /// * `ENOSPC` - No space left on device
     pub fn create(&self, parent: InodeId, name: &str, mode: InodeMode) -> Result<InodeId> {
-        let mut parent = self.read_inode(parent);
-        // Can only create a file in a directory
-        if !parent.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
-        }
-        // Create child inode and link it to parent directory
-        let mut child = self.create_inode(mode)?;
-        self.link_inode(&mut parent, &mut child, name)?;
-        // Create file handler
-        Ok(child.id)
+        self.transaction(|| {
+            let mut parent = self.read_inode(parent)?;
+            // Can only create a file in a directory
+            if !parent.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
+            }
+            // Create child inode and link it to parent directory
+            let mut child = self.create_inode(mode)?;
+            self.link_inode(&mut parent, &mut child, name)?;
+            // Create file handler
+            Ok(child.id)
+        })
     }
 
     /// Read data from a file. This function will read exactly `buf.len()`
@@ -161,7 +160,7 @@ impl Ext4 {
     /// * `EISDIR` - `file` is not a regular file
     pub fn read(&self, file: InodeId, offset: usize, buf: &mut [u8]) -> Result<usize> {
         // Get the inode of the file
-        let file = self.read_inode(file);
+        let file = self.read_inode(file)?;
         if !file.inode.is_file() {
             return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
         }
@@ -171,33 +170,27 @@ impl Ext4 {
             return Ok(0);
         }
         // Calc the actual size to read
-        let read_size = min(buf.len(), file.inode.size() as usize - offset);
-        // Calc the start block of reading
-        let start_iblock = (offset / BLOCK_SIZE) as LBlockId;
-        // Calc the length that is not aligned to the block size
-        let misaligned = offset % BLOCK_SIZE;
+        let read_size = min(
+            buf.len(),
+            (file.inode.size() as usize).saturating_sub(offset),
+        );
 
         let mut cursor = 0;
-        let mut iblock = start_iblock;
-        // Read first block
-        if misaligned > 0 {
-            let read_len = min(BLOCK_SIZE - misaligned, read_size);
-            let fblock = self.extent_query(&file, start_iblock).unwrap();
-            let block = self.read_block(fblock);
-            // Copy data from block to the user buffer
-            buf[cursor..cursor + read_len].copy_from_slice(block.read_offset(misaligned, read_len));
-            cursor += read_len;
-            iblock += 1;
-        }
-        // Continue with full block reads
         while cursor < read_size {
-            let read_len = min(BLOCK_SIZE, read_size - cursor);
-            let fblock = self.extent_query(&file, iblock).unwrap();
-            let block = self.read_block(fblock);
-            // Copy data from block to the user buffer
-            buf[cursor..cursor + read_len].copy_from_slice(block.read_offset(0, read_len));
+            let iblock = ((offset + cursor) / BLOCK_SIZE) as LBlockId;
+            let misaligned = (offset + cursor) % BLOCK_SIZE;
+            let read_len = min(BLOCK_SIZE - misaligned, read_size - cursor);
+            match self.extent_lookup(&file, iblock)? {
+                Some(ex) if !ex.is_unwritten() => {
+                    let block = self.read_block(ex.pblock_of(iblock));
+                    // Copy data from block to the user buffer
+                    buf[cursor..cursor + read_len]
+                        .copy_from_slice(block.read_offset(misaligned, read_len));
+                }
+                // Holes and unwritten blocks read as zeros
+                _ => buf[cursor..cursor + read_len].fill(0),
+            }
             cursor += read_len;
-            iblock += 1;
         }
 
         Ok(cursor)
@@ -220,43 +213,302 @@ impl Ext4 {
     /// * `EISDIR` - `file` is not a regular file
     /// * `ENOSPC` - no space left on device
     pub fn write(&self, file: InodeId, offset: usize, data: &[u8]) -> Result<usize> {
-        // Get the inode of the file
-        let mut file = self.read_inode(file);
+        self.transaction(|| {
+            // Get the inode of the file
+            let mut file = self.read_inode(file)?;
+            if !file.inode.is_file() {
+                return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
+            }
+
+            let write_size = data.len();
+            // Write data
+            let mut cursor = 0;
+            let mut unwritten = false;
+            while cursor < write_size {
+                let iblock = ((offset + cursor) / BLOCK_SIZE) as LBlockId;
+                let misaligned = (offset + cursor) % BLOCK_SIZE;
+                let write_len = min(BLOCK_SIZE - misaligned, write_size - cursor);
+                let mut block = match self.extent_lookup(&file, iblock)? {
+                    Some(ex) if !ex.is_unwritten() => self.read_block(ex.pblock_of(iblock)),
+                    Some(ex) => {
+                        // Unwritten blocks are converted after writing
+                        unwritten = true;
+                        Block::new(ex.pblock_of(iblock), [0; BLOCK_SIZE])
+                    }
+                    None => {
+                        // Allocate a block for the hole
+                        let fblock = self.extent_query_or_create(&mut file, iblock, 1)?;
+                        let block_count = file.inode.fs_block_count() + 1;
+                        file.inode.set_fs_block_count(block_count);
+                        Block::new(fblock, [0; BLOCK_SIZE])
+                    }
+                };
+                block.write_offset(misaligned, &data[cursor..cursor + write_len]);
+                self.write_data_block(&block);
+                cursor += write_len;
+            }
+            if unwritten {
+                let start_iblock = (offset / BLOCK_SIZE) as LBlockId;
+                let end_iblock = (offset + write_size).div_ceil(BLOCK_SIZE) as LBlockId;
+                self.extent_mark_written(&mut file, start_iblock, end_iblock)?;
+            }
+            if offset + cursor > file.inode.size() as usize {
+                file.inode.set_size((offset + cursor) as u64);
+            }
+            self.write_inode_with_csum(&mut file);
+
+            Ok(cursor)
+        })
+    }
+
+    /// Map a logic block of a file to a physical block for reading.
+    ///
+    /// # Params
+    ///
+    /// * `file` - the file handler, acquired by `open` or `create`
+    /// * `iblock` - the logic block id
+    ///
+    /// # Return
+    ///
+    /// `Ok(Some(pblock))` - the physical block id
+    /// `Ok(None)` - the block is a hole or unwritten, which reads as zeros
+    ///
+    /// # Error
+    ///
+    /// * `EISDIR` - `file` is not a regular file
+    pub fn bmap(&self, file: InodeId, iblock: LBlockId) -> Result<Option<PBlockId>> {
+        let file = self.read_inode(file)?;
         if !file.inode.is_file() {
             return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
         }
+        Ok(self
+            .extent_lookup(&file, iblock)?
+            .filter(|ex| !ex.is_unwritten())
+            .map(|ex| ex.pblock_of(iblock)))
+    }
 
-        let write_size = data.len();
-        // Calc the start and end block of writing
-        let start_iblock = (offset / BLOCK_SIZE) as LBlockId;
-        let end_iblock = ((offset + write_size) / BLOCK_SIZE) as LBlockId;
-        // Append enough block for writing
-        let append_block_count = end_iblock as i64 + 1 - file.inode.fs_block_count() as i64;
-        for _ in 0..append_block_count {
-            self.inode_append_block(&mut file)?;
-        }
+    /// Map a logic block of a file to a physical block for writing. An
+    /// unwritten block is allocated if the block is a hole.
+    ///
+    /// Unwritten blocks should be marked as written by `mark_written` after
+    /// the data is written to them. The file size is not changed.
+    ///
+    /// # Params
+    ///
+    /// * `file` - the file handler, acquired by `open` or `create`
+    /// * `iblock` - the logic block id
+    ///
+    /// # Return
+    ///
+    /// `Ok((pblock, unwritten))` - the physical block id and whether it is unwritten
+    ///
+    /// # Error
+    ///
+    /// * `EISDIR` - `file` is not a regular file
+    /// * `ENOSPC` - no space left on device
+    pub fn bmap_alloc(&self, file: InodeId, iblock: LBlockId) -> Result<(PBlockId, bool)> {
+        self.transaction(|| {
+            let mut file = self.read_inode(file)?;
+            if !file.inode.is_file() {
+                return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
+            }
+            if let Some(ex) = self.extent_lookup(&file, iblock)? {
+                return Ok((ex.pblock_of(iblock), ex.is_unwritten()));
+            }
+            self.extent_alloc_range(&mut file, iblock, iblock + 1)?;
+            self.write_inode_with_csum(&mut file);
+            let pblock = self.extent_query(&file, iblock)?;
+            Ok((pblock, true))
+        })
+    }
 
-        // Write data
-        let mut cursor = 0;
-        let mut iblock = start_iblock;
-        while cursor < write_size {
-            let write_len = min(BLOCK_SIZE, write_size - cursor);
-            let fblock = self.extent_query(&file, iblock)?;
-            let mut block = self.read_block(fblock);
-            block.write_offset(
-                (offset + cursor) % BLOCK_SIZE,
-                &data[cursor..cursor + write_len],
-            );
-            self.write_block(&block);
-            cursor += write_len;
-            iblock += 1;
+    /// Mark the unwritten blocks of a file in range `[iblock, iblock + count)`
+    /// as written.
+    ///
+    /// # Params
+    ///
+    /// * `file` - the file handler, acquired by `open` or `create`
+    /// * `iblock` - the first logic block id
+    /// * `count` - the number of blocks
+    ///
+    /// # Error
+    ///
+    /// * `EISDIR` - `file` is not a regular file
+    /// * `ENOSPC` - no space left on device
+    pub fn mark_written(&self, file: InodeId, iblock: LBlockId, count: u32) -> Result<()> {
+        self.transaction(|| {
+            let mut file = self.read_inode(file)?;
+            if !file.inode.is_file() {
+                return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
+            }
+            self.extent_mark_written(&mut file, iblock, iblock.saturating_add(count))?;
+            self.write_inode_with_csum(&mut file);
+            Ok(())
+        })
+    }
+
+    /// Manipulate the allocated space of a file.
+    ///
+    /// # Params
+    ///
+    /// * `file` - the file handler, acquired by `open` or `create`
+    /// * `offset` - start of the byte range
+    /// * `len` - length of the byte range
+    /// * `flags` - the operation mode, empty flags allocate the range and extend
+    ///   the file if needed
+    ///
+    /// # Error
+    ///
+    /// * `EISDIR` - `file` is not a regular file
+    /// * `EINVAL` - `len` is 0, or the range is invalid for collapsing
+    /// * `ENOSPC` - no space left on device
+    /// * `ENOTSUP` - the flags combination is not supported
+    pub fn fallocate(
+        &self,
+        file: InodeId,
+        offset: usize,
+        len: usize,
+        flags: FallocFlags,
+    ) -> Result<()> {
+        self.transaction(|| {
+            let mut file = self.read_inode(file)?;
+            if !file.inode.is_file() {
+                return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
+            }
+            if len == 0 {
+                return_error!(ErrCode::EINVAL, "Invalid fallocate length 0");
+            }
+            if (offset + len).div_ceil(BLOCK_SIZE) > MAX_BLOCKS as usize {
+                return_error!(
+                    ErrCode::EFBIG,
+                    "Fallocate range {}+{} is too large",
+                    offset,
+                    len
+                );
+            }
+            let keep_size = flags.contains(FallocFlags::KEEP_SIZE);
+            let mode = flags - FallocFlags::KEEP_SIZE;
+            if mode.is_empty() {
+                self.falloc_allocate(&mut file, offset, len, keep_size)?;
+            } else if mode == FallocFlags::PUNCH_HOLE && keep_size {
+                self.falloc_punch_hole(&mut file, offset, len)?;
+            } else if mode == FallocFlags::ZERO_RANGE {
+                self.falloc_zero_range(&mut file, offset, len, keep_size)?;
+            } else if mode == FallocFlags::COLLAPSE_RANGE && !keep_size {
+                self.falloc_collapse_range(&mut file, offset, len)?;
+            } else {
+                return_error!(ErrCode::ENOTSUP, "Unsupported fallocate flags {:?}", flags);
+            }
+            self.write_inode_with_csum(&mut file);
+            Ok(())
+        })
+    }
+
+    /// Get the inode flags.
+    ///
+    /// # Params
+    ///
+    /// * `inode` - the inode id
+    ///
+    /// # Return
+    ///
+    /// The flags of the inode, unknown flags are dropped.
+    ///
+    /// # Error
+    ///
+    /// `EINVAL` if the inode is invalid (link count == 0).
+    pub fn getflags(&self, inode: InodeId) -> Result<InodeFlags> {
+        let inode = self.read_inode(inode)?;
+        if inode.inode.link_count() == 0 {
+            return_error!(ErrCode::EINVAL, "Invalid inode {}", inode.id);
         }
-        if offset + cursor > file.inode.size() as usize {
-            file.inode.set_size((offset + cursor) as u64);
+        Ok(InodeFlags::from_bits_truncate(inode.inode.flags()))
+    }
+
+    /// Set the inode flags. Only the flags in `InodeFlags::USER_MODIFIABLE`
+    /// are changed, the others must be the same as the current ones.
+    ///
+    /// # Params
+    ///
+    /// * `inode` - the inode id
+    /// * `flags` - the new flags
+    ///
+    /// # Error
+    ///
+    /// * `EINVAL` - the inode is invalid (link count == 0)
+    /// * `ENOTSUP` - trying to change the flags that are not user modifiable
+    pub fn setflags(&self, inode: InodeId, flags: InodeFlags) -> Result<()> {
+        self.transaction(|| {
+            let mut inode = self.read_inode(inode)?;
+            if inode.inode.link_count() == 0 {
+                return_error!(ErrCode::EINVAL, "Invalid inode {}", inode.id);
+            }
+            let old_flags = InodeFlags::from_bits_retain(inode.inode.flags());
+            let changed = (old_flags ^ flags) & InodeFlags::all();
+            if !InodeFlags::USER_MODIFIABLE.contains(changed) {
+                return_error!(
+                    ErrCode::ENOTSUP,
+                    "Inode flags {:?} can not be changed",
+                    changed - InodeFlags::USER_MODIFIABLE
+                );
+            }
+            let new_flags =
+                (old_flags - InodeFlags::USER_MODIFIABLE) | (flags & InodeFlags::USER_MODIFIABLE);
+            inode.inode.replace_flags(new_flags.bits());
+            self.write_inode_with_csum(&mut inode);
+            Ok(())
+        })
+    }
+
+    /// Get the extents of a file, sorted by logic block id.
+    ///
+    /// # Params
+    ///
+    /// * `file` - the file handler, acquired by `open` or `create`
+    ///
+    /// # Error
+    ///
+    /// `EISDIR` - `file` is not a regular file
+    pub fn fiemap(&self, file: InodeId) -> Result<Vec<Extent>> {
+        let file = self.read_inode(file)?;
+        if !file.inode.is_file() {
+            return_error!(ErrCode::EISDIR, "Inode {} is not a file", file.id);
         }
-        self.write_inode_with_csum(&mut file);
+        self.extent_list(&file)
+    }
 
-        Ok(cursor)
+    /// Discard the free blocks in the physical block range `[start, start + len)`.
+    ///
+    /// # Params
+    ///
+    /// * `start` - the first physical block id
+    /// * `len` - the number of blocks in the range
+    /// * `min_len` - free extents shorter than this are skipped
+    /// * `discard` - called with the first block id and the length of each
+    ///   free extent to discard
+    ///
+    /// # Return
+    ///
+    /// The number of blocks discarded.
+    ///
+    /// # Error
+    ///
+    /// Errors returned by `discard`.
+    pub fn fstrim(
+        &self,
+        start: PBlockId,
+        len: u64,
+        min_len: u64,
+        mut discard: impl FnMut(PBlockId, u64) -> Result<()>,
+    ) -> Result<u64> {
+        let sb = self.read_super_block();
+        let end = start.saturating_add(len).min(sb.block_count());
+        let start = start.max(sb.first_data_block() as PBlockId);
+        let mut trimmed = 0;
+        for bgid in 0..sb.block_group_count() {
+            trimmed += self.trim_group(bgid, start, end, min_len.max(1), &mut discard)?;
+        }
+        Ok(trimmed)
     }
 
     /// Create a hard link. This function will not check name conflict,
@@ -272,18 +524,20 @@ impl Ext4 {
     /// * `ENOTDIR` - `parent` is not a directory
     /// * `ENOSPC` - no space left on device
     pub fn link(&self, child: InodeId, parent: InodeId, name: &str) -> Result<()> {
-        let mut parent = self.read_inode(parent);
-        // Can only link to a directory
-        if !parent.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
-        }
-        let mut child = self.read_inode(child);
-        // Cannot link a directory
-        if child.inode.is_dir() {
-            return_error!(ErrCode::EISDIR, "Cannot link a directory");
-        }
-        self.link_inode(&mut parent, &mut child, name)?;
-        Ok(())
+        self.transaction(|| {
+            let mut parent = self.read_inode(parent)?;
+            // Can only link to a directory
+            if !parent.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
+            }
+            let mut child = self.read_inode(child)?;
+            // Cannot link a directory
+            if child.inode.is_dir() {
+                return_error!(ErrCode::EISDIR, "Cannot link a directory");
+            }
+            self.link_inode(&mut parent, &mut child, name)?;
+            Ok(())
+        })
     }
 
     /// Unlink a file.
@@ -292,25 +546,29 @@ impl Ext4 {
     ///
     /// * `parent` - the inode of the directory to unlink from
     /// * `name` - the name of the file to unlink
+    /// * `free` - whether to free the file if it has no links, otherwise
+    ///   call `free_orphan` to free it later
     ///
     /// # Error
     ///
     /// * `ENOTDIR` - `parent` is not a directory
     /// * `ENOENT` - `name` does not exist in `parent`
     /// * `EISDIR` - `parent/name` is a directory
-    pub fn unlink(&self, parent: InodeId, name: &str) -> Result<()> {
-        let mut parent = self.read_inode(parent);
-        // Can only unlink from a directory
-        if !parent.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
-        }
-        // Cannot unlink directory
-        let child_id = self.dir_find_entry(&parent, name)?;
-        let mut child = self.read_inode(child_id);
-        if child.inode.is_dir() {
-            return_error!(ErrCode::EISDIR, "Cannot unlink a directory");
-        }
-        self.unlink_inode(&mut parent, &mut child, name, true)
+    pub fn unlink(&self, parent: InodeId, name: &str, free: bool) -> Result<()> {
+        self.transaction(|| {
+            let mut parent = self.read_inode(parent)?;
+            // Can only unlink from a directory
+            if !parent.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
+            }
+            // Cannot unlink directory
+            let child_id = self.dir_find_entry(&parent, name)?;
+            let mut child = self.read_inode(child_id)?;
+            if child.inode.is_dir() {
+                return_error!(ErrCode::EISDIR, "Cannot unlink a directory");
+            }
+            self.unlink_inode(&mut parent, &mut child, name, free)
+        })
     }
 
     /// Move a file.
@@ -335,30 +593,32 @@ impl Ext4 {
         new_parent: InodeId,
         new_name: &str,
     ) -> Result<()> {
-        // Check parent
-        let mut parent = self.read_inode(parent);
-        if !parent.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
-        }
-        // Check new parent
-        let mut new_parent = self.read_inode(new_parent);
-        if !new_parent.inode.is_dir() {
-            return_error!(
-                ErrCode::ENOTDIR,
-                "Inode {} is not a directory",
-                new_parent.id
-            );
-        }
-        // Check child existence
-        let child_id = self.dir_find_entry(&parent, name)?;
-        let mut child = self.read_inode(child_id);
-        // Check name conflict
-        if self.dir_find_entry(&new_parent, new_name).is_ok() {
-            return_error!(ErrCode::EEXIST, "Dest name {} already exists", new_name);
-        }
-        // Move
-        self.unlink_inode(&mut parent, &mut child, name, false)?;
-        self.link_inode(&mut new_parent, &mut child, new_name)
+        self.transaction(|| {
+            // Check parent
+            let mut parent = self.read_inode(parent)?;
+            if !parent.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
+            }
+            // Check new parent
+            let mut new_parent = self.read_inode(new_parent)?;
+            if !new_parent.inode.is_dir() {
+                return_error!(
+                    ErrCode::ENOTDIR,
+                    "Inode {} is not a directory",
+                    new_parent.id
+                );
+            }
+            // Check child existence
+            let child_id = self.dir_find_entry(&parent, name)?;
+            let mut child = self.read_inode(child_id)?;
+            // Check name conflict
+            if self.dir_find_entry(&new_parent, new_name).is_ok() {
+                return_error!(ErrCode::EEXIST, "Dest name {} already exists", new_name);
+            }
+            // Move
+            self.unlink_inode(&mut parent, &mut child, name, false)?;
+            self.link_inode(&mut new_parent, &mut child, new_name)
+        })
     }
 
     /// Create a directory. This function will not check name conflict,
@@ -379,21 +639,23 @@ impl Ext4 {
     /// * `ENOTDIR` - `parent` is not a directory
     /// * `ENOSPC` - no space left on device
     pub fn mkdir(&self, parent: InodeId, name: &str, mode: InodeMode) -> Result<InodeId> {
-        let mut parent = self.read_inode(parent);
-        // Can only create a directory in a directory
-        if !parent.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
-        }
-        // Create file/directory
-        let mode = mode & InodeMode::PERM_MASK | InodeMode::DIRECTORY;
-        let mut child = self.create_inode(mode)?;
-        // Add "." entry
-        let child_self = child.clone();
-        self.dir_add_entry(&mut child, &child_self, ".")?;
-        child.inode.set_link_count(1);
-        // Link the new inode
-        self.link_inode(&mut parent, &mut child, name)?;
-        Ok(child.id)
+        self.transaction(|| {
+            let mut parent = self.read_inode(parent)?;
+            // Can only create a directory in a directory
+            if !parent.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
+            }
+            // Create file/directory
+            let mode = mode & InodeMode::PERM_MASK | InodeMode::DIRECTORY;
+            let mut child = self.create_inode(mode)?;
+            // Add "." entry
+            let child_self = child.clone();
+            self.dir_add_entry(&mut child, &child_self, ".")?;
+            child.inode.set_link_count(1);
+            // Link the new inode
+            self.link_inode(&mut parent, &mut child, name)?;
+            Ok(child.id)
+        })
     }
 
     /// Look up a directory entry by name.
@@ -412,7 +674,7 @@ impl Ext4 {
     /// * `ENOTDIR` - `parent` is not a directory
     /// * `ENOENT` - `name` does not exist in `parent`
     pub fn lookup(&self, parent: InodeId, name: &str) -> Result<InodeId> {
-        let parent = self.read_inode(parent);
+        let parent = self.read_inode(parent)?;
         // Can only lookup in a directory
         if !parent.inode.is_dir() {
             return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
@@ -434,7 +696,7 @@ impl Ext4 {
     ///
     /// `ENOTDIR` - `inode` is not a directory
     pub fn listdir(&self, inode: InodeId) -> Result<Vec<DirEntry>> {
-        let inode_ref = self.read_inode(inode);
+        let inode_ref = self.read_inode(inode)?;
         // Can only list a directory
         if inode_ref.inode.file_type() != FileType::Directory {
             return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", inode);
@@ -448,29 +710,59 @@ impl Ext4 {
     ///
     /// * `parent` - the parent directory where the directory is located
     /// * `name` - the name of the directory to remove
+    /// * `free` - whether to free the directory, otherwise call `free_orphan`
+    ///   to free it later
     ///
     /// # Error
     ///
     /// * `ENOTDIR` - `parent` or `child` is not a directory
     /// * `ENOENT` - `name` does not exist in `parent`
     /// * `ENOTEMPTY` - `child` is not empty
-    pub fn rmdir(&self, parent: InodeId, name: &str) -> Result<()> {
-        let mut parent = self.read_inode(parent);
-        // Can only remove a directory in a directory
-        if !parent.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
-        }
-        let mut child = self.read_inode(self.dir_find_entry(&parent, name)?);
-        // Child must be a directory
-        if !child.inode.is_dir() {
-            return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", child.id);
-        }
-        // Child must be empty
-        if self.dir_list_entries(&child).len() > 2 {
-            return_error!(ErrCode::ENOTEMPTY, "Directory {} is not empty", child.id);
-        }
-        // Remove directory entry
-        self.unlink_inode(&mut parent, &mut child, name, true)
+    pub fn rmdir(&self, parent: InodeId, name: &str, free: bool) -> Result<()> {
+        self.transaction(|| {
+            let mut parent = self.read_inode(parent)?;
+            // Can only remove a directory in a directory
+            if !parent.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", parent.id);
+            }
+            let mut child = self.read_inode(self.dir_find_entry(&parent, name)?)?;
+            // Child must be a directory
+            if !child.inode.is_dir() {
+                return_error!(ErrCode::ENOTDIR, "Inode {} is not a directory", child.id);
+            }
+            // Child must be empty
+            if self.dir_list_entries(&child).len() > 2 {
+                return_error!(ErrCode::ENOTEMPTY, "Directory {} is not empty", child.id);
+            }
+            // Remove directory entry
+            self.unlink_inode(&mut parent, &mut child, name, free)?;
+            if !free {
+                // A removed directory has no links, even if it is not freed yet
+                child.inode.set_link_count(0);
+                self.write_inode_with_csum(&mut child);
+            }
+            Ok(())
+        })
+    }
+
+    /// Free an orphan inode, i.e. an inode that has been unlinked but not
+    /// freed by `unlink` or `rmdir`.
+    ///
+    /// # Params
+    ///
+    /// * `inode` - the inode to free
+    ///
+    /// # Error
+    ///
+    /// * `EINVAL` - `inode` still has links
+    pub fn free_orphan(&self, inode: InodeId) -> Result<()> {
+        self.transaction(|| {
+            let mut inode = self.read_inode(inode)?;
+            if inode.inode.link_count() > 0 {
+                return_error!(ErrCode::EINVAL, "Inode {} still has links", inode.id);
+            }
+            self.free_inode(&mut inode)
+        })
     }
 
     /// Get extended attribute of a file.
@@ -488,14 +780,9 @@ impl Ext4 {
     ///
     /// `ENODATA` - the attribute does not exist
     pub fn getxattr(&self, inode: InodeId, name: &str) -> Result<Vec<u8>> {
-        let inode_ref = self.read_inode(inode);
-        let xattr_block_id = inode_ref.inode.xattr_block();
-        if xattr_block_id == 0 {
-            return_error!(ErrCode::ENODATA, "Xattr {} does not exist", name);
-        }
-        let xattr_block = XattrBlock::new(self.read_block(xattr_block_id));
-        match xattr_block.get(name) {
-            Some(value) => Ok(value.to_owned()),
+        let mut inode_ref = self.read_inode(inode)?;
+        match self.xattr_get(&mut inode_ref, name)? {
+            Some(value) => Ok(value),
             None => Err(format_error!(
                 ErrCode::ENODATA,
                 "Xattr {} does not exist",
@@ -504,8 +791,11 @@ impl Ext4 {
         }
     }
 
-    /// Set extended attribute of a file. This function will not check name conflict,
-    /// call `getxattr` to check beforehand.
+    /// Set extended attribute of a file. The old value is replaced if the
+    /// attribute already exists, call `getxattr` to check beforehand.
+    ///
+    /// The attribute is stored in the inode if there is enough space,
+    /// otherwise in the xattr block.
     ///
     /// # Params
     ///
@@ -515,30 +805,12 @@ impl Ext4 {
     ///
     /// # Error
     ///
-    /// `ENOSPC` - xattr block does not have enough space
+    /// `ENOSPC` - neither the inode nor the xattr block has enough space
     pub fn setxattr(&self, inode: InodeId, name: &str, value: &[u8]) -> Result<()> {
-        let mut inode_ref = self.read_inode(inode);
-        let xattr_block_id = inode_ref.inode.xattr_block();
-        if xattr_block_id == 0 {
-            // lazy allocate xattr block
-            let pblock = self.alloc_block(&mut inode_ref)?;
-            inode_ref.inode.set_xattr_block(pblock);
-            self.write_inode_with_csum(&mut inode_ref);
-        }
-        let mut xattr_block = XattrBlock::new(self.read_block(inode_ref.inode.xattr_block()));
-        if xattr_block_id == 0 {
-            xattr_block.init();
-        }
-        if xattr_block.insert(name, value) {
-            self.write_block(&xattr_block.block());
-            Ok(())
-        } else {
-            return_error!(
-                ErrCode::ENOSPC,
-                "Xattr block of Inode {} does not have enough space",
-                inode
-            );
-        }
+        self.transaction(|| {
+            let mut inode_ref = self.read_inode(inode)?;
+            self.xattr_set(&mut inode_ref, name, value)
+        })
     }
 
     /// Remove extended attribute of a file.
@@ -552,18 +824,10 @@ impl Ext4 {
     ///
     /// `ENODATA` - the attribute does not exist
     pub fn removexattr(&self, inode: InodeId, name: &str) -> Result<()> {
-        let inode_ref = self.read_inode(inode);
-        let xattr_block_id = inode_ref.inode.xattr_block();
-        if xattr_block_id == 0 {
-            return_error!(ErrCode::ENODATA, "Xattr {} does not exist", name);
-        }
-        let mut xattr_block = XattrBlock::new(self.read_block(xattr_block_id));
-        if xattr_block.remove(name) {
-            self.write_block(&xattr_block.block());
-            Ok(())
-        } else {
-            return_error!(ErrCode::ENODATA, "Xattr {} does not exist", name);
-        }
+        self.transaction(|| {
+            let mut inode_ref = self.read_inode(inode)?;
+            self.xattr_remove(&mut inode_ref, name)
+        })
     }
 
     /// List extended attributes of a file.
@@ -576,13 +840,8 @@ impl Ext4 {
     ///
     /// A list of extended attributes of the file.
     pub fn listxattr(&self, inode: InodeId) -> Result<Vec<String>> {
-        let inode_ref = self.read_inode(inode);
-        let xattr_block_id = inode_ref.inode.xattr_block();
-        if xattr_block_id == 0 {
-            return Ok(Vec::new());
-        }
-        let xattr_block = XattrBlock::new(self.read_block(xattr_block_id));
-        Ok(xattr_block.list())
+        let mut inode_ref = self.read_inode(inode)?;
+        self.xattr_list(&mut inode_ref)
     }
 
     /// Flush all dirty blocks in cache to disk.
@@ -1,16 +1,20 @@
 use crate::constants::*;
 use crate::ext4_defs::*;
+use crate::jbd2::Journal;
 use crate::prelude::*;
 use crate::return_error;
+use spin::Mutex;
 
 mod alloc;
 mod dir;
 mod extent;
+mod falloc;
 mod high_level;
 mod journal;
 mod link;
 mod low_level;
 mod rw;
+mod xattr;
 
 /// The Ext4 filesystem implementation.
 pub struct Ext4 {
@@ -18,6 +22,10 @@ pub struct Ext4 {
     block_cache: BlockCache,
     #[cfg(not(feature = "block_cache"))]
     block_device: Arc<dyn BlockDevice>,
+    /// The journal, if the filesystem has one.
+    journal: Option<Mutex<Journal>>,
+    /// Whether the filesystem can only be mounted read-only.
+    read_only: bool,
 }
 
 impl Ext4 {
@@ -32,6 +40,34 @@ impl Ext4 {
         if !sb.check_magic() {
             return_error!(ErrCode::EINVAL, "Invalid magic number");
         }
+        // Check features that must be understood to access the filesystem
+        let incompat = sb.unsupported_incompat_features();
+        if incompat != 0 {
+            return_error!(
+                ErrCode::EINVAL,
+                "Unsupported incompat features {:#x}",
+                incompat
+            );
+        }
+        // Check superblock checksum
+        if sb.has_metadata_csum() {
+            if !sb.has_valid_checksum_type() {
+                return_error!(ErrCode::EINVAL, "Unsupported metadata checksum type");
+            }
+            if !sb.verify_checksum() {
+                return_error!(ErrCode::EBADMSG, "Superblock checksum mismatch");
+            }
+        }
+        // Check features that must be understood to write the filesystem
+        let ro_compat = sb.unsupported_ro_compat_features();
+        let mut read_only = false;
+        if ro_compat != 0 {
+            warn!(
+                "Unsupported ro_compat features {:#x}, mounting read-only",
+                ro_compat
+            );
+            read_only = true;
+        }
         // Check inode size
         if sb.inode_size() != SB_GOOD_INODE_SIZE {
             return_error!(ErrCode::EINVAL, "Invalid inode size {}", sb.inode_size());
@@ -45,17 +81,41 @@ impl Ext4 {
             );
         }
         // Create Ext4 instance
-        Ok(Self {
+        let mut ext4 = Self {
             #[cfg(feature = "block_cache")]
             block_cache: BlockCache::new(block_device),
             #[cfg(not(feature = "block_cache"))]
             block_device,
-        })
+            journal: None,
+            read_only,
+        };
+        // Replay the journal if the filesystem was not cleanly unmounted
+        ext4.load_journal()?;
+        // Check block group descriptor checksums
+        let sb = ext4.read_super_block();
+        for bgid in 0..sb.block_group_count() {
+            if !ext4.read_block_group(bgid).verify_checksum(&sb) {
+                warn!(
+                    "Block group {} descriptor checksum mismatch, mounting read-only",
+                    bgid
+                );
+                ext4.read_only = true;
+                break;
+            }
+        }
+        Ok(ext4)
     }
-    
+
+    /// Whether the filesystem must be mounted read-only, because it uses
+    /// unsupported read-only compatible features or has corrupted block
+    /// group descriptors.
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Initializes the root directory.
     pub fn init(&mut self) -> Result<()> {
         // Create root directory
-        self.create_root_inode().map(|_| ())
+        self.transaction(|| self.create_root_inode().map(|_| ()))
     }
 }
@@ -2,10 +2,44 @@ use super::Ext4;
 use crate::constants::*;
 use crate::ext4_defs::*;
 use crate::prelude::*;
+use crate::return_error;
 
 impl Ext4 {
-    /// Read a block from block device
+    /// Read a block, including the uncommitted changes in the running transaction
     pub(super) fn read_block(&self, block_id: PBlockId) -> Block {
+        if let Some(journal) = &self.journal {
+            if let Some(block) = journal.lock().get_block(block_id) {
+                return *block;
+            }
+        }
+        self.read_block_direct(block_id)
+    }
+
+    /// Write a metadata block. If a transaction is running, the block is
+    /// recorded in the transaction and written to disk on commit.
+    pub(super) fn write_block(&self, block: &Block) {
+        if self.trans_put_block(block) {
+            return;
+        }
+        self.write_block_direct(block)
+    }
+
+    /// Write a data block. Data blocks bypass the journal (ordered mode),
+    /// so they reach the disk before the metadata referring to them.
+    pub(super) fn write_data_block(&self, block: &Block) {
+        if let Some(journal) = &self.journal {
+            let mut journal = journal.lock();
+            if journal.get_block(block.id).is_some() {
+                // The block is also metadata of the running transaction
+                journal.put_block(block);
+                return;
+            }
+        }
+        self.write_block_direct(block)
+    }
+
+    /// Read a block from block device
+    pub(super) fn read_block_direct(&self, block_id: PBlockId) -> Block {
         #[cfg(feature = "block_cache")]
         {
             self.block_cache.read_block(block_id)
@@ -17,7 +51,7 @@ impl Ext4 {
     }
 
     /// Write a block to block device
-    pub(super) fn write_block(&self, block: &Block) {
+    pub(super) fn write_block_direct(&self, block: &Block) {
         #[cfg(feature = "block_cache")]
         {
             self.block_cache.write_block(block)
@@ -35,37 +69,50 @@ impl Ext4 {
         block.read_offset_as(BASE_OFFSET)
     }
 
-    /// Write super block to block device
+    /// Write super block to block device with checksum
     pub(super) fn write_super_block(&self, sb: &SuperBlock) {
+        let mut sb = *sb;
+        sb.set_checksum();
         let mut block = Block::new(0, [0; BLOCK_SIZE]);
-        block.write_offset_as(BASE_OFFSET, sb);
+        block.write_offset_as(BASE_OFFSET, &sb);
         self.write_block(&block)
     }
 
+    /// The filesystem checksum seed, or `None` if `metadata_csum` is disabled.
+    pub(super) fn csum_seed(&self) -> Option<u32> {
+        let sb = self.read_super_block();
+        sb.has_metadata_csum().then(|| sb.csum_seed())
+    }
+
     /// Read an inode from block device, return an `InodeRef` that
     /// combines the inode and its id.
-    pub fn read_inode(&self, inode_id: InodeId) -> InodeRef {
+    ///
+    /// # Error
+    ///
+    /// `EBADMSG` if the inode checksum mismatches.
+    pub fn read_inode(&self, inode_id: InodeId) -> Result<InodeRef> {
         let (block_id, offset) = self.inode_disk_pos(inode_id);
         let block = self.read_block(block_id);
-        
-        InodeRef::new(inode_id, block.read_offset_as(offset))
+        let inode_ref = InodeRef::new(inode_id, block.read_offset_as(offset));
+        if let Some(seed) = self.csum_seed() {
+            if !inode_ref.verify_checksum(seed) {
+                return_error!(ErrCode::EBADMSG, "Inode {} checksum mismatch", inode_id);
+            }
+        }
+        Ok(inode_ref)
     }
 
     /// Read the root inode from block device
     #[allow(unused)]
-    pub(super) fn read_root_inode(&self) -> InodeRef {
+    pub(super) fn read_root_inode(&self) -> Result<InodeRef> {
         self.read_inode(EXT4_ROOT_INO)
     }
 
     /// Write an inode to block device with checksum
     pub(super) fn write_inode_with_csum(&self, inode_ref: &mut InodeRef) {
-        let super_block = self.read_super_block();
-        inode_ref.set_checksum(&super_block.uuid());
-        self.write_inode_without_csum(inode_ref);
-    }
-
-    /// Write an inode to block device without checksum
-    pub(super) fn write_inode_without_csum(&self, inode_ref: &InodeRef) {
+        if let Some(seed) = self.csum_seed() {
+            inode_ref.set_checksum(seed);
+        }
         let (block_id, offset) = self.inode_disk_pos(inode_ref.id);
         let mut block = self.read_block(block_id);
         block.write_offset_as(offset, &inode_ref.inode);
@@ -86,13 +133,7 @@ impl Ext4 {
     /// Write a block group descriptor to block device with checksum
     pub(super) fn write_block_group_with_csum(&self, bg_ref: &mut BlockGroupRef) {
         let super_block = self.read_super_block();
-        bg_ref.set_checksum(&super_block.uuid());
-        self.write_block_group_without_csum(bg_ref);
-    }
-
-    /// Write a block group descriptor to block device without checksum
-    #[allow(unused)]
-    pub(super) fn write_block_group_without_csum(&self, bg_ref: &BlockGroupRef) {
+        bg_ref.set_checksum(&super_block);
         let (block_id, offset) = self.block_group_disk_pos(bg_ref.id);
         let mut block = self.read_block(block_id as PBlockId);
         block.write_offset_as(offset, &bg_ref.desc);
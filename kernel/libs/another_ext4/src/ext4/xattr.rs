@@ -0,0 +1,147 @@
+use super::Ext4;
+use crate::constants::*;
+use crate::ext4_defs::*;
+use crate::prelude::*;
+use crate::return_error;
+
+impl Ext4 {
+    /// Get a xattr of an inode, looking in the inode first and then in
+    /// the xattr block.
+    pub(super) fn xattr_get(&self, inode: &mut InodeRef, name: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(value) = XattrIbody::new(inode.inode.xattr_ibody_mut()).get(name) {
+            return Ok(Some(value.to_owned()));
+        }
+        match self.read_xattr_block(inode)? {
+            Some(xattr_block) => Ok(xattr_block.get(name).map(|value| value.to_owned())),
+            None => Ok(None),
+        }
+    }
+
+    /// List the xattr names of an inode.
+    pub(super) fn xattr_list(&self, inode: &mut InodeRef) -> Result<Vec<String>> {
+        let mut names = XattrIbody::new(inode.inode.xattr_ibody_mut()).list();
+        if let Some(xattr_block) = self.read_xattr_block(inode)? {
+            names.extend(xattr_block.list());
+        }
+        Ok(names)
+    }
+
+    /// Set a xattr of an inode, replacing the old value if it exists. The
+    /// xattr is stored in the inode if possible, otherwise in the xattr
+    /// block. Nothing is changed on failure.
+    pub(super) fn xattr_set(&self, inode: &mut InodeRef, name: &str, value: &[u8]) -> Result<()> {
+        let mut new_inode = inode.clone();
+        let mut xattr_block = self.read_xattr_block(&new_inode)?;
+        let mut block_dirty = false;
+        // Remove the old value
+        if !XattrIbody::new(new_inode.inode.xattr_ibody_mut()).remove(name) {
+            if let Some(xattr_block) = xattr_block.as_mut() {
+                block_dirty = xattr_block.remove(name);
+            }
+        }
+        // Insert the new value, in the inode first
+        if !XattrIbody::new(new_inode.inode.xattr_ibody_mut()).insert(name, value) {
+            let xattr_block = xattr_block.get_or_insert_with(|| {
+                let mut xattr_block = XattrBlock::new(Block::default());
+                xattr_block.init();
+                xattr_block
+            });
+            if !xattr_block.insert(name, value) {
+                return_error!(
+                    ErrCode::ENOSPC,
+                    "No space for xattr {} of inode {}",
+                    name,
+                    inode.id
+                );
+            }
+            block_dirty = true;
+        }
+        if block_dirty {
+            self.write_xattr_block(&mut new_inode, xattr_block.unwrap())?;
+        }
+        *inode = new_inode;
+        self.write_inode_with_csum(inode);
+        Ok(())
+    }
+
+    /// Remove a xattr of an inode.
+    pub(super) fn xattr_remove(&self, inode: &mut InodeRef, name: &str) -> Result<()> {
+        if XattrIbody::new(inode.inode.xattr_ibody_mut()).remove(name) {
+            self.write_inode_with_csum(inode);
+            return Ok(());
+        }
+        if let Some(mut xattr_block) = self.read_xattr_block(inode)? {
+            if xattr_block.remove(name) {
+                self.write_xattr_block(inode, xattr_block)?;
+                self.write_inode_with_csum(inode);
+                return Ok(());
+            }
+        }
+        return_error!(ErrCode::ENODATA, "Xattr {} does not exist", name);
+    }
+
+    /// Drop the reference of an inode to its xattr block, free the block
+    /// if it is no longer shared. The inode is not written.
+    pub(super) fn xattr_release_block(&self, inode: &mut InodeRef) -> Result<()> {
+        let Some(mut xattr_block) = self.read_xattr_block(inode)? else {
+            return Ok(());
+        };
+        let refcount = xattr_block.refcount();
+        if refcount > 1 {
+            xattr_block.set_refcount(refcount - 1);
+            if let Some(seed) = self.csum_seed() {
+                xattr_block.set_checksum(seed);
+            }
+            self.write_block(&xattr_block.block());
+        } else {
+            let pblock = inode.inode.xattr_block();
+            self.dealloc_block(inode, pblock)?;
+            self.write_block(&Block::new(pblock, [0; BLOCK_SIZE]));
+        }
+        inode.inode.set_xattr_block(0);
+        Ok(())
+    }
+
+    /// Read the xattr block of an inode, if any.
+    fn read_xattr_block(&self, inode: &InodeRef) -> Result<Option<XattrBlock>> {
+        let xattr_block_id = inode.inode.xattr_block();
+        if xattr_block_id == 0 {
+            return Ok(None);
+        }
+        let xattr_block = XattrBlock::new(self.read_block(xattr_block_id));
+        if !xattr_block.is_valid() {
+            return_error!(
+                ErrCode::EIO,
+                "Invalid xattr block {} of inode {}",
+                xattr_block_id,
+                inode.id
+            );
+        }
+        Ok(Some(xattr_block))
+    }
+
+    /// Write the modified xattr block of an inode. A shared block is copied
+    /// before writing, and an empty block is released. `inode.xattr_block` is
+    /// updated but the inode is not written.
+    fn write_xattr_block(&self, inode: &mut InodeRef, mut xattr_block: XattrBlock) -> Result<()> {
+        if xattr_block.is_empty() {
+            return self.xattr_release_block(inode);
+        }
+        let old_block_id = inode.inode.xattr_block();
+        if old_block_id == 0 || xattr_block.refcount() > 1 {
+            // Allocate a new block for the inode
+            let pblock = self.alloc_block(inode)?;
+            if old_block_id != 0 {
+                self.xattr_release_block(inode)?;
+            }
+            inode.inode.set_xattr_block(pblock);
+            xattr_block.set_id(pblock);
+            xattr_block.set_refcount(1);
+        }
+        if let Some(seed) = self.csum_seed() {
+            xattr_block.set_checksum(seed);
+        }
+        self.write_block(&xattr_block.block());
+        Ok(())
+    }
+}
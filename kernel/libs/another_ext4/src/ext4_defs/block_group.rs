@@ -7,9 +7,10 @@
 
 use super::crc::*;
 use super::AsBytes;
-use super::Bitmap;
+use super::SuperBlock;
 use crate::constants::*;
 use crate::prelude::*;
+use core::mem::offset_of;
 
 /// The Block Group Descriptor.
 ///
@@ -57,6 +58,8 @@ impl BlockGroupDesc {
     const MIN_BLOCK_GROUP_DESC_SIZE: usize = 32;
     #[allow(unused)]
     const MAX_BLOCK_GROUP_DESC_SIZE: usize = 64;
+    /// The block bitmap is not initialized.
+    const BLOCK_UNINIT: u16 = 0x0002;
 
     pub fn block_bitmap_block(&self) -> PBlockId {
         ((self.block_bitmap_hi as PBlockId) << 32) | self.block_bitmap_lo as PBlockId
@@ -66,6 +69,12 @@ impl BlockGroupDesc {
         ((self.inode_bitmap_hi as PBlockId) << 32) | self.inode_bitmap_lo as PBlockId
     }
 
+    /// Whether the block bitmap is not initialized. Blocks in such a group
+    /// are not allocated.
+    pub fn is_block_uninit(&self) -> bool {
+        self.flags & Self::BLOCK_UNINIT != 0
+    }
+
     pub fn itable_unused(&self) -> u32 {
         ((self.itable_unused_hi as u32) << 16) | self.itable_unused_lo as u32
     }
@@ -106,16 +115,26 @@ impl BlockGroupDesc {
         self.free_blocks_count_hi = (cnt >> 32) as u16;
     }
 
-    pub fn set_inode_bitmap_csum(&mut self, uuid: &[u8], bitmap: &Bitmap) {
-        let mut csum = crc32(CRC32_INIT, uuid);
-        csum = crc32(csum, bitmap.as_bytes());
+    /// Set the inode bitmap checksum if `metadata_csum` is enabled. `bitmap`
+    /// is the whole bitmap block.
+    pub fn set_inode_bitmap_csum(&mut self, sb: &SuperBlock, bitmap: &[u8]) {
+        if !sb.has_metadata_csum() {
+            return;
+        }
+        let size = sb.inodes_per_group() as usize / 8;
+        let csum = crc32(sb.csum_seed(), &bitmap[..size]);
         self.inode_bitmap_csum_lo = csum as u16;
-        self.block_bitmap_csum_hi = (csum >> 16) as u16;
+        self.inode_bitmap_csum_hi = (csum >> 16) as u16;
     }
 
-    pub fn set_block_bitmap_csum(&mut self, uuid: &[u8], bitmap: &Bitmap) {
-        let mut csum = crc32(CRC32_INIT, uuid);
-        csum = crc32(csum, bitmap.as_bytes());
+    /// Set the block bitmap checksum if `metadata_csum` is enabled. `bitmap`
+    /// is the whole bitmap block.
+    pub fn set_block_bitmap_csum(&mut self, sb: &SuperBlock, bitmap: &[u8]) {
+        if !sb.has_metadata_csum() {
+            return;
+        }
+        let size = sb.blocks_per_group() as usize / 8;
+        let csum = crc32(sb.csum_seed(), &bitmap[..size]);
         self.block_bitmap_csum_lo = csum as u16;
         self.block_bitmap_csum_hi = (csum >> 16) as u16;
     }
@@ -135,10 +154,38 @@ impl BlockGroupRef {
         Self { id, desc }
     }
 
-    pub fn set_checksum(&mut self, uuid: &[u8]) {
-        let mut checksum = crc32(CRC32_INIT, uuid);
-        checksum = crc32(checksum, &self.id.to_le_bytes());
-        checksum = crc32(checksum, self.desc.to_bytes());
-        self.desc.checksum = checksum as u16;
+    /// The checksum of the descriptor, with the checksum field itself
+    /// excluded. `None` if neither `metadata_csum` nor `gdt_csum` is enabled.
+    fn calc_checksum(&self, sb: &SuperBlock) -> Option<u16> {
+        let group = self.id.to_le_bytes();
+        if sb.has_metadata_csum() {
+            let mut desc = self.desc;
+            desc.checksum = 0;
+            let mut csum = crc32(sb.csum_seed(), &group);
+            csum = crc32(csum, desc.to_bytes());
+            Some(csum as u16)
+        } else if sb.has_gdt_csum() {
+            // The checksum field is skipped rather than zeroed
+            let bytes = self.desc.to_bytes();
+            let offset = offset_of!(BlockGroupDesc, checksum);
+            let mut crc = crc16(CRC16_INIT, &sb.uuid());
+            crc = crc16(crc, &group);
+            crc = crc16(crc, &bytes[..offset]);
+            crc = crc16(crc, &bytes[offset + size_of::<u16>()..]);
+            Some(crc)
+        } else {
+            None
+        }
+    }
+
+    /// Set the descriptor checksum.
+    pub fn set_checksum(&mut self, sb: &SuperBlock) {
+        self.desc.checksum = self.calc_checksum(sb).unwrap_or(0);
+    }
+
+    /// Verify the descriptor checksum. Always true if checksums are disabled.
+    pub fn verify_checksum(&self, sb: &SuperBlock) -> bool {
+        self.calc_checksum(sb)
+            .map_or(true, |csum| csum == self.desc.checksum)
     }
 }
@@ -63,3 +63,25 @@ pub fn crc32(crc_init: u32, data: &[u8]) -> u32 {
     }
     crc
 }
+
+/// Calc CRC16 checksum on a byte slice, used by the group descriptors of
+/// filesystems with `uninit_bg` but without `metadata_csum`.
+///
+/// # Params
+///
+/// * `crc_init`: initial CRC value
+/// * `data`: data to calculate CRC16 checksum
+pub fn crc16(crc_init: u16, data: &[u8]) -> u16 {
+    let mut crc = crc_init;
+    for byte in data {
+        crc ^= *byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
@@ -149,11 +149,11 @@ impl DirEntryTail {
         }
     }
 
-    pub fn set_checksum(&mut self, uuid: &[u8], ino: InodeId, ino_gen: u32, block: &Block) {
-        let mut csum = crc32(CRC32_INIT, uuid);
-        csum = crc32(csum, &ino.to_le_bytes());
-        csum = crc32(csum, &ino_gen.to_le_bytes());
-        self.checksum = crc32(csum, &block.data[..size_of::<DirEntryTail>()]);
+    /// Set the checksum over the entries before the tail in `block`,
+    /// `inode_seed` is the checksum seed of the directory inode.
+    pub fn set_checksum(&mut self, inode_seed: u32, block: &Block) {
+        let tail_offset = BLOCK_SIZE - size_of::<DirEntryTail>();
+        self.checksum = crc32(inode_seed, &block.data[..tail_offset]);
     }
 }
 
@@ -257,10 +257,10 @@ impl DirBlock {
     }
 
     /// Calc and set block checksum
-    pub fn set_checksum(&mut self, uuid: &[u8], ino: InodeId, ino_gen: u32) {
+    pub fn set_checksum(&mut self, inode_seed: u32) {
         let tail_offset = BLOCK_SIZE - size_of::<DirEntryTail>();
         let mut tail: DirEntryTail = self.0.read_offset_as(tail_offset);
-        tail.set_checksum(uuid, ino, ino_gen, &self.0);
+        tail.set_checksum(inode_seed, &self.0);
         self.0.write_offset_as(tail_offset, &tail);
     }
 }
@@ -13,6 +13,8 @@
 //! inode.i_block, which allows for the first four extents to be recorded without
 //! the use of extra metadata blocks.
 
+use super::crc::*;
+use crate::constants::*;
 use crate::prelude::*;
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -52,6 +54,11 @@ impl ExtentHeader {
         }
     }
 
+    /// Whether the magic number is correct
+    pub fn check_magic(&self) -> bool {
+        self.magic == Self::EXTENT_MAGIC
+    }
+
     /// 获取extent header的条目数
     pub fn entries_count(&self) -> u16 {
         self.entries_count
@@ -153,6 +160,9 @@ impl Extent {
     /// Extent with `block_count` greater than 32768 is considered unwritten.
     const INIT_MAX_LEN: u16 = 32768;
 
+    /// The maximum number of blocks an unwritten extent can cover.
+    pub const UNWRITTEN_MAX_LEN: u16 = Self::INIT_MAX_LEN - 1;
+
     /// Create a new extent with start logic block number, start physical block number, and block count
     pub fn new(start_lblock: LBlockId, start_pblock: PBlockId, block_count: u16) -> Self {
         Self {
@@ -193,9 +203,13 @@ impl Extent {
         }) as LBlockId
     }
 
-    /// Set the number of blocks covered by this extent
+    /// Set the number of blocks covered by this extent, keep the unwritten flag
     pub fn set_block_count(&mut self, block_count: LBlockId) {
+        let unwritten = self.is_unwritten();
         self.block_count = block_count as u16;
+        if unwritten {
+            self.mark_unwritten();
+        }
     }
 
     /// Check if the extent is unwritten
@@ -208,15 +222,50 @@ impl Extent {
         self.block_count |= Self::INIT_MAX_LEN;
     }
 
+    /// Mark the extent as written (initialized)
+    pub fn mark_written(&mut self) {
+        self.block_count = self.block_count() as u16;
+    }
+
+    /// The maximum number of blocks this extent can cover
+    pub fn max_block_count(&self) -> LBlockId {
+        if self.is_unwritten() {
+            Self::UNWRITTEN_MAX_LEN as LBlockId
+        } else {
+            Self::INIT_MAX_LEN as LBlockId
+        }
+    }
+
+    /// The end logic block number (exclusive) that this extent covers
+    pub fn end_lblock(&self) -> LBlockId {
+        self.first_block + self.block_count()
+    }
+
+    /// The physical block number that the given logic block is mapped to.
+    /// Require `start_lblock <= lblock < end_lblock`.
+    pub fn pblock_of(&self, lblock: LBlockId) -> PBlockId {
+        self.start_pblock() + (lblock - self.first_block) as PBlockId
+    }
+
+    /// The part of this extent that covers logic blocks `[start, end)`.
+    /// Require `start_lblock <= start < end <= end_lblock`.
+    pub fn sub_extent(&self, start: LBlockId, end: LBlockId) -> Extent {
+        let mut ex = *self;
+        ex.set_start_lblock(start);
+        ex.set_start_pblock(self.pblock_of(start));
+        ex.set_block_count(end - start);
+        ex
+    }
+
     /// Check whether the `ex2` extent can be appended to the `ex1` extent
     pub fn can_append(ex1: &Extent, ex2: &Extent) -> bool {
-        if ex1.start_pblock() + ex1.block_count() as u64 != ex2.start_pblock() {
+        if ex1.is_unwritten() != ex2.is_unwritten() {
             return false;
         }
-        if ex1.is_unwritten() && ex1.block_count() + ex2.block_count() > 65535 as LBlockId {
+        if ex1.start_pblock() + ex1.block_count() as u64 != ex2.start_pblock() {
             return false;
         }
-        if ex1.block_count() + ex2.block_count() > Self::INIT_MAX_LEN as LBlockId {
+        if ex1.block_count() + ex2.block_count() > ex1.max_block_count() {
             return false;
         }
         if ex1.first_block + ex1.block_count() != ex2.first_block {
@@ -260,11 +309,37 @@ pub struct ExtentNode<'a> {
 }
 
 impl<'a> ExtentNode<'a> {
+    /// The maximum number of entries in an extent node stored in a block
+    pub const BLOCK_MAX_ENTRIES: usize =
+        (BLOCK_SIZE - size_of::<ExtentHeader>()) / size_of::<Extent>();
+
     /// Interpret a byte slice as an extent node
     pub fn from_bytes(raw_data: &'a [u8]) -> Self {
         Self { raw_data }
     }
 
+    /// Whether this node, stored in a block, has a sane header
+    pub fn is_valid_block(&self) -> bool {
+        let header = self.header();
+        header.check_magic()
+            && header.max_entries_count() as usize <= Self::BLOCK_MAX_ENTRIES
+            && header.entries_count() <= header.max_entries_count()
+    }
+
+    /// The offset of the checksum tail of a node stored in a block, following
+    /// the space of `max_entries_count` entries.
+    fn tail_offset(&self) -> usize {
+        size_of::<ExtentHeader>() + self.header().max_entries_count() as usize * size_of::<Extent>()
+    }
+
+    /// Verify the checksum of a node stored in a block. `inode_seed` is the
+    /// checksum seed of the inode owning the extent tree.
+    pub fn verify_checksum(&self, inode_seed: u32) -> bool {
+        let offset = self.tail_offset();
+        let stored = u32::from_le_bytes(self.raw_data[offset..offset + 4].try_into().unwrap());
+        stored == crc32(inode_seed, &self.raw_data[..offset])
+    }
+
     /// Get a immutable reference to the extent header
     pub fn header(&self) -> &ExtentHeader {
         unsafe { &*(self.raw_data.as_ptr() as *const ExtentHeader) }
@@ -280,6 +355,12 @@ impl<'a> ExtentNode<'a> {
         unsafe { &*((self.header() as *const ExtentHeader).add(1) as *const ExtentIndex).add(pos) }
     }
 
+    /// Get an immutable reference to the extent or extent index at a given position,
+    /// ignore the detailed type information
+    pub fn fake_extent_at(&self, pos: usize) -> &FakeExtent {
+        unsafe { &*((self.header() as *const ExtentHeader).add(1) as *const FakeExtent).add(pos) }
+    }
+
     /// Find the extent that covers the given logical block number.
     ///
     /// Return `Ok(index)` if found, and `eh.extent_at(index)` is the extent that covers
@@ -292,9 +373,8 @@ impl<'a> ExtentNode<'a> {
             let extent = self.extent_at(i);
             if extent.start_lblock() <= lblock {
                 if extent.start_lblock() + (extent.block_count() as LBlockId) > lblock {
-                    let res = if extent.is_unwritten() { Err(i) } else { Ok(i) };
                     // debug!("Search res: {:?}", res);
-                    return res;
+                    return Ok(i);
                 }
                 i += 1;
             } else {
@@ -324,7 +404,8 @@ impl<'a> ExtentNode<'a> {
         }
         
         // debug!("Search res: {:?}", res);
-        Ok(i - 1)
+        // Blocks before the first index are covered by the first index
+        Ok(i.saturating_sub(1))
     }
 
     pub fn print(&self) {
@@ -422,6 +503,24 @@ impl<'a> ExtentNodeMut<'a> {
         }
     }
 
+    /// Set the checksum of a node stored in a block. `inode_seed` is the
+    /// checksum seed of the inode owning the extent tree.
+    pub fn set_checksum(&mut self, inode_seed: u32) {
+        let offset = self.as_immut().tail_offset();
+        let csum = crc32(inode_seed, &self.raw_data[..offset]);
+        self.raw_data[offset..offset + 4].copy_from_slice(&csum.to_le_bytes());
+    }
+
+    /// Remove the extent or extent index at a given position, and move the
+    /// following ones forward
+    pub fn remove_entry(&mut self, pos: usize) {
+        let count = self.header().entries_count() as usize;
+        for i in pos..count - 1 {
+            *self.fake_extent_mut_at(i) = *self.fake_extent_at(i + 1);
+        }
+        self.header_mut().entries_count -= 1;
+    }
+
     /// Initialize the extent node
     pub fn init(&mut self, depth: u16, generation: u32) {
         let max_entries_count =
@@ -441,57 +540,19 @@ impl<'a> ExtentNodeMut<'a> {
         extent: &Extent,
         pos: usize,
     ) -> core::result::Result<(), Vec<FakeExtent>> {
-        if self.extent_at(pos).is_unwritten() {
-            // The position has an uninitialized extent
-            *self.extent_mut_at(pos) = *extent;
-            self.header_mut().entries_count += 1;
-            return Ok(());
-        }
-        // The position has a valid extent
         if self.header().entries_count() < self.header().max_entries_count() {
             // The extent node is not full
             // Insert the extent and move the following extents
-            let mut i = pos;
-            while i < self.header().entries_count() as usize {
-                *self.extent_mut_at(i + 1) = *self.extent_at(i);
-                i += 1;
+            let mut i = self.header().entries_count() as usize;
+            while i > pos {
+                *self.extent_mut_at(i) = *self.extent_at(i - 1);
+                i -= 1;
             }
             *self.extent_mut_at(pos) = *extent;
             self.header_mut().entries_count += 1;
             return Ok(());
         }
         // The extent node is full
-        // There may be some unwritten extents, we could find the first
-        // unwritten extent and adjust the extents.
-        let mut unwritten = None;
-        for i in 0..self.header().entries_count() as usize {
-            if self.extent_at(i).is_unwritten() {
-                unwritten = Some(i);
-                break;
-            }
-        }
-        if let Some(unwritten) = unwritten {
-            // There is an uninitialized extent, we could adjust the extents.
-            if unwritten < pos {
-                // Move the extents from `unwritten` to `pos`
-                let mut i = unwritten;
-                while i < pos {
-                    *self.extent_mut_at(i) = *self.extent_at(i + 1);
-                    i += 1;
-                }
-            } else {
-                // Move the extents from `pos` to `unwritten`
-                let mut i = pos;
-                while i < unwritten {
-                    *self.extent_mut_at(i + 1) = *self.extent_at(i);
-                    i += 1;
-                }
-            }
-            *self.extent_mut_at(pos) = *extent;
-            self.header_mut().entries_count += 1;
-            return Ok(());
-        }
-        // The extent node is full and all extents are valid
         // Split the node, return the extents in the right half
         let mut split = Vec::new();
         let mid = self.header().entries_count() as usize * 2 / 3;
@@ -530,10 +591,10 @@ impl<'a> ExtentNodeMut<'a> {
         if self.header().entries_count() < self.header().max_entries_count() {
             // The extent node is not full
             // Insert the extent index and move the following extent indexs
-            let mut i = pos;
-            while i < self.header().entries_count() as usize {
-                *self.extent_index_mut_at(i + 1) = *self.extent_index_at(i);
-                i += 1;
+            let mut i = self.header().entries_count() as usize;
+            while i > pos {
+                *self.extent_index_mut_at(i) = *self.extent_index_at(i - 1);
+                i -= 1;
             }
             *self.extent_index_mut_at(pos) = *extent_index;
             self.header_mut().entries_count += 1;
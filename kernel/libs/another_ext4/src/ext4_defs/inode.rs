@@ -11,6 +11,7 @@ use super::{ExtentNode, ExtentNodeMut};
 use crate::constants::*;
 use crate::prelude::*;
 use crate::FileType;
+use core::mem::offset_of;
 
 bitflags! {
     #[derive(PartialEq, Eq, Debug, Clone, Copy)]
@@ -102,7 +103,8 @@ pub struct Linux2 {
 ///
 /// We only implement the larger version for simplicity. Guarantee that
 /// `sb.inode_size` equals to 256. This value will be checked when
-/// loading the filesystem.
+/// loading the filesystem. The space after the inode fields holds the
+/// in-inode extended attributes.
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct Inode {
@@ -161,21 +163,27 @@ pub struct Inode {
     version_hi: u32,
     /// Project id
     projid: u32,
+    /// The rest of the on-disk inode, used for in-inode extended attributes.
+    ibody: [u8; SB_GOOD_INODE_SIZE - Inode::FIELDS_SIZE],
 }
 
 /// Because `[u8; 60]` cannot derive `Default`, we implement it manually.
 impl Default for Inode {
     fn default() -> Self {
         let mut inode: Self = unsafe { mem::zeroed() };
-        inode.extra_isize = (size_of::<Inode>() - 128) as u16;
+        inode.extra_isize = (Self::FIELDS_SIZE - EXT4_GOOD_OLD_INODE_SIZE) as u16;
         inode
     }
 }
 
 unsafe impl AsBytes for Inode {}
 
+const _: () = assert!(size_of::<Inode>() == SB_GOOD_INODE_SIZE);
+
 impl Inode {
     const FLAG_EXTENTS: u32 = 0x00080000;
+    /// The size of the inode fields, excluding the in-inode xattr area.
+    const FIELDS_SIZE: usize = 160;
 
     pub fn mode(&self) -> InodeMode {
         InodeMode::from_bits_truncate(self.mode)
@@ -321,6 +329,11 @@ impl Inode {
         self.flags |= f;
     }
 
+    /// Replace all the inode flags with `f`.
+    pub fn replace_flags(&mut self, f: u32) {
+        self.flags = f;
+    }
+
     pub fn xattr_block(&self) -> PBlockId {
         ((self.osd2.l_file_acl_hi as u64) << 32) | self.file_acl as u64
     }
@@ -330,6 +343,23 @@ impl Inode {
         self.osd2.l_file_acl_hi = (block >> 32) as u16;
     }
 
+    /// Get the mutable in-inode extended attribute area
+    pub fn xattr_ibody_mut(&mut self) -> &mut [u8] {
+        let start = (EXT4_GOOD_OLD_INODE_SIZE + self.extra_isize as usize).min(SB_GOOD_INODE_SIZE);
+        unsafe {
+            let bytes = core::slice::from_raw_parts_mut(
+                self as *mut Self as *mut u8,
+                SB_GOOD_INODE_SIZE,
+            );
+            &mut bytes[start..]
+        }
+    }
+
+    /// Whether the inode uses extents for block mapping.
+    pub fn has_extents(&self) -> bool {
+        self.flags & Self::FLAG_EXTENTS != 0
+    }
+
     /* Extent methods */
 
     /// Get the immutable extent root node
@@ -367,13 +397,50 @@ impl InodeRef {
         Self { id, inode }
     }
 
-    pub fn set_checksum(&mut self, uuid: &[u8]) {
-        let mut checksum = crc32(CRC32_INIT, uuid);
-        checksum = crc32(checksum, &self.id.to_le_bytes());
-        checksum = crc32(checksum, &self.inode.generation.to_le_bytes());
-        checksum = crc32(checksum, self.inode.to_bytes());
-        self.inode.osd2.l_checksum_lo = checksum as u16;
-        self.inode.checksum_hi = (checksum >> 16) as u16;
+    /// The checksum seed of the inode's metadata (inode, extent blocks and
+    /// directory blocks), derived from the filesystem seed `fs_seed`.
+    pub fn csum_seed(&self, fs_seed: u32) -> u32 {
+        let csum = crc32(fs_seed, &self.id.to_le_bytes());
+        crc32(csum, &self.inode.generation.to_le_bytes())
+    }
+
+    /// Whether the inode is large enough to hold `checksum_hi`.
+    fn has_checksum_hi(&self) -> bool {
+        self.inode.extra_isize as usize + EXT4_GOOD_OLD_INODE_SIZE
+            >= offset_of!(Inode, checksum_hi) + size_of::<u16>()
+    }
+
+    /// The checksum of the inode, with the checksum fields themselves zeroed.
+    fn calc_checksum(&self, fs_seed: u32) -> u32 {
+        let mut inode = self.inode.clone();
+        inode.osd2.l_checksum_lo = 0;
+        if self.has_checksum_hi() {
+            inode.checksum_hi = 0;
+        }
+        let csum = crc32(self.csum_seed(fs_seed), inode.to_bytes());
+        if self.has_checksum_hi() {
+            csum
+        } else {
+            csum & 0xFFFF
+        }
+    }
+
+    /// Set the inode checksum.
+    pub fn set_checksum(&mut self, fs_seed: u32) {
+        let csum = self.calc_checksum(fs_seed);
+        self.inode.osd2.l_checksum_lo = csum as u16;
+        if self.has_checksum_hi() {
+            self.inode.checksum_hi = (csum >> 16) as u16;
+        }
+    }
+
+    /// Verify the inode checksum.
+    pub fn verify_checksum(&self, fs_seed: u32) -> bool {
+        let mut stored = self.inode.osd2.l_checksum_lo as u32;
+        if self.has_checksum_hi() {
+            stored |= (self.inode.checksum_hi as u32) << 16;
+        }
+        stored == self.calc_checksum(fs_seed)
     }
 }
 
@@ -392,3 +459,74 @@ pub struct FileAttr {
     pub uid: u32,
     pub gid: u32,
 }
+
+bitflags! {
+    /// The inode flags, matches the Linux `EXT4_*_FL` and `FS_*_FL` flags.
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    pub struct InodeFlags: u32 {
+        /// Secure deletion.
+        const SECRM = 0x1;
+        /// Undelete.
+        const UNRM = 0x2;
+        /// Compress file.
+        const COMPR = 0x4;
+        /// Synchronous updates.
+        const SYNC = 0x8;
+        /// The file cannot be modified.
+        const IMMUTABLE = 0x10;
+        /// Writes to the file may only append.
+        const APPEND = 0x20;
+        /// Do not dump the file.
+        const NODUMP = 0x40;
+        /// Do not update access time.
+        const NOATIME = 0x80;
+        /// Hash-indexed directory.
+        const INDEX = 0x1000;
+        /// File data should be journaled.
+        const JOURNAL_DATA = 0x4000;
+        /// File tail should not be merged.
+        const NOTAIL = 0x8000;
+        /// Directory updates are synchronous.
+        const DIRSYNC = 0x10000;
+        /// Top of directory hierarchies.
+        const TOPDIR = 0x20000;
+        /// Huge file.
+        const HUGE_FILE = 0x40000;
+        /// The inode uses extents.
+        const EXTENTS = 0x80000;
+        /// The inode stores a large extended attribute value.
+        const EA_INODE = 0x200000;
+        /// The inode has inline data.
+        const INLINE_DATA = 0x10000000;
+    }
+}
+
+impl InodeFlags {
+    /// The flags that can be changed by users.
+    pub const USER_MODIFIABLE: InodeFlags = InodeFlags::SECRM
+        .union(InodeFlags::UNRM)
+        .union(InodeFlags::COMPR)
+        .union(InodeFlags::SYNC)
+        .union(InodeFlags::IMMUTABLE)
+        .union(InodeFlags::APPEND)
+        .union(InodeFlags::NODUMP)
+        .union(InodeFlags::NOATIME)
+        .union(InodeFlags::NOTAIL)
+        .union(InodeFlags::DIRSYNC)
+        .union(InodeFlags::TOPDIR);
+}
+
+bitflags! {
+    /// The mode of `fallocate`, matches the Linux `FALLOC_FL_*` flags.
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    pub struct FallocFlags: u32 {
+        /// Do not change the file size.
+        const KEEP_SIZE = 0x01;
+        /// Deallocate the range, must be used with `KEEP_SIZE`.
+        const PUNCH_HOLE = 0x02;
+        /// Remove the range without leaving a hole.
+        const COLLAPSE_RANGE = 0x08;
+        /// Convert the range to zeros.
+        const ZERO_RANGE = 0x10;
+    }
+}
@@ -31,6 +31,7 @@ mod cache;
 pub use bitmap::*;
 pub use block::*;
 pub use block_group::*;
+pub use crc::*;
 pub use dir::*;
 pub use extent::*;
 pub use inode::*;
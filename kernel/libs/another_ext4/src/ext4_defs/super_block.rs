@@ -2,7 +2,9 @@
 //!
 //! See [`super::block_group`] for details.
 
+use super::crc::*;
 use super::AsBytes;
+use crate::constants::*;
 use crate::prelude::*;
 
 // 结构体表示超级块
@@ -104,14 +106,68 @@ pub struct SuperBlock {
     encrypt_algos: [u8; 4],    // 使用的加密算法
     encrypt_pw_salt: [u8; 16], // 用于string2key算法的盐
     lpf_ino: u32,              // lost+found节点的位置
-    padding: [u32; 100],       // 块的末尾的填充
+    prj_quota_inum: u32,       // 用于跟踪项目配额的节点
+    checksum_seed: u32,        // crc32c(uuid)，如果设置了csum_seed
+    padding: [u32; 98],        // 块的末尾的填充
     checksum: u32,             // crc32c(superblock)
 }
 
 unsafe impl AsBytes for SuperBlock {}
 
+const _: () = assert!(size_of::<SuperBlock>() == 1024);
+
 impl SuperBlock {
     const SB_MAGIC: u16 = 0xEF53;
+    /// Compatible feature: has a journal.
+    const FEATURE_COMPAT_HAS_JOURNAL: u32 = 0x4;
+
+    /// Incompatible feature: directory entries record the file type.
+    const FEATURE_INCOMPAT_FILETYPE: u32 = 0x2;
+    /// Incompatible feature: filesystem needs journal recovery.
+    const FEATURE_INCOMPAT_RECOVER: u32 = 0x4;
+    /// Incompatible feature: files use extents.
+    const FEATURE_INCOMPAT_EXTENTS: u32 = 0x40;
+    /// Incompatible feature: block numbers are 64-bit.
+    const FEATURE_INCOMPAT_64BIT: u32 = 0x80;
+    /// Incompatible feature: flexible block groups.
+    const FEATURE_INCOMPAT_FLEX_BG: u32 = 0x200;
+    /// Incompatible feature: the metadata checksum seed is stored in the superblock.
+    const FEATURE_INCOMPAT_CSUM_SEED: u32 = 0x2000;
+    /// The incompatible features this implementation understands. A filesystem
+    /// with any other incompatible feature cannot be mounted.
+    const FEATURE_INCOMPAT_SUPPORTED: u32 = Self::FEATURE_INCOMPAT_FILETYPE
+        | Self::FEATURE_INCOMPAT_RECOVER
+        | Self::FEATURE_INCOMPAT_EXTENTS
+        | Self::FEATURE_INCOMPAT_64BIT
+        | Self::FEATURE_INCOMPAT_FLEX_BG
+        | Self::FEATURE_INCOMPAT_CSUM_SEED;
+
+    /// Read-only compatible feature: sparse superblocks.
+    const FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x1;
+    /// Read-only compatible feature: files larger than 2GiB.
+    const FEATURE_RO_COMPAT_LARGE_FILE: u32 = 0x2;
+    /// Read-only compatible feature: files larger than 2TiB.
+    const FEATURE_RO_COMPAT_HUGE_FILE: u32 = 0x8;
+    /// Read-only compatible feature: group descriptors have crc16 checksums.
+    const FEATURE_RO_COMPAT_GDT_CSUM: u32 = 0x10;
+    /// Read-only compatible feature: directories may have more than 65000 links.
+    const FEATURE_RO_COMPAT_DIR_NLINK: u32 = 0x20;
+    /// Read-only compatible feature: inodes have extra fields.
+    const FEATURE_RO_COMPAT_EXTRA_ISIZE: u32 = 0x40;
+    /// Read-only compatible feature: metadata have crc32c checksums.
+    const FEATURE_RO_COMPAT_METADATA_CSUM: u32 = 0x400;
+    /// The read-only compatible features this implementation understands. A
+    /// filesystem with any other such feature can only be mounted read-only.
+    const FEATURE_RO_COMPAT_SUPPORTED: u32 = Self::FEATURE_RO_COMPAT_SPARSE_SUPER
+        | Self::FEATURE_RO_COMPAT_LARGE_FILE
+        | Self::FEATURE_RO_COMPAT_HUGE_FILE
+        | Self::FEATURE_RO_COMPAT_GDT_CSUM
+        | Self::FEATURE_RO_COMPAT_DIR_NLINK
+        | Self::FEATURE_RO_COMPAT_EXTRA_ISIZE
+        | Self::FEATURE_RO_COMPAT_METADATA_CSUM;
+
+    /// The checksum type of `metadata_csum`: crc32c.
+    const CHECKSUM_TYPE_CRC32C: u8 = 1;
 
     pub fn check_magic(&self) -> bool {
         self.magic == Self::SB_MAGIC
@@ -141,7 +197,6 @@ impl SuperBlock {
     }
 
     /// The number of blocks in each block group.
-    #[allow(unused)]
     pub fn blocks_per_group(&self) -> u32 {
         self.blocks_per_group
     }
@@ -172,6 +227,12 @@ impl SuperBlock {
         self.want_extra_isize
     }
 
+    /// The number of blocks in a block group, the last group may be smaller.
+    pub fn block_count_in_group(&self, bgid: u32) -> u32 {
+        let first_block = self.first_data_block as u64 + bgid as u64 * self.blocks_per_group as u64;
+        core::cmp::min(self.block_count() - first_block, self.blocks_per_group as u64) as u32
+    }
+
     pub fn inode_count_in_group(&self, bgid: u32) -> u32 {
         let bg_count = self.block_group_count();
         if bgid < bg_count {
@@ -182,10 +243,103 @@ impl SuperBlock {
         }
     }
 
+    /// Whether the filesystem has a journal.
+    pub fn has_journal(&self) -> bool {
+        self.features_compatible & Self::FEATURE_COMPAT_HAS_JOURNAL != 0
+    }
+
+    /// The inode number of the journal file.
+    pub fn journal_inode_number(&self) -> u32 {
+        self.journal_inode_number
+    }
+
+    /// Whether the journal needs to be replayed before use.
+    pub fn needs_recovery(&self) -> bool {
+        self.features_incompatible & Self::FEATURE_INCOMPAT_RECOVER != 0
+    }
+
+    /// Set or clear the `needs_recovery` flag.
+    pub fn set_needs_recovery(&mut self, needs_recovery: bool) {
+        if needs_recovery {
+            self.features_incompatible |= Self::FEATURE_INCOMPAT_RECOVER;
+        } else {
+            self.features_incompatible &= !Self::FEATURE_INCOMPAT_RECOVER;
+        }
+    }
+
+    /// The incompatible features that are not supported.
+    pub fn unsupported_incompat_features(&self) -> u32 {
+        self.features_incompatible & !Self::FEATURE_INCOMPAT_SUPPORTED
+    }
+
+    /// The read-only compatible features that are not supported.
+    pub fn unsupported_ro_compat_features(&self) -> u32 {
+        self.features_read_only & !Self::FEATURE_RO_COMPAT_SUPPORTED
+    }
+
+    /// Whether metadata are protected by crc32c checksums.
+    pub fn has_metadata_csum(&self) -> bool {
+        self.features_read_only & Self::FEATURE_RO_COMPAT_METADATA_CSUM != 0
+    }
+
+    /// Whether group descriptors are protected by crc16 checksums. Superseded
+    /// by `metadata_csum` if both are set.
+    pub fn has_gdt_csum(&self) -> bool {
+        self.features_read_only & Self::FEATURE_RO_COMPAT_GDT_CSUM != 0
+    }
+
+    /// Whether the checksum type is crc32c, the only type defined.
+    pub fn has_valid_checksum_type(&self) -> bool {
+        self.checksum_type == Self::CHECKSUM_TYPE_CRC32C
+    }
+
+    /// The seed of the metadata checksums, either stored in the superblock
+    /// or computed from the uuid.
+    pub fn csum_seed(&self) -> u32 {
+        if self.features_incompatible & Self::FEATURE_INCOMPAT_CSUM_SEED != 0 {
+            self.checksum_seed
+        } else {
+            crc32(CRC32_INIT, &self.uuid)
+        }
+    }
+
+    /// The checksum of the superblock, over all the fields before it.
+    fn calc_checksum(&self) -> u32 {
+        let bytes = self.to_bytes();
+        crc32(CRC32_INIT, &bytes[..bytes.len() - size_of::<u32>()])
+    }
+
+    /// Set the superblock checksum if `metadata_csum` is enabled.
+    pub fn set_checksum(&mut self) {
+        if self.has_metadata_csum() {
+            self.checksum = self.calc_checksum();
+        }
+    }
+
+    /// Verify the superblock checksum. Always true without `metadata_csum`.
+    pub fn verify_checksum(&self) -> bool {
+        !self.has_metadata_csum() || self.checksum == self.calc_checksum()
+    }
+
     pub fn set_free_inodes_count(&mut self, count: u32) {
         self.free_inode_count = count;
     }
 
+    /// The number of blocks reserved for the privileged users.
+    pub fn reserved_blocks_count(&self) -> u64 {
+        self.reserved_block_count_lo as u64 | ((self.reserved_blocks_count_hi as u64) << 32)
+    }
+
+    /// The default mount options, a comma-separated string such as `"ro,noatime"`.
+    pub fn mount_opts(&self) -> &str {
+        let len = self
+            .mount_opts
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.mount_opts.len());
+        core::str::from_utf8(&self.mount_opts[..len]).unwrap_or("")
+    }
+
     pub fn free_blocks_count(&self) -> u64 {
         self.free_block_count_lo as u64 | ((self.free_blocks_count_hi as u64) << 32).to_le()
     }
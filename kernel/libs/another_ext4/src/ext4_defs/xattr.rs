@@ -6,13 +6,21 @@
 //! entry. The second place where extended attributes can be found is in the block
 //! pointed to by `inode.file_acl`.
 //!
-//! We only implement the seperate data block storage of extended attributes.
+//! Both places share the same layout: an entry table growing downwards and
+//! the values growing upwards from the end of the area. Values stored in
+//! other inodes (`value_inum != 0`) are not supported.
 
+use super::crc::*;
 use super::{AsBytes, Block};
-use crate::constants::*;
 use crate::prelude::*;
 use core::cmp::Ordering;
 
+/// Magic number of the xattr block and the in-inode xattr area.
+const XATTR_MAGIC: u32 = 0xEA020000;
+
+/// Xattr entries and values are aligned to 4 bytes.
+const XATTR_PAD: usize = 4;
+
 /// The beginning of an extended attribute block.
 #[repr(C)]
 #[derive(Debug)]
@@ -23,7 +31,7 @@ pub struct XattrHeader {
     refcount: u32,
     /// Number of disk blocks used.
     blocks: u32,
-    /// Hash value of all attributes. (UNUSED by now)
+    /// Hash value of all attributes.
     hash: u32,
     /// Checksum of the extended attribute block.
     checksum: u32,
@@ -34,11 +42,9 @@ pub struct XattrHeader {
 unsafe impl AsBytes for XattrHeader {}
 
 impl XattrHeader {
-    const XATTR_MAGIC: u32 = 0xEA020000;
-
     pub fn new() -> Self {
         XattrHeader {
-            magic: Self::XATTR_MAGIC,
+            magic: XATTR_MAGIC,
             refcount: 1,
             blocks: 1,
             hash: 0,
@@ -63,7 +69,8 @@ pub struct XattrEntry {
     name_index: u8,
     /// Location of this attribute's value on the disk block where
     /// it is stored. For a block this value is relative to the start
-    /// of the block (i.e. the header).
+    /// of the block (i.e. the header). For an inode this value is
+    /// relative to the first entry.
     /// value = `block[value_offset..value_offset + value_size]`
     value_offset: u16,
     /// The inode where the value is stored. Zero indicates the value
@@ -71,7 +78,7 @@ pub struct XattrEntry {
     value_inum: u32,
     /// Length of attribute value.
     value_size: u32,
-    /// Hash value of attribute name and attribute value (UNUSED by now)
+    /// Hash value of attribute name and attribute value
     hash: u32,
     /// Attribute name, max 255 bytes.
     name: [u8; 255],
@@ -121,28 +128,30 @@ unsafe impl AsBytes for XattrEntry {
 
 impl XattrEntry {
     /// Create a new xattr entry.
-    pub fn new(name: &str, value_size: usize, value_offset: usize) -> Self {
+    pub fn new(name: &str, value: &[u8], value_offset: usize) -> Self {
         let mut name_bytes = [0u8; 255];
         let (name_index, name) = Self::match_name(name);
         let name_len = name.len();
         name_bytes[..name_len].copy_from_slice(name.as_bytes());
-        Self {
+        let mut entry = Self {
             name_len: name.len() as u8,
             name_index,
             value_offset: value_offset as u16,
             value_inum: 0,
-            value_size: value_size as u32,
+            value_size: value.len() as u32,
             hash: 0,
             name: name_bytes,
-        }
+        };
+        entry.hash = entry.compute_hash(value);
+        entry
     }
 
     /// Get the name of the xattr entry
     pub fn name(&self) -> String {
         let prefix = match self.name_index {
             1 => "user.",
-            2 => "system.posix_acl_access.",
-            3 => "system.posix_acl_default.",
+            2 => "system.posix_acl_access",
+            3 => "system.posix_acl_default",
             4 => "trusted.",
             6 => "security.",
             7 => "system.",
@@ -158,12 +167,17 @@ impl XattrEntry {
         let (_, name) = Self::match_name(name);
         let name_len = name.len();
         // FakeXattrEntry + name -> align to 4
-        (core::mem::size_of::<FakeXattrEntry>() + name_len + 3) / 4 * 4
+        (size_of::<FakeXattrEntry>() + name_len).next_multiple_of(XATTR_PAD)
     }
 
     /// Get the used size of this xattr entry, 4-bytes alighed
     pub fn used_size(&self) -> usize {
-        (core::mem::size_of::<FakeXattrEntry>() + self.name_len as usize + 3) / 4 * 4
+        (size_of::<FakeXattrEntry>() + self.name_len as usize).next_multiple_of(XATTR_PAD)
+    }
+
+    /// Get the used size of the value of this xattr entry, 4-bytes aligned
+    pub fn value_used_size(&self) -> usize {
+        (self.value_size as usize).next_multiple_of(XATTR_PAD)
     }
 
     /// Compare the name of the xattr entry with a given name
@@ -180,13 +194,28 @@ impl XattrEntry {
         self.name[..self.name_len as usize].cmp(name.as_bytes())
     }
 
+    /// Compute the hash of the attribute name and value, which is checked
+    /// by e2fsck.
+    fn compute_hash(&self, value: &[u8]) -> u32 {
+        let mut hash: u32 = 0;
+        for &c in &self.name[..self.name_len as usize] {
+            hash = (hash << 5) ^ (hash >> 27) ^ c as u32;
+        }
+        for word in value.chunks(4) {
+            let mut bytes = [0u8; 4];
+            bytes[..word.len()].copy_from_slice(word);
+            hash = (hash << 16) ^ (hash >> 16) ^ u32::from_le_bytes(bytes);
+        }
+        hash
+    }
+
     /// Match the attribute name prefix to get name index. If one is found,
     /// return the name index and the string with the prefix removed.
     fn match_name(name: &str) -> (u8, &str) {
         let prefixes = [
             ("user.", 1),
-            ("system.posix_acl_access.", 2),
-            ("system.posix_acl_default.", 3),
+            ("system.posix_acl_access", 2),
+            ("system.posix_acl_default", 3),
             ("trusted.", 4),
             ("security.", 6),
             ("system.", 7),
@@ -200,6 +229,126 @@ impl XattrEntry {
     }
 }
 
+/// Collect the entries of the entry table starting at `data[first]`,
+/// together with their offsets.
+fn xattr_entries(data: &[u8], first: usize) -> Vec<(usize, XattrEntry)> {
+    let mut entries = Vec::new();
+    let mut p_entry = first;
+    while p_entry + size_of::<FakeXattrEntry>() <= data.len() {
+        // Check `name_len`, 0 indicates the end of the entry table.
+        if data[p_entry] == 0 {
+            break;
+        }
+        let entry = XattrEntry::from_bytes(&data[p_entry..]);
+        let used_size = entry.used_size();
+        entries.push((p_entry, entry));
+        p_entry += used_size;
+    }
+    entries
+}
+
+/// Get the end of the entry table and the lowest value offset.
+/// `[end, lowest)` is the blank area.
+fn xattr_blank_area(data: &[u8], first: usize, entries: &[(usize, XattrEntry)]) -> (usize, usize) {
+    let end = entries
+        .last()
+        .map_or(first, |(offset, entry)| offset + entry.used_size());
+    let lowest = entries
+        .iter()
+        .filter(|(_, entry)| entry.value_size != 0)
+        .map(|(_, entry)| entry.value_offset as usize)
+        .min()
+        .unwrap_or(data.len());
+    (end, lowest)
+}
+
+/// Get a xattr by name from the entry table, return the value.
+fn xattr_get<'a>(data: &'a [u8], first: usize, name: &str) -> Option<&'a [u8]> {
+    let (_, entry) = xattr_entries(data, first)
+        .into_iter()
+        .find(|(_, entry)| entry.compare_name(name).is_eq())?;
+    let offset = entry.value_offset as usize;
+    let size = entry.value_size as usize;
+    data.get(offset..offset + size)
+}
+
+/// List all xattr names in the entry table.
+fn xattr_list(data: &[u8], first: usize) -> Vec<String> {
+    xattr_entries(data, first)
+        .into_iter()
+        .map(|(_, entry)| entry.name())
+        .collect()
+}
+
+/// Insert a xattr into the entry table, keeping the entries sorted by name.
+/// The value is placed right below the lowest value. Return true if success.
+fn xattr_insert(data: &mut [u8], first: usize, name: &str, value: &[u8]) -> bool {
+    let entries = xattr_entries(data, first);
+    let (p_entry, p_value) = xattr_blank_area(data, first, &entries);
+    // Insert before the first entry with a greater name
+    let ins_entry_pos = entries
+        .iter()
+        .find(|(_, entry)| entry.compare_name(name).is_gt())
+        .map_or(p_entry, |(offset, _)| *offset);
+    let ins_entry_size = XattrEntry::required_size(name);
+    let ins_value_size = value.len().next_multiple_of(XATTR_PAD);
+
+    // Check space, 4 bytes are reserved for the end of the entry table
+    if p_value < p_entry + ins_entry_size + ins_value_size + XATTR_PAD {
+        // Not enough space
+        return false;
+    }
+
+    // Move the entries `[ins_entry_pos, p_entry)` backwards
+    data.copy_within(ins_entry_pos..p_entry, ins_entry_pos + ins_entry_size);
+    // Insert value to `[p_value - ins_value_size, p_value)`, zero padded
+    let ins_value_pos = p_value - ins_value_size;
+    data[ins_value_pos..p_value].fill(0);
+    data[ins_value_pos..ins_value_pos + value.len()].copy_from_slice(value);
+    // Insert entry to `[ins_entry_pos, ins_entry_pos + ins_entry_size)`
+    let entry = XattrEntry::new(name, value, ins_value_pos);
+    let entry_bytes = entry.to_bytes();
+    data[ins_entry_pos..ins_entry_pos + ins_entry_size].fill(0);
+    data[ins_entry_pos..ins_entry_pos + entry_bytes.len()].copy_from_slice(entry_bytes);
+
+    true
+}
+
+/// Remove a xattr from the entry table. The values below the removed one
+/// are moved upwards to keep the blank area contiguous. Return true if success.
+fn xattr_remove(data: &mut [u8], first: usize, name: &str) -> bool {
+    let entries = xattr_entries(data, first);
+    let (p_entry, p_value) = xattr_blank_area(data, first, &entries);
+    let Some((rem_entry_pos, rem_entry)) = entries
+        .iter()
+        .find(|(_, entry)| entry.compare_name(name).is_eq())
+    else {
+        return false;
+    };
+    let rem_entry_pos = *rem_entry_pos;
+    let rem_entry_size = rem_entry.used_size();
+    let rem_value_pos = rem_entry.value_offset as usize;
+    let rem_value_size = rem_entry.value_used_size();
+
+    if rem_entry.value_size != 0 {
+        // Move the values `[p_value, rem_value_pos)` upwards
+        data.copy_within(p_value..rem_value_pos, p_value + rem_value_size);
+        data[p_value..p_value + rem_value_size].fill(0);
+        // Update the value offset of the moved values
+        for (offset, entry) in entries.iter() {
+            if entry.value_size != 0 && (entry.value_offset as usize) < rem_value_pos {
+                let value_offset = entry.value_offset + rem_value_size as u16;
+                data[offset + 2..offset + 4].copy_from_slice(&value_offset.to_le_bytes());
+            }
+        }
+    }
+    // Move the entries `[rem_entry_pos + rem_entry_size, p_entry)` forwards
+    data.copy_within(rem_entry_pos + rem_entry_size..p_entry, rem_entry_pos);
+    data[p_entry - rem_entry_size..p_entry].fill(0);
+
+    true
+}
+
 /// The block that stores extended attributes for an inode. The block is
 /// pointed to `by inode.file_acl`.
 ///
@@ -208,6 +357,9 @@ impl XattrEntry {
 /// follow the end of the entry table. The values are stored starting at the
 /// end of the block and grow towards the xattr_header/xattr_entry table. When
 /// the two collide, the disk block fills up, and the filesystem returns `ENOSPC`.
+///
+/// Inodes with identical xattrs may share a block, `XattrHeader::refcount`
+/// records the number of sharing inodes.
 pub struct XattrBlock(Block);
 
 impl XattrBlock {
@@ -228,190 +380,140 @@ impl XattrBlock {
         self.0.write_offset_as(0, &header);
     }
 
+    /// Check the magic number and the block count of the block.
+    pub fn is_valid(&self) -> bool {
+        let header: XattrHeader = self.0.read_offset_as(0);
+        header.magic == XATTR_MAGIC && header.blocks == 1
+    }
+
+    /// Get the number of inodes sharing the block.
+    pub fn refcount(&self) -> u32 {
+        let header: XattrHeader = self.0.read_offset_as(0);
+        header.refcount
+    }
+
+    /// Set the number of inodes sharing the block.
+    pub fn set_refcount(&mut self, refcount: u32) {
+        let mut header: XattrHeader = self.0.read_offset_as(0);
+        header.refcount = refcount;
+        self.0.write_offset_as(0, &header);
+    }
+
+    /// Move the block to another physical block.
+    pub fn set_id(&mut self, block_id: PBlockId) {
+        self.0.id = block_id;
+    }
+
+    /// Check if the block contains no xattr.
+    pub fn is_empty(&self) -> bool {
+        xattr_entries(&self.0.data, size_of::<XattrHeader>()).is_empty()
+    }
+
     /// Get a xattr by name, return the value.
     pub fn get(&self, name: &str) -> Option<&[u8]> {
-        let mut entry_start = size_of::<XattrHeader>();
-        // Iterate over entry table
-        while entry_start < BLOCK_SIZE {
-            // Check `name_len`, 0 indicates the end of the entry table.
-            if self.0.data[entry_start] == 0 {
-                // Target xattr not found
-                break;
-            }
-            let entry: XattrEntry = self.0.read_offset_as(entry_start);
-            if entry.compare_name(name).is_eq() {
-                let offset = entry.value_offset as usize;
-                let size = entry.value_size as usize;
-                return Some(&self.0.data[offset..offset + size]);
-            }
-            entry_start += entry.used_size();
-        }
-        None
+        xattr_get(&self.0.data, size_of::<XattrHeader>(), name)
     }
 
     /// List all xattr names
     pub fn list(&self) -> Vec<String> {
-        let mut entry_start = size_of::<XattrHeader>();
-        let mut names = Vec::new();
-        // Iterate over entry table
-        while entry_start < BLOCK_SIZE {
-            // Check `name_len`, 0 indicates the end of the entry table.
-            if self.0.data[entry_start] == 0 {
-                break;
-            }
-            let entry: XattrEntry = self.0.read_offset_as(entry_start);
-            names.push(entry.name());
-            entry_start += entry.used_size();
-        }
-        names
+        xattr_list(&self.0.data, size_of::<XattrHeader>())
     }
 
     /// Insert a xattr entry into the block. Return true if success.
     pub fn insert(&mut self, name: &str, value: &[u8]) -> bool {
-        let mut p_entry = size_of::<XattrHeader>();
-        let mut p_value = BLOCK_SIZE;
-
-        let mut is_ins_pos_found = false;
-        let mut ins_entry_pos = p_entry;
-        let mut ins_value_pos = p_value;
-        let ins_entry_size = XattrEntry::required_size(name);
-        let ins_value_size = value.len();
-
-        // Iterate over entry table, find the position to insert entry
-        // and the end of entry table
-        while p_entry < BLOCK_SIZE {
-            // Check `name_len`, 0 indicates the end of the entry table.
-            if self.0.data[p_entry] == 0 {
-                // Reach the end of table
-                break;
-            }
-            let entry: XattrEntry = self.0.read_offset_as(p_entry);
-            if !is_ins_pos_found && entry.compare_name(name).is_gt() {
-                // Insert before this entry
-                ins_entry_pos = p_entry;
-                ins_value_pos = p_value;
-                is_ins_pos_found = true;
-            }
-            p_value = entry.value_offset as usize;
-            p_entry += entry.used_size();
-        }
-        if !is_ins_pos_found {
-            // Insert at the end of table
-            ins_entry_pos = p_entry;
-            ins_value_pos = p_value;
-        }
+        let res = xattr_insert(&mut self.0.data, size_of::<XattrHeader>(), name, value);
+        self.rehash();
+        res
+    }
 
-        // `ins_entry_pos` points to the position to insert entry,
-        // `ins_value_pos - ins_value_size` points to the position to insert value.
-        // `p_entry` points to the start of blank area,
-        // `p_value` points to the last value,
-        // `[p_entry, p_value)` is the blank area.
-        
-        // Check space, '+1' is reserved for blank area
-        if p_value - p_entry < ins_entry_size + ins_value_size + 1 {
-            // Not enough space
-            return false;
-        }
+    /// Remove a xattr entry from the block. Return true if success.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let res = xattr_remove(&mut self.0.data, size_of::<XattrHeader>(), name);
+        self.rehash();
+        res
+    }
 
-        // Move the entries from `ins_entry_pos` to `p_entry`
-        // Copy `[ins_entry_pos, p_entry)` to `[ins_entry_pos+ins_entry_size, p_entry+ins_entry_size)`
-        self.0
-            .data
-            .copy_within(ins_entry_pos..p_entry, ins_entry_pos + ins_entry_size);
-        // Set `[ins_entry_pos..ins_entry_pos+entry_req_size)` to 0
-        self.0.data[ins_entry_pos..ins_entry_pos + ins_entry_size].fill(0);
+    /// Set the checksum of the block, `fs_seed` is the filesystem checksum seed.
+    pub fn set_checksum(&mut self, fs_seed: u32) {
+        let mut header: XattrHeader = self.0.read_offset_as(0);
+        header.checksum = 0;
+        self.0.write_offset_as(0, &header);
+        let mut checksum = fs_seed;
+        checksum = crc32(checksum, &self.0.id.to_le_bytes());
+        header.checksum = crc32(checksum, &self.0.data);
+        self.0.write_offset_as(0, &header);
+    }
 
-        // Move the corresponding values
-        // Copy `[p_value, ins_value_pos)` to `[p_value-ins_value_size, ins_value_pos-ins_value_size)`
-        self.0
-            .data
-            .copy_within(p_value..ins_value_pos, p_value - ins_value_size);
-        // Set `[ins_value_pos-ins_value_size, ins_value_pos)` to 0
-        self.0.data[ins_value_pos - ins_value_size..ins_value_pos].fill(0);
-
-        // Update the value offset of the moved entries
-        let mut p_entry2 = ins_entry_pos + ins_entry_size;
-        while p_entry2 < p_entry + ins_entry_size {
-            let mut entry: XattrEntry = self.0.read_offset_as(p_entry2);
-            entry.value_offset -= ins_value_size as u16;
-            self.0.write_offset_as(p_entry2, &entry);
-            p_entry2 += entry.used_size();
+    /// Compute the block hash from the entry hashes.
+    fn rehash(&mut self) {
+        let mut hash: u32 = 0;
+        for (_, entry) in xattr_entries(&self.0.data, size_of::<XattrHeader>()) {
+            if entry.hash == 0 {
+                // The block can not be shared
+                hash = 0;
+                break;
+            }
+            hash = (hash << 16) ^ (hash >> 16) ^ entry.hash;
         }
+        let mut header: XattrHeader = self.0.read_offset_as(0);
+        header.hash = hash;
+        self.0.write_offset_as(0, &header);
+    }
+}
 
-        // Insert entry to `[ins_entry_pos, ins_entry_pos+ins_entry_size)`
-        let entry = XattrEntry::new(name, value.len(), ins_value_pos - ins_value_size);
-        self.0.write_offset_as(ins_entry_pos, &entry);
-        // Insert value to `[ins_value_pos-ins_value_size, ins_value_pos)`
-        self.0.write_offset(ins_value_pos - ins_value_size, value);
+/// The extended attribute area of an inode, which is the space between the
+/// end of the inode fields (`128 + inode.extra_isize`) and the end of the
+/// on-disk inode.
+///
+/// The area starts with the 4-byte magic number, followed by an array of
+/// `XattrEntry` and the values. Value offsets are relative to the first entry.
+pub struct XattrIbody<'a>(&'a mut [u8]);
+
+impl<'a> XattrIbody<'a> {
+    /// Wrap the xattr area of an inode as `XattrIbody`.
+    pub fn new(data: &'a mut [u8]) -> Self {
+        XattrIbody(data)
+    }
 
-        true
+    /// Check if the area is large enough and starts with the magic number.
+    pub fn is_valid(&self) -> bool {
+        self.0.len() > 2 * size_of::<u32>() && self.0[..4] == XATTR_MAGIC.to_le_bytes()
     }
 
-    /// Remove a xattr entry from the block. Return true if success.
-    pub fn remove(&mut self, name: &str) -> bool {
-        let mut p_entry = size_of::<XattrHeader>();
-        let mut p_value = BLOCK_SIZE;
-
-        let mut is_rem_pos_found = false;
-        let mut rem_entry_pos = p_entry;
-        let mut rem_value_pos = p_value;
-        let mut rem_entry_size = 0;
-        let mut rem_value_size = 0;
-
-        // Iterate over entry table, find the entry to remove
-        while p_entry < BLOCK_SIZE {
-            // Check `name_len`, 0 indicates the end of the entry table.
-            if self.0.data[p_entry] == 0 {
-                break;
-            }
-            let entry: XattrEntry = self.0.read_offset_as(p_entry);
-            p_value = entry.value_offset as usize;
-            // Compare name
-            if !is_rem_pos_found && entry.compare_name(name).is_eq() {
-                rem_entry_pos = p_entry;
-                rem_value_pos = p_value;
-                rem_entry_size = entry.used_size();
-                rem_value_size = entry.value_size as usize;
-                is_rem_pos_found = true;
-            }
-            p_entry += entry.used_size();
+    /// Initialize the area, write the magic number to the beginning.
+    pub fn init(&mut self) {
+        if self.0.len() > 2 * size_of::<u32>() {
+            self.0.fill(0);
+            self.0[..4].copy_from_slice(&XATTR_MAGIC.to_le_bytes());
         }
-        if !is_rem_pos_found {
-            return false;
+    }
+
+    /// Get a xattr by name, return the value.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        if !self.is_valid() {
+            return None;
         }
+        xattr_get(&self.0[4..], 0, name)
+    }
 
-        // `rem_entry_pos` points to the entry to remove,
-        // `rem_value_pos` points to the value to remove.
-        // `p_entry` points to the start of blank area,
-        // `p_value` points to the last value,
-        // `[p_entry, p_value)` is the blank area.
+    /// List all xattr names
+    pub fn list(&self) -> Vec<String> {
+        if !self.is_valid() {
+            return Vec::new();
+        }
+        xattr_list(&self.0[4..], 0)
+    }
 
-        // Move the following entries
-        // Copy `[rem_entry_pos + rem_entry_size, p_entry)` to `[rem_entry_pos, p_entry - rem_entry_size)`
-        self.0
-            .data
-            .copy_within(rem_entry_pos + rem_entry_size..p_entry, rem_entry_pos);
-        // Set `[p_entry - rem_entry_size, p_entry)` to 0
-        self.0.data[p_entry - rem_entry_size..p_entry].fill(0);
-
-        // Move the corresponding values
-        // Copy `[p_value, rem_value_pos)` to `[p_value + rem_value_size, rem_value_pos + rem_value_size)`
-        self.0.data.copy_within(
-            p_value..rem_value_pos,
-            p_value + rem_value_size,
-        );
-        // Set `[p_value, p_value + rem_value_size)` to 0
-        self.0.data[p_value..p_value + rem_value_size].fill(0);
-
-        // Update the value offset of the moved entries
-        let mut p_entry2 = rem_entry_pos;
-        while p_entry2 < p_entry - rem_entry_size {
-            let mut entry: XattrEntry = self.0.read_offset_as(p_entry2);
-            entry.value_offset += rem_value_size as u16;
-            self.0.write_offset_as(p_entry2, &entry);
-            p_entry2 += entry.used_size();
+    /// Insert a xattr entry into the area. Return true if success.
+    pub fn insert(&mut self, name: &str, value: &[u8]) -> bool {
+        if !self.is_valid() {
+            self.init();
         }
+        self.is_valid() && xattr_insert(&mut self.0[4..], 0, name, value)
+    }
 
-        true
+    /// Remove a xattr entry from the area. Return true if success.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.is_valid() && xattr_remove(&mut self.0[4..], 0, name)
     }
 }
@@ -1,12 +1,633 @@
+//! The Defination of JBD2 (Journaling Block Device 2) Data Structures
+//!
+//! The journal of an ext4 filesystem is stored in a hidden inode (usually inode 8).
+//! It is a circular log of transactions. The first block of the journal is the
+//! journal superblock. Each transaction is laid out as follows:
+//!
+//! - Descriptor block: records the final locations of the following blocks
+//! - Metadata blocks: copies of the metadata blocks to be written
+//! - (Optional) Revoke block: blocks that must not be replayed anymore
+//! - Commit block: marks the transaction as complete
+//!
+//! All fields of JBD2 data structures are stored in big-endian order.
+
+use crate::constants::*;
+use crate::ext4_defs::{crc32, AsBytes, Block};
 use crate::prelude::*;
 
-#[allow(unused)]
-pub trait Jbd2: Send + Sync + Any + Debug {
-    fn load_journal(&mut self);
-    fn journal_start(&mut self);
-    fn transaction_start(&mut self);
-    fn write_transaction(&mut self, block_id: usize, block_data: Vec<u8>);
-    fn transaction_stop(&mut self);
-    fn journal_stop(&mut self);
-    fn recover(&mut self);
+/// Magic number of every journal metadata block.
+pub const JBD2_MAGIC: u32 = 0xC03B3998;
+
+/// Journal block types.
+pub const JBD2_DESCRIPTOR_BLOCK: u32 = 1;
+pub const JBD2_COMMIT_BLOCK: u32 = 2;
+pub const JBD2_SUPERBLOCK_V1: u32 = 3;
+pub const JBD2_SUPERBLOCK_V2: u32 = 4;
+pub const JBD2_REVOKE_BLOCK: u32 = 5;
+
+/// Journal incompatible features.
+pub const JBD2_FEATURE_INCOMPAT_REVOKE: u32 = 0x1;
+pub const JBD2_FEATURE_INCOMPAT_64BIT: u32 = 0x2;
+pub const JBD2_FEATURE_INCOMPAT_ASYNC_COMMIT: u32 = 0x4;
+pub const JBD2_FEATURE_INCOMPAT_CSUM_V2: u32 = 0x8;
+pub const JBD2_FEATURE_INCOMPAT_CSUM_V3: u32 = 0x10;
+pub const JBD2_FEATURE_INCOMPAT_FAST_COMMIT: u32 = 0x20;
+/// Journal incompatible features supported by this implementation.
+pub const JBD2_KNOWN_INCOMPAT_FEATURES: u32 = JBD2_FEATURE_INCOMPAT_REVOKE
+    | JBD2_FEATURE_INCOMPAT_64BIT
+    | JBD2_FEATURE_INCOMPAT_CSUM_V2
+    | JBD2_FEATURE_INCOMPAT_CSUM_V3;
+
+/// Descriptor block tag flags.
+pub const JBD2_FLAG_ESCAPE: u32 = 0x1;
+pub const JBD2_FLAG_SAME_UUID: u32 = 0x2;
+pub const JBD2_FLAG_LAST_TAG: u32 = 0x8;
+
+/// The common header of journal metadata blocks.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JournalHeader {
+    /// Magic number, `JBD2_MAGIC`.
+    magic: u32,
+    /// Description of what this block contains.
+    block_type: u32,
+    /// The transaction ID that goes with this block.
+    sequence: u32,
+}
+
+unsafe impl AsBytes for JournalHeader {}
+
+impl JournalHeader {
+    pub fn new(block_type: u32, sequence: u32) -> Self {
+        Self {
+            magic: JBD2_MAGIC.to_be(),
+            block_type: block_type.to_be(),
+            sequence: sequence.to_be(),
+        }
+    }
+
+    pub fn check_magic(&self) -> bool {
+        u32::from_be(self.magic) == JBD2_MAGIC
+    }
+
+    pub fn block_type(&self) -> u32 {
+        u32::from_be(self.block_type)
+    }
+
+    pub fn sequence(&self) -> u32 {
+        u32::from_be(self.sequence)
+    }
+}
+
+/// The journal superblock, stored in the first block of the journal.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct JournalSuperBlock {
+    header: JournalHeader,
+    /// Journal device block size.
+    block_size: u32,
+    /// Total number of blocks in this journal.
+    max_len: u32,
+    /// First block of log information.
+    first: u32,
+    /// First commit ID expected in log.
+    sequence: u32,
+    /// Block number of the start of log. Zero means the journal is clean.
+    start: u32,
+    /// Error value, as set by `jbd2_journal_abort()`.
+    errno: u32,
+    /// Compatible feature set.
+    feature_compat: u32,
+    /// Incompatible feature set.
+    feature_incompat: u32,
+    /// Read-only compatible feature set.
+    feature_ro_compat: u32,
+    /// 128-bit uuid for journal.
+    uuid: [u8; 16],
+    /// Number of file systems sharing this journal.
+    nr_users: u32,
+    /// Location of dynamic super block copy. (Not used)
+    dyn_super: u32,
+    /// Limit of journal blocks per transaction. (Not used)
+    max_transaction: u32,
+    /// Limit of data blocks per transaction. (Not used)
+    max_trans_data: u32,
+    /// Checksum algorithm used for the journal, 4 = crc32c.
+    checksum_type: u8,
+    padding2: [u8; 3],
+    /// Number of fast commit blocks in the journal.
+    num_fc_blocks: u32,
+    /// Block number of the head (first unused block) of the journal.
+    head: u32,
+    padding: [u32; 40],
+    /// Checksum of the entire superblock, with this field set to zero.
+    checksum: u32,
+    /// Ids of all file systems sharing the log.
+    users: [u8; 16 * 48],
+}
+
+unsafe impl AsBytes for JournalSuperBlock {}
+
+impl JournalSuperBlock {
+    /// Check the magic number and the block type.
+    pub fn check_magic(&self) -> bool {
+        self.header.check_magic()
+            && matches!(
+                self.header.block_type(),
+                JBD2_SUPERBLOCK_V1 | JBD2_SUPERBLOCK_V2
+            )
+    }
+
+    pub fn block_size(&self) -> u32 {
+        u32::from_be(self.block_size)
+    }
+
+    /// The total number of blocks of the journal.
+    pub fn max_len(&self) -> u32 {
+        u32::from_be(self.max_len)
+    }
+
+    pub fn first(&self) -> u32 {
+        u32::from_be(self.first)
+    }
+
+    pub fn sequence(&self) -> u32 {
+        u32::from_be(self.sequence)
+    }
+
+    pub fn set_sequence(&mut self, sequence: u32) {
+        self.sequence = sequence.to_be();
+    }
+
+    pub fn start(&self) -> u32 {
+        u32::from_be(self.start)
+    }
+
+    pub fn set_start(&mut self, start: u32) {
+        self.start = start.to_be();
+    }
+
+    pub fn uuid(&self) -> [u8; 16] {
+        self.uuid
+    }
+
+    pub fn feature_incompat(&self) -> u32 {
+        if self.header.block_type() == JBD2_SUPERBLOCK_V1 {
+            return 0;
+        }
+        u32::from_be(self.feature_incompat)
+    }
+
+    pub fn has_incompat(&self, feature: u32) -> bool {
+        self.feature_incompat() & feature != 0
+    }
+
+    /// Whether the journal uses checksum v2 or v3.
+    pub fn has_csum_v2or3(&self) -> bool {
+        self.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V2 | JBD2_FEATURE_INCOMPAT_CSUM_V3)
+    }
+
+    /// The size of a block tag in the descriptor block.
+    pub fn tag_bytes(&self) -> usize {
+        if self.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            return 16;
+        }
+        let mut size = 12;
+        if self.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V2) {
+            size += 2;
+        }
+        if self.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT) {
+            size
+        } else {
+            size - 4
+        }
+    }
+
+    /// The checksum seed of the journal, computed from the journal uuid.
+    pub fn csum_seed(&self) -> u32 {
+        crc32(CRC32_INIT, &self.uuid)
+    }
+
+    /// Set the checksum of the journal superblock if checksums are enabled.
+    pub fn set_checksum(&mut self) {
+        if !self.has_csum_v2or3() {
+            return;
+        }
+        self.checksum = 0;
+        self.checksum = crc32(CRC32_INIT, self.to_bytes()).to_be();
+    }
+}
+
+/// A block tag in a descriptor block, which describes the final
+/// location of a journaled block.
+#[derive(Debug, Clone, Copy)]
+pub struct JournalBlockTag {
+    /// The final location of the journaled block.
+    pub block: PBlockId,
+    /// Tag flags, `JBD2_FLAG_*`.
+    pub flags: u32,
+    /// Checksum of the journaled block data.
+    pub checksum: u32,
+}
+
+impl JournalBlockTag {
+    /// Parse a block tag from `bytes` according to the journal features.
+    pub fn from_bytes(jsb: &JournalSuperBlock, bytes: &[u8]) -> Self {
+        let be32 = |off: usize| u32::from_be_bytes(bytes[off..off + 4].try_into().unwrap());
+        let be16 = |off: usize| u16::from_be_bytes(bytes[off..off + 2].try_into().unwrap());
+        let is_64bit = jsb.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT);
+        if jsb.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            // blocknr, flags, blocknr_high, checksum
+            let high = if is_64bit { be32(8) as u64 } else { 0 };
+            Self {
+                block: (high << 32) | be32(0) as u64,
+                flags: be32(4),
+                checksum: be32(12),
+            }
+        } else {
+            // blocknr, checksum(16), flags(16), blocknr_high
+            let high = if is_64bit { be32(8) as u64 } else { 0 };
+            Self {
+                block: (high << 32) | be32(0) as u64,
+                flags: be16(6) as u32,
+                checksum: be16(4) as u32,
+            }
+        }
+    }
+
+    /// Serialize the block tag to `bytes` according to the journal features.
+    pub fn to_bytes(&self, jsb: &JournalSuperBlock, bytes: &mut [u8]) {
+        let is_64bit = jsb.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT);
+        bytes[0..4].copy_from_slice(&(self.block as u32).to_be_bytes());
+        if jsb.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            bytes[4..8].copy_from_slice(&self.flags.to_be_bytes());
+            bytes[12..16].copy_from_slice(&self.checksum.to_be_bytes());
+        } else {
+            bytes[4..6].copy_from_slice(&(self.checksum as u16).to_be_bytes());
+            bytes[6..8].copy_from_slice(&(self.flags as u16).to_be_bytes());
+        }
+        if is_64bit {
+            bytes[8..12].copy_from_slice(&((self.block >> 32) as u32).to_be_bytes());
+        }
+    }
+
+    /// Compute the checksum of a journaled block.
+    pub fn compute_checksum(jsb: &JournalSuperBlock, sequence: u32, data: &[u8]) -> u32 {
+        let mut csum = crc32(jsb.csum_seed(), &sequence.to_be_bytes());
+        csum = crc32(csum, data);
+        if jsb.has_incompat(JBD2_FEATURE_INCOMPAT_CSUM_V3) {
+            csum
+        } else {
+            csum & 0xFFFF
+        }
+    }
+}
+
+/// Interpret a block as a journal descriptor block, which is followed by
+/// the journaled blocks it describes.
+pub struct DescriptorBlock {
+    block: Block,
+}
+
+impl DescriptorBlock {
+    /// The size of the checksum tail at the end of descriptor and revoke blocks.
+    const TAIL_SIZE: usize = 4;
+
+    /// Wrap a journal block as a descriptor block.
+    pub fn new(block: Block) -> Self {
+        Self { block }
+    }
+
+    /// Initialize an empty descriptor block.
+    pub fn init(&mut self, sequence: u32) {
+        self.block.data.fill(0);
+        self.block
+            .write_offset_as(0, &JournalHeader::new(JBD2_DESCRIPTOR_BLOCK, sequence));
+    }
+
+    /// Get the wrapped block.
+    pub fn block(self) -> Block {
+        self.block
+    }
+
+    /// The maximum number of tags that fit into a descriptor block.
+    pub fn max_tags(jsb: &JournalSuperBlock) -> usize {
+        let mut space = BLOCK_SIZE - size_of::<JournalHeader>() - 16;
+        if jsb.has_csum_v2or3() {
+            space -= Self::TAIL_SIZE;
+        }
+        space / jsb.tag_bytes()
+    }
+
+    /// Parse all tags in the descriptor block.
+    pub fn tags(&self, jsb: &JournalSuperBlock) -> Vec<JournalBlockTag> {
+        let tag_bytes = jsb.tag_bytes();
+        let mut end = BLOCK_SIZE;
+        if jsb.has_csum_v2or3() {
+            end -= Self::TAIL_SIZE;
+        }
+        let mut tags = Vec::new();
+        let mut offset = size_of::<JournalHeader>();
+        while offset + tag_bytes <= end {
+            let tag = JournalBlockTag::from_bytes(jsb, &self.block.data[offset..]);
+            offset += tag_bytes;
+            if tag.flags & JBD2_FLAG_SAME_UUID == 0 {
+                offset += 16;
+            }
+            let last = tag.flags & JBD2_FLAG_LAST_TAG != 0;
+            tags.push(tag);
+            if last {
+                break;
+            }
+        }
+        tags
+    }
+
+    /// Write all tags to the descriptor block.
+    pub fn set_tags(&mut self, jsb: &JournalSuperBlock, tags: &[JournalBlockTag]) {
+        let tag_bytes = jsb.tag_bytes();
+        let mut offset = size_of::<JournalHeader>();
+        for (i, tag) in tags.iter().enumerate() {
+            let mut tag = *tag;
+            if i != 0 {
+                tag.flags |= JBD2_FLAG_SAME_UUID;
+            }
+            if i == tags.len() - 1 {
+                tag.flags |= JBD2_FLAG_LAST_TAG;
+            }
+            tag.to_bytes(jsb, &mut self.block.data[offset..offset + tag_bytes]);
+            offset += tag_bytes;
+            if i == 0 {
+                // The first tag is followed by the journal uuid
+                self.block.write_offset(offset, &jsb.uuid());
+                offset += 16;
+            }
+        }
+    }
+
+    /// Verify the checksum tail of a descriptor or revoke block.
+    pub fn verify_tail(jsb: &JournalSuperBlock, block: &Block) -> bool {
+        if !jsb.has_csum_v2or3() {
+            return true;
+        }
+        let tail_offset = BLOCK_SIZE - Self::TAIL_SIZE;
+        let expected = u32::from_be_bytes(block.data[tail_offset..].try_into().unwrap());
+        let mut data = block.data;
+        data[tail_offset..].fill(0);
+        crc32(jsb.csum_seed(), &data) == expected
+    }
+
+    /// Set the checksum tail of a descriptor or revoke block.
+    pub fn set_tail(jsb: &JournalSuperBlock, block: &mut Block) {
+        if !jsb.has_csum_v2or3() {
+            return;
+        }
+        let tail_offset = BLOCK_SIZE - Self::TAIL_SIZE;
+        block.data[tail_offset..].fill(0);
+        let csum = crc32(jsb.csum_seed(), &block.data);
+        block.write_offset(tail_offset, &csum.to_be_bytes());
+    }
+}
+
+/// Interpret a block as a journal revoke block, which lists blocks that
+/// must not be replayed from earlier transactions.
+pub struct RevokeBlock<'a> {
+    block: &'a Block,
+}
+
+impl<'a> RevokeBlock<'a> {
+    pub fn new(block: &'a Block) -> Self {
+        Self { block }
+    }
+
+    /// Parse all revoked block numbers.
+    pub fn records(&self, jsb: &JournalSuperBlock) -> Vec<PBlockId> {
+        let header_size = size_of::<JournalHeader>();
+        let count = u32::from_be_bytes(
+            self.block.data[header_size..header_size + 4]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        let record_size = if jsb.has_incompat(JBD2_FEATURE_INCOMPAT_64BIT) {
+            8
+        } else {
+            4
+        };
+        let mut records = Vec::new();
+        let mut offset = header_size + 4;
+        while offset + record_size <= count.min(BLOCK_SIZE) {
+            let bytes = &self.block.data[offset..offset + record_size];
+            records.push(if record_size == 8 {
+                u64::from_be_bytes(bytes.try_into().unwrap())
+            } else {
+                u32::from_be_bytes(bytes.try_into().unwrap()) as u64
+            });
+            offset += record_size;
+        }
+        records
+    }
+}
+
+/// The journal commit block, which marks a transaction as complete.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CommitHeader {
+    header: JournalHeader,
+    /// Type of the checksum, only used by `JBD2_FEATURE_COMPAT_CHECKSUM`.
+    checksum_type: u8,
+    /// Size of the checksum, only used by `JBD2_FEATURE_COMPAT_CHECKSUM`.
+    checksum_size: u8,
+    padding: [u8; 2],
+    /// Checksum of the transaction. Only the first word is used.
+    checksum: [u32; 8],
+    /// Commit time, seconds.
+    commit_sec: u64,
+    /// Commit time, nanoseconds.
+    commit_nsec: u32,
+}
+
+unsafe impl AsBytes for CommitHeader {}
+
+impl CommitHeader {
+    pub fn new(sequence: u32) -> Self {
+        Self {
+            header: JournalHeader::new(JBD2_COMMIT_BLOCK, sequence),
+            checksum_type: 0,
+            checksum_size: 0,
+            padding: [0; 2],
+            checksum: [0; 8],
+            commit_sec: 0,
+            commit_nsec: 0,
+        }
+    }
+
+    /// Verify the checksum of a commit block.
+    pub fn verify_checksum(jsb: &JournalSuperBlock, block: &Block) -> bool {
+        if !jsb.has_csum_v2or3() {
+            return true;
+        }
+        let commit: CommitHeader = block.read_offset_as(0);
+        let expected = u32::from_be(commit.checksum[0]);
+        let mut data = block.data;
+        let offset = Self::checksum_offset();
+        data[offset..offset + 4].fill(0);
+        crc32(jsb.csum_seed(), &data) == expected
+    }
+
+    /// Set the checksum of a commit block.
+    pub fn set_checksum(jsb: &JournalSuperBlock, block: &mut Block) {
+        if !jsb.has_csum_v2or3() {
+            return;
+        }
+        let offset = Self::checksum_offset();
+        block.data[offset..offset + 4].fill(0);
+        let csum = crc32(jsb.csum_seed(), &block.data);
+        block.write_offset(offset, &csum.to_be_bytes());
+    }
+
+    fn checksum_offset() -> usize {
+        size_of::<JournalHeader>() + 4
+    }
+}
+
+/// The running transaction of a journal.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    /// Number of active handles that belong to this transaction.
+    handles: usize,
+    /// Dirty metadata blocks that will be written to the journal on commit.
+    blocks: BTreeMap<PBlockId, Block>,
+}
+
+/// The in-memory state of a JBD2 journal.
+pub struct Journal {
+    /// The journal superblock.
+    jsb: JournalSuperBlock,
+    /// Physical block ids of all journal blocks, indexed by journal block number.
+    blocks: Vec<PBlockId>,
+    /// The sequence number of the next transaction to commit.
+    sequence: u32,
+    /// The running transaction, if any.
+    running: Option<Transaction>,
+}
+
+impl Journal {
+    /// Create a journal from its superblock and the block mapping of the journal inode.
+    pub fn new(jsb: JournalSuperBlock, blocks: Vec<PBlockId>) -> Self {
+        let sequence = jsb.sequence();
+        Self {
+            jsb,
+            blocks,
+            sequence,
+            running: None,
+        }
+    }
+
+    pub fn superblock(&self) -> &JournalSuperBlock {
+        &self.jsb
+    }
+
+    pub fn superblock_mut(&mut self) -> &mut JournalSuperBlock {
+        &mut self.jsb
+    }
+
+    /// Map a journal block number to a physical block id.
+    pub fn bmap(&self, jblock: u32) -> PBlockId {
+        self.blocks[jblock as usize]
+    }
+
+    /// The next journal block number of `jblock`, wrapping around the log area.
+    pub fn next_block(&self, jblock: u32) -> u32 {
+        let next = jblock + 1;
+        if next >= self.jsb.max_len() {
+            self.jsb.first()
+        } else {
+            next
+        }
+    }
+
+    /// The maximum number of metadata blocks a single transaction can hold.
+    pub fn max_transaction_blocks(&self) -> usize {
+        let log_blocks = (self.jsb.max_len() - self.jsb.first()) as usize;
+        let max_tags = DescriptorBlock::max_tags(&self.jsb);
+        // Each `max_tags` blocks need one descriptor block, plus one commit block.
+        (log_blocks - 1) * max_tags / (max_tags + 1)
+    }
+
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    pub fn set_sequence(&mut self, sequence: u32) {
+        self.sequence = sequence;
+    }
+
+    /// Join the running transaction, creating one if there is none.
+    pub fn start_handle(&mut self) {
+        self.running
+            .get_or_insert_with(Transaction::default)
+            .handles += 1;
+    }
+
+    /// Leave the running transaction. Return the transaction if this was
+    /// the last handle and the transaction is ready to commit.
+    pub fn stop_handle(&mut self) -> Option<Transaction> {
+        let trans = self.running.as_mut()?;
+        trans.handles -= 1;
+        if trans.handles == 0 {
+            self.running.take()
+        } else {
+            None
+        }
+    }
+
+    /// Whether the running transaction is full, so that recording `block_id`
+    /// would make it too large to fit in the log.
+    pub fn is_running_full(&self, block_id: PBlockId) -> bool {
+        self.running.as_ref().is_some_and(|trans| {
+            !trans.blocks.contains_key(&block_id)
+                && trans.blocks.len() >= self.max_transaction_blocks()
+        })
+    }
+
+    /// Take the blocks recorded so far out of the running transaction, so
+    /// that they can be committed while its handles go on in a new one.
+    pub fn split_running(&mut self) -> Option<Transaction> {
+        let trans = self.running.as_mut()?;
+        Some(Transaction {
+            handles: 0,
+            blocks: core::mem::take(&mut trans.blocks),
+        })
+    }
+
+    /// Look up a block in the running transaction.
+    pub fn get_block(&self, block_id: PBlockId) -> Option<&Block> {
+        self.running.as_ref()?.blocks.get(&block_id)
+    }
+
+    /// Record a metadata block in the running transaction. Return false if
+    /// there is no running transaction.
+    pub fn put_block(&mut self, block: &Block) -> bool {
+        match self.running.as_mut() {
+            Some(trans) => {
+                trans.blocks.insert(block.id, *block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop a freed block from the running transaction, so that stale
+    /// metadata will not overwrite the block if it is reused.
+    pub fn forget_block(&mut self, block_id: PBlockId) {
+        if let Some(trans) = self.running.as_mut() {
+            trans.blocks.remove(&block_id);
+        }
+    }
+}
+
+impl Transaction {
+    pub fn blocks(&self) -> &BTreeMap<PBlockId, Block> {
+        &self.blocks
+    }
 }
@@ -11,6 +11,9 @@ mod prelude;
 pub use constants::{BLOCK_SIZE, EXT4_ROOT_INO, INODE_BLOCK_SIZE};
 pub use error::{ErrCode, Ext4Error};
 pub use ext4::Ext4;
-pub use ext4_defs::{Block, BlockDevice, DirEntry, FileAttr, FileType, Inode, InodeMode, InodeRef};
+pub use ext4_defs::{
+    Block, BlockDevice, DirEntry, Extent, FallocFlags, FileAttr, FileType, Inode, InodeFlags,
+    InodeMode, InodeRef,
+};
 pub use prelude::{LBlockId, PBlockId};
 
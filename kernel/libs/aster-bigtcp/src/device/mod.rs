@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod queue;
+
+pub use queue::QueueDevice;
 pub use smoltcp::phy::{
     Checksum, ChecksumCapabilities, Device, DeviceCapabilities, Loopback, Medium, RxToken, TxToken,
 };
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, vec, vec::Vec};
+
+use smoltcp::{
+    phy::{self, DeviceCapabilities, Medium},
+    time::Instant,
+};
+
+use super::NotifyDevice;
+
+/// A device that stores packets in memory queues.
+///
+/// The device has no underlying hardware. Instead, packets to be received by the network stack are
+/// pushed to the receive queue via [`QueueDevice::push_rx`], and packets transmitted by the
+/// network stack are popped from the transmit queue via [`QueueDevice::pop_tx`]. This allows
+/// devices whose packets come from and go to user programs (e.g., TUN/TAP devices).
+pub struct QueueDevice {
+    medium: Medium,
+    mtu: usize,
+    queue_len: usize,
+    rx_queue: VecDeque<Vec<u8>>,
+    tx_queue: VecDeque<Vec<u8>>,
+}
+
+impl QueueDevice {
+    /// Creates a new device.
+    ///
+    /// `mtu` is the maximum transmission unit, which includes the Ethernet header if `medium` is
+    /// [`Medium::Ethernet`]. `queue_len` is the maximum number of packets in each queue.
+    pub fn new(medium: Medium, mtu: usize, queue_len: usize) -> Self {
+        Self {
+            medium,
+            mtu,
+            queue_len,
+            rx_queue: VecDeque::new(),
+            tx_queue: VecDeque::new(),
+        }
+    }
+
+    /// Pushes a packet to the receive queue.
+    ///
+    /// If the receive queue is full, the packet is dropped and `false` is returned. Packets that
+    /// are larger than the MTU are also dropped.
+    pub fn push_rx(&mut self, packet: Vec<u8>) -> bool {
+        if self.rx_queue.len() >= self.queue_len || packet.len() > self.mtu {
+            return false;
+        }
+
+        self.rx_queue.push_back(packet);
+        true
+    }
+
+    /// Pops a packet from the transmit queue.
+    pub fn pop_tx(&mut self) -> Option<Vec<u8>> {
+        self.tx_queue.pop_front()
+    }
+
+    /// Returns whether the transmit queue has packets.
+    pub fn has_tx(&self) -> bool {
+        !self.tx_queue.is_empty()
+    }
+
+    fn can_transmit(&self) -> bool {
+        self.tx_queue.len() < self.queue_len
+    }
+}
+
+impl phy::Device for QueueDevice {
+    type RxToken<'a> = RxToken;
+    type TxToken<'a> = TxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        if !self.can_transmit() {
+            return None;
+        }
+
+        let packet = self.rx_queue.pop_front()?;
+        Some((
+            RxToken(packet),
+            TxToken {
+                queue: &mut self.tx_queue,
+            },
+        ))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        if !self.can_transmit() {
+            return None;
+        }
+
+        Some(TxToken {
+            queue: &mut self.tx_queue,
+        })
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.medium = self.medium;
+        caps.max_transmission_unit = self.mtu;
+        caps
+    }
+}
+
+impl NotifyDevice for QueueDevice {
+    fn notify_poll_end(&mut self) {}
+}
+
+pub struct RxToken(Vec<u8>);
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.0)
+    }
+}
+
+pub struct TxToken<'a> {
+    queue: &'a mut VecDeque<Vec<u8>>,
+}
+
+impl phy::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buffer = vec![0u8; len];
+        let res = f(&mut buffer);
+        self.queue.push_back(buffer);
+        res
+    }
+}
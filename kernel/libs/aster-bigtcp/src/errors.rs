@@ -9,6 +9,17 @@ pub enum BindError {
     InUse,
 }
 
+/// An error describing the reason why configuring the addresses or the routes of an iface failed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IfaceConfigError {
+    /// The address or the route already exists.
+    Exists,
+    /// The address or the route does not exist.
+    NotFound,
+    /// There is no space for more addresses or routes.
+    TableFull,
+}
+
 pub mod tcp {
     /// An error returned by [`TcpListener::new_listen`].
     ///
@@ -112,3 +123,51 @@ pub mod udp {
         }
     }
 }
+
+pub mod raw {
+    /// An error returned by [`IpRawSocket::send`].
+    ///
+    /// [`IpRawSocket::send`]: crate::socket::IpRawSocket::send
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SendError {
+        Unaddressable,
+        BufferFull,
+        /// The packet is too large.
+        TooLarge,
+        /// The packet is malformed.
+        Malformed,
+    }
+
+    /// An error returned by [`IpRawSocket::recv`].
+    ///
+    /// [`IpRawSocket::recv`]: crate::socket::IpRawSocket::recv
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvError {
+        /// The receive queue is empty.
+        Exhausted,
+    }
+}
+
+pub mod link {
+    /// An error returned by [`LinkSocket::send`].
+    ///
+    /// [`LinkSocket::send`]: crate::socket::LinkSocket::send
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SendError {
+        Unaddressable,
+        BufferFull,
+        /// The frame is too large.
+        TooLarge,
+        /// The frame is malformed.
+        Malformed,
+    }
+
+    /// An error returned by [`LinkSocket::recv`].
+    ///
+    /// [`LinkSocket::recv`]: crate::socket::LinkSocket::recv
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvError {
+        /// The receive queue is empty.
+        Exhausted,
+    }
+}
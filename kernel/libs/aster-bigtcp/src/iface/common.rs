@@ -6,7 +6,7 @@ use alloc::{
     sync::Arc,
     vec::Vec,
 };
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 
 use aster_softirq::BottomHalfDisabled;
 use bitflags::bitflags;
@@ -14,21 +14,28 @@ use int_to_c_enum::TryFromInt;
 use ostd::sync::{SpinLock, SpinLockGuard};
 use smoltcp::{
     iface::{packet::Packet, Context},
-    phy::Device,
-    wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv4Packet},
+    phy::{Device, TxToken},
+    time::Instant,
+    wire::{
+        EthernetAddress, EthernetFrame, HardwareAddress, IpAddress, IpCidr, IpEndpoint, IpVersion,
+        Ipv4Address, Ipv6Address, ETHERNET_HEADER_LEN,
+    },
 };
 
 use super::{
-    poll::{FnHelper, PollContext, SocketTableAction},
+    poll::{FnHelper, IpPacket, PollContext, SocketTableAction},
     poll_iface::PollableIface,
     port::BindPortConfig,
     time::get_network_timestamp,
-    Iface,
+    Iface, Route,
 };
 use crate::{
-    errors::BindError,
+    errors::{BindError, IfaceConfigError},
     ext::Ext,
-    socket::{TcpListenerBg, UdpSocketBg},
+    socket::{
+        IpRawSocketBg, LinkFrameMeta, LinkPacketType, LinkSocketBg, NeedIfacePoll, TcpListenerBg,
+        UdpSocketBg,
+    },
     socket_table::SocketTable,
 };
 
@@ -36,11 +43,15 @@ pub struct IfaceCommon<E: Ext> {
     index: u32,
     name: String,
     type_: InterfaceType,
-    flags: InterfaceFlags,
+    flags: AtomicU32,
+    mtu: AtomicUsize,
+    ether_addr: Option<EthernetAddress>,
+    promisc_count: AtomicUsize,
 
     interface: SpinLock<PollableIface<E>, BottomHalfDisabled>,
     used_ports: SpinLock<BTreeMap<u16, usize>, BottomHalfDisabled>,
     sockets: SpinLock<SocketTable<E>, BottomHalfDisabled>,
+    link_sockets: SpinLock<Vec<Arc<LinkSocketBg<E>>>, BottomHalfDisabled>,
     sched_poll: E::ScheduleNextPoll,
 }
 
@@ -53,15 +64,23 @@ impl<E: Ext> IfaceCommon<E> {
         sched_poll: E::ScheduleNextPoll,
     ) -> Self {
         let index = INTERFACE_INDEX_ALLOCATOR.fetch_add(1, Ordering::Relaxed);
+        let ether_addr = match interface.hardware_addr() {
+            HardwareAddress::Ethernet(ether_addr) => Some(ether_addr),
+            _ => None,
+        };
 
         Self {
             index,
             name,
             type_,
-            flags,
+            flags: AtomicU32::new(flags.bits()),
+            mtu: AtomicUsize::new(0),
+            ether_addr,
+            promisc_count: AtomicUsize::new(0),
             interface: SpinLock::new(PollableIface::new(interface)),
             used_ports: SpinLock::new(BTreeMap::new()),
             sockets: SpinLock::new(SocketTable::new()),
+            link_sockets: SpinLock::new(Vec::new()),
             sched_poll,
         }
     }
@@ -79,7 +98,41 @@ impl<E: Ext> IfaceCommon<E> {
     }
 
     pub(super) fn flags(&self) -> InterfaceFlags {
-        self.flags
+        let flags = InterfaceFlags::from_bits_truncate(self.flags.load(Ordering::Relaxed));
+        if self.is_promisc() {
+            flags | InterfaceFlags::PROMISC
+        } else {
+            flags
+        }
+    }
+
+    pub(super) fn set_up(&self, is_up: bool) {
+        let up_flags = (InterfaceFlags::UP | InterfaceFlags::RUNNING).bits();
+        if is_up {
+            self.flags.fetch_or(up_flags, Ordering::Relaxed);
+        } else {
+            self.flags.fetch_and(!up_flags, Ordering::Relaxed);
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        self.flags().contains(InterfaceFlags::UP)
+    }
+
+    /// Returns the MTU configured by the user, if any.
+    pub(super) fn mtu(&self) -> Option<usize> {
+        match self.mtu.load(Ordering::Relaxed) {
+            0 => None,
+            mtu => Some(mtu),
+        }
+    }
+
+    pub(super) fn set_mtu(&self, mtu: usize) {
+        self.mtu.store(mtu, Ordering::Relaxed);
+    }
+
+    pub(super) fn ether_addr(&self) -> Option<EthernetAddress> {
+        self.ether_addr
     }
 
     pub(super) fn ipv4_addr(&self) -> Option<Ipv4Address> {
@@ -90,6 +143,50 @@ impl<E: Ext> IfaceCommon<E> {
         self.interface.lock().prefix_len()
     }
 
+    pub(super) fn ipv6_addr(&self) -> Option<Ipv6Address> {
+        self.interface.lock().ipv6_addr()
+    }
+
+    pub(super) fn ip_addrs(&self) -> Vec<IpCidr> {
+        self.interface.lock().ip_addrs()
+    }
+
+    pub(super) fn add_ip_addr(&self, ip_cidr: IpCidr) -> Result<(), IfaceConfigError> {
+        self.interface.lock().add_ip_addr(ip_cidr)
+    }
+
+    pub(super) fn remove_ip_addr(&self, ip_cidr: IpCidr) -> Result<(), IfaceConfigError> {
+        self.interface.lock().remove_ip_addr(ip_cidr)
+    }
+
+    pub(super) fn routes(&self) -> Vec<Route> {
+        self.interface.lock().routes()
+    }
+
+    pub(super) fn add_route(&self, route: Route) -> Result<(), IfaceConfigError> {
+        self.interface.lock().add_route(route)
+    }
+
+    pub(super) fn remove_route(&self, route: &Route) -> Result<(), IfaceConfigError> {
+        self.interface.lock().remove_route(route)
+    }
+
+    pub(super) fn join_multicast_group(&self, group: Ipv4Address) -> NeedIfacePoll {
+        if self.interface.lock().join_multicast_group(group) {
+            NeedIfacePoll::TRUE
+        } else {
+            NeedIfacePoll::FALSE
+        }
+    }
+
+    pub(super) fn leave_multicast_group(&self, group: Ipv4Address) -> NeedIfacePoll {
+        if self.interface.lock().leave_multicast_group(group) {
+            NeedIfacePoll::TRUE
+        } else {
+            NeedIfacePoll::FALSE
+        }
+    }
+
     pub(super) fn sched_poll(&self) -> &E::ScheduleNextPoll {
         &self.sched_poll
     }
@@ -100,7 +197,7 @@ impl<E: Ext> IfaceCommon<E> {
 // FIXME: This allocator is specific to each network namespace.
 pub static INTERFACE_INDEX_ALLOCATOR: AtomicU32 = AtomicU32::new(1);
 
-// Lock order: `interface` -> `sockets`
+// Lock order: `interface` -> `sockets` -> `link_sockets`
 impl<E: Ext> IfaceCommon<E> {
     /// Acquires the lock to the interface.
     pub(crate) fn interface(&self) -> SpinLockGuard<'_, PollableIface<E>, BottomHalfDisabled> {
@@ -120,10 +217,15 @@ impl<E: Ext> IfaceCommon<E> {
     pub(super) fn bind(
         &self,
         iface: Arc<dyn Iface<E>>,
+        ip_version: IpVersion,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
         let port = self.bind_port(config)?;
-        Ok(BoundPort { iface, port })
+        Ok(BoundPort {
+            iface,
+            ip_version,
+            port,
+        })
     }
 
     /// Allocates an unused ephemeral port.
@@ -196,8 +298,229 @@ impl<E: Ext> IfaceCommon<E> {
         let removed = sockets.remove_udp_socket(socket);
         debug_assert!(removed.is_some());
     }
+
+    pub(crate) fn register_raw_socket(&self, socket: Arc<IpRawSocketBg<E>>) {
+        let mut sockets = self.sockets.lock();
+        sockets.insert_raw_socket(socket);
+    }
+
+    /// Registers an ICMP echo socket created by `new_socket` with an unused identifier.
+    ///
+    /// If `ident` is `None`, an unused identifier will be allocated from the same range as
+    /// ephemeral ports, as Linux does.
+    pub(crate) fn register_ping_socket<F>(
+        &self,
+        ip_version: IpVersion,
+        ident: Option<u16>,
+        new_socket: F,
+    ) -> Result<Arc<IpRawSocketBg<E>>, BindError>
+    where
+        F: FnOnce(u16) -> Arc<IpRawSocketBg<E>>,
+    {
+        let mut sockets = self.sockets.lock();
+
+        let is_used = |ident: u16| {
+            sockets
+                .raw_socket_iter()
+                .any(|socket| socket.can_process_ping(ip_version, ident))
+        };
+        let ident = match ident {
+            Some(ident) if is_used(ident) => return Err(BindError::InUse),
+            Some(ident) => ident,
+            None => (IP_LOCAL_PORT_START..=IP_LOCAL_PORT_END)
+                .find(|ident| !is_used(*ident))
+                .ok_or(BindError::Exhausted)?,
+        };
+
+        let socket = new_socket(ident);
+        sockets.insert_raw_socket(socket.clone());
+
+        Ok(socket)
+    }
+
+    pub(crate) fn remove_raw_socket(&self, socket: &Arc<IpRawSocketBg<E>>) {
+        let mut sockets = self.sockets.lock();
+        let removed = sockets.remove_raw_socket(socket);
+        debug_assert!(removed.is_some());
+    }
+
+    pub(crate) fn register_link_socket(&self, socket: Arc<LinkSocketBg<E>>) {
+        self.link_sockets.lock().push(socket);
+    }
+
+    pub(crate) fn remove_link_socket(&self, socket: &Arc<LinkSocketBg<E>>) {
+        let mut link_sockets = self.link_sockets.lock();
+        let pos = link_sockets
+            .iter()
+            .position(|link_socket| Arc::ptr_eq(link_socket, socket));
+        debug_assert!(pos.is_some());
+        if let Some(pos) = pos {
+            link_sockets.swap_remove(pos);
+        }
+    }
 }
 
+impl<E: Ext> IfaceCommon<E> {
+    pub(super) fn enter_promisc(&self) {
+        self.promisc_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn leave_promisc(&self) {
+        let old_count = self.promisc_count.fetch_sub(1, Ordering::Relaxed);
+        debug_assert_ne!(old_count, 0);
+    }
+
+    fn is_promisc(&self) -> bool {
+        self.promisc_count.load(Ordering::Relaxed) != 0
+    }
+
+    /// Delivers a frame to link-layer sockets.
+    ///
+    /// This should be called for every frame received from the device (where `sender` is `None`)
+    /// and every frame transmitted to the device (where `sender` is the link-layer socket that
+    /// sends the frame, if any). The sender does not receive its own frames.
+    pub(super) fn process_link_frame(
+        &self,
+        frame: &[u8],
+        sender: Option<&Arc<LinkSocketBg<E>>>,
+        is_outgoing: bool,
+    ) {
+        let link_sockets = self.link_sockets.lock();
+        if link_sockets.is_empty() {
+            return;
+        }
+
+        let Some((meta, header_len)) = self.parse_link_frame(frame, is_outgoing) else {
+            return;
+        };
+        // FIXME: We do not ask the device to enter promiscuous mode. Frames sent to others will
+        // only be seen if the device delivers them anyway (which QEMU does by default).
+        if meta.pkt_type == LinkPacketType::OtherHost && !self.is_promisc() {
+            return;
+        }
+
+        for socket in link_sockets.iter() {
+            if sender.is_some_and(|sender| Arc::ptr_eq(sender, socket)) {
+                continue;
+            }
+            if socket.can_process(&meta) {
+                socket.process(frame, &meta, header_len);
+            }
+        }
+    }
+
+    /// Parses the metadata and the link-layer header length of a frame.
+    fn parse_link_frame(&self, frame: &[u8], is_outgoing: bool) -> Option<(LinkFrameMeta, usize)> {
+        let Some(ether_addr) = self.ether_addr else {
+            // There are no link-layer headers. The frame is an IP packet.
+            let protocol = match IpVersion::of_packet(frame).ok()? {
+                IpVersion::Ipv4 => ETH_P_IP,
+                IpVersion::Ipv6 => ETH_P_IPV6,
+            };
+            let pkt_type = if is_outgoing {
+                LinkPacketType::Outgoing
+            } else {
+                LinkPacketType::Host
+            };
+            let meta = LinkFrameMeta {
+                protocol,
+                pkt_type,
+                src_addr: None,
+            };
+            return Some((meta, 0));
+        };
+
+        let ether_frame = EthernetFrame::new_checked(frame).ok()?;
+        let dst_addr = ether_frame.dst_addr();
+        let pkt_type = if is_outgoing {
+            LinkPacketType::Outgoing
+        } else if dst_addr == ether_addr {
+            LinkPacketType::Host
+        } else if dst_addr.is_broadcast() {
+            LinkPacketType::Broadcast
+        } else if dst_addr.is_multicast() {
+            LinkPacketType::Multicast
+        } else {
+            LinkPacketType::OtherHost
+        };
+        let meta = LinkFrameMeta {
+            protocol: u16::from(ether_frame.ethertype()),
+            pkt_type,
+            src_addr: Some(ether_frame.src_addr()),
+        };
+
+        Some((meta, ETHERNET_HEADER_LEN))
+    }
+
+    /// Wraps the TX token so that the transmitted frame will be delivered to link-layer sockets.
+    pub(super) fn capture<'a, T: TxToken>(
+        &'a self,
+        tx_token: T,
+        sender: Option<&'a Arc<LinkSocketBg<E>>>,
+    ) -> CaptureTxToken<'a, T, E> {
+        CaptureTxToken {
+            inner: tx_token,
+            common: self,
+            sender,
+        }
+    }
+
+    /// Transmits frames that are sent by link-layer sockets.
+    fn dispatch_link_frames<D: Device + ?Sized>(&self, device: &mut D, now: Instant) {
+        let senders: Vec<_> = self
+            .link_sockets
+            .lock()
+            .iter()
+            .filter(|socket| socket.need_dispatch())
+            .cloned()
+            .collect();
+
+        for sender in senders.iter() {
+            while sender.need_dispatch() {
+                let Some(tx_token) = device.transmit(now) else {
+                    return;
+                };
+                let Some(frame) = sender.dispatch() else {
+                    break;
+                };
+
+                self.capture(tx_token, Some(sender))
+                    .consume(frame.len(), |buffer| buffer.copy_from_slice(&frame));
+            }
+        }
+    }
+}
+
+/// A TX token that delivers the transmitted frame to link-layer sockets.
+pub(super) struct CaptureTxToken<'a, T, E: Ext> {
+    inner: T,
+    common: &'a IfaceCommon<E>,
+    sender: Option<&'a Arc<LinkSocketBg<E>>>,
+}
+
+impl<T: TxToken, E: Ext> TxToken for CaptureTxToken<'_, T, E> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let Self {
+            inner,
+            common,
+            sender,
+        } = self;
+
+        inner.consume(len, |buffer| {
+            let res = f(buffer);
+            common.process_link_frame(buffer, sender, true);
+            res
+        })
+    }
+}
+
+// Link-layer protocols (i.e., EtherTypes) of IP packets:
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86DD;
+
 impl<E: Ext> IfaceCommon<E> {
     pub(super) fn poll<D, P, Q>(
         &self,
@@ -211,10 +534,16 @@ impl<E: Ext> IfaceCommon<E> {
             &'pkt [u8],
             &'cx mut Context,
             D::TxToken<'tx>,
-            Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
+            Option<(IpPacket<&'pkt [u8]>, D::TxToken<'tx>)>,
         >,
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
+        // FIXME: Packets are left in the device queues when the iface is down. We should drop
+        // them as Linux does.
+        if !self.is_up() {
+            return None;
+        }
+
         let mut interface = self.interface();
         interface.context_mut().now = get_network_timestamp();
 
@@ -225,6 +554,9 @@ impl<E: Ext> IfaceCommon<E> {
         context.poll_ingress(device, &mut process_phy, &mut dispatch_phy);
         context.poll_egress(device, &mut dispatch_phy);
 
+        let now = interface.context_mut().now;
+        self.dispatch_link_frames(device, now);
+
         // Insert new connections and remove dead connections.
         for action in socket_actions.into_iter() {
             match action {
@@ -251,6 +583,7 @@ impl<E: Ext> IfaceCommon<E> {
 // FIXME: TCP and UDP ports are independent. Find a way to track the protocol here.
 pub struct BoundPort<E: Ext> {
     iface: Arc<dyn Iface<E>>,
+    ip_version: IpVersion,
     port: u16,
 }
 
@@ -260,6 +593,11 @@ impl<E: Ext> BoundPort<E> {
         &self.iface
     }
 
+    /// Returns the IP version of the bound endpoint.
+    pub fn ip_version(&self) -> IpVersion {
+        self.ip_version
+    }
+
     /// Returns the port number.
     pub fn port(&self) -> u16 {
         self.port
@@ -267,9 +605,9 @@ impl<E: Ext> BoundPort<E> {
 
     /// Returns the bound endpoint.
     pub fn endpoint(&self) -> Option<IpEndpoint> {
-        let ip_addr = {
-            let ipv4_addr = self.iface().ipv4_addr()?;
-            IpAddress::Ipv4(ipv4_addr)
+        let ip_addr = match self.ip_version {
+            IpVersion::Ipv4 => IpAddress::Ipv4(self.iface().ipv4_addr()?),
+            IpVersion::Ipv6 => IpAddress::Ipv6(self.iface().ipv6_addr()?),
         };
         Some(IpEndpoint::new(ip_addr, self.port))
     }
@@ -308,6 +646,8 @@ pub enum InterfaceType {
     LOOPBACK = 772,
     /// Localtalk device
     LOCALTALK = 773,
+    /// Zero header length
+    NONE = 0xFFFE,
     // TODO: This enum is not exhaustive
 }
 
@@ -1,11 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 
-use smoltcp::wire::Ipv4Address;
+use smoltcp::wire::{EthernetAddress, IpCidr, IpVersion, Ipv4Address, Ipv6Address};
 
-use super::{port::BindPortConfig, BoundPort, InterfaceFlags, InterfaceType};
-use crate::{errors::BindError, ext::Ext};
+use super::{port::BindPortConfig, BoundPort, InterfaceFlags, InterfaceType, Route};
+use crate::{
+    errors::{BindError, IfaceConfigError},
+    ext::Ext,
+    socket::NeedIfacePoll,
+};
 
 /// A network interface.
 ///
@@ -17,15 +21,15 @@ pub trait Iface<E>: internal::IfaceInternal<E> + Send + Sync {
     /// Transmits or receives packets queued in the iface, and updates socket status accordingly.
     fn poll(&self);
 
-    /// Returns the maximum transmission unit.
-    fn mtu(&self) -> usize;
+    /// Returns the largest maximum transmission unit that the device supports.
+    fn max_mtu(&self) -> usize;
 }
 
 impl<E: Ext> dyn Iface<E> {
     /// Binds a socket to the iface.
     ///
     /// After binding the socket to the iface, the iface will handle all packets to and from the
-    /// socket.
+    /// socket. The socket will use the address of the iface whose version is `ip_version`.
     ///
     /// If [`BindPortConfig::Ephemeral`] is specified, the iface will pick up an ephemeral port for
     /// the socket.
@@ -35,10 +39,11 @@ impl<E: Ext> dyn Iface<E> {
     /// <https://github.com/smoltcp-rs/smoltcp/issues/779>.
     pub fn bind(
         self: &Arc<Self>,
+        ip_version: IpVersion,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
         let common = self.common();
-        common.bind(self.clone(), config)
+        common.bind(self.clone(), ip_version, config)
     }
 
     /// Returns the interface index.
@@ -63,6 +68,73 @@ impl<E: Ext> dyn Iface<E> {
         self.common().flags()
     }
 
+    /// Brings the iface up or down.
+    ///
+    /// No packets are transmitted or received while the iface is down.
+    pub fn set_up(&self, is_up: bool) {
+        self.common().set_up(is_up);
+    }
+
+    /// Returns the maximum transmission unit.
+    pub fn mtu(&self) -> usize {
+        self.common().mtu().unwrap_or_else(|| self.max_mtu())
+    }
+
+    /// Sets the maximum transmission unit.
+    ///
+    /// The caller must ensure that `mtu` is no larger than [`Iface::max_mtu`].
+    ///
+    /// FIXME: The MTU only limits the packets sent by raw sockets and link-layer sockets. The TCP
+    /// and UDP sockets in smoltcp still use the MTU of the device.
+    pub fn set_mtu(&self, mtu: usize) {
+        debug_assert!(mtu <= self.max_mtu());
+        self.common().set_mtu(mtu);
+    }
+
+    /// Gets the hardware address of the iface, if any.
+    ///
+    /// Ifaces that have no link-layer headers (e.g., the loopback iface) have no hardware
+    /// addresses.
+    pub fn hw_addr(&self) -> Option<EthernetAddress> {
+        self.common().ether_addr()
+    }
+
+    /// Enters the promiscuous mode.
+    ///
+    /// In the promiscuous mode, frames that are sent to other hosts are delivered to link-layer
+    /// sockets. The iface stays in the promiscuous mode until [`Self::leave_promisc`] is called
+    /// as many times as this method is called.
+    pub fn enter_promisc(&self) {
+        self.common().enter_promisc();
+    }
+
+    /// Leaves the promiscuous mode.
+    ///
+    /// See [`Self::enter_promisc`] for details.
+    pub fn leave_promisc(&self) {
+        self.common().leave_promisc();
+    }
+
+    /// Joins an IPv4 multicast group.
+    ///
+    /// The iface receives packets sent to the group until [`Self::leave_multicast_group`] is
+    /// called as many times as this method is called. When the group is joined for the first
+    /// time, a membership report is sent to multicast routers.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    pub fn join_multicast_group(&self, group: Ipv4Address) -> NeedIfacePoll {
+        self.common().join_multicast_group(group)
+    }
+
+    /// Leaves an IPv4 multicast group.
+    ///
+    /// See [`Self::join_multicast_group`] for details.
+    ///
+    /// Polling the iface _may_ be required after this method succeeds.
+    pub fn leave_multicast_group(&self, group: Ipv4Address) -> NeedIfacePoll {
+        self.common().leave_multicast_group(group)
+    }
+
     /// Gets the IPv4 address of the iface, if any.
     ///
     /// FIXME: One iface may have multiple IPv4 addresses.
@@ -78,6 +150,48 @@ impl<E: Ext> dyn Iface<E> {
         self.common().prefix_len()
     }
 
+    /// Gets the IPv6 address of the iface, if any.
+    ///
+    /// FIXME: One iface may have multiple IPv6 addresses.
+    pub fn ipv6_addr(&self) -> Option<Ipv6Address> {
+        self.common().ipv6_addr()
+    }
+
+    /// Returns all IP addresses of the iface.
+    pub fn ip_addrs(&self) -> Vec<IpCidr> {
+        self.common().ip_addrs()
+    }
+
+    /// Adds an IP address to the iface.
+    pub fn add_ip_addr(&self, ip_cidr: IpCidr) -> Result<(), IfaceConfigError> {
+        self.common().add_ip_addr(ip_cidr)
+    }
+
+    /// Removes an IP address from the iface.
+    ///
+    /// Sockets that are bound to the address are _not_ closed.
+    pub fn remove_ip_addr(&self, ip_cidr: IpCidr) -> Result<(), IfaceConfigError> {
+        self.common().remove_ip_addr(ip_cidr)
+    }
+
+    /// Returns all routes of the iface.
+    pub fn routes(&self) -> Vec<Route> {
+        self.common().routes()
+    }
+
+    /// Adds a route to the iface.
+    ///
+    /// This method fails with [`IfaceConfigError::Exists`] if there is already a route to the same
+    /// destination subnet.
+    pub fn add_route(&self, route: Route) -> Result<(), IfaceConfigError> {
+        self.common().add_route(route)
+    }
+
+    /// Removes a route from the iface.
+    pub fn remove_route(&self, route: &Route) -> Result<(), IfaceConfigError> {
+        self.common().remove_route(route)
+    }
+
     /// Returns a reference to the associated [`ScheduleNextPoll`].
     pub fn sched_poll(&self) -> &E::ScheduleNextPoll {
         self.common().sched_poll()
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! IPv4 multicast group management.
+//!
+//! Group memberships are reported to multicast routers with IGMPv2 messages. See
+//! <https://datatracker.ietf.org/doc/html/rfc2236>.
+
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    vec::Vec,
+};
+
+use smoltcp::wire::Ipv4Address;
+
+/// The IPv4 multicast groups joined by an iface.
+pub(crate) struct MulticastGroups {
+    /// The joined groups and the number of times each group is joined.
+    groups: BTreeMap<Ipv4Address, usize>,
+    /// The IGMP messages that are waiting to be sent.
+    pending: VecDeque<IgmpMessage>,
+}
+
+/// An outgoing IGMP message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum IgmpMessage {
+    /// A Version 2 Membership Report.
+    Report(Ipv4Address),
+    /// A Leave Group message.
+    Leave(Ipv4Address),
+}
+
+/// The all-systems multicast group, which every host joins implicitly.
+const ALL_SYSTEMS: Ipv4Address = Ipv4Address::new(224, 0, 0, 1);
+/// The all-routers multicast group, to which Leave Group messages are sent.
+const ALL_ROUTERS: Ipv4Address = Ipv4Address::new(224, 0, 0, 2);
+
+/// The length of an IGMPv2 message.
+pub(super) const IGMP_MESSAGE_LEN: usize = 8;
+/// The hop limit of IGMP messages.
+pub(super) const IGMP_HOP_LIMIT: u8 = 1;
+
+// IGMP message types:
+const IGMP_MEMBERSHIP_QUERY: u8 = 0x11;
+const IGMP_V2_MEMBERSHIP_REPORT: u8 = 0x16;
+const IGMP_LEAVE_GROUP: u8 = 0x17;
+
+impl MulticastGroups {
+    pub(super) const fn new() -> Self {
+        Self {
+            groups: BTreeMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Joins the multicast group.
+    ///
+    /// This method returns whether a membership report is queued, i.e., whether the group is
+    /// joined for the first time.
+    pub(super) fn join(&mut self, group: Ipv4Address) -> bool {
+        debug_assert!(group.is_multicast());
+
+        let count = self.groups.entry(group).or_insert(0);
+        *count += 1;
+        if *count != 1 || group == ALL_SYSTEMS {
+            return false;
+        }
+
+        // Linux sends the unsolicited report more than once in case it is lost. We only send it
+        // once for simplicity.
+        self.queue(IgmpMessage::Report(group));
+        true
+    }
+
+    /// Leaves the multicast group.
+    ///
+    /// This method returns whether a Leave Group message is queued, i.e., whether the group is
+    /// left for the last time.
+    pub(super) fn leave(&mut self, group: Ipv4Address) -> bool {
+        debug_assert!(self.groups.contains_key(&group));
+        let Some(count) = self.groups.get_mut(&group) else {
+            return false;
+        };
+
+        *count -= 1;
+        if *count != 0 {
+            return false;
+        }
+        self.groups.remove(&group);
+
+        if group == ALL_SYSTEMS {
+            return false;
+        }
+
+        self.pending
+            .retain(|message| *message != IgmpMessage::Report(group));
+        self.queue(IgmpMessage::Leave(group));
+        true
+    }
+
+    /// Returns whether packets sent to the multicast group should be received.
+    pub(super) fn contains(&self, group: Ipv4Address) -> bool {
+        group == ALL_SYSTEMS || self.groups.contains_key(&group)
+    }
+
+    /// Processes an incoming IGMP message.
+    ///
+    /// Membership queries are answered with membership reports, which will be sent when the iface
+    /// is polled. Other messages are ignored.
+    pub(super) fn process(&mut self, data: &[u8]) {
+        if data.len() < IGMP_MESSAGE_LEN || checksum(data) != 0 {
+            return;
+        }
+        if data[0] != IGMP_MEMBERSHIP_QUERY {
+            return;
+        }
+
+        // TODO: Delay the reports by a random time no longer than the maximum response time, so
+        // that reports from other hosts can suppress our reports.
+        let group = Ipv4Address::new(data[4], data[5], data[6], data[7]);
+        if group.is_unspecified() {
+            // This is a General Query, which asks for all groups.
+            let groups: Vec<_> = self
+                .groups
+                .keys()
+                .filter(|group| **group != ALL_SYSTEMS)
+                .copied()
+                .collect();
+            for group in groups {
+                self.queue(IgmpMessage::Report(group));
+            }
+        } else if group != ALL_SYSTEMS && self.groups.contains_key(&group) {
+            // This is a Group-Specific Query.
+            self.queue(IgmpMessage::Report(group));
+        }
+    }
+
+    /// Takes the next IGMP message to send.
+    pub(super) fn pop_pending(&mut self) -> Option<IgmpMessage> {
+        self.pending.pop_front()
+    }
+
+    fn queue(&mut self, message: IgmpMessage) {
+        if !self.pending.contains(&message) {
+            self.pending.push_back(message);
+        }
+    }
+}
+
+impl IgmpMessage {
+    /// Returns the destination address of the IGMP message.
+    pub(super) fn dst_addr(&self) -> Ipv4Address {
+        match self {
+            Self::Report(group) => *group,
+            Self::Leave(_) => ALL_ROUTERS,
+        }
+    }
+
+    /// Emits the IGMP message.
+    pub(super) fn emit(&self) -> [u8; IGMP_MESSAGE_LEN] {
+        let (type_, group) = match self {
+            Self::Report(group) => (IGMP_V2_MEMBERSHIP_REPORT, group),
+            Self::Leave(group) => (IGMP_LEAVE_GROUP, group),
+        };
+
+        let mut data = [0; IGMP_MESSAGE_LEN];
+        data[0] = type_;
+        data[4..8].copy_from_slice(&group.octets());
+        let checksum = checksum(&data);
+        data[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+        data
+    }
+}
+
+/// Computes the Internet checksum.
+///
+/// If the data contains a valid checksum, the result will be zero. See
+/// <https://datatracker.ietf.org/doc/html/rfc1071>.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]) as u32)
+        .sum::<u32>();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
@@ -3,10 +3,12 @@
 mod common;
 #[expect(clippy::module_inception)]
 mod iface;
+mod igmp;
 mod phy;
 mod poll;
 mod poll_iface;
 mod port;
+mod route;
 mod sched;
 mod time;
 
@@ -15,4 +17,5 @@ pub use iface::Iface;
 pub use phy::{EtherIface, IpIface};
 pub(crate) use poll_iface::{PollKey, PollableIfaceMut};
 pub use port::BindPortConfig;
+pub use route::Route;
 pub use sched::ScheduleNextPoll;
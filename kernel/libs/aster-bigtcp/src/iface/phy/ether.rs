@@ -5,11 +5,16 @@ use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc};
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
-    iface::{packet::Packet, Config, Context},
-    phy::{Device, DeviceCapabilities, TxToken},
+    iface::{
+        packet::{IpPayload, Packet},
+        Config, Context,
+    },
+    phy::{Device, DeviceCapabilities, Medium, TxToken},
     wire::{
         self, ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetFrame, EthernetProtocol,
-        EthernetRepr, IpAddress, Ipv4Address, Ipv4AddressExt, Ipv4Cidr, Ipv4Packet,
+        EthernetRepr, HardwareAddress, Icmpv6Packet, Icmpv6Repr, IpAddress, IpProtocol,
+        Ipv4Address, Ipv4AddressExt, Ipv4Cidr, Ipv4Packet, Ipv6Address, Ipv6Cidr, Ipv6Packet,
+        Ipv6Repr, NdiscNeighborFlags, NdiscRepr, RawHardwareAddress,
     },
 };
 
@@ -19,6 +24,7 @@ use crate::{
     iface::{
         common::{IfaceCommon, InterfaceType},
         iface::internal::IfaceInternal,
+        poll::IpPacket,
         time::get_network_timestamp,
         Iface, InterfaceFlags, ScheduleNextPoll,
     },
@@ -29,14 +35,19 @@ pub struct EtherIface<D, E: Ext> {
     common: IfaceCommon<E>,
     ether_addr: EthernetAddress,
     arp_table: SpinLock<BTreeMap<Ipv4Address, EthernetAddress>, BottomHalfDisabled>,
+    ndisc_table: SpinLock<BTreeMap<Ipv6Address, EthernetAddress>, BottomHalfDisabled>,
 }
 
 impl<D: WithDevice, E: Ext> EtherIface<D, E> {
+    /// Creates an Ethernet iface.
+    ///
+    /// The IPv4 address and the default IPv4 gateway are optional, since they can be configured
+    /// later. The link-local IPv6 address is always configured according to `ether_addr`.
     pub fn new(
         driver: D,
         ether_addr: EthernetAddress,
-        ip_cidr: Ipv4Cidr,
-        gateway: Ipv4Address,
+        ip_cidr: Option<Ipv4Cidr>,
+        gateway: Option<Ipv4Address>,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         flags: InterfaceFlags,
@@ -48,12 +59,22 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             let mut interface = smoltcp::iface::Interface::new(config, device, now);
             interface.update_ip_addrs(|ip_addrs| {
                 debug_assert!(ip_addrs.is_empty());
-                ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                if let Some(ip_cidr) = ip_cidr {
+                    ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                }
+                ip_addrs
+                    .push(wire::IpCidr::Ipv6(Ipv6Cidr::new(
+                        link_local_ipv6_addr(ether_addr),
+                        LINK_LOCAL_PREFIX_LEN,
+                    )))
+                    .unwrap();
             });
-            interface
-                .routes_mut()
-                .add_default_ipv4_route(gateway)
-                .unwrap();
+            if let Some(gateway) = gateway {
+                interface
+                    .routes_mut()
+                    .add_default_ipv4_route(gateway)
+                    .unwrap();
+            }
             interface
         });
 
@@ -64,6 +85,7 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             common,
             ether_addr,
             arp_table: SpinLock::new(BTreeMap::new()),
+            ndisc_table: SpinLock::new(BTreeMap::new()),
         })
     }
 }
@@ -83,14 +105,16 @@ where
             let next_poll = self.common.poll(
                 &mut *device,
                 |data, iface_cx, tx_token| self.process(data, iface_cx, tx_token),
-                |pkt, iface_cx, tx_token| self.dispatch(pkt, iface_cx, tx_token),
+                |pkt, iface_cx, tx_token| {
+                    self.dispatch(pkt, iface_cx, self.common.capture(tx_token, None))
+                },
             );
             device.notify_poll_end();
             self.common.sched_poll().schedule_next_poll(next_poll);
         });
     }
 
-    fn mtu(&self) -> usize {
+    fn max_mtu(&self) -> usize {
         self.driver
             .with(|device| device.capabilities().max_transmission_unit)
     }
@@ -102,40 +126,51 @@ impl<D, E: Ext> EtherIface<D, E> {
         data: &'pkt [u8],
         iface_cx: &mut Context,
         tx_token: T,
-    ) -> Option<(Ipv4Packet<&'pkt [u8]>, T)> {
-        match self.parse_ip_or_process_arp(data, iface_cx) {
+    ) -> Option<(IpPacket<&'pkt [u8]>, T)> {
+        self.common.process_link_frame(data, None, false);
+
+        match self.parse_ip_or_process_control(data, iface_cx) {
             Ok(pkt) => Some((pkt, tx_token)),
-            Err(Some(arp)) => {
-                Self::emit_arp(&arp, tx_token);
+            Err(Some(control)) => {
+                let tx_token = self.common.capture(tx_token, None);
+                self.emit_control(&control, &iface_cx.caps, tx_token);
                 None
             }
             Err(None) => None,
         }
     }
 
-    fn parse_ip_or_process_arp<'pkt>(
+    fn parse_ip_or_process_control<'pkt>(
         &self,
         data: &'pkt [u8],
         iface_cx: &mut Context,
-    ) -> Result<Ipv4Packet<&'pkt [u8]>, Option<ArpRepr>> {
+    ) -> Result<IpPacket<&'pkt [u8]>, Option<Control>> {
         // Parse the Ethernet header. Ignore the packet if the header is ill-formed.
         let frame = EthernetFrame::new_checked(data).map_err(|_| None)?;
         let repr = EthernetRepr::parse(&frame).map_err(|_| None)?;
 
-        // Ignore the Ethernet frame if it is not sent to us.
-        if !repr.dst_addr.is_broadcast() && repr.dst_addr != self.ether_addr {
+        // Ignore the Ethernet frame if it is not sent to us. Note that multicast frames include
+        // broadcast frames.
+        if !repr.dst_addr.is_multicast() && repr.dst_addr != self.ether_addr {
             return Err(None);
         }
 
         // Ignore the Ethernet frame if the protocol is not supported.
         match repr.ethertype {
-            EthernetProtocol::Ipv4 => {
-                Ok(Ipv4Packet::new_checked(frame.payload()).map_err(|_| None)?)
+            EthernetProtocol::Ipv4 => Ok(IpPacket::Ipv4(
+                Ipv4Packet::new_checked(frame.payload()).map_err(|_| None)?,
+            )),
+            EthernetProtocol::Ipv6 => {
+                let pkt = Ipv6Packet::new_checked(frame.payload()).map_err(|_| None)?;
+                match self.try_process_ndisc(&pkt, iface_cx) {
+                    Some(control) => Err(control),
+                    None => Ok(IpPacket::Ipv6(pkt)),
+                }
             }
             EthernetProtocol::Arp => {
                 let pkt = ArpPacket::new_checked(frame.payload()).map_err(|_| None)?;
                 let arp = ArpRepr::parse(&pkt).map_err(|_| None)?;
-                Err(self.process_arp(&arp, iface_cx))
+                Err(self.process_arp(&arp, iface_cx).map(Control::Arp))
             }
             _ => Err(None),
         }
@@ -197,23 +232,147 @@ impl<D, E: Ext> EtherIface<D, E> {
         }
     }
 
+    /// Processes the packet if it is a Neighbor Discovery message.
+    ///
+    /// This returns `None` if the packet is not a Neighbor Discovery message, in which case the
+    /// packet should be processed by the IP layer. Otherwise, this returns `Some(_)` with the
+    /// reply, if any.
+    fn try_process_ndisc(
+        &self,
+        pkt: &Ipv6Packet<&[u8]>,
+        iface_cx: &mut Context,
+    ) -> Option<Option<Control>> {
+        let ipv6_repr = Ipv6Repr::parse(pkt).ok()?;
+        if ipv6_repr.next_header != IpProtocol::Icmpv6 {
+            return None;
+        }
+
+        let icmp_pkt = Icmpv6Packet::new_checked(pkt.payload()).ok()?;
+        let icmp_repr = Icmpv6Repr::parse(
+            &ipv6_repr.src_addr,
+            &ipv6_repr.dst_addr,
+            &icmp_pkt,
+            &iface_cx.checksum_caps(),
+        )
+        .ok()?;
+        let Icmpv6Repr::Ndisc(ndisc_repr) = icmp_repr else {
+            return None;
+        };
+
+        // Ignore the Neighbor Discovery message if it may come from another link. See
+        // <https://datatracker.ietf.org/doc/html/rfc4861#section-7.1>.
+        if ipv6_repr.hop_limit != NDISC_HOP_LIMIT {
+            return Some(None);
+        }
+
+        Some(self.process_ndisc(&ipv6_repr, &ndisc_repr, iface_cx))
+    }
+
+    fn process_ndisc(
+        &self,
+        ipv6_repr: &Ipv6Repr,
+        ndisc_repr: &NdiscRepr,
+        iface_cx: &mut Context,
+    ) -> Option<Control> {
+        match ndisc_repr {
+            NdiscRepr::NeighborAdvert {
+                target_addr,
+                lladdr,
+                ..
+            } => {
+                // Ignore the advertisement if it does not carry a unicast Ethernet address.
+                let target_ether = lladdr.and_then(parse_ether_addr)?;
+                if !target_ether.is_unicast() {
+                    return None;
+                }
+
+                // Insert the mapping between the Ethernet address and the IP address.
+                //
+                // TODO: Remove the mapping if it expires.
+                self.ndisc_table.lock().insert(*target_addr, target_ether);
+
+                None
+            }
+            NdiscRepr::NeighborSolicit {
+                target_addr,
+                lladdr,
+            } => {
+                // Ignore the solicitation for duplicate address detection, which has no source
+                // address.
+                //
+                // TODO: Reply to the solicitation if the address is tentative.
+                if ipv6_repr.src_addr.is_unspecified() {
+                    return None;
+                }
+
+                // Ignore the solicitation if it does not carry a unicast Ethernet address.
+                let source_ether = lladdr.and_then(parse_ether_addr)?;
+                if !source_ether.is_unicast() {
+                    return None;
+                }
+
+                // Ignore the solicitation if we do not own the target address.
+                if iface_cx.ipv6_addr().is_none_or(|addr| addr != *target_addr) {
+                    return None;
+                }
+
+                self.ndisc_table
+                    .lock()
+                    .insert(ipv6_repr.src_addr, source_ether);
+
+                let advert = NdiscRepr::NeighborAdvert {
+                    flags: NdiscNeighborFlags::SOLICITED | NdiscNeighborFlags::OVERRIDE,
+                    target_addr: *target_addr,
+                    lladdr: Some(RawHardwareAddress::from_bytes(self.ether_addr.as_bytes())),
+                };
+                Some(Control::Ndisc(
+                    source_ether,
+                    new_ndisc_packet(*target_addr, ipv6_repr.src_addr, advert),
+                ))
+            }
+            _ => None,
+        }
+    }
+
     fn dispatch<T: TxToken>(&self, pkt: &Packet, iface_cx: &mut Context, tx_token: T) {
-        match self.resolve_ether_or_generate_arp(pkt, iface_cx) {
+        match self.resolve_ether_or_generate_control(pkt, iface_cx) {
             Ok(ether) => Self::emit_ip(&ether, pkt, &iface_cx.caps, tx_token),
-            Err(Some(arp)) => Self::emit_arp(&arp, tx_token),
+            Err(Some(control)) => self.emit_control(&control, &iface_cx.caps, tx_token),
             Err(None) => (),
         }
     }
 
-    fn resolve_ether_or_generate_arp(
+    fn resolve_ether_or_generate_control(
         &self,
         pkt: &Packet,
         iface_cx: &mut Context,
+    ) -> Result<EthernetRepr, Option<Control>> {
+        match pkt.ip_repr().dst_addr() {
+            IpAddress::Ipv4(dst_addr) => self
+                .resolve_ether_or_generate_arp(dst_addr, iface_cx)
+                .map_err(|arp| arp.map(Control::Arp)),
+            IpAddress::Ipv6(dst_addr) => self.resolve_ether_or_generate_ndisc(dst_addr, iface_cx),
+        }
+    }
+
+    fn resolve_ether_or_generate_arp(
+        &self,
+        dst_addr: Ipv4Address,
+        iface_cx: &mut Context,
     ) -> Result<EthernetRepr, Option<ArpRepr>> {
+        // Multicast IPv4 addresses are mapped to Ethernet addresses directly.
+        if dst_addr.is_multicast() {
+            return Ok(EthernetRepr {
+                src_addr: self.ether_addr,
+                dst_addr: ipv4_multicast_ether_addr(dst_addr),
+                ethertype: EthernetProtocol::Ipv4,
+            });
+        }
+
         // Resolve the next-hop IP address.
-        let next_hop_ip = match iface_cx.route(&pkt.ip_repr().dst_addr(), iface_cx.now()) {
+        let next_hop_ip = match iface_cx.route(&IpAddress::Ipv4(dst_addr), iface_cx.now()) {
             Some(IpAddress::Ipv4(next_hop_ip)) => next_hop_ip,
-            None => return Err(None),
+            _ => return Err(None),
         };
 
         // Resolve the next-hop Ethernet address.
@@ -241,6 +400,53 @@ impl<D, E: Ext> EtherIface<D, E> {
         })
     }
 
+    fn resolve_ether_or_generate_ndisc(
+        &self,
+        dst_addr: Ipv6Address,
+        iface_cx: &mut Context,
+    ) -> Result<EthernetRepr, Option<Control>> {
+        // Multicast IPv6 addresses are mapped to Ethernet addresses directly.
+        if dst_addr.is_multicast() {
+            return Ok(EthernetRepr {
+                src_addr: self.ether_addr,
+                dst_addr: ipv6_multicast_ether_addr(dst_addr),
+                ethertype: EthernetProtocol::Ipv6,
+            });
+        }
+
+        // Resolve the next-hop IP address.
+        let next_hop_ip = match iface_cx.route(&IpAddress::Ipv6(dst_addr), iface_cx.now()) {
+            Some(IpAddress::Ipv6(next_hop_ip)) => next_hop_ip,
+            _ => return Err(None),
+        };
+
+        // Resolve the next-hop Ethernet address.
+        let next_hop_ether = if let Some(next_hop_ether) = self.ndisc_table.lock().get(&next_hop_ip)
+        {
+            *next_hop_ether
+        } else {
+            // Like ARP, if the next-hop Ethernet address cannot be resolved, we drop the original
+            // packet and send a Neighbor Solicitation message to the solicited-node multicast
+            // address instead.
+            let src_addr = iface_cx.ipv6_addr().ok_or(None)?;
+            let solicited_node = solicited_node_addr(next_hop_ip);
+            let solicit = NdiscRepr::NeighborSolicit {
+                target_addr: next_hop_ip,
+                lladdr: Some(RawHardwareAddress::from_bytes(self.ether_addr.as_bytes())),
+            };
+            return Err(Some(Control::Ndisc(
+                ipv6_multicast_ether_addr(solicited_node),
+                new_ndisc_packet(src_addr, solicited_node, solicit),
+            )));
+        };
+
+        Ok(EthernetRepr {
+            src_addr: self.ether_addr,
+            dst_addr: next_hop_ether,
+            ethertype: EthernetProtocol::Ipv6,
+        })
+    }
+
     /// Consumes the token and emits an IP packet.
     fn emit_ip<T: TxToken>(
         ether_repr: &EthernetRepr,
@@ -265,6 +471,21 @@ impl<D, E: Ext> EtherIface<D, E> {
         );
     }
 
+    /// Consumes the token and emits a control packet.
+    fn emit_control<T: TxToken>(&self, control: &Control, caps: &DeviceCapabilities, tx_token: T) {
+        match control {
+            Control::Arp(arp_repr) => Self::emit_arp(arp_repr, tx_token),
+            Control::Ndisc(dst_addr, ip_pkt) => {
+                let ether_repr = EthernetRepr {
+                    src_addr: self.ether_addr,
+                    dst_addr: *dst_addr,
+                    ethertype: EthernetProtocol::Ipv6,
+                };
+                Self::emit_ip(&ether_repr, ip_pkt, caps, tx_token);
+            }
+        }
+    }
+
     /// Consumes the token and emits an ARP packet.
     fn emit_arp<T: TxToken>(arp_repr: &ArpRepr, tx_token: T) {
         let ether_repr = match arp_repr {
@@ -289,3 +510,93 @@ impl<D, E: Ext> EtherIface<D, E> {
         });
     }
 }
+
+/// A control packet that is generated by the link layer.
+enum Control {
+    /// An ARP packet.
+    Arp(ArpRepr),
+    /// A Neighbor Discovery packet and its destination Ethernet address.
+    Ndisc(EthernetAddress, Packet<'static>),
+}
+
+/// The prefix length of link-local IPv6 addresses.
+const LINK_LOCAL_PREFIX_LEN: u8 = 64;
+
+/// The hop limit of Neighbor Discovery messages.
+const NDISC_HOP_LIMIT: u8 = 255;
+
+/// Generates the link-local IPv6 address from the Ethernet address.
+///
+/// The interface identifier is in the modified EUI-64 format. See
+/// <https://datatracker.ietf.org/doc/html/rfc4291#appendix-A>.
+fn link_local_ipv6_addr(ether_addr: EthernetAddress) -> Ipv6Address {
+    let mac = ether_addr.0;
+    Ipv6Address::new(
+        0xfe80,
+        0,
+        0,
+        0,
+        u16::from_be_bytes([mac[0] ^ 0x02, mac[1]]),
+        u16::from_be_bytes([mac[2], 0xff]),
+        u16::from_be_bytes([0xfe, mac[3]]),
+        u16::from_be_bytes([mac[4], mac[5]]),
+    )
+}
+
+/// Returns the solicited-node multicast address of the IPv6 address.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc4291#section-2.7.1>.
+fn solicited_node_addr(addr: Ipv6Address) -> Ipv6Address {
+    let octets = addr.octets();
+    Ipv6Address::new(
+        0xff02,
+        0,
+        0,
+        0,
+        0,
+        1,
+        u16::from_be_bytes([0xff, octets[13]]),
+        u16::from_be_bytes([octets[14], octets[15]]),
+    )
+}
+
+/// Maps the multicast IPv4 address to the Ethernet address.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc1112#section-6.4>.
+fn ipv4_multicast_ether_addr(addr: Ipv4Address) -> EthernetAddress {
+    let octets = addr.octets();
+    EthernetAddress([0x01, 0x00, 0x5e, octets[1] & 0x7f, octets[2], octets[3]])
+}
+
+/// Maps the multicast IPv6 address to the Ethernet address.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc2464#section-7>.
+fn ipv6_multicast_ether_addr(addr: Ipv6Address) -> EthernetAddress {
+    let octets = addr.octets();
+    EthernetAddress([0x33, 0x33, octets[12], octets[13], octets[14], octets[15]])
+}
+
+fn parse_ether_addr(lladdr: RawHardwareAddress) -> Option<EthernetAddress> {
+    match lladdr.parse(Medium::Ethernet) {
+        Ok(HardwareAddress::Ethernet(ether_addr)) => Some(ether_addr),
+        _ => None,
+    }
+}
+
+fn new_ndisc_packet(
+    src_addr: Ipv6Address,
+    dst_addr: Ipv6Address,
+    ndisc_repr: NdiscRepr<'static>,
+) -> Packet<'static> {
+    let icmp_repr = Icmpv6Repr::Ndisc(ndisc_repr);
+    Packet::new_ipv6(
+        Ipv6Repr {
+            src_addr,
+            dst_addr,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: NDISC_HOP_LIMIT,
+        },
+        IpPayload::Icmpv6(icmp_repr),
+    )
+}
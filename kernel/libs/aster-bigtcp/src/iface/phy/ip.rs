@@ -5,7 +5,7 @@ use alloc::{string::String, sync::Arc};
 use smoltcp::{
     iface::Config,
     phy::{Device, TxToken},
-    wire::{self, Ipv4Cidr, Ipv4Packet},
+    wire::{self, IpVersion, Ipv4Cidr, Ipv4Packet, Ipv6Cidr, Ipv6Packet},
 };
 
 use crate::{
@@ -14,6 +14,7 @@ use crate::{
     iface::{
         common::{IfaceCommon, InterfaceFlags, InterfaceType},
         iface::internal::IfaceInternal,
+        poll::IpPacket,
         time::get_network_timestamp,
         Iface, ScheduleNextPoll,
     },
@@ -25,9 +26,13 @@ pub struct IpIface<D, E: Ext> {
 }
 
 impl<D: WithDevice, E: Ext> IpIface<D, E> {
+    /// Creates an IP iface.
+    ///
+    /// The IPv4 and IPv6 addresses are optional, since they can be configured later.
     pub fn new(
         driver: D,
-        ip_cidr: Ipv4Cidr,
+        ip_cidr: Option<Ipv4Cidr>,
+        ipv6_cidr: Option<Ipv6Cidr>,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         type_: InterfaceType,
@@ -40,7 +45,12 @@ impl<D: WithDevice, E: Ext> IpIface<D, E> {
             let mut interface = smoltcp::iface::Interface::new(config, device, now);
             interface.update_ip_addrs(|ip_addrs| {
                 debug_assert!(ip_addrs.is_empty());
-                ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                if let Some(ip_cidr) = ip_cidr {
+                    ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                }
+                if let Some(ipv6_cidr) = ipv6_cidr {
+                    ip_addrs.push(wire::IpCidr::Ipv6(ipv6_cidr)).unwrap();
+                }
             });
             interface
         });
@@ -62,9 +72,18 @@ impl<D: WithDevice + 'static, E: Ext> Iface<E> for IpIface<D, E> {
         self.driver.with(|device| {
             let next_poll = self.common.poll(
                 device,
-                |data, _iface_cx, tx_token| Some((Ipv4Packet::new_checked(data).ok()?, tx_token)),
+                |data, _iface_cx, tx_token| {
+                    self.common.process_link_frame(data, None, false);
+
+                    let pkt = match IpVersion::of_packet(data).ok()? {
+                        IpVersion::Ipv4 => IpPacket::Ipv4(Ipv4Packet::new_checked(data).ok()?),
+                        IpVersion::Ipv6 => IpPacket::Ipv6(Ipv6Packet::new_checked(data).ok()?),
+                    };
+                    Some((pkt, tx_token))
+                },
                 |pkt, iface_cx, tx_token| {
                     let ip_repr = pkt.ip_repr();
+                    let tx_token = self.common.capture(tx_token, None);
                     tx_token.consume(ip_repr.buffer_len(), |buffer| {
                         ip_repr.emit(&mut buffer[..], &iface_cx.checksum_caps());
                         pkt.emit_payload(
@@ -79,7 +98,7 @@ impl<D: WithDevice + 'static, E: Ext> Iface<E> for IpIface<D, E> {
         });
     }
 
-    fn mtu(&self) -> usize {
+    fn max_mtu(&self) -> usize {
         self.driver
             .with(|device| device.capabilities().max_transmission_unit)
     }
@@ -7,18 +7,22 @@ use smoltcp::{
         packet::{icmp_reply_payload_len, IpPayload, Packet},
         Context,
     },
-    phy::{ChecksumCapabilities, Device, RxToken, TxToken},
+    phy::{ChecksumCapabilities, Device, DeviceCapabilities, RxToken, TxToken},
     wire::{
-        Icmpv4DstUnreachable, Icmpv4Repr, IpAddress, IpProtocol, IpRepr, Ipv4Address, Ipv4Packet,
-        Ipv4Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket, UdpRepr, IPV4_HEADER_LEN,
-        IPV4_MIN_MTU,
+        Icmpv4DstUnreachable, Icmpv4Packet, Icmpv4Repr, Icmpv6DstUnreachable, Icmpv6Packet,
+        Icmpv6Repr, IpAddress, IpEndpoint, IpProtocol, IpRepr, Ipv4Address, Ipv4Packet, Ipv4Repr,
+        Ipv6Address, Ipv6Packet, Ipv6Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket, UdpRepr,
+        IPV4_HEADER_LEN, IPV4_MIN_MTU, IPV6_HEADER_LEN, IPV6_MIN_MTU, UDP_HEADER_LEN,
     },
 };
 
-use super::poll_iface::PollableIfaceMut;
+use super::{
+    igmp::{IGMP_HOP_LIMIT, IGMP_MESSAGE_LEN},
+    poll_iface::PollableIfaceMut,
+};
 use crate::{
     ext::Ext,
-    socket::{TcpConnectionBg, TcpProcessResult},
+    socket::{IcmpError, TcpConnectionBg, TcpProcessResult},
     socket_table::{ConnectionKey, ListenerKey, SocketTable},
 };
 
@@ -28,6 +32,19 @@ pub(super) struct PollContext<'a, E: Ext> {
     actions: &'a mut Vec<SocketTableAction<E>>,
 }
 
+/// An incoming IP packet, which is parsed by the physical layer.
+pub(super) enum IpPacket<T: AsRef<[u8]>> {
+    Ipv4(Ipv4Packet<T>),
+    Ipv6(Ipv6Packet<T>),
+}
+
+/// The reason for generating an ICMP Destination Unreachable message.
+#[derive(Clone, Copy)]
+enum UnreachableReason {
+    Host,
+    Port,
+}
+
 /// Socket table actions such as adding or removing TCP connections.
 ///
 /// Note that they must be performed in order. This is because the same connection key can occur
@@ -68,7 +85,7 @@ impl<E: Ext> PollContext<'_, E> {
             &'pkt [u8],
             &'cx mut Context,
             D::TxToken<'tx>,
-            Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
+            Option<(IpPacket<&'pkt [u8]>, D::TxToken<'tx>)>,
         >,
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
@@ -79,7 +96,11 @@ impl<E: Ext> PollContext<'_, E> {
                     return;
                 };
 
-                let Some(reply) = self.parse_and_process_ipv4(pkt) else {
+                let reply = match pkt {
+                    IpPacket::Ipv4(pkt) => self.parse_and_process_ipv4(pkt),
+                    IpPacket::Ipv6(pkt) => self.parse_and_process_ipv6(pkt),
+                };
+                let Some(reply) = reply else {
                     return;
                 };
 
@@ -95,14 +116,23 @@ impl<E: Ext> PollContext<'_, E> {
         // Parse the IP header. Ignore the packet if the header is ill-formed.
         let repr = Ipv4Repr::parse(&pkt, &self.iface.context().checksum_caps()).ok()?;
 
-        if !repr.dst_addr.is_broadcast() && !self.is_unicast_local(IpAddress::Ipv4(repr.dst_addr)) {
+        if !repr.dst_addr.is_broadcast()
+            && !self.is_multicast_member(IpAddress::Ipv4(repr.dst_addr))
+            && !self.is_unicast_local(IpAddress::Ipv4(repr.dst_addr))
+        {
             return self.generate_icmp_unreachable(
                 &IpRepr::Ipv4(repr),
                 pkt.payload(),
-                Icmpv4DstUnreachable::HostUnreachable,
+                UnreachableReason::Host,
             );
         }
 
+        // Raw IPv4 sockets receive the whole packet, including the IP header.
+        self.process_raw(
+            &IpRepr::Ipv4(repr),
+            &pkt.as_ref()[..usize::from(pkt.total_len())],
+        );
+
         let checksum_caps = self.iface.context().checksum_caps();
         match repr.next_header {
             IpProtocol::Tcp => {
@@ -111,6 +141,46 @@ impl<E: Ext> PollContext<'_, E> {
             IpProtocol::Udp => {
                 self.parse_and_process_udp(&IpRepr::Ipv4(repr), pkt.payload(), &checksum_caps)
             }
+            IpProtocol::Icmp => self.parse_and_process_icmpv4(&repr, pkt.payload(), &checksum_caps),
+            IpProtocol::Igmp => {
+                self.iface.multicast_groups_mut().process(pkt.payload());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_and_process_ipv6<'pkt>(
+        &mut self,
+        pkt: Ipv6Packet<&'pkt [u8]>,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the IP header. Ignore the packet if the header is ill-formed.
+        let repr = Ipv6Repr::parse(&pkt).ok()?;
+
+        if !repr.dst_addr.is_multicast() && !self.is_unicast_local(IpAddress::Ipv6(repr.dst_addr)) {
+            return self.generate_icmp_unreachable(
+                &IpRepr::Ipv6(repr),
+                pkt.payload(),
+                UnreachableReason::Host,
+            );
+        }
+
+        // Raw IPv6 sockets receive the IP payload only.
+        self.process_raw(&IpRepr::Ipv6(repr), pkt.payload());
+
+        // TODO: Support IPv6 extension headers. Packets with extension headers are ignored for
+        // now.
+        let checksum_caps = self.iface.context().checksum_caps();
+        match repr.next_header {
+            IpProtocol::Tcp => {
+                self.parse_and_process_tcp(&IpRepr::Ipv6(repr), pkt.payload(), &checksum_caps)
+            }
+            IpProtocol::Udp => {
+                self.parse_and_process_udp(&IpRepr::Ipv6(repr), pkt.payload(), &checksum_caps)
+            }
+            IpProtocol::Icmpv6 => {
+                self.parse_and_process_icmpv6(&repr, pkt.payload(), &checksum_caps)
+            }
             _ => None,
         }
     }
@@ -265,11 +335,7 @@ impl<E: Ext> PollContext<'_, E> {
         .ok()?;
 
         if !self.process_udp(ip_repr, &udp_repr, udp_pkt.payload()) {
-            return self.generate_icmp_unreachable(
-                ip_repr,
-                ip_payload,
-                Icmpv4DstUnreachable::PortUnreachable,
-            );
+            return self.generate_icmp_unreachable(ip_repr, ip_payload, UnreachableReason::Port);
         }
 
         None
@@ -292,11 +358,280 @@ impl<E: Ext> PollContext<'_, E> {
         processed
     }
 
+    fn parse_and_process_icmpv4<'pkt>(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        ip_payload: &'pkt [u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the ICMP header. Ignore the packet if the header is ill-formed.
+        let icmp_pkt = Icmpv4Packet::new_checked(ip_payload).ok()?;
+        let icmp_repr = Icmpv4Repr::parse(&icmp_pkt, checksum_caps).ok()?;
+
+        match icmp_repr {
+            Icmpv4Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => {
+                // Like Linux, ignore broadcast echo requests (see `icmp_echo_ignore_broadcasts`).
+                if ipv4_repr.dst_addr.is_broadcast()
+                    || !IpAddress::Ipv4(ipv4_repr.src_addr).is_unicast()
+                {
+                    return None;
+                }
+
+                let icmp_repr = Icmpv4Repr::EchoReply {
+                    ident,
+                    seq_no,
+                    data,
+                };
+                Some(Packet::new_ipv4(
+                    Ipv4Repr {
+                        src_addr: ipv4_repr.dst_addr,
+                        dst_addr: ipv4_repr.src_addr,
+                        next_header: IpProtocol::Icmp,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv4(icmp_repr),
+                ))
+            }
+            Icmpv4Repr::EchoReply { ident, .. } => {
+                self.process_ping(&IpRepr::Ipv4(*ipv4_repr), ident, ip_payload);
+                None
+            }
+            Icmpv4Repr::DstUnreachable {
+                reason,
+                header,
+                data,
+            } => {
+                // For "Fragmentation Needed" messages, the next-hop MTU is stored in the low-order
+                // 16 bits of the second word of the ICMP header. See RFC 1191, Section 4.
+                let info = if reason == Icmpv4DstUnreachable::FragRequired {
+                    u16::from_be_bytes([ip_payload[6], ip_payload[7]]) as u32
+                } else {
+                    0
+                };
+                self.process_udp_icmp_error(
+                    IpAddress::Ipv4(ipv4_repr.src_addr),
+                    (icmp_pkt.msg_type().into(), icmp_pkt.msg_code()),
+                    info,
+                    &IpRepr::Ipv4(header),
+                    data,
+                );
+                None
+            }
+            Icmpv4Repr::TimeExceeded { header, data, .. } => {
+                self.process_udp_icmp_error(
+                    IpAddress::Ipv4(ipv4_repr.src_addr),
+                    (icmp_pkt.msg_type().into(), icmp_pkt.msg_code()),
+                    0,
+                    &IpRepr::Ipv4(header),
+                    data,
+                );
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_and_process_icmpv6<'pkt>(
+        &mut self,
+        ipv6_repr: &Ipv6Repr,
+        ip_payload: &'pkt [u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the ICMP header. Ignore the packet if the header is ill-formed.
+        let icmp_pkt = Icmpv6Packet::new_checked(ip_payload).ok()?;
+        let icmp_repr = Icmpv6Repr::parse(
+            &ipv6_repr.src_addr,
+            &ipv6_repr.dst_addr,
+            &icmp_pkt,
+            checksum_caps,
+        )
+        .ok()?;
+
+        match icmp_repr {
+            Icmpv6Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => {
+                // TODO: Reply to multicast echo requests.
+                if ipv6_repr.dst_addr.is_multicast()
+                    || !IpAddress::Ipv6(ipv6_repr.src_addr).is_unicast()
+                {
+                    return None;
+                }
+
+                let icmp_repr = Icmpv6Repr::EchoReply {
+                    ident,
+                    seq_no,
+                    data,
+                };
+                Some(Packet::new_ipv6(
+                    Ipv6Repr {
+                        src_addr: ipv6_repr.dst_addr,
+                        dst_addr: ipv6_repr.src_addr,
+                        next_header: IpProtocol::Icmpv6,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv6(icmp_repr),
+                ))
+            }
+            Icmpv6Repr::EchoReply { ident, .. } => {
+                self.process_ping(&IpRepr::Ipv6(*ipv6_repr), ident, ip_payload);
+                None
+            }
+            Icmpv6Repr::DstUnreachable { header, data, .. }
+            | Icmpv6Repr::TimeExceeded { header, data, .. } => {
+                self.process_udp_icmp_error(
+                    IpAddress::Ipv6(ipv6_repr.src_addr),
+                    (icmp_pkt.msg_type().into(), icmp_pkt.msg_code()),
+                    0,
+                    &IpRepr::Ipv6(header),
+                    data,
+                );
+                None
+            }
+            Icmpv6Repr::PktTooBig { mtu, header, data } => {
+                self.process_udp_icmp_error(
+                    IpAddress::Ipv6(ipv6_repr.src_addr),
+                    (icmp_pkt.msg_type().into(), icmp_pkt.msg_code()),
+                    mtu,
+                    &IpRepr::Ipv6(header),
+                    data,
+                );
+                None
+            }
+            Icmpv6Repr::ParamProblem {
+                pointer,
+                header,
+                data,
+                ..
+            } => {
+                self.process_udp_icmp_error(
+                    IpAddress::Ipv6(ipv6_repr.src_addr),
+                    (icmp_pkt.msg_type().into(), icmp_pkt.msg_code()),
+                    pointer,
+                    &IpRepr::Ipv6(header),
+                    data,
+                );
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Delivers an incoming packet to raw IP sockets.
+    ///
+    /// Following Linux, `data` should contain the IP header for IPv4 packets, but not for IPv6
+    /// packets.
+    fn process_raw(&self, ip_repr: &IpRepr, data: &[u8]) {
+        for socket in self.sockets.raw_socket_iter() {
+            if socket.can_process_raw(ip_repr.version(), ip_repr.next_header()) {
+                socket.process(ip_repr.src_addr(), data);
+            }
+        }
+    }
+
+    /// Delivers an incoming ICMP echo reply to the ICMP echo socket, if any.
+    fn process_ping(&self, ip_repr: &IpRepr, ident: u16, icmp_data: &[u8]) {
+        // There can be at most one ICMP echo socket with the same identifier.
+        if let Some(socket) = self
+            .sockets
+            .raw_socket_iter()
+            .find(|socket| socket.can_process_ping(ip_repr.version(), ident))
+        {
+            socket.process(ip_repr.src_addr(), icmp_data);
+        }
+    }
+
+    /// Delivers an incoming ICMP error message to the UDP socket that sent the offending packet,
+    /// if any.
+    ///
+    /// `header` and `data` are the IP header and the IP payload of the offending packet quoted
+    /// in the ICMP error message.
+    fn process_udp_icmp_error(
+        &self,
+        offender: IpAddress,
+        (icmp_type, icmp_code): (u8, u8),
+        info: u32,
+        header: &IpRepr,
+        data: &[u8],
+    ) {
+        // The quoted payload must contain at least the UDP header to identify the socket.
+        if header.next_header() != IpProtocol::Udp || data.len() < UDP_HEADER_LEN {
+            return;
+        }
+
+        let udp_pkt = UdpPacket::new_unchecked(data);
+        let local_endpoint = IpEndpoint::new(header.src_addr(), udp_pkt.src_port());
+        let remote_endpoint = IpEndpoint::new(header.dst_addr(), udp_pkt.dst_port());
+
+        if let Some(socket) = self
+            .sockets
+            .udp_socket_iter()
+            .find(|socket| socket.can_process_icmp_error(&local_endpoint))
+        {
+            socket.process_icmp_error(IcmpError {
+                offender,
+                icmp_type,
+                icmp_code,
+                info,
+                remote_endpoint,
+                payload: data[UDP_HEADER_LEN..].to_vec(),
+            });
+        }
+    }
+
+    /// Processes a locally generated IP packet until an outgoing packet is generated.
+    ///
+    /// The packet is processed as if it were received from the network. If processing the packet
+    /// generates a reply that is also destined for the local interface (e.g., an ICMP echo reply),
+    /// the reply will be processed in the same way.
+    fn process_ip_until_outgoing(
+        &mut self,
+        mut ip_repr: IpRepr,
+        mut ip_payload: Vec<u8>,
+    ) -> Option<(IpRepr, Vec<u8>)> {
+        loop {
+            let data = {
+                let mut data = vec![0; ip_repr.buffer_len()];
+                ip_repr.emit(&mut data[..], &ChecksumCapabilities::default());
+                data[ip_repr.header_len()..].copy_from_slice(&ip_payload);
+                data
+            };
+
+            let reply = match ip_repr {
+                IpRepr::Ipv4(_) => {
+                    self.parse_and_process_ipv4(Ipv4Packet::new_unchecked(data.as_slice()))
+                }
+                IpRepr::Ipv6(_) => {
+                    self.parse_and_process_ipv6(Ipv6Packet::new_unchecked(data.as_slice()))
+                }
+            }?;
+
+            ip_repr = reply.ip_repr();
+            ip_payload = {
+                let mut payload = vec![0; ip_repr.payload_len()];
+                reply.emit_payload(&ip_repr, &mut payload, &DeviceCapabilities::default());
+                payload
+            };
+
+            if !self.is_unicast_local(ip_repr.dst_addr()) {
+                return Some((ip_repr, ip_payload));
+            }
+        }
+    }
+
     fn generate_icmp_unreachable<'pkt>(
         &self,
         ip_repr: &IpRepr,
         ip_payload: &'pkt [u8],
-        reason: Icmpv4DstUnreachable,
+        reason: UnreachableReason,
     ) -> Option<Packet<'pkt>> {
         if !ip_repr.src_addr().is_unicast() || !ip_repr.dst_addr().is_unicast() {
             return None;
@@ -312,29 +647,64 @@ impl<E: Ext> PollContext<'_, E> {
             return None;
         }
 
-        let IpRepr::Ipv4(ipv4_repr) = ip_repr;
+        match ip_repr {
+            IpRepr::Ipv4(ipv4_repr) => {
+                let reason = match reason {
+                    UnreachableReason::Host => Icmpv4DstUnreachable::HostUnreachable,
+                    UnreachableReason::Port => Icmpv4DstUnreachable::PortUnreachable,
+                };
+                let reply_len =
+                    icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, IPV4_HEADER_LEN);
+                let icmp_repr = Icmpv4Repr::DstUnreachable {
+                    reason,
+                    header: *ipv4_repr,
+                    data: &ip_payload[..reply_len],
+                };
 
-        let reply_len = icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, IPV4_HEADER_LEN);
-        let icmp_repr = Icmpv4Repr::DstUnreachable {
-            reason,
-            header: *ipv4_repr,
-            data: &ip_payload[..reply_len],
-        };
+                Some(Packet::new_ipv4(
+                    Ipv4Repr {
+                        src_addr: self
+                            .iface
+                            .context()
+                            .ipv4_addr()
+                            .unwrap_or(Ipv4Address::UNSPECIFIED),
+                        dst_addr: ipv4_repr.src_addr,
+                        next_header: IpProtocol::Icmp,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv4(icmp_repr),
+                ))
+            }
+            IpRepr::Ipv6(ipv6_repr) => {
+                let reason = match reason {
+                    UnreachableReason::Host => Icmpv6DstUnreachable::AddrUnreachable,
+                    UnreachableReason::Port => Icmpv6DstUnreachable::PortUnreachable,
+                };
+                let reply_len =
+                    icmp_reply_payload_len(ip_payload.len(), IPV6_MIN_MTU, IPV6_HEADER_LEN);
+                let icmp_repr = Icmpv6Repr::DstUnreachable {
+                    reason,
+                    header: *ipv6_repr,
+                    data: &ip_payload[..reply_len],
+                };
 
-        Some(Packet::new_ipv4(
-            Ipv4Repr {
-                src_addr: self
-                    .iface
-                    .context()
-                    .ipv4_addr()
-                    .unwrap_or(Ipv4Address::UNSPECIFIED),
-                dst_addr: ipv4_repr.src_addr,
-                next_header: IpProtocol::Icmp,
-                payload_len: icmp_repr.buffer_len(),
-                hop_limit: 64,
-            },
-            IpPayload::Icmpv4(icmp_repr),
-        ))
+                Some(Packet::new_ipv6(
+                    Ipv6Repr {
+                        src_addr: self
+                            .iface
+                            .context()
+                            .ipv6_addr()
+                            .unwrap_or(Ipv6Address::UNSPECIFIED),
+                        dst_addr: ipv6_repr.src_addr,
+                        next_header: IpProtocol::Icmpv6,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv6(icmp_repr),
+                ))
+            }
+        }
     }
 
     /// Returns whether the destination address is the unicast address of a local interface.
@@ -348,6 +718,25 @@ impl<E: Ext> PollContext<'_, E> {
                 .context()
                 .ipv4_addr()
                 .is_some_and(|addr| addr == dst_addr),
+            IpAddress::Ipv6(dst_addr) => self
+                .iface
+                .context()
+                .ipv6_addr()
+                .is_some_and(|addr| addr == dst_addr),
+        }
+    }
+
+    /// Returns whether the destination address is an IPv4 multicast group joined by the
+    /// interface.
+    ///
+    /// TODO: Track IPv6 multicast groups. Currently, all incoming IPv6 multicast packets are
+    /// accepted, but outgoing IPv6 multicast packets are never looped back.
+    fn is_multicast_member(&self, dst_addr: IpAddress) -> bool {
+        match dst_addr {
+            IpAddress::Ipv4(dst_addr) => {
+                dst_addr.is_multicast() && self.iface.multicast_groups().contains(dst_addr)
+            }
+            IpAddress::Ipv6(_) => false,
         }
     }
 }
@@ -376,9 +765,21 @@ impl<E: Ext> PollContext<'_, E> {
             return did_something_tcp;
         };
 
-        let (did_something_udp, _tx_token) = self.dispatch_udp(tx_token, dispatch_phy);
+        let (did_something_udp, tx_token) = self.dispatch_udp(tx_token, dispatch_phy);
+
+        let Some(tx_token) = tx_token else {
+            return did_something_tcp || did_something_udp;
+        };
 
-        did_something_tcp || did_something_udp
+        let (did_something_raw, tx_token) = self.dispatch_raw(tx_token, dispatch_phy);
+
+        let Some(tx_token) = tx_token else {
+            return did_something_tcp || did_something_udp || did_something_raw;
+        };
+
+        let (did_something_igmp, _tx_token) = self.dispatch_igmp(tx_token, dispatch_phy);
+
+        did_something_tcp || did_something_udp || did_something_raw || did_something_igmp
     }
 
     fn dispatch_tcp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
@@ -499,18 +900,24 @@ impl<E: Ext> PollContext<'_, E> {
 
             let mut deferred = None;
 
-            let (cx, pending) = self.iface.inner_mut();
+            let (cx, pending, multicast_groups) = self.iface.inner_mut();
             socket.dispatch(cx, |cx, ip_repr, udp_repr, udp_payload| {
-                let iface = PollableIfaceMut::new(cx, pending);
+                let iface = PollableIfaceMut::new(cx, pending, multicast_groups);
                 let mut this = PollContext::new(iface, self.sockets, &mut actions);
 
-                if ip_repr.dst_addr().is_broadcast() || !this.is_unicast_local(ip_repr.dst_addr()) {
+                let dst_addr = ip_repr.dst_addr();
+                if dst_addr.is_broadcast() || !this.is_unicast_local(dst_addr) {
                     dispatch_phy(
                         &Packet::new(ip_repr.clone(), IpPayload::Udp(*udp_repr, udp_payload)),
                         this.iface.context_mut(),
                         tx_token.take().unwrap(),
                     );
-                    if !ip_repr.dst_addr().is_broadcast() {
+
+                    // Broadcast packets are always looped back. Multicast packets are looped back
+                    // if the socket allows it and the interface has joined the group.
+                    let is_looped_back = dst_addr.is_broadcast()
+                        || (socket.multicast_loop() && this.is_multicast_member(dst_addr));
+                    if !is_looped_back {
                         return;
                     }
                 }
@@ -560,4 +967,87 @@ impl<E: Ext> PollContext<'_, E> {
 
         (did_something, tx_token)
     }
+    fn dispatch_raw<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
+    where
+        T: TxToken,
+        Q: FnMut(&Packet, &mut Context, T),
+    {
+        let mut tx_token = Some(tx_token);
+        let mut did_something = false;
+
+        for socket in self.sockets.raw_socket_iter() {
+            if !socket.need_dispatch() {
+                continue;
+            }
+
+            // The packet is dequeued and the socket lock is released, so we can process the
+            // packet locally below without causing deadlocks.
+            let Some((ip_repr, ip_payload)) = socket.dispatch() else {
+                continue;
+            };
+            did_something = true;
+
+            let dst_addr = ip_repr.dst_addr();
+            if dst_addr.is_broadcast() || !self.is_unicast_local(dst_addr) {
+                dispatch_phy(
+                    &Packet::new(ip_repr.clone(), IpPayload::Raw(&ip_payload)),
+                    self.iface.context_mut(),
+                    tx_token.take().unwrap(),
+                );
+            }
+
+            // Note that broadcast packets never generate replies, so the TX token must be
+            // available if there is a reply to send.
+            if dst_addr.is_broadcast() || self.is_unicast_local(dst_addr) {
+                if let Some((ip_repr, ip_payload)) =
+                    self.process_ip_until_outgoing(ip_repr, ip_payload)
+                {
+                    dispatch_phy(
+                        &Packet::new(ip_repr, IpPayload::Raw(&ip_payload)),
+                        self.iface.context_mut(),
+                        tx_token.take().unwrap(),
+                    );
+                }
+            }
+
+            if tx_token.is_none() {
+                break;
+            }
+        }
+
+        (did_something, tx_token)
+    }
+
+    fn dispatch_igmp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
+    where
+        T: TxToken,
+        Q: FnMut(&Packet, &mut Context, T),
+    {
+        let Some(message) = self.iface.multicast_groups_mut().pop_pending() else {
+            return (false, Some(tx_token));
+        };
+
+        // TODO: Add the Router Alert option, which is required by RFC 2236, Section 2. This
+        // requires support for IPv4 options when emitting packets.
+        let data = message.emit();
+        let ip_repr = Ipv4Repr {
+            src_addr: self
+                .iface
+                .context()
+                .ipv4_addr()
+                .unwrap_or(Ipv4Address::UNSPECIFIED),
+            dst_addr: message.dst_addr(),
+            next_header: IpProtocol::Igmp,
+            payload_len: IGMP_MESSAGE_LEN,
+            hop_limit: IGMP_HOP_LIMIT,
+        };
+
+        dispatch_phy(
+            &Packet::new_ipv4(ip_repr, IpPayload::Raw(&data)),
+            self.iface.context_mut(),
+            tx_token,
+        );
+
+        (true, None)
+    }
 }
@@ -1,12 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{collections::btree_set::BTreeSet, sync::Arc};
+use alloc::{collections::btree_set::BTreeSet, sync::Arc, vec::Vec};
 use core::{
     borrow::Borrow,
     sync::atomic::{AtomicU64, Ordering},
 };
 
+use smoltcp::wire::{IpCidr, Ipv4Address};
+
+use super::{igmp::MulticastGroups, Route};
 use crate::{
+    errors::IfaceConfigError,
     ext::Ext,
     socket::{NeedIfacePoll, TcpConnectionBg},
 };
@@ -18,6 +22,7 @@ use crate::{
 pub(crate) struct PollableIface<E: Ext> {
     interface: smoltcp::iface::Interface,
     pending_conns: PendingConnSet<E>,
+    multicast_groups: MulticastGroups,
 }
 
 impl<E: Ext> PollableIface<E> {
@@ -25,6 +30,7 @@ impl<E: Ext> PollableIface<E> {
         Self {
             interface,
             pending_conns: PendingConnSet::new(),
+            multicast_groups: MulticastGroups::new(),
         }
     }
 
@@ -32,6 +38,7 @@ impl<E: Ext> PollableIface<E> {
         PollableIfaceMut {
             context: self.interface.context(),
             pending_conns: &mut self.pending_conns,
+            multicast_groups: &mut self.multicast_groups,
         }
     }
 
@@ -42,10 +49,93 @@ impl<E: Ext> PollableIface<E> {
     pub(super) fn prefix_len(&self) -> Option<u8> {
         self.interface
             .ip_addrs()
-            .first()
+            .iter()
+            .find(|ip_addr| matches!(ip_addr, IpCidr::Ipv4(_)))
             .map(|ip_addr| ip_addr.prefix_len())
     }
 
+    pub(super) fn ipv6_addr(&self) -> Option<smoltcp::wire::Ipv6Address> {
+        self.interface.ipv6_addr()
+    }
+
+    pub(super) fn ip_addrs(&self) -> Vec<IpCidr> {
+        self.interface.ip_addrs().to_vec()
+    }
+
+    pub(super) fn add_ip_addr(&mut self, ip_cidr: IpCidr) -> Result<(), IfaceConfigError> {
+        if self
+            .interface
+            .ip_addrs()
+            .iter()
+            .any(|ip_addr| ip_addr.address() == ip_cidr.address())
+        {
+            return Err(IfaceConfigError::Exists);
+        }
+
+        let mut result = Ok(());
+        self.interface.update_ip_addrs(|ip_addrs| {
+            if ip_addrs.push(ip_cidr).is_err() {
+                result = Err(IfaceConfigError::TableFull);
+            }
+        });
+        result
+    }
+
+    pub(super) fn remove_ip_addr(&mut self, ip_cidr: IpCidr) -> Result<(), IfaceConfigError> {
+        if !self.interface.ip_addrs().contains(&ip_cidr) {
+            return Err(IfaceConfigError::NotFound);
+        }
+
+        self.interface
+            .update_ip_addrs(|ip_addrs| ip_addrs.retain(|ip_addr| *ip_addr != ip_cidr));
+        Ok(())
+    }
+
+    pub(super) fn routes(&mut self) -> Vec<Route> {
+        let mut routes = Vec::new();
+        self.interface
+            .routes_mut()
+            .update(|storage| routes.extend(storage.iter().map(Route::from)));
+        routes
+    }
+
+    pub(super) fn add_route(&mut self, route: Route) -> Result<(), IfaceConfigError> {
+        let mut result = Ok(());
+        self.interface.routes_mut().update(|storage| {
+            if storage.iter().any(|old_route| old_route.cidr == route.dst) {
+                result = Err(IfaceConfigError::Exists);
+            } else if storage.push(route.into()).is_err() {
+                result = Err(IfaceConfigError::TableFull);
+            }
+        });
+        result
+    }
+
+    pub(super) fn remove_route(&mut self, route: &Route) -> Result<(), IfaceConfigError> {
+        let mut result = Err(IfaceConfigError::NotFound);
+        self.interface.routes_mut().update(|storage| {
+            storage.retain(|old_route| {
+                if Route::from(old_route) == *route {
+                    result = Ok(());
+                    false
+                } else {
+                    true
+                }
+            });
+        });
+        result
+    }
+
+    /// Joins the IPv4 multicast group and returns whether an IGMP message is queued.
+    pub(super) fn join_multicast_group(&mut self, group: Ipv4Address) -> bool {
+        self.multicast_groups.join(group)
+    }
+
+    /// Leaves the IPv4 multicast group and returns whether an IGMP message is queued.
+    pub(super) fn leave_multicast_group(&mut self, group: Ipv4Address) -> bool {
+        self.multicast_groups.leave(group)
+    }
+
     /// Returns the next poll time.
     pub(super) fn next_poll_at_ms(&self) -> Option<u64> {
         self.pending_conns.next_poll_at_ms()
@@ -79,6 +169,7 @@ impl<E: Ext> PollableIface<E> {
 pub(crate) struct PollableIfaceMut<'a, E: Ext> {
     context: &'a mut smoltcp::iface::Context,
     pending_conns: &'a mut PendingConnSet<E>,
+    multicast_groups: &'a mut MulticastGroups,
 }
 
 // FIXME: We provide `new()` and `inner_mut()` as `pub(crate)` methods because it's necessary to
@@ -88,15 +179,23 @@ impl<'a, E: Ext> PollableIfaceMut<'a, E> {
     pub(crate) fn new(
         context: &'a mut smoltcp::iface::Context,
         pending_conns: &'a mut PendingConnSet<E>,
+        multicast_groups: &'a mut MulticastGroups,
     ) -> Self {
         Self {
             context,
             pending_conns,
+            multicast_groups,
         }
     }
 
-    pub(crate) fn inner_mut(&mut self) -> (&mut smoltcp::iface::Context, &mut PendingConnSet<E>) {
-        (self.context, self.pending_conns)
+    pub(crate) fn inner_mut(
+        &mut self,
+    ) -> (
+        &mut smoltcp::iface::Context,
+        &mut PendingConnSet<E>,
+        &mut MulticastGroups,
+    ) {
+        (self.context, self.pending_conns, self.multicast_groups)
     }
 }
 
@@ -105,6 +204,16 @@ impl<E: Ext> PollableIfaceMut<'_, E> {
         let now = self.context.now.total_millis() as u64;
         self.pending_conns.pop_tcp_before_now(now)
     }
+
+    /// Returns the IPv4 multicast groups joined by the iface.
+    pub(super) fn multicast_groups(&self) -> &MulticastGroups {
+        self.multicast_groups
+    }
+
+    /// Returns the IPv4 multicast groups joined by the iface for processing IGMP messages.
+    pub(super) fn multicast_groups_mut(&mut self) -> &mut MulticastGroups {
+        self.multicast_groups
+    }
 }
 
 impl<E: Ext> PollableIfaceMut<'_, E> {
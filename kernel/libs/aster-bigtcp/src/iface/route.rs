@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use smoltcp::wire::{IpAddress, IpCidr};
+
+/// A route that forwards packets to a gateway.
+///
+/// Routes to the subnets of the iface addresses are implicit, so they are not represented by this
+/// type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    /// The destination subnet.
+    pub dst: IpCidr,
+    /// The gateway, which must be directly reachable from the iface.
+    pub gateway: IpAddress,
+}
+
+impl From<&smoltcp::iface::Route> for Route {
+    fn from(value: &smoltcp::iface::Route) -> Self {
+        Self {
+            dst: value.cidr,
+            gateway: value.via_router,
+        }
+    }
+}
+
+impl From<Route> for smoltcp::iface::Route {
+    fn from(value: Route) -> Self {
+        Self {
+            cidr: value.dst,
+            via_router: value.gateway,
+            preferred_until: None,
+            expires_at: None,
+        }
+    }
+}
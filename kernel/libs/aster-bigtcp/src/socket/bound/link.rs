@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_softirq::BottomHalfDisabled;
+use ostd::sync::SpinLock;
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, EthernetRepr, ETHERNET_HEADER_LEN,
+};
+use spin::once::Once;
+
+use crate::{
+    errors::link::{RecvError, SendError},
+    ext::Ext,
+    iface::Iface,
+    socket::event::{SocketEventObserver, SocketEvents},
+};
+
+/// A link-layer socket.
+///
+/// A link-layer socket sends and receives frames at the link layer of an iface, bypassing the
+/// network stack. Incoming frames are delivered to the socket before they are processed by the
+/// IP layer, and outgoing frames are delivered to the socket right before they are transmitted.
+///
+/// For ifaces that have no link-layer headers (e.g., the loopback iface), the frames are IP
+/// packets.
+pub struct LinkSocket<E: Ext>(Arc<LinkSocketBg<E>>);
+
+/// The kind of a [`LinkSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkSocketKind {
+    /// The frames to send and receive include the link-layer header.
+    Raw,
+    /// The frames to send and receive do not include the link-layer header.
+    ///
+    /// The link-layer header of outgoing frames is built from the destination address and the
+    /// protocol.
+    Cooked,
+}
+
+/// The type of a link-layer frame.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L26>.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkPacketType {
+    /// The frame is sent to us.
+    Host = 0,
+    /// The frame is a broadcast frame.
+    Broadcast = 1,
+    /// The frame is a multicast frame.
+    Multicast = 2,
+    /// The frame is sent to others, which is only seen in promiscuous mode.
+    OtherHost = 3,
+    /// The frame is sent by us.
+    Outgoing = 4,
+}
+
+/// The metadata of a link-layer frame.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkFrameMeta {
+    /// The link-layer protocol (i.e., the EtherType).
+    pub protocol: u16,
+    /// The type of the frame.
+    pub pkt_type: LinkPacketType,
+    /// The source hardware address, if the iface has hardware addresses.
+    pub src_addr: Option<EthernetAddress>,
+}
+
+/// A filter that decides whether a [`LinkSocket`] accepts incoming frames.
+pub trait LinkFrameFilter: Send + Sync {
+    /// Runs the filter against the frame.
+    ///
+    /// This returns the number of bytes of the frame to accept. If the number is zero, the frame
+    /// will be dropped. If the number is larger than the frame length, the whole frame will be
+    /// accepted.
+    fn run(&self, frame: &[u8]) -> usize;
+}
+
+/// The link-layer protocol that matches all protocols (i.e., `ETH_P_ALL`).
+pub const LINK_PROTOCOL_ALL: u16 = 0x0003;
+
+/// The background part of a [`LinkSocket`], which handles frames from the iface.
+pub(crate) struct LinkSocketBg<E: Ext> {
+    iface: Arc<dyn Iface<E>>,
+    kind: LinkSocketKind,
+    protocol: u16,
+    queues: SpinLock<FrameQueues, BottomHalfDisabled>,
+    filter: SpinLock<Option<Arc<dyn LinkFrameFilter>>, BottomHalfDisabled>,
+    need_dispatch: AtomicBool,
+    observer: Once<E::LinkEventObserver>,
+}
+
+struct FrameQueues {
+    /// Incoming frames and their metadata.
+    recv_queue: VecDeque<(LinkFrameMeta, Vec<u8>)>,
+    recv_len: usize,
+    /// Outgoing frames, including their link-layer headers.
+    send_queue: VecDeque<Vec<u8>>,
+    send_len: usize,
+}
+
+impl FrameQueues {
+    const fn new() -> Self {
+        Self {
+            recv_queue: VecDeque::new(),
+            recv_len: 0,
+            send_queue: VecDeque::new(),
+            send_len: 0,
+        }
+    }
+}
+
+impl<E: Ext> LinkSocketBg<E> {
+    /// Returns whether the socket handles the frame.
+    pub(crate) fn can_process(&self, meta: &LinkFrameMeta) -> bool {
+        match self.protocol {
+            // Sockets of protocol zero never receive frames.
+            0 => false,
+            // Like Linux, outgoing frames are only delivered to sockets that handle all protocols.
+            LINK_PROTOCOL_ALL => true,
+            protocol => protocol == meta.protocol && meta.pkt_type != LinkPacketType::Outgoing,
+        }
+    }
+
+    /// Queues an incoming frame, whose link-layer header has `header_len` bytes.
+    ///
+    /// The frame is silently dropped if the filter rejects it or the receive queue is full.
+    pub(crate) fn process(&self, frame: &[u8], meta: &LinkFrameMeta, header_len: usize) {
+        let data = match self.kind {
+            LinkSocketKind::Raw => frame,
+            LinkSocketKind::Cooked => &frame[header_len..],
+        };
+
+        let accept_len = match self.filter.lock().as_ref() {
+            Some(filter) => filter.run(data).min(data.len()),
+            None => data.len(),
+        };
+        if accept_len == 0 {
+            return;
+        }
+        let data = &data[..accept_len];
+
+        let mut queues = self.queues.lock();
+
+        if queues.recv_len + data.len() > LINK_RECV_BUF_LEN {
+            return;
+        }
+
+        queues.recv_len += data.len();
+        queues.recv_queue.push_back((*meta, data.to_vec()));
+        drop(queues);
+
+        self.notify_events(SocketEvents::CAN_RECV);
+    }
+
+    /// Dequeues an outgoing frame.
+    pub(crate) fn dispatch(&self) -> Option<Vec<u8>> {
+        let mut queues = self.queues.lock();
+
+        let frame = queues.send_queue.pop_front();
+        if let Some(frame) = frame.as_ref() {
+            queues.send_len -= frame.len();
+        }
+
+        self.need_dispatch
+            .store(!queues.send_queue.is_empty(), Ordering::Relaxed);
+        drop(queues);
+
+        // Dequeuing a frame means that we can queue more frames.
+        if frame.is_some() {
+            self.notify_events(SocketEvents::CAN_SEND);
+        }
+
+        frame
+    }
+
+    /// Returns whether the socket _may_ generate an outgoing frame.
+    ///
+    /// The check is intended to be lock-free and fast, but may have false positives.
+    pub(crate) fn need_dispatch(&self) -> bool {
+        self.need_dispatch.load(Ordering::Relaxed)
+    }
+
+    fn notify_events(&self, events: SocketEvents) {
+        if let Some(observer) = self.observer.get() {
+            observer.on_events(events);
+        }
+    }
+}
+
+impl<E: Ext> LinkSocket<E> {
+    /// Creates a link-layer socket that handles frames of the link-layer protocol.
+    ///
+    /// If `protocol` is [`LINK_PROTOCOL_ALL`], the socket handles frames of all protocols,
+    /// including outgoing ones. If `protocol` is zero, the socket can only send frames.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new(
+        iface: Arc<dyn Iface<E>>,
+        kind: LinkSocketKind,
+        protocol: u16,
+        observer: E::LinkEventObserver,
+    ) -> Self {
+        let socket = Arc::new(LinkSocketBg {
+            iface,
+            kind,
+            protocol,
+            queues: SpinLock::new(FrameQueues::new()),
+            filter: SpinLock::new(None),
+            need_dispatch: AtomicBool::new(false),
+            observer: Once::new(),
+        });
+        socket.observer.call_once(|| observer);
+        socket.iface.common().register_link_socket(socket.clone());
+
+        Self(socket)
+    }
+
+    /// Sends a frame.
+    ///
+    /// For [`LinkSocketKind::Raw`] sockets, `data` is the whole frame and the other arguments are
+    /// ignored. For [`LinkSocketKind::Cooked`] sockets, the link-layer header is built from
+    /// `protocol` and `dst_addr`, where `dst_addr` is required if the iface has hardware
+    /// addresses.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn send(
+        &self,
+        data: Vec<u8>,
+        protocol: u16,
+        dst_addr: Option<EthernetAddress>,
+    ) -> Result<(), SendError> {
+        let Some(src_addr) = self.0.iface.hw_addr() else {
+            // There are no link-layer headers, so the frame is sent as is.
+            return self.queue_frame(data, 0);
+        };
+
+        let frame = match self.0.kind {
+            LinkSocketKind::Raw if data.len() < ETHERNET_HEADER_LEN => {
+                return Err(SendError::Malformed)
+            }
+            LinkSocketKind::Raw => data,
+            LinkSocketKind::Cooked => {
+                let ether_repr = EthernetRepr {
+                    src_addr,
+                    dst_addr: dst_addr.ok_or(SendError::Unaddressable)?,
+                    ethertype: EthernetProtocol::from(protocol),
+                };
+
+                let mut frame = vec![0u8; ether_repr.buffer_len() + data.len()];
+                let mut ether_frame = EthernetFrame::new_unchecked(frame.as_mut_slice());
+                ether_repr.emit(&mut ether_frame);
+                ether_frame.payload_mut().copy_from_slice(&data);
+
+                frame
+            }
+        };
+
+        self.queue_frame(frame, ETHERNET_HEADER_LEN)
+    }
+
+    fn queue_frame(&self, frame: Vec<u8>, header_len: usize) -> Result<(), SendError> {
+        if frame.len() > self.0.iface.mtu() + header_len || frame.len() > LINK_SEND_BUF_LEN {
+            return Err(SendError::TooLarge);
+        }
+
+        let mut queues = self.0.queues.lock();
+
+        if queues.send_len + frame.len() > LINK_SEND_BUF_LEN {
+            return Err(SendError::BufferFull);
+        }
+
+        queues.send_len += frame.len();
+        queues.send_queue.push_back(frame);
+        self.0.need_dispatch.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Receives a frame.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn recv<F, R>(&self, f: F) -> Result<R, RecvError>
+    where
+        F: FnOnce(&[u8], &LinkFrameMeta) -> R,
+    {
+        let mut queues = self.0.queues.lock();
+
+        let Some((meta, data)) = queues.recv_queue.pop_front() else {
+            return Err(RecvError::Exhausted);
+        };
+        queues.recv_len -= data.len();
+        drop(queues);
+
+        Ok(f(&data, &meta))
+    }
+
+    /// Returns whether there are frames to receive.
+    pub fn can_recv(&self) -> bool {
+        !self.0.queues.lock().recv_queue.is_empty()
+    }
+
+    /// Returns whether there is free space in the send queue.
+    pub fn can_send(&self) -> bool {
+        self.0.queues.lock().send_len < LINK_SEND_BUF_LEN
+    }
+
+    /// Sets the filter for incoming frames.
+    ///
+    /// Frames that are already queued are not affected.
+    pub fn set_filter(&self, filter: Option<Arc<dyn LinkFrameFilter>>) {
+        *self.0.filter.lock() = filter;
+    }
+
+    /// Returns the kind of the socket.
+    pub fn kind(&self) -> LinkSocketKind {
+        self.0.kind
+    }
+
+    /// Returns the link-layer protocol that the socket handles.
+    pub fn protocol(&self) -> u16 {
+        self.0.protocol
+    }
+
+    pub fn iface(&self) -> &Arc<dyn Iface<E>> {
+        &self.0.iface
+    }
+}
+
+impl<E: Ext> Drop for LinkSocket<E> {
+    fn drop(&mut self) {
+        // A link-layer socket can be removed immediately.
+        self.0.iface.common().remove_link_socket(&self.0);
+    }
+}
+
+// Link-layer socket buffer sizes:
+const LINK_SEND_BUF_LEN: usize = 65536;
+const LINK_RECV_BUF_LEN: usize = 65536;
@@ -1,14 +1,22 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod common;
+mod link;
+mod raw;
 mod tcp_conn;
 mod tcp_listen;
 mod udp;
 
 pub use common::NeedIfacePoll;
+pub(crate) use link::LinkSocketBg;
+pub use link::{
+    LinkFrameFilter, LinkFrameMeta, LinkPacketType, LinkSocket, LinkSocketKind, LINK_PROTOCOL_ALL,
+};
+pub(crate) use raw::IpRawSocketBg;
+pub use raw::{IpRawSocket, IpRawSocketKind};
 pub use tcp_conn::{ConnectState, RawTcpSocketExt, TcpConnection};
 pub(crate) use tcp_conn::{TcpConnectionBg, TcpProcessResult};
 pub use tcp_listen::TcpListener;
 pub(crate) use tcp_listen::TcpListenerBg;
-pub use udp::UdpSocket;
 pub(crate) use udp::UdpSocketBg;
+pub use udp::{IcmpError, UdpSocket};
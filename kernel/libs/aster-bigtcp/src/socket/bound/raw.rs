@@ -0,0 +1,389 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_softirq::BottomHalfDisabled;
+use ostd::sync::SpinLock;
+use smoltcp::wire::{
+    Icmpv4Message, Icmpv4Packet, Icmpv6Message, Icmpv6Packet, IpAddress, IpProtocol, IpRepr,
+    IpVersion, Ipv4Packet, Ipv4Repr, Ipv6Address, IPV4_HEADER_LEN,
+};
+use spin::once::Once;
+
+use crate::{
+    errors::{
+        raw::{RecvError, SendError},
+        BindError,
+    },
+    ext::Ext,
+    iface::Iface,
+    socket::event::{SocketEventObserver, SocketEvents},
+};
+
+/// A raw IP socket or an ICMP echo socket.
+///
+/// A raw IP socket sends and receives packets of a specific IP protocol. An ICMP echo socket
+/// (i.e., a ping socket) sends ICMP echo requests and receives the corresponding echo replies.
+///
+/// Unlike TCP and UDP sockets, the socket is not bound to a port. Instead, it is bound to an
+/// iface, and it uses the address of the iface whose version is the socket's IP version.
+pub struct IpRawSocket<E: Ext>(Arc<IpRawSocketBg<E>>);
+
+/// The kind of an [`IpRawSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpRawSocketKind {
+    /// A raw IP socket that handles all packets of the IP protocol.
+    Raw(IpProtocol),
+    /// An ICMP echo socket that handles echo messages of the identifier.
+    Ping(u16),
+}
+
+/// The background part of an [`IpRawSocket`], which handles packets from the network.
+pub(crate) struct IpRawSocketBg<E: Ext> {
+    iface: Arc<dyn Iface<E>>,
+    ip_version: IpVersion,
+    kind: IpRawSocketKind,
+    queues: SpinLock<PacketQueues, BottomHalfDisabled>,
+    need_dispatch: AtomicBool,
+    observer: Once<E::RawEventObserver>,
+}
+
+struct PacketQueues {
+    /// Incoming packets and their source addresses.
+    recv_queue: VecDeque<(IpAddress, Vec<u8>)>,
+    recv_len: usize,
+    /// Outgoing packets and their IP headers.
+    send_queue: VecDeque<(IpRepr, Vec<u8>)>,
+    send_len: usize,
+}
+
+impl PacketQueues {
+    const fn new() -> Self {
+        Self {
+            recv_queue: VecDeque::new(),
+            recv_len: 0,
+            send_queue: VecDeque::new(),
+            send_len: 0,
+        }
+    }
+}
+
+impl<E: Ext> IpRawSocketBg<E> {
+    fn new(iface: Arc<dyn Iface<E>>, ip_version: IpVersion, kind: IpRawSocketKind) -> Self {
+        Self {
+            iface,
+            ip_version,
+            kind,
+            queues: SpinLock::new(PacketQueues::new()),
+            need_dispatch: AtomicBool::new(false),
+            observer: Once::new(),
+        }
+    }
+
+    /// Returns whether the socket handles incoming packets of the IP protocol.
+    pub(crate) fn can_process_raw(&self, ip_version: IpVersion, protocol: IpProtocol) -> bool {
+        self.ip_version == ip_version && self.kind == IpRawSocketKind::Raw(protocol)
+    }
+
+    /// Returns whether the socket handles incoming ICMP echo replies of the identifier.
+    pub(crate) fn can_process_ping(&self, ip_version: IpVersion, ident: u16) -> bool {
+        self.ip_version == ip_version && self.kind == IpRawSocketKind::Ping(ident)
+    }
+
+    /// Queues an incoming packet.
+    ///
+    /// The packet is silently dropped if the receive queue is full.
+    pub(crate) fn process(&self, src_addr: IpAddress, data: &[u8]) {
+        let mut queues = self.queues.lock();
+
+        if queues.recv_len + data.len() > RAW_RECV_BUF_LEN {
+            return;
+        }
+
+        queues.recv_len += data.len();
+        queues.recv_queue.push_back((src_addr, data.to_vec()));
+        drop(queues);
+
+        self.notify_events(SocketEvents::CAN_RECV);
+    }
+
+    /// Dequeues an outgoing packet.
+    pub(crate) fn dispatch(&self) -> Option<(IpRepr, Vec<u8>)> {
+        let mut queues = self.queues.lock();
+
+        let packet = queues.send_queue.pop_front();
+        if let Some((_, payload)) = packet.as_ref() {
+            queues.send_len -= payload.len();
+        }
+
+        self.need_dispatch
+            .store(!queues.send_queue.is_empty(), Ordering::Relaxed);
+        drop(queues);
+
+        // Dequeuing a packet means that we can queue more packets.
+        if packet.is_some() {
+            self.notify_events(SocketEvents::CAN_SEND);
+        }
+
+        packet
+    }
+
+    /// Returns whether the socket _may_ generate an outgoing packet.
+    ///
+    /// The check is intended to be lock-free and fast, but may have false positives.
+    pub(crate) fn need_dispatch(&self) -> bool {
+        self.need_dispatch.load(Ordering::Relaxed)
+    }
+
+    fn notify_events(&self, events: SocketEvents) {
+        if let Some(observer) = self.observer.get() {
+            observer.on_events(events);
+        }
+    }
+}
+
+impl<E: Ext> IpRawSocket<E> {
+    /// Creates a raw IP socket that handles packets of the IP protocol.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new_raw(
+        iface: Arc<dyn Iface<E>>,
+        ip_version: IpVersion,
+        protocol: IpProtocol,
+        observer: E::RawEventObserver,
+    ) -> Self {
+        let socket = Arc::new(IpRawSocketBg::new(
+            iface,
+            ip_version,
+            IpRawSocketKind::Raw(protocol),
+        ));
+        socket.observer.call_once(|| observer);
+        socket.iface.common().register_raw_socket(socket.clone());
+
+        Self(socket)
+    }
+
+    /// Creates an ICMP echo socket that handles echo messages of the identifier.
+    ///
+    /// If `ident` is `None`, an unused identifier will be allocated.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new_ping(
+        iface: Arc<dyn Iface<E>>,
+        ip_version: IpVersion,
+        ident: Option<u16>,
+        observer: E::RawEventObserver,
+    ) -> Result<Self, BindError> {
+        let socket = iface
+            .common()
+            .register_ping_socket(ip_version, ident, |ident| {
+                let socket = Arc::new(IpRawSocketBg::new(
+                    iface.clone(),
+                    ip_version,
+                    IpRawSocketKind::Ping(ident),
+                ));
+                socket.observer.call_once(|| observer);
+                socket
+            })?;
+
+        Ok(Self(socket))
+    }
+
+    /// Sends an IP payload to the remote address.
+    ///
+    /// For ICMP echo sockets, the payload must be an ICMP echo request. Its identifier will be
+    /// replaced by the identifier of the socket and its checksum will be computed automatically.
+    /// The checksum of ICMPv6 messages sent via raw IPv6 sockets is also computed automatically,
+    /// as required by RFC 3542.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn send(
+        &self,
+        remote_addr: IpAddress,
+        hop_limit: u8,
+        mut payload: Vec<u8>,
+    ) -> Result<(), SendError> {
+        // The remote address cannot be reached if it has a different IP version.
+        if remote_addr.version() != self.0.ip_version {
+            return Err(SendError::Unaddressable);
+        }
+        let local_addr = self.local_addr().ok_or(SendError::Unaddressable)?;
+
+        let protocol = match (self.0.kind, self.0.ip_version) {
+            (IpRawSocketKind::Raw(protocol), _) => protocol,
+            (IpRawSocketKind::Ping(_), IpVersion::Ipv4) => IpProtocol::Icmp,
+            (IpRawSocketKind::Ping(_), IpVersion::Ipv6) => IpProtocol::Icmpv6,
+        };
+        let ping_ident = match self.0.kind {
+            IpRawSocketKind::Raw(_) => None,
+            IpRawSocketKind::Ping(ident) => Some(ident),
+        };
+
+        match (local_addr, remote_addr, ping_ident) {
+            (IpAddress::Ipv4(_), _, Some(ident)) => fill_icmpv4_echo_request(&mut payload, ident)?,
+            (IpAddress::Ipv6(local_addr), IpAddress::Ipv6(remote_addr), _)
+                if protocol == IpProtocol::Icmpv6 =>
+            {
+                fill_icmpv6(&mut payload, ping_ident, &local_addr, &remote_addr)?
+            }
+            _ => (),
+        }
+
+        let ip_repr = IpRepr::new(local_addr, remote_addr, protocol, payload.len(), hop_limit);
+        self.queue_packet(ip_repr, payload)
+    }
+
+    /// Sends an IPv4 packet whose header is provided by the caller.
+    ///
+    /// This is what happens if `IP_HDRINCL` is set for raw IPv4 sockets. Like Linux, the total
+    /// length and the checksum in the header are always filled in, and the source address is
+    /// filled in if it is unspecified.
+    ///
+    /// Note that IP options in the header, if any, are discarded.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn send_with_header(&self, packet: Vec<u8>) -> Result<(), SendError> {
+        if self.0.ip_version != IpVersion::Ipv4 {
+            return Err(SendError::Unaddressable);
+        }
+
+        if packet.len() < IPV4_HEADER_LEN {
+            return Err(SendError::Malformed);
+        }
+        let ip_pkt = Ipv4Packet::new_unchecked(packet.as_slice());
+        let header_len = usize::from(ip_pkt.header_len());
+        if ip_pkt.version() != 4 || header_len < IPV4_HEADER_LEN || header_len > packet.len() {
+            return Err(SendError::Malformed);
+        }
+
+        let src_addr = if ip_pkt.src_addr().is_unspecified() {
+            self.0.iface.ipv4_addr().ok_or(SendError::Unaddressable)?
+        } else {
+            ip_pkt.src_addr()
+        };
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr,
+            dst_addr: ip_pkt.dst_addr(),
+            next_header: ip_pkt.next_header(),
+            payload_len: packet.len() - header_len,
+            hop_limit: ip_pkt.hop_limit(),
+        });
+
+        self.queue_packet(ip_repr, packet[header_len..].to_vec())
+    }
+
+    fn queue_packet(&self, ip_repr: IpRepr, payload: Vec<u8>) -> Result<(), SendError> {
+        // Packets larger than the MTU cannot be sent because IP fragmentation is not supported.
+        if ip_repr.buffer_len() > self.0.iface.mtu() || payload.len() > RAW_SEND_BUF_LEN {
+            return Err(SendError::TooLarge);
+        }
+
+        let mut queues = self.0.queues.lock();
+
+        if queues.send_len + payload.len() > RAW_SEND_BUF_LEN {
+            return Err(SendError::BufferFull);
+        }
+
+        queues.send_len += payload.len();
+        queues.send_queue.push_back((ip_repr, payload));
+        self.0.need_dispatch.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Receives a packet.
+    ///
+    /// For raw IPv4 sockets, the packet includes the IP header. For raw IPv6 sockets, the packet
+    /// does not include the IP header. For ICMP echo sockets, the packet is the ICMP message.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn recv<F, R>(&self, f: F) -> Result<R, RecvError>
+    where
+        F: FnOnce(&[u8], IpAddress) -> R,
+    {
+        let mut queues = self.0.queues.lock();
+
+        let Some((src_addr, data)) = queues.recv_queue.pop_front() else {
+            return Err(RecvError::Exhausted);
+        };
+        queues.recv_len -= data.len();
+        drop(queues);
+
+        Ok(f(&data, src_addr))
+    }
+
+    /// Returns whether there are packets to receive.
+    pub fn can_recv(&self) -> bool {
+        !self.0.queues.lock().recv_queue.is_empty()
+    }
+
+    /// Returns whether there is free space in the send queue.
+    pub fn can_send(&self) -> bool {
+        self.0.queues.lock().send_len < RAW_SEND_BUF_LEN
+    }
+
+    /// Returns the local address, which is the address of the iface.
+    pub fn local_addr(&self) -> Option<IpAddress> {
+        match self.0.ip_version {
+            IpVersion::Ipv4 => self.0.iface.ipv4_addr().map(IpAddress::Ipv4),
+            IpVersion::Ipv6 => self.0.iface.ipv6_addr().map(IpAddress::Ipv6),
+        }
+    }
+
+    /// Returns the kind of the socket.
+    pub fn kind(&self) -> IpRawSocketKind {
+        self.0.kind
+    }
+
+    pub fn iface(&self) -> &Arc<dyn Iface<E>> {
+        &self.0.iface
+    }
+}
+
+impl<E: Ext> Drop for IpRawSocket<E> {
+    fn drop(&mut self) {
+        // A raw IP socket can be removed immediately.
+        self.0.iface.common().remove_raw_socket(&self.0);
+    }
+}
+
+/// Fills in the identifier and the checksum of an ICMPv4 echo request.
+fn fill_icmpv4_echo_request(payload: &mut [u8], ident: u16) -> Result<(), SendError> {
+    let mut icmp_pkt = Icmpv4Packet::new_checked(payload).map_err(|_| SendError::Malformed)?;
+    if icmp_pkt.msg_type() != Icmpv4Message::EchoRequest || icmp_pkt.msg_code() != 0 {
+        return Err(SendError::Malformed);
+    }
+
+    icmp_pkt.set_echo_ident(ident);
+    icmp_pkt.fill_checksum();
+
+    Ok(())
+}
+
+/// Fills in the checksum of an ICMPv6 message.
+///
+/// If `ping_ident` is `Some(_)`, the message must be an ICMPv6 echo request and its identifier
+/// will also be filled in.
+fn fill_icmpv6(
+    payload: &mut [u8],
+    ping_ident: Option<u16>,
+    src_addr: &Ipv6Address,
+    dst_addr: &Ipv6Address,
+) -> Result<(), SendError> {
+    let mut icmp_pkt = Icmpv6Packet::new_checked(payload).map_err(|_| SendError::Malformed)?;
+
+    if let Some(ident) = ping_ident {
+        if icmp_pkt.msg_type() != Icmpv6Message::EchoRequest || icmp_pkt.msg_code() != 0 {
+            return Err(SendError::Malformed);
+        }
+        icmp_pkt.set_echo_ident(ident);
+    }
+    icmp_pkt.fill_checksum(src_addr, dst_addr);
+
+    Ok(())
+}
+
+// Raw IP socket buffer sizes:
+const RAW_SEND_BUF_LEN: usize = 65536;
+const RAW_RECV_BUF_LEN: usize = 65536;
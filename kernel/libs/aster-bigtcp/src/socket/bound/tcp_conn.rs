@@ -297,6 +297,9 @@ impl<E: Ext> TcpConnection<E> {
         let Some(local_endpoint) = bound.endpoint() else {
             return Err((bound, ConnectError::Unaddressable));
         };
+        if remote_endpoint.addr.version() != bound.ip_version() {
+            return Err((bound, ConnectError::Unaddressable));
+        }
 
         let iface = bound.iface().clone();
         // We have to lock `interface` before locking `sockets`
@@ -647,10 +650,14 @@ impl<E: Ext> TcpConnectionBg<E> {
         let mut events = SocketEvents::empty();
 
         let mut reply = None;
-        let (cx, pending) = iface.inner_mut();
+        let (cx, pending, multicast_groups) = iface.inner_mut();
         socket
             .dispatch(cx, |cx, (ip_repr, tcp_repr)| {
-                reply = dispatch(PollableIfaceMut::new(cx, pending), &ip_repr, &tcp_repr);
+                reply = dispatch(
+                    PollableIfaceMut::new(cx, pending, multicast_groups),
+                    &ip_repr,
+                    &tcp_repr,
+                );
                 Ok::<(), ()>(())
             })
             .unwrap();
@@ -1,14 +1,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{boxed::Box, sync::Arc};
-use core::sync::atomic::{AtomicBool, Ordering};
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
     iface::Context,
     socket::udp::UdpMetadata,
-    wire::{IpRepr, UdpRepr},
+    wire::{IpAddress, IpEndpoint, IpRepr, UdpRepr},
 };
 
 use super::common::{Inner, Socket, SocketBg};
@@ -21,12 +21,42 @@ use crate::{
 
 pub type UdpSocket<E> = Socket<UdpSocketInner, E>;
 
+/// An ICMP error message caused by a packet sent from a UDP socket.
+#[derive(Debug, Clone)]
+pub struct IcmpError {
+    /// The address of the node that generated the ICMP error message.
+    pub offender: IpAddress,
+    /// The ICMP message type.
+    pub icmp_type: u8,
+    /// The ICMP message code.
+    pub icmp_code: u8,
+    /// The type-specific information (e.g., the MTU for "Packet Too Big" messages).
+    pub info: u32,
+    /// The destination of the offending packet.
+    pub remote_endpoint: IpEndpoint,
+    /// The UDP payload of the offending packet, which may be truncated.
+    pub payload: Vec<u8>,
+}
+
+/// The maximum number of ICMP errors that can be queued in a UDP socket.
+///
+/// New errors will be dropped if the queue is full.
+const ICMP_ERROR_QUEUE_LEN: usize = 64;
+
 /// States needed by [`UdpSocketBg`].
 pub struct UdpSocketInner {
     socket: SpinLock<Box<RawUdpSocket>, BottomHalfDisabled>,
     need_dispatch: AtomicBool,
+    icmp_errors: SpinLock<VecDeque<IcmpError>, BottomHalfDisabled>,
+    multicast_hop_limit: AtomicU8,
+    multicast_loop: AtomicBool,
 }
 
+/// The default hop limit of multicast packets.
+///
+/// Like Linux, multicast packets do not leave the local network by default.
+const DEFAULT_MULTICAST_HOP_LIMIT: u8 = 1;
+
 impl<E: Ext> Inner<E> for UdpSocketInner {
     type Observer = E::UdpEventObserver;
 
@@ -76,7 +106,14 @@ impl<E: Ext> UdpSocketBg<E> {
         let mut socket = self.inner.socket.lock();
 
         socket
-            .dispatch(cx, |cx, _meta, (ip_repr, udp_repr, udp_payload)| {
+            .dispatch(cx, |cx, _meta, (mut ip_repr, udp_repr, udp_payload)| {
+                if ip_repr.dst_addr().is_multicast() {
+                    let hop_limit = self.inner.multicast_hop_limit.load(Ordering::Relaxed);
+                    match &mut ip_repr {
+                        IpRepr::Ipv4(ipv4_repr) => ipv4_repr.hop_limit = hop_limit,
+                        IpRepr::Ipv6(ipv6_repr) => ipv6_repr.hop_limit = hop_limit,
+                    }
+                }
                 dispatch(cx, &ip_repr, &udp_repr, udp_payload);
                 Ok::<(), ()>(())
             })
@@ -90,6 +127,34 @@ impl<E: Ext> UdpSocketBg<E> {
             .store(socket.send_queue() > 0, Ordering::Relaxed);
     }
 
+    /// Returns whether an ICMP error message caused by a packet sent from `local_endpoint` should
+    /// be processed by the socket.
+    pub(crate) fn can_process_icmp_error(&self, local_endpoint: &IpEndpoint) -> bool {
+        let Some(bound_endpoint) = self.bound.endpoint() else {
+            return false;
+        };
+
+        bound_endpoint.port == local_endpoint.port
+            && (bound_endpoint.addr.is_unspecified() || bound_endpoint.addr == local_endpoint.addr)
+    }
+
+    /// Queues an ICMP error message caused by a packet sent from the socket.
+    pub(crate) fn process_icmp_error(&self, error: IcmpError) {
+        let mut icmp_errors = self.inner.icmp_errors.lock();
+        if icmp_errors.len() >= ICMP_ERROR_QUEUE_LEN {
+            return;
+        }
+        icmp_errors.push_back(error);
+        drop(icmp_errors);
+
+        self.notify_events(SocketEvents::ERROR);
+    }
+
+    /// Returns whether multicast packets sent from the socket should be looped back.
+    pub(crate) fn multicast_loop(&self) -> bool {
+        self.inner.multicast_loop.load(Ordering::Relaxed)
+    }
+
     /// Returns whether the socket _may_ generate an outgoing packet.
     ///
     /// The check is intended to be lock-free and fast, but may have false positives.
@@ -123,6 +188,9 @@ impl<E: Ext> UdpSocket<E> {
         let inner = UdpSocketInner {
             socket: SpinLock::new(socket),
             need_dispatch: AtomicBool::new(false),
+            icmp_errors: SpinLock::new(VecDeque::new()),
+            multicast_hop_limit: AtomicU8::new(DEFAULT_MULTICAST_HOP_LIMIT),
+            multicast_loop: AtomicBool::new(true),
         };
 
         let socket = Self::new(bound, inner);
@@ -147,6 +215,13 @@ impl<E: Ext> UdpSocket<E> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
+        let meta = meta.into();
+
+        // The remote endpoint cannot be reached if it has a different IP version.
+        if meta.endpoint.addr.version() != self.0.bound.ip_version() {
+            return Err(SendError::Unaddressable);
+        }
+
         let mut socket = self.0.inner.socket.lock();
 
         if size > socket.packet_send_capacity() {
@@ -182,6 +257,37 @@ impl<E: Ext> UdpSocket<E> {
         Ok(result)
     }
 
+    /// Takes the oldest ICMP error message caused by a packet sent from the socket.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn pop_icmp_error(&self) -> Option<IcmpError> {
+        self.0.inner.icmp_errors.lock().pop_front()
+    }
+
+    /// Sets the hop limit of multicast packets.
+    ///
+    /// The new hop limit also applies to packets that are queued but not yet sent.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_multicast_hop_limit(&self, hop_limit: u8) {
+        self.0
+            .inner
+            .multicast_hop_limit
+            .store(hop_limit, Ordering::Relaxed);
+    }
+
+    /// Sets whether multicast packets sent from the socket are looped back.
+    ///
+    /// Multicast packets are looped back only if the iface has joined the multicast group.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn set_multicast_loop(&self, multicast_loop: bool) {
+        self.0
+            .inner
+            .multicast_loop
+            .store(multicast_loop, Ordering::Relaxed);
+    }
+
     /// Calls `f` with an immutable reference to the associated [`RawUdpSocket`].
     //
     // NOTE: If a mutable reference is required, add a method above that correctly updates the next
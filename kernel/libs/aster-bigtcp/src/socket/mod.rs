@@ -6,9 +6,13 @@ mod option;
 mod unbound;
 
 pub use bound::{
-    ConnectState, NeedIfacePoll, RawTcpSocketExt, TcpConnection, TcpListener, UdpSocket,
+    ConnectState, IcmpError, IpRawSocket, IpRawSocketKind, LinkFrameFilter, LinkFrameMeta,
+    LinkPacketType, LinkSocket, LinkSocketKind, NeedIfacePoll, RawTcpSocketExt, TcpConnection,
+    TcpListener, UdpSocket, LINK_PROTOCOL_ALL,
+};
+pub(crate) use bound::{
+    IpRawSocketBg, LinkSocketBg, TcpConnectionBg, TcpListenerBg, TcpProcessResult, UdpSocketBg,
 };
-pub(crate) use bound::{TcpConnectionBg, TcpListenerBg, TcpProcessResult, UdpSocketBg};
 pub use event::{SocketEventObserver, SocketEvents};
 pub use option::{RawTcpOption, RawTcpSetOption};
 pub use unbound::{
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-//! This module defines the socket table, which manages all TCP and UDP sockets,
+//! This module defines the socket table, which manages all TCP, UDP, and raw IP sockets,
 //! for efficiently inserting, looking up, and removing sockets.
 
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
@@ -12,7 +12,7 @@ use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
 use crate::{
     ext::Ext,
-    socket::{TcpConnectionBg, TcpListenerBg, UdpSocketBg},
+    socket::{IpRawSocketBg, TcpConnectionBg, TcpListenerBg, UdpSocketBg},
     wire::PortNum,
 };
 
@@ -103,26 +103,32 @@ const fn hash_local_remote(
     remote_addr: IpAddress,
     remote_port: PortNum,
 ) -> SocketHash {
-    // FIXME: Deal with IPv6 addresses once IPv6 is supported.
-    let IpAddress::Ipv4(local_ipv4) = local_addr;
-    let IpAddress::Ipv4(remote_ipv4) = remote_addr;
-
     jhash_3vals(
-        local_ipv4.to_bits(),
-        remote_ipv4.to_bits(),
+        fold_addr(local_addr),
+        fold_addr(remote_addr),
         (local_port as u32).wrapping_shl(16) | remote_port as u32,
         HASH_SECRET.wrapping_add(NET_HASHMIX),
     )
 }
 
 const fn hash_addr_port(addr: IpAddress, port: PortNum) -> SocketHash {
-    // FIXME: Deal with IPv6 addresses once IPv6 is supported.
-    let IpAddress::Ipv4(ipv4_addr) = addr;
+    jhash_1vals(fold_addr(addr), NET_HASHMIX) ^ (port as u32)
+}
 
-    jhash_1vals(ipv4_addr.to_bits(), NET_HASHMIX) ^ (port as u32)
+/// Folds an IP address into 32 bits.
+///
+/// IPv6 addresses are folded by XORing their 32-bit words, like `ipv6_addr_hash` in Linux.
+const fn fold_addr(addr: IpAddress) -> u32 {
+    match addr {
+        IpAddress::Ipv4(ipv4_addr) => ipv4_addr.to_bits(),
+        IpAddress::Ipv6(ipv6_addr) => {
+            let bits = ipv6_addr.to_bits();
+            (bits ^ (bits >> 32) ^ (bits >> 64) ^ (bits >> 96)) as u32
+        }
+    }
 }
 
-/// The socket table manages TCP and UDP sockets.
+/// The socket table manages TCP, UDP, and raw IP sockets.
 ///
 /// Unlike the Linux inet hashtable, which is shared across a single network namespace,
 /// this table is currently limited to a single interface.
@@ -142,6 +148,9 @@ pub(crate) struct SocketTable<E: Ext> {
     // Note that multiple UDP sockets can be bound to the same address,
     // so we cannot use (addr, port) as a _unique_ key for UDP sockets.
     udp_sockets: Vec<Arc<UdpSocketBg<E>>>,
+    // Raw IP sockets (including ICMP echo sockets) are looked up by the IP protocol or the ICMP
+    // echo identifier. Since there are usually only a few of them, we simply keep them in a list.
+    raw_sockets: Vec<Arc<IpRawSocketBg<E>>>,
 }
 
 // On Linux, the number of buckets is determined at runtime based on the available memory.
@@ -167,10 +176,13 @@ impl<E: Ext> SocketTable<E> {
 
         let udp_sockets = Vec::new();
 
+        let raw_sockets = Vec::new();
+
         Self {
             listener_buckets,
             connection_buckets,
             udp_sockets,
+            raw_sockets,
         }
     }
 
@@ -234,6 +246,14 @@ impl<E: Ext> SocketTable<E> {
         self.udp_sockets.push(udp_socket);
     }
 
+    pub(crate) fn insert_raw_socket(&mut self, raw_socket: Arc<IpRawSocketBg<E>>) {
+        debug_assert!(!self
+            .raw_sockets
+            .iter()
+            .any(|socket| Arc::ptr_eq(socket, &raw_socket)));
+        self.raw_sockets.push(raw_socket);
+    }
+
     pub(crate) fn lookup_listener(&self, key: &ListenerKey) -> Option<&Arc<TcpListenerBg<E>>> {
         let bucket = {
             let hash = key.hash();
@@ -312,6 +332,21 @@ impl<E: Ext> SocketTable<E> {
     pub(crate) fn udp_socket_iter(&self) -> impl Iterator<Item = &Arc<UdpSocketBg<E>>> {
         self.udp_sockets.iter()
     }
+
+    pub(crate) fn remove_raw_socket(
+        &mut self,
+        socket: &Arc<IpRawSocketBg<E>>,
+    ) -> Option<Arc<IpRawSocketBg<E>>> {
+        let index = self
+            .raw_sockets
+            .iter()
+            .position(|raw_socket| Arc::ptr_eq(raw_socket, socket))?;
+        Some(self.raw_sockets.swap_remove(index))
+    }
+
+    pub(crate) fn raw_socket_iter(&self) -> impl Iterator<Item = &Arc<IpRawSocketBg<E>>> {
+        self.raw_sockets.iter()
+    }
 }
 
 impl<E: Ext> Default for SocketTable<E> {
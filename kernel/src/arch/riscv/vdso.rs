@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The vDSO image of RISC-V.
+//!
+//! Unlike x86-64, where the vDSO library of Linux is loaded from the file system, the RISC-V vDSO
+//! is built into the kernel. See `vdso.S` for its layout.
+
+use core::arch::global_asm;
+
+global_asm!(include_str!("vdso.S"));
+
+/// Returns the vDSO image, which is an ELF shared object to be mapped into the user space.
+pub fn vdso_image() -> &'static [u8] {
+    // The symbols are defined in `vdso.S`.
+    extern "C" {
+        fn __vdso_image_start();
+        fn __vdso_image_end();
+    }
+
+    let start = __vdso_image_start as usize;
+    let len = __vdso_image_end as usize - start;
+
+    // SAFETY: The memory between the two symbols contains the vDSO image, which lives in the
+    // read-only data section of the kernel and is never modified.
+    unsafe { core::slice::from_raw_parts(start as *const u8, len) }
+}
@@ -5,6 +5,7 @@ mod pty;
 mod random;
 mod shm;
 pub mod tty;
+mod tun;
 mod urandom;
 mod zero;
 
@@ -57,6 +58,8 @@ pub fn init() -> Result<()> {
 
     shm::init()?;
 
+    add_node(Arc::new(tun::TunDevice), "net/tun")?;
+
     Ok(())
 }
 
@@ -79,6 +82,7 @@ pub fn get_device(dev: usize) -> Result<Arc<dyn Device>> {
         (5, 0) => Ok(Arc::new(tty::TtyDevice)),
         (1, 8) => Ok(Arc::new(random::Random)),
         (1, 9) => Ok(Arc::new(urandom::Urandom)),
+        (10, 200) => Ok(Arc::new(tun::TunDevice)),
         _ => return_errno_with_message!(Errno::EINVAL, "unsupported device"),
     }
 }
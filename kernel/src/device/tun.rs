@@ -0,0 +1,508 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The TUN/TAP device.
+//!
+//! Opening `/dev/net/tun` and issuing `TUNSETIFF` creates an iface whose packets are sent to and
+//! received from the file. A TUN iface works on IP packets, while a TAP iface works on Ethernet
+//! frames.
+//!
+//! Reference: <https://docs.kernel.org/networking/tuntap.html>.
+
+use aster_bigtcp::{
+    device::{Medium, QueueDevice, WithDevice},
+    iface::{EtherIface, InterfaceFlags, InterfaceType, IpIface},
+    wire::EthernetAddress,
+};
+use spin::Once;
+
+use crate::{
+    current_userspace,
+    events::IoEvents,
+    fs::{
+        device::{Device, DeviceId, DeviceType},
+        inode_handle::FileIo,
+        utils::{IoctlCmd, StatusFlags},
+    },
+    net::iface::{iter_all_ifaces, register_iface, unregister_iface, Iface, PollScheduler},
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    util::random::getrandom,
+};
+
+/// Same major number with Linux.
+const TUN_MAJOR_NUM: u32 = 10;
+/// Same minor number with Linux.
+const TUN_MINOR_NUM: u32 = 200;
+
+/// The TUN/TAP device (i.e., `/dev/net/tun`).
+///
+/// Every time the device is opened, a new [`TunFile`] is returned. The file can then be attached
+/// to a new iface via `TUNSETIFF`.
+pub struct TunDevice;
+
+impl Device for TunDevice {
+    fn type_(&self) -> DeviceType {
+        DeviceType::CharDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        DeviceId::new(TUN_MAJOR_NUM, TUN_MINOR_NUM)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(Some(Arc::new(TunFile::new())))
+    }
+}
+
+impl Pollable for TunDevice {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for TunDevice {
+    fn read(&self, _writer: &mut VmWriter, _status_flags: StatusFlags) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "cannot read the TUN/TAP device");
+    }
+
+    fn write(&self, _reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "cannot write the TUN/TAP device");
+    }
+}
+
+/// An opened TUN/TAP device.
+struct TunFile {
+    iface: Once<TunIface>,
+    pollee: Pollee,
+}
+
+impl TunFile {
+    fn new() -> Self {
+        Self {
+            iface: Once::new(),
+            pollee: Pollee::new(),
+        }
+    }
+
+    fn attached_iface(&self) -> Result<&TunIface> {
+        self.iface.get().ok_or_else(|| {
+            Error::with_message(Errno::EBADFD, "the file is not attached to an interface")
+        })
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let Some(tun_iface) = self.iface.get() else {
+            return IoEvents::ERR;
+        };
+
+        let mut events = IoEvents::OUT;
+
+        if tun_iface.driver.device.lock().has_tx() {
+            events |= IoEvents::IN;
+        }
+
+        events
+    }
+
+    fn set_iff(&self, arg: usize) -> Result<()> {
+        // Like Linux, creating a new iface requires `CAP_NET_ADMIN`.
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+            return_errno_with_message!(Errno::EPERM, "creating ifaces requires `CAP_NET_ADMIN`");
+        }
+
+        let mut req: CIfReq = current_userspace!().read_val(arg)?;
+
+        let flags = TunFlags::from_bits(req.flags)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the flags are invalid"))?;
+        if flags.intersects(!TunFlags::SUPPORTED) {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "the flags are not supported");
+        }
+        let template = match (flags.contains(TunFlags::TUN), flags.contains(TunFlags::TAP)) {
+            (true, false) => "tun%d",
+            (false, true) => "tap%d",
+            _ => return_errno_with_message!(Errno::EINVAL, "the device type is invalid"),
+        };
+
+        let name = req.name()?;
+        let template = if name.is_empty() { template } else { name };
+
+        // Hold the lock so that no one else can take the name before we register the iface.
+        let _guard = CREATION_LOCK.lock();
+
+        if self.iface.is_completed() {
+            return_errno_with_message!(Errno::EEXIST, "the file is already attached");
+        }
+
+        let name = alloc_iface_name(template)?;
+
+        // Like Linux, the name of the new iface is reported back to the user.
+        req.set_name(&name);
+        current_userspace!().write_val(arg, &req)?;
+
+        let tun_iface = TunIface::new(flags, name, self.pollee.clone())?;
+        register_iface(tun_iface.iface.clone());
+        self.iface.call_once(|| tun_iface);
+
+        Ok(())
+    }
+
+    fn get_iff(&self, arg: usize) -> Result<()> {
+        let tun_iface = self.attached_iface()?;
+
+        let mut req = CIfReq::new_zeroed();
+        req.set_name(tun_iface.iface.name());
+        req.flags = tun_iface.flags.bits();
+        current_userspace!().write_val(arg, &req)?;
+
+        Ok(())
+    }
+}
+
+impl Pollable for TunFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl FileIo for TunFile {
+    fn read(&self, writer: &mut VmWriter, status_flags: StatusFlags) -> Result<usize> {
+        let tun_iface = self.attached_iface()?;
+        let has_pi = !tun_iface.flags.contains(TunFlags::NO_PI);
+
+        if has_pi && writer.avail() < size_of::<CTunPi>() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small");
+        }
+
+        let try_pop = || {
+            tun_iface
+                .driver
+                .device
+                .lock()
+                .pop_tx()
+                .ok_or_else(|| Error::with_message(Errno::EAGAIN, "no packets are available"))
+        };
+        // TODO: Add support for timeout
+        let packet = if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            try_pop()?
+        } else {
+            self.wait_events(IoEvents::IN, None, try_pop)?
+        };
+        self.pollee.invalidate();
+
+        let mut read_len = 0;
+
+        if has_pi {
+            let mut pi_flags = 0;
+            if writer.avail() - size_of::<CTunPi>() < packet.len() {
+                pi_flags |= CTunPi::STRIP;
+            }
+            let pi = CTunPi {
+                flags: pi_flags,
+                proto: tun_iface.proto_of(&packet).to_be(),
+            };
+            writer.write_val(&pi)?;
+            read_len += size_of::<CTunPi>();
+        }
+
+        // Like Linux, the packet is truncated if the buffer is too small.
+        read_len += writer.write_fallible(&mut packet.as_slice().into())?;
+
+        Ok(read_len)
+    }
+
+    fn write(&self, reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        let tun_iface = self.attached_iface()?;
+        let write_len = reader.remain();
+
+        if !tun_iface.flags.contains(TunFlags::NO_PI) {
+            // The packet information is not useful because we can determine the packet type from
+            // the packet itself.
+            let _pi = reader.read_val::<CTunPi>().map_err(|_| {
+                Error::with_message(Errno::EINVAL, "the packet information is incomplete")
+            })?;
+        }
+
+        if reader.remain() > MAX_PACKET_LEN {
+            return_errno_with_message!(Errno::EINVAL, "the packet is too large");
+        }
+        if !tun_iface.iface.flags().contains(InterfaceFlags::UP) {
+            return_errno_with_message!(Errno::EIO, "the interface is down");
+        }
+
+        let mut packet = vec![0u8; reader.remain()];
+        reader.read_fallible(&mut packet.as_mut_slice().into())?;
+
+        // Like Linux, the packet is silently dropped if it cannot be queued.
+        let is_queued = tun_iface.driver.device.lock().push_rx(packet);
+        if is_queued {
+            tun_iface.iface.poll();
+        }
+
+        Ok(write_len)
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::TUNSETIFF => self.set_iff(arg)?,
+            IoctlCmd::TUNGETIFF => self.get_iff(arg)?,
+            _ => return_errno_with_message!(Errno::EINVAL, "the ioctl command is unknown"),
+        }
+
+        Ok(0)
+    }
+}
+
+impl Drop for TunFile {
+    fn drop(&mut self) {
+        // TODO: Support persistent ifaces (i.e., `TUNSETPERSIST`), which outlive the file.
+        if let Some(tun_iface) = self.iface.get() {
+            unregister_iface(&tun_iface.iface);
+        }
+    }
+}
+
+/// The lock that serializes the creation of TUN/TAP ifaces.
+static CREATION_LOCK: Mutex<()> = Mutex::new(());
+
+/// The maximum number of packets in each queue, which is the same as Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/drivers/net/tun.c#L134>.
+const QUEUE_LEN: usize = 500;
+
+/// The default MTU, which is the same as Linux.
+const DEFAULT_MTU: usize = 1500;
+
+/// The length of Ethernet headers.
+const ETHER_HEADER_LEN: usize = 14;
+
+/// The maximum length of packets written by the user.
+///
+/// Packets that are larger than the MTU are dropped by the device. This limit only prevents the
+/// user from requesting huge allocations.
+const MAX_PACKET_LEN: usize = u16::MAX as usize;
+
+/// An iface that is attached to a [`TunFile`].
+struct TunIface {
+    iface: Arc<Iface>,
+    driver: Arc<TunDriver>,
+    flags: TunFlags,
+}
+
+struct TunDriver {
+    device: Mutex<QueueDevice>,
+    /// The pollee of the [`TunFile`], which is notified when packets are transmitted.
+    pollee: Pollee,
+}
+
+struct Wrapper(Arc<TunDriver>);
+
+impl WithDevice for Wrapper {
+    type Device = QueueDevice;
+
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Self::Device) -> R,
+    {
+        let mut device = self.0.device.lock();
+        let res = f(&mut device);
+
+        if device.has_tx() {
+            self.0.pollee.notify(IoEvents::IN);
+        }
+
+        res
+    }
+}
+
+impl TunIface {
+    fn new(flags: TunFlags, name: String, pollee: Pollee) -> Result<Self> {
+        let is_tap = flags.contains(TunFlags::TAP);
+
+        let (medium, mtu) = if is_tap {
+            (Medium::Ethernet, DEFAULT_MTU + ETHER_HEADER_LEN)
+        } else {
+            (Medium::Ip, DEFAULT_MTU)
+        };
+        let driver = Arc::new(TunDriver {
+            device: Mutex::new(QueueDevice::new(medium, mtu, QUEUE_LEN)),
+            pollee,
+        });
+
+        // Like Linux, the iface is down until it is brought up by the user. The addresses are
+        // also configured by the user.
+        let iface = if is_tap {
+            EtherIface::new(
+                Wrapper(driver.clone()),
+                random_ether_addr()?,
+                None,
+                None,
+                name,
+                PollScheduler::new(),
+                InterfaceFlags::BROADCAST | InterfaceFlags::MULTICAST,
+            ) as Arc<Iface>
+        } else {
+            IpIface::new(
+                Wrapper(driver.clone()),
+                None,
+                None,
+                name,
+                PollScheduler::new(),
+                InterfaceType::NONE,
+                InterfaceFlags::POINTOPOINT | InterfaceFlags::NOARP | InterfaceFlags::MULTICAST,
+            ) as Arc<Iface>
+        };
+
+        Ok(Self {
+            iface,
+            driver,
+            flags,
+        })
+    }
+
+    /// Returns the protocol of the packet, which is reported in the packet information.
+    fn proto_of(&self, packet: &[u8]) -> u16 {
+        const ETH_P_IP: u16 = 0x0800;
+        const ETH_P_IPV6: u16 = 0x86DD;
+
+        if self.flags.contains(TunFlags::TAP) {
+            return packet
+                .get(12..ETHER_HEADER_LEN)
+                .map_or(0, |proto| u16::from_be_bytes([proto[0], proto[1]]));
+        }
+
+        match packet.first().map(|byte| byte >> 4) {
+            Some(4) => ETH_P_IP,
+            Some(6) => ETH_P_IPV6,
+            _ => 0,
+        }
+    }
+}
+
+/// Generates a random, locally administered, unicast Ethernet address.
+fn random_ether_addr() -> Result<EthernetAddress> {
+    let mut addr = [0u8; 6];
+    getrandom(&mut addr)?;
+
+    addr[0] &= !0x01; // Clear the multicast bit
+    addr[0] |= 0x02; // Set the locally administered bit
+
+    Ok(EthernetAddress(addr))
+}
+
+/// Allocates an iface name according to the template.
+///
+/// Like Linux, `%d` in the template is replaced by the smallest number that makes the name
+/// unique.
+fn alloc_iface_name(template: &str) -> Result<String> {
+    let used_names: Vec<String> = iter_all_ifaces()
+        .map(|iface| iface.name().to_string())
+        .collect();
+
+    if !template.contains("%d") {
+        if used_names.iter().any(|name| name == template) {
+            // TODO: Support attaching to existing persistent ifaces.
+            return_errno_with_message!(Errno::EBUSY, "the interface already exists");
+        }
+        return Ok(template.to_string());
+    }
+
+    for nth in 0.. {
+        let name = template.replacen("%d", &nth.to_string(), 1);
+        if name.len() >= IFNAMSIZ {
+            break;
+        }
+        if !used_names.contains(&name) {
+            return Ok(name);
+        }
+    }
+
+    return_errno_with_message!(Errno::ENFILE, "no interface names are available");
+}
+
+bitflags! {
+    /// Flags for `TUNSETIFF`.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_tun.h#L66>.
+    struct TunFlags: u16 {
+        const TUN         = 0x0001;
+        const TAP         = 0x0002;
+        const NAPI        = 0x0010;
+        const NAPI_FRAGS  = 0x0020;
+        const NO_CARRIER  = 0x0040;
+        const MULTI_QUEUE = 0x0100;
+        const NO_PI       = 0x1000;
+        /// This flag has no effect.
+        const ONE_QUEUE   = 0x2000;
+        const VNET_HDR    = 0x4000;
+        const TUN_EXCL    = 0x8000;
+
+        const SUPPORTED = Self::TUN.bits
+            | Self::TAP.bits
+            | Self::NO_PI.bits
+            | Self::ONE_QUEUE.bits
+            | Self::TUN_EXCL.bits;
+    }
+}
+
+/// The maximum length of iface names, including the trailing NUL.
+const IFNAMSIZ: usize = 16;
+
+/// `ifreq` in Linux, with the union truncated to `ifr_flags`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if.h#L234>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIfReq {
+    name: [u8; IFNAMSIZ],
+    flags: u16,
+    _pad: [u8; 22],
+}
+
+impl CIfReq {
+    fn name(&self) -> Result<&str> {
+        // Like Linux, the last byte is always treated as the trailing NUL.
+        let name = &self.name[..IFNAMSIZ - 1];
+        let len = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+        let name = core::str::from_utf8(&name[..len])
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the interface name is invalid"))?;
+
+        if name == "."
+            || name == ".."
+            || name.contains(['/', ':'])
+            || name.contains(char::is_whitespace)
+        {
+            return_errno_with_message!(Errno::EINVAL, "the interface name is invalid");
+        }
+
+        Ok(name)
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = [0; IFNAMSIZ];
+        self.name[..name.len()].copy_from_slice(name.as_bytes());
+    }
+}
+
+/// `tun_pi` in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_tun.h#L90>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CTunPi {
+    flags: u16,
+    /// The protocol in network byte order
+    proto: u16,
+}
+
+impl CTunPi {
+    /// The packet is truncated because the buffer is too small.
+    const STRIP: u16 = 0x0001;
+}
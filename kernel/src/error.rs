@@ -304,6 +304,39 @@ impl From<cpio_decoder::error::Error> for Error {
     }
 }
 
+impl From<another_ext4::Ext4Error> for Error {
+    fn from(error: another_ext4::Ext4Error) -> Self {
+        use another_ext4::ErrCode;
+        let errno = match error.code() {
+            ErrCode::EPERM => Errno::EPERM,
+            ErrCode::ENOENT => Errno::ENOENT,
+            ErrCode::EIO => Errno::EIO,
+            ErrCode::ENXIO => Errno::ENXIO,
+            ErrCode::E2BIG => Errno::E2BIG,
+            ErrCode::ENOMEM => Errno::ENOMEM,
+            ErrCode::EACCES => Errno::EACCES,
+            ErrCode::EFAULT => Errno::EFAULT,
+            ErrCode::EEXIST => Errno::EEXIST,
+            ErrCode::ENODEV => Errno::ENODEV,
+            ErrCode::ENOTDIR => Errno::ENOTDIR,
+            ErrCode::EISDIR => Errno::EISDIR,
+            ErrCode::EINVAL => Errno::EINVAL,
+            ErrCode::EFBIG => Errno::EFBIG,
+            ErrCode::ENOSPC => Errno::ENOSPC,
+            ErrCode::EROFS => Errno::EROFS,
+            ErrCode::EMLINK => Errno::EMLINK,
+            ErrCode::ERANGE => Errno::ERANGE,
+            ErrCode::ENOTEMPTY => Errno::ENOTEMPTY,
+            ErrCode::ENODATA => Errno::ENODATA,
+            ErrCode::EBADMSG => Errno::EBADMSG,
+            ErrCode::ENOTSUP => Errno::EOPNOTSUPP,
+            ErrCode::EUCLEAN => Errno::EUCLEAN,
+            ErrCode::ELINKFAIL | ErrCode::EALLOCFAIL => Errno::EIO,
+        };
+        Error::new(errno)
+    }
+}
+
 impl From<Error> for ostd::Error {
     fn from(error: Error) -> Self {
         match error.errno {
@@ -1,20 +1,29 @@
 use alloc::{sync::Arc, vec, vec::Vec};
 use bitvec::access;
-use core::time::Duration;
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use align_ext::AlignExt;
-use aster_block::{id::Bid, BlockDevice};
+use another_ext4::FallocFlags;
+use aster_block::{
+    bio::{BioDirection, BioSegment, BioStatus, BioWaiter},
+    id::Bid,
+    BlockDevice,
+};
 use aster_rights::Full;
-use ostd::mm::VmIo;
+use ostd::mm::{Segment, VmIo};
 
 use crate::{
     fs::utils::{
-        CStr256, DirentVisitor, Extension, FallocMode, FileSystem, FsFlags, Inode, InodeMode,
-        InodeType, IoctlCmd, Metadata, MknodType, PageCache, SuperBlock, XattrName, XattrNamespace,
-        XattrSetFlags,
+        CStr256, CachePage, DirentVisitor, Extension, FallocMode, FileSystem, FsFlags, Inode,
+        InodeFlags, InodeMode, InodeType, IoctlCmd, Metadata, MknodType, PageCache,
+        PageCacheBackend, SuperBlock, XattrName, XattrNamespace, XattrSetFlags,
     },
     prelude::*,
-    process::{Gid, Uid},
+    process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread, Gid, Uid},
+    thread::Thread,
     util::MultiWrite,
     vm::vmo::Vmo,
 };
@@ -22,6 +31,48 @@ use crate::{
 const BLOCK_SIZE: usize = another_ext4::BLOCK_SIZE;
 const ROOT_INO: usize = 2;
 const NAME_MAX: usize = 255;
+const EXT4_SUPER_MAGIC: u64 = 0xEF53;
+
+// Mount flags reported by statfs.
+const ST_RDONLY: u64 = 1;
+const ST_NOATIME: u64 = 1 << 10;
+
+// Flags of `FS_IOC_FIEMAP`.
+const FIEMAP_FLAG_SYNC: u32 = 0x1;
+const FIEMAP_EXTENT_LAST: u32 = 0x1;
+const FIEMAP_EXTENT_UNWRITTEN: u32 = 0x800;
+
+/// The header of the `FS_IOC_FIEMAP` argument, followed by `fm_extent_count` extents.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct Fiemap {
+    fm_start: u64,
+    fm_length: u64,
+    fm_flags: u32,
+    fm_mapped_extents: u32,
+    fm_extent_count: u32,
+    fm_reserved: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, Pod)]
+struct FiemapExtent {
+    fe_logical: u64,
+    fe_physical: u64,
+    fe_length: u64,
+    fe_reserved64: [u64; 2],
+    fe_flags: u32,
+    fe_reserved: [u32; 3],
+}
+
+/// The argument of `FITRIM`, all in bytes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct FstrimRange {
+    start: u64,
+    len: u64,
+    minlen: u64,
+}
 
 #[derive(Debug)]
 pub struct BlockDeviceWrapper {
@@ -31,19 +82,28 @@ pub struct BlockDeviceWrapper {
 impl another_ext4::BlockDevice for BlockDeviceWrapper {
     /// Read a block from disk.
     fn read_block(&self, block_id: another_ext4::PBlockId) -> another_ext4::Block {
-        let offset = (block_id * BLOCK_SIZE as u64) as usize;
-        let mut buf = vec![0u8; BLOCK_SIZE];
-        self.inner.read_bytes(offset, buf.as_mut());
-        another_ext4::Block {
-            id: block_id,
-            data: buf.try_into().unwrap(),
+        let mut block = another_ext4::Block::new(block_id, [0; BLOCK_SIZE]);
+        let bio_segment = BioSegment::alloc(1, BioDirection::FromDevice);
+        match self
+            .inner
+            .read_blocks(Bid::new(block_id), bio_segment.clone())
+        {
+            Ok(BioStatus::Complete) => {
+                bio_segment.read_bytes(0, &mut block.data).unwrap();
+            }
+            _ => error!("ext4: failed to read block {}", block_id),
         }
+        block
     }
 
     /// Write a block to disk.
     fn write_block(&self, block: &another_ext4::Block) {
-        let offset = (block.id * BLOCK_SIZE as u64) as usize;
-        self.inner.write_bytes(offset, &block.data);
+        let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
+        bio_segment.write_bytes(0, &block.data).unwrap();
+        match self.inner.write_blocks(Bid::new(block.id), bio_segment) {
+            Ok(BioStatus::Complete) => (),
+            _ => error!("ext4: failed to write block {}", block.id),
+        }
     }
 }
 
@@ -54,6 +114,10 @@ pub struct Ext4Inode {
 
 pub struct Ext4InodeInner {
     inode: another_ext4::InodeRef,
+    page_cache: PageCache,
+    block_manager: Arc<InodeBlockManager>,
+    /// Whether the cached metadata has changes that are not yet written to disk.
+    is_metadata_dirty: bool,
 }
 
 impl From<another_ext4::FileType> for InodeType {
@@ -127,37 +191,424 @@ impl Ext4Inode {
     pub fn file_size(&self) -> usize {
         self.inner.read().inode.inode.size() as _
     }
+
+    fn new(inode: another_ext4::InodeRef, fs: Weak<Ext4>) -> Result<Arc<Self>> {
+        let file_size = inode.inode.size() as usize;
+        let block_manager = Arc::new(InodeBlockManager {
+            ino: inode.id,
+            nblocks: AtomicUsize::new(file_size.div_ceil(BLOCK_SIZE)),
+            fs: fs.clone(),
+        });
+        let page_cache = PageCache::with_capacity(
+            file_size.align_up(BLOCK_SIZE),
+            Arc::downgrade(&block_manager) as _,
+        )?;
+        Ok(Arc::new(Self {
+            fs,
+            inner: RwMutex::new(Ext4InodeInner {
+                inode,
+                page_cache,
+                block_manager,
+                is_metadata_dirty: false,
+            }),
+        }))
+    }
+
+    /// Updates the access time after a read, unless the filesystem or the inode disables it.
+    fn touch_atime(&self) {
+        let fs = self.fs();
+        if fs.flags.intersects(FsFlags::RDONLY | FsFlags::NOATIME)
+            || self.inode_flags().contains(InodeFlags::FS_NOATIME_FL)
+        {
+            return;
+        }
+        self.set_atime(now());
+    }
+
+    fn set_inode_flags(&self, arg: usize) -> Result<()> {
+        let fs = self.fs();
+        fs.check_writable()?;
+        let new_flags = InodeFlags::from_bits_truncate(current_userspace!().read_val::<u32>(arg)?);
+
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let capset = credentials.effective_capset();
+        let mut inner = self.inner.write();
+        let owner = Uid::new(inner.inode.inode.uid() as u32);
+        if credentials.fsuid() != owner && !capset.contains(CapSet::FOWNER) {
+            return_errno_with_message!(Errno::EPERM, "only the owner can set the inode flags");
+        }
+        let changed = new_flags ^ InodeFlags::from_bits_truncate(inner.inode.inode.flags());
+        if changed.intersects(InodeFlags::FS_IMMUTABLE_FL | InodeFlags::FS_APPEND_FL)
+            && !capset.contains(CapSet::LINUX_IMMUTABLE)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "changing the immutable or append-only flag needs CAP_LINUX_IMMUTABLE"
+            );
+        }
+
+        inner.sync_metadata()?;
+        fs.inner.setflags(
+            inner.inode.id,
+            another_ext4::InodeFlags::from_bits_truncate(new_flags.bits()),
+        )?;
+        inner.reload()
+    }
+
+    fn fiemap(&self, arg: usize) -> Result<()> {
+        let mut fiemap: Fiemap = current_userspace!().read_val(arg)?;
+        if fiemap.fm_flags & !FIEMAP_FLAG_SYNC != 0 {
+            fiemap.fm_flags &= !FIEMAP_FLAG_SYNC;
+            current_userspace!().write_val(arg, &fiemap)?;
+            return_errno_with_message!(Errno::EBADR, "the fiemap flags are not supported");
+        }
+        if fiemap.fm_flags & FIEMAP_FLAG_SYNC != 0 {
+            self.inner.read().sync_data()?;
+        }
+
+        let extents = self.fs().inner.fiemap(self.ino())?;
+        let start = fiemap.fm_start;
+        let end = start.saturating_add(fiemap.fm_length);
+        let last_lblock = extents.last().map(|ext| ext.start_lblock());
+        let mut mapped = 0u32;
+        for ext in extents.iter() {
+            let logical = ext.start_lblock() as u64 * BLOCK_SIZE as u64;
+            let length = ext.block_count() as u64 * BLOCK_SIZE as u64;
+            if logical + length <= start || logical >= end {
+                continue;
+            }
+            if fiemap.fm_extent_count != 0 {
+                if mapped == fiemap.fm_extent_count {
+                    break;
+                }
+                let mut fe_flags = 0;
+                if ext.is_unwritten() {
+                    fe_flags |= FIEMAP_EXTENT_UNWRITTEN;
+                }
+                if Some(ext.start_lblock()) == last_lblock {
+                    fe_flags |= FIEMAP_EXTENT_LAST;
+                }
+                let fe = FiemapExtent {
+                    fe_logical: logical,
+                    fe_physical: ext.start_pblock() * BLOCK_SIZE as u64,
+                    fe_length: length,
+                    fe_flags,
+                    ..Default::default()
+                };
+                let addr = arg + size_of::<Fiemap>() + mapped as usize * size_of::<FiemapExtent>();
+                current_userspace!().write_val(addr, &fe)?;
+            }
+            mapped += 1;
+        }
+
+        fiemap.fm_mapped_extents = mapped;
+        current_userspace!().write_val(arg, &fiemap)?;
+        Ok(())
+    }
+
+    /// Only regular files and directories can have xattrs.
+    fn check_xattr_file_type(&self) -> Result<()> {
+        match self.inode_type() {
+            InodeType::File | InodeType::Dir => Ok(()),
+            _ => Err(Error::with_message(
+                Errno::EPERM,
+                "xattr is not supported on the file type",
+            )),
+        }
+    }
 }
 
 impl Ext4InodeInner {
+    pub fn file_size(&self) -> usize {
+        self.inode.inode.size() as _
+    }
+
     pub fn resize(&mut self, new_size: usize) -> Result<()> {
-        // self.page_cache.resize(new_size)?;
+        self.block_manager
+            .nblocks
+            .store(new_size.div_ceil(BLOCK_SIZE), Ordering::Release);
+        self.page_cache.resize(new_size)?;
+        let now = now().as_secs() as u32;
+        self.block_manager.fs().inner.setattr(
+            self.inode.id,
+            None,
+            None,
+            None,
+            Some(new_size as _),
+            None,
+            Some(now),
+            Some(now),
+            None,
+        )?;
         self.inode.inode.set_size(new_size as _);
+        self.inode.inode.set_mtime(now);
+        self.inode.inode.set_ctime(now);
         Ok(())
     }
-}
 
-impl Inode for Ext4Inode {
-    fn size(&self) -> usize {
-        self.file_size()
+    /// Writes the cached metadata back to disk if it is dirty.
+    ///
+    /// The metadata setters only update the cached inode, so this must be done
+    /// before an operation that modifies the inode on disk and then reloads it.
+    fn sync_metadata(&mut self) -> Result<()> {
+        if !self.is_metadata_dirty {
+            return Ok(());
+        }
+        let inode = &self.inode.inode;
+        self.block_manager.fs().inner.setattr(
+            self.inode.id,
+            Some(inode.mode()),
+            Some(inode.uid()),
+            Some(inode.gid()),
+            None,
+            Some(inode.atime()),
+            Some(inode.mtime()),
+            Some(inode.ctime()),
+            None,
+        )?;
+        self.is_metadata_dirty = false;
+        Ok(())
     }
 
-    fn resize(&self, new_size: usize) -> Result<()> {
-        let now = now();
-        let fs = self.fs();
-        fs.inner.setattr(
-            self.ino() as _,
+    /// Reloads the cached inode after it is modified on disk.
+    fn reload(&mut self) -> Result<()> {
+        self.inode = self.block_manager.fs().inner.read_inode(self.inode.id)?;
+        Ok(())
+    }
+
+    pub fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let (offset, read_len) = {
+            let file_size = self.file_size();
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + writer.avail());
+            (start, end - start)
+        };
+        self.page_cache.pages().read(offset, writer)?;
+        Ok(read_len)
+    }
+
+    pub fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let (start, end) = {
+            let file_size = self.file_size();
+            (
+                file_size.min(offset),
+                file_size.min(offset + writer.avail()),
+            )
+        };
+        self.page_cache.discard_range(start..end);
+
+        // The last block may be partially covered by the file size, in which
+        // case only the part within the file is copied out.
+        let start_bid = start / BLOCK_SIZE;
+        let end_bid = end.div_ceil(BLOCK_SIZE);
+        for bid in start_bid..end_bid {
+            let len = (end - bid * BLOCK_SIZE).min(BLOCK_SIZE);
+            self.block_manager.read_block_direct(bid, len, writer)?;
+        }
+        Ok(end - start)
+    }
+
+    pub fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let write_len = reader.remain();
+        self.page_cache.pages().write(offset, reader)?;
+        Ok(write_len)
+    }
+
+    pub fn extend_write_at(&mut self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let write_len = reader.remain();
+        let new_size = offset + write_len;
+        self.page_cache.resize(new_size.align_up(BLOCK_SIZE))?;
+        self.page_cache.pages().write(offset, reader)?;
+        self.set_size(new_size)?;
+        Ok(write_len)
+    }
+
+    pub fn write_direct_at(&mut self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let file_size = self.file_size();
+        let write_len = reader.remain();
+        let end_offset = offset + write_len;
+
+        let start = offset.min(file_size);
+        let end = end_offset.min(file_size);
+        self.page_cache.discard_range(start..end);
+
+        if end_offset > file_size {
+            self.page_cache.resize(end_offset)?;
+            self.set_size(end_offset)?;
+        }
+
+        let start_bid = offset / BLOCK_SIZE;
+        let end_bid = end_offset / BLOCK_SIZE;
+        for bid in start_bid..end_bid {
+            self.block_manager.write_block_direct(bid, reader)?;
+        }
+        Ok(write_len)
+    }
+
+    pub fn sync_data(&self) -> Result<()> {
+        self.page_cache.evict_range(0..self.file_size())
+    }
+
+    /// Writes the inode back to disk, or frees it if it has no links.
+    ///
+    /// This is called when the last handle to the inode is dropped.
+    fn release(&mut self) -> Result<()> {
+        let fs = self.block_manager.fs();
+        if fs.inner.read_inode(self.inode.id)?.inode.link_count() > 0 {
+            self.sync_data()?;
+            return self.sync_metadata();
+        }
+        // The data of a freed inode are discarded instead of written back
+        self.block_manager.nblocks.store(0, Ordering::Release);
+        self.page_cache.discard_range(0..self.file_size());
+        fs.inner.free_orphan(self.inode.id)?;
+        Ok(())
+    }
+
+    /// Sets the file size on disk without touching the page cache.
+    fn set_size(&mut self, new_size: usize) -> Result<()> {
+        self.block_manager.fs().inner.setattr(
+            self.inode.id,
             None,
             None,
             None,
             Some(new_size as _),
-            Some(now.as_secs() as _),
-            Some(now.as_secs() as _),
-            Some(now.as_secs() as _),
             None,
-        );
+            None,
+            None,
+            None,
+        )?;
+        self.inode.inode.set_size(new_size as _);
+        self.block_manager
+            .nblocks
+            .store(new_size.div_ceil(BLOCK_SIZE), Ordering::Release);
         Ok(())
     }
+}
+
+struct InodeBlockManager {
+    ino: u32,
+    /// The number of blocks covered by the file size.
+    nblocks: AtomicUsize,
+    fs: Weak<Ext4>,
+}
+
+impl InodeBlockManager {
+    fn fs(&self) -> Arc<Ext4> {
+        self.fs.upgrade().unwrap()
+    }
+
+    /// Reads the first `len` bytes of the block at `bid` of the file into
+    /// `writer`, bypassing the page cache.
+    ///
+    /// The whole block is read from the device into a bounce buffer first.
+    fn read_block_direct(&self, bid: usize, len: usize, writer: &mut VmWriter) -> Result<()> {
+        let fs = self.fs();
+        let Some(pblock) = fs.inner.bmap(self.ino, bid as _)? else {
+            writer.fill_zeros(len)?;
+            return Ok(());
+        };
+        let bio_segment = BioSegment::alloc(1, BioDirection::FromDevice);
+        match fs
+            .block_device
+            .read_blocks(Bid::new(pblock), bio_segment.clone())?
+        {
+            BioStatus::Complete => {
+                bio_segment
+                    .reader()
+                    .unwrap()
+                    .limit(len)
+                    .read_fallible(writer)?;
+                Ok(())
+            }
+            err_status => Err(Error::from(err_status)),
+        }
+    }
+
+    /// Writes a block of data from `reader` to the block at `bid` of the file,
+    /// bypassing the page cache.
+    fn write_block_direct(&self, bid: usize, reader: &mut VmReader) -> Result<()> {
+        let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
+        bio_segment.writer().unwrap().write_fallible(reader)?;
+        let waiter = self.write_segment_async(bid, bio_segment)?;
+        match waiter.wait() {
+            Some(BioStatus::Complete) => Ok(()),
+            _ => return_errno!(Errno::EIO),
+        }
+    }
+
+    /// Writes `bio_segment` to the block at `bid` of the file, allocating the
+    /// block if it is a hole.
+    ///
+    /// Writing to an unwritten block is done synchronously, so that the block
+    /// is marked as written only after the data reaches the disk.
+    fn write_segment_async(&self, bid: usize, bio_segment: BioSegment) -> Result<BioWaiter> {
+        let fs = self.fs();
+        let (pblock, unwritten) = fs.inner.bmap_alloc(self.ino, bid as _)?;
+        let waiter = fs
+            .block_device
+            .write_blocks_async(Bid::new(pblock), bio_segment)?;
+        if !unwritten {
+            return Ok(waiter);
+        }
+        match waiter.wait() {
+            Some(BioStatus::Complete) => {
+                fs.inner.mark_written(self.ino, bid as _, 1)?;
+                Ok(BioWaiter::new())
+            }
+            _ => return_errno!(Errno::EIO),
+        }
+    }
+}
+
+impl PageCacheBackend for InodeBlockManager {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let fs = self.fs();
+        let Some(pblock) = fs.inner.bmap(self.ino, idx as _)? else {
+            // Holes and unwritten blocks read as zeros
+            frame.writer().to_fallible().fill_zeros(frame.size())?;
+            return Ok(BioWaiter::new());
+        };
+        let bio_segment = BioSegment::new_from_segment(
+            Segment::from(frame.clone()).into(),
+            BioDirection::FromDevice,
+        );
+        let waiter = fs
+            .block_device
+            .read_blocks_async(Bid::new(pblock), bio_segment)?;
+        Ok(waiter)
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
+        bio_segment
+            .writer()
+            .unwrap()
+            .write_fallible(&mut frame.reader().to_fallible())?;
+        self.write_segment_async(idx, bio_segment)
+    }
+
+    fn npages(&self) -> usize {
+        self.nblocks.load(Ordering::Acquire)
+    }
+}
+
+impl Inode for Ext4Inode {
+    fn size(&self) -> usize {
+        self.file_size()
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        if self.type_() != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+        self.fs().check_writable()?;
+        let mut inner = self.inner.write();
+        if new_size == inner.file_size() {
+            return Ok(());
+        }
+        inner.resize(new_size)
+    }
 
     fn metadata(&self) -> Metadata {
         Metadata {
@@ -183,11 +634,9 @@ impl Inode for Ext4Inode {
     }
 
     fn set_atime(&self, time: Duration) {
-        self.inner
-            .write()
-            .inode
-            .inode
-            .set_atime(time.as_secs() as u32);
+        let mut inner = self.inner.write();
+        inner.inode.inode.set_atime(time.as_secs() as u32);
+        inner.is_metadata_dirty = true;
     }
     /// Returns the last modification time of the file.
     fn mtime(&self) -> Duration {
@@ -195,11 +644,9 @@ impl Inode for Ext4Inode {
     }
 
     fn set_mtime(&self, time: Duration) {
-        self.inner
-            .write()
-            .inode
-            .inode
-            .set_mtime(time.as_secs() as u32);
+        let mut inner = self.inner.write();
+        inner.inode.inode.set_mtime(time.as_secs() as u32);
+        inner.is_metadata_dirty = true;
     }
 
     fn ctime(&self) -> Duration {
@@ -207,11 +654,9 @@ impl Inode for Ext4Inode {
     }
 
     fn set_ctime(&self, time: Duration) {
-        self.inner
-            .write()
-            .inode
-            .inode
-            .set_ctime(time.as_secs() as u32);
+        let mut inner = self.inner.write();
+        inner.inode.inode.set_ctime(time.as_secs() as u32);
+        inner.is_metadata_dirty = true;
     }
 
     fn ino(&self) -> u64 {
@@ -227,11 +672,17 @@ impl Inode for Ext4Inode {
     }
 
     fn set_mode(&self, mode: InodeMode) -> Result<()> {
-        self.inner
-            .write()
+        self.fs().check_writable()?;
+        let mut inner = self.inner.write();
+        let file_type = inner.inode.inode.file_type();
+        inner
             .inode
             .inode
-            .set_mode(another_ext4::InodeMode::from(mode));
+            .set_mode(another_ext4::InodeMode::from_type_and_perm(
+                file_type,
+                another_ext4::InodeMode::from(mode),
+            ));
+        inner.is_metadata_dirty = true;
         Ok(())
     }
 
@@ -240,11 +691,10 @@ impl Inode for Ext4Inode {
     }
 
     fn set_owner(&self, uid: Uid) -> Result<()> {
-        self.inner
-            .write()
-            .inode
-            .inode
-            .set_uid(Into::<u32>::into(uid) as _);
+        self.fs().check_writable()?;
+        let mut inner = self.inner.write();
+        inner.inode.inode.set_uid(Into::<u32>::into(uid) as _);
+        inner.is_metadata_dirty = true;
         Ok(())
     }
 
@@ -253,79 +703,62 @@ impl Inode for Ext4Inode {
     }
 
     fn set_group(&self, gid: Gid) -> Result<()> {
-        self.inner
-            .write()
-            .inode
-            .inode
-            .set_gid(Into::<u32>::into(gid) as _);
+        self.fs().check_writable()?;
+        let mut inner = self.inner.write();
+        inner.inode.inode.set_gid(Into::<u32>::into(gid) as _);
+        inner.is_metadata_dirty = true;
         Ok(())
     }
 
     fn page_cache(&self) -> Option<Vmo<Full>> {
-        None
+        Some(self.inner.read().page_cache.pages().dup())
     }
 
     fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
-        self.read_direct_at(offset, writer)
+        if self.type_() != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+        let read_len = self.inner.read().read_at(offset, writer)?;
+        self.touch_atime();
+        Ok(read_len)
     }
 
     fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
-        // if self.type_() != InodeType::File {
-        //     return_errno!(Errno::EISDIR);
-        // }
-        // if !is_block_aligned(offset) || !is_block_aligned(writer.avail()) {
-        //     return_errno_with_message!(Errno::EINVAL, "not block-aligned");
-        // }
-
-        // let (offset, read_len) = {
-        //     let file_size = self.size();
-        //     let start = file_size.min(offset).align_down(BLOCK_SIZE);
-        //     let end = file_size
-        //         .min(offset + writer.avail())
-        //         .align_down(BLOCK_SIZE);
-        //     (start, end - start)
-        // };
-
-        // if read_len == 0 {
-        //     return Ok(read_len);
-        // }
-
-        let file_size = self.size();
-        let avail = writer.avail();
-
-        let read_len = (file_size-offset).min(avail);
-
-        let mut read_buf = vec![0u8; read_len];
-
-        self.fs().inner.read(self.ino() as _, offset, &mut read_buf);
-
-        let mut reader = VmReader::from(read_buf.as_slice());
-
-        writer.write(&mut reader);
-
-        
-        // for i in 0..read_len {
-        //     writer.write_val(&read_buf[i]);
-        // }
-
-        self.set_atime(now());
-
+        if self.type_() != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+        if !is_block_aligned(offset) || !is_block_aligned(writer.avail()) {
+            return_errno_with_message!(Errno::EINVAL, "not block-aligned");
+        }
+        let read_len = self.inner.read().read_direct_at(offset, writer)?;
+        self.touch_atime();
         Ok(read_len)
     }
 
     fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
-        self.write_direct_at(offset, reader)
+        if self.type_() != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+        self.fs().check_writable()?;
+        let mut inner = self.inner.write();
+        let file_size = inner.file_size();
+        let new_size = offset + reader.remain();
+        if new_size > file_size {
+            inner.extend_write_at(offset, reader)
+        } else {
+            inner.write_at(offset, reader)
+        }
     }
 
     fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
         if self.type_() != InodeType::File {
             return_errno!(Errno::EISDIR);
         }
-
-        let write_buf = reader.collect().unwrap();
-
-        self.fs().inner.write(self.ino() as _, offset, &write_buf);
-        Ok(write_buf.len())
+        self.fs().check_writable()?;
+        if !is_block_aligned(offset) || !is_block_aligned(reader.remain()) {
+            return_errno_with_message!(Errno::EINVAL, "not block aligned");
+        }
+        self.inner.write().write_direct_at(offset, reader)
     }
 
     fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
@@ -333,22 +766,15 @@ impl Inode for Ext4Inode {
         const EXT4_INODE_FLAG_EXTENTS: usize = 0x00080000; /* Inode uses extents */
 
         let fs = self.fs();
+        fs.check_writable()?;
 
         let mode = another_ext4::InodeMode::from_type_and_perm(
             another_ext4::FileType::from(type_),
             another_ext4::InodeMode::from(mode),
         );
 
-        let child_ino = fs.inner.create(self.ino() as _, name, mode).unwrap();
-
-        let child_inode_ref = fs.inner.read_inode(child_ino);
-
-        Ok(Arc::new(Ext4Inode {
-            fs: Arc::downgrade(&fs),
-            inner: RwMutex::new(Ext4InodeInner {
-                inode: child_inode_ref,
-            }),
-        }))
+        let child_ino = fs.inner.create(self.ino() as _, name, mode)?;
+        Ok(fs.lookup_inode(child_ino)?)
     }
 
     fn mknod(&self, name: &str, mode: InodeMode, type_: MknodType) -> Result<Arc<dyn Inode>> {
@@ -371,13 +797,7 @@ impl Inode for Ext4Inode {
         let ino = fs.inner.lookup(self.ino() as _, name);
 
         match ino {
-            Ok(ino) => {
-                let inode_ref = fs.inner.read_inode(ino);
-                Ok(Arc::new(Ext4Inode {
-                    fs: Arc::downgrade(&fs),
-                    inner: RwMutex::new(Ext4InodeInner { inode: inode_ref }),
-                }))
-            }
+            Ok(ino) => Ok(fs.lookup_inode(ino)?),
             Err(_) => return_errno!(Errno::ENOENT),
         }
     }
@@ -415,6 +835,7 @@ impl Inode for Ext4Inode {
             .downcast_ref::<Ext4Inode>()
             .ok_or_else(|| Error::with_message(Errno::EXDEV, "not same fs"))?;
         let fs = self.fs();
+        fs.check_writable()?;
         fs.inner.link(
             old.inner.write().inode.id as _,
             self.inner.write().inode.id as _,
@@ -425,14 +846,26 @@ impl Inode for Ext4Inode {
 
     fn unlink(&self, name: &str) -> Result<()> {
         let fs = self.fs();
-        fs.inner.unlink(self.ino() as _, name);
-        Ok(())
+        fs.check_writable()?;
+        let parent = self.ino();
+        // The unlinked inode is freed when it is dropped, i.e., after the
+        // files that have it open are closed.
+        let inode = fs.lookup_inode(fs.inner.lookup(parent as _, name)?)?;
+        let mut inner = inode.inner.write();
+        inner.sync_metadata()?;
+        fs.inner.unlink(parent as _, name, false)?;
+        inner.reload()
     }
 
     fn rmdir(&self, name: &str) -> Result<()> {
         let fs = self.fs();
-        fs.inner.rmdir(self.ino() as _, name);
-        Ok(())
+        fs.check_writable()?;
+        let parent = self.ino();
+        let inode = fs.lookup_inode(fs.inner.lookup(parent as _, name)?)?;
+        let mut inner = inode.inner.write();
+        inner.sync_metadata()?;
+        fs.inner.rmdir(parent as _, name, false)?;
+        inner.reload()
     }
 
     fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
@@ -440,6 +873,7 @@ impl Inode for Ext4Inode {
             .downcast_ref::<Ext4Inode>()
             .ok_or_else(|| Error::with_message(Errno::EXDEV, "not same fs"))?;
         let fs = self.fs();
+        fs.check_writable()?;
         fs.inner.rename(
             self.ino() as _,
             old_name,
@@ -464,6 +898,7 @@ impl Inode for Ext4Inode {
         if self.type_() != InodeType::SymLink {
             return_errno!(Errno::EISDIR);
         }
+        self.fs().check_writable()?;
         let file_size = self.size();
         let mut symlink = vec![0u8; file_size];
         let fs = self.fs();
@@ -472,22 +907,90 @@ impl Inode for Ext4Inode {
     }
 
     fn fallocate(&self, mode: FallocMode, offset: usize, len: usize) -> Result<()> {
-        unimplemented!()
+        if self.type_() != InodeType::File {
+            return_errno_with_message!(Errno::EISDIR, "not regular file");
+        }
+        self.fs().check_writable()?;
+
+        let flags = match mode {
+            FallocMode::Allocate => FallocFlags::empty(),
+            FallocMode::AllocateKeepSize => FallocFlags::KEEP_SIZE,
+            FallocMode::PunchHoleKeepSize => FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+            FallocMode::ZeroRange => FallocFlags::ZERO_RANGE,
+            FallocMode::ZeroRangeKeepSize => FallocFlags::ZERO_RANGE | FallocFlags::KEEP_SIZE,
+            FallocMode::CollapseRange => FallocFlags::COLLAPSE_RANGE,
+            _ => {
+                return_errno_with_message!(
+                    Errno::EOPNOTSUPP,
+                    "fallocate with the specified flags is not supported"
+                );
+            }
+        };
+        let fs = self.fs();
+        let mut inner = self.inner.write();
+        let file_size = inner.file_size();
+        // Write back the affected range first, since the blocks under it are
+        // changed or moved on disk.
+        let range = if mode == FallocMode::CollapseRange {
+            offset.align_down(BLOCK_SIZE)..file_size.max(offset + len)
+        } else {
+            offset.align_down(BLOCK_SIZE)..(offset + len).align_up(BLOCK_SIZE)
+        };
+        inner.page_cache.evict_range(range.clone())?;
+        inner.sync_metadata()?;
+        fs.inner.fallocate(inner.inode.id, offset, len, flags)?;
+        inner.page_cache.discard_range(range);
+
+        inner.reload()?;
+        let new_size = inner.file_size();
+        if new_size != file_size {
+            inner
+                .block_manager
+                .nblocks
+                .store(new_size.div_ceil(BLOCK_SIZE), Ordering::Release);
+            inner.page_cache.resize(new_size)?;
+        }
+        Ok(())
     }
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
-        Err(Error::new(Errno::EINVAL))
+        match cmd {
+            IoctlCmd::FS_IOC_GETFLAGS => {
+                current_userspace!().write_val(arg, &self.inode_flags().bits())?;
+                Ok(0)
+            }
+            IoctlCmd::FS_IOC_SETFLAGS => {
+                self.set_inode_flags(arg)?;
+                Ok(0)
+            }
+            IoctlCmd::FS_IOC_FIEMAP => {
+                self.fiemap(arg)?;
+                Ok(0)
+            }
+            IoctlCmd::FITRIM => {
+                self.fs().fstrim(arg)?;
+                Ok(0)
+            }
+            _ => Err(Error::new(Errno::EINVAL)),
+        }
+    }
+
+    fn inode_flags(&self) -> InodeFlags {
+        InodeFlags::from_bits_truncate(self.inner.read().inode.inode.flags())
     }
 
     fn sync_all(&self) -> Result<()> {
-        let fs = self.fs();
-        fs.inner.flush_all();
+        let mut inner = self.inner.write();
+        inner.sync_data()?;
+        inner.sync_metadata()?;
+        drop(inner);
+        self.fs().inner.flush_all();
         Ok(())
     }
 
     fn sync_data(&self) -> Result<()> {
-        let fs = self.fs();
-        fs.inner.flush_all();
+        self.inner.read().sync_data()?;
+        self.fs().inner.flush_all();
         Ok(())
     }
 
@@ -505,52 +1008,211 @@ impl Inode for Ext4Inode {
         value_reader: &mut VmReader,
         flags: XattrSetFlags,
     ) -> Result<()> {
-        Ok(())
+        self.check_xattr_file_type()?;
+        let fs = self.fs();
+        fs.check_writable()?;
+        let value = value_reader.collect()?;
+
+        // Check and set under the inode lock, so that racing `XATTR_CREATE`
+        // or `XATTR_REPLACE` calls cannot both succeed.
+        let mut inner = self.inner.write();
+        let exists = fs.inner.getxattr(inner.inode.id, name.full_name()).is_ok();
+        if exists && flags.contains(XattrSetFlags::CREATE_ONLY) {
+            return_errno_with_message!(Errno::EEXIST, "the target xattr already exists");
+        }
+        if !exists && flags.contains(XattrSetFlags::REPLACE_ONLY) {
+            return_errno_with_message!(Errno::ENODATA, "the target xattr does not exist");
+        }
+        inner.sync_metadata()?;
+        fs.inner
+            .setxattr(inner.inode.id, name.full_name(), &value)?;
+        inner.reload()
     }
 
     fn get_xattr(&self, name: XattrName, value_writer: &mut VmWriter) -> Result<usize> {
-        Ok(0)
+        self.check_xattr_file_type()?;
+        let value = self.fs().inner.getxattr(self.ino(), name.full_name())?;
+
+        let value_avail_len = value_writer.avail();
+        if value_avail_len == 0 {
+            return Ok(value.len());
+        }
+        if value.len() > value_avail_len {
+            return_errno_with_message!(Errno::ERANGE, "the xattr value buffer is too small");
+        }
+        value_writer.write_fallible(&mut VmReader::from(value.as_slice()))?;
+        Ok(value.len())
     }
 
     fn list_xattr(&self, namespace: XattrNamespace, list_writer: &mut VmWriter) -> Result<usize> {
-        Ok(0)
+        if self.check_xattr_file_type().is_err() {
+            return Ok(0);
+        }
+        let names: Vec<String> = self
+            .fs()
+            .inner
+            .listxattr(self.ino())?
+            .into_iter()
+            .filter(|name| {
+                !namespace.is_user()
+                    || XattrNamespace::try_from_full_name(name) == Some(XattrNamespace::User)
+            })
+            .collect();
+
+        // Include the null byte following each name
+        let list_actual_len = names.iter().map(|name| name.len() + 1).sum::<usize>();
+        let list_avail_len = list_writer.avail();
+        if list_avail_len == 0 {
+            return Ok(list_actual_len);
+        }
+        if list_actual_len > list_avail_len {
+            return_errno_with_message!(Errno::ERANGE, "the xattr list buffer is too small");
+        }
+        for name in names {
+            list_writer.write_fallible(&mut VmReader::from(name.as_bytes()))?;
+            list_writer.write_val(&0u8)?;
+        }
+        Ok(list_actual_len)
     }
 
     fn remove_xattr(&self, name: XattrName) -> Result<()> {
-        Ok(())
+        self.check_xattr_file_type()?;
+        let fs = self.fs();
+        fs.check_writable()?;
+        let mut inner = self.inner.write();
+        inner.sync_metadata()?;
+        fs.inner.removexattr(inner.inode.id, name.full_name())?;
+        inner.reload()
+    }
+}
+
+impl Drop for Ext4Inode {
+    fn drop(&mut self) {
+        let Some(fs) = self.fs.upgrade() else {
+            return;
+        };
+        let inner = self.inner.get_mut();
+        let ino = inner.inode.id;
+        if let Err(err) = inner.release() {
+            error!("ext4: failed to release inode {}: {:?}", ino, err);
+        }
+        // Lookups of the inode wait until it is removed from the cache, so
+        // that they see the written-back inode on disk.
+        fs.inode_cache.lock().remove(&ino);
     }
 }
 
 /// The Ext4 filesystem.
 pub struct Ext4 {
     inner: another_ext4::Ext4,
+    block_device: Arc<dyn BlockDevice>,
+    /// The inodes in use, which are released when the last handle is dropped.
+    inode_cache: Mutex<BTreeMap<u32, Weak<Ext4Inode>>>,
+    flags: FsFlags,
     self_ref: Weak<Self>,
 }
 
 impl Ext4 {
     /// Opens and loads an Ext4 from the `block_device`.
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
-        let block_device = Arc::new(BlockDeviceWrapper {
-            inner: block_device,
+        let wrapper = Arc::new(BlockDeviceWrapper {
+            inner: block_device.clone(),
         });
+        let inner = another_ext4::Ext4::load(wrapper)?;
+        let mut flags = parse_mount_opts(inner.read_super_block().mount_opts());
+        if inner.read_only() {
+            flags.insert(FsFlags::RDONLY);
+        }
         let ext4 = Arc::new_cyclic(|weak_ref| Self {
-            inner: another_ext4::Ext4::load(block_device).unwrap(),
+            inner,
+            block_device,
+            inode_cache: Mutex::new(BTreeMap::new()),
+            flags,
             self_ref: weak_ref.clone(),
         });
         Ok(ext4)
     }
 
+    /// Returns `EROFS` if the filesystem is read-only.
+    fn check_writable(&self) -> Result<()> {
+        if self.flags.contains(FsFlags::RDONLY) {
+            return_errno_with_message!(Errno::EROFS, "the filesystem is read-only");
+        }
+        Ok(())
+    }
+
+    /// Discards the free blocks in the range given by the `FITRIM` argument.
+    fn fstrim(&self, arg: usize) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.effective_capset().contains(CapSet::SYS_ADMIN) {
+            return_errno_with_message!(Errno::EPERM, "FITRIM needs CAP_SYS_ADMIN");
+        }
+        let mut range: FstrimRange = current_userspace!().read_val(arg)?;
+        let block_size = BLOCK_SIZE as u64;
+        if range.start >= self.inner.read_super_block().block_count() * block_size {
+            return_errno_with_message!(Errno::EINVAL, "the trim range is beyond the filesystem");
+        }
+
+        let trimmed = self.inner.fstrim(
+            range.start / block_size,
+            range.len / block_size,
+            range.minlen.div_ceil(block_size),
+            |block, count| match self
+                .block_device
+                .discard_blocks(Bid::new(block), count as usize)
+            {
+                Ok(BioStatus::Complete) => Ok(()),
+                Ok(BioStatus::NotSupported) => Err(another_ext4::Ext4Error::new(
+                    another_ext4::ErrCode::ENOTSUP,
+                )),
+                _ => Err(another_ext4::Ext4Error::new(another_ext4::ErrCode::EIO)),
+            },
+        )?;
+        range.len = trimmed * block_size;
+        current_userspace!().write_val(arg, &range)?;
+        Ok(())
+    }
+
     pub fn root_inode(&self) -> Result<Arc<dyn Inode>> {
-        let inode = self.inner.read_inode(ROOT_INO as _);
-        Ok(Arc::new(Ext4Inode {
-            inner: RwMutex::new(Ext4InodeInner { inode }),
-            fs: self.self_ref.clone(),
-        }))
+        Ok(self.lookup_inode(ROOT_INO as _)?)
+    }
+
+    /// Returns the cached inode of `ino`, loading it from disk if necessary.
+    ///
+    /// All handles to the same file share one inode, and hence one page cache.
+    fn lookup_inode(&self, ino: u32) -> Result<Arc<Ext4Inode>> {
+        loop {
+            let mut inode_cache = self.inode_cache.lock();
+            match inode_cache.get(&ino).map(Weak::upgrade) {
+                Some(Some(inode)) => return Ok(inode),
+                // The inode is being dropped and written back
+                Some(None) => {
+                    drop(inode_cache);
+                    Thread::yield_now();
+                }
+                None => {
+                    let inode = Ext4Inode::new(self.inner.read_inode(ino)?, self.self_ref.clone())?;
+                    inode_cache.insert(ino, Arc::downgrade(&inode));
+                    return Ok(inode);
+                }
+            }
+        }
     }
 }
 
 impl FileSystem for Ext4 {
     fn sync(&self) -> Result<()> {
+        let inodes: Vec<_> = self
+            .inode_cache
+            .lock()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for inode in inodes {
+            let mut inner = inode.inner.write();
+            inner.sync_data()?;
+            inner.sync_metadata()?;
+        }
         self.inner.flush_all();
         Ok(())
     }
@@ -562,24 +1224,52 @@ impl FileSystem for Ext4 {
     fn sb(&self) -> SuperBlock {
         let sb = self.inner.read_super_block();
 
+        let free_blocks = sb.free_blocks_count();
+        // The same fsid as Linux: the two halves of the UUID folded together
+        let uuid = sb.uuid();
+        let fsid = u64::from_le_bytes(uuid[..8].try_into().unwrap())
+            ^ u64::from_le_bytes(uuid[8..].try_into().unwrap());
+        let mut flags = 0;
+        if self.flags.contains(FsFlags::RDONLY) {
+            flags |= ST_RDONLY;
+        }
+        if self.flags.contains(FsFlags::NOATIME) {
+            flags |= ST_NOATIME;
+        }
+
         SuperBlock {
-            magic: 0x0,
+            magic: EXT4_SUPER_MAGIC,
             bsize: BLOCK_SIZE,
             blocks: sb.block_count() as _,
-            bfree: sb.free_blocks_count() as _,
-            bavail: sb.free_blocks_count() as _,
+            bfree: free_blocks as _,
+            bavail: free_blocks.saturating_sub(sb.reserved_blocks_count()) as _,
             files: sb.inode_count() as _,
             ffree: sb.free_inodes_count() as _,
-            fsid: 0, // TODO
+            fsid,
             namelen: NAME_MAX,
             frsize: BLOCK_SIZE,
-            flags: 0, // TODO
+            flags,
         }
     }
 
     fn flags(&self) -> FsFlags {
-        FsFlags::empty()
+        self.flags
+    }
+}
+
+/// Parses the default mount options stored in the superblock.
+fn parse_mount_opts(opts: &str) -> FsFlags {
+    let mut flags = FsFlags::empty();
+    for opt in opts.split(',') {
+        match opt.trim() {
+            "ro" => flags.insert(FsFlags::RDONLY),
+            "rw" => flags.remove(FsFlags::RDONLY),
+            "noatime" => flags.insert(FsFlags::NOATIME),
+            "atime" | "relatime" | "strictatime" => flags.remove(FsFlags::NOATIME),
+            _ => (),
+        }
     }
+    flags
 }
 
 /// Returns the current time.
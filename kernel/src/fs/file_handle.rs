@@ -101,6 +101,12 @@ pub trait FileLike: Pollable + Send + Sync + Any {
     fn as_socket(&self) -> Option<&dyn Socket> {
         None
     }
+
+    /// Called each time a file descriptor referring to the file is closed.
+    ///
+    /// Unlike `Drop`, which runs after the last reference is gone, this is the `flush` file
+    /// operation in Linux, which runs on every `close`.
+    fn on_close(&self) {}
 }
 
 impl dyn FileLike {
@@ -112,10 +112,11 @@ impl FileTable {
     ) -> Option<Arc<dyn FileLike>> {
         let entry = FileTableEntry::new(item, flags);
         let entry = self.table.put_at(fd as usize, entry);
-        if entry.is_some() {
+        if let Some(entry) = entry.as_ref() {
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
-            entry.as_ref().unwrap().notify_fd_events(&events);
+            entry.notify_fd_events(&events);
+            entry.file.on_close();
         }
         entry.map(|e| e.file)
     }
@@ -126,6 +127,7 @@ impl FileTable {
         let events = FdEvents::Close(fd);
         self.notify_fd_events(&events);
         removed_entry.notify_fd_events(&events);
+        removed_entry.file.on_close();
 
         Some(removed_entry.file)
     }
@@ -156,6 +158,7 @@ impl FileTable {
             let events = FdEvents::Close(fd);
             self.notify_fd_events(&events);
             removed_entry.notify_fd_events(&events);
+            removed_entry.file.on_close();
             closed_files.push(removed_entry.file);
         }
 
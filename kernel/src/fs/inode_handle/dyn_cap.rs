@@ -22,6 +22,20 @@ impl InodeHandle<Rights> {
         if inode.type_() == InodeType::Dir && access_mode.is_writable() {
             return_errno_with_message!(Errno::EISDIR, "directory cannot open to write");
         }
+        if access_mode.is_writable() {
+            let inode_flags = inode.inode_flags();
+            if inode_flags.contains(InodeFlags::FS_IMMUTABLE_FL) {
+                return_errno_with_message!(Errno::EPERM, "immutable file cannot open to write");
+            }
+            if inode_flags.contains(InodeFlags::FS_APPEND_FL)
+                && !status_flags.contains(StatusFlags::O_APPEND)
+            {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "append-only file must be opened with O_APPEND to write"
+                );
+            }
+        }
 
         let file_io = if let Some(device) = inode.as_device() {
             device.open()?
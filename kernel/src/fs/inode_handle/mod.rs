@@ -18,9 +18,9 @@ use crate::{
         file_handle::FileLike,
         path::Dentry,
         utils::{
-            AccessMode, DirentVisitor, FallocMode, FileRange, FlockItem, FlockList, InodeMode,
-            InodeType, IoctlCmd, Metadata, RangeLockItem, RangeLockItemBuilder, RangeLockList,
-            RangeLockType, SeekFrom, StatusFlags, OFFSET_MAX,
+            AccessMode, DirentVisitor, FallocMode, FileRange, FlockItem, FlockList, InodeFlags,
+            InodeMode, InodeType, IoctlCmd, Metadata, RangeLockItem, RangeLockItemBuilder,
+            RangeLockList, RangeLockType, SeekFrom, StatusFlags, OFFSET_MAX,
         },
     },
     prelude::*,
@@ -47,7 +47,7 @@ struct InodeHandle_ {
 impl InodeHandle_ {
     pub fn read(&self, writer: &mut VmWriter) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            return file_io.read(writer);
+            return file_io.read(writer, self.status_flags());
         }
 
         if !self.dentry.inode().is_seekable() {
@@ -64,7 +64,7 @@ impl InodeHandle_ {
 
     pub fn write(&self, reader: &mut VmReader) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            return file_io.write(reader);
+            return file_io.write(reader, self.status_flags());
         }
 
         if !self.dentry.inode().is_seekable() {
@@ -73,7 +73,7 @@ impl InodeHandle_ {
 
         let mut offset = self.offset.lock();
 
-        if self.status_flags().contains(StatusFlags::O_APPEND) {
+        if self.is_append() {
             *offset = self.dentry.size();
         }
 
@@ -101,8 +101,8 @@ impl InodeHandle_ {
         }
 
         let status_flags = self.status_flags();
-        if status_flags.contains(StatusFlags::O_APPEND) {
-            // If the file has the O_APPEND flag, the offset is ignored
+        if self.is_append() {
+            // If the file has the O_APPEND flag or is append-only, the offset is ignored
             offset = self.dentry.size();
         }
 
@@ -148,12 +148,23 @@ impl InodeHandle_ {
     }
 
     pub fn resize(&self, new_size: usize) -> Result<()> {
-        if self.status_flags().contains(StatusFlags::O_APPEND) {
+        if self.is_append() {
             return_errno_with_message!(Errno::EPERM, "can not resize append-only file");
         }
         self.dentry.resize(new_size)
     }
 
+    /// Returns whether writes always go to the end of the file, either because
+    /// the file is opened with `O_APPEND` or because the inode is append-only.
+    fn is_append(&self) -> bool {
+        self.status_flags().contains(StatusFlags::O_APPEND)
+            || self
+                .dentry
+                .inode()
+                .inode_flags()
+                .contains(InodeFlags::FS_APPEND_FL)
+    }
+
     pub fn access_mode(&self) -> AccessMode {
         self.access_mode
     }
@@ -184,6 +195,20 @@ impl InodeHandle_ {
     }
 
     fn fallocate(&self, mode: FallocMode, offset: usize, len: usize) -> Result<()> {
+        let inode_flags = self.dentry.inode().inode_flags();
+        if inode_flags.contains(InodeFlags::FS_IMMUTABLE_FL) {
+            return_errno_with_message!(Errno::EPERM, "the file is immutable");
+        }
+        if inode_flags.contains(InodeFlags::FS_APPEND_FL)
+            && mode != FallocMode::Allocate
+            && mode != FallocMode::AllocateKeepSize
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "only allocation is allowed on the append-only file"
+            );
+        }
+
         let status_flags = self.status_flags();
         if status_flags.contains(StatusFlags::O_APPEND)
             && (mode == FallocMode::PunchHoleKeepSize
@@ -380,9 +405,9 @@ impl<R> Drop for InodeHandle<R> {
 }
 
 pub trait FileIo: Pollable + Send + Sync + 'static {
-    fn read(&self, writer: &mut VmWriter) -> Result<usize>;
+    fn read(&self, writer: &mut VmWriter, status_flags: StatusFlags) -> Result<usize>;
 
-    fn write(&self, reader: &mut VmReader) -> Result<usize>;
+    fn write(&self, reader: &mut VmReader, status_flags: StatusFlags) -> Result<usize>;
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
         return_errno_with_message!(Errno::EINVAL, "ioctl is not supported");
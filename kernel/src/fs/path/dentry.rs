@@ -232,6 +232,7 @@ impl Dentry_ {
         }
 
         let old_inode = old.inode();
+        old_inode.check_modifiable()?;
         self.inode.link(old_inode, name)?;
         let name = String::from(name);
         let dentry = Dentry_::new(
@@ -252,7 +253,8 @@ impl Dentry_ {
         }
 
         let children = self.children.upread();
-        children.check_mountpoint(name)?;
+        let dentry = children.check_mountpoint_then_find(name)?;
+        self.check_deletable(dentry.as_deref(), name)?;
 
         self.inode.unlink(name)?;
 
@@ -268,7 +270,8 @@ impl Dentry_ {
         }
 
         let children = self.children.upread();
-        children.check_mountpoint(name)?;
+        let dentry = children.check_mountpoint_then_find(name)?;
+        self.check_deletable(dentry.as_deref(), name)?;
 
         self.inode.rmdir(name)?;
 
@@ -295,6 +298,8 @@ impl Dentry_ {
             let children = self.children.upread();
             let old_dentry = children.check_mountpoint_then_find(old_name)?;
             children.check_mountpoint(new_name)?;
+            self.check_deletable(old_dentry.as_deref(), old_name)?;
+            self.check_replaceable(new_name)?;
 
             self.inode.rename(old_name, &self.inode, new_name)?;
 
@@ -317,6 +322,8 @@ impl Dentry_ {
                 write_lock_children_on_two_dentries(self, new_dir);
             let old_dentry = self_children.check_mountpoint_then_find(old_name)?;
             new_dir_children.check_mountpoint(new_name)?;
+            self.check_deletable(old_dentry.as_deref(), old_name)?;
+            new_dir.check_replaceable(new_name)?;
 
             self.inode.rename(old_name, &new_dir.inode, new_name)?;
             match old_dentry.as_ref() {
@@ -334,6 +341,65 @@ impl Dentry_ {
         }
         Ok(())
     }
+
+    /// Checks whether the child named `name` can be removed from this directory,
+    /// which is not allowed if either of them is immutable or append-only.
+    ///
+    /// The child is only looked up if the caller has not found its dentry.
+    fn check_deletable(&self, child: Option<&Dentry_>, name: &str) -> Result<()> {
+        self.inode.check_modifiable()?;
+        match child {
+            Some(child) => child.inode.check_modifiable(),
+            None => self.inode.lookup(name)?.check_modifiable(),
+        }
+    }
+
+    /// Checks whether the child named `name`, if it exists, can be replaced by a rename.
+    fn check_replaceable(&self, name: &str) -> Result<()> {
+        match self.inode.lookup(name) {
+            Ok(inode) => {
+                self.inode.check_modifiable()?;
+                inode.check_modifiable()
+            }
+            Err(e) if e.error() == Errno::ENOENT => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.inode.check_modifiable()?;
+        self.inode.set_mode(mode)
+    }
+
+    pub fn resize(&self, size: usize) -> Result<()> {
+        self.inode.check_modifiable()?;
+        self.inode.resize(size)
+    }
+
+    pub fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.inode.check_modifiable()?;
+        self.inode.set_owner(uid)
+    }
+
+    pub fn set_group(&self, gid: Gid) -> Result<()> {
+        self.inode.check_modifiable()?;
+        self.inode.set_group(gid)
+    }
+
+    pub fn set_xattr(
+        &self,
+        name: XattrName,
+        value_reader: &mut VmReader,
+        flags: XattrSetFlags,
+    ) -> Result<()> {
+        self.inode.check_modifiable()?;
+        self.inode.set_xattr(name, value_reader, flags)
+    }
+
+    pub fn remove_xattr(&self, name: XattrName) -> Result<()> {
+        self.inode.check_modifiable()?;
+        self.inode.remove_xattr(name)
+    }
 }
 
 #[inherit_methods(from = "self.inode")]
@@ -343,13 +409,9 @@ impl Dentry_ {
     pub fn sync_data(&self) -> Result<()>;
     pub fn metadata(&self) -> Metadata;
     pub fn mode(&self) -> Result<InodeMode>;
-    pub fn set_mode(&self, mode: InodeMode) -> Result<()>;
     pub fn size(&self) -> usize;
-    pub fn resize(&self, size: usize) -> Result<()>;
     pub fn owner(&self) -> Result<Uid>;
-    pub fn set_owner(&self, uid: Uid) -> Result<()>;
     pub fn group(&self) -> Result<Gid>;
-    pub fn set_group(&self, gid: Gid) -> Result<()>;
     pub fn atime(&self) -> Duration;
     pub fn set_atime(&self, time: Duration);
     pub fn mtime(&self) -> Duration;
@@ -357,19 +419,12 @@ impl Dentry_ {
     pub fn ctime(&self) -> Duration;
     pub fn set_ctime(&self, time: Duration);
     pub fn is_dentry_cacheable(&self) -> bool;
-    pub fn set_xattr(
-        &self,
-        name: XattrName,
-        value_reader: &mut VmReader,
-        flags: XattrSetFlags,
-    ) -> Result<()>;
     pub fn get_xattr(&self, name: XattrName, value_writer: &mut VmWriter) -> Result<usize>;
     pub fn list_xattr(
         &self,
         namespace: XattrNamespace,
         list_writer: &mut VmWriter,
     ) -> Result<usize>;
-    pub fn remove_xattr(&self, name: XattrName) -> Result<()>;
 }
 
 impl Debug for Dentry_ {
@@ -43,6 +43,7 @@ pub(super) fn init() {
             FileSystemType::new("proc", true),
             FileSystemType::new("ramfs", true),
             FileSystemType::new("devpts", true),
+            FileSystemType::new("mqueue", true),
             FileSystemType::new("ext2", false),
             FileSystemType::new("ext4", false),
             FileSystemType::new("exfat", false),
@@ -3,7 +3,10 @@
 use crate::{
     fs::{
         procfs::{
-            sys::kernel::cap_last_cap::CapLastCapFileOps,
+            sys::kernel::{
+                cap_last_cap::CapLastCapFileOps,
+                shm::{ShmLimit, ShmLimitFileOps},
+            },
             template::{DirOps, ProcDirBuilder},
             ProcDir,
         },
@@ -13,6 +16,7 @@ use crate::{
 };
 
 mod cap_last_cap;
+mod shm;
 
 /// Represents the inode at `/proc/sys/kernel`.
 pub struct KernelDirOps;
@@ -27,6 +31,9 @@ impl DirOps for KernelDirOps {
     fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
         let inode = match name {
             "cap_last_cap" => CapLastCapFileOps::new_inode(this_ptr.clone()),
+            "shmall" => ShmLimitFileOps::new_inode(ShmLimit::All, this_ptr.clone()),
+            "shmmax" => ShmLimitFileOps::new_inode(ShmLimit::Max, this_ptr.clone()),
+            "shmmni" => ShmLimitFileOps::new_inode(ShmLimit::Mni, this_ptr.clone()),
             _ => return_errno!(Errno::ENOENT),
         };
         Ok(inode)
@@ -41,5 +48,14 @@ impl DirOps for KernelDirOps {
         cached_children.put_entry_if_not_found("cap_last_cap", || {
             CapLastCapFileOps::new_inode(this_ptr.clone())
         });
+        for (name, limit) in [
+            ("shmall", ShmLimit::All),
+            ("shmmax", ShmLimit::Max),
+            ("shmmni", ShmLimit::Mni),
+        ] {
+            cached_children.put_entry_if_not_found(name, || {
+                ShmLimitFileOps::new_inode(limit, this_ptr.clone())
+            });
+        }
     }
 }
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    ipc::shm::{SHMALL, SHMMAX, SHMMNI},
+    prelude::*,
+};
+
+/// The limits of System V shared memory under `/proc/sys/kernel`.
+#[derive(Clone, Copy)]
+pub enum ShmLimit {
+    /// `/proc/sys/kernel/shmmax`
+    Max,
+    /// `/proc/sys/kernel/shmall`
+    All,
+    /// `/proc/sys/kernel/shmmni`
+    Mni,
+}
+
+/// Represents the inode at `/proc/sys/kernel/shmmax`, `/proc/sys/kernel/shmall` or
+/// `/proc/sys/kernel/shmmni`.
+pub struct ShmLimitFileOps(ShmLimit);
+
+impl ShmLimitFileOps {
+    pub fn new_inode(limit: ShmLimit, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(limit))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for ShmLimitFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let value = match self.0 {
+            ShmLimit::Max => SHMMAX,
+            ShmLimit::All => SHMALL,
+            ShmLimit::Mni => SHMMNI,
+        };
+        let output = format!("{}\n", value);
+        Ok(output.into_bytes())
+    }
+}
@@ -38,8 +38,12 @@ impl SuperBlock {
 
 bitflags! {
     pub struct FsFlags: u32 {
+        /// The filesystem is read-only.
+        const RDONLY = 1 << 0;
         /// Dentry cannot be evicted.
         const DENTRY_UNEVICTABLE = 1 << 1;
+        /// Access times are not updated.
+        const NOATIME = 1 << 2;
     }
 }
 
@@ -9,8 +9,8 @@ use core2::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult,
 use ostd::task::Task;
 
 use super::{
-    AccessMode, DirentVisitor, FallocMode, FileSystem, IoctlCmd, XattrName, XattrNamespace,
-    XattrSetFlags,
+    AccessMode, DirentVisitor, FallocMode, FileSystem, InodeFlags, IoctlCmd, XattrName,
+    XattrNamespace, XattrSetFlags,
 };
 use crate::{
     events::IoEvents,
@@ -463,6 +463,11 @@ pub trait Inode: Any + Sync + Send {
         return_errno!(Errno::EOPNOTSUPP);
     }
 
+    /// Returns the attribute flags of the inode, see `FS_IOC_GETFLAGS`.
+    fn inode_flags(&self) -> InodeFlags {
+        InodeFlags::empty()
+    }
+
     fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
         let events = IoEvents::IN | IoEvents::OUT;
         events & mask
@@ -536,6 +541,10 @@ pub trait Inode: Any + Sync + Send {
 
         perm =
             perm.intersection(Permission::MAY_READ | Permission::MAY_WRITE | Permission::MAY_EXEC);
+        if perm.may_write() && self.inode_flags().contains(InodeFlags::FS_IMMUTABLE_FL) {
+            return_errno_with_message!(Errno::EPERM, "the inode is immutable");
+        }
+
         let metadata = self.metadata();
         let mode = metadata.mode;
 
@@ -569,6 +578,19 @@ impl dyn Inode {
         (self as &dyn Any).downcast_ref::<T>()
     }
 
+    /// Checks whether the inode may be modified other than by appending,
+    /// i.e., it is neither immutable nor append-only.
+    pub fn check_modifiable(&self) -> Result<()> {
+        let flags = self.inode_flags();
+        if flags.contains(InodeFlags::FS_IMMUTABLE_FL) {
+            return_errno_with_message!(Errno::EPERM, "the inode is immutable");
+        }
+        if flags.contains(InodeFlags::FS_APPEND_FL) {
+            return_errno_with_message!(Errno::EPERM, "the inode is append-only");
+        }
+        Ok(())
+    }
+
     pub fn writer(&self, from_offset: usize) -> InodeWriter {
         InodeWriter {
             inner: self,
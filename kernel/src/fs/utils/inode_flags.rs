@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use bitflags::bitflags;
+
+bitflags! {
+    /// The inode attribute flags, accessed by `FS_IOC_GETFLAGS` and `FS_IOC_SETFLAGS`.
+    pub struct InodeFlags: u32 {
+        /// secure deletion
+        const FS_SECRM_FL = 0x0000_0001;
+        /// undelete
+        const FS_UNRM_FL = 0x0000_0002;
+        /// compress file
+        const FS_COMPR_FL = 0x0000_0004;
+        /// synchronous updates
+        const FS_SYNC_FL = 0x0000_0008;
+        /// immutable file
+        const FS_IMMUTABLE_FL = 0x0000_0010;
+        /// writes to file may only append
+        const FS_APPEND_FL = 0x0000_0020;
+        /// do not dump file
+        const FS_NODUMP_FL = 0x0000_0040;
+        /// do not update atime
+        const FS_NOATIME_FL = 0x0000_0080;
+        /// hash-indexed directory
+        const FS_INDEX_FL = 0x0000_1000;
+        /// file data should be journaled
+        const FS_JOURNAL_DATA_FL = 0x0000_4000;
+        /// file tail should not be merged
+        const FS_NOTAIL_FL = 0x0000_8000;
+        /// dirsync behaviour (directories only)
+        const FS_DIRSYNC_FL = 0x0001_0000;
+        /// top of directory hierarchies
+        const FS_TOPDIR_FL = 0x0002_0000;
+        /// reserved for ext4
+        const FS_HUGE_FILE_FL = 0x0004_0000;
+        /// extents
+        const FS_EXTENT_FL = 0x0008_0000;
+        /// inode used for large extended attribute
+        const FS_EA_INODE_FL = 0x0020_0000;
+        /// inode has inline data
+        const FS_INLINE_DATA_FL = 0x1000_0000;
+    }
+}
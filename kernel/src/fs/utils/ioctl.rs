@@ -41,6 +41,18 @@ pub enum IoctlCmd {
     TIOCSPTLCK = 0x40045431,
     /// Safely open the slave
     TIOCGPTPEER = 0x40045441,
+    /// Create or attach a TUN/TAP interface
+    TUNSETIFF = 0x400454ca,
+    /// Get the TUN/TAP interface
+    TUNGETIFF = 0x800454d2,
+    /// Get the inode attribute flags
+    FS_IOC_GETFLAGS = 0x80086601,
+    /// Set the inode attribute flags
+    FS_IOC_SETFLAGS = 0x40086602,
+    /// Get the extent mappings of a file
+    FS_IOC_FIEMAP = 0xc020660b,
+    /// Discard the unused blocks of a filesystem
+    FITRIM = 0xc0185879,
     /// Get tdx report using TDCALL
     TDXGETREPORT = 0xc4405401,
 }
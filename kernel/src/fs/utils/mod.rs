@@ -12,6 +12,7 @@ pub use file_creation_mask::FileCreationMask;
 pub use flock::{FlockItem, FlockList, FlockType};
 pub use fs::{FileSystem, FsFlags, SuperBlock};
 pub use inode::{Extension, Inode, InodeMode, InodeType, Metadata, MknodType, Permission};
+pub use inode_flags::InodeFlags;
 pub use ioctl::IoctlCmd;
 pub use page_cache::{CachePage, PageCache, PageCacheBackend};
 pub use random_test::{generate_random_operation, new_fs_in_memory};
@@ -34,6 +35,7 @@ mod file_creation_mask;
 mod flock;
 mod fs;
 mod inode;
+mod inode_flags;
 mod ioctl;
 mod page_cache;
 mod random_test;
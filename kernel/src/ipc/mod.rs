@@ -1,11 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_rights::ReadOp;
+
 use crate::{
     prelude::*,
-    process::{Gid, Uid},
+    process::{credentials::capabilities::CapSet, Credentials, Gid, Uid},
 };
 
+pub mod mqueue;
+pub mod msg;
 pub mod semaphore;
+pub mod shm;
 
 #[expect(non_camel_case_types)]
 pub type key_t = i32;
@@ -85,7 +90,7 @@ impl IpcPermission {
         self.mode
     }
 
-    pub(self) fn new_sem_perm(key: key_t, uid: Uid, gid: Gid, mode: u16) -> Self {
+    pub(self) fn new(key: key_t, uid: Uid, gid: Gid, mode: u16) -> Self {
         Self {
             key,
             uid,
@@ -95,8 +100,62 @@ impl IpcPermission {
             mode,
         }
     }
+
+    /// Checks whether the process with `credentials` is granted the `requested` permission bits,
+    /// which are given in the position of the bits for others (e.g., 0o4 for reading).
+    pub fn check_access(&self, credentials: &Credentials<ReadOp>, requested: u16) -> Result<()> {
+        let euid = credentials.euid();
+        let egid = credentials.egid();
+
+        let granted = if euid == self.cuid || euid == self.uid {
+            self.mode >> 6
+        } else if egid == self.cguid
+            || egid == self.gid
+            || credentials.groups().contains(&self.cguid)
+            || credentials.groups().contains(&self.gid)
+        {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+
+        if requested & !granted & 0o7 != 0
+            && !credentials.effective_capset().contains(CapSet::IPC_OWNER)
+        {
+            return_errno_with_message!(Errno::EACCES, "the IPC permission check failed");
+        }
+        Ok(())
+    }
+
+    /// Returns whether the process with `credentials` is the owner or the creator.
+    pub fn is_owner(&self, credentials: &Credentials<ReadOp>) -> bool {
+        let euid = credentials.euid();
+        euid == self.uid
+            || euid == self.cuid
+            || credentials.effective_capset().contains(CapSet::SYS_ADMIN)
+    }
+}
+
+/// The permission of an IPC object, i.e., `struct ipc64_perm`.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+pub struct IpcPerm64 {
+    pub key: i32,
+    pub uid: u32,
+    pub gid: u32,
+    pub cuid: u32,
+    pub cgid: u32,
+    pub mode: u32,
+    seq: u16,
+    __pad1: u16,
+    __pad2: u32,
+    __unused1: u64,
+    __unused2: u64,
 }
 
 pub(super) fn init() {
     semaphore::init();
+    mqueue::init();
+    msg::init();
+    shm::init();
 }
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::fs::MqueueInode;
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        utils::{AccessMode, Inode, Metadata, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        signal::{PollHandle, Pollable},
+        Process,
+    },
+};
+
+/// A message queue descriptor opened by `mq_open`.
+pub struct MqueueFile {
+    inode: Arc<MqueueInode>,
+    access_mode: AccessMode,
+    is_nonblocking: AtomicBool,
+}
+
+impl MqueueFile {
+    pub fn new(inode: Arc<MqueueInode>, access_mode: AccessMode, is_nonblocking: bool) -> Self {
+        Self {
+            inode,
+            access_mode,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+        }
+    }
+
+    pub fn inode(&self) -> &Arc<MqueueInode> {
+        &self.inode
+    }
+
+    pub fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+}
+
+impl Pollable for MqueueFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.inode.queue().poll(mask, poller)
+    }
+}
+
+impl FileLike for MqueueFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        self.inode.read_at(0, writer)
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.inode.read_at(offset, writer)
+    }
+
+    fn metadata(&self) -> Metadata {
+        self.inode.metadata()
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking() {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        self.is_nonblocking.store(
+            new_flags.contains(StatusFlags::O_NONBLOCK),
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
+    fn on_close(&self) {
+        // Like Linux, the registration made by `mq_notify` is removed once the registered
+        // process closes any descriptor of the queue.
+        if let Some(current) = Process::current() {
+            self.inode.queue().remove_notify(current.pid());
+        }
+    }
+}
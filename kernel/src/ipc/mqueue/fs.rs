@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#![expect(unused_variables)]
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use spin::Once;
+
+use super::{queue::MessageQueue, DFLT_MSG, DFLT_MSGSIZE};
+use crate::{
+    events::IoEvents,
+    fs::utils::{
+        DirentVisitor, FileSystem, FsFlags, Inode, InodeMode, InodeType, Metadata, MknodType,
+        Permission, SuperBlock, NAME_MAX,
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+        Gid, Uid,
+    },
+};
+
+const MQUEUE_MAGIC: u64 = 0x19800202;
+const BLOCK_SIZE: usize = 4096;
+const ROOT_INO: u64 = 1;
+
+static MQUEUE_FS: Once<Arc<MqueueFs>> = Once::new();
+
+/// Returns the mqueue filesystem, in which `mq_open` looks up the queues.
+pub fn mqueue_fs() -> &'static Arc<MqueueFs> {
+    MQUEUE_FS.get().unwrap()
+}
+
+pub(super) fn init() {
+    MQUEUE_FS.call_once(MqueueFs::new);
+}
+
+/// The mqueue filesystem, which holds all POSIX message queues as regular files.
+///
+/// It is usually mounted at "/dev/mqueue". Reading a queue file gives the status of the queue,
+/// and removing the file removes the queue.
+pub struct MqueueFs {
+    sb: SuperBlock,
+    root: Arc<MqueueRoot>,
+    ino_allocator: AtomicU64,
+}
+
+impl MqueueFs {
+    fn new() -> Arc<Self> {
+        Arc::new_cyclic(|weak_fs| Self {
+            sb: SuperBlock::new(MQUEUE_MAGIC, BLOCK_SIZE, NAME_MAX),
+            root: Arc::new(MqueueRoot {
+                queues: RwLock::new(BTreeMap::new()),
+                metadata: RwLock::new(Metadata::new_dir(
+                    ROOT_INO,
+                    InodeMode::from_bits_truncate(0o1777),
+                    BLOCK_SIZE,
+                )),
+                fs: weak_fs.clone(),
+            }),
+            ino_allocator: AtomicU64::new(ROOT_INO + 1),
+        })
+    }
+
+    /// Looks up the queue named `name`.
+    pub fn lookup(&self, name: &str) -> Result<Arc<MqueueInode>> {
+        self.root
+            .queues
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::with_message(Errno::ENOENT, "the message queue does not exist"))
+    }
+
+    /// Creates a queue named `name`.
+    pub fn create(
+        &self,
+        name: &str,
+        mode: InodeMode,
+        uid: Uid,
+        gid: Gid,
+        queue: MessageQueue,
+    ) -> Result<Arc<MqueueInode>> {
+        let mut queues = self.root.queues.write();
+        if queues.contains_key(name) {
+            return_errno_with_message!(Errno::EEXIST, "the message queue already exists");
+        }
+
+        let mut metadata = Metadata::new_file(
+            self.ino_allocator.fetch_add(1, Ordering::Relaxed),
+            mode,
+            BLOCK_SIZE,
+        );
+        metadata.uid = uid;
+        metadata.gid = gid;
+        let inode = Arc::new(MqueueInode {
+            queue,
+            metadata: RwLock::new(metadata),
+            fs: self.root.fs.clone(),
+        });
+        queues.insert(name.to_string(), inode.clone());
+        Ok(inode)
+    }
+
+    /// Removes the queue named `name`.
+    ///
+    /// The queue is destroyed after all its descriptors are closed.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let mut queues = self.root.queues.write();
+        let Some(queue) = queues.get(name) else {
+            return_errno_with_message!(Errno::ENOENT, "the message queue does not exist");
+        };
+
+        self.root
+            .check_permission(Permission::MAY_WRITE | Permission::MAY_EXEC)?;
+        // The root directory is sticky, so only the owners can remove the queue.
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let fsuid = credentials.fsuid();
+        if self.root.mode()?.has_sticky_bit()
+            && fsuid != queue.owner()?
+            && fsuid != self.root.owner()?
+            && !credentials.effective_capset().contains(CapSet::FOWNER)
+        {
+            return_errno_with_message!(Errno::EPERM, "only the owner can remove the message queue");
+        }
+
+        queues.remove(name);
+        Ok(())
+    }
+}
+
+impl FileSystem for MqueueFs {
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::empty()
+    }
+}
+
+struct MqueueRoot {
+    queues: RwLock<BTreeMap<String, Arc<MqueueInode>>>,
+    metadata: RwLock<Metadata>,
+    fs: Weak<MqueueFs>,
+}
+
+impl Inode for MqueueRoot {
+    fn size(&self) -> usize {
+        self.metadata.read().size
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        Err(Error::new(Errno::EISDIR))
+    }
+
+    fn metadata(&self) -> Metadata {
+        *self.metadata.read()
+    }
+
+    fn ino(&self) -> u64 {
+        self.metadata.read().ino as _
+    }
+
+    fn type_(&self) -> InodeType {
+        self.metadata.read().type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(self.metadata.read().mode)
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.metadata.write().mode = mode;
+        Ok(())
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.metadata.read().uid)
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.metadata.write().uid = uid;
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.metadata.read().gid)
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.metadata.write().gid = gid;
+        Ok(())
+    }
+
+    fn atime(&self) -> Duration {
+        self.metadata.read().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.metadata.write().atime = time;
+    }
+
+    fn mtime(&self) -> Duration {
+        self.metadata.read().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.metadata.write().mtime = time;
+    }
+
+    fn ctime(&self) -> Duration {
+        self.metadata.read().ctime
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.metadata.write().ctime = time;
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        if type_ != InodeType::File {
+            return_errno_with_message!(Errno::EPERM, "only message queues can be created");
+        }
+        let queue = MessageQueue::new(DFLT_MSG, DFLT_MSGSIZE);
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let inode = self.fs.upgrade().unwrap().create(
+            name,
+            mode,
+            credentials.fsuid(),
+            credentials.fsgid(),
+            queue,
+        )?;
+        Ok(inode)
+    }
+
+    fn mknod(&self, name: &str, mode: InodeMode, type_: MknodType) -> Result<Arc<dyn Inode>> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+            if *offset == 0 {
+                visitor.visit(".", self.ino(), self.type_(), *offset)?;
+                *offset += 1;
+            }
+            if *offset == 1 {
+                visitor.visit("..", self.ino(), self.type_(), *offset)?;
+                *offset += 1;
+            }
+
+            let queues = self.queues.read();
+            for (idx, (name, inode)) in queues.iter().enumerate().skip(*offset - 2) {
+                visitor.visit(name, inode.ino(), inode.type_(), idx + 2)?;
+                *offset = idx + 3;
+            }
+            Ok(())
+        };
+
+        let mut iterate_offset = offset;
+        match try_readdir(&mut iterate_offset, visitor) {
+            Err(e) if offset == iterate_offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+
+    fn link(&self, old: &Arc<dyn Inode>, name: &str) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        self.fs.upgrade().unwrap().remove(name)
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        Err(Error::new(Errno::ENOTDIR))
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        let inode: Arc<dyn Inode> = match name {
+            "." | ".." => self.fs().root_inode(),
+            name => self.fs.upgrade().unwrap().lookup(name)?,
+        };
+        Ok(inode)
+    }
+
+    fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        false
+    }
+}
+
+/// The inode of a POSIX message queue.
+pub struct MqueueInode {
+    queue: MessageQueue,
+    metadata: RwLock<Metadata>,
+    fs: Weak<MqueueFs>,
+}
+
+impl MqueueInode {
+    pub fn queue(&self) -> &MessageQueue {
+        &self.queue
+    }
+}
+
+impl Inode for MqueueInode {
+    fn size(&self) -> usize {
+        self.metadata.read().size
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn metadata(&self) -> Metadata {
+        *self.metadata.read()
+    }
+
+    fn ino(&self) -> u64 {
+        self.metadata.read().ino as _
+    }
+
+    fn type_(&self) -> InodeType {
+        self.metadata.read().type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(self.metadata.read().mode)
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.metadata.write().mode = mode;
+        Ok(())
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.metadata.read().uid)
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.metadata.write().uid = uid;
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.metadata.read().gid)
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.metadata.write().gid = gid;
+        Ok(())
+    }
+
+    fn atime(&self) -> Duration {
+        self.metadata.read().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.metadata.write().atime = time;
+    }
+
+    fn mtime(&self) -> Duration {
+        self.metadata.read().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.metadata.write().mtime = time;
+    }
+
+    fn ctime(&self) -> Duration {
+        self.metadata.read().ctime
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.metadata.write().ctime = time;
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let status = self.queue.status();
+        let Some(bytes) = status.as_bytes().get(offset..) else {
+            return Ok(0);
+        };
+        let len = writer.write_fallible(&mut bytes.into())?;
+        Ok(len)
+    }
+
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.queue.poll(mask, poller)
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! POSIX message queues.
+//!
+//! Each queue is a file in the mqueue filesystem. The queue is opened by `mq_open` and
+//! the returned descriptor refers to a [`MqueueFile`], which can be polled like other files.
+
+mod file;
+mod fs;
+mod notify;
+mod queue;
+
+pub use file::MqueueFile;
+pub use fs::{mqueue_fs, MqueueFs, MqueueInode};
+pub use notify::{has_cookie, recv_cookie};
+pub use queue::{MessageQueue, MqAttr, MqNotify, MqNotifyKind};
+
+/// The maximum priority of messages plus one.
+pub const MQ_PRIO_MAX: u32 = 32768;
+
+/// The length of the cookie delivered to the notification thread.
+pub const NOTIFY_COOKIE_LEN: usize = 32;
+/// The cookie status telling that a message has arrived.
+const NOTIFY_WOKENUP: u8 = 1;
+/// The cookie status telling that the registration has been removed.
+const NOTIFY_REMOVED: u8 = 2;
+
+/// The default maximum number of messages in a queue.
+pub const DFLT_MSG: usize = 10;
+/// The default maximum size of a message.
+pub const DFLT_MSGSIZE: usize = 8192;
+/// The maximum number of messages in a queue for unprivileged processes.
+pub const DFLT_MSGMAX: usize = 10;
+/// The maximum size of a message for unprivileged processes.
+pub const DFLT_MSGSIZEMAX: usize = 8192;
+/// The maximum number of messages in a queue for processes with `CAP_SYS_RESOURCE`.
+pub const HARD_MSGMAX: usize = 65536;
+/// The maximum size of a message for processes with `CAP_SYS_RESOURCE`.
+pub const HARD_MSGSIZEMAX: usize = 16 * 1024 * 1024;
+
+pub(super) fn init() {
+    fs::init();
+}
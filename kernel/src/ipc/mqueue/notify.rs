@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The delivery of `SIGEV_THREAD` notifications.
+//!
+//! The C library implements `SIGEV_THREAD` with a helper thread, which receives the cookies
+//! from a netlink socket and starts the notification threads. The cookies are not netlink
+//! messages, so they are kept here and received before any message in the socket.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::NOTIFY_COOKIE_LEN;
+use crate::{
+    fs::file_handle::FileLike,
+    net::socket::{netlink::NetlinkRouteSocket, util::SendRecvFlags, Socket},
+    prelude::*,
+    util::MultiWrite,
+};
+
+/// The pending cookies, keyed by the address of the netlink socket.
+static PENDING_COOKIES: Mutex<BTreeMap<usize, PendingCookies>> = Mutex::new(BTreeMap::new());
+
+/// Whether there may be any pending cookies, which keeps the receive path of other netlink
+/// sockets away from the lock.
+static HAS_PENDING_COOKIES: AtomicBool = AtomicBool::new(false);
+
+struct PendingCookies {
+    socket: Weak<dyn FileLike>,
+    cookies: VecDeque<[u8; NOTIFY_COOKIE_LEN]>,
+}
+
+impl PendingCookies {
+    /// Returns whether the socket is still alive.
+    ///
+    /// Otherwise, the key may have been reused by another socket at the same address.
+    fn is_alive(&self) -> bool {
+        self.socket.strong_count() > 0
+    }
+}
+
+/// Queues a cookie to the netlink socket and wakes up the helper thread.
+pub(super) fn send_cookie(socket: &Arc<dyn FileLike>, cookie: [u8; NOTIFY_COOKIE_LEN]) {
+    let Some(netlink_socket) = socket.downcast_ref::<NetlinkRouteSocket>() else {
+        return;
+    };
+
+    {
+        let mut pending_cookies = PENDING_COOKIES.lock();
+        // Drop the cookies of the sockets that have been closed.
+        pending_cookies.retain(|_, pending| pending.is_alive());
+        pending_cookies
+            .entry(socket_key(netlink_socket))
+            .or_insert_with(|| PendingCookies {
+                socket: Arc::downgrade(socket),
+                cookies: VecDeque::new(),
+            })
+            .cookies
+            .push_back(cookie);
+        HAS_PENDING_COOKIES.store(true, Ordering::Relaxed);
+    }
+
+    netlink_socket.notify_readable();
+}
+
+/// Receives a pending cookie from the netlink socket.
+///
+/// This returns `None` if no cookie is pending on the socket.
+pub fn recv_cookie(
+    socket: &dyn Socket,
+    writer: &mut dyn MultiWrite,
+    flags: SendRecvFlags,
+) -> Option<Result<usize>> {
+    if !HAS_PENDING_COOKIES.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let mut pending_cookies = PENDING_COOKIES.lock();
+    let key = socket_key(socket);
+    let cookie = *pending_cookies
+        .get(&key)
+        .filter(|pending| pending.is_alive())?
+        .cookies
+        .front()?;
+
+    let result = writer.write(&mut VmReader::from(cookie.as_slice()));
+    if result.is_ok() && !flags.contains(SendRecvFlags::MSG_PEEK) {
+        let pending = pending_cookies.get_mut(&key).unwrap();
+        pending.cookies.pop_front();
+        if pending.cookies.is_empty() {
+            pending_cookies.remove(&key);
+            HAS_PENDING_COOKIES.store(!pending_cookies.is_empty(), Ordering::Relaxed);
+        }
+    }
+
+    Some(result)
+}
+
+/// Returns whether any cookie is pending on the netlink socket.
+pub fn has_cookie(socket: &dyn Socket) -> bool {
+    HAS_PENDING_COOKIES.load(Ordering::Relaxed)
+        && PENDING_COOKIES
+            .lock()
+            .get(&socket_key(socket))
+            .is_some_and(PendingCookies::is_alive)
+}
+
+fn socket_key(socket: &dyn Socket) -> usize {
+    socket as *const dyn Socket as *const () as usize
+}
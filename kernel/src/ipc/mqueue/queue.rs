@@ -0,0 +1,338 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use super::{notify, MQ_PRIO_MAX, NOTIFY_COOKIE_LEN, NOTIFY_REMOVED, NOTIFY_WOKENUP};
+use crate::{
+    events::IoEvents,
+    fs::file_handle::FileLike,
+    prelude::*,
+    process::{
+        signal::{
+            c_types::SigNotify,
+            sig_num::SigNum,
+            signals::user::{UserSignal, UserSignalKind},
+            PollHandle, Pollable, Pollee,
+        },
+        Pid, Process, Uid,
+    },
+};
+
+/// The attributes of a message queue, i.e., `struct mq_attr`.
+#[derive(Debug, Clone, Copy, Default, Pod)]
+#[repr(C)]
+pub struct MqAttr {
+    /// The flags of the queue descriptor, which is either 0 or `O_NONBLOCK`.
+    pub mq_flags: i64,
+    /// The maximum number of messages in the queue.
+    pub mq_maxmsg: i64,
+    /// The maximum size of a message in bytes.
+    pub mq_msgsize: i64,
+    /// The number of messages currently in the queue.
+    pub mq_curmsgs: i64,
+    __reserved: [i64; 4],
+}
+
+/// A POSIX message queue.
+///
+/// Messages are received in the order of decreasing priority, and messages of the same priority
+/// are received in the order they are sent.
+pub struct MessageQueue {
+    max_msgs: usize,
+    msg_size: usize,
+    inner: Mutex<QueueInner>,
+    /// The number of threads blocked in receiving messages.
+    num_receivers: AtomicUsize,
+    pollee: Pollee,
+}
+
+struct QueueInner {
+    /// The messages, grouped and ordered by priority.
+    messages: BTreeMap<u32, VecDeque<Vec<u8>>>,
+    num_msgs: usize,
+    num_bytes: usize,
+    notify: Option<MqNotify>,
+}
+
+/// A registration made by `mq_notify`.
+pub struct MqNotify {
+    owner: Weak<Process>,
+    owner_pid: Pid,
+    kind: MqNotifyKind,
+}
+
+/// How a process is notified when a message arrives at an empty queue.
+pub enum MqNotifyKind {
+    /// No notification is sent, but the registration is still removed (`SIGEV_NONE`).
+    None,
+    /// A signal carrying `value` is sent to the owner process (`SIGEV_SIGNAL`).
+    Signal { signo: SigNum, value: usize },
+    /// The cookie is delivered to the netlink socket, on which the C library waits to start
+    /// the notification thread (`SIGEV_THREAD`).
+    Thread {
+        socket: Arc<dyn FileLike>,
+        cookie: [u8; NOTIFY_COOKIE_LEN],
+    },
+}
+
+impl MqNotify {
+    pub fn new(owner: &Arc<Process>, kind: MqNotifyKind) -> Self {
+        Self {
+            owner: Arc::downgrade(owner),
+            owner_pid: owner.pid(),
+            kind,
+        }
+    }
+
+    fn sigev_notify(&self) -> SigNotify {
+        match self.kind {
+            MqNotifyKind::None => SigNotify::SIGEV_NONE,
+            MqNotifyKind::Signal { .. } => SigNotify::SIGEV_SIGNAL,
+            MqNotifyKind::Thread { .. } => SigNotify::SIGEV_THREAD,
+        }
+    }
+
+    /// Sends the notification on behalf of the sender of the message.
+    fn send(self, sender_pid: Pid, sender_uid: Uid) {
+        match self.kind {
+            MqNotifyKind::None => (),
+            MqNotifyKind::Signal { signo, value } => {
+                let Some(owner) = self.owner.upgrade() else {
+                    return;
+                };
+                let kind = UserSignalKind::Mesgq(value);
+                owner.enqueue_signal(UserSignal::new(signo, kind, sender_pid, sender_uid));
+            }
+            MqNotifyKind::Thread { socket, cookie } => {
+                send_cookie(&socket, cookie, NOTIFY_WOKENUP);
+            }
+        }
+    }
+
+    /// Tells the notification thread that the registration is removed.
+    fn cancel(self) {
+        if let MqNotifyKind::Thread { socket, cookie } = self.kind {
+            send_cookie(&socket, cookie, NOTIFY_REMOVED);
+        }
+    }
+}
+
+fn send_cookie(socket: &Arc<dyn FileLike>, mut cookie: [u8; NOTIFY_COOKIE_LEN], status: u8) {
+    cookie[NOTIFY_COOKIE_LEN - 1] = status;
+    notify::send_cookie(socket, cookie);
+}
+
+impl MessageQueue {
+    pub fn new(max_msgs: usize, msg_size: usize) -> Self {
+        Self {
+            max_msgs,
+            msg_size,
+            inner: Mutex::new(QueueInner {
+                messages: BTreeMap::new(),
+                num_msgs: 0,
+                num_bytes: 0,
+                notify: None,
+            }),
+            num_receivers: AtomicUsize::new(0),
+            pollee: Pollee::new(),
+        }
+    }
+
+    /// Returns the attributes of the queue, with `mq_flags` left as zero.
+    pub fn attr(&self) -> MqAttr {
+        MqAttr {
+            mq_maxmsg: self.max_msgs as i64,
+            mq_msgsize: self.msg_size as i64,
+            mq_curmsgs: self.inner.lock().num_msgs as i64,
+            ..Default::default()
+        }
+    }
+
+    /// Sends a message with the priority `prio`.
+    ///
+    /// If the queue is full, this method blocks until there is room for the message or the
+    /// `timeout` expires, unless `is_nonblocking` is true.
+    pub fn send(
+        &self,
+        msg: Vec<u8>,
+        prio: u32,
+        is_nonblocking: bool,
+        timeout: Option<&Duration>,
+        ctx: &Context,
+    ) -> Result<()> {
+        if msg.len() > self.msg_size {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too long");
+        }
+        if prio >= MQ_PRIO_MAX {
+            return_errno_with_message!(Errno::EINVAL, "the priority is too large");
+        }
+
+        let mut msg = Some(msg);
+        let mut try_send = || self.try_send(&mut msg, prio);
+        let notify = if is_nonblocking {
+            try_send()
+        } else {
+            self.wait_events(IoEvents::OUT, timeout, try_send)
+                .map_err(map_timeout_error)
+        }?;
+
+        if let Some(notify) = notify {
+            let sender_uid = ctx.posix_thread.credentials().ruid();
+            notify.send(ctx.process.pid(), sender_uid);
+        }
+        Ok(())
+    }
+
+    /// Receives the oldest message of the highest priority into `writer`.
+    ///
+    /// If the queue is empty, this method blocks until a message arrives or the `timeout`
+    /// expires, unless `is_nonblocking` is true.
+    ///
+    /// Returns the length and the priority of the message.
+    pub fn receive(
+        &self,
+        writer: &mut VmWriter,
+        is_nonblocking: bool,
+        timeout: Option<&Duration>,
+    ) -> Result<(usize, u32)> {
+        if writer.avail() < self.msg_size {
+            return_errno_with_message!(Errno::EMSGSIZE, "the buffer is smaller than mq_msgsize");
+        }
+
+        let (msg, prio) = if is_nonblocking {
+            self.try_receive()
+        } else {
+            self.num_receivers.fetch_add(1, Ordering::Relaxed);
+            let res = self
+                .wait_events(IoEvents::IN, timeout, || self.try_receive())
+                .map_err(map_timeout_error);
+            self.num_receivers.fetch_sub(1, Ordering::Relaxed);
+            res
+        }?;
+
+        writer.write_fallible(&mut msg.as_slice().into())?;
+        Ok((msg.len(), prio))
+    }
+
+    fn try_send(&self, msg: &mut Option<Vec<u8>>, prio: u32) -> Result<Option<MqNotify>> {
+        let mut inner = self.inner.lock();
+        if inner.num_msgs >= self.max_msgs {
+            return_errno_with_message!(Errno::EAGAIN, "the message queue is full");
+        }
+
+        let msg = msg.take().unwrap();
+        inner.num_msgs += 1;
+        inner.num_bytes += msg.len();
+        inner.messages.entry(prio).or_default().push_back(msg);
+
+        // Notify the registered process only if the queue was empty and no thread is waiting to
+        // receive the message.
+        let notify = if inner.num_msgs == 1 && self.num_receivers.load(Ordering::Relaxed) == 0 {
+            inner.notify.take()
+        } else {
+            None
+        };
+        drop(inner);
+
+        self.pollee.notify(IoEvents::IN);
+        Ok(notify)
+    }
+
+    fn try_receive(&self) -> Result<(Vec<u8>, u32)> {
+        let mut inner = self.inner.lock();
+        let Some(mut entry) = inner.messages.last_entry() else {
+            return_errno_with_message!(Errno::EAGAIN, "the message queue is empty");
+        };
+
+        let prio = *entry.key();
+        let msg = entry.get_mut().pop_front().unwrap();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        inner.num_msgs -= 1;
+        inner.num_bytes -= msg.len();
+        drop(inner);
+
+        self.pollee.notify(IoEvents::OUT);
+        Ok((msg, prio))
+    }
+
+    /// Registers the process for notification.
+    ///
+    /// Only one process can be registered at a time.
+    pub fn set_notify(&self, notify: MqNotify) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if inner.notify.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "another process has registered");
+        }
+        inner.notify = Some(notify);
+        Ok(())
+    }
+
+    /// Removes the registration if it is made by the process `pid`.
+    pub fn remove_notify(&self, pid: Pid) {
+        let mut inner = self.inner.lock();
+        if inner
+            .notify
+            .as_ref()
+            .is_some_and(|notify| notify.owner_pid == pid)
+        {
+            let notify = inner.notify.take().unwrap();
+            drop(inner);
+            notify.cancel();
+        }
+    }
+
+    /// Returns the status line shown when reading the queue file.
+    pub fn status(&self) -> String {
+        let inner = self.inner.lock();
+        let (sigev_notify, signo, pid) = match &inner.notify {
+            Some(notify) => {
+                let signo = match notify.kind {
+                    MqNotifyKind::Signal { signo, .. } => signo.as_u8(),
+                    _ => 0,
+                };
+                (notify.sigev_notify() as i32, signo, notify.owner_pid)
+            }
+            None => (0, 0, 0),
+        };
+        format!(
+            "QSIZE:{:<10} NOTIFY:{:<5} SIGNO:{:<5} NOTIFY_PID:{:<6}\n",
+            inner.num_bytes, sigev_notify, signo, pid
+        )
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.lock();
+
+        let mut events = IoEvents::empty();
+        if inner.num_msgs > 0 {
+            events |= IoEvents::IN;
+        }
+        if inner.num_msgs < self.max_msgs {
+            events |= IoEvents::OUT;
+        }
+        events
+    }
+}
+
+impl Pollable for MessageQueue {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+/// Maps the timeout error of [`Pollable::wait_events`] to the one of `mq_timedsend` and
+/// `mq_timedreceive`.
+fn map_timeout_error(err: Error) -> Error {
+    if err.error() == Errno::ETIME {
+        Error::with_message(Errno::ETIMEDOUT, "the timeout expired")
+    } else {
+        err
+    }
+}
@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! System V message queues.
+//!
+//! A message queue is identified by a free identifier allocated on creation. Unless it is created
+//! with `IPC_PRIVATE`, it can also be found by its key.
+
+use crate::prelude::*;
+
+pub mod msg_queue;
+
+pub use msg_queue::{Message, MsgQueue, MsgStat};
+
+// The following constant values are derived from the default values in Linux.
+
+/// Maximum number of message queues.
+pub const MSGMNI: usize = 32000;
+/// Maximum size of a message in bytes.
+pub const MSGMAX: usize = 8192;
+/// Default maximum size of a message queue in bytes.
+pub const MSGMNB: usize = 16384;
+
+bitflags! {
+    pub struct MsgFlags: u32 {
+        /// Return error on wait
+        const IPC_NOWAIT = 1 << 11;
+        /// Truncate the message if it is too long
+        const MSG_NOERROR = 0o10000;
+        /// Receive the first message whose type is not `msgtyp`
+        const MSG_EXCEPT = 0o20000;
+        /// Copy the message at the position `msgtyp` without removing it
+        const MSG_COPY = 0o40000;
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+pub enum MsgControlCmd {
+    IPC_RMID = 0,
+    IPC_SET = 1,
+    IPC_STAT = 2,
+    IPC_INFO = 3,
+
+    MSG_STAT = 11,
+    MSG_INFO = 12,
+    MSG_STAT_ANY = 13,
+}
+
+pub(super) fn init() {
+    msg_queue::init();
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+
+use aster_rights::ReadOp;
+use id_alloc::IdAlloc;
+use ostd::sync::WaitQueue;
+use spin::Once;
+
+use super::{MsgFlags, MSGMNB, MSGMNI};
+use crate::{
+    ipc::{key_t, shm::IPC_PRIVATE, IpcFlags, IpcPermission},
+    prelude::*,
+    process::{credentials::capabilities::CapSet, Credentials, Pid},
+    time::clocks::RealTimeCoarseClock,
+};
+
+/// A message in a System V message queue.
+#[derive(Debug)]
+pub struct Message {
+    mtype: i64,
+    data: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(mtype: i64, data: Vec<u8>) -> Self {
+        Self { mtype, data }
+    }
+
+    pub fn mtype(&self) -> i64 {
+        self.mtype
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A System V message queue.
+#[derive(Debug)]
+pub struct MsgQueue {
+    /// The identifier of the queue
+    id: key_t,
+    /// Inner
+    inner: SpinLock<MsgQueueInner>,
+    /// Senders waiting for free space
+    send_wait_queue: WaitQueue,
+    /// Receivers waiting for messages
+    recv_wait_queue: WaitQueue,
+}
+
+#[derive(Debug)]
+struct MsgQueueInner {
+    /// Queue permission
+    permission: IpcPermission,
+    /// Messages in the order they are sent
+    messages: VecDeque<Message>,
+    /// Number of bytes of all messages
+    cbytes: usize,
+    /// Maximum number of bytes allowed in the queue
+    qbytes: usize,
+    /// Last send time
+    stime: u64,
+    /// Last receive time
+    rtime: u64,
+    /// Creation time or last modification via `msgctl`
+    ctime: u64,
+    /// PID of the last `msgsnd`
+    lspid: Pid,
+    /// PID of the last `msgrcv`
+    lrpid: Pid,
+    /// Whether the queue is removed
+    is_removed: bool,
+}
+
+/// The status of a queue, as reported by `IPC_STAT`.
+#[derive(Debug)]
+pub struct MsgStat {
+    pub key: key_t,
+    pub uid: u32,
+    pub gid: u32,
+    pub cuid: u32,
+    pub cgid: u32,
+    pub mode: u16,
+    pub stime: u64,
+    pub rtime: u64,
+    pub ctime: u64,
+    pub cbytes: usize,
+    pub qnum: usize,
+    pub qbytes: usize,
+    pub lspid: Pid,
+    pub lrpid: Pid,
+}
+
+impl MsgQueue {
+    pub fn id(&self) -> key_t {
+        self.id
+    }
+
+    /// Checks whether the process with `credentials` is granted the `requested` permission bits.
+    pub fn check_access(&self, credentials: &Credentials<ReadOp>, requested: u16) -> Result<()> {
+        self.inner
+            .lock()
+            .permission
+            .check_access(credentials, requested)
+    }
+
+    /// Returns whether the process with `credentials` can change or remove the queue.
+    pub fn is_owner(&self, credentials: &Credentials<ReadOp>) -> bool {
+        self.inner.lock().permission.is_owner(credentials)
+    }
+
+    /// Sends `message` on behalf of the process `pid`.
+    ///
+    /// If the queue is full, the method blocks until there is enough space, unless
+    /// `is_nonblocking` is true.
+    pub fn send(&self, message: Message, is_nonblocking: bool, pid: Pid) -> Result<()> {
+        let mut message = Some(message);
+        let mut try_send = || {
+            let mut inner = self.inner.lock();
+            if inner.is_removed {
+                return Some(Err(Error::with_message(
+                    Errno::EIDRM,
+                    "the message queue is removed",
+                )));
+            }
+
+            let len = message.as_ref().unwrap().data.len();
+            // Like Linux, the number of messages is also limited by `qbytes`, so that zero-sized
+            // messages cannot be queued infinitely.
+            if inner.cbytes + len > inner.qbytes || inner.messages.len() + 1 > inner.qbytes {
+                return None;
+            }
+
+            inner.cbytes += len;
+            inner.messages.push_back(message.take().unwrap());
+            inner.stime = now_secs();
+            inner.lspid = pid;
+            drop(inner);
+
+            self.recv_wait_queue.wake_all();
+            Some(Ok(()))
+        };
+
+        if is_nonblocking {
+            return try_send()
+                .unwrap_or_else(|| Err(Error::with_message(Errno::EAGAIN, "the queue is full")));
+        }
+        self.send_wait_queue.pause_until(try_send)?
+    }
+
+    /// Receives a message selected by `msgtyp` on behalf of the process `pid`.
+    ///
+    /// If `msgtyp` is zero, the first message is received. If `msgtyp` is positive, the first
+    /// message of type `msgtyp` is received, or the first message of any other type if
+    /// `MSG_EXCEPT` is given. If `msgtyp` is negative, the first message of the lowest type that
+    /// is less than or equal to the absolute value of `msgtyp` is received.
+    ///
+    /// A message longer than `max_len` is truncated if `MSG_NOERROR` is given, or is left in the
+    /// queue otherwise.
+    pub fn receive(
+        &self,
+        msgtyp: i64,
+        max_len: usize,
+        flags: MsgFlags,
+        pid: Pid,
+    ) -> Result<Message> {
+        let try_receive = || {
+            let mut inner = self.inner.lock();
+            if inner.is_removed {
+                return Some(Err(Error::with_message(
+                    Errno::EIDRM,
+                    "the message queue is removed",
+                )));
+            }
+
+            let index = inner.find_message(msgtyp, flags.contains(MsgFlags::MSG_EXCEPT))?;
+            if inner.messages[index].data.len() > max_len && !flags.contains(MsgFlags::MSG_NOERROR)
+            {
+                return Some(Err(Error::with_message(
+                    Errno::E2BIG,
+                    "the message is longer than the buffer",
+                )));
+            }
+
+            let mut message = inner.messages.remove(index).unwrap();
+            inner.cbytes -= message.data.len();
+            inner.rtime = now_secs();
+            inner.lrpid = pid;
+            drop(inner);
+
+            message.data.truncate(max_len);
+            self.send_wait_queue.wake_all();
+            Some(Ok(message))
+        };
+
+        if flags.contains(MsgFlags::IPC_NOWAIT) {
+            return try_receive()
+                .unwrap_or_else(|| Err(Error::with_message(Errno::ENOMSG, "no message is found")));
+        }
+        self.recv_wait_queue.pause_until(try_receive)?
+    }
+
+    pub fn stat(&self) -> MsgStat {
+        let inner = self.inner.lock();
+        let permission = &inner.permission;
+        MsgStat {
+            key: permission.key(),
+            uid: permission.uid().into(),
+            gid: permission.gid().into(),
+            cuid: permission.cuid().into(),
+            cgid: permission.cguid().into(),
+            mode: permission.mode(),
+            stime: inner.stime,
+            rtime: inner.rtime,
+            ctime: inner.ctime,
+            cbytes: inner.cbytes,
+            qnum: inner.messages.len(),
+            qbytes: inner.qbytes,
+            lspid: inner.lspid,
+            lrpid: inner.lrpid,
+        }
+    }
+
+    /// Sets the owner, the permission bits and the maximum size, as done by `IPC_SET`.
+    ///
+    /// Raising the maximum size above `MSGMNB` requires `CAP_SYS_RESOURCE`.
+    pub fn set(
+        &self,
+        uid: u32,
+        gid: u32,
+        mode: u16,
+        qbytes: usize,
+        credentials: &Credentials<ReadOp>,
+    ) -> Result<()> {
+        let mut inner = self.inner.lock();
+        if qbytes > MSGMNB
+            && qbytes > inner.qbytes
+            && !credentials
+                .effective_capset()
+                .contains(CapSet::SYS_RESOURCE)
+        {
+            return_errno_with_message!(Errno::EPERM, "the queue size exceeds MSGMNB");
+        }
+
+        let permission = &mut inner.permission;
+        permission.uid = uid.into();
+        permission.gid = gid.into();
+        permission.mode = (permission.mode & !0o777) | (mode & 0o777);
+        inner.qbytes = qbytes;
+        inner.ctime = now_secs();
+        drop(inner);
+
+        // The senders may be able to proceed with a larger queue.
+        self.send_wait_queue.wake_all();
+        Ok(())
+    }
+
+    fn new(id: key_t, key: key_t, mode: u16, credentials: &Credentials<ReadOp>) -> Self {
+        let permission = IpcPermission::new(key, credentials.euid(), credentials.egid(), mode);
+
+        Self {
+            id,
+            inner: SpinLock::new(MsgQueueInner {
+                permission,
+                messages: VecDeque::new(),
+                cbytes: 0,
+                qbytes: MSGMNB,
+                stime: 0,
+                rtime: 0,
+                ctime: now_secs(),
+                lspid: 0,
+                lrpid: 0,
+                is_removed: false,
+            }),
+            send_wait_queue: WaitQueue::new(),
+            recv_wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Marks the queue as removed and wakes up all waiters, which will fail with `EIDRM`.
+    fn mark_removed(&self) {
+        self.inner.lock().is_removed = true;
+        self.send_wait_queue.wake_all();
+        self.recv_wait_queue.wake_all();
+    }
+}
+
+impl MsgQueueInner {
+    fn find_message(&self, msgtyp: i64, is_except: bool) -> Option<usize> {
+        match msgtyp {
+            0 => (!self.messages.is_empty()).then_some(0),
+            msgtyp if msgtyp > 0 => self
+                .messages
+                .iter()
+                .position(|message| (message.mtype == msgtyp) != is_except),
+            msgtyp => {
+                let limit = msgtyp.unsigned_abs();
+                self.messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, message)| message.mtype as u64 <= limit)
+                    .min_by_key(|(_, message)| message.mtype)
+                    .map(|(index, _)| index)
+            }
+        }
+    }
+}
+
+/// Returns the identifier of the queue with `key`, creating the queue if necessary.
+///
+/// A new queue is always created for `IPC_PRIVATE`. Otherwise, it is created only if no queue
+/// has `key` and `flags` contains `IPC_CREAT`. The lookup and the creation are done with the
+/// lock held, so that the callers racing on the same key get the same queue.
+pub fn get_or_create_msg_queue(
+    key: key_t,
+    flags: IpcFlags,
+    mode: u16,
+    credentials: &Credentials<ReadOp>,
+) -> Result<key_t> {
+    let mut msg_queues = MSG_QUEUES.write();
+
+    if key != IPC_PRIVATE {
+        let queue = msg_queues
+            .values()
+            .find(|queue| queue.inner.lock().permission.key() == key);
+        if let Some(queue) = queue {
+            if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+                return_errno_with_message!(Errno::EEXIST, "the message queue already exists");
+            }
+            let requested = (mode >> 6) | (mode >> 3) | mode;
+            queue.check_access(credentials, requested)?;
+            return Ok(queue.id());
+        }
+        if !flags.contains(IpcFlags::IPC_CREAT) {
+            return_errno_with_message!(Errno::ENOENT, "the message queue does not exist");
+        }
+    }
+
+    let id = ID_ALLOCATOR
+        .get()
+        .unwrap()
+        .lock()
+        .alloc()
+        .ok_or(Error::with_message(
+            Errno::ENOSPC,
+            "too many message queues",
+        ))? as key_t;
+
+    msg_queues.insert(id, Arc::new(MsgQueue::new(id, key, mode, credentials)));
+
+    Ok(id)
+}
+
+/// Returns the queue identified by `id`.
+pub fn get_msg_queue(id: key_t) -> Result<Arc<MsgQueue>> {
+    MSG_QUEUES
+        .read()
+        .get(&id)
+        .cloned()
+        .ok_or(Error::with_message(
+            Errno::EINVAL,
+            "the message queue does not exist",
+        ))
+}
+
+/// Removes the queue identified by `id`.
+///
+/// The processes blocking on the queue are woken up and fail with `EIDRM`.
+pub fn remove_msg_queue(id: key_t) -> Result<()> {
+    let queue = MSG_QUEUES.write().remove(&id).ok_or(Error::with_message(
+        Errno::EINVAL,
+        "the message queue does not exist",
+    ))?;
+    queue.mark_removed();
+
+    ID_ALLOCATOR.get().unwrap().lock().free(id as usize);
+    Ok(())
+}
+
+/// Returns the identifiers of all queues.
+pub fn msg_queue_ids() -> Vec<key_t> {
+    MSG_QUEUES.read().keys().copied().collect()
+}
+
+fn now_secs() -> u64 {
+    RealTimeCoarseClock::get().read_time().as_secs()
+}
+
+static ID_ALLOCATOR: Once<SpinLock<IdAlloc>> = Once::new();
+
+/// Message queues in system
+static MSG_QUEUES: RwLock<BTreeMap<key_t, Arc<MsgQueue>>> = RwLock::new(BTreeMap::new());
+
+pub(super) fn init() {
+    ID_ALLOCATOR.call_once(|| {
+        let mut id_alloc = IdAlloc::with_capacity(MSGMNI + 1);
+        // Remove the first index 0
+        id_alloc.alloc();
+
+        SpinLock::new(id_alloc)
+    });
+}
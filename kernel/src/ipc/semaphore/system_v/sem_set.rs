@@ -181,8 +181,7 @@ impl SemaphoreSet {
             sems.push(Semaphore::new(0));
         }
 
-        let permission =
-            IpcPermission::new_sem_perm(key, credentials.euid(), credentials.egid(), mode);
+        let permission = IpcPermission::new(key, credentials.euid(), credentials.egid(), mode);
 
         Ok(Self {
             nsems,
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! System V shared memory.
+//!
+//! Each segment is backed by a [`Vmo`] and is mapped into the address space of the attaching
+//! process as a shared mapping. Every mapping of a segment holds a [`ShmAttachment`], so the
+//! segment knows how many times it is attached and can be destroyed after `IPC_RMID` once the
+//! last attachment goes away.
+//!
+//! [`Vmo`]: crate::vm::vmo::Vmo
+
+use super::key_t;
+use crate::prelude::*;
+
+pub mod segment;
+
+pub use segment::{SharedMemory, ShmAttachment};
+
+/// The key that always creates a new segment.
+pub const IPC_PRIVATE: key_t = 0;
+
+// The following constant values are derived from the default values in Linux.
+
+/// Maximum size of a segment in bytes.
+pub const SHMMAX: usize = usize::MAX - (1 << 24);
+/// Minimum size of a segment in bytes.
+pub const SHMMIN: usize = 1;
+/// Maximum number of segments.
+pub const SHMMNI: usize = 4096;
+/// Maximum number of segments that a process can attach.
+pub const SHMSEG: usize = SHMMNI;
+/// Maximum number of pages of all segments.
+pub const SHMALL: usize = usize::MAX - (1 << 24);
+
+/// The mode bit telling that the segment will be destroyed after the last detach.
+pub const SHM_DEST: u16 = 0o1000;
+/// The mode bit telling that the segment is locked in memory.
+pub const SHM_LOCKED: u16 = 0o2000;
+
+bitflags! {
+    pub struct ShmFlags: u32 {
+        /// Attach the segment for reading only.
+        const SHM_RDONLY = 0o10000;
+        /// Round the attach address down to a multiple of `SHMLBA`.
+        const SHM_RND = 0o20000;
+        /// Take over the region at the attach address.
+        const SHM_REMAP = 0o40000;
+        /// Attach the segment with the execute permission.
+        const SHM_EXEC = 0o100000;
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+pub enum ShmControlCmd {
+    IPC_RMID = 0,
+    IPC_SET = 1,
+    IPC_STAT = 2,
+    IPC_INFO = 3,
+
+    SHM_LOCK = 11,
+    SHM_UNLOCK = 12,
+    SHM_STAT = 13,
+    SHM_INFO = 14,
+    SHM_STAT_ANY = 15,
+}
+
+pub(super) fn init() {
+    segment::init();
+}
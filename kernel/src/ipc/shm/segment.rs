@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::btree_map::BTreeMap;
+
+use align_ext::AlignExt;
+use aster_rights::{ReadOp, Rights};
+use id_alloc::IdAlloc;
+use spin::Once;
+
+use super::{IPC_PRIVATE, SHMALL, SHMMAX, SHMMIN, SHMMNI, SHM_DEST};
+use crate::{
+    ipc::{key_t, IpcFlags, IpcPermission},
+    prelude::*,
+    process::{Credentials, Pid},
+    time::clocks::RealTimeCoarseClock,
+    vm::vmo::{Vmo, VmoOptions},
+};
+
+/// A System V shared memory segment.
+#[derive(Debug)]
+pub struct SharedMemory {
+    /// The identifier of the segment
+    shmid: key_t,
+    /// The size requested by `shmget`, in bytes
+    size: usize,
+    /// The memory pages of the segment
+    vmo: Vmo<Rights>,
+    /// The PID of the creator
+    cpid: Pid,
+    /// Inner
+    inner: SpinLock<ShmInner>,
+}
+
+#[derive(Debug)]
+struct ShmInner {
+    /// Segment permission
+    permission: IpcPermission,
+    /// Number of current attaches
+    nattch: usize,
+    /// Last attach time
+    atime: u64,
+    /// Last detach time
+    dtime: u64,
+    /// Creation time or last modification via `shmctl`
+    ctime: u64,
+    /// PID of the last `shmat` or `shmdt`
+    lpid: Pid,
+}
+
+/// The status of a segment, as reported by `IPC_STAT`.
+#[derive(Debug)]
+pub struct ShmStat {
+    pub key: key_t,
+    pub uid: u32,
+    pub gid: u32,
+    pub cuid: u32,
+    pub cgid: u32,
+    pub mode: u16,
+    pub size: usize,
+    pub atime: u64,
+    pub dtime: u64,
+    pub ctime: u64,
+    pub cpid: Pid,
+    pub lpid: Pid,
+    pub nattch: usize,
+}
+
+impl SharedMemory {
+    pub fn shmid(&self) -> key_t {
+        self.shmid
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn vmo(&self) -> &Vmo<Rights> {
+        &self.vmo
+    }
+
+    /// Checks whether the process with `credentials` is granted the `requested` permission bits.
+    pub fn check_access(&self, credentials: &Credentials<ReadOp>, requested: u16) -> Result<()> {
+        self.inner
+            .lock()
+            .permission
+            .check_access(credentials, requested)
+    }
+
+    /// Returns whether the process with `credentials` can change or remove the segment.
+    pub fn is_owner(&self, credentials: &Credentials<ReadOp>) -> bool {
+        self.inner.lock().permission.is_owner(credentials)
+    }
+
+    /// Attaches the segment on behalf of the process `pid`.
+    ///
+    /// The returned attachment should be kept by the mapping of the segment.
+    pub fn attach(self: &Arc<Self>, pid: Pid) -> ShmAttachment {
+        let mut inner = self.inner.lock();
+        inner.nattch += 1;
+        inner.atime = now_secs();
+        inner.lpid = pid;
+        drop(inner);
+
+        ShmAttachment {
+            segment: self.clone(),
+        }
+    }
+
+    /// Updates the time and the PID of the last detach.
+    pub fn record_detach(&self, pid: Pid) {
+        let mut inner = self.inner.lock();
+        inner.dtime = now_secs();
+        inner.lpid = pid;
+    }
+
+    pub fn stat(&self) -> ShmStat {
+        let inner = self.inner.lock();
+        let permission = &inner.permission;
+        ShmStat {
+            key: permission.key(),
+            uid: permission.uid().into(),
+            gid: permission.gid().into(),
+            cuid: permission.cuid().into(),
+            cgid: permission.cguid().into(),
+            mode: permission.mode(),
+            size: self.size,
+            atime: inner.atime,
+            dtime: inner.dtime,
+            ctime: inner.ctime,
+            cpid: self.cpid,
+            lpid: inner.lpid,
+            nattch: inner.nattch,
+        }
+    }
+
+    /// Sets the owner and the permission bits, as done by `IPC_SET`.
+    pub fn set_owner_and_mode(&self, uid: u32, gid: u32, mode: u16) {
+        let mut inner = self.inner.lock();
+        let permission = &mut inner.permission;
+        permission.uid = uid.into();
+        permission.gid = gid.into();
+        permission.mode = (permission.mode & !0o777) | (mode & 0o777);
+        inner.ctime = now_secs();
+    }
+
+    /// Sets or clears the mode bits given by `flags`.
+    pub fn set_mode_flags(&self, flags: u16, is_set: bool) {
+        let mut inner = self.inner.lock();
+        if is_set {
+            inner.permission.mode |= flags;
+        } else {
+            inner.permission.mode &= !flags;
+        }
+        inner.ctime = now_secs();
+    }
+
+    fn new(
+        shmid: key_t,
+        key: key_t,
+        size: usize,
+        vmo: Vmo<Rights>,
+        mode: u16,
+        credentials: &Credentials<ReadOp>,
+        cpid: Pid,
+    ) -> Self {
+        let permission = IpcPermission::new(key, credentials.euid(), credentials.egid(), mode);
+
+        Self {
+            shmid,
+            size,
+            vmo,
+            cpid,
+            inner: SpinLock::new(ShmInner {
+                permission,
+                nattch: 0,
+                atime: 0,
+                dtime: 0,
+                ctime: now_secs(),
+                lpid: 0,
+            }),
+        }
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        ID_ALLOCATOR.get().unwrap().lock().free(self.shmid as usize);
+    }
+}
+
+/// An attachment of a shared memory segment, which is kept by the mapping of the segment.
+///
+/// Cloning the attachment, e.g., when the mapping is forked or split, counts as a new attach.
+/// Dropping the last attachment of a removed segment destroys the segment.
+#[derive(Debug)]
+pub struct ShmAttachment {
+    segment: Arc<SharedMemory>,
+}
+
+impl ShmAttachment {
+    pub fn segment(&self) -> &Arc<SharedMemory> {
+        &self.segment
+    }
+}
+
+impl Clone for ShmAttachment {
+    fn clone(&self) -> Self {
+        self.segment.inner.lock().nattch += 1;
+        Self {
+            segment: self.segment.clone(),
+        }
+    }
+}
+
+impl Drop for ShmAttachment {
+    fn drop(&mut self) {
+        let should_destroy = {
+            let mut inner = self.segment.inner.lock();
+            inner.nattch -= 1;
+            inner.nattch == 0 && inner.permission.mode & SHM_DEST != 0
+        };
+        if should_destroy {
+            remove_segment(&self.segment);
+        }
+    }
+}
+
+/// Returns the identifier of the segment with `key`, creating the segment if necessary.
+///
+/// A new segment is always created for `IPC_PRIVATE`. Otherwise, it is created only if no
+/// segment has `key` and `flags` contains `IPC_CREAT`. The lookup and the creation are done with
+/// the lock held, so that the callers racing on the same key get the same segment.
+pub fn get_or_create_shm(
+    key: key_t,
+    size: usize,
+    flags: IpcFlags,
+    mode: u16,
+    credentials: &Credentials<ReadOp>,
+    cpid: Pid,
+) -> Result<key_t> {
+    let can_create = key == IPC_PRIVATE || flags.contains(IpcFlags::IPC_CREAT);
+    let vmo = if can_create && (SHMMIN..=SHMMAX).contains(&size) {
+        Some(VmoOptions::<Rights>::new(size.align_up(PAGE_SIZE)).alloc()?)
+    } else {
+        None
+    };
+    let mut segments = SHM_SEGMENTS.write();
+
+    if key != IPC_PRIVATE {
+        let segment = segments.values().find(|segment| {
+            let inner = segment.inner.lock();
+            inner.permission.key() == key && inner.permission.mode & SHM_DEST == 0
+        });
+        if let Some(segment) = segment {
+            if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+                return_errno_with_message!(Errno::EEXIST, "the segment already exists");
+            }
+            if size > segment.size() {
+                return_errno_with_message!(Errno::EINVAL, "the segment is smaller than size");
+            }
+            let requested = (mode >> 6) | (mode >> 3) | mode;
+            segment.check_access(credentials, requested)?;
+            return Ok(segment.shmid());
+        }
+        if !can_create {
+            return_errno_with_message!(Errno::ENOENT, "the segment does not exist");
+        }
+    }
+
+    let Some(vmo) = vmo else {
+        return_errno_with_message!(Errno::EINVAL, "the segment size is out of range");
+    };
+
+    let num_pages = size.div_ceil(PAGE_SIZE);
+    let total_pages = segments
+        .values()
+        .map(|segment| segment.size.div_ceil(PAGE_SIZE))
+        .sum::<usize>();
+    if total_pages.saturating_add(num_pages) > SHMALL {
+        return_errno_with_message!(Errno::ENOSPC, "the total size of segments exceeds SHMALL");
+    }
+
+    let shmid = ID_ALLOCATOR
+        .get()
+        .unwrap()
+        .lock()
+        .alloc()
+        .ok_or(Error::with_message(Errno::ENOSPC, "too many segments"))? as key_t;
+
+    let segment = SharedMemory::new(shmid, key, size, vmo, mode, credentials, cpid);
+    segments.insert(shmid, Arc::new(segment));
+
+    Ok(shmid)
+}
+
+/// Returns the segment identified by `shmid`.
+pub fn get_shm(shmid: key_t) -> Result<Arc<SharedMemory>> {
+    SHM_SEGMENTS
+        .read()
+        .get(&shmid)
+        .cloned()
+        .ok_or(Error::with_message(
+            Errno::EINVAL,
+            "the segment does not exist",
+        ))
+}
+
+/// Marks the segment as removed.
+///
+/// The key of the segment is released at once, but the segment is destroyed after it is
+/// detached from all processes.
+pub fn remove_shm(segment: &Arc<SharedMemory>) {
+    let should_destroy = {
+        let mut inner = segment.inner.lock();
+        inner.permission.key = IPC_PRIVATE;
+        inner.permission.mode |= SHM_DEST;
+        inner.nattch == 0
+    };
+    if should_destroy {
+        remove_segment(segment);
+    }
+}
+
+fn remove_segment(segment: &Arc<SharedMemory>) {
+    let mut segments = SHM_SEGMENTS.write();
+    let removed = if segments
+        .get(&segment.shmid)
+        .is_some_and(|found| Arc::ptr_eq(found, segment))
+    {
+        segments.remove(&segment.shmid)
+    } else {
+        None
+    };
+    drop(segments);
+
+    // The segment may be freed here, which should be done without holding the lock.
+    drop(removed);
+}
+
+/// Returns the identifiers of all segments.
+pub fn shm_ids() -> Vec<key_t> {
+    SHM_SEGMENTS.read().keys().copied().collect()
+}
+
+fn now_secs() -> u64 {
+    RealTimeCoarseClock::get().read_time().as_secs()
+}
+
+static ID_ALLOCATOR: Once<SpinLock<IdAlloc>> = Once::new();
+
+/// Shared memory segments in system
+static SHM_SEGMENTS: RwLock<BTreeMap<key_t, Arc<SharedMemory>>> = RwLock::new(BTreeMap::new());
+
+pub(super) fn init() {
+    ID_ALLOCATOR.call_once(|| {
+        let mut id_alloc = IdAlloc::with_capacity(SHMMNI + 1);
+        // Remove the first index 0
+        id_alloc.alloc();
+
+        SpinLock::new(id_alloc)
+    });
+}
@@ -92,13 +92,12 @@ pub fn init() {
     util::random::init();
     driver::init();
     time::init();
-    #[cfg(target_arch = "x86_64")]
     net::init();
     sched::init();
-    fs::rootfs::init(boot_info().embeded_data).unwrap();
+    let initramfs_buf = boot_info().initramfs.expect("No initramfs found!");
+    fs::rootfs::init(initramfs_buf).unwrap();
     device::init().unwrap();
     syscall::init();
-    #[cfg(target_arch = "x86_64")]
     vdso::init();
     process::init();
 }
@@ -129,7 +128,6 @@ fn init_thread() {
     // Work queue should be initialized before interrupt is enabled,
     // in case any irq handler uses work queue as bottom half
     thread::work_queue::init();
-    #[cfg(target_arch = "x86_64")]
     net::lazy_init();
     fs::lazy_init();
     ipc::init();
@@ -10,4 +10,6 @@ impl aster_bigtcp::ext::Ext for BigtcpExt {
 
     type TcpEventObserver = StreamObserver;
     type UdpEventObserver = DatagramObserver;
+    type RawEventObserver = DatagramObserver;
+    type LinkEventObserver = DatagramObserver;
 }
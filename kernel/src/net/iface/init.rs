@@ -1,74 +1,100 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{borrow::ToOwned, sync::Arc};
-use core::slice::Iter;
+use alloc::{borrow::ToOwned, format, sync::Arc, vec};
 
 use aster_bigtcp::{
     device::WithDevice,
     iface::{InterfaceFlags, InterfaceType},
 };
+use aster_network::AnyNetworkDevice;
 use aster_softirq::BottomHalfDisabled;
 use spin::Once;
 
-use super::{poll::poll_ifaces, Iface};
+use super::{
+    poll::{poll_ifaces, spawn_background_poll_thread},
+    Iface,
+};
 use crate::{net::iface::sched::PollScheduler, prelude::*};
 
-static IFACES: Once<Vec<Arc<Iface>>> = Once::new();
+static LOOPBACK_IFACE: Once<Arc<Iface>> = Once::new();
+
+/// All registered ifaces, in the order of registration.
+static IFACES: RwLock<Vec<Arc<Iface>>> = RwLock::new(Vec::new());
 
 pub fn loopback_iface() -> &'static Arc<Iface> {
-    &IFACES.get().unwrap()[0]
+    LOOPBACK_IFACE.get().unwrap()
 }
 
-pub fn virtio_iface() -> Option<&'static Arc<Iface>> {
-    IFACES.get().unwrap().get(1)
+/// Iterates over all registered ifaces.
+///
+/// Since ifaces can be registered or unregistered at any time, the iterator works on a snapshot
+/// of the registered ifaces.
+pub fn iter_all_ifaces() -> vec::IntoIter<Arc<Iface>> {
+    IFACES.read().clone().into_iter()
 }
 
-pub fn iter_all_ifaces() -> Iter<'static, Arc<Iface>> {
-    IFACES.get().unwrap().iter()
+/// Registers an iface that is created at runtime (e.g., by a TUN/TAP device).
+///
+/// The iface will be polled by a new background thread until it is unregistered.
+pub fn register_iface(iface: Arc<Iface>) {
+    IFACES.write().push(iface.clone());
+
+    spawn_background_poll_thread(iface);
+}
+
+/// Unregisters an iface that is registered via [`register_iface`].
+///
+/// Sockets that are bound to the iface may keep the iface alive, but the iface will no longer be
+/// found or polled in the background.
+pub fn unregister_iface(iface: &Arc<Iface>) {
+    IFACES.write().retain(|other| !Arc::ptr_eq(other, iface));
+
+    iface.sched_poll().stop();
 }
 
 pub fn init() {
-    IFACES.call_once(|| {
-        let mut ifaces = Vec::with_capacity(2);
+    let devices = aster_network::all_devices();
+    let mut ifaces = Vec::with_capacity(devices.len() + 1);
 
-        // Initialize loopback before virtio
-        // to ensure the loopback interface index is ahead of virtio.
-        ifaces.push(new_loopback());
+    // Initialize loopback before network devices
+    // to ensure the loopback interface index is ahead of others.
+    ifaces.push(LOOPBACK_IFACE.call_once(new_loopback).clone());
 
-        if let Some(iface_virtio) = new_virtio() {
-            ifaces.push(iface_virtio);
-        }
+    // The network devices are named `ethN` in the order of registration, which is stable
+    // across boots as long as the devices are probed in the same order.
+    for (i, (name, device)) in devices.into_iter().enumerate() {
+        let iface = new_ether(device, i);
 
-        ifaces
-    });
+        // Only the iface of the device is polled when the device has events.
+        let iface_cloned = iface.clone();
+        aster_network::register_recv_callback(&name, move || iface_cloned.poll());
+        let iface_cloned = iface.clone();
+        aster_network::register_send_callback(&name, move || iface_cloned.poll());
 
-    if let Some(iface_virtio) = virtio_iface() {
-        for (name, _) in aster_network::all_devices() {
-            // TODO: further check that the irq num is the same as iface's irq num
-            let callback = || iface_virtio.poll();
-            aster_network::register_recv_callback(&name, callback);
-            aster_network::register_send_callback(&name, callback);
-        }
+        ifaces.push(iface);
     }
 
+    *IFACES.write() = ifaces;
+
     poll_ifaces();
 }
 
-fn new_virtio() -> Option<Arc<Iface>> {
+fn new_ether(
+    device: Arc<SpinLock<dyn AnyNetworkDevice, BottomHalfDisabled>>,
+    nth: usize,
+) -> Arc<Iface> {
     use aster_bigtcp::{
         iface::EtherIface,
         wire::{EthernetAddress, Ipv4Address, Ipv4Cidr},
     };
-    use aster_network::AnyNetworkDevice;
-    use aster_virtio::device::network::DEVICE_NAME;
-
-    const VIRTIO_ADDRESS: Ipv4Address = Ipv4Address::new(10, 0, 2, 15);
-    const VIRTIO_ADDRESS_PREFIX_LEN: u8 = 24; // mask: 255.255.255.0
-    const VIRTIO_GATEWAY: Ipv4Address = Ipv4Address::new(10, 0, 2, 2);
 
-    let virtio_net = aster_network::get_device(DEVICE_NAME)?;
+    // The first iface is configured for the user-mode network of QEMU. Other ifaces are left
+    // unconfigured, and can be configured by user programs (e.g., `ip` and DHCP clients).
+    const ETH0_ADDRESS: Ipv4Address = Ipv4Address::new(10, 0, 2, 15);
+    const ETH0_ADDRESS_PREFIX_LEN: u8 = 24; // mask: 255.255.255.0
+    const ETH0_GATEWAY: Ipv4Address = Ipv4Address::new(10, 0, 2, 2);
 
-    let ether_addr = virtio_net.lock().mac_addr().0;
+    let ether_addr = device.lock().mac_addr().0;
 
     struct Wrapper(Arc<SpinLock<dyn AnyNetworkDevice, BottomHalfDisabled>>);
 
@@ -92,26 +118,38 @@ fn new_virtio() -> Option<Arc<Iface>> {
         | InterfaceFlags::MULTICAST
         | InterfaceFlags::LOWER_UP;
 
-    Some(EtherIface::new(
-        Wrapper(virtio_net),
+    let (ip_cidr, gateway) = if nth == 0 {
+        (
+            Some(Ipv4Cidr::new(ETH0_ADDRESS, ETH0_ADDRESS_PREFIX_LEN)),
+            Some(ETH0_GATEWAY),
+        )
+    } else {
+        (None, None)
+    };
+
+    // The link-local IPv6 address is configured automatically by `EtherIface::new`.
+    EtherIface::new(
+        Wrapper(device),
         EthernetAddress(ether_addr),
-        Ipv4Cidr::new(VIRTIO_ADDRESS, VIRTIO_ADDRESS_PREFIX_LEN),
-        VIRTIO_GATEWAY,
-        "eth0".to_owned(),
+        ip_cidr,
+        gateway,
+        format!("eth{}", nth),
         PollScheduler::new(),
         flags,
-    ))
+    ) as Arc<Iface>
 }
 
 fn new_loopback() -> Arc<Iface> {
     use aster_bigtcp::{
         device::{Loopback, Medium},
         iface::IpIface,
-        wire::{Ipv4Address, Ipv4Cidr},
+        wire::{Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr},
     };
 
     const LOOPBACK_ADDRESS: Ipv4Address = Ipv4Address::new(127, 0, 0, 1);
     const LOOPBACK_ADDRESS_PREFIX_LEN: u8 = 8; // mask: 255.0.0.0
+    const LOOPBACK_IPV6_ADDRESS: Ipv6Address = Ipv6Address::LOCALHOST;
+    const LOOPBACK_IPV6_ADDRESS_PREFIX_LEN: u8 = 128;
 
     struct Wrapper(Mutex<Loopback>);
 
@@ -136,7 +174,11 @@ fn new_loopback() -> Arc<Iface> {
 
     IpIface::new(
         Wrapper(Mutex::new(Loopback::new(Medium::Ip))),
-        Ipv4Cidr::new(LOOPBACK_ADDRESS, LOOPBACK_ADDRESS_PREFIX_LEN),
+        Some(Ipv4Cidr::new(LOOPBACK_ADDRESS, LOOPBACK_ADDRESS_PREFIX_LEN)),
+        Some(Ipv6Cidr::new(
+            LOOPBACK_IPV6_ADDRESS,
+            LOOPBACK_IPV6_ADDRESS_PREFIX_LEN,
+        )),
         "lo".to_owned(),
         PollScheduler::new(),
         InterfaceType::LOOPBACK,
@@ -15,7 +15,7 @@ use crate::{
 
 pub fn lazy_init() {
     for iface in iter_all_ifaces() {
-        spawn_background_poll_thread(iface.clone());
+        spawn_background_poll_thread(iface);
     }
 }
 
@@ -25,18 +25,27 @@ pub(super) fn poll_ifaces() {
     }
 }
 
-fn spawn_background_poll_thread(iface: Arc<Iface>) {
+pub(super) fn spawn_background_poll_thread(iface: Arc<Iface>) {
     let task_fn = move || {
         trace!("spawn background poll thread for {}", iface.name());
 
         let sched_poll = iface.sched_poll();
         let wait_queue = sched_poll.polling_wait_queue();
 
-        loop {
+        while !sched_poll.is_stopped() {
             let next_poll_at_ms = if let Some(next_poll_at_ms) = sched_poll.next_poll_at_ms() {
                 next_poll_at_ms
             } else {
-                wait_queue.wait_until(|| sched_poll.next_poll_at_ms())
+                let next_poll_at_ms = wait_queue.wait_until(|| {
+                    if sched_poll.is_stopped() {
+                        return Some(None);
+                    }
+                    sched_poll.next_poll_at_ms().map(Some)
+                });
+                let Some(next_poll_at_ms) = next_poll_at_ms else {
+                    break;
+                };
+                next_poll_at_ms
             };
 
             let now_as_ms = Jiffies::elapsed().as_duration().as_millis() as u64;
@@ -56,12 +65,20 @@ fn spawn_background_poll_thread(iface: Arc<Iface>) {
 
             let duration = Duration::from_millis(next_poll_at_ms - now_as_ms);
             let _ = wait_queue.wait_until_or_timeout(
-                // If `sched_poll.next_poll_at_ms()` changes to an earlier time, we will end the
-                // waiting.
-                || (sched_poll.next_poll_at_ms()? < next_poll_at_ms).then_some(()),
+                // If `sched_poll.next_poll_at_ms()` changes to an earlier time, or if the thread
+                // is stopped, we will end the waiting.
+                || {
+                    (sched_poll.is_stopped()
+                        || sched_poll
+                            .next_poll_at_ms()
+                            .is_some_and(|poll_at_ms| poll_at_ms < next_poll_at_ms))
+                    .then_some(())
+                },
                 &duration,
             );
         }
+
+        trace!("exit background poll thread for {}", iface.name());
     };
 
     ThreadOptions::new(task_fn)
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use aster_bigtcp::iface::ScheduleNextPoll;
 use ostd::sync::WaitQueue;
@@ -11,13 +11,16 @@ pub struct PollScheduler {
     next_poll_at_ms: AtomicU64,
     /// The wait queue that the background polling thread will sleep on.
     polling_wait_queue: WaitQueue,
+    /// Whether the background polling thread should exit.
+    is_stopped: AtomicBool,
 }
 
 impl PollScheduler {
-    pub(super) fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             next_poll_at_ms: AtomicU64::new(0),
             polling_wait_queue: WaitQueue::new(),
+            is_stopped: AtomicBool::new(false),
         }
     }
 
@@ -33,6 +36,18 @@ impl PollScheduler {
     pub(super) fn polling_wait_queue(&self) -> &WaitQueue {
         &self.polling_wait_queue
     }
+
+    /// Stops the background polling thread.
+    ///
+    /// This should be called when the iface is unregistered.
+    pub(super) fn stop(&self) {
+        self.is_stopped.store(true, Ordering::Relaxed);
+        self.polling_wait_queue.wake_all();
+    }
+
+    pub(super) fn is_stopped(&self) -> bool {
+        self.is_stopped.load(Ordering::Relaxed)
+    }
 }
 
 impl ScheduleNextPoll for PollScheduler {
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::wire::{IpAddress, IpEndpoint, Ipv4Address};
+use aster_bigtcp::wire::{IpAddress, IpEndpoint, IpVersion, Ipv4Address, Ipv6Address};
 
 use crate::{net::socket::util::SocketAddr, prelude::*, return_errno_with_message};
 
@@ -10,6 +10,7 @@ impl TryFrom<SocketAddr> for IpEndpoint {
     fn try_from(value: SocketAddr) -> Result<Self> {
         match value {
             SocketAddr::IPv4(addr, port) => Ok(IpEndpoint::new(addr.into(), port)),
+            SocketAddr::IPv6(addr, port) => Ok(IpEndpoint::new(addr.into(), port)),
             _ => return_errno_with_message!(
                 Errno::EAFNOSUPPORT,
                 "the address is in an unsupported address family"
@@ -23,15 +24,101 @@ impl From<IpEndpoint> for SocketAddr {
         let port = endpoint.port;
         match endpoint.addr {
             IpAddress::Ipv4(addr) => SocketAddr::IPv4(addr, port),
-            // TODO: support IPv6
+            IpAddress::Ipv6(addr) => SocketAddr::IPv6(addr, port),
         }
     }
 }
 
+/// Converts a socket address to a local endpoint to bind for a socket of the IP version.
+///
+/// See [`to_remote_endpoint`] for how IPv4-mapped IPv6 addresses are handled. The only difference
+/// is that binding an IPv6-only socket to an IPv4-mapped IPv6 address fails with `EINVAL`.
+pub(super) fn to_local_endpoint(
+    socket_addr: SocketAddr,
+    ip_version: IpVersion,
+    is_v6only: bool,
+) -> Result<IpEndpoint> {
+    let (endpoint, is_ipv4_mapped) = to_endpoint(socket_addr, ip_version)?;
+
+    if is_ipv4_mapped && is_v6only {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "IPv6-only sockets cannot be bound to IPv4-mapped addresses"
+        );
+    }
+
+    Ok(endpoint)
+}
+
+/// Returns whether a TCP or UDP socket bound to the local endpoint is a dual-stack socket.
+///
+/// A dual-stack socket is an IPv6 socket that is bound to the unspecified IPv6 address while
+/// `IPV6_V6ONLY` is not set. Like Linux, such a socket communicates with both IPv6 peers and IPv4
+/// peers (via IPv4-mapped IPv6 addresses).
+pub(super) fn is_dual_stack_endpoint(endpoint: &IpEndpoint, is_v6only: bool) -> bool {
+    !is_v6only && endpoint.addr == IpAddress::Ipv6(Ipv6Address::UNSPECIFIED)
+}
+
+/// Converts a socket address to a remote endpoint for a socket of the IP version.
+///
+/// For IPv6 sockets, IPv4-mapped IPv6 addresses (i.e., `::ffff:a.b.c.d`) are converted to IPv4
+/// endpoints, unless the socket is IPv6-only (i.e., `IPV6_V6ONLY` is set). This allows IPv6
+/// sockets to communicate with IPv4 peers.
+pub(super) fn to_remote_endpoint(
+    socket_addr: SocketAddr,
+    ip_version: IpVersion,
+    is_v6only: bool,
+) -> Result<IpEndpoint> {
+    let (endpoint, is_ipv4_mapped) = to_endpoint(socket_addr, ip_version)?;
+
+    if is_ipv4_mapped && is_v6only {
+        return_errno_with_message!(
+            Errno::ENETUNREACH,
+            "IPv6-only sockets cannot reach IPv4-mapped addresses"
+        );
+    }
+
+    Ok(endpoint)
+}
+
+fn to_endpoint(socket_addr: SocketAddr, ip_version: IpVersion) -> Result<(IpEndpoint, bool)> {
+    match (socket_addr, ip_version) {
+        (SocketAddr::IPv4(addr, port), IpVersion::Ipv4) => {
+            Ok((IpEndpoint::new(addr.into(), port), false))
+        }
+        (SocketAddr::IPv6(addr, port), IpVersion::Ipv6) => match addr.to_ipv4_mapped() {
+            Some(ipv4_addr) => Ok((IpEndpoint::new(ipv4_addr.into(), port), true)),
+            None => Ok((IpEndpoint::new(addr.into(), port), false)),
+        },
+        _ => return_errno_with_message!(
+            Errno::EAFNOSUPPORT,
+            "the address is in an unsupported address family"
+        ),
+    }
+}
+
+/// Converts an endpoint to a socket address for a socket of the IP version.
+///
+/// This is the reverse of [`to_local_endpoint`] and [`to_remote_endpoint`]. For IPv6 sockets,
+/// IPv4 endpoints are converted back to IPv4-mapped IPv6 addresses, except that the unspecified
+/// IPv4 address is reported as the unspecified IPv6 address.
+pub(super) fn to_socket_addr(endpoint: IpEndpoint, ip_version: IpVersion) -> SocketAddr {
+    let port = endpoint.port;
+    match (endpoint.addr, ip_version) {
+        (IpAddress::Ipv4(addr), IpVersion::Ipv6) if addr.is_unspecified() => {
+            SocketAddr::IPv6(Ipv6Address::UNSPECIFIED, port)
+        }
+        (IpAddress::Ipv4(addr), IpVersion::Ipv6) => SocketAddr::IPv6(addr.to_ipv6_mapped(), port),
+        _ => endpoint.into(),
+    }
+}
+
 /// A local endpoint, which indicates that the local endpoint is unspecified.
 ///
 /// According to the Linux man pages and the Linux implementation, `getsockname()` will _not_ fail
 /// even if the socket is unbound. Instead, it will return an unspecified socket address. This
 /// unspecified endpoint helps with that.
+///
+/// For IPv6 sockets, [`to_socket_addr`] reports this endpoint as the unspecified IPv6 address.
 pub(super) const UNSPECIFIED_LOCAL_ENDPOINT: IpEndpoint =
     IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0);
@@ -3,49 +3,105 @@
 use aster_bigtcp::{
     errors::BindError,
     iface::BindPortConfig,
-    wire::{IpAddress, IpEndpoint},
+    wire::{IpAddress, IpEndpoint, IpVersion, Ipv4Address, Ipv6Address},
 };
 
+use super::options::IpMulticastIf;
 use crate::{
-    net::iface::{iter_all_ifaces, loopback_iface, virtio_iface, BoundPort, Iface},
+    net::iface::{iter_all_ifaces, loopback_iface, BoundPort, Iface},
     prelude::*,
 };
 
 pub(super) fn get_iface_to_bind(ip_addr: &IpAddress) -> Option<Arc<Iface>> {
-    let IpAddress::Ipv4(ipv4_addr) = ip_addr;
+    if ip_addr.is_unspecified() {
+        return get_iface_to_bind_unspecified(ip_addr.version());
+    }
+
+    iter_all_ifaces().find(|iface| iface_has_addr(iface, ip_addr))
+}
+
+/// Gets an iface for a socket that is bound to the unspecified address of the IP version.
+///
+/// The first non-loopback iface that has an address of the IP version is preferred. Otherwise,
+/// the loopback iface is used if it has such an address.
+//
+// FIXME: Linux accepts packets from all the ifaces if the socket is bound to the unspecified
+// address. However, a bound port belongs to a single iface, so we have to pick one here.
+fn get_iface_to_bind_unspecified(ip_version: IpVersion) -> Option<Arc<Iface>> {
+    let has_addr_of_version = |iface: &Arc<Iface>| match ip_version {
+        IpVersion::Ipv4 => iface.ipv4_addr().is_some(),
+        IpVersion::Ipv6 => iface.ipv6_addr().is_some(),
+    };
+
     iter_all_ifaces()
-        .find(|iface| {
-            if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-                iface_ipv4_addr == *ipv4_addr
-            } else {
-                false
-            }
-        })
-        .map(Clone::clone)
+        .filter(has_addr_of_version)
+        .find(|iface| !Arc::ptr_eq(iface, loopback_iface()))
+        .or_else(|| Some(loopback_iface().clone()).filter(has_addr_of_version))
+}
+
+fn iface_has_addr(iface: &Iface, ip_addr: &IpAddress) -> bool {
+    iface
+        .ip_addrs()
+        .iter()
+        .any(|ip_cidr| ip_cidr.address() == *ip_addr)
 }
 
 /// Get a suitable iface to deal with sendto/connect request if the socket is not bound to an iface.
 /// If the remote address is the same as that of some iface, we will use the iface.
-/// Otherwise, we will use a default interface.
+/// Otherwise, we will use the iface that can reach the remote address, or a default interface.
 fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
-    let IpAddress::Ipv4(remote_ipv4_addr) = remote_ip_addr;
+    if let Some(iface) = iter_all_ifaces().find(|iface| iface_has_addr(iface, remote_ip_addr)) {
+        return iface;
+    }
+
+    // Prefer the iface whose subnet contains the remote address.
     if let Some(iface) = iter_all_ifaces().find(|iface| {
-        if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-            iface_ipv4_addr == *remote_ipv4_addr
-        } else {
-            false
-        }
+        iface
+            .ip_addrs()
+            .iter()
+            .any(|ip_cidr| ip_cidr.contains_addr(remote_ip_addr))
     }) {
-        return iface.clone();
+        return iface;
+    }
+
+    // Then, prefer the iface with the most specific route to the remote address.
+    if let Some((iface, _)) = iter_all_ifaces()
+        .flat_map(|iface| {
+            iface
+                .routes()
+                .into_iter()
+                .map(move |route| (iface.clone(), route))
+        })
+        .filter(|(_, route)| route.dst.contains_addr(remote_ip_addr))
+        .max_by_key(|(_, route)| route.dst.prefix_len())
+    {
+        return iface;
+    }
+
+    // FIXME: Linux reports `ENETUNREACH` if there are no routes. We use the first non-loopback
+    // iface instead, since user programs may expect the network to be available.
+    iter_all_ifaces()
+        .find(|iface| !Arc::ptr_eq(iface, loopback_iface()))
+        .unwrap_or_else(|| loopback_iface().clone())
+}
+
+/// Finds the iface for multicast packets.
+///
+/// The iface is specified by `interface`. If neither the index nor the address is specified, the
+/// iface is selected as if sending packets to `group`, if any.
+pub(super) fn find_multicast_iface(
+    group: Option<Ipv4Address>,
+    interface: &IpMulticastIf,
+) -> Option<Arc<Iface>> {
+    if interface.ifindex != 0 {
+        return iter_all_ifaces().find(|iface| iface.index() == interface.ifindex);
     }
 
-    // FIXME: Instead of hardcoding the rules here, we should choose the
-    // default interface according to the routing table.
-    if let Some(virtio_iface) = virtio_iface() {
-        virtio_iface.clone()
-    } else {
-        loopback_iface().clone()
+    if !interface.address.is_unspecified() {
+        return get_iface_to_bind(&IpAddress::Ipv4(interface.address));
     }
+
+    group.map(|group| get_ephemeral_iface(&IpAddress::Ipv4(group)))
 }
 
 pub(super) fn bind_port(endpoint: &IpEndpoint, can_reuse: bool) -> Result<BoundPort> {
@@ -61,7 +117,36 @@ pub(super) fn bind_port(endpoint: &IpEndpoint, can_reuse: bool) -> Result<BoundP
 
     let bind_port_config = BindPortConfig::new(endpoint.port, can_reuse);
 
-    Ok(iface.bind(bind_port_config)?)
+    Ok(iface.bind(endpoint.addr.version(), bind_port_config)?)
+}
+
+/// Binds the ports for a dual-stack socket.
+///
+/// The IPv6 port and the IPv4 port share the same port number. The latter is `None` if no iface
+/// has an IPv4 address. See [`is_dual_stack_endpoint`] for what a dual-stack socket is.
+///
+/// [`is_dual_stack_endpoint`]: super::addr::is_dual_stack_endpoint
+//
+// FIXME: If an ephemeral port is requested and the IPv4 port is bound to another iface, the
+// ephemeral port picked for the IPv6 iface may be in use on the IPv4 iface. In that case, Linux
+// will try another ephemeral port, but we fail with `EADDRINUSE`.
+pub(super) fn bind_dual_stack_port(
+    port: u16,
+    can_reuse: bool,
+) -> Result<(BoundPort, Option<BoundPort>)> {
+    let ipv6_endpoint = IpEndpoint::new(IpAddress::Ipv6(Ipv6Address::UNSPECIFIED), port);
+    let ipv6_port = bind_port(&ipv6_endpoint, can_reuse)?;
+
+    let Some(iface) = get_iface_to_bind_unspecified(IpVersion::Ipv4) else {
+        return Ok((ipv6_port, None));
+    };
+
+    // If both IP versions are bound to the same iface, the port is already owned by the socket.
+    let can_reuse = can_reuse || Arc::ptr_eq(&iface, ipv6_port.iface());
+    let bind_port_config = BindPortConfig::new(ipv6_port.port(), can_reuse);
+    let ipv4_port = iface.bind(IpVersion::Ipv4, bind_port_config)?;
+
+    Ok((ipv6_port, Some(ipv4_port)))
 }
 
 impl From<BindError> for Error {
@@ -77,8 +162,17 @@ impl From<BindError> for Error {
     }
 }
 
-pub(super) fn get_ephemeral_endpoint(remote_endpoint: &IpEndpoint) -> IpEndpoint {
+pub(super) fn get_ephemeral_endpoint(remote_endpoint: &IpEndpoint) -> Result<IpEndpoint> {
     let iface = get_ephemeral_iface(&remote_endpoint.addr);
-    let ip_addr = iface.ipv4_addr().unwrap();
-    IpEndpoint::new(IpAddress::Ipv4(ip_addr), 0)
+    let ip_addr = match remote_endpoint.addr {
+        IpAddress::Ipv4(_) => iface.ipv4_addr().map(IpAddress::Ipv4),
+        IpAddress::Ipv6(_) => iface.ipv6_addr().map(IpAddress::Ipv6),
+    };
+    let Some(ip_addr) = ip_addr else {
+        return_errno_with_message!(
+            Errno::ENETUNREACH,
+            "the interface has no address in the same IP version"
+        );
+    };
+    Ok(IpEndpoint::new(ip_addr, 0))
 }
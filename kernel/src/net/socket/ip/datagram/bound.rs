@@ -2,7 +2,8 @@
 
 use aster_bigtcp::{
     errors::udp::{RecvError, SendError},
-    wire::IpEndpoint,
+    socket::IcmpError,
+    wire::{IpEndpoint, Ipv6Address},
 };
 
 use crate::{
@@ -17,6 +18,11 @@ use crate::{
 
 pub(super) struct BoundDatagram {
     bound_socket: UdpSocket,
+    /// The IPv4 socket of a dual-stack socket, if any.
+    ///
+    /// It shares the same port with `bound_socket` and serves the IPv4 peers.
+    ipv4_bound_socket: Option<UdpSocket>,
+    is_dual_stack: bool,
     remote_endpoint: Option<IpEndpoint>,
 }
 
@@ -24,6 +30,20 @@ impl BoundDatagram {
     pub(super) fn new(bound_socket: UdpSocket) -> Self {
         Self {
             bound_socket,
+            ipv4_bound_socket: None,
+            is_dual_stack: false,
+            remote_endpoint: None,
+        }
+    }
+
+    pub(super) fn new_dual_stack(
+        bound_socket: UdpSocket,
+        ipv4_bound_socket: Option<UdpSocket>,
+    ) -> Self {
+        Self {
+            bound_socket,
+            ipv4_bound_socket,
+            is_dual_stack: true,
             remote_endpoint: None,
         }
     }
@@ -31,13 +51,47 @@ impl BoundDatagram {
     pub(super) fn iface(&self) -> &Arc<Iface> {
         self.bound_socket.iface()
     }
+
+    /// Returns the iface that sends packets to the remote endpoint.
+    pub(super) fn iface_to(&self, remote: &IpEndpoint) -> &Arc<Iface> {
+        self.socket_to(remote).unwrap_or(&self.bound_socket).iface()
+    }
+
+    pub(super) fn pop_icmp_error(&self) -> Option<IcmpError> {
+        self.bound_socket
+            .pop_icmp_error()
+            .or_else(|| self.ipv4_bound_socket.as_ref()?.pop_icmp_error())
+    }
+
+    pub(super) fn set_multicast_options(&self, hop_limit: u8, multicast_loop: bool) {
+        for socket in self.sockets() {
+            socket.set_multicast_hop_limit(hop_limit);
+            socket.set_multicast_loop(multicast_loop);
+        }
+    }
+
+    fn sockets(&self) -> impl Iterator<Item = &UdpSocket> {
+        core::iter::once(&self.bound_socket).chain(self.ipv4_bound_socket.as_ref())
+    }
+
+    /// Returns the socket that can send packets to the remote endpoint, if any.
+    fn socket_to(&self, remote: &IpEndpoint) -> Option<&UdpSocket> {
+        self.sockets()
+            .find(|socket| socket.local_endpoint().unwrap().addr.version() == remote.addr.version())
+    }
 }
 
 impl datagram_common::Bound for BoundDatagram {
     type Endpoint = IpEndpoint;
 
     fn local_endpoint(&self) -> Self::Endpoint {
-        self.bound_socket.local_endpoint().unwrap()
+        let local_endpoint = self.bound_socket.local_endpoint().unwrap();
+
+        if self.is_dual_stack {
+            IpEndpoint::new(Ipv6Address::UNSPECIFIED.into(), local_endpoint.port)
+        } else {
+            local_endpoint
+        }
     }
 
     fn remote_endpoint(&self) -> Option<&Self::Endpoint> {
@@ -53,11 +107,20 @@ impl datagram_common::Bound for BoundDatagram {
         writer: &mut dyn MultiWrite,
         _flags: SendRecvFlags,
     ) -> Result<(usize, Self::Endpoint)> {
-        let result = self.bound_socket.recv(|packet, udp_metadata| {
-            let copied_res = writer.write(&mut VmReader::from(packet));
-            let endpoint = udp_metadata.endpoint;
-            (copied_res, endpoint)
-        });
+        let mut recv = |socket: &UdpSocket| {
+            socket.recv(|packet, udp_metadata| {
+                let copied_res = writer.write(&mut VmReader::from(packet));
+                let endpoint = udp_metadata.endpoint;
+                (copied_res, endpoint)
+            })
+        };
+
+        let mut result = recv(&self.bound_socket);
+        if matches!(result, Err(RecvError::Exhausted)) {
+            if let Some(ipv4_bound_socket) = self.ipv4_bound_socket.as_ref() {
+                result = recv(ipv4_bound_socket);
+            }
+        }
 
         match result {
             Ok((Ok(res), endpoint)) => Ok((res, endpoint)),
@@ -77,17 +140,22 @@ impl datagram_common::Bound for BoundDatagram {
         remote: &Self::Endpoint,
         _flags: SendRecvFlags,
     ) -> Result<usize> {
-        let result = self
-            .bound_socket
-            .send(reader.sum_lens(), *remote, |socket_buffer| {
-                // FIXME: If copy failed, we should not send any packet.
-                // But current smoltcp API seems not to support this behavior.
-                reader
-                    .read(&mut VmWriter::from(socket_buffer))
-                    .inspect_err(|e| {
-                        warn!("unexpected UDP packet {e:#?} will be sent");
-                    })
-            });
+        let Some(socket) = self.socket_to(remote) else {
+            return_errno_with_message!(
+                Errno::ENETUNREACH,
+                "the destination address is in a different IP version"
+            );
+        };
+
+        let result = socket.send(reader.sum_lens(), *remote, |socket_buffer| {
+            // FIXME: If copy failed, we should not send any packet.
+            // But current smoltcp API seems not to support this behavior.
+            reader
+                .read(&mut VmWriter::from(socket_buffer))
+                .inspect_err(|e| {
+                    warn!("unexpected UDP packet {e:#?} will be sent");
+                })
+        });
 
         match result {
             Ok(inner) => inner,
@@ -104,18 +172,20 @@ impl datagram_common::Bound for BoundDatagram {
     }
 
     fn check_io_events(&self) -> IoEvents {
-        self.bound_socket.raw_with(|socket| {
-            let mut events = IoEvents::empty();
+        let mut events = IoEvents::empty();
 
-            if socket.can_recv() {
-                events |= IoEvents::IN;
-            }
+        for socket in self.sockets() {
+            socket.raw_with(|socket| {
+                if socket.can_recv() {
+                    events |= IoEvents::IN;
+                }
 
-            if socket.can_send() {
-                events |= IoEvents::OUT;
-            }
+                if socket.can_send() {
+                    events |= IoEvents::OUT;
+                }
+            });
+        }
 
-            events
-        })
+        events
     }
 }
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::{
+    socket::IcmpError,
+    wire::{IpAddress, IpEndpoint},
+};
+
+use crate::{
+    net::socket::util::{ErrorOrigin, ExtendedError},
+    prelude::*,
+};
+
+/// Errors reported to a UDP socket.
+pub(super) struct SocketErrors {
+    /// The pending error (`sk_err` in Linux).
+    ///
+    /// The pending error will be reported and cleared by the next `send`, `recv`, or `SO_ERROR`.
+    pending: Option<Errno>,
+    /// The error queue (`sk_error_queue` in Linux).
+    ///
+    /// Errors are queued only if `IP_RECVERR` (or `IPV6_RECVERR`) is enabled. They can be
+    /// received with `MSG_ERRQUEUE`.
+    queue: VecDeque<QueuedError>,
+}
+
+/// An error in the error queue.
+pub(super) struct QueuedError {
+    /// The extended error that will be reported in the control message.
+    pub(super) extended_error: ExtendedError,
+    /// The destination of the offending datagram.
+    pub(super) remote_endpoint: IpEndpoint,
+    /// The payload of the offending datagram, which may be truncated.
+    pub(super) payload: Vec<u8>,
+}
+
+/// The maximum number of errors in the error queue.
+///
+/// New errors will be dropped if the queue is full.
+const ERROR_QUEUE_LEN: usize = 64;
+
+impl SocketErrors {
+    pub(super) const fn new() -> Self {
+        Self {
+            pending: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Records an ICMP error caused by a datagram sent from the socket.
+    pub(super) fn add_icmp_error(
+        &mut self,
+        icmp_error: IcmpError,
+        is_recverr: bool,
+        is_connected: bool,
+    ) {
+        let Some((errno, is_fatal)) = icmp_error_to_errno(&icmp_error) else {
+            return;
+        };
+
+        // Following Linux, if `IP_RECVERR` is disabled, only fatal errors on connected sockets
+        // are reported. See
+        // <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/udp.c#L759>.
+        if !is_recverr {
+            if is_fatal && is_connected {
+                self.pending = Some(errno);
+            }
+            return;
+        }
+
+        if self.queue.len() < ERROR_QUEUE_LEN {
+            let origin = match icmp_error.offender {
+                IpAddress::Ipv4(_) => ErrorOrigin::Icmp,
+                IpAddress::Ipv6(_) => ErrorOrigin::Icmp6,
+            };
+            self.queue.push_back(QueuedError {
+                extended_error: ExtendedError {
+                    errno,
+                    origin,
+                    type_: icmp_error.icmp_type,
+                    code: icmp_error.icmp_code,
+                    info: icmp_error.info,
+                    offender: icmp_error.offender,
+                },
+                remote_endpoint: icmp_error.remote_endpoint,
+                payload: icmp_error.payload,
+            });
+        }
+        self.pending = Some(errno);
+    }
+
+    /// Takes the pending error.
+    pub(super) fn take_pending(&mut self) -> Option<Error> {
+        self.pending.take().map(Error::new)
+    }
+
+    /// Takes the oldest error in the error queue.
+    pub(super) fn pop_queued(&mut self) -> Option<QueuedError> {
+        let error = self.queue.pop_front()?;
+
+        // Like Linux, the pending error is regenerated from the next error in the queue.
+        self.pending = self.queue.front().map(|error| error.extended_error.errno);
+
+        Some(error)
+    }
+
+    /// Discards all errors in the error queue.
+    pub(super) fn purge_queue(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Returns whether there are errors to report.
+    pub(super) fn has_error(&self) -> bool {
+        self.pending.is_some() || !self.queue.is_empty()
+    }
+}
+
+/// Converts an ICMP error to an error number, along with whether the error is fatal.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/icmp.c#L118> and
+/// <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv6/icmp.c#L1122>.
+fn icmp_error_to_errno(icmp_error: &IcmpError) -> Option<(Errno, bool)> {
+    const ICMP_DEST_UNREACH: u8 = 3;
+    const ICMP_TIME_EXCEEDED: u8 = 11;
+    const ICMP_PARAMETERPROB: u8 = 12;
+    const ICMP_FRAG_NEEDED: u8 = 4;
+
+    const ICMPV6_DEST_UNREACH: u8 = 1;
+    const ICMPV6_PKT_TOOBIG: u8 = 2;
+    const ICMPV6_TIME_EXCEED: u8 = 3;
+    const ICMPV6_PARAMPROB: u8 = 4;
+
+    const ICMP_UNREACH_TABLE: [(Errno, bool); 16] = [
+        (Errno::ENETUNREACH, false),  // Network unreachable
+        (Errno::EHOSTUNREACH, false), // Host unreachable
+        (Errno::ENOPROTOOPT, true),   // Protocol unreachable
+        (Errno::ECONNREFUSED, true),  // Port unreachable
+        (Errno::EMSGSIZE, false),     // Fragmentation needed
+        (Errno::EOPNOTSUPP, false),   // Source route failed
+        (Errno::ENETUNREACH, true),   // Destination network unknown
+        (Errno::EHOSTDOWN, true),     // Destination host unknown
+        (Errno::ENONET, true),        // Source host isolated
+        (Errno::ENETUNREACH, true),   // Network administratively prohibited
+        (Errno::EHOSTUNREACH, true),  // Host administratively prohibited
+        (Errno::ENETUNREACH, false),  // Network unreachable for TOS
+        (Errno::EHOSTUNREACH, false), // Host unreachable for TOS
+        (Errno::EHOSTUNREACH, true),  // Communication administratively prohibited
+        (Errno::EHOSTUNREACH, true),  // Host precedence violation
+        (Errno::EHOSTUNREACH, true),  // Precedence cutoff in effect
+    ];
+
+    const ICMPV6_UNREACH_TABLE: [(Errno, bool); 7] = [
+        (Errno::ENETUNREACH, false),  // No route to destination
+        (Errno::EACCES, true),        // Communication administratively prohibited
+        (Errno::EHOSTUNREACH, false), // Beyond scope of source address
+        (Errno::EHOSTUNREACH, false), // Address unreachable
+        (Errno::ECONNREFUSED, true),  // Port unreachable
+        (Errno::EACCES, true),        // Source address failed ingress/egress policy
+        (Errno::EACCES, true),        // Reject route to destination
+    ];
+
+    let code = icmp_error.icmp_code as usize;
+
+    let result = match (icmp_error.offender, icmp_error.icmp_type) {
+        // Like Linux, UDP sockets report "Fragmentation Needed" as a fatal error because path MTU
+        // discovery is enabled by default.
+        (IpAddress::Ipv4(_), ICMP_DEST_UNREACH) if icmp_error.icmp_code == ICMP_FRAG_NEEDED => {
+            (Errno::EMSGSIZE, true)
+        }
+        (IpAddress::Ipv4(_), ICMP_DEST_UNREACH) => ICMP_UNREACH_TABLE
+            .get(code)
+            .copied()
+            .unwrap_or((Errno::EHOSTUNREACH, false)),
+        (IpAddress::Ipv4(_), ICMP_TIME_EXCEEDED) => (Errno::EHOSTUNREACH, false),
+        (IpAddress::Ipv4(_), ICMP_PARAMETERPROB) => (Errno::EPROTO, true),
+        (IpAddress::Ipv6(_), ICMPV6_DEST_UNREACH) => ICMPV6_UNREACH_TABLE
+            .get(code)
+            .copied()
+            .unwrap_or((Errno::EPROTO, true)),
+        // Like Linux, UDP sockets report "Packet Too Big" as a fatal error because path MTU
+        // discovery is enabled by default.
+        (IpAddress::Ipv6(_), ICMPV6_PKT_TOOBIG) => (Errno::EMSGSIZE, true),
+        (IpAddress::Ipv6(_), ICMPV6_TIME_EXCEED) => (Errno::EHOSTUNREACH, false),
+        (IpAddress::Ipv6(_), ICMPV6_PARAMPROB) => (Errno::EPROTO, true),
+        _ => return None,
+    };
+
+    Some(result)
+}
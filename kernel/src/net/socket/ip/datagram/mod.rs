@@ -2,21 +2,37 @@
 
 use core::sync::atomic::{AtomicBool, Ordering};
 
-use aster_bigtcp::wire::IpEndpoint;
+use aster_bigtcp::{
+    socket::NeedIfacePoll,
+    wire::{IpAddress, IpEndpoint, IpVersion},
+};
 use bound::BoundDatagram;
+use error::{QueuedError, SocketErrors};
+use multicast::MulticastMemberships;
+use options::{Cork, Segment, UdpOptionSet, UDP_MAX_SEGMENTS};
 use unbound::{BindOptions, UnboundDatagram};
 
-use super::addr::UNSPECIFIED_LOCAL_ENDPOINT;
+use super::{
+    addr::{
+        is_dual_stack_endpoint, to_local_endpoint, to_remote_endpoint, to_socket_addr,
+        UNSPECIFIED_LOCAL_ENDPOINT,
+    },
+    common::find_multicast_iface,
+    options::{
+        AddMembership, DropMembership, IpOptionSet, Ipv6OptionSet, SetIpLevelOption,
+        SetIpv6LevelOption,
+    },
+};
 use crate::{
     events::IoEvents,
-    match_sock_option_mut,
+    match_sock_option_mut, match_sock_option_ref,
     net::socket::{
         options::{Error as SocketError, SocketOption},
         private::SocketPrivate,
         util::{
             datagram_common::{select_remote_and_bind, Bound, Inner},
             options::{SetSocketLevelOption, SocketOptionSet},
-            MessageHeader, SendRecvFlags, SocketAddr,
+            ControlMessage, MessageHeader, SendRecvFlags, SocketAddr,
         },
         Socket,
     },
@@ -26,37 +42,68 @@ use crate::{
 };
 
 mod bound;
+mod error;
+mod multicast;
 pub(super) mod observer;
+pub mod options;
 mod unbound;
 
 #[derive(Debug, Clone)]
 struct OptionSet {
     socket: SocketOptionSet,
-    // TODO: UDP option set
+    ip: IpOptionSet,
+    ipv6: Ipv6OptionSet,
+    udp: UdpOptionSet,
 }
 
 impl OptionSet {
     fn new() -> Self {
         let socket = SocketOptionSet::new_udp();
-        OptionSet { socket }
+        let ip = IpOptionSet::new_udp();
+        let ipv6 = Ipv6OptionSet::new();
+        let udp = UdpOptionSet::new();
+        OptionSet {
+            socket,
+            ip,
+            ipv6,
+            udp,
+        }
     }
 }
 
 pub struct DatagramSocket {
-    // Lock order: `inner` first, `options` second
+    // Lock order: `corked` first, `inner` second, `options` third, `errors` fourth
+    corked: Mutex<Option<CorkedDatagram>>,
     inner: RwMutex<Inner<UnboundDatagram, BoundDatagram>>,
     options: RwLock<OptionSet>,
+    errors: SpinLock<SocketErrors>,
+    memberships: Mutex<MulticastMemberships>,
 
+    ip_version: IpVersion,
     is_nonblocking: AtomicBool,
     pollee: Pollee,
 }
 
+/// A datagram that is being built with `UDP_CORK` or `MSG_MORE`.
+struct CorkedDatagram {
+    remote_endpoint: IpEndpoint,
+    payload: Vec<u8>,
+}
+
 impl DatagramSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    /// Creates a new UDP socket.
+    ///
+    /// The IP version is [`IpVersion::Ipv4`] for `AF_INET` sockets and [`IpVersion::Ipv6`] for
+    /// `AF_INET6` sockets.
+    pub fn new(is_nonblocking: bool, ip_version: IpVersion) -> Arc<Self> {
         let unbound_datagram = UnboundDatagram::new();
         Arc::new(Self {
+            corked: Mutex::new(None),
             inner: RwMutex::new(Inner::Unbound(unbound_datagram)),
             options: RwLock::new(OptionSet::new()),
+            errors: SpinLock::new(SocketErrors::new()),
+            memberships: Mutex::new(MulticastMemberships::new()),
+            ip_version,
             is_nonblocking: AtomicBool::new(is_nonblocking),
             pollee: Pollee::new(),
         })
@@ -67,22 +114,59 @@ impl DatagramSocket {
         writer: &mut dyn MultiWrite,
         flags: SendRecvFlags,
     ) -> Result<(usize, SocketAddr)> {
-        let recv_bytes = self
-            .inner
-            .read()
-            .try_recv(writer, flags)
-            .map(|(recv_bytes, remote_endpoint)| (recv_bytes, remote_endpoint.into()))?;
+        if let Some(err) = self.test_and_clear_error() {
+            return Err(err);
+        }
+
+        let recv_bytes =
+            self.inner
+                .read()
+                .try_recv(writer, flags)
+                .map(|(recv_bytes, remote_endpoint)| {
+                    (recv_bytes, to_socket_addr(remote_endpoint, self.ip_version))
+                })?;
         self.pollee.invalidate();
 
         Ok(recv_bytes)
     }
 
+    /// Receives an error from the error queue (i.e., `recvmsg` with `MSG_ERRQUEUE`).
+    fn recv_error_queue(&self, writer: &mut dyn MultiWrite) -> Result<(usize, MessageHeader)> {
+        let queued_error = {
+            let inner = self.inner.read();
+            self.with_errors(&inner, SocketErrors::pop_queued)
+        };
+        // Like Linux, receiving from the error queue never blocks.
+        let Some(QueuedError {
+            extended_error,
+            remote_endpoint,
+            payload,
+        }) = queued_error
+        else {
+            return_errno_with_message!(Errno::EAGAIN, "the error queue is empty");
+        };
+        self.pollee.invalidate();
+
+        let received_bytes = writer.write(&mut VmReader::from(payload.as_slice()))?;
+
+        let message_header = MessageHeader::new(
+            Some(to_socket_addr(remote_endpoint, self.ip_version)),
+            vec![ControlMessage::RecvErr(extended_error)],
+        );
+
+        Ok((received_bytes, message_header))
+    }
+
     fn try_send(
         &self,
         reader: &mut dyn MultiRead,
         remote: Option<&IpEndpoint>,
         flags: SendRecvFlags,
     ) -> Result<usize> {
+        if let Some(err) = self.test_and_clear_error() {
+            return Err(err);
+        }
+
         let (sent_bytes, iface_to_poll) = select_remote_and_bind(
             &self.inner,
             remote,
@@ -93,13 +177,19 @@ impl DatagramSocket {
                         "the destination address is not specified",
                     )
                 })?;
-                self.inner
-                    .write()
-                    .bind_ephemeral(remote_endpoint, &self.pollee)
+                self.bind_ephemeral(remote_endpoint)
             },
             |bound_datagram, remote_endpoint| {
+                if remote_endpoint.addr.is_multicast() {
+                    let options = self.options.read();
+                    bound_datagram.set_multicast_options(
+                        options.ip.multicast_ttl(),
+                        options.ip.multicast_loop(),
+                    );
+                }
+
                 let sent_bytes = bound_datagram.try_send(reader, remote_endpoint, flags)?;
-                let iface_to_poll = bound_datagram.iface().clone();
+                let iface_to_poll = bound_datagram.iface_to(remote_endpoint).clone();
                 Ok((sent_bytes, iface_to_poll))
             },
         )?;
@@ -109,12 +199,211 @@ impl DatagramSocket {
 
         Ok(sent_bytes)
     }
+
+    /// Binds the socket to an ephemeral endpoint for sending datagrams to the remote endpoint.
+    ///
+    /// If the remote address is a multicast address, the iface specified by `IP_MULTICAST_IF` is
+    /// preferred.
+    fn bind_ephemeral(&self, remote_endpoint: &IpEndpoint) -> Result<()> {
+        let local_addr = match remote_endpoint.addr {
+            IpAddress::Ipv4(group) if group.is_multicast() => {
+                let multicast_if = self.options.read().ip.multicast_if();
+                find_multicast_iface(None, &multicast_if).and_then(|iface| iface.ipv4_addr())
+            }
+            _ => None,
+        };
+
+        let mut inner = self.inner.write();
+        match local_addr {
+            Some(local_addr) if matches!(*inner, Inner::Unbound(_)) => inner.bind(
+                &IpEndpoint::new(IpAddress::Ipv4(local_addr), 0),
+                &self.pollee,
+                BindOptions { can_reuse: false },
+            ),
+            _ => inner.bind_ephemeral(remote_endpoint, &self.pollee),
+        }
+    }
+
+    /// Appends the data to the corked datagram and sends the datagram if `is_more` is false.
+    fn send_corked(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: Option<&IpEndpoint>,
+        is_more: bool,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        let mut corked = self.corked.lock();
+
+        if corked.is_none() {
+            // Like Linux, the destination is determined when the first piece of data is
+            // corked. Subsequent destinations are ignored until the datagram is sent.
+            let remote_endpoint = match remote {
+                Some(remote_endpoint) => *remote_endpoint,
+                None => *self.inner.read().peer_addr().ok_or_else(|| {
+                    Error::with_message(
+                        Errno::EDESTADDRREQ,
+                        "the destination address is not specified",
+                    )
+                })?,
+            };
+            *corked = Some(CorkedDatagram {
+                remote_endpoint,
+                payload: Vec::new(),
+            });
+        }
+
+        let corked_datagram = corked.as_mut().unwrap();
+        let len = reader.sum_lens();
+        if corked_datagram.payload.len() + len > max_payload_len(self.ip_version) {
+            // Like Linux, the corked data is discarded if an error occurs.
+            *corked = None;
+            return_errno_with_message!(Errno::EMSGSIZE, "the corked datagram is too large");
+        }
+
+        let old_len = corked_datagram.payload.len();
+        corked_datagram.payload.resize(old_len + len, 0);
+        if let Err(err) = reader.read(&mut VmWriter::from(&mut corked_datagram.payload[old_len..]))
+        {
+            *corked = None;
+            return Err(err);
+        }
+
+        if is_more {
+            return Ok(len);
+        }
+
+        let CorkedDatagram {
+            remote_endpoint,
+            payload,
+        } = corked.take().unwrap();
+        drop(corked);
+
+        self.send_payload(&payload, Some(&remote_endpoint), flags)?;
+
+        Ok(len)
+    }
+
+    /// Sends the corked datagram, if any, after `UDP_CORK` is cleared.
+    fn flush_corked(&self) {
+        let Some(CorkedDatagram {
+            remote_endpoint,
+            payload,
+        }) = self.corked.lock().take()
+        else {
+            return;
+        };
+
+        let Ok(segments) = self.split_into_segments(&payload) else {
+            return;
+        };
+
+        // Like Linux, errors are ignored and clearing `UDP_CORK` never blocks.
+        for segment in segments {
+            let mut reader = VmReader::from(segment).to_fallible();
+            let _ = self.try_send(&mut reader, Some(&remote_endpoint), SendRecvFlags::empty());
+        }
+    }
+
+    /// Sends the payload, which is split into multiple datagrams if `UDP_SEGMENT` is set.
+    fn send_payload(
+        &self,
+        payload: &[u8],
+        remote: Option<&IpEndpoint>,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        let segments = self.split_into_segments(payload)?;
+
+        let mut sent_bytes = 0;
+        for segment in segments {
+            let result = self.block_on(IoEvents::OUT, || {
+                let mut reader = VmReader::from(segment).to_fallible();
+                self.try_send(&mut reader, remote, flags)
+            });
+            match result {
+                Ok(len) => sent_bytes += len,
+                // Errors are reported only if no datagrams have been sent.
+                Err(err) if sent_bytes == 0 => return Err(err),
+                Err(_) => break,
+            }
+        }
+
+        Ok(sent_bytes)
+    }
+
+    /// Splits the payload into segments according to `UDP_SEGMENT`.
+    fn split_into_segments<'a>(&self, payload: &'a [u8]) -> Result<Vec<&'a [u8]>> {
+        let gso_size = self.options.read().udp.gso_size() as usize;
+
+        if gso_size == 0 || payload.len() <= gso_size {
+            return Ok(vec![payload]);
+        }
+
+        if payload.len() > gso_size * UDP_MAX_SEGMENTS {
+            return_errno_with_message!(Errno::EINVAL, "the payload has too many segments");
+        }
+
+        Ok(payload.chunks(gso_size).collect())
+    }
+
+    /// Calls `f` with the socket errors after collecting new errors from the network.
+    fn with_errors<R>(
+        &self,
+        inner: &Inner<UnboundDatagram, BoundDatagram>,
+        f: impl FnOnce(&mut SocketErrors) -> R,
+    ) -> R {
+        let Inner::Bound(bound_datagram) = inner else {
+            return f(&mut self.errors.lock());
+        };
+
+        let is_recverr = self.is_recverr();
+        let is_connected = bound_datagram.remote_endpoint().is_some();
+
+        let mut errors = self.errors.lock();
+        while let Some(icmp_error) = bound_datagram.pop_icmp_error() {
+            errors.add_icmp_error(icmp_error, is_recverr, is_connected);
+        }
+
+        f(&mut errors)
+    }
+
+    fn test_and_clear_error(&self) -> Option<Error> {
+        let error = {
+            let inner = self.inner.read();
+            self.with_errors(&inner, SocketErrors::take_pending)
+        };
+
+        if error.is_some() {
+            self.pollee.invalidate();
+        }
+
+        error
+    }
+
+    /// Returns whether `IP_RECVERR` (or `IPV6_RECVERR` for IPv6 sockets) is enabled.
+    fn is_recverr(&self) -> bool {
+        let options = self.options.read();
+        match self.ip_version {
+            IpVersion::Ipv4 => options.ip.recverr(),
+            IpVersion::Ipv6 => options.ipv6.recverr(),
+        }
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.read();
+
+        let mut events = inner.check_io_events();
+        if self.with_errors(&inner, |errors| errors.has_error()) {
+            events |= IoEvents::ERR;
+        }
+
+        events
+    }
 }
 
 impl Pollable for DatagramSocket {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
         self.pollee
-            .poll_with(mask, poller, || self.inner.read().check_io_events())
+            .poll_with(mask, poller, || self.check_io_events())
     }
 }
 
@@ -130,16 +419,26 @@ impl SocketPrivate for DatagramSocket {
 
 impl Socket for DatagramSocket {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
-        let can_reuse = self.options.read().socket.reuse_addr();
-
-        self.inner
-            .write()
-            .bind(&endpoint, &self.pollee, BindOptions { can_reuse })
+        let (can_reuse, is_v6only) = {
+            let options = self.options.read();
+            (options.socket.reuse_addr(), options.ipv6.v6only())
+        };
+        let endpoint = to_local_endpoint(socket_addr, self.ip_version, is_v6only)?;
+        let is_dual_stack = is_dual_stack_endpoint(&endpoint, is_v6only);
+
+        self.inner.write().bind(
+            &endpoint,
+            &self.pollee,
+            BindOptions {
+                can_reuse,
+                is_dual_stack,
+            },
+        )
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let is_v6only = self.options.read().ipv6.v6only();
+        let endpoint = to_remote_endpoint(socket_addr, self.ip_version, is_v6only)?;
 
         self.inner.write().connect(&endpoint, &self.pollee)
     }
@@ -151,7 +450,7 @@ impl Socket for DatagramSocket {
             .addr()
             .unwrap_or(UNSPECIFIED_LOCAL_ENDPOINT);
 
-        Ok(endpoint.into())
+        Ok(to_socket_addr(endpoint, self.ip_version))
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
@@ -160,7 +459,7 @@ impl Socket for DatagramSocket {
                 Error::with_message(Errno::ENOTCONN, "the socket is not connected")
             })?;
 
-        Ok(endpoint.into())
+        Ok(to_socket_addr(endpoint, self.ip_version))
     }
 
     fn sendmsg(
@@ -176,21 +475,50 @@ impl Socket for DatagramSocket {
 
         let MessageHeader {
             addr,
-            control_message,
+            control_messages,
+            ..
         } = message_header;
 
         let endpoint = match addr {
-            Some(addr) => Some(addr.try_into()?),
+            Some(addr) => {
+                let is_v6only = self.options.read().ipv6.v6only();
+                Some(to_remote_endpoint(addr, self.ip_version, is_v6only)?)
+            }
             None => None,
         };
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
 
-        // TODO: Block if the send buffer is full
-        self.try_send(reader, endpoint.as_ref(), flags)
+        let (is_corked, gso_size) = {
+            let options = self.options.read();
+            (options.udp.cork(), options.udp.gso_size() as usize)
+        };
+        let is_more = is_corked || flags.contains(SendRecvFlags::MSG_MORE);
+
+        // Deal with the pending corked datagram, if any.
+        if is_more || self.corked.lock().is_some() {
+            return self.send_corked(reader, endpoint.as_ref(), is_more, flags);
+        }
+
+        // Deal with generic segmentation offload (GSO).
+        let len = reader.sum_lens();
+        if gso_size != 0 && len > gso_size {
+            if len > max_payload_len(self.ip_version) {
+                return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+            }
+
+            let mut payload = vec![0; len];
+            reader.read(&mut VmWriter::from(payload.as_mut_slice()))?;
+            return self.send_payload(&payload, endpoint.as_ref(), flags);
+        }
+
+        // The send buffer is checked before any data is read, so it is safe to retry.
+        self.block_on(IoEvents::OUT, || {
+            self.try_send(reader, endpoint.as_ref(), flags)
+        })
     }
 
     fn recvmsg(
@@ -203,12 +531,16 @@ impl Socket for DatagramSocket {
             warn!("unsupported flags: {:?}", flags);
         }
 
+        if flags.contains(SendRecvFlags::MSG_ERRQUEUE) {
+            return self.recv_error_queue(writer);
+        }
+
         let (received_bytes, peer_addr) =
             self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
 
         // TODO: Receive control message
 
-        let message_header = MessageHeader::new(Some(peer_addr), None);
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
 
         Ok((received_bytes, message_header))
     }
@@ -216,41 +548,191 @@ impl Socket for DatagramSocket {
     fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
         match_sock_option_mut!(option, {
             socket_errors: SocketError => {
-                // TODO: Support socket errors for UDP sockets
-                socket_errors.set(None);
+                socket_errors.set(self.test_and_clear_error());
                 return Ok(());
             },
             _ => ()
         });
 
-        self.options.read().socket.get_option(option)
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IP-level options
+        match options.ip.get_option(option) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IPv6-level options
+        if self.ip_version == IpVersion::Ipv6 {
+            match options.ipv6.get_option(option) {
+                Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+                res => return res,
+            }
+        }
+
+        // Deal with UDP-level options
+        match_sock_option_mut!(option, {
+            udp_cork: Cork => {
+                let cork = options.udp.cork();
+                udp_cork.set(cork);
+            },
+            udp_segment: Segment => {
+                let gso_size = options.udp.gso_size();
+                udp_segment.set(gso_size);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
+
+        Ok(())
     }
 
     fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        // Deal with multicast memberships, which are not part of the option set
+        match_sock_option_ref!(option, {
+            add_membership: AddMembership => {
+                return self.memberships.lock().join(add_membership.get().unwrap());
+            },
+            drop_membership: DropMembership => {
+                return self.memberships.lock().leave(drop_membership.get().unwrap());
+            },
+            _ => ()
+        });
+
         let inner = self.inner.read();
         let mut options = self.options.write();
 
-        match options.socket.set_option(option, &*inner) {
-            Err(e) => Err(e),
-            Ok(need_iface_poll) => {
-                let iface_to_poll = need_iface_poll
-                    .then(|| match &*inner {
-                        Inner::Unbound(_) => None,
-                        Inner::Bound(bound_datagram) => Some(bound_datagram.iface().clone()),
-                    })
-                    .flatten();
-
-                drop(inner);
-                drop(options);
-
-                if let Some(iface) = iface_to_poll {
-                    iface.poll();
+        // Deal with socket-level options
+        let need_iface_poll = match options.socket.set_option(option, &*inner) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                // Deal with IP-level options
+                match options.ip.set_option(option, &*inner) {
+                    Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                        // Deal with IPv6-level options
+                        match do_ipv6_setsockopt(option, self.ip_version, &mut options, &inner) {
+                            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                                // Deal with UDP-level options
+                                do_udp_setsockopt(option, &mut options)?
+                            }
+                            Err(err) => return Err(err),
+                            Ok(need_iface_poll) => need_iface_poll,
+                        }
+                    }
+                    Err(err) => return Err(err),
+                    Ok(need_iface_poll) => need_iface_poll,
                 }
-
-                Ok(())
             }
+            Err(err) => return Err(err),
+            Ok(need_iface_poll) => need_iface_poll,
+        };
+
+        // Like Linux, the error queue is purged if `IP_RECVERR` (or `IPV6_RECVERR`) is disabled.
+        let is_recverr = match self.ip_version {
+            IpVersion::Ipv4 => options.ip.recverr(),
+            IpVersion::Ipv6 => options.ipv6.recverr(),
+        };
+        if !is_recverr {
+            self.errors.lock().purge_queue();
+        }
+
+        let iface_to_poll = need_iface_poll
+            .then(|| match &*inner {
+                Inner::Unbound(_) => None,
+                Inner::Bound(bound_datagram) => Some(bound_datagram.iface().clone()),
+            })
+            .flatten();
+
+        drop(inner);
+        drop(options);
+
+        if let Some(iface) = iface_to_poll {
+            iface.poll();
         }
+
+        // Like Linux, the corked datagram is sent when `UDP_CORK` is cleared.
+        match_sock_option_ref!(option, {
+            udp_cork: Cork => {
+                if !*udp_cork.get().unwrap() {
+                    self.flush_corked();
+                }
+            },
+            _ => ()
+        });
+
+        Ok(())
+    }
+}
+
+/// Returns the maximum length of a UDP payload.
+///
+/// The length is limited by the 16-bit length fields in the IP and UDP headers.
+fn max_payload_len(ip_version: IpVersion) -> usize {
+    const IPV4_HEADER_LEN: usize = 20;
+    const UDP_HEADER_LEN: usize = 8;
+
+    match ip_version {
+        IpVersion::Ipv4 => u16::MAX as usize - IPV4_HEADER_LEN - UDP_HEADER_LEN,
+        // The payload length in the IPv6 header does not include the IPv6 header itself.
+        IpVersion::Ipv6 => u16::MAX as usize - UDP_HEADER_LEN,
+    }
+}
+
+fn do_ipv6_setsockopt(
+    option: &dyn SocketOption,
+    ip_version: IpVersion,
+    options: &mut OptionSet,
+    inner: &Inner<UnboundDatagram, BoundDatagram>,
+) -> Result<NeedIfacePoll> {
+    if ip_version != IpVersion::Ipv6 {
+        return_errno_with_message!(
+            Errno::ENOPROTOOPT,
+            "IPv6-level options cannot be set on IPv4 sockets"
+        );
     }
+
+    options.ipv6.set_option(option, inner)
+}
+
+fn do_udp_setsockopt(option: &dyn SocketOption, options: &mut OptionSet) -> Result<NeedIfacePoll> {
+    match_sock_option_ref!(option, {
+        udp_cork: Cork => {
+            let cork = udp_cork.get().unwrap();
+            options.udp.set_cork(*cork);
+        },
+        udp_segment: Segment => {
+            let gso_size = udp_segment.get().unwrap();
+            options.udp.set_gso_size(*gso_size);
+        },
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+    });
+
+    Ok(NeedIfacePoll::FALSE)
 }
 
 impl SetSocketLevelOption for Inner<UnboundDatagram, BoundDatagram> {}
+
+impl SetIpLevelOption for Inner<UnboundDatagram, BoundDatagram> {
+    fn set_hdrincl(&self, _hdrincl: bool) -> Result<()> {
+        return_errno_with_message!(
+            Errno::ENOPROTOOPT,
+            "IP_HDRINCL cannot be set on UDP sockets"
+        );
+    }
+}
+
+impl SetIpv6LevelOption for Inner<UnboundDatagram, BoundDatagram> {
+    fn set_v6only(&self, _v6only: bool) -> Result<()> {
+        match self {
+            Inner::Unbound(_) => Ok(()),
+            Inner::Bound(_) => return_errno_with_message!(
+                Errno::EINVAL,
+                "IPV6_V6ONLY cannot be set after the socket is bound"
+            ),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::Ipv4Address;
+
+use crate::{
+    net::{
+        iface::Iface,
+        socket::ip::{
+            common::find_multicast_iface,
+            options::{IpMreq, IpMulticastIf},
+        },
+    },
+    prelude::*,
+};
+
+/// The IPv4 multicast groups joined by a UDP socket.
+///
+/// When dropped, the socket leaves all the groups.
+pub(super) struct MulticastMemberships(Vec<Membership>);
+
+struct Membership {
+    iface: Arc<Iface>,
+    group: Ipv4Address,
+}
+
+/// The maximum number of multicast groups that a socket can join.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/igmp.c#L113>.
+const MAX_MEMBERSHIPS: usize = 20;
+
+impl MulticastMemberships {
+    pub(super) const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Joins the multicast group (i.e., `IP_ADD_MEMBERSHIP`).
+    pub(super) fn join(&mut self, mreq: &IpMreq) -> Result<()> {
+        let group = mreq.multiaddr;
+        if !group.is_multicast() {
+            return_errno_with_message!(Errno::EINVAL, "the address is not a multicast address");
+        }
+
+        let Some(iface) = find_multicast_iface(Some(group), &mreq.interface) else {
+            return_errno_with_message!(Errno::ENODEV, "the interface does not exist");
+        };
+
+        if self
+            .0
+            .iter()
+            .any(|membership| membership.group == group && Arc::ptr_eq(&membership.iface, &iface))
+        {
+            return_errno_with_message!(Errno::EADDRINUSE, "the group has already been joined");
+        }
+        if self.0.len() >= MAX_MEMBERSHIPS {
+            return_errno_with_message!(Errno::ENOBUFS, "too many groups have been joined");
+        }
+
+        if *iface.join_multicast_group(group) {
+            iface.poll();
+        }
+        self.0.push(Membership { iface, group });
+
+        Ok(())
+    }
+
+    /// Leaves the multicast group (i.e., `IP_DROP_MEMBERSHIP`).
+    pub(super) fn leave(&mut self, mreq: &IpMreq) -> Result<()> {
+        let group = mreq.multiaddr;
+
+        // Like Linux, if the iface is not specified, the first membership of the group is dropped,
+        // regardless of its iface.
+        let iface = if mreq.interface == IpMulticastIf::UNSPECIFIED {
+            None
+        } else {
+            let Some(iface) = find_multicast_iface(None, &mreq.interface) else {
+                return_errno_with_message!(Errno::ENODEV, "the interface does not exist");
+            };
+            Some(iface)
+        };
+
+        let Some(pos) = self.0.iter().position(|membership| {
+            membership.group == group
+                && iface
+                    .as_ref()
+                    .is_none_or(|iface| Arc::ptr_eq(&membership.iface, iface))
+        }) else {
+            return_errno_with_message!(Errno::EADDRNOTAVAIL, "the group has not been joined");
+        };
+
+        let membership = self.0.swap_remove(pos);
+        membership.release();
+
+        Ok(())
+    }
+}
+
+impl Membership {
+    fn release(&self) {
+        if *self.iface.leave_multicast_group(self.group) {
+            self.iface.poll();
+        }
+    }
+}
+
+impl Drop for MulticastMemberships {
+    fn drop(&mut self) {
+        for membership in self.0.iter() {
+            membership.release();
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{impl_socket_options, prelude::*};
+
+impl_socket_options!(
+    pub struct Cork(bool);
+    pub struct Segment(u16);
+);
+
+/// UDP-level socket options.
+#[derive(Debug, Clone, Copy, CopyGetters, Setters)]
+#[get_copy = "pub"]
+#[set = "pub"]
+pub(super) struct UdpOptionSet {
+    cork: bool,
+    gso_size: u16,
+}
+
+impl UdpOptionSet {
+    pub(super) const fn new() -> Self {
+        Self {
+            cork: false,
+            gso_size: 0,
+        }
+    }
+}
+
+/// The maximum number of segments that can be sent in one call with `UDP_SEGMENT`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/udp.h#L97>.
+pub(super) const UDP_MAX_SEGMENTS: usize = 64;
@@ -1,13 +1,16 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::{socket::UdpSocket, wire::IpEndpoint};
+use aster_bigtcp::wire::IpEndpoint;
 
 use super::{bound::BoundDatagram, observer::DatagramObserver};
 use crate::{
     events::IoEvents,
-    net::socket::{
-        ip::common::{bind_port, get_ephemeral_endpoint},
-        util::datagram_common,
+    net::{
+        iface::{BoundPort, UdpSocket},
+        socket::{
+            ip::common::{bind_dual_stack_port, bind_port, get_ephemeral_endpoint},
+            util::datagram_common,
+        },
     },
     prelude::*,
     process::signal::Pollee,
@@ -25,6 +28,7 @@ impl UnboundDatagram {
 
 pub(super) struct BindOptions {
     pub(super) can_reuse: bool,
+    pub(super) is_dual_stack: bool,
 }
 
 impl datagram_common::Unbound for UnboundDatagram {
@@ -39,17 +43,23 @@ impl datagram_common::Unbound for UnboundDatagram {
         pollee: &Pollee,
         options: BindOptions,
     ) -> Result<Self::Bound> {
-        let bound_port = bind_port(endpoint, options.can_reuse)?;
+        if options.is_dual_stack {
+            let (bound_port, ipv4_bound_port) =
+                bind_dual_stack_port(endpoint.port, options.can_reuse)?;
+
+            let bound_socket = new_bound_socket(bound_port, pollee);
+            let ipv4_bound_socket =
+                ipv4_bound_port.map(|ipv4_bound_port| new_bound_socket(ipv4_bound_port, pollee));
 
-        let bound_socket =
-            match UdpSocket::new_bind(bound_port, DatagramObserver::new(pollee.clone())) {
-                Ok(bound_socket) => bound_socket,
-                Err((_, err)) => {
-                    unreachable!("`new_bind` fails with {:?}, which should not happen", err)
-                }
-            };
+            return Ok(BoundDatagram::new_dual_stack(
+                bound_socket,
+                ipv4_bound_socket,
+            ));
+        }
+
+        let bound_port = bind_port(endpoint, options.can_reuse)?;
 
-        Ok(BoundDatagram::new(bound_socket))
+        Ok(BoundDatagram::new(new_bound_socket(bound_port, pollee)))
     }
 
     fn bind_ephemeral(
@@ -57,11 +67,27 @@ impl datagram_common::Unbound for UnboundDatagram {
         remote_endpoint: &Self::Endpoint,
         pollee: &Pollee,
     ) -> Result<Self::Bound> {
-        let endpoint = get_ephemeral_endpoint(remote_endpoint);
-        self.bind(&endpoint, pollee, BindOptions { can_reuse: false })
+        let endpoint = get_ephemeral_endpoint(remote_endpoint)?;
+        self.bind(
+            &endpoint,
+            pollee,
+            BindOptions {
+                can_reuse: false,
+                is_dual_stack: false,
+            },
+        )
     }
 
     fn check_io_events(&self) -> IoEvents {
         IoEvents::OUT
     }
 }
+
+fn new_bound_socket(bound_port: BoundPort, pollee: &Pollee) -> UdpSocket {
+    match UdpSocket::new_bind(bound_port, DatagramObserver::new(pollee.clone())) {
+        Ok(bound_socket) => bound_socket,
+        Err((_, err)) => {
+            unreachable!("`new_bind` fails with {:?}, which should not happen", err)
+        }
+    }
+}
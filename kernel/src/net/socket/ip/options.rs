@@ -2,8 +2,9 @@
 
 use core::num::NonZeroU8;
 
-use aster_bigtcp::socket::NeedIfacePoll;
+use aster_bigtcp::{socket::NeedIfacePoll, wire::Ipv4Address};
 
+use super::common::find_multicast_iface;
 use crate::{
     impl_socket_options, match_sock_option_mut, match_sock_option_ref,
     net::socket::options::SocketOption, prelude::*,
@@ -17,9 +18,14 @@ pub(super) struct IpOptionSet {
     tos: u8,
     ttl: IpTtl,
     hdrincl: bool,
+    recverr: bool,
+    multicast_if: IpMulticastIf,
+    multicast_ttl: u8,
+    multicast_loop: bool,
 }
 
 const DEFAULT_TTL: u8 = 64;
+const DEFAULT_MULTICAST_TTL: u8 = 1;
 pub(super) const INET_ECN_MASK: u8 = 3;
 
 impl IpOptionSet {
@@ -28,6 +34,34 @@ impl IpOptionSet {
             tos: 0,
             ttl: IpTtl(None),
             hdrincl: false,
+            recverr: false,
+            multicast_if: IpMulticastIf::UNSPECIFIED,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_loop: true,
+        }
+    }
+
+    pub(super) const fn new_udp() -> Self {
+        Self {
+            tos: 0,
+            ttl: IpTtl(None),
+            hdrincl: false,
+            recverr: false,
+            multicast_if: IpMulticastIf::UNSPECIFIED,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_loop: true,
+        }
+    }
+
+    pub(super) const fn new_raw(hdrincl: bool) -> Self {
+        Self {
+            tos: 0,
+            ttl: IpTtl(None),
+            hdrincl,
+            recverr: false,
+            multicast_if: IpMulticastIf::UNSPECIFIED,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_loop: true,
         }
     }
 
@@ -45,6 +79,22 @@ impl IpOptionSet {
                 let hdrincl = self.hdrincl();
                 ip_hdrincl.set(hdrincl);
             },
+            ip_recverr: RecvErr => {
+                let recverr = self.recverr();
+                ip_recverr.set(recverr);
+            },
+            ip_multicast_if: MulticastIf => {
+                let multicast_if = self.multicast_if();
+                ip_multicast_if.set(multicast_if);
+            },
+            ip_multicast_ttl: MulticastTtl => {
+                let multicast_ttl = self.multicast_ttl();
+                ip_multicast_ttl.set(multicast_ttl);
+            },
+            ip_multicast_loop: MulticastLoop => {
+                let multicast_loop = self.multicast_loop();
+                ip_multicast_loop.set(multicast_loop);
+            },
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option is unknown")
         });
 
@@ -73,6 +123,31 @@ impl IpOptionSet {
                 socket.set_hdrincl(*hdrincl)?;
                 self.set_hdrincl(*hdrincl);
             },
+            ip_recverr: RecvErr => {
+                let recverr = ip_recverr.get().unwrap();
+                self.set_recverr(*recverr);
+            },
+            ip_multicast_if: MulticastIf => {
+                let multicast_if = ip_multicast_if.get().unwrap();
+                // Like Linux, specifying neither the index nor the address resets the iface.
+                if *multicast_if != IpMulticastIf::UNSPECIFIED
+                    && find_multicast_iface(None, multicast_if).is_none()
+                {
+                    return_errno_with_message!(
+                        Errno::EADDRNOTAVAIL,
+                        "the interface does not exist"
+                    );
+                }
+                self.set_multicast_if(*multicast_if);
+            },
+            ip_multicast_ttl: MulticastTtl => {
+                let multicast_ttl = ip_multicast_ttl.get().unwrap();
+                self.set_multicast_ttl(*multicast_ttl);
+            },
+            ip_multicast_loop: MulticastLoop => {
+                let multicast_loop = ip_multicast_loop.get().unwrap();
+                self.set_multicast_loop(*multicast_loop);
+            },
             _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
         });
 
@@ -84,6 +159,12 @@ impl_socket_options!(
     pub struct Tos(i32);
     pub struct Ttl(IpTtl);
     pub struct Hdrincl(bool);
+    pub struct RecvErr(bool);
+    pub struct MulticastIf(IpMulticastIf);
+    pub struct MulticastTtl(u8);
+    pub struct MulticastLoop(bool);
+    pub struct AddMembership(IpMreq);
+    pub struct DropMembership(IpMreq);
 );
 
 #[derive(Debug, Clone, Copy)]
@@ -103,6 +184,96 @@ impl IpTtl {
     }
 }
 
+/// The iface for multicast packets.
+///
+/// The iface is specified by its index, or by its local address if the index is zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpMulticastIf {
+    pub address: Ipv4Address,
+    pub ifindex: u32,
+}
+
+impl IpMulticastIf {
+    pub const UNSPECIFIED: Self = Self {
+        address: Ipv4Address::UNSPECIFIED,
+        ifindex: 0,
+    };
+}
+
+/// A request to join or leave a multicast group (i.e., `struct ip_mreqn` or `struct ip_mreq`).
+#[derive(Debug, Clone, Copy)]
+pub struct IpMreq {
+    pub multiaddr: Ipv4Address,
+    pub interface: IpMulticastIf,
+}
+
 pub(super) trait SetIpLevelOption {
     fn set_hdrincl(&self, _hdrincl: bool) -> Result<()>;
 }
+
+/// IPv6-level socket options.
+#[derive(Debug, Clone, Copy, CopyGetters, Setters)]
+#[get_copy = "pub"]
+#[set = "pub"]
+pub(super) struct Ipv6OptionSet {
+    v6only: bool,
+    recverr: bool,
+}
+
+impl Ipv6OptionSet {
+    pub(super) const fn new() -> Self {
+        Self {
+            v6only: false,
+            recverr: false,
+        }
+    }
+
+    pub(super) fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            ipv6_v6only: V6Only => {
+                let v6only = self.v6only();
+                ipv6_v6only.set(v6only);
+            },
+            ipv6_recverr: Ipv6RecvErr => {
+                let recverr = self.recverr();
+                ipv6_recverr.set(recverr);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option is unknown")
+        });
+
+        Ok(())
+    }
+
+    pub(super) fn set_option(
+        &mut self,
+        option: &dyn SocketOption,
+        socket: &dyn SetIpv6LevelOption,
+    ) -> Result<NeedIfacePoll> {
+        match_sock_option_ref!(option, {
+            ipv6_v6only: V6Only => {
+                let v6only = ipv6_v6only.get().unwrap();
+                socket.set_v6only(*v6only)?;
+                self.set_v6only(*v6only);
+            },
+            ipv6_recverr: Ipv6RecvErr => {
+                let recverr = ipv6_recverr.get().unwrap();
+                self.set_recverr(*recverr);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+
+        Ok(NeedIfacePoll::FALSE)
+    }
+}
+
+impl_socket_options!(
+    pub struct V6Only(bool);
+    pub struct Ipv6RecvErr(bool);
+);
+
+pub(super) trait SetIpv6LevelOption {
+    /// Checks whether `IPV6_V6ONLY` can be set.
+    ///
+    /// Linux does not allow changing `IPV6_V6ONLY` after the socket is bound.
+    fn set_v6only(&self, _v6only: bool) -> Result<()>;
+}
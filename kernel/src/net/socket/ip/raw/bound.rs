@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::{
+    errors::raw::{RecvError, SendError},
+    socket::IpRawSocketKind,
+    wire::IpEndpoint,
+};
+
+use crate::{
+    events::IoEvents,
+    net::{
+        iface::{Iface, IpRawSocket},
+        socket::{
+            ip::options::IpTtl,
+            util::{datagram_common, SendRecvFlags},
+        },
+    },
+    prelude::*,
+    util::{MultiRead, MultiWrite},
+};
+
+pub(super) struct BoundRaw {
+    bound_socket: IpRawSocket,
+    remote_endpoint: Option<IpEndpoint>,
+}
+
+impl BoundRaw {
+    pub(super) fn new(bound_socket: IpRawSocket) -> Self {
+        Self {
+            bound_socket,
+            remote_endpoint: None,
+        }
+    }
+
+    pub(super) fn iface(&self) -> &Arc<Iface> {
+        self.bound_socket.iface()
+    }
+
+    /// Sends an IPv4 packet with the IP header provided in the reader.
+    ///
+    /// This is used when `IP_HDRINCL` is set.
+    pub(super) fn try_send_with_header(&self, reader: &mut dyn MultiRead) -> Result<usize> {
+        let packet = self.read_packet(reader)?;
+        let len = packet.len();
+
+        self.bound_socket
+            .send_with_header(packet)
+            .map_err(Error::from)?;
+
+        Ok(len)
+    }
+
+    /// Sends a packet with the hop limit (i.e., the TTL for IPv4).
+    pub(super) fn try_send_with_hop_limit(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: &IpEndpoint,
+        hop_limit: u8,
+    ) -> Result<usize> {
+        if self
+            .bound_socket
+            .local_addr()
+            .is_none_or(|local_addr| local_addr.version() != remote.addr.version())
+        {
+            return_errno_with_message!(
+                Errno::ENETUNREACH,
+                "the destination address is in a different IP version"
+            );
+        }
+
+        let payload = self.read_packet(reader)?;
+        let len = payload.len();
+
+        self.bound_socket
+            .send(remote.addr, hop_limit, payload)
+            .map_err(Error::from)?;
+
+        Ok(len)
+    }
+
+    fn read_packet(&self, reader: &mut dyn MultiRead) -> Result<Vec<u8>> {
+        let len = reader.sum_lens();
+        if len > self.iface().mtu() {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+
+        let mut packet = vec![0u8; len];
+        reader.read(&mut VmWriter::from(packet.as_mut_slice()))?;
+
+        Ok(packet)
+    }
+}
+
+impl datagram_common::Bound for BoundRaw {
+    type Endpoint = IpEndpoint;
+
+    fn local_endpoint(&self) -> Self::Endpoint {
+        let addr = self.bound_socket.local_addr().unwrap();
+        let port = match self.bound_socket.kind() {
+            IpRawSocketKind::Raw(_) => 0,
+            IpRawSocketKind::Ping(ident) => ident,
+        };
+
+        IpEndpoint::new(addr, port)
+    }
+
+    fn remote_endpoint(&self) -> Option<&Self::Endpoint> {
+        self.remote_endpoint.as_ref()
+    }
+
+    fn set_remote_endpoint(&mut self, endpoint: &Self::Endpoint) {
+        self.remote_endpoint = Some(*endpoint)
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, Self::Endpoint)> {
+        let result = self.bound_socket.recv(|packet, src_addr| {
+            let copied_res = writer.write(&mut VmReader::from(packet));
+            (copied_res, IpEndpoint::new(src_addr, 0))
+        });
+
+        match result {
+            Ok((Ok(res), endpoint)) => Ok((res, endpoint)),
+            Ok((Err(e), _)) => Err(e),
+            Err(RecvError::Exhausted) => {
+                return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty")
+            }
+        }
+    }
+
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: &Self::Endpoint,
+        _flags: SendRecvFlags,
+    ) -> Result<usize> {
+        self.try_send_with_hop_limit(reader, remote, IpTtl::new(None).get())
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let mut events = IoEvents::empty();
+
+        if self.bound_socket.can_recv() {
+            events |= IoEvents::IN;
+        }
+
+        if self.bound_socket.can_send() {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+}
+
+impl From<SendError> for Error {
+    fn from(value: SendError) -> Self {
+        match value {
+            SendError::TooLarge => Error::with_message(Errno::EMSGSIZE, "the message is too large"),
+            SendError::Unaddressable => {
+                Error::with_message(Errno::EINVAL, "the destination address is invalid")
+            }
+            SendError::BufferFull => Error::with_message(Errno::EAGAIN, "the send buffer is full"),
+            SendError::Malformed => Error::with_message(Errno::EINVAL, "the packet is malformed"),
+        }
+    }
+}
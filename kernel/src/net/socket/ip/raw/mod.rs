@@ -0,0 +1,374 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_bigtcp::wire::{IpEndpoint, IpVersion};
+use bound::BoundRaw;
+use unbound::UnboundRaw;
+
+use super::{
+    addr::{to_local_endpoint, to_remote_endpoint, to_socket_addr, UNSPECIFIED_LOCAL_ENDPOINT},
+    options::{IpOptionSet, Ipv6OptionSet, SetIpLevelOption, SetIpv6LevelOption},
+};
+use crate::{
+    events::IoEvents,
+    match_sock_option_mut,
+    net::socket::{
+        options::{Error as SocketError, SocketOption},
+        private::SocketPrivate,
+        util::{
+            datagram_common::{select_remote_and_bind, Inner},
+            options::{SetSocketLevelOption, SocketOptionSet},
+            MessageHeader, SendRecvFlags, SocketAddr,
+        },
+        Socket,
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    util::{MultiRead, MultiWrite},
+};
+
+mod bound;
+mod unbound;
+
+/// The kind of a [`RawSocket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawSocketKind {
+    /// A raw IP socket (i.e., `SOCK_RAW`) that handles packets of the IP protocol.
+    Raw(u8),
+    /// An ICMP echo socket (i.e., `SOCK_DGRAM` with `IPPROTO_ICMP` or `IPPROTO_ICMPV6`).
+    Ping,
+}
+
+const IPPROTO_RAW: u8 = 255;
+
+#[derive(Debug, Clone)]
+struct OptionSet {
+    socket: SocketOptionSet,
+    ip: IpOptionSet,
+    ipv6: Ipv6OptionSet,
+}
+
+impl OptionSet {
+    fn new(kind: RawSocketKind) -> Self {
+        let socket = SocketOptionSet::new_udp();
+        // Like Linux, `IP_HDRINCL` is enabled by default for `IPPROTO_RAW` sockets.
+        let ip = IpOptionSet::new_raw(kind == RawSocketKind::Raw(IPPROTO_RAW));
+        let ipv6 = Ipv6OptionSet::new();
+        OptionSet { socket, ip, ipv6 }
+    }
+}
+
+pub struct RawSocket {
+    // Lock order: `inner` first, `options` second
+    inner: RwMutex<Inner<UnboundRaw, BoundRaw>>,
+    options: RwLock<OptionSet>,
+
+    kind: RawSocketKind,
+    ip_version: IpVersion,
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+}
+
+impl RawSocket {
+    /// Creates a new raw IP socket or a new ICMP echo socket.
+    ///
+    /// Creating raw IP sockets requires `CAP_NET_RAW`.
+    pub fn new(
+        is_nonblocking: bool,
+        ip_version: IpVersion,
+        kind: RawSocketKind,
+    ) -> Result<Arc<Self>> {
+        if let RawSocketKind::Raw(_) = kind {
+            let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+            if !credentials.effective_capset().contains(CapSet::NET_RAW) {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "creating raw IP sockets requires `CAP_NET_RAW`"
+                );
+            }
+        }
+        // FIXME: Linux only allows the groups in the `net.ipv4.ping_group_range` sysctl to create
+        // ICMP echo sockets. We allow all users to create them, which is the same as what most
+        // Linux distributions configure.
+
+        let unbound_raw = UnboundRaw::new(kind);
+        Ok(Arc::new(Self {
+            inner: RwMutex::new(Inner::Unbound(unbound_raw)),
+            options: RwLock::new(OptionSet::new(kind)),
+            kind,
+            ip_version,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee: Pollee::new(),
+        }))
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, SocketAddr)> {
+        let recv_bytes =
+            self.inner
+                .read()
+                .try_recv(writer, flags)
+                .map(|(recv_bytes, remote_endpoint)| {
+                    (recv_bytes, to_socket_addr(remote_endpoint, self.ip_version))
+                })?;
+        self.pollee.invalidate();
+
+        Ok(recv_bytes)
+    }
+
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: Option<&IpEndpoint>,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        let (hop_limit, hdrincl) = {
+            let options = self.options.read();
+            (options.ip.ttl().get(), options.ip.hdrincl())
+        };
+
+        let (sent_bytes, iface_to_poll) = select_remote_and_bind(
+            &self.inner,
+            remote,
+            || {
+                let remote_endpoint = remote.ok_or_else(|| {
+                    Error::with_message(
+                        Errno::EDESTADDRREQ,
+                        "the destination address is not specified",
+                    )
+                })?;
+                self.inner
+                    .write()
+                    .bind_ephemeral(remote_endpoint, &self.pollee)
+            },
+            |bound_raw, remote_endpoint| {
+                let sent_bytes = if hdrincl && self.ip_version == IpVersion::Ipv4 {
+                    bound_raw.try_send_with_header(reader)?
+                } else {
+                    bound_raw.try_send_with_hop_limit(reader, remote_endpoint, hop_limit)?
+                };
+                let iface_to_poll = bound_raw.iface().clone();
+                Ok((sent_bytes, iface_to_poll))
+            },
+        )?;
+
+        self.pollee.invalidate();
+        iface_to_poll.poll();
+
+        Ok(sent_bytes)
+    }
+}
+
+impl Pollable for RawSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.inner.read().check_io_events())
+    }
+}
+
+impl SocketPrivate for RawSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for RawSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let is_v6only = self.options.read().ipv6.v6only();
+        let endpoint = to_local_endpoint(socket_addr, self.ip_version, is_v6only)?;
+
+        self.inner.write().bind(&endpoint, &self.pollee, ())
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        let is_v6only = self.options.read().ipv6.v6only();
+        let endpoint = to_remote_endpoint(socket_addr, self.ip_version, is_v6only)?;
+
+        self.inner.write().connect(&endpoint, &self.pollee)
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        let endpoint = self
+            .inner
+            .read()
+            .addr()
+            .unwrap_or(UNSPECIFIED_LOCAL_ENDPOINT);
+
+        Ok(to_socket_addr(endpoint, self.ip_version))
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        let endpoint =
+            *self.inner.read().peer_addr().ok_or_else(|| {
+                Error::with_message(Errno::ENOTCONN, "the socket is not connected")
+            })?;
+
+        Ok(to_socket_addr(endpoint, self.ip_version))
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+            ..
+        } = message_header;
+
+        let endpoint = match addr {
+            Some(addr) => {
+                let is_v6only = self.options.read().ipv6.v6only();
+                Some(to_remote_endpoint(addr, self.ip_version, is_v6only)?)
+            }
+            None => None,
+        };
+
+        if !control_messages.is_empty() {
+            // TODO: Support sending control message
+            warn!("sending control message is not supported");
+        }
+
+        // TODO: Block if the send buffer is full
+        self.try_send(reader, endpoint.as_ref(), flags)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, peer_addr) =
+            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+
+        // TODO: Receive control message
+
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_errors: SocketError => {
+                // TODO: Support socket errors for raw sockets
+                socket_errors.set(None);
+                return Ok(());
+            },
+            _ => ()
+        });
+
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IP-level options
+        match options.ip.get_option(option) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IPv6-level options
+        if self.ip_version == IpVersion::Ipv6 {
+            return options.ipv6.get_option(option);
+        }
+
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        let inner = self.inner.read();
+        let mut options = self.options.write();
+
+        let result = match options.socket.set_option(option, &*inner) {
+            // Deal with IP-level options
+            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                match options.ip.set_option(option, &self.kind) {
+                    // Deal with IPv6-level options
+                    Err(err)
+                        if err.error() == Errno::ENOPROTOOPT
+                            && self.ip_version == IpVersion::Ipv6 =>
+                    {
+                        options.ipv6.set_option(option, &*inner)
+                    }
+                    result => result,
+                }
+            }
+            result => result,
+        };
+
+        match result {
+            Err(e) => Err(e),
+            Ok(need_iface_poll) => {
+                let iface_to_poll = need_iface_poll
+                    .then(|| match &*inner {
+                        Inner::Unbound(_) => None,
+                        Inner::Bound(bound_raw) => Some(bound_raw.iface().clone()),
+                    })
+                    .flatten();
+
+                drop(inner);
+                drop(options);
+
+                if let Some(iface) = iface_to_poll {
+                    iface.poll();
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl SetSocketLevelOption for Inner<UnboundRaw, BoundRaw> {}
+
+impl SetIpLevelOption for RawSocketKind {
+    fn set_hdrincl(&self, _hdrincl: bool) -> Result<()> {
+        match self {
+            RawSocketKind::Raw(_) => Ok(()),
+            RawSocketKind::Ping => return_errno_with_message!(
+                Errno::ENOPROTOOPT,
+                "IP_HDRINCL cannot be set on ICMP echo sockets"
+            ),
+        }
+    }
+}
+
+impl SetIpv6LevelOption for Inner<UnboundRaw, BoundRaw> {
+    fn set_v6only(&self, _v6only: bool) -> Result<()> {
+        match self {
+            Inner::Unbound(_) => Ok(()),
+            Inner::Bound(_) => return_errno_with_message!(
+                Errno::EINVAL,
+                "IPV6_V6ONLY cannot be set after the socket is bound"
+            ),
+        }
+    }
+}
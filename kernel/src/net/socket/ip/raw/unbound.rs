@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::{IpEndpoint, IpProtocol};
+
+use super::{bound::BoundRaw, RawSocketKind};
+use crate::{
+    events::IoEvents,
+    net::{
+        iface::IpRawSocket,
+        socket::{
+            ip::{
+                common::{get_ephemeral_endpoint, get_iface_to_bind},
+                datagram::observer::DatagramObserver,
+            },
+            util::datagram_common,
+        },
+    },
+    prelude::*,
+    process::signal::Pollee,
+};
+
+pub(super) struct UnboundRaw {
+    kind: RawSocketKind,
+}
+
+impl UnboundRaw {
+    pub(super) fn new(kind: RawSocketKind) -> Self {
+        Self { kind }
+    }
+}
+
+impl datagram_common::Unbound for UnboundRaw {
+    type Endpoint = IpEndpoint;
+    type BindOptions = ();
+
+    type Bound = BoundRaw;
+
+    fn bind(
+        &mut self,
+        endpoint: &Self::Endpoint,
+        pollee: &Pollee,
+        _options: (),
+    ) -> Result<Self::Bound> {
+        let Some(iface) = get_iface_to_bind(&endpoint.addr) else {
+            return_errno_with_message!(
+                Errno::EADDRNOTAVAIL,
+                "the address is not available from the local machine"
+            );
+        };
+
+        let ip_version = endpoint.addr.version();
+        let observer = DatagramObserver::new(pollee.clone());
+
+        let bound_socket = match self.kind {
+            RawSocketKind::Raw(protocol) => {
+                IpRawSocket::new_raw(iface, ip_version, IpProtocol::from(protocol), observer)
+            }
+            RawSocketKind::Ping => {
+                // For ICMP echo sockets, the port is the identifier of echo messages.
+                let ident = (endpoint.port != 0).then_some(endpoint.port);
+                IpRawSocket::new_ping(iface, ip_version, ident, observer)?
+            }
+        };
+
+        Ok(BoundRaw::new(bound_socket))
+    }
+
+    fn bind_ephemeral(
+        &mut self,
+        remote_endpoint: &Self::Endpoint,
+        pollee: &Pollee,
+    ) -> Result<Self::Bound> {
+        let endpoint = get_ephemeral_endpoint(remote_endpoint)?;
+        self.bind(&endpoint, pollee, ())
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        IoEvents::OUT
+    }
+}
@@ -31,6 +31,16 @@ impl ConnectingStream {
         option: &RawTcpOption,
         observer: StreamObserver,
     ) -> core::result::Result<Self, (Error, BoundPort)> {
+        if remote_endpoint.addr.version() != bound_port.ip_version() {
+            return Err((
+                Error::with_message(
+                    Errno::ENETUNREACH,
+                    "the remote address is in a different IP version",
+                ),
+                bound_port,
+            ));
+        }
+
         let tcp_conn =
             match TcpConnection::new_connect(bound_port, remote_endpoint, option, observer) {
                 Ok(tcp_conn) => tcp_conn,
@@ -5,7 +5,10 @@ use core::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use aster_bigtcp::{socket::RawTcpOption, wire::IpEndpoint};
+use aster_bigtcp::{
+    socket::RawTcpOption,
+    wire::{IpEndpoint, IpVersion, Ipv6Address},
+};
 
 use super::{connecting::ConnectingStream, listen::ListenStream, observer::StreamObserver};
 use crate::{
@@ -13,7 +16,7 @@ use crate::{
     net::{
         iface::BoundPort,
         socket::{
-            ip::common::{bind_port, get_ephemeral_endpoint},
+            ip::common::{bind_dual_stack_port, bind_port, get_ephemeral_endpoint},
             util::SocketAddr,
         },
     },
@@ -22,6 +25,11 @@ use crate::{
 
 pub(super) struct InitStream {
     bound_port: Option<BoundPort>,
+    /// The IPv4 port of a dual-stack socket, if any.
+    ///
+    /// It shares the same port number with `bound_port` and serves the IPv4 peers.
+    ipv4_bound_port: Option<BoundPort>,
+    is_dual_stack: bool,
     /// Indicates if the last `connect()` is considered to be done.
     ///
     /// If `connect()` is called but we're still in the `InitStream`, this means that the
@@ -48,6 +56,8 @@ impl InitStream {
     pub(super) fn new() -> Self {
         Self {
             bound_port: None,
+            ipv4_bound_port: None,
+            is_dual_stack: false,
             is_connect_done: true,
             is_conn_refused: AtomicBool::new(false),
         }
@@ -56,6 +66,8 @@ impl InitStream {
     pub(super) fn new_bound(bound_port: BoundPort) -> Self {
         Self {
             bound_port: Some(bound_port),
+            ipv4_bound_port: None,
+            is_dual_stack: false,
             is_connect_done: true,
             is_conn_refused: AtomicBool::new(false),
         }
@@ -64,23 +76,37 @@ impl InitStream {
     pub(super) fn new_refused(bound_port: BoundPort) -> Self {
         Self {
             bound_port: Some(bound_port),
+            ipv4_bound_port: None,
+            is_dual_stack: false,
             is_connect_done: false,
             is_conn_refused: AtomicBool::new(true),
         }
     }
 
-    pub(super) fn bind(&mut self, endpoint: &IpEndpoint, can_reuse: bool) -> Result<()> {
+    pub(super) fn bind(
+        &mut self,
+        endpoint: &IpEndpoint,
+        can_reuse: bool,
+        is_dual_stack: bool,
+    ) -> Result<()> {
         if self.bound_port.is_some() {
             return_errno_with_message!(Errno::EINVAL, "the socket is already bound to an address");
         }
 
-        self.bound_port = Some(bind_port(endpoint, can_reuse)?);
+        if is_dual_stack {
+            let (bound_port, ipv4_bound_port) = bind_dual_stack_port(endpoint.port, can_reuse)?;
+            self.bound_port = Some(bound_port);
+            self.ipv4_bound_port = ipv4_bound_port;
+            self.is_dual_stack = true;
+        } else {
+            self.bound_port = Some(bind_port(endpoint, can_reuse)?);
+        }
 
         Ok(())
     }
 
     pub(super) fn connect(
-        self,
+        mut self,
         remote_endpoint: &IpEndpoint,
         option: &RawTcpOption,
         observer: StreamObserver,
@@ -90,10 +116,22 @@ impl InitStream {
             "`finish_last_connect()` should be called before calling `connect()`"
         );
 
-        let bound_port = if let Some(bound_port) = self.bound_port {
+        // A dual-stack socket connects to IPv4 peers via its IPv4 port.
+        let is_ipv4_peer =
+            remote_endpoint.addr.version() == IpVersion::Ipv4 && self.ipv4_bound_port.is_some();
+        let bound_port = if is_ipv4_peer {
+            self.ipv4_bound_port.take()
+        } else {
+            self.bound_port.take()
+        };
+
+        let bound_port = if let Some(bound_port) = bound_port {
             bound_port
         } else {
-            let endpoint = get_ephemeral_endpoint(remote_endpoint);
+            let endpoint = match get_ephemeral_endpoint(remote_endpoint) {
+                Ok(endpoint) => endpoint,
+                Err(err) => return Err((err, self)),
+            };
             match bind_port(&endpoint, false) {
                 Ok(bound_port) => bound_port,
                 Err(err) => return Err((err, self)),
@@ -102,11 +140,17 @@ impl InitStream {
 
         ConnectingStream::new(bound_port, *remote_endpoint, option, observer).map_err(
             |(err, bound_port)| {
-                if err.error() == Errno::ECONNREFUSED {
-                    (err, InitStream::new_refused(bound_port))
+                if is_ipv4_peer {
+                    self.ipv4_bound_port = Some(bound_port);
                 } else {
-                    (err, InitStream::new_bound(bound_port))
+                    self.bound_port = Some(bound_port);
                 }
+
+                let is_conn_refused = err.error() == Errno::ECONNREFUSED;
+                self.is_connect_done = !is_conn_refused;
+                *self.is_conn_refused.get_mut() = is_conn_refused;
+
+                (err, self)
             },
         )
     }
@@ -158,9 +202,23 @@ impl InitStream {
             ));
         };
 
-        match ListenStream::new(bound_port, backlog, option, observer) {
+        match ListenStream::new(
+            bound_port,
+            self.ipv4_bound_port,
+            self.is_dual_stack,
+            backlog,
+            option,
+            observer,
+        ) {
             Ok(listen_stream) => Ok(listen_stream),
-            Err((bound_port, error)) => Err((error, Self::new_bound(bound_port))),
+            Err((bound_port, ipv4_bound_port, error)) => Err((
+                error,
+                Self {
+                    ipv4_bound_port,
+                    is_dual_stack: self.is_dual_stack,
+                    ..Self::new_bound(bound_port)
+                },
+            )),
         }
     }
 
@@ -192,9 +250,16 @@ impl InitStream {
     }
 
     pub(super) fn local_endpoint(&self) -> Option<IpEndpoint> {
-        self.bound_port
-            .as_ref()
-            .map(|bound_port| bound_port.endpoint().unwrap())
+        let local_endpoint = self.bound_port.as_ref()?.endpoint().unwrap();
+
+        if self.is_dual_stack {
+            Some(IpEndpoint::new(
+                Ipv6Address::UNSPECIFIED.into(),
+                local_endpoint.port,
+            ))
+        } else {
+            Some(local_endpoint)
+        }
     }
 
     pub(super) fn check_io_events(&self) -> IoEvents {
@@ -3,7 +3,7 @@
 use aster_bigtcp::{
     errors::tcp::ListenError,
     socket::{RawTcpOption, RawTcpSetOption},
-    wire::IpEndpoint,
+    wire::{IpEndpoint, Ipv6Address},
 };
 
 use super::{connected::ConnectedStream, observer::StreamObserver};
@@ -15,40 +15,79 @@ use crate::{
 
 pub(super) struct ListenStream {
     tcp_listener: TcpListener,
+    /// The IPv4 listener of a dual-stack socket, if any.
+    ///
+    /// It shares the same port number with `tcp_listener` and accepts the IPv4 peers.
+    ipv4_tcp_listener: Option<TcpListener>,
+    is_dual_stack: bool,
 }
 
 impl ListenStream {
+    /// Listens at the bound ports.
+    ///
+    /// On failure, the bound ports are returned. The IPv4 port of a dual-stack socket is lost if
+    /// the IPv4 listener is created but the IPv6 listener fails to be created.
     pub(super) fn new(
         bound_port: BoundPort,
+        ipv4_bound_port: Option<BoundPort>,
+        is_dual_stack: bool,
         backlog: usize,
         option: &RawTcpOption,
         observer: StreamObserver,
-    ) -> core::result::Result<Self, (BoundPort, Error)> {
+    ) -> core::result::Result<Self, (BoundPort, Option<BoundPort>, Error)> {
         const SOMAXCONN: usize = 4096;
         let max_conn = SOMAXCONN.min(backlog);
 
-        match TcpListener::new_listen(bound_port, max_conn, option, observer) {
-            Ok(tcp_listener) => Ok(Self { tcp_listener }),
-            Err((bound_port, ListenError::AddressInUse)) => Err((
-                bound_port,
-                Error::with_message(Errno::EADDRINUSE, "listener key conflicts"),
-            )),
-            Err((_, err)) => {
-                unreachable!("`new_listen` fails with {:?}, which should not happen", err)
+        let ipv4_tcp_listener = match ipv4_bound_port {
+            Some(ipv4_bound_port) => {
+                match new_listen(ipv4_bound_port, max_conn, option, observer.clone()) {
+                    Ok(ipv4_tcp_listener) => Some(ipv4_tcp_listener),
+                    Err((ipv4_bound_port, error)) => {
+                        return Err((bound_port, Some(ipv4_bound_port), error));
+                    }
+                }
             }
-        }
+            None => None,
+        };
+
+        let tcp_listener = match new_listen(bound_port, max_conn, option, observer) {
+            Ok(tcp_listener) => tcp_listener,
+            Err((bound_port, error)) => {
+                if let Some(ipv4_tcp_listener) = ipv4_tcp_listener {
+                    ipv4_tcp_listener.close();
+                    ipv4_tcp_listener.iface().poll();
+                }
+                return Err((bound_port, None, error));
+            }
+        };
+
+        Ok(Self {
+            tcp_listener,
+            ipv4_tcp_listener,
+            is_dual_stack,
+        })
     }
 
     pub(super) fn try_accept(&self) -> Result<ConnectedStream> {
-        let (new_conn, remote_endpoint) = self.tcp_listener.accept().ok_or_else(|| {
-            Error::with_message(Errno::EAGAIN, "no pending connection is available")
-        })?;
+        let (new_conn, remote_endpoint) = self
+            .tcp_listener
+            .accept()
+            .or_else(|| self.ipv4_tcp_listener.as_ref()?.accept())
+            .ok_or_else(|| {
+                Error::with_message(Errno::EAGAIN, "no pending connection is available")
+            })?;
 
         Ok(ConnectedStream::new(new_conn, remote_endpoint, false))
     }
 
     pub(super) fn local_endpoint(&self) -> IpEndpoint {
-        self.tcp_listener.local_endpoint().unwrap()
+        let local_endpoint = self.tcp_listener.local_endpoint().unwrap();
+
+        if self.is_dual_stack {
+            IpEndpoint::new(Ipv6Address::UNSPECIFIED.into(), local_endpoint.port)
+        } else {
+            local_endpoint
+        }
     }
 
     pub(super) fn iface(&self) -> &Arc<Iface> {
@@ -56,7 +95,11 @@ impl ListenStream {
     }
 
     pub(super) fn check_io_events(&self) -> IoEvents {
-        let can_accept = self.tcp_listener.can_accept();
+        let can_accept = self.tcp_listener.can_accept()
+            || self
+                .ipv4_tcp_listener
+                .as_ref()
+                .is_some_and(|ipv4_tcp_listener| ipv4_tcp_listener.can_accept());
 
         // If network packets come in simultaneously, the socket state may change in the middle.
         // However, the current pollee implementation should be able to handle this race condition.
@@ -67,14 +110,36 @@ impl ListenStream {
         }
     }
 
-    pub(super) fn set_raw_option<R>(
-        &self,
-        set_option: impl FnOnce(&dyn RawTcpSetOption) -> R,
-    ) -> R {
+    pub(super) fn set_raw_option<R>(&self, set_option: impl Fn(&dyn RawTcpSetOption) -> R) -> R {
+        if let Some(ipv4_tcp_listener) = self.ipv4_tcp_listener.as_ref() {
+            set_option(ipv4_tcp_listener);
+        }
         set_option(&self.tcp_listener)
     }
 
-    pub(super) fn into_listener(self) -> TcpListener {
-        self.tcp_listener
+    /// Closes the listeners.
+    pub(super) fn close(self) {
+        for tcp_listener in core::iter::once(self.tcp_listener).chain(self.ipv4_tcp_listener) {
+            tcp_listener.close();
+            tcp_listener.iface().poll();
+        }
+    }
+}
+
+fn new_listen(
+    bound_port: BoundPort,
+    max_conn: usize,
+    option: &RawTcpOption,
+    observer: StreamObserver,
+) -> core::result::Result<TcpListener, (BoundPort, Error)> {
+    match TcpListener::new_listen(bound_port, max_conn, option, observer) {
+        Ok(tcp_listener) => Ok(tcp_listener),
+        Err((bound_port, ListenError::AddressInUse)) => Err((
+            bound_port,
+            Error::with_message(Errno::EADDRINUSE, "listener key conflicts"),
+        )),
+        Err((_, err)) => {
+            unreachable!("`new_listen` fails with {:?}, which should not happen", err)
+        }
     }
 }
@@ -4,7 +4,7 @@ use core::sync::atomic::{AtomicBool, Ordering};
 
 use aster_bigtcp::{
     socket::{NeedIfacePoll, RawTcpOption, RawTcpSetOption},
-    wire::IpEndpoint,
+    wire::{IpEndpoint, IpVersion},
 };
 use connected::{close_and_linger, ConnectedStream};
 use connecting::{ConnResult, ConnectingStream};
@@ -20,8 +20,11 @@ use takeable::Takeable;
 use util::{Retrans, TcpOptionSet};
 
 use super::{
-    addr::UNSPECIFIED_LOCAL_ENDPOINT,
-    options::{IpOptionSet, SetIpLevelOption},
+    addr::{
+        is_dual_stack_endpoint, to_local_endpoint, to_remote_endpoint, to_socket_addr,
+        UNSPECIFIED_LOCAL_ENDPOINT,
+    },
+    options::{IpOptionSet, Ipv6OptionSet, SetIpLevelOption, SetIpv6LevelOption},
 };
 use crate::{
     events::IoEvents,
@@ -57,6 +60,7 @@ pub struct StreamSocket {
     state: RwLock<Takeable<State>, PreemptDisabled>,
     options: RwLock<OptionSet>,
 
+    ip_version: IpVersion,
     is_nonblocking: AtomicBool,
     pollee: Pollee,
 }
@@ -76,6 +80,7 @@ enum State {
 struct OptionSet {
     socket: SocketOptionSet,
     ip: IpOptionSet,
+    ipv6: Ipv6OptionSet,
     tcp: TcpOptionSet,
 }
 
@@ -83,8 +88,14 @@ impl OptionSet {
     fn new() -> Self {
         let socket = SocketOptionSet::new_tcp();
         let ip = IpOptionSet::new_tcp();
+        let ipv6 = Ipv6OptionSet::new();
         let tcp = TcpOptionSet::new();
-        OptionSet { socket, ip, tcp }
+        OptionSet {
+            socket,
+            ip,
+            ipv6,
+            tcp,
+        }
     }
 
     fn raw(&self) -> RawTcpOption {
@@ -96,19 +107,29 @@ impl OptionSet {
 }
 
 impl StreamSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    /// Creates a new TCP socket.
+    ///
+    /// The IP version is [`IpVersion::Ipv4`] for `AF_INET` sockets and [`IpVersion::Ipv6`] for
+    /// `AF_INET6` sockets.
+    pub fn new(is_nonblocking: bool, ip_version: IpVersion) -> Arc<Self> {
         let init_stream = InitStream::new();
         Arc::new(Self {
             state: RwLock::new(Takeable::new(State::Init(init_stream))),
             options: RwLock::new(OptionSet::new()),
+            ip_version,
             is_nonblocking: AtomicBool::new(is_nonblocking),
             pollee: Pollee::new(),
         })
     }
 
-    fn new_accepted(connected_stream: ConnectedStream) -> Arc<Self> {
+    fn new_accepted(
+        connected_stream: ConnectedStream,
+        ip_version: IpVersion,
+        ipv6_options: Ipv6OptionSet,
+    ) -> Arc<Self> {
         let options = connected_stream.raw_with(|raw_tcp_socket| {
             let mut options = OptionSet::new();
+            options.ipv6 = ipv6_options;
 
             if raw_tcp_socket.keep_alive().is_some() {
                 options.socket.set_keep_alive(true);
@@ -129,6 +150,7 @@ impl StreamSocket {
         Arc::new(Self {
             options: RwLock::new(options),
             state: RwLock::new(Takeable::new(State::Connected(connected_stream))),
+            ip_version,
             is_nonblocking: AtomicBool::new(false),
             pollee,
         })
@@ -292,12 +314,19 @@ impl StreamSocket {
             return_errno_with_message!(Errno::EINVAL, "the socket is not listening");
         };
 
+        let ipv6_options = self.options.read().ipv6;
+        let mut iface_to_poll = listen_stream.iface().clone();
         let accepted = listen_stream.try_accept().map(|connected_stream| {
+            // The connection may be accepted by the IPv4 listener of a dual-stack socket.
+            iface_to_poll = connected_stream.iface().clone();
             let remote_endpoint = connected_stream.remote_endpoint();
-            let accepted_socket = Self::new_accepted(connected_stream);
-            (accepted_socket as _, remote_endpoint.into())
+            let accepted_socket =
+                Self::new_accepted(connected_stream, self.ip_version, ipv6_options);
+            (
+                accepted_socket as _,
+                to_socket_addr(remote_endpoint, self.ip_version),
+            )
         });
-        let iface_to_poll = listen_stream.iface().clone();
 
         drop(state);
         self.pollee.invalidate();
@@ -340,7 +369,7 @@ impl StreamSocket {
             iface.poll();
         }
 
-        Ok((recv_bytes, remote_endpoint.into()))
+        Ok((recv_bytes, to_socket_addr(remote_endpoint, self.ip_version)))
     }
 
     fn try_send(&self, reader: &mut dyn MultiRead, flags: SendRecvFlags) -> Result<usize> {
@@ -420,7 +449,9 @@ impl SocketPrivate for StreamSocket {
 
 impl Socket for StreamSocket {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let is_v6only = self.options.read().ipv6.v6only();
+        let endpoint = to_local_endpoint(socket_addr, self.ip_version, is_v6only)?;
+        let is_dual_stack = is_dual_stack_endpoint(&endpoint, is_v6only);
 
         let mut state = self.write_updated_state();
         let State::Init(init_stream) = state.as_mut() else {
@@ -428,11 +459,12 @@ impl Socket for StreamSocket {
         };
 
         let can_reuse = self.options.read().socket.reuse_addr();
-        init_stream.bind(&endpoint, can_reuse)
+        init_stream.bind(&endpoint, can_reuse, is_dual_stack)
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let remote_endpoint = socket_addr.try_into()?;
+        let is_v6only = self.options.read().ipv6.v6only();
+        let remote_endpoint = to_remote_endpoint(socket_addr, self.ip_version, is_v6only)?;
 
         if let Some(result) = self.start_connect(&remote_endpoint) {
             return result;
@@ -514,7 +546,7 @@ impl Socket for StreamSocket {
             State::Listen(listen_stream) => listen_stream.local_endpoint(),
             State::Connected(connected_stream) => connected_stream.local_endpoint(),
         };
-        Ok(local_endpoint.into())
+        Ok(to_socket_addr(local_endpoint, self.ip_version))
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
@@ -526,7 +558,7 @@ impl Socket for StreamSocket {
             State::Connecting(connecting_stream) => connecting_stream.remote_endpoint(),
             State::Connected(connected_stream) => connected_stream.remote_endpoint(),
         };
-        Ok(remote_endpoint.into())
+        Ok(to_socket_addr(remote_endpoint, self.ip_version))
     }
 
     fn sendmsg(
@@ -541,14 +573,14 @@ impl Socket for StreamSocket {
         }
 
         let MessageHeader {
-            control_message, ..
+            control_messages, ..
         } = message_header;
 
         // According to the Linux man pages, `EISCONN` _may_ be returned when the destination
         // address is specified for a connection-mode socket. In practice, the destination address
         // is simply ignored. We follow the same behavior as the Linux implementation to ignore it.
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -574,7 +606,7 @@ impl Socket for StreamSocket {
 
         // According to <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/tcp.c#L2645>,
         // peer address is ignored for connected socket.
-        let message_header = MessageHeader::new(None, None);
+        let message_header = MessageHeader::new(None, Vec::new());
 
         Ok((received_bytes, message_header))
     }
@@ -602,6 +634,14 @@ impl Socket for StreamSocket {
             res => return res,
         }
 
+        // Deal with IPv6-level options
+        if self.ip_version == IpVersion::Ipv6 {
+            match options.ipv6.get_option(option) {
+                Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+                res => return res,
+            }
+        }
+
         // Deal with TCP-level options
         // FIXME: Here we only return the previously set values, without actually
         // asking the underlying sockets for the real, effective values.
@@ -670,8 +710,20 @@ impl Socket for StreamSocket {
                 // Deal with IP-level options
                 match options.ip.set_option(option, state.as_mut()) {
                     Err(err) if err.error() == Errno::ENOPROTOOPT => {
-                        // Deal with TCP-level options
-                        do_tcp_setsockopt(option, &mut options, state.as_mut())?
+                        // Deal with IPv6-level options
+                        match do_ipv6_setsockopt(
+                            option,
+                            self.ip_version,
+                            &mut options,
+                            state.as_mut(),
+                        ) {
+                            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                                // Deal with TCP-level options
+                                do_tcp_setsockopt(option, &mut options, state.as_mut())?
+                            }
+                            Err(err) => return Err(err),
+                            Ok(need_iface_poll) => need_iface_poll,
+                        }
                     }
                     Err(err) => return Err(err),
                     Ok(need_iface_poll) => need_iface_poll,
@@ -694,6 +746,22 @@ impl Socket for StreamSocket {
     }
 }
 
+fn do_ipv6_setsockopt(
+    option: &dyn SocketOption,
+    ip_version: IpVersion,
+    options: &mut OptionSet,
+    state: &mut State,
+) -> Result<NeedIfacePoll> {
+    if ip_version != IpVersion::Ipv6 {
+        return_errno_with_message!(
+            Errno::ENOPROTOOPT,
+            "IPv6-level options cannot be set on IPv4 sockets"
+        );
+    }
+
+    options.ipv6.set_option(option, state)
+}
+
 fn do_tcp_setsockopt(
     option: &dyn SocketOption,
     options: &mut OptionSet,
@@ -779,7 +847,7 @@ impl State {
     ///
     /// For listening sockets, socket options are inherited by new connections. However, they are
     /// not updated for connections in the backlog queue.
-    fn set_raw_option<R>(&self, set_option: impl FnOnce(&dyn RawTcpSetOption) -> R) -> Option<R> {
+    fn set_raw_option<R>(&self, set_option: impl Fn(&dyn RawTcpSetOption) -> R) -> Option<R> {
         match self {
             State::Init(_) => None,
             State::Connecting(connecting_stream) => {
@@ -824,6 +892,18 @@ impl SetIpLevelOption for State {
     }
 }
 
+impl SetIpv6LevelOption for State {
+    fn set_v6only(&self, _v6only: bool) -> Result<()> {
+        match self {
+            State::Init(init_stream) if init_stream.local_endpoint().is_none() => Ok(()),
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "IPV6_V6ONLY cannot be set after the socket is bound"
+            ),
+        }
+    }
+}
+
 impl Drop for StreamSocket {
     fn drop(&mut self) {
         let state = self.state.get_mut().take();
@@ -833,9 +913,7 @@ impl Drop for StreamSocket {
             State::Connecting(connecting_stream) => connecting_stream.into_connection(),
             State::Connected(connected_stream) => connected_stream.into_connection(),
             State::Listen(listen_stream) => {
-                let listener = listen_stream.into_listener();
-                listener.close();
-                listener.iface().poll();
+                listen_stream.close();
                 return;
             }
         };
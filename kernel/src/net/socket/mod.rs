@@ -15,6 +15,7 @@ use crate::{
 pub mod ip;
 pub mod netlink;
 pub mod options;
+pub mod packet;
 pub mod unix;
 pub mod util;
 pub mod vsock;
@@ -139,10 +140,7 @@ impl<T: Socket + 'static> FileLike for T {
         // TODO: Set correct flags
         self.sendmsg(
             reader,
-            MessageHeader {
-                addr: None,
-                control_message: None,
-            },
+            MessageHeader::new(None, Vec::new()),
             SendRecvFlags::empty(),
         )
     }
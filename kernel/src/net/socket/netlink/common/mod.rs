@@ -8,6 +8,7 @@ use unbound::UnboundNetlink;
 use super::{GroupIdSet, NetlinkSocketAddr};
 use crate::{
     events::IoEvents,
+    ipc::mqueue,
     match_sock_option_ref,
     net::socket::{
         netlink::{table::SupportedNetlinkProtocol, AddMembership, DropMembership},
@@ -68,12 +69,24 @@ where
         Ok(sent_bytes)
     }
 
+    /// Notifies the waiters that something is ready to be received.
+    pub(crate) fn notify_readable(&self) {
+        self.pollee.notify(IoEvents::IN);
+    }
+
     // FIXME: This method is marked as `pub(super)` because it's invoked during kernel mode testing.
     pub(super) fn try_recv(
         &self,
         writer: &mut dyn MultiWrite,
         flags: SendRecvFlags,
     ) -> Result<(usize, SocketAddr)> {
+        // The cookies of POSIX message queue notifications can be received even if the socket
+        // is not bound.
+        if let Some(result) = mqueue::recv_cookie(self, writer, flags) {
+            self.pollee.invalidate();
+            return Ok((result?, NetlinkSocketAddr::new_unspecified().into()));
+        }
+
         let recv_bytes = self
             .inner
             .read()
@@ -129,7 +142,8 @@ where
     ) -> Result<usize> {
         let MessageHeader {
             addr,
-            control_message,
+            control_messages,
+            ..
         } = message_header;
 
         let remote = match addr {
@@ -137,7 +151,7 @@ where
             Some(addr) => Some(addr.try_into()?),
         };
 
-        if control_message.is_some() {
+        if !control_messages.is_empty() {
             // TODO: Support sending control message
             warn!("sending control message is not supported");
         }
@@ -160,7 +174,7 @@ where
 
         // TODO: Receive control message
 
-        let message_header = MessageHeader::new(Some(addr), None);
+        let message_header = MessageHeader::new(Some(addr), Vec::new());
 
         Ok((received_len, message_header))
     }
@@ -197,8 +211,13 @@ where
     BoundNetlink<P::Message>: Bound<Endpoint = NetlinkSocketAddr>,
 {
     fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
-        self.pollee
-            .poll_with(mask, poller, || self.inner.read().check_io_events())
+        self.pollee.poll_with(mask, poller, || {
+            let mut events = self.inner.read().check_io_events();
+            if mqueue::has_cookie(self) {
+                events |= IoEvents::IN;
+            }
+            events
+        })
     }
 }
 
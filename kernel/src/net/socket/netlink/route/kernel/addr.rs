@@ -4,12 +4,19 @@
 
 use core::num::NonZeroU32;
 
-use super::util::finish_response;
+use aster_bigtcp::{
+    errors::IfaceConfigError,
+    wire::{IpAddress, IpCidr},
+};
+
+use super::util::{find_iface_by_index, finish_response, is_family_matched, notify, RtnlGroup};
 use crate::{
     net::{
         iface::{iter_all_ifaces, Iface},
         socket::netlink::{
-            message::{CMsgSegHdr, CSegmentType, GetRequestFlags, SegHdrCommonFlags},
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
             route::message::{
                 AddrAttr, AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope, RtnlSegment,
             },
@@ -28,9 +35,24 @@ pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegme
         return_errno_with_message!(Errno::EOPNOTSUPP, "GETADDR only supports dump requests");
     }
 
+    let family = request_segment.body().family;
+
     let mut response_segments: Vec<RtnlSegment> = iter_all_ifaces()
         // GETADDR only supports dump mode, so we're going to report all addresses.
-        .filter_map(|iface| iface_to_new_addr(request_segment.header(), iface))
+        .flat_map(|iface| {
+            iface
+                .ip_addrs()
+                .into_iter()
+                .filter(move |ip_cidr| is_family_matched(family, ip_cidr.address()))
+                .map(move |ip_cidr| {
+                    addr_to_segment(
+                        request_segment.header(),
+                        CSegmentType::NEWADDR,
+                        &iface,
+                        ip_cidr,
+                    )
+                })
+        })
         .map(RtnlSegment::NewAddr)
         .collect();
 
@@ -39,30 +61,147 @@ pub(super) fn do_get_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegme
     Ok(response_segments)
 }
 
-fn iface_to_new_addr(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> Option<AddrSegment> {
-    let ipv4_addr = iface.ipv4_addr()?;
+pub(super) fn do_new_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegment>> {
+    let (iface, ip_cidr) = parse_addr_request(request_segment)?;
+
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+    if flags.contains(NewRequestFlags::REPLACE)
+        && !flags.contains(NewRequestFlags::EXCL)
+        && iface.ip_addrs().contains(&ip_cidr)
+    {
+        // Like Linux, replacing an existing address succeeds. There is nothing to update since
+        // the address lifetimes and flags are not supported.
+        return Ok(Vec::new());
+    }
+
+    iface.add_ip_addr(ip_cidr).map_err(|err| match err {
+        IfaceConfigError::Exists => {
+            Error::with_message(Errno::EEXIST, "the address already exists")
+        }
+        IfaceConfigError::TableFull => {
+            Error::with_message(Errno::ENOSPC, "the iface has too many addresses")
+        }
+        IfaceConfigError::NotFound => unreachable!(),
+    })?;
+
+    let segment = addr_to_segment(
+        request_segment.header(),
+        CSegmentType::NEWADDR,
+        &iface,
+        ip_cidr,
+    );
+    notify(group_of(ip_cidr.address()), RtnlSegment::NewAddr(segment));
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_addr(request_segment: &AddrSegment) -> Result<Vec<RtnlSegment>> {
+    let (iface, ip_cidr) = parse_addr_request(request_segment)?;
+
+    iface.remove_ip_addr(ip_cidr).map_err(|err| match err {
+        IfaceConfigError::NotFound => {
+            Error::with_message(Errno::EADDRNOTAVAIL, "the address does not exist")
+        }
+        IfaceConfigError::Exists | IfaceConfigError::TableFull => unreachable!(),
+    })?;
+
+    let segment = addr_to_segment(
+        request_segment.header(),
+        CSegmentType::DELADDR,
+        &iface,
+        ip_cidr,
+    );
+    notify(group_of(ip_cidr.address()), RtnlSegment::DelAddr(segment));
+
+    Ok(Vec::new())
+}
+
+/// Parses the iface and the address in a NEWADDR or DELADDR request.
+fn parse_addr_request(request_segment: &AddrSegment) -> Result<(Arc<Iface>, IpCidr)> {
+    let body = request_segment.body();
 
+    let Some(index) = body.index else {
+        return_errno_with_message!(Errno::ENODEV, "the interface index is not specified");
+    };
+    let iface = find_iface_by_index(index.get())?;
+
+    // Like Linux, `IFA_LOCAL` takes precedence over `IFA_ADDRESS`. The two attributes differ only
+    // for point-to-point links.
+    let mut local = None;
+    let mut address = None;
+    for attr in request_segment.attrs() {
+        match attr {
+            AddrAttr::Local(addr) => local = Some(*addr),
+            AddrAttr::Address(addr) => address = Some(*addr),
+            AddrAttr::Label(_) => (),
+        }
+    }
+    let Some(addr) = local.or(address) else {
+        return_errno_with_message!(Errno::EINVAL, "the address is not specified");
+    };
+    let addr = IpAddress::from(addr);
+
+    let max_prefix_len = match addr {
+        IpAddress::Ipv4(_) if body.family == CSocketAddrFamily::AF_INET as i32 => 32,
+        IpAddress::Ipv6(_) if body.family == CSocketAddrFamily::AF_INET6 as i32 => 128,
+        _ => return_errno_with_message!(Errno::EINVAL, "the address family is invalid"),
+    };
+    if body.prefix_len > max_prefix_len {
+        return_errno_with_message!(Errno::EINVAL, "the prefix length is invalid");
+    }
+
+    Ok((iface, IpCidr::new(addr, body.prefix_len)))
+}
+
+fn addr_to_segment(
+    request_header: &CMsgSegHdr,
+    type_: CSegmentType,
+    iface: &Arc<Iface>,
+    ip_cidr: IpCidr,
+) -> AddrSegment {
     let header = CMsgSegHdr {
         len: 0,
-        type_: CSegmentType::NEWADDR as _,
+        type_: type_ as _,
         flags: SegHdrCommonFlags::empty().bits(),
         seq: request_header.seq,
         pid: request_header.pid,
     };
 
+    let (family, scope) = match ip_cidr.address() {
+        IpAddress::Ipv4(addr) if addr.is_loopback() => (CSocketAddrFamily::AF_INET, RtScope::HOST),
+        IpAddress::Ipv4(_) => (CSocketAddrFamily::AF_INET, RtScope::UNIVERSE),
+        IpAddress::Ipv6(addr) if addr.is_loopback() => (CSocketAddrFamily::AF_INET6, RtScope::HOST),
+        IpAddress::Ipv6(addr) if addr.segments()[0] & 0xffc0 == 0xfe80 => {
+            (CSocketAddrFamily::AF_INET6, RtScope::LINK)
+        }
+        IpAddress::Ipv6(_) => (CSocketAddrFamily::AF_INET6, RtScope::UNIVERSE),
+    };
+
     let addr_message = AddrSegmentBody {
-        family: CSocketAddrFamily::AF_INET as _,
-        prefix_len: iface.prefix_len().unwrap(),
+        family: family as _,
+        prefix_len: ip_cidr.prefix_len(),
         flags: AddrMessageFlags::PERMANENT,
-        scope: RtScope::HOST,
+        scope,
         index: NonZeroU32::new(iface.index()),
     };
 
-    let attrs = vec![
-        AddrAttr::Address(ipv4_addr.octets()),
-        AddrAttr::Label(CString::new(iface.name()).unwrap()),
-        AddrAttr::Local(ipv4_addr.octets()),
-    ];
+    let addr = ip_cidr.address().into();
+    let attrs = match family {
+        CSocketAddrFamily::AF_INET => vec![
+            AddrAttr::Address(addr),
+            AddrAttr::Label(CString::new(iface.name()).unwrap()),
+            AddrAttr::Local(addr),
+        ],
+        // Like Linux, IPv6 addresses have neither local addresses nor labels.
+        _ => vec![AddrAttr::Address(addr)],
+    };
+
+    AddrSegment::new(header, addr_message, attrs)
+}
 
-    Some(AddrSegment::new(header, addr_message, attrs))
+fn group_of(addr: IpAddress) -> RtnlGroup {
+    match addr {
+        IpAddress::Ipv4(_) => RtnlGroup::IPV4_IFADDR,
+        IpAddress::Ipv6(_) => RtnlGroup::IPV6_IFADDR,
+    }
 }
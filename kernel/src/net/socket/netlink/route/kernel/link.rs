@@ -4,14 +4,16 @@
 
 use core::num::NonZero;
 
-use aster_bigtcp::iface::InterfaceType;
+use aster_bigtcp::iface::{InterfaceFlags, InterfaceType};
 
-use super::util::finish_response;
+use super::util::{find_iface_by_index, finish_response, notify, RtnlGroup};
 use crate::{
     net::{
         iface::{iter_all_ifaces, Iface},
         socket::netlink::{
-            message::{CMsgSegHdr, CSegmentType, GetRequestFlags, SegHdrCommonFlags},
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
             route::message::{LinkAttr, LinkSegment, LinkSegmentBody, RtnlSegment},
         },
     },
@@ -29,7 +31,7 @@ pub(super) fn do_get_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegme
             FilterBy::Name(name) => *name == iface.name(),
             FilterBy::Dump => true,
         })
-        .map(|iface| iface_to_new_link(request_segment.header(), iface))
+        .map(|iface| iface_to_new_link(request_segment.header(), &iface))
         .map(RtnlSegment::NewLink)
         .collect();
 
@@ -44,6 +46,87 @@ pub(super) fn do_get_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegme
     Ok(response_segments)
 }
 
+pub(super) fn do_new_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegment>> {
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+    if flags.contains(NewRequestFlags::CREATE) {
+        // TODO: Support creating virtual links (e.g., bridges and VLANs).
+        return_errno_with_message!(Errno::EOPNOTSUPP, "creating links is not supported");
+    }
+
+    // Without `NLM_F_CREATE`, NEWLINK modifies an existing link, just like SETLINK.
+    do_set_link(request_segment)
+}
+
+pub(super) fn do_set_link(request_segment: &LinkSegment) -> Result<Vec<RtnlSegment>> {
+    let body = request_segment.body();
+
+    let required_name = request_segment.attrs().iter().find_map(|attr| {
+        if let LinkAttr::Name(name) = attr {
+            Some(name.to_str().unwrap())
+        } else {
+            None
+        }
+    });
+
+    // `index` takes precedence over `required_name`.
+    let iface = match (body.index, required_name) {
+        (Some(index), _) => find_iface_by_index(index.get())?,
+        (None, Some(name)) => iter_all_ifaces()
+            .find(|iface| iface.name() == name)
+            .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))?,
+        (None, None) => return_errno_with_message!(
+            Errno::EINVAL,
+            "either interface name or index should be specified"
+        ),
+    };
+
+    if body.index.is_some() && required_name.is_some_and(|name| name != iface.name()) {
+        // TODO: Support renaming links.
+        return_errno_with_message!(Errno::EOPNOTSUPP, "renaming links is not supported");
+    }
+
+    let mtu = request_segment.attrs().iter().find_map(|attr| {
+        if let LinkAttr::Mtu(mtu) = attr {
+            Some(*mtu as usize)
+        } else {
+            None
+        }
+    });
+    // Check all arguments before applying any changes.
+    if let Some(mtu) = mtu
+        && !(MIN_MTU..=iface.max_mtu()).contains(&mtu)
+    {
+        return_errno_with_message!(Errno::EINVAL, "the MTU is out of range");
+    }
+
+    // Like Linux, a zero change mask means that all flags should be changed.
+    // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L3021>.
+    let change = if body.change.is_empty() {
+        InterfaceFlags::all()
+    } else {
+        body.change
+    };
+    // TODO: Support changing flags other than `IFF_UP`.
+    if change.contains(InterfaceFlags::UP) {
+        iface.set_up(body.flags.contains(InterfaceFlags::UP));
+    }
+    if let Some(mtu) = mtu {
+        iface.set_mtu(mtu);
+    }
+
+    notify(
+        RtnlGroup::LINK,
+        RtnlSegment::NewLink(iface_to_new_link(request_segment.header(), &iface)),
+    );
+
+    Ok(Vec::new())
+}
+
+/// The minimum MTU, which is required by IPv4.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/if_ether.h#L36>.
+const MIN_MTU: usize = 68;
+
 enum FilterBy<'a> {
     Index(u32),
     Name(&'a str),
@@ -91,22 +174,27 @@ impl<'a> FilterBy<'a> {
 // Reference: <https://docs.kernel.org/userspace-api/netlink/intro.html#strict-checking>.
 
 fn validate_getlink_request(body: &LinkSegmentBody) -> Result<()> {
-    // FIXME: The Linux implementation also checks the `padding` and `change` fields,
-    // but these fields are lost during the conversion of a `CIfInfoMsg` to `LinkSegmentBody`.
-    // We should consider including the `change` field in `LinkSegmentBody`.
+    // FIXME: The Linux implementation also checks the `padding` field,
+    // but the field is lost during the conversion of a `CIfInfoMsg` to `LinkSegmentBody`.
     // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L4043>.
-    if !body.flags.is_empty() || body.type_ != InterfaceType::NETROM {
-        return_errno_with_message!(Errno::EINVAL, "the flags or the type is not valid");
+    if !body.flags.is_empty() || !body.change.is_empty() || body.type_ != InterfaceType::NETROM {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the flags, the change mask or the type is not valid"
+        );
     }
 
     Ok(())
 }
 
 fn validate_dumplink_request(body: &LinkSegmentBody) -> Result<()> {
-    // FIXME: The Linux implementation also checks the `padding` and `change` fields.
+    // FIXME: The Linux implementation also checks the `padding` field.
     // Reference: <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L2378>.
-    if !body.flags.is_empty() || body.type_ != InterfaceType::NETROM {
-        return_errno_with_message!(Errno::EINVAL, "the flags or the type is not valid");
+    if !body.flags.is_empty() || !body.change.is_empty() || body.type_ != InterfaceType::NETROM {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the flags, the change mask or the type is not valid"
+        );
     }
 
     // The check is from <https://elixir.bootlin.com/linux/v6.13/source/net/core/rtnetlink.c#L2383>.
@@ -134,6 +222,7 @@ fn iface_to_new_link(request_header: &CMsgSegHdr, iface: &Arc<Iface>) -> LinkSeg
         type_: iface.type_(),
         index: NonZero::new(iface.index()),
         flags: iface.flags(),
+        change: InterfaceFlags::empty(),
     };
 
     let attrs = vec![
@@ -9,14 +9,16 @@ use super::message::{RtnlMessage, RtnlSegment};
 use crate::{
     net::socket::netlink::{
         addr::PortNum,
-        message::{CSegmentType, ErrorSegment, ProtocolSegment},
+        message::{CSegmentType, ErrorSegment, ProtocolSegment, SegHdrCommonFlags},
         table::{NetlinkRouteProtocol, SupportedNetlinkProtocol},
     },
     prelude::*,
+    process::credentials::capabilities::CapSet,
 };
 
 mod addr;
 mod link;
+mod route;
 mod util;
 
 pub(super) struct NetlinkRouteKernelSocket {
@@ -38,22 +40,41 @@ impl NetlinkRouteKernelSocket {
 
             let segment_type = CSegmentType::try_from(request_header.type_).unwrap();
 
-            let response_segments = match segment {
-                RtnlSegment::GetLink(request_segment) => link::do_get_link(request_segment),
-                RtnlSegment::GetAddr(request_segment) => addr::do_get_addr(request_segment),
-                _ => {
-                    // FIXME: The error is currently silently ignored.
-                    warn!("unsupported request type: {:?}", segment_type);
-                    return;
-                }
+            let response_segments = match check_permission(segment) {
+                Ok(()) => match segment {
+                    RtnlSegment::NewLink(request_segment) => link::do_new_link(request_segment),
+                    RtnlSegment::GetLink(request_segment) => link::do_get_link(request_segment),
+                    RtnlSegment::SetLink(request_segment) => link::do_set_link(request_segment),
+                    RtnlSegment::NewAddr(request_segment) => addr::do_new_addr(request_segment),
+                    RtnlSegment::DelAddr(request_segment) => addr::do_del_addr(request_segment),
+                    RtnlSegment::GetAddr(request_segment) => addr::do_get_addr(request_segment),
+                    RtnlSegment::NewRoute(request_segment) => route::do_new_route(request_segment),
+                    RtnlSegment::DelRoute(request_segment) => route::do_del_route(request_segment),
+                    RtnlSegment::GetRoute(request_segment) => route::do_get_route(request_segment),
+                    _ => {
+                        // FIXME: The error is currently silently ignored.
+                        warn!("unsupported request type: {:?}", segment_type);
+                        return;
+                    }
+                },
+                Err(error) => Err(error),
             };
 
             let response = match response_segments {
-                Ok(segments) => RtnlMessage::new(segments),
-                Err(error) => {
-                    // TODO: Deal with the `NetlinkMessageCommonFlags::ACK` flag.
-                    // Should we return `ErrorSegment` if ACK flag does not exist?
+                Ok(segments) if !segments.is_empty() => RtnlMessage::new(segments),
+                Ok(_) => {
+                    // Requests that have no responses (e.g., NEWADDR) are acknowledged only if the
+                    // `ACK` flag is set.
                     // Reference: <https://docs.kernel.org/userspace-api/netlink/intro.html#netlink-message-types>.
+                    let flags = SegHdrCommonFlags::from_bits_truncate(request_header.flags);
+                    if !flags.contains(SegHdrCommonFlags::ACK) {
+                        continue;
+                    }
+                    let ack_segment = ErrorSegment::new_from_request(request_header, None);
+                    RtnlMessage::new(vec![RtnlSegment::Error(ack_segment)])
+                }
+                Err(error) => {
+                    // Errors are always reported, regardless of the `ACK` flag.
                     let err_segment = ErrorSegment::new_from_request(request_header, Some(error));
                     RtnlMessage::new(vec![RtnlSegment::Error(err_segment)])
                 }
@@ -66,6 +87,29 @@ impl NetlinkRouteKernelSocket {
     }
 }
 
+/// Checks whether the sender is allowed to make the request.
+///
+/// Like Linux, all requests except GET requests change the network configuration, so they
+/// require `CAP_NET_ADMIN`.
+fn check_permission(segment: &RtnlSegment) -> Result<()> {
+    if matches!(
+        segment,
+        RtnlSegment::GetLink(_) | RtnlSegment::GetAddr(_) | RtnlSegment::GetRoute(_)
+    ) {
+        return Ok(());
+    }
+
+    let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+    if !credentials.effective_capset().contains(CapSet::NET_ADMIN) {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "changing the network configuration requires `CAP_NET_ADMIN`"
+        );
+    }
+
+    Ok(())
+}
+
 /// FIXME: NETLINK_ROUTE_KERNEL should be a per-network namespace socket
 static NETLINK_ROUTE_KERNEL: NetlinkRouteKernelSocket = NetlinkRouteKernelSocket::new();
 
@@ -0,0 +1,379 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Handle route-related requests.
+
+use aster_bigtcp::{
+    errors::IfaceConfigError,
+    iface::Route,
+    wire::{IpAddress, IpCidr, Ipv4Address, Ipv6Address},
+};
+
+use super::util::{find_iface_by_index, finish_response, is_family_matched, notify, RtnlGroup};
+use crate::{
+    net::{
+        iface::{iter_all_ifaces, Iface},
+        socket::netlink::{
+            message::{
+                CMsgSegHdr, CSegmentType, GetRequestFlags, NewRequestFlags, SegHdrCommonFlags,
+            },
+            route::message::{
+                route_protocol, route_table, RouteAttr, RouteSegment, RouteSegmentBody, RouteType,
+                RtScope, RtnlSegment,
+            },
+        },
+    },
+    prelude::*,
+    util::net::CSocketAddrFamily,
+};
+
+pub(super) fn do_get_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let dump_all = {
+        let flags = GetRequestFlags::from_bits_truncate(request_segment.header().flags);
+        flags.contains(GetRequestFlags::DUMP)
+    };
+    if !dump_all {
+        return do_lookup_route(request_segment);
+    }
+
+    let family = request_segment.body().family;
+
+    let mut response_segments: Vec<RtnlSegment> = iter_all_ifaces()
+        .flat_map(|iface| iface_to_route_entries(&iface).into_iter())
+        .filter(|entry| is_family_matched(family, entry.dst.address()))
+        .map(|entry| entry.to_segment(request_segment.header(), CSegmentType::NEWROUTE))
+        .map(RtnlSegment::NewRoute)
+        .collect();
+
+    finish_response(request_segment.header(), dump_all, &mut response_segments);
+
+    Ok(response_segments)
+}
+
+/// Looks up the route to the destination in a non-dump GETROUTE request.
+fn do_lookup_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let dst_addr = request_segment.attrs().iter().find_map(|attr| match attr {
+        RouteAttr::Dst(dst) => Some(IpAddress::from(*dst)),
+        _ => None,
+    });
+    let Some(dst_addr) = dst_addr else {
+        return_errno_with_message!(Errno::EINVAL, "the destination is not specified");
+    };
+
+    // Like Linux, the route with the longest prefix wins.
+    let Some(entry) = iter_all_ifaces()
+        .flat_map(|iface| iface_to_route_entries(&iface).into_iter())
+        .filter(|entry| entry.dst.contains_addr(&dst_addr))
+        .max_by_key(|entry| entry.dst.prefix_len())
+    else {
+        return_errno_with_message!(Errno::ENETUNREACH, "no route to the destination");
+    };
+
+    let host_entry = RouteEntry {
+        dst: IpCidr::new(dst_addr, max_prefix_len(dst_addr)),
+        ..entry
+    };
+    let mut response_segments = vec![RtnlSegment::NewRoute(
+        host_entry.to_segment(request_segment.header(), CSegmentType::NEWROUTE),
+    )];
+
+    finish_response(request_segment.header(), false, &mut response_segments);
+
+    Ok(response_segments)
+}
+
+pub(super) fn do_new_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let request = RouteRequest::parse(request_segment)?;
+
+    if request.type_ != RouteType::UNICAST {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "only unicast routes are supported");
+    }
+
+    let Some(gateway) = request.gateway else {
+        // Routes to the subnets of the iface addresses are implicitly added with the addresses.
+        let is_connected = iter_all_ifaces()
+            .filter(|iface| request.oif.is_none_or(|oif| oif == iface.index()))
+            .flat_map(|iface| iface.ip_addrs())
+            .any(|ip_cidr| network_of(ip_cidr) == request.dst);
+        if is_connected {
+            return_errno_with_message!(Errno::EEXIST, "the route already exists");
+        }
+        // FIXME: Support routes without gateways (e.g., `ip route add 10.0.0.0/8 dev eth0`).
+        return_errno_with_message!(
+            Errno::EOPNOTSUPP,
+            "routes without gateways are not supported"
+        );
+    };
+
+    // The gateway must be directly reachable from the iface.
+    let Some(iface) = iter_all_ifaces()
+        .filter(|iface| request.oif.is_none_or(|oif| oif == iface.index()))
+        .find(|iface| {
+            iface
+                .ip_addrs()
+                .iter()
+                .any(|ip_cidr| ip_cidr.contains_addr(&gateway))
+        })
+    else {
+        if let Some(oif) = request.oif {
+            find_iface_by_index(oif)?;
+        }
+        return_errno_with_message!(Errno::ENETUNREACH, "the gateway is unreachable");
+    };
+
+    let flags = NewRequestFlags::from_bits_truncate(request_segment.header().flags);
+    if flags.contains(NewRequestFlags::REPLACE) {
+        // Remove the old route to the same destination, if any.
+        for old_iface in iter_all_ifaces() {
+            let Some(old_route) = old_iface
+                .routes()
+                .into_iter()
+                .find(|old_route| old_route.dst == request.dst)
+            else {
+                continue;
+            };
+            old_iface.remove_route(&old_route).unwrap();
+        }
+    } else if iter_all_ifaces()
+        .flat_map(|iface| iface.routes())
+        .any(|old_route| old_route.dst == request.dst)
+    {
+        return_errno_with_message!(Errno::EEXIST, "the route already exists");
+    }
+
+    let route = Route {
+        dst: request.dst,
+        gateway,
+    };
+    iface.add_route(route).map_err(|err| match err {
+        IfaceConfigError::Exists => Error::with_message(Errno::EEXIST, "the route already exists"),
+        IfaceConfigError::TableFull => {
+            Error::with_message(Errno::ENOSPC, "the iface has too many routes")
+        }
+        IfaceConfigError::NotFound => unreachable!(),
+    })?;
+
+    let entry = RouteEntry::from_route(&iface, route);
+    notify(
+        group_of(route.dst.address()),
+        RtnlSegment::NewRoute(entry.to_segment(request_segment.header(), CSegmentType::NEWROUTE)),
+    );
+
+    Ok(Vec::new())
+}
+
+pub(super) fn do_del_route(request_segment: &RouteSegment) -> Result<Vec<RtnlSegment>> {
+    let request = RouteRequest::parse(request_segment)?;
+
+    let Some((iface, route)) = iter_all_ifaces()
+        .filter(|iface| request.oif.is_none_or(|oif| oif == iface.index()))
+        .flat_map(|iface| {
+            iface
+                .routes()
+                .into_iter()
+                .map(move |route| (iface.clone(), route))
+        })
+        .find(|(_, route)| {
+            route.dst == request.dst && request.gateway.is_none_or(|gw| gw == route.gateway)
+        })
+    else {
+        return_errno_with_message!(Errno::ESRCH, "the route does not exist");
+    };
+
+    iface.remove_route(&route).map_err(|err| match err {
+        IfaceConfigError::NotFound => Error::with_message(Errno::ESRCH, "the route does not exist"),
+        IfaceConfigError::Exists | IfaceConfigError::TableFull => unreachable!(),
+    })?;
+
+    let entry = RouteEntry::from_route(&iface, route);
+    notify(
+        group_of(route.dst.address()),
+        RtnlSegment::DelRoute(entry.to_segment(request_segment.header(), CSegmentType::DELROUTE)),
+    );
+
+    Ok(Vec::new())
+}
+
+/// The parsed content of a NEWROUTE or DELROUTE request.
+struct RouteRequest {
+    type_: RouteType,
+    dst: IpCidr,
+    gateway: Option<IpAddress>,
+    oif: Option<u32>,
+}
+
+impl RouteRequest {
+    fn parse(request_segment: &RouteSegment) -> Result<Self> {
+        let body = request_segment.body();
+
+        let unspecified_addr = if body.family == CSocketAddrFamily::AF_INET as i32 {
+            IpAddress::Ipv4(Ipv4Address::UNSPECIFIED)
+        } else if body.family == CSocketAddrFamily::AF_INET6 as i32 {
+            IpAddress::Ipv6(Ipv6Address::UNSPECIFIED)
+        } else {
+            return_errno_with_message!(Errno::EAFNOSUPPORT, "the address family is not supported");
+        };
+
+        let mut table = body.table as u32;
+        let mut dst_addr = unspecified_addr;
+        let mut gateway = None;
+        let mut oif = None;
+        for attr in request_segment.attrs() {
+            match attr {
+                RouteAttr::Dst(dst) => dst_addr = IpAddress::from(*dst),
+                RouteAttr::Gateway(gw) => gateway = Some(IpAddress::from(*gw)),
+                RouteAttr::Oif(index) => oif = Some(*index),
+                RouteAttr::Table(id) => table = *id,
+                RouteAttr::Priority(_) | RouteAttr::PrefSrc(_) => (),
+            }
+        }
+
+        // TODO: Support multiple routing tables.
+        if table != route_table::UNSPEC as u32 && table != route_table::MAIN as u32 {
+            return_errno_with_message!(Errno::EOPNOTSUPP, "only the main table is supported");
+        }
+
+        let is_same_family = |addr: &IpAddress| {
+            matches!(
+                (addr, unspecified_addr),
+                (IpAddress::Ipv4(_), IpAddress::Ipv4(_)) | (IpAddress::Ipv6(_), IpAddress::Ipv6(_))
+            )
+        };
+        if !is_same_family(&dst_addr) || gateway.as_ref().is_some_and(|gw| !is_same_family(gw)) {
+            return_errno_with_message!(Errno::EINVAL, "the address family does not match");
+        }
+
+        if body.dst_len > max_prefix_len(dst_addr) {
+            return_errno_with_message!(Errno::EINVAL, "the prefix length is invalid");
+        }
+        let dst = IpCidr::new(dst_addr, body.dst_len);
+        if network_of(dst) != dst {
+            return_errno_with_message!(Errno::EINVAL, "the prefix does not match the length");
+        }
+
+        Ok(Self {
+            type_: body.type_,
+            dst,
+            gateway,
+            oif,
+        })
+    }
+}
+
+/// An entry in the routing table.
+#[derive(Clone, Copy)]
+struct RouteEntry {
+    dst: IpCidr,
+    gateway: Option<IpAddress>,
+    pref_src: Option<IpAddress>,
+    oif: u32,
+}
+
+impl RouteEntry {
+    fn from_route(iface: &Arc<Iface>, route: Route) -> Self {
+        Self {
+            dst: route.dst,
+            gateway: Some(route.gateway),
+            pref_src: None,
+            oif: iface.index(),
+        }
+    }
+
+    fn to_segment(self, request_header: &CMsgSegHdr, type_: CSegmentType) -> RouteSegment {
+        let header = CMsgSegHdr {
+            len: 0,
+            type_: type_ as _,
+            flags: SegHdrCommonFlags::empty().bits(),
+            seq: request_header.seq,
+            pid: request_header.pid,
+        };
+
+        let family = match self.dst.address() {
+            IpAddress::Ipv4(_) => CSocketAddrFamily::AF_INET,
+            IpAddress::Ipv6(_) => CSocketAddrFamily::AF_INET6,
+        };
+        // Routes via gateways are installed by users (or during boot), while routes to directly
+        // connected subnets are installed by the kernel.
+        let (protocol, scope) = if self.gateway.is_some() {
+            (route_protocol::BOOT, RtScope::UNIVERSE)
+        } else {
+            (route_protocol::KERNEL, RtScope::LINK)
+        };
+
+        let route_message = RouteSegmentBody {
+            family: family as _,
+            dst_len: self.dst.prefix_len(),
+            src_len: 0,
+            tos: 0,
+            table: route_table::MAIN,
+            protocol,
+            scope,
+            type_: RouteType::UNICAST,
+            flags: 0,
+        };
+
+        let mut attrs = vec![RouteAttr::Table(route_table::MAIN as u32)];
+        // Like Linux, the destination is omitted for default routes.
+        if self.dst.prefix_len() != 0 {
+            attrs.push(RouteAttr::Dst(self.dst.address().into()));
+        }
+        if let Some(pref_src) = self.pref_src {
+            attrs.push(RouteAttr::PrefSrc(pref_src.into()));
+        }
+        if let Some(gateway) = self.gateway {
+            attrs.push(RouteAttr::Gateway(gateway.into()));
+        }
+        attrs.push(RouteAttr::Oif(self.oif));
+
+        RouteSegment::new(header, route_message, attrs)
+    }
+}
+
+/// Returns all routing table entries of the iface.
+///
+/// This includes the routes to the subnets of the iface addresses and the routes via gateways.
+fn iface_to_route_entries(iface: &Arc<Iface>) -> Vec<RouteEntry> {
+    let connected = iface.ip_addrs().into_iter().map(|ip_cidr| RouteEntry {
+        dst: network_of(ip_cidr),
+        gateway: None,
+        pref_src: Some(ip_cidr.address()),
+        oif: iface.index(),
+    });
+    let via_gateway = iface
+        .routes()
+        .into_iter()
+        .map(|route| RouteEntry::from_route(iface, route));
+
+    connected.chain(via_gateway).collect()
+}
+
+/// Returns the subnet that contains the address, with the host bits cleared.
+fn network_of(ip_cidr: IpCidr) -> IpCidr {
+    let prefix_len = ip_cidr.prefix_len() as u32;
+
+    let addr = match ip_cidr.address() {
+        IpAddress::Ipv4(addr) => {
+            let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+            IpAddress::Ipv4(Ipv4Address::from(u32::from(addr) & mask))
+        }
+        IpAddress::Ipv6(addr) => {
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            IpAddress::Ipv6(Ipv6Address::from(u128::from(addr) & mask))
+        }
+    };
+
+    IpCidr::new(addr, ip_cidr.prefix_len())
+}
+
+fn max_prefix_len(addr: IpAddress) -> u8 {
+    match addr {
+        IpAddress::Ipv4(_) => 32,
+        IpAddress::Ipv6(_) => 128,
+    }
+}
+
+fn group_of(addr: IpAddress) -> RtnlGroup {
+    match addr {
+        IpAddress::Ipv4(_) => RtnlGroup::IPV4_ROUTE,
+        IpAddress::Ipv6(_) => RtnlGroup::IPV6_ROUTE,
+    }
+}
@@ -1,11 +1,19 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_bigtcp::wire::IpAddress;
+
 use crate::{
-    net::socket::netlink::{
-        message::{CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
-        route::message::RtnlSegment,
+    net::{
+        iface::{iter_all_ifaces, Iface},
+        socket::netlink::{
+            message::{CMsgSegHdr, DoneSegment, ProtocolSegment, SegHdrCommonFlags},
+            route::message::{RtnlMessage, RtnlSegment},
+            table::{NetlinkRouteProtocol, SupportedNetlinkProtocol},
+            GroupIdSet,
+        },
     },
     prelude::*,
+    util::net::CSocketAddrFamily,
 };
 
 /// Finishes a response message.
@@ -37,3 +45,44 @@ fn add_multi_flag(response_segments: &mut [RtnlSegment]) {
         header.flags = flags.bits();
     }
 }
+
+/// Netlink route multicast groups.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.13/source/include/uapi/linux/rtnetlink.h#L724>.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy)]
+#[expect(clippy::upper_case_acronyms)]
+#[expect(non_camel_case_types)]
+pub enum RtnlGroup {
+    LINK = 1,
+    IPV4_IFADDR = 5,
+    IPV4_ROUTE = 7,
+    IPV6_IFADDR = 9,
+    IPV6_ROUTE = 11,
+}
+
+/// Notifies the listeners of the multicast group about a change.
+pub fn notify(group: RtnlGroup, segment: RtnlSegment) {
+    let dst_groups = GroupIdSet::new(1 << (group as u32 - 1));
+    let message = RtnlMessage::new(vec![segment]);
+
+    if let Err(error) = NetlinkRouteProtocol::multicast(dst_groups, message) {
+        warn!("failed to send netlink route notifications: {:?}", error);
+    }
+}
+
+/// Finds the iface with the index.
+pub fn find_iface_by_index(index: u32) -> Result<Arc<Iface>> {
+    iter_all_ifaces()
+        .find(|iface| iface.index() == index)
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+/// Checks whether the address belongs to the address family in a request.
+pub fn is_family_matched(family: i32, addr: IpAddress) -> bool {
+    match addr {
+        _ if family == CSocketAddrFamily::AF_UNSPEC as i32 => true,
+        IpAddress::Ipv4(_) => family == CSocketAddrFamily::AF_INET as i32,
+        IpAddress::Ipv6(_) => family == CSocketAddrFamily::AF_INET6 as i32,
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::IFNAME_SIZE;
+use super::{IpAddrPayload, IFNAME_SIZE};
 use crate::{
     net::socket::netlink::message::{Attribute, CAttrHeader},
     prelude::*,
@@ -28,10 +28,10 @@ enum AddrAttrClass {
     TARGET_NETNSID = 10,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AddrAttr {
-    Address([u8; 4]),
-    Local([u8; 4]),
+    Address(IpAddrPayload),
+    Local(IpAddrPayload),
     Label(CString),
 }
 
@@ -52,8 +52,8 @@ impl Attribute for AddrAttr {
 
     fn payload_as_bytes(&self) -> &[u8] {
         match self {
-            AddrAttr::Address(address) => address,
-            AddrAttr::Local(local) => local,
+            AddrAttr::Address(address) => address.as_bytes(),
+            AddrAttr::Local(local) => local.as_bytes(),
             AddrAttr::Label(label) => label.as_bytes_with_nul(),
         }
     }
@@ -73,10 +73,12 @@ impl Attribute for AddrAttr {
         };
 
         let res = match (class, payload_len) {
-            (AddrAttrClass::ADDRESS, 4) => {
-                Self::Address(reader.read_val_opt::<[u8; 4]>()?.unwrap())
+            (AddrAttrClass::ADDRESS, 4 | 16) => {
+                Self::Address(IpAddrPayload::read_from(reader, payload_len)?.unwrap())
+            }
+            (AddrAttrClass::LOCAL, 4 | 16) => {
+                Self::Local(IpAddrPayload::read_from(reader, payload_len)?.unwrap())
             }
-            (AddrAttrClass::LOCAL, 4) => Self::Local(reader.read_val_opt::<[u8; 4]>()?.unwrap()),
             (AddrAttrClass::LABEL, 1..=IFNAME_SIZE) => {
                 Self::Label(reader.read_cstring_with_max_len(payload_len)?)
             }
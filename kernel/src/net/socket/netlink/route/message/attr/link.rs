@@ -82,7 +82,7 @@ enum LinkAttrClass {
     PARENT_DEV_BUS_NAME = 57,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LinkAttr {
     Name(CString),
     Mtu(u32),
@@ -1,7 +1,57 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_bigtcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
+
+use crate::{prelude::*, util::MultiRead};
+
 pub mod addr;
 pub mod link;
+pub mod route;
 
 /// The size limit for interface names.
 const IFNAME_SIZE: usize = 16;
+
+/// An IP address as the payload of an attribute, which is in network byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpAddrPayload {
+    V4([u8; 4]),
+    V6([u8; 16]),
+}
+
+impl IpAddrPayload {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            IpAddrPayload::V4(bytes) => bytes,
+            IpAddrPayload::V6(bytes) => bytes,
+        }
+    }
+
+    /// Reads the IP address if the payload length matches that of an IPv4 or IPv6 address.
+    fn read_from(reader: &mut dyn MultiRead, payload_len: usize) -> Result<Option<Self>> {
+        let res = match payload_len {
+            4 => Self::V4(reader.read_val_opt::<[u8; 4]>()?.unwrap()),
+            16 => Self::V6(reader.read_val_opt::<[u8; 16]>()?.unwrap()),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(res))
+    }
+}
+
+impl From<IpAddress> for IpAddrPayload {
+    fn from(value: IpAddress) -> Self {
+        match value {
+            IpAddress::Ipv4(addr) => Self::V4(addr.octets()),
+            IpAddress::Ipv6(addr) => Self::V6(addr.octets()),
+        }
+    }
+}
+
+impl From<IpAddrPayload> for IpAddress {
+    fn from(value: IpAddrPayload) -> Self {
+        match value {
+            IpAddrPayload::V4(bytes) => IpAddress::Ipv4(Ipv4Address::from(bytes)),
+            IpAddrPayload::V6(bytes) => IpAddress::Ipv6(Ipv6Address::from(bytes)),
+        }
+    }
+}
@@ -8,14 +8,17 @@
 mod attr;
 mod segment;
 
-pub(super) use attr::{addr::AddrAttr, link::LinkAttr};
+pub(super) use attr::{addr::AddrAttr, link::LinkAttr, route::RouteAttr, IpAddrPayload};
 pub(super) use segment::{
     addr::{AddrMessageFlags, AddrSegment, AddrSegmentBody, RtScope},
     link::{LinkSegment, LinkSegmentBody},
+    route::{route_protocol, route_table, RouteSegment, RouteSegmentBody, RouteType},
     RtnlSegment,
 };
 
-use crate::net::socket::netlink::message::Message;
+use crate::net::socket::netlink::{message::Message, table::MulticastMessage};
 
 /// A netlink route message.
 pub(in crate::net::socket::netlink) type RtnlMessage = Message<RtnlSegment>;
+
+impl MulticastMessage for RtnlMessage {}
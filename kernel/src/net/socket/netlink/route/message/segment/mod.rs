@@ -36,6 +36,7 @@ pub mod route;
 
 use addr::AddrSegment;
 use link::LinkSegment;
+use route::RouteSegment;
 
 use crate::{
     net::socket::netlink::message::{
@@ -46,12 +47,17 @@ use crate::{
 };
 
 /// The netlink route segment, which is the basic unit of a netlink route message.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RtnlSegment {
     NewLink(LinkSegment),
     GetLink(LinkSegment),
+    SetLink(LinkSegment),
     NewAddr(AddrSegment),
+    DelAddr(AddrSegment),
     GetAddr(AddrSegment),
+    NewRoute(RouteSegment),
+    DelRoute(RouteSegment),
+    GetRoute(RouteSegment),
     Done(DoneSegment),
     Error(ErrorSegment),
 }
@@ -59,12 +65,15 @@ pub enum RtnlSegment {
 impl ProtocolSegment for RtnlSegment {
     fn header(&self) -> &CMsgSegHdr {
         match self {
-            RtnlSegment::NewLink(link_segment) | RtnlSegment::GetLink(link_segment) => {
-                link_segment.header()
-            }
-            RtnlSegment::NewAddr(addr_segment) | RtnlSegment::GetAddr(addr_segment) => {
-                addr_segment.header()
-            }
+            RtnlSegment::NewLink(link_segment)
+            | RtnlSegment::GetLink(link_segment)
+            | RtnlSegment::SetLink(link_segment) => link_segment.header(),
+            RtnlSegment::NewAddr(addr_segment)
+            | RtnlSegment::DelAddr(addr_segment)
+            | RtnlSegment::GetAddr(addr_segment) => addr_segment.header(),
+            RtnlSegment::NewRoute(route_segment)
+            | RtnlSegment::DelRoute(route_segment)
+            | RtnlSegment::GetRoute(route_segment) => route_segment.header(),
             RtnlSegment::Done(done_segment) => done_segment.header(),
             RtnlSegment::Error(error_segment) => error_segment.header(),
         }
@@ -72,12 +81,15 @@ impl ProtocolSegment for RtnlSegment {
 
     fn header_mut(&mut self) -> &mut CMsgSegHdr {
         match self {
-            RtnlSegment::NewLink(link_segment) | RtnlSegment::GetLink(link_segment) => {
-                link_segment.header_mut()
-            }
-            RtnlSegment::NewAddr(addr_segment) | RtnlSegment::GetAddr(addr_segment) => {
-                addr_segment.header_mut()
-            }
+            RtnlSegment::NewLink(link_segment)
+            | RtnlSegment::GetLink(link_segment)
+            | RtnlSegment::SetLink(link_segment) => link_segment.header_mut(),
+            RtnlSegment::NewAddr(addr_segment)
+            | RtnlSegment::DelAddr(addr_segment)
+            | RtnlSegment::GetAddr(addr_segment) => addr_segment.header_mut(),
+            RtnlSegment::NewRoute(route_segment)
+            | RtnlSegment::DelRoute(route_segment)
+            | RtnlSegment::GetRoute(route_segment) => route_segment.header_mut(),
             RtnlSegment::Done(done_segment) => done_segment.header_mut(),
             RtnlSegment::Error(error_segment) => error_segment.header_mut(),
         }
@@ -89,8 +101,21 @@ impl ProtocolSegment for RtnlSegment {
             .ok_or_else(|| Error::with_message(Errno::EINVAL, "the reader length is too small"))?;
 
         let segment = match CSegmentType::try_from(header.type_)? {
+            CSegmentType::NEWLINK => RtnlSegment::NewLink(LinkSegment::read_from(header, reader)?),
             CSegmentType::GETLINK => RtnlSegment::GetLink(LinkSegment::read_from(header, reader)?),
+            CSegmentType::SETLINK => RtnlSegment::SetLink(LinkSegment::read_from(header, reader)?),
+            CSegmentType::NEWADDR => RtnlSegment::NewAddr(AddrSegment::read_from(header, reader)?),
+            CSegmentType::DELADDR => RtnlSegment::DelAddr(AddrSegment::read_from(header, reader)?),
             CSegmentType::GETADDR => RtnlSegment::GetAddr(AddrSegment::read_from(header, reader)?),
+            CSegmentType::NEWROUTE => {
+                RtnlSegment::NewRoute(RouteSegment::read_from(header, reader)?)
+            }
+            CSegmentType::DELROUTE => {
+                RtnlSegment::DelRoute(RouteSegment::read_from(header, reader)?)
+            }
+            CSegmentType::GETROUTE => {
+                RtnlSegment::GetRoute(RouteSegment::read_from(header, reader)?)
+            }
             _ => return_errno_with_message!(Errno::EINVAL, "unsupported segment type"),
         };
 
@@ -100,11 +125,19 @@ impl ProtocolSegment for RtnlSegment {
     fn write_to(&self, writer: &mut dyn MultiWrite) -> Result<()> {
         match self {
             RtnlSegment::NewLink(link_segment) => link_segment.write_to(writer)?,
-            RtnlSegment::NewAddr(addr_segment) => addr_segment.write_to(writer)?,
+            RtnlSegment::NewAddr(addr_segment) | RtnlSegment::DelAddr(addr_segment) => {
+                addr_segment.write_to(writer)?
+            }
+            RtnlSegment::NewRoute(route_segment) | RtnlSegment::DelRoute(route_segment) => {
+                route_segment.write_to(writer)?
+            }
             RtnlSegment::Done(done_segment) => done_segment.write_to(writer)?,
             RtnlSegment::Error(error_segment) => error_segment.write_to(writer)?,
-            RtnlSegment::GetAddr(_) | RtnlSegment::GetLink(_) => {
-                unreachable!("kernel should not write get requests to user space");
+            RtnlSegment::GetAddr(_)
+            | RtnlSegment::GetLink(_)
+            | RtnlSegment::SetLink(_)
+            | RtnlSegment::GetRoute(_) => {
+                unreachable!("kernel should not write get or set requests to user space");
             }
         }
         Ok(())
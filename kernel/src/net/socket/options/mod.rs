@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::util::LingerOption;
+use super::{
+    unix::UCred,
+    util::{LingerOption, SocketFilter},
+};
 use crate::{impl_socket_options, prelude::*};
 
 mod macros;
@@ -20,4 +23,8 @@ impl_socket_options!(
     pub struct Error(Option<crate::error::Error>);
     pub struct Linger(LingerOption);
     pub struct KeepAlive(bool);
+    pub struct PassCred(bool);
+    pub struct PeerCred(UCred);
+    pub struct AttachFilter(SocketFilter);
+    pub struct DetachFilter(u32);
 );
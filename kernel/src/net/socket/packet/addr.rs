@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_bigtcp::wire::EthernetAddress;
+
+use crate::{net::socket::util::SocketAddr, prelude::*};
+
+/// The address of a packet socket (i.e., the kernel counterpart of `struct sockaddr_ll`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketSocketAddr {
+    /// The link-layer protocol in host byte order.
+    pub protocol: u16,
+    /// The interface index, where zero means any interface.
+    pub ifindex: u32,
+    /// The ARP hardware type of the interface.
+    pub hatype: u16,
+    /// The packet type.
+    pub pkttype: u8,
+    /// The length of the hardware address.
+    pub halen: u8,
+    /// The hardware address.
+    pub addr: [u8; 8],
+}
+
+impl PacketSocketAddr {
+    /// Returns the hardware address as an Ethernet address, if it has one.
+    pub(super) fn ether_addr(&self) -> Option<EthernetAddress> {
+        if (self.halen as usize) < 6 {
+            return None;
+        }
+        Some(EthernetAddress::from_bytes(&self.addr[..6]))
+    }
+}
+
+impl TryFrom<SocketAddr> for PacketSocketAddr {
+    type Error = Error;
+
+    fn try_from(value: SocketAddr) -> Result<Self> {
+        let SocketAddr::Packet(packet_addr) = value else {
+            return_errno_with_message!(Errno::EINVAL, "invalid packet socket addr");
+        };
+        Ok(packet_addr)
+    }
+}
+
+impl From<PacketSocketAddr> for SocketAddr {
+    fn from(value: PacketSocketAddr) -> Self {
+        SocketAddr::Packet(value)
+    }
+}
@@ -0,0 +1,530 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! This module defines packet sockets.
+//!
+//! Packet sockets (i.e., `AF_PACKET` sockets) send and receive frames at the link layer. A
+//! `SOCK_RAW` packet socket handles frames that include the link-layer header, whereas a
+//! `SOCK_DGRAM` packet socket handles frames without the link-layer header.
+//!
+//! A packet socket receives frames of its link-layer protocol from all interfaces unless it is
+//! bound to a specific interface. Socket filters (i.e., `SO_ATTACH_FILTER`) can be used to drop or
+//! truncate received frames, and `PACKET_ADD_MEMBERSHIP` can be used to put an interface into the
+//! promiscuous mode.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_bigtcp::{
+    errors::link::SendError,
+    socket::{LinkFrameFilter, LinkSocketKind},
+};
+
+use crate::{
+    events::IoEvents,
+    match_sock_option_mut, match_sock_option_ref,
+    net::{
+        iface::{iter_all_ifaces, Iface, LinkSocket},
+        socket::{
+            ip::DatagramObserver,
+            options::{AttachFilter, DetachFilter, Error as SocketError, SocketOption},
+            private::SocketPrivate,
+            util::{
+                options::{SetSocketLevelOption, SocketOptionSet},
+                MessageHeader, SendRecvFlags, SocketAddr, SocketFilter,
+            },
+            Socket,
+        },
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    util::{MultiRead, MultiWrite},
+};
+
+mod addr;
+mod options;
+
+pub use addr::PacketSocketAddr;
+use options::PacketMreqType;
+pub use options::{AddMembership, DropMembership, PacketMreq};
+
+pub struct PacketSocket {
+    // Lock order: `inner` first, `options` second
+    inner: RwMutex<Inner>,
+    options: RwLock<SocketOptionSet>,
+
+    kind: LinkSocketKind,
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+}
+
+struct Inner {
+    /// The link-layer protocol in host byte order.
+    protocol: u16,
+    /// The bound interface, or `None` if the socket handles frames of all interfaces.
+    bound_iface: Option<Arc<Iface>>,
+    /// The link-layer sockets, one for each handled interface.
+    link_sockets: Vec<LinkSocket>,
+    filter: Option<Arc<SocketFilter>>,
+    memberships: Vec<Membership>,
+}
+
+struct Membership {
+    iface: Arc<Iface>,
+    mreq: PacketMreq,
+}
+
+impl PacketSocket {
+    /// Creates a new packet socket that handles frames of the link-layer protocol.
+    ///
+    /// The protocol is in host byte order. Creating packet sockets requires `CAP_NET_RAW`.
+    pub fn new(is_nonblocking: bool, kind: LinkSocketKind, protocol: u16) -> Result<Arc<Self>> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        if !credentials.effective_capset().contains(CapSet::NET_RAW) {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "creating packet sockets requires `CAP_NET_RAW`"
+            );
+        }
+
+        let pollee = Pollee::new();
+        let mut inner = Inner {
+            protocol,
+            bound_iface: None,
+            link_sockets: Vec::new(),
+            filter: None,
+            memberships: Vec::new(),
+        };
+        inner.rebind(kind, &pollee);
+
+        Ok(Arc::new(Self {
+            inner: RwMutex::new(inner),
+            options: RwLock::new(SocketOptionSet::new_udp()),
+            kind,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee,
+        }))
+    }
+
+    fn try_recv(&self, writer: &mut dyn MultiWrite) -> Result<(usize, SocketAddr)> {
+        let result = self
+            .inner
+            .read()
+            .link_sockets
+            .iter()
+            .find_map(|link_socket| {
+                link_socket
+                    .recv(|frame, meta| {
+                        let copied_res = writer.write(&mut VmReader::from(frame));
+
+                        let (halen, addr) = match meta.src_addr {
+                            Some(src_addr) => (6, to_c_hw_addr(src_addr.as_bytes())),
+                            None => (0, [0u8; 8]),
+                        };
+                        let iface = link_socket.iface();
+                        let packet_addr = PacketSocketAddr {
+                            protocol: meta.protocol,
+                            ifindex: iface.index(),
+                            hatype: iface.type_() as u16,
+                            pkttype: meta.pkt_type as u8,
+                            halen,
+                            addr,
+                        };
+
+                        (copied_res, packet_addr)
+                    })
+                    .ok()
+            });
+
+        let Some((copied_res, packet_addr)) = result else {
+            return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty");
+        };
+        self.pollee.invalidate();
+
+        Ok((copied_res?, packet_addr.into()))
+    }
+
+    fn try_send(
+        &self,
+        reader: &mut dyn MultiRead,
+        remote: Option<&PacketSocketAddr>,
+    ) -> Result<usize> {
+        let inner = self.inner.read();
+
+        let ifindex = match remote {
+            Some(remote) if remote.ifindex != 0 => remote.ifindex,
+            _ => match inner.bound_iface.as_ref() {
+                Some(iface) => iface.index(),
+                None => return_errno_with_message!(Errno::ENXIO, "the interface is not specified"),
+            },
+        };
+        let Some(link_socket) = inner
+            .link_sockets
+            .iter()
+            .find(|link_socket| link_socket.iface().index() == ifindex)
+        else {
+            return_errno_with_message!(Errno::ENXIO, "the interface is not bound");
+        };
+
+        let (protocol, dst_addr) = match remote {
+            Some(remote) => (remote.protocol, remote.ether_addr()),
+            None => (inner.protocol, None),
+        };
+
+        let len = reader.sum_lens();
+        // The link-layer socket will check the frame length, but we check it first to avoid
+        // allocating a huge buffer.
+        if len > u16::MAX as usize {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+        let mut frame = vec![0u8; len];
+        reader.read(&mut VmWriter::from(frame.as_mut_slice()))?;
+
+        link_socket
+            .send(frame, protocol, dst_addr)
+            .map_err(Error::from)?;
+
+        let iface_to_poll = link_socket.iface().clone();
+        drop(inner);
+
+        self.pollee.invalidate();
+        iface_to_poll.poll();
+
+        Ok(len)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.read();
+
+        let mut events = IoEvents::empty();
+
+        if inner
+            .link_sockets
+            .iter()
+            .any(|link_socket| link_socket.can_recv())
+        {
+            events |= IoEvents::IN;
+        }
+
+        if inner
+            .link_sockets
+            .iter()
+            .all(|link_socket| link_socket.can_send())
+        {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+}
+
+impl Inner {
+    /// Recreates the link-layer sockets for the current protocol and the bound interface.
+    ///
+    /// Frames that are queued in the old link-layer sockets are discarded.
+    fn rebind(&mut self, kind: LinkSocketKind, pollee: &Pollee) {
+        // Drop the old link-layer sockets first so that no frames are delivered twice.
+        self.link_sockets.clear();
+
+        let ifaces: Vec<Arc<Iface>> = match self.bound_iface.as_ref() {
+            Some(iface) => vec![iface.clone()],
+            None => iter_all_ifaces().collect(),
+        };
+
+        let filter = self.link_filter();
+        self.link_sockets = ifaces
+            .into_iter()
+            .map(|iface| {
+                let link_socket = LinkSocket::new(
+                    iface,
+                    kind,
+                    self.protocol,
+                    DatagramObserver::new(pollee.clone()),
+                );
+                link_socket.set_filter(filter.clone());
+                link_socket
+            })
+            .collect();
+    }
+
+    fn set_filter(&mut self, filter: Option<Arc<SocketFilter>>) {
+        self.filter = filter;
+
+        let filter = self.link_filter();
+        for link_socket in self.link_sockets.iter() {
+            link_socket.set_filter(filter.clone());
+        }
+    }
+
+    fn link_filter(&self) -> Option<Arc<dyn LinkFrameFilter>> {
+        self.filter
+            .clone()
+            .map(|filter| filter as Arc<dyn LinkFrameFilter>)
+    }
+
+    fn add_membership(&mut self, mreq: &PacketMreq) -> Result<()> {
+        let (iface, mreq_type) = check_mreq(mreq)?;
+
+        if self
+            .memberships
+            .iter()
+            .any(|membership| is_same_mreq(&membership.mreq, mreq))
+        {
+            // Like Linux, adding the same membership twice only takes effect once.
+            return Ok(());
+        }
+
+        match mreq_type {
+            PacketMreqType::Promisc => iface.enter_promisc(),
+            // TODO: Support multicast and unicast address filters. Currently, the device receives
+            // all multicast frames sent to our MAC address, so these requests are simply recorded.
+            PacketMreqType::Multicast | PacketMreqType::AllMulti | PacketMreqType::Unicast => (),
+        }
+
+        self.memberships.push(Membership { iface, mreq: *mreq });
+
+        Ok(())
+    }
+
+    fn drop_membership(&mut self, mreq: &PacketMreq) -> Result<()> {
+        let Some(pos) = self
+            .memberships
+            .iter()
+            .position(|membership| is_same_mreq(&membership.mreq, mreq))
+        else {
+            return_errno_with_message!(Errno::EADDRNOTAVAIL, "the membership does not exist");
+        };
+
+        let membership = self.memberships.swap_remove(pos);
+        membership.release();
+
+        Ok(())
+    }
+}
+
+impl Membership {
+    fn release(&self) {
+        if self.mreq.type_ == PacketMreqType::Promisc as u16 {
+            self.iface.leave_promisc();
+        }
+    }
+}
+
+fn check_mreq(mreq: &PacketMreq) -> Result<(Arc<Iface>, PacketMreqType)> {
+    let Some(iface) = iter_all_ifaces().find(|iface| iface.index() as i32 == mreq.ifindex) else {
+        return_errno_with_message!(Errno::ENODEV, "the interface does not exist");
+    };
+
+    let mreq_type = PacketMreqType::try_from(mreq.type_)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the membership type is invalid"))?;
+
+    if mreq.alen as usize > mreq.address.len() {
+        return_errno_with_message!(Errno::EINVAL, "the address length is too large");
+    }
+
+    Ok((iface, mreq_type))
+}
+
+fn is_same_mreq(lhs: &PacketMreq, rhs: &PacketMreq) -> bool {
+    let alen = lhs.alen as usize;
+    lhs.ifindex == rhs.ifindex
+        && lhs.type_ == rhs.type_
+        && lhs.alen == rhs.alen
+        && lhs.address[..alen] == rhs.address[..alen]
+}
+
+fn to_c_hw_addr(bytes: &[u8]) -> [u8; 8] {
+    let mut addr = [0u8; 8];
+    addr[..bytes.len()].copy_from_slice(bytes);
+    addr
+}
+
+impl Pollable for PacketSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl SocketPrivate for PacketSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for PacketSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let packet_addr = PacketSocketAddr::try_from(socket_addr)?;
+
+        let bound_iface = if packet_addr.ifindex == 0 {
+            None
+        } else {
+            let Some(iface) = iter_all_ifaces().find(|iface| iface.index() == packet_addr.ifindex)
+            else {
+                return_errno_with_message!(Errno::ENODEV, "the interface does not exist");
+            };
+            Some(iface)
+        };
+
+        let mut inner = self.inner.write();
+
+        // Like Linux, a zero protocol means keeping the current protocol.
+        if packet_addr.protocol != 0 {
+            inner.protocol = packet_addr.protocol;
+        }
+        inner.bound_iface = bound_iface;
+        inner.rebind(self.kind, &self.pollee);
+
+        drop(inner);
+        self.pollee.invalidate();
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        let inner = self.inner.read();
+
+        let (ifindex, hatype, hw_addr) = match inner.bound_iface.as_ref() {
+            Some(iface) => (iface.index(), iface.type_() as u16, iface.hw_addr()),
+            None => (0, 0, None),
+        };
+        let (halen, addr) = match hw_addr {
+            Some(hw_addr) => (6, to_c_hw_addr(hw_addr.as_bytes())),
+            None => (0, [0u8; 8]),
+        };
+
+        Ok(PacketSocketAddr {
+            protocol: inner.protocol,
+            ifindex,
+            hatype,
+            pkttype: 0,
+            halen,
+            addr,
+        }
+        .into())
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+            ..
+        } = message_header;
+
+        let remote = addr.map(PacketSocketAddr::try_from).transpose()?;
+
+        if !control_messages.is_empty() {
+            // TODO: Support sending control message
+            warn!("sending control message is not supported");
+        }
+
+        // TODO: Block if the send buffer is full
+        self.try_send(reader, remote.as_ref())
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, peer_addr) = self.block_on(IoEvents::IN, || self.try_recv(writer))?;
+
+        // TODO: Receive control message
+
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_errors: SocketError => {
+                // TODO: Support socket errors for packet sockets
+                socket_errors.set(None);
+                return Ok(());
+            },
+            _ => ()
+        });
+
+        self.options.read().get_option(option)
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        match_sock_option_ref!(option, {
+            attach_filter: AttachFilter => {
+                let filter = attach_filter.get().unwrap();
+                inner.set_filter(Some(Arc::new(filter.clone())));
+                return Ok(());
+            },
+            _detach_filter: DetachFilter => {
+                if inner.filter.is_none() {
+                    return_errno_with_message!(Errno::ENOENT, "no filter is attached");
+                }
+                inner.set_filter(None);
+                return Ok(());
+            },
+            add_membership: AddMembership => {
+                return inner.add_membership(add_membership.get().unwrap());
+            },
+            drop_membership: DropMembership => {
+                return inner.drop_membership(drop_membership.get().unwrap());
+            },
+            _ => ()
+        });
+
+        self.options.write().set_option(option, &*inner)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for PacketSocket {
+    fn drop(&mut self) {
+        for membership in self.inner.get_mut().memberships.iter() {
+            membership.release();
+        }
+    }
+}
+
+impl SetSocketLevelOption for Inner {}
+
+impl LinkFrameFilter for SocketFilter {
+    fn run(&self, frame: &[u8]) -> usize {
+        SocketFilter::run(self, frame) as usize
+    }
+}
+
+impl From<SendError> for Error {
+    fn from(value: SendError) -> Self {
+        match value {
+            SendError::TooLarge => Error::with_message(Errno::EMSGSIZE, "the message is too large"),
+            SendError::Unaddressable => {
+                Error::with_message(Errno::EINVAL, "the destination address is invalid")
+            }
+            SendError::BufferFull => Error::with_message(Errno::EAGAIN, "the send buffer is full"),
+            SendError::Malformed => Error::with_message(Errno::EINVAL, "the frame is malformed"),
+        }
+    }
+}
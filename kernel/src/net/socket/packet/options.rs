@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{impl_socket_options, prelude::*};
+
+impl_socket_options!(
+    pub struct AddMembership(PacketMreq);
+    pub struct DropMembership(PacketMreq);
+);
+
+/// A membership request of a packet socket (i.e., `struct packet_mreq`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct PacketMreq {
+    pub ifindex: i32,
+    pub type_: u16,
+    pub alen: u16,
+    pub address: [u8; 8],
+}
+
+/// The type of a membership request.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L303>.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, TryFromInt, PartialEq, Eq)]
+pub(super) enum PacketMreqType {
+    Multicast = 0,
+    Promisc = 1,
+    AllMulti = 2,
+    Unicast = 3,
+}
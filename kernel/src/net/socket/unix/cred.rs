@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, Gid, Pid, Uid},
+};
+
+/// The credentials of a process that are passed over UNIX domain sockets.
+///
+/// This is the `struct ucred` in Linux, which is used by the `SCM_CREDENTIALS` control message
+/// and the `SO_PEERCRED` socket option.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod)]
+pub struct UCred {
+    pid: Pid,
+    uid: Uid,
+    gid: Gid,
+}
+
+impl UCred {
+    /// Returns the credentials of the current process.
+    ///
+    /// The effective user and group IDs are used, as Linux does for `SO_PEERCRED`.
+    pub fn new_current() -> Self {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+
+        Self {
+            pid: current!().pid(),
+            uid: credentials.euid(),
+            gid: credentials.egid(),
+        }
+    }
+
+    /// Returns the credentials that indicate an unknown process.
+    ///
+    /// Linux reports these credentials for `SO_PEERCRED` if the socket has no peer.
+    pub const fn new_unknown() -> Self {
+        Self {
+            pid: 0,
+            uid: Uid::new(u32::MAX),
+            gid: Gid::new(u32::MAX),
+        }
+    }
+
+    /// Checks whether the current process can send the credentials.
+    ///
+    /// A process can only claim its own PID, user IDs, and group IDs, unless it has the
+    /// `CAP_SYS_ADMIN`, `CAP_SETUID`, and `CAP_SETGID` capability, respectively.
+    ///
+    /// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/net/core/scm.c#L55>.
+    pub fn check_sendable(&self) -> Result<()> {
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let capset = credentials.effective_capset();
+
+        let is_pid_allowed = self.pid == current!().pid() || capset.contains(CapSet::SYS_ADMIN);
+        let is_uid_allowed = [credentials.ruid(), credentials.euid(), credentials.suid()]
+            .contains(&self.uid)
+            || capset.contains(CapSet::SETUID);
+        let is_gid_allowed = [credentials.rgid(), credentials.egid(), credentials.sgid()]
+            .contains(&self.gid)
+            || capset.contains(CapSet::SETGID);
+
+        if !is_pid_allowed || !is_uid_allowed || !is_gid_allowed {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the credentials do not belong to the current process"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::sync::WaitQueue;
+
+use crate::{
+    events::IoEvents,
+    net::socket::{
+        unix::{
+            addr::{UnixSocketAddrBound, UnixSocketAddrKey},
+            UCred,
+        },
+        util::ControlMessage,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollee},
+};
+
+/// A message sent over UNIX datagram sockets.
+pub(super) struct Message {
+    pub(super) src_addr: Option<UnixSocketAddrBound>,
+    pub(super) bytes: Vec<u8>,
+    pub(super) cred: UCred,
+    pub(super) control_messages: Vec<ControlMessage>,
+}
+
+/// The receiving end of a UNIX datagram socket.
+///
+/// Other sockets send messages to the socket by pushing them into the inbox. An inbox can be found
+/// by its address if the socket is bound.
+pub(super) struct Inbox {
+    addr: Mutex<Option<UnixSocketAddrBound>>,
+    /// The inbox of the peer socket if the socket is connected.
+    ///
+    /// A connected socket only accepts messages from its peer.
+    peer: Mutex<Option<Weak<Inbox>>>,
+    queue: SpinLock<MessageQueue>,
+    pollee: Pollee,
+    /// Senders that are waiting for free space in the queue.
+    wait_queue: WaitQueue,
+}
+
+struct MessageQueue {
+    messages: VecDeque<Message>,
+    total_len: usize,
+    is_shutdown: bool,
+}
+
+impl Inbox {
+    pub(super) fn new() -> Self {
+        Self {
+            addr: Mutex::new(None),
+            peer: Mutex::new(None),
+            queue: SpinLock::new(MessageQueue {
+                messages: VecDeque::new(),
+                total_len: 0,
+                is_shutdown: false,
+            }),
+            pollee: Pollee::new(),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    pub(super) fn addr(&self) -> MutexGuard<Option<UnixSocketAddrBound>> {
+        self.addr.lock()
+    }
+
+    /// Records the inbox of the peer socket when the socket is connected.
+    pub(super) fn set_peer(&self, peer: Weak<Inbox>) {
+        *self.peer.lock() = Some(peer);
+    }
+
+    /// Checks whether the socket that owns the `sender` inbox can send messages to the inbox.
+    pub(super) fn check_sender(&self, sender: &Arc<Inbox>) -> Result<()> {
+        match self.peer.lock().as_ref() {
+            Some(peer) if !core::ptr::eq(peer.as_ptr(), Arc::as_ptr(sender)) => {
+                return_errno_with_message!(
+                    Errno::EPERM,
+                    "the receiving socket is connected to another socket"
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Tries to push a message into the inbox.
+    ///
+    /// If the inbox is full, this method fails with `EAGAIN` and gives the message back.
+    pub(super) fn try_push(&self, message: Message) -> core::result::Result<(), (Error, Message)> {
+        let mut queue = self.queue.lock();
+
+        if queue.is_shutdown {
+            return Err((
+                Error::with_message(Errno::EPIPE, "the receiving socket is shut down"),
+                message,
+            ));
+        }
+
+        // A message is always accepted by an empty queue, so that large messages can be sent.
+        if !queue.messages.is_empty() && queue.total_len + message.bytes.len() > DEFAULT_BUF_SIZE {
+            return Err((
+                Error::with_message(Errno::EAGAIN, "the receiving queue is full"),
+                message,
+            ));
+        }
+
+        queue.total_len += message.bytes.len();
+        queue.messages.push_back(message);
+        drop(queue);
+
+        self.pollee.notify(IoEvents::IN);
+
+        Ok(())
+    }
+
+    /// Pushes a message into the inbox, blocking until there is free space.
+    pub(super) fn push(&self, message: Message) -> Result<()> {
+        let mut message = Some(message);
+
+        self.wait_queue
+            .pause_until(|| match self.try_push(message.take().unwrap()) {
+                Ok(()) => Some(Ok(())),
+                Err((err, returned)) if err.error() == Errno::EAGAIN => {
+                    message = Some(returned);
+                    None
+                }
+                Err((err, _)) => Some(Err(err)),
+            })?
+    }
+
+    /// Tries to pop a message from the inbox.
+    ///
+    /// - Returns `Ok(Some(_))` with the popped message if successful.
+    /// - Returns `Ok(None)` if the inbox is shut down and there are no messages left.
+    /// - Returns `Err(EAGAIN)` if the inbox is empty.
+    pub(super) fn try_pop(&self) -> Result<Option<Message>> {
+        let mut queue = self.queue.lock();
+
+        let Some(message) = queue.messages.pop_front() else {
+            if queue.is_shutdown {
+                return Ok(None);
+            }
+            return_errno_with_message!(Errno::EAGAIN, "the receiving queue is empty");
+        };
+        queue.total_len -= message.bytes.len();
+        drop(queue);
+
+        self.pollee.invalidate();
+        self.wait_queue.wake_all();
+
+        Ok(Some(message))
+    }
+
+    /// Shuts down the inbox.
+    ///
+    /// Further attempts to push messages will fail with `EPIPE`. Messages that are already in the
+    /// inbox can still be popped.
+    pub(super) fn shutdown(&self) {
+        self.queue.lock().is_shutdown = true;
+
+        self.pollee.notify(IoEvents::IN | IoEvents::RDHUP);
+        self.wait_queue.wake_all();
+    }
+
+    pub(super) fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let queue = self.queue.lock();
+
+        let mut events = IoEvents::empty();
+        if !queue.messages.is_empty() {
+            events |= IoEvents::IN;
+        }
+        if queue.is_shutdown {
+            events |= IoEvents::IN | IoEvents::RDHUP;
+        }
+        events
+    }
+}
+
+/// The maximum number of bytes that can be queued in an inbox.
+const DEFAULT_BUF_SIZE: usize = 65536;
+
+/// The maximum size of a message.
+pub(super) const MAX_MESSAGE_SIZE: usize = DEFAULT_BUF_SIZE;
+
+static INBOX_TABLE: InboxTable = InboxTable::new();
+
+struct InboxTable {
+    inboxes: RwLock<BTreeMap<UnixSocketAddrKey, Weak<Inbox>>>,
+}
+
+impl InboxTable {
+    const fn new() -> Self {
+        Self {
+            inboxes: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+/// Registers the inbox so that it can be found by its address.
+pub(super) fn register_inbox(addr_key: UnixSocketAddrKey, inbox: &Arc<Inbox>) {
+    INBOX_TABLE
+        .inboxes
+        .write()
+        .insert(addr_key, Arc::downgrade(inbox));
+}
+
+pub(super) fn unregister_inbox(addr_key: &UnixSocketAddrKey) {
+    INBOX_TABLE.inboxes.write().remove(addr_key);
+}
+
+pub(super) fn get_inbox(addr_key: &UnixSocketAddrKey) -> Result<Arc<Inbox>> {
+    INBOX_TABLE
+        .inboxes
+        .read()
+        .get(addr_key)
+        .and_then(Weak::upgrade)
+        .ok_or_else(|| {
+            Error::with_message(
+                Errno::ECONNREFUSED,
+                "no datagram socket is bound to the remote address",
+            )
+        })
+}
@@ -0,0 +1,316 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::inbox::{get_inbox, register_inbox, unregister_inbox, Inbox, Message, MAX_MESSAGE_SIZE};
+use crate::{
+    events::IoEvents,
+    match_sock_option_mut, match_sock_option_ref,
+    net::socket::{
+        options::{PassCred, PeerCred, SocketOption},
+        private::SocketPrivate,
+        unix::{UCred, UnixSocketAddr},
+        util::{ControlMessage, MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr},
+        Socket,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+    util::{MultiRead, MultiWrite},
+};
+
+/// A UNIX domain socket of the `SOCK_DGRAM` type.
+pub struct UnixDatagramSocket {
+    inbox: Arc<Inbox>,
+    peer: RwMutex<Option<Peer>>,
+    is_nonblocking: AtomicBool,
+    is_pass_cred: AtomicBool,
+    is_write_shutdown: AtomicBool,
+}
+
+struct Peer {
+    inbox: Weak<Inbox>,
+    /// The credentials of the peer process, which are only known for socket pairs.
+    cred: Option<UCred>,
+}
+
+impl UnixDatagramSocket {
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        Arc::new(Self::new_with_peer(None, is_nonblocking))
+    }
+
+    pub fn new_pair(is_nonblocking: bool) -> (Arc<Self>, Arc<Self>) {
+        let cred = UCred::new_current();
+
+        let socket_a = Arc::new(Self::new_with_peer(None, is_nonblocking));
+        let socket_b = Arc::new(Self::new_with_peer(
+            Some(Peer {
+                inbox: Arc::downgrade(&socket_a.inbox),
+                cred: Some(cred),
+            }),
+            is_nonblocking,
+        ));
+        *socket_a.peer.write() = Some(Peer {
+            inbox: Arc::downgrade(&socket_b.inbox),
+            cred: Some(cred),
+        });
+        socket_a.inbox.set_peer(Arc::downgrade(&socket_b.inbox));
+        socket_b.inbox.set_peer(Arc::downgrade(&socket_a.inbox));
+
+        (socket_a, socket_b)
+    }
+
+    fn new_with_peer(peer: Option<Peer>, is_nonblocking: bool) -> Self {
+        Self {
+            inbox: Arc::new(Inbox::new()),
+            peer: RwMutex::new(peer),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_pass_cred: AtomicBool::new(false),
+            is_write_shutdown: AtomicBool::new(false),
+        }
+    }
+
+    fn select_remote_inbox(&self, addr: Option<SocketAddr>) -> Result<Arc<Inbox>> {
+        if let Some(addr) = addr {
+            let remote_key = UnixSocketAddr::try_from(addr)?.connect()?;
+            return get_inbox(&remote_key);
+        }
+
+        match self.peer.read().as_ref() {
+            Some(peer) => peer.inbox.upgrade().ok_or_else(|| {
+                Error::with_message(Errno::ECONNREFUSED, "the peer socket is closed")
+            }),
+            None => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        let Some(message) = self.inbox.try_pop()? else {
+            return Ok((0, MessageHeader::new(None, Vec::new())));
+        };
+
+        let Message {
+            src_addr,
+            bytes,
+            cred,
+            mut control_messages,
+        } = message;
+
+        let copied_len = writer.write(&mut VmReader::from(bytes.as_slice()))?;
+
+        let has_cred = control_messages
+            .iter()
+            .any(|control_message| matches!(control_message, ControlMessage::Credentials(_)));
+        if self.is_pass_cred() && !has_cred {
+            control_messages.insert(0, ControlMessage::Credentials(cred));
+        }
+
+        let mut message_header = MessageHeader::new(Some(src_addr.into()), control_messages);
+        if copied_len < bytes.len() {
+            message_header.set_truncated();
+        }
+        if flags.contains(SendRecvFlags::MSG_TRUNC) {
+            return Ok((bytes.len(), message_header));
+        }
+
+        Ok((copied_len, message_header))
+    }
+
+    fn is_pass_cred(&self) -> bool {
+        self.is_pass_cred.load(Ordering::Relaxed)
+    }
+
+    fn peer_cred(&self) -> UCred {
+        self.peer
+            .read()
+            .as_ref()
+            .and_then(|peer| peer.cred)
+            .unwrap_or(UCred::new_unknown())
+    }
+}
+
+impl Pollable for UnixDatagramSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        let mut events = self.inbox.poll(mask, poller) | IoEvents::OUT;
+
+        if self.is_write_shutdown.load(Ordering::Relaxed) {
+            events |= IoEvents::ERR;
+        }
+
+        events & (mask | IoEvents::ALWAYS_POLL)
+    }
+}
+
+impl SocketPrivate for UnixDatagramSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for UnixDatagramSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let addr_to_bind = UnixSocketAddr::try_from(socket_addr)?;
+
+        let mut addr = self.inbox.addr();
+        if addr.is_some() {
+            return addr_to_bind.bind_unnamed();
+        }
+
+        let bound_addr = addr_to_bind.bind()?;
+        register_inbox(bound_addr.to_key(), &self.inbox);
+        *addr = Some(bound_addr);
+
+        Ok(())
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        let remote_key = UnixSocketAddr::try_from(socket_addr)?.connect()?;
+        let remote_inbox = get_inbox(&remote_key)?;
+
+        *self.peer.write() = Some(Peer {
+            inbox: Arc::downgrade(&remote_inbox),
+            cred: None,
+        });
+        self.inbox.set_peer(Arc::downgrade(&remote_inbox));
+
+        Ok(())
+    }
+
+    fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
+        if self.peer.read().is_none() {
+            return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected");
+        }
+
+        if cmd.shut_read() {
+            self.inbox.shutdown();
+        }
+
+        if cmd.shut_write() {
+            self.is_write_shutdown.store(true, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        Ok(self.inbox.addr().clone().into())
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        let peer = self.peer.read();
+        let Some(peer) = peer.as_ref() else {
+            return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected");
+        };
+
+        let peer_addr = match peer.inbox.upgrade() {
+            Some(inbox) => inbox.addr().clone(),
+            None => None,
+        };
+
+        Ok(peer_addr.into())
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            pass_cred: PassCred => {
+                pass_cred.set(self.is_pass_cred());
+            },
+            peer_cred: PeerCred => {
+                peer_cred.set(self.peer_cred());
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
+
+        Ok(())
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        match_sock_option_ref!(option, {
+            pass_cred: PassCred => {
+                let is_pass_cred = *pass_cred.get().unwrap();
+                self.is_pass_cred.store(is_pass_cred, Ordering::Relaxed);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+
+        Ok(())
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+            ..
+        } = message_header;
+
+        if self.is_write_shutdown.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EPIPE, "the socket is shut down for writing");
+        }
+
+        let remote_inbox = self.select_remote_inbox(addr)?;
+        remote_inbox.check_sender(&self.inbox)?;
+
+        let message_len = reader.sum_lens();
+        if message_len > MAX_MESSAGE_SIZE {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+
+        let mut bytes = vec![0u8; message_len];
+        reader.read(&mut VmWriter::from(bytes.as_mut_slice()))?;
+
+        let message = Message {
+            src_addr: self.inbox.addr().clone(),
+            bytes,
+            cred: UCred::new_current(),
+            control_messages,
+        };
+
+        if self.is_nonblocking() {
+            remote_inbox.try_push(message).map_err(|(err, _)| err)?;
+        } else {
+            remote_inbox.push(message)?;
+        }
+
+        Ok(message_len)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        self.block_on(IoEvents::IN, || self.try_recv(writer, flags))
+    }
+}
+
+impl Drop for UnixDatagramSocket {
+    fn drop(&mut self) {
+        self.inbox.shutdown();
+
+        if let Some(addr) = self.inbox.addr().as_ref() {
+            unregister_inbox(&addr.to_key());
+        }
+    }
+}
@@ -1,8 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod addr;
+mod cred;
+mod datagram;
 mod ns;
 mod stream;
 
 pub use addr::UnixSocketAddr;
+pub use cred::UCred;
+pub use datagram::UnixDatagramSocket;
 pub use stream::UnixStreamSocket;
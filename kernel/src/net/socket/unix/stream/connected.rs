@@ -8,8 +8,8 @@ use crate::{
     events::IoEvents,
     fs::utils::{Channel, Consumer, Producer},
     net::socket::{
-        unix::{addr::UnixSocketAddrBound, UnixSocketAddr},
-        util::SockShutdownCmd,
+        unix::{addr::UnixSocketAddrBound, UCred, UnixSocketAddr},
+        util::{ControlMessage, MessageHeader, SendRecvFlags, SockShutdownCmd},
     },
     prelude::*,
     process::signal::{PollHandle, Pollee},
@@ -18,14 +18,29 @@ use crate::{
 
 pub(super) struct Connected {
     addr: AddrView,
+    peer_cred: UCred,
+    is_seqpacket: bool,
     reader: Consumer<u8>,
+    reader_pos: Mutex<usize>,
+    reader_aux: Arc<AuxQueue>,
     writer: Producer<u8>,
+    writer_pos: Mutex<usize>,
+    writer_aux: Arc<AuxQueue>,
 }
 
 impl Connected {
+    /// Creates a pair of connected sockets.
+    ///
+    /// `cred` and `peer_cred` are the credentials of the processes that own this socket and the
+    /// peer socket when the connection is established, which will be reported by `SO_PEERCRED`.
+    ///
+    /// If `is_seqpacket` is true, message boundaries are preserved (i.e., `SOCK_SEQPACKET`).
     pub(super) fn new_pair(
         addr: Option<UnixSocketAddrBound>,
         peer_addr: Option<UnixSocketAddrBound>,
+        cred: UCred,
+        peer_cred: UCred,
+        is_seqpacket: bool,
         reader_pollee: Option<Pollee>,
         writer_pollee: Option<Pollee>,
     ) -> (Connected, Connected) {
@@ -36,15 +51,30 @@ impl Connected {
 
         let (addr_this, addr_peer) = AddrView::new_pair(addr, peer_addr);
 
+        let aux_this = Arc::new(AuxQueue::new());
+        let aux_peer = Arc::new(AuxQueue::new());
+
         let this = Connected {
             addr: addr_this,
+            peer_cred,
+            is_seqpacket,
             reader: reader_this,
+            reader_pos: Mutex::new(0),
+            reader_aux: aux_this.clone(),
             writer: writer_this,
+            writer_pos: Mutex::new(0),
+            writer_aux: aux_peer.clone(),
         };
         let peer = Connected {
             addr: addr_peer,
+            peer_cred: cred,
+            is_seqpacket,
             reader: reader_peer,
+            reader_pos: Mutex::new(0),
+            reader_aux: aux_peer,
             writer: writer_peer,
+            writer_pos: Mutex::new(0),
+            writer_aux: aux_this,
         };
 
         (this, peer)
@@ -71,18 +101,96 @@ impl Connected {
         Ok(())
     }
 
-    pub(super) fn try_read(&self, writer: &mut dyn MultiWrite) -> Result<usize> {
+    pub(super) fn peer_cred(&self) -> UCred {
+        self.peer_cred
+    }
+
+    /// Reads bytes and the control messages attached to them.
+    ///
+    /// For `SOCK_SEQPACKET` sockets, at most one message is read. If the message does not fit
+    /// in `writer`, the rest of the message is discarded and the message is marked as truncated.
+    /// With `MSG_TRUNC`, the full length of the message is returned even if it is truncated.
+    pub(super) fn try_read(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        if self.is_seqpacket {
+            return self.try_read_message(writer, flags);
+        }
+
         if writer.is_empty() {
             if self.reader.is_empty() {
                 return_errno_with_message!(Errno::EAGAIN, "the channel is empty");
             }
-            return Ok(0);
+            return Ok((0, MessageHeader::new(None, Vec::new())));
         }
 
-        self.reader.try_read(writer)
+        let mut reader_pos = self.reader_pos.lock();
+
+        let read_len = self.reader.try_read(writer)?;
+        let control_messages = self.reader_aux.pop_within(*reader_pos, read_len);
+        *reader_pos = reader_pos.wrapping_add(read_len);
+
+        Ok((read_len, MessageHeader::new(None, control_messages)))
     }
 
-    pub(super) fn try_write(&self, reader: &mut dyn MultiRead) -> Result<usize> {
+    fn try_read_message(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        let mut reader_pos = self.reader_pos.lock();
+
+        let Some(message_len) = self.reader_aux.front_len() else {
+            // If there are bytes left, the writer is publishing the message. So the end of file
+            // is reached only if the channel is both shut down and empty.
+            if self.reader.is_shutdown() && self.reader.is_empty() {
+                return Ok((0, MessageHeader::new(None, Vec::new())));
+            }
+            return_errno_with_message!(Errno::EAGAIN, "the channel is empty");
+        };
+
+        // The entry of a message is published after all the bytes of the message are written.
+        // See `try_write_message` for details.
+        let mut buffer = vec![0u8; message_len];
+        let read_len = self
+            .reader
+            .try_read(&mut VmWriter::from(buffer.as_mut_slice()))?;
+        debug_assert_eq!(read_len, message_len);
+
+        let control_messages = self.reader_aux.pop_front().unwrap();
+        *reader_pos = reader_pos.wrapping_add(message_len);
+
+        let copied_len = writer.write(&mut VmReader::from(buffer.as_slice()))?;
+
+        let mut message_header = MessageHeader::new(None, control_messages);
+        if copied_len < message_len {
+            message_header.set_truncated();
+        }
+        if flags.contains(SendRecvFlags::MSG_TRUNC) {
+            return Ok((message_len, message_header));
+        }
+
+        Ok((copied_len, message_header))
+    }
+
+    /// Writes bytes and attaches the control messages to them.
+    ///
+    /// The control messages are taken only if some bytes are written. Otherwise, they are left
+    /// untouched so that they can be sent when the write is retried.
+    ///
+    /// For `SOCK_SEQPACKET` sockets, all the bytes are written as one message, or nothing is
+    /// written.
+    pub(super) fn try_write(
+        &self,
+        reader: &mut dyn MultiRead,
+        control_messages: &mut Vec<ControlMessage>,
+    ) -> Result<usize> {
+        if self.is_seqpacket {
+            return self.try_write_message(reader, control_messages);
+        }
+
         if reader.is_empty() {
             if self.writer.is_shutdown() {
                 return_errno_with_message!(Errno::EPIPE, "the channel is shut down");
@@ -90,7 +198,71 @@ impl Connected {
             return Ok(0);
         }
 
-        self.writer.try_write(reader)
+        let mut writer_pos = self.writer_pos.lock();
+
+        // The control messages must be visible before the bytes are visible. Otherwise, the
+        // reader may read the bytes without receiving the control messages.
+        let has_control_messages = !control_messages.is_empty();
+        if has_control_messages {
+            self.writer_aux
+                .push(*writer_pos, 0, core::mem::take(control_messages));
+        }
+
+        let write_res = self.writer.try_write(reader);
+        match write_res {
+            Ok(write_len) if write_len > 0 => {
+                *writer_pos = writer_pos.wrapping_add(write_len);
+            }
+            _ if has_control_messages => {
+                *control_messages = self.writer_aux.pop_back().unwrap();
+            }
+            _ => (),
+        }
+
+        write_res
+    }
+
+    fn try_write_message(
+        &self,
+        reader: &mut dyn MultiRead,
+        control_messages: &mut Vec<ControlMessage>,
+    ) -> Result<usize> {
+        // TODO: Support zero-length messages. Linux delivers them as empty messages.
+        let message_len = reader.sum_lens();
+        if message_len == 0 {
+            if self.writer.is_shutdown() {
+                return_errno_with_message!(Errno::EPIPE, "the channel is shut down");
+            }
+            return Ok(0);
+        }
+        if message_len > DEFAULT_BUF_SIZE {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+
+        let mut writer_pos = self.writer_pos.lock();
+
+        if self.writer.is_shutdown() {
+            return_errno_with_message!(Errno::EPIPE, "the channel is shut down");
+        }
+        // Only we can write to the channel, so the free space will not shrink after the check.
+        if self.writer.free_len() < message_len {
+            return_errno_with_message!(Errno::EAGAIN, "the channel is full");
+        }
+
+        let mut buffer = vec![0u8; message_len];
+        reader.read(&mut VmWriter::from(buffer.as_mut_slice()))?;
+
+        self.writer
+            .try_write(&mut VmReader::from(buffer.as_slice()))?;
+
+        // Each message has an entry, which is published after all the bytes are written, so that
+        // the reader will never see a message whose bytes are not ready. In the meantime, the
+        // reader sees the bytes but no entry, and fails with `EAGAIN`.
+        self.writer_aux
+            .push(*writer_pos, message_len, core::mem::take(control_messages));
+        *writer_pos = writer_pos.wrapping_add(message_len);
+
+        Ok(message_len)
     }
 
     pub(super) fn shutdown(&self, cmd: SockShutdownCmd) {
@@ -170,4 +342,96 @@ impl AddrView {
     }
 }
 
+/// A queue of the control messages attached to the bytes in a channel.
+///
+/// Each entry records the position of the first byte that is sent together with the control
+/// messages. The control messages are received when the byte is read. For `SOCK_SEQPACKET`
+/// sockets, every message has an entry that also records the message length.
+//
+// FIXME: If a socket is sent over itself (or over a cycle of sockets) with `SCM_RIGHTS` and never
+// received, the reference cycle will keep the sockets alive forever. Linux deals with this by
+// running a garbage collector for in-flight sockets, which we do not have yet.
+struct AuxQueue {
+    entries: SpinLock<VecDeque<AuxEntry>>,
+}
+
+struct AuxEntry {
+    pos: usize,
+    len: usize,
+    control_messages: Vec<ControlMessage>,
+}
+
+impl AuxQueue {
+    fn new() -> Self {
+        Self {
+            entries: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    fn push(&self, pos: usize, len: usize, control_messages: Vec<ControlMessage>) {
+        self.entries.lock().push_back(AuxEntry {
+            pos,
+            len,
+            control_messages,
+        });
+    }
+
+    fn pop_back(&self) -> Option<Vec<ControlMessage>> {
+        self.entries
+            .lock()
+            .pop_back()
+            .map(|entry| entry.control_messages)
+    }
+
+    fn front_len(&self) -> Option<usize> {
+        self.entries.lock().front().map(|entry| entry.len)
+    }
+
+    fn pop_front(&self) -> Option<Vec<ControlMessage>> {
+        self.entries
+            .lock()
+            .pop_front()
+            .map(|entry| entry.control_messages)
+    }
+
+    /// Pops the control messages attached to the bytes in `[start, start + len)`.
+    ///
+    /// If there are multiple entries, all the files are merged into one `SCM_RIGHTS` message
+    /// and only the first credentials are kept.
+    fn pop_within(&self, start: usize, len: usize) -> Vec<ControlMessage> {
+        let mut popped = Vec::new();
+
+        let mut entries = self.entries.lock();
+        while let Some(entry) = entries.front() {
+            if entry.pos.wrapping_sub(start) >= len {
+                break;
+            }
+            popped.extend(entries.pop_front().unwrap().control_messages);
+        }
+        drop(entries);
+
+        let mut cred = None;
+        let mut files = Vec::new();
+        for control_message in popped {
+            match control_message {
+                ControlMessage::Rights(mut rights) => files.append(&mut rights),
+                ControlMessage::Credentials(credentials) => {
+                    cred.get_or_insert(credentials);
+                }
+                // Errors cannot be sent by the user, so they will never appear here.
+                ControlMessage::RecvErr(_) => (),
+            }
+        }
+
+        let mut control_messages = Vec::new();
+        if let Some(cred) = cred {
+            control_messages.push(ControlMessage::Credentials(cred));
+        }
+        if !files.is_empty() {
+            control_messages.push(ControlMessage::Rights(files));
+        }
+        control_messages
+    }
+}
+
 const DEFAULT_BUF_SIZE: usize = 65536;
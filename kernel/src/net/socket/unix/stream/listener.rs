@@ -13,7 +13,10 @@ use crate::{
     events::IoEvents,
     fs::file_handle::FileLike,
     net::socket::{
-        unix::addr::{UnixSocketAddrBound, UnixSocketAddrKey},
+        unix::{
+            addr::{UnixSocketAddrBound, UnixSocketAddrKey},
+            UCred,
+        },
         util::{SockShutdownCmd, SocketAddr},
     },
     prelude::*,
@@ -32,11 +35,12 @@ impl Listener {
         reader_pollee: Pollee,
         writer_pollee: Pollee,
         backlog: usize,
+        is_seqpacket: bool,
         is_read_shutdown: bool,
         is_write_shutdown: bool,
     ) -> Self {
         let backlog = BACKLOG_TABLE
-            .add_backlog(addr, reader_pollee, backlog, is_read_shutdown)
+            .add_backlog(addr, reader_pollee, backlog, is_seqpacket, is_read_shutdown)
             .unwrap();
         writer_pollee.invalidate();
 
@@ -51,11 +55,20 @@ impl Listener {
         self.backlog.addr()
     }
 
-    pub(super) fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
+    pub(super) fn cred(&self) -> UCred {
+        self.backlog.cred
+    }
+
+    pub(super) fn try_accept(&self, is_pass_cred: bool) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         let connected = self.backlog.pop_incoming()?;
         let peer_addr = connected.peer_addr().into();
 
-        let socket = UnixStreamSocket::new_connected(connected, false);
+        let socket = UnixStreamSocket::new_connected(
+            connected,
+            false,
+            is_pass_cred,
+            self.backlog.is_seqpacket,
+        );
         Ok((socket, peer_addr))
     }
 
@@ -121,6 +134,7 @@ impl BacklogTable {
         addr: UnixSocketAddrBound,
         pollee: Pollee,
         backlog: usize,
+        is_seqpacket: bool,
         is_shutdown: bool,
     ) -> Option<Arc<Backlog>> {
         let addr_key = addr.to_key();
@@ -133,7 +147,13 @@ impl BacklogTable {
 
         // Note that the cached events can be correctly inherited from `Init`, so there is no need
         // to explicitly call `Pollee::invalidate`.
-        let new_backlog = Arc::new(Backlog::new(addr, pollee, backlog, is_shutdown));
+        let new_backlog = Arc::new(Backlog::new(
+            addr,
+            pollee,
+            backlog,
+            is_seqpacket,
+            is_shutdown,
+        ));
         backlog_sockets.insert(addr_key, new_backlog.clone());
 
         Some(new_backlog)
@@ -150,6 +170,11 @@ impl BacklogTable {
 
 pub(super) struct Backlog {
     addr: UnixSocketAddrBound,
+    /// The credentials of the process that starts listening.
+    ///
+    /// They will be reported as the peer credentials of the connecting sockets.
+    cred: UCred,
+    is_seqpacket: bool,
     pollee: Pollee,
     backlog: AtomicUsize,
     incoming_conns: SpinLock<Option<VecDeque<Connected>>>,
@@ -157,7 +182,13 @@ pub(super) struct Backlog {
 }
 
 impl Backlog {
-    fn new(addr: UnixSocketAddrBound, pollee: Pollee, backlog: usize, is_shutdown: bool) -> Self {
+    fn new(
+        addr: UnixSocketAddrBound,
+        pollee: Pollee,
+        backlog: usize,
+        is_seqpacket: bool,
+        is_shutdown: bool,
+    ) -> Self {
         let incoming_sockets = if is_shutdown {
             None
         } else {
@@ -166,6 +197,8 @@ impl Backlog {
 
         Self {
             addr,
+            cred: UCred::new_current(),
+            is_seqpacket,
             pollee,
             backlog: AtomicUsize::new(backlog),
             incoming_conns: SpinLock::new(incoming_sockets),
@@ -231,6 +264,10 @@ impl Backlog {
 }
 
 impl Backlog {
+    pub(super) fn is_seqpacket(&self) -> bool {
+        self.is_seqpacket
+    }
+
     pub(super) fn push_incoming(
         &self,
         init: Init,
@@ -257,7 +294,8 @@ impl Backlog {
             ));
         }
 
-        let (client_conn, server_conn) = init.into_connected(self.addr.clone());
+        let (client_conn, server_conn) =
+            init.into_connected(self.addr.clone(), self.cred, self.is_seqpacket);
 
         incoming_conns.push_back(server_conn);
         self.pollee.notify(IoEvents::IN);
@@ -12,10 +12,12 @@ use super::{
 use crate::{
     events::IoEvents,
     fs::file_handle::FileLike,
+    match_sock_option_mut, match_sock_option_ref,
     net::socket::{
+        options::{PassCred, PeerCred, SocketOption},
         private::SocketPrivate,
-        unix::UnixSocketAddr,
-        util::{MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr},
+        unix::{UCred, UnixSocketAddr},
+        util::{ControlMessage, MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr},
         Socket,
     },
     prelude::*,
@@ -23,23 +25,38 @@ use crate::{
     util::{MultiRead, MultiWrite},
 };
 
+/// A UNIX domain socket of the `SOCK_STREAM` or `SOCK_SEQPACKET` type.
+///
+/// Both types are connection-oriented. The only difference is that `SOCK_SEQPACKET` sockets
+/// preserve message boundaries.
 pub struct UnixStreamSocket {
     state: RwMutex<Takeable<State>>,
     is_nonblocking: AtomicBool,
+    is_pass_cred: AtomicBool,
+    is_seqpacket: bool,
 }
 
 impl UnixStreamSocket {
-    pub(super) fn new_init(init: Init, is_nonblocking: bool) -> Arc<Self> {
+    pub(super) fn new_init(init: Init, is_nonblocking: bool, is_seqpacket: bool) -> Arc<Self> {
         Arc::new(Self {
             state: RwMutex::new(Takeable::new(State::Init(init))),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_pass_cred: AtomicBool::new(false),
+            is_seqpacket,
         })
     }
 
-    pub(super) fn new_connected(connected: Connected, is_nonblocking: bool) -> Arc<Self> {
+    pub(super) fn new_connected(
+        connected: Connected,
+        is_nonblocking: bool,
+        is_pass_cred: bool,
+        is_seqpacket: bool,
+    ) -> Arc<Self> {
         Arc::new(Self {
             state: RwMutex::new(Takeable::new(State::Connected(connected))),
             is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_pass_cred: AtomicBool::new(is_pass_cred),
+            is_seqpacket,
         })
     }
 }
@@ -51,30 +68,44 @@ enum State {
 }
 
 impl UnixStreamSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
-        Self::new_init(Init::new(), is_nonblocking)
+    pub fn new(is_nonblocking: bool, is_seqpacket: bool) -> Arc<Self> {
+        Self::new_init(Init::new(), is_nonblocking, is_seqpacket)
     }
 
-    pub fn new_pair(is_nonblocking: bool) -> (Arc<Self>, Arc<Self>) {
-        let (conn_a, conn_b) = Connected::new_pair(None, None, None, None);
+    pub fn new_pair(is_nonblocking: bool, is_seqpacket: bool) -> (Arc<Self>, Arc<Self>) {
+        let cred = UCred::new_current();
+        let (conn_a, conn_b) =
+            Connected::new_pair(None, None, cred, cred, is_seqpacket, None, None);
         (
-            Self::new_connected(conn_a, is_nonblocking),
-            Self::new_connected(conn_b, is_nonblocking),
+            Self::new_connected(conn_a, is_nonblocking, false, is_seqpacket),
+            Self::new_connected(conn_b, is_nonblocking, false, is_seqpacket),
         )
     }
 
-    fn try_send(&self, buf: &mut dyn MultiRead, _flags: SendRecvFlags) -> Result<usize> {
+    fn try_send(
+        &self,
+        buf: &mut dyn MultiRead,
+        control_messages: &mut Vec<ControlMessage>,
+        _flags: SendRecvFlags,
+    ) -> Result<usize> {
         match self.state.read().as_ref() {
-            State::Connected(connected) => connected.try_write(buf),
+            State::Connected(connected) => connected.try_write(buf, control_messages),
             State::Init(_) | State::Listen(_) => {
                 return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
             }
         }
     }
 
-    fn try_recv(&self, buf: &mut dyn MultiWrite, _flags: SendRecvFlags) -> Result<usize> {
+    fn try_recv(
+        &self,
+        buf: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
         match self.state.read().as_ref() {
-            State::Connected(connected) => connected.try_read(buf),
+            State::Connected(connected) => connected.try_read(buf, flags),
+            State::Init(_) | State::Listen(_) if self.is_seqpacket => {
+                return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
+            }
             State::Init(_) | State::Listen(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not connected")
             }
@@ -82,6 +113,13 @@ impl UnixStreamSocket {
     }
 
     fn try_connect(&self, backlog: &Arc<Backlog>) -> Result<()> {
+        if backlog.is_seqpacket() != self.is_seqpacket {
+            return_errno_with_message!(
+                Errno::EPROTOTYPE,
+                "the listening socket is of a different type"
+            );
+        }
+
         let mut state = self.state.write();
 
         state.borrow_result(|owned_state| {
@@ -116,9 +154,23 @@ impl UnixStreamSocket {
         })
     }
 
+    fn is_pass_cred(&self) -> bool {
+        self.is_pass_cred.load(Ordering::Relaxed)
+    }
+
+    fn peer_cred(&self) -> UCred {
+        match self.state.read().as_ref() {
+            State::Connected(connected) => connected.peer_cred(),
+            // Linux reports the credentials of the listening process itself for listening
+            // sockets.
+            State::Listen(listen) => listen.cred(),
+            State::Init(_) => UCred::new_unknown(),
+        }
+    }
+
     fn try_accept(&self) -> Result<(Arc<dyn FileLike>, SocketAddr)> {
         match self.state.read().as_ref() {
-            State::Listen(listen) => listen.try_accept() as _,
+            State::Listen(listen) => listen.try_accept(self.is_pass_cred()) as _,
             State::Init(_) | State::Connected(_) => {
                 return_errno_with_message!(Errno::EINVAL, "the socket is not listening")
             }
@@ -200,7 +252,7 @@ impl Socket for UnixStreamSocket {
                 }
             };
 
-            let listener = match init.listen(backlog) {
+            let listener = match init.listen(backlog, self.is_seqpacket) {
                 Ok(listener) => listener,
                 Err((err, init)) => {
                     return (State::Init(init), Err(err));
@@ -258,15 +310,13 @@ impl Socket for UnixStreamSocket {
         }
 
         let MessageHeader {
-            control_message, ..
+            mut control_messages,
+            ..
         } = message_header;
 
-        if control_message.is_some() {
-            // TODO: Support sending control message
-            warn!("sending control message is not supported");
-        }
-
-        self.block_on(IoEvents::OUT, || self.try_send(reader, flags))
+        self.block_on(IoEvents::OUT, || {
+            self.try_send(reader, &mut control_messages, flags)
+        })
     }
 
     fn recvmsg(
@@ -279,12 +329,47 @@ impl Socket for UnixStreamSocket {
             warn!("unsupported flags: {:?}", flags);
         }
 
-        let received_bytes = self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+        let (received_bytes, mut message_header) =
+            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+
+        let control_messages = &mut message_header.control_messages;
+        let has_cred = control_messages
+            .iter()
+            .any(|control_message| matches!(control_message, ControlMessage::Credentials(_)));
+        if self.is_pass_cred() && !has_cred {
+            // FIXME: Linux records the credentials of the sending process with each message if
+            // `SO_PASSCRED` is enabled. Here we use the credentials of the peer process when the
+            // connection is established instead.
+            let cred = self.peer_cred();
+            control_messages.insert(0, ControlMessage::Credentials(cred));
+        }
 
-        // TODO: Receive control message
+        Ok((received_bytes, message_header))
+    }
 
-        let message_header = MessageHeader::new(None, None);
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            pass_cred: PassCred => {
+                pass_cred.set(self.is_pass_cred());
+            },
+            peer_cred: PeerCred => {
+                peer_cred.set(self.peer_cred());
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+        });
 
-        Ok((received_bytes, message_header))
+        Ok(())
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        match_sock_option_ref!(option, {
+            pass_cred: PassCred => {
+                let is_pass_cred = *pass_cred.get().unwrap();
+                self.is_pass_cred.store(is_pass_cred, Ordering::Relaxed);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+
+        Ok(())
     }
 }
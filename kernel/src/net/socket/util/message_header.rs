@@ -1,32 +1,100 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_bigtcp::wire::IpAddress;
+
 use super::SocketAddr;
-use crate::prelude::*;
+use crate::{fs::file_handle::FileLike, net::socket::unix::UCred, prelude::*};
 
 /// Message header used for sendmsg/recvmsg.
 #[derive(Debug)]
 pub struct MessageHeader {
     pub(in crate::net) addr: Option<SocketAddr>,
-    pub(in crate::net) control_message: Option<ControlMessage>,
+    pub(in crate::net) control_messages: Vec<ControlMessage>,
+    /// Whether the received message is truncated because the buffer is too small.
+    pub(in crate::net) is_truncated: bool,
 }
 
 impl MessageHeader {
     /// Creates a new `MessageHeader`.
-    pub const fn new(addr: Option<SocketAddr>, control_message: Option<ControlMessage>) -> Self {
+    pub fn new(addr: Option<SocketAddr>, control_messages: Vec<ControlMessage>) -> Self {
         Self {
             addr,
-            control_message,
+            control_messages,
+            is_truncated: false,
         }
     }
 
+    /// Marks the received message as truncated, which is reported by `MSG_TRUNC`.
+    pub fn set_truncated(&mut self) {
+        self.is_truncated = true;
+    }
+
+    /// Returns whether the received message is truncated.
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
     /// Returns the socket address.
     pub fn addr(&self) -> Option<&SocketAddr> {
         self.addr.as_ref()
     }
+
+    /// Takes the control messages out of the message header.
+    pub fn take_control_messages(&mut self) -> Vec<ControlMessage> {
+        core::mem::take(&mut self.control_messages)
+    }
 }
 
 /// Control message carried by MessageHeader.
 ///
-/// TODO: Implement the struct. The struct is empty now.
-#[derive(Debug)]
-pub struct ControlMessage;
+/// Currently, only the control messages at the `SOL_SOCKET` level can be sent.
+pub enum ControlMessage {
+    /// Open files passed to the peer (`SCM_RIGHTS`).
+    Rights(Vec<Arc<dyn FileLike>>),
+    /// Credentials of the sending process (`SCM_CREDENTIALS`).
+    Credentials(UCred),
+    /// An error from the error queue (`IP_RECVERR` or `IPV6_RECVERR`).
+    RecvErr(ExtendedError),
+}
+
+/// An extended socket error (`struct sock_extended_err` in Linux).
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/errqueue.h#L8>.
+#[derive(Debug, Clone)]
+pub struct ExtendedError {
+    /// The error number.
+    pub errno: Errno,
+    /// Where the error comes from.
+    pub origin: ErrorOrigin,
+    /// The ICMP message type.
+    pub type_: u8,
+    /// The ICMP message code.
+    pub code: u8,
+    /// The type-specific information (e.g., the MTU for "Packet Too Big" messages).
+    pub info: u32,
+    /// The address of the node that caused the error.
+    pub offender: IpAddress,
+}
+
+/// The origin of an [`ExtendedError`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorOrigin {
+    /// The error comes from an ICMP message.
+    Icmp = 2,
+    /// The error comes from an ICMPv6 message.
+    Icmp6 = 3,
+}
+
+impl Debug for ControlMessage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Rights(files) => f
+                .debug_struct("Rights")
+                .field("num_files", &files.len())
+                .finish(),
+            Self::Credentials(cred) => f.debug_tuple("Credentials").field(cred).finish(),
+            Self::RecvErr(error) => f.debug_tuple("RecvErr").field(error).finish(),
+        }
+    }
+}
@@ -7,9 +7,11 @@ pub(super) mod options;
 mod send_recv_flags;
 mod shutdown_cmd;
 mod socket_addr;
+mod socket_filter;
 
 pub use linger_option::LingerOption;
-pub use message_header::MessageHeader;
+pub use message_header::{ControlMessage, ErrorOrigin, ExtendedError, MessageHeader};
 pub use send_recv_flags::SendRecvFlags;
 pub use shutdown_cmd::SockShutdownCmd;
 pub use socket_addr::SocketAddr;
+pub use socket_filter::{BpfInsn, SocketFilter, BPF_MAXINSNS};
@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Classic BPF socket filters.
+//!
+//! A socket filter is a classic BPF program that is attached to a socket via `SO_ATTACH_FILTER`.
+//! The program runs against each incoming packet and returns the number of bytes of the packet
+//! to accept, where zero means dropping the packet.
+//!
+//! Reference: <https://www.kernel.org/doc/html/v6.0/networking/filter.html>.
+
+use crate::prelude::*;
+
+/// A classic BPF instruction (i.e., `struct sock_filter`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct BpfInsn {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+/// A validated classic BPF program.
+#[derive(Debug, Clone)]
+pub struct SocketFilter {
+    insns: Arc<[BpfInsn]>,
+}
+
+/// The maximum number of instructions in a program.
+pub const BPF_MAXINSNS: usize = 4096;
+
+/// The number of words in the scratch memory.
+const BPF_MEMWORDS: usize = 16;
+
+/// The lowest special offset of `BPF_ABS` loads.
+///
+/// Negative offsets from this one refer to the link layer header (`SKF_LL_OFF`), the network
+/// layer header (`SKF_NET_OFF`) and the ancillary data (`SKF_AD_OFF`), which are not supported.
+const SKF_LL_OFF: i32 = -0x200000;
+
+// Instruction classes:
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// Load sizes:
+const BPF_W: u16 = 0x00;
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+
+// Load modes:
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_IND: u16 = 0x40;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+const BPF_MSH: u16 = 0xa0;
+
+// ALU operations:
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// Jump operations:
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// Operand sources:
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// Miscellaneous operations:
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+impl SocketFilter {
+    /// Creates a socket filter after validating the program.
+    ///
+    /// Like Linux, the program is rejected with `EINVAL` if it contains unknown instructions,
+    /// out-of-range jumps or memory accesses, divisions by constant zero, or if it does not end
+    /// with a return instruction. Unlike Linux, loads from the ancillary data or other special
+    /// offsets (e.g., `SKF_AD_OFF + SKF_AD_PROTOCOL`) are also rejected.
+    pub fn new(insns: Vec<BpfInsn>) -> Result<Self> {
+        if insns.is_empty() || insns.len() > BPF_MAXINSNS {
+            return_errno_with_message!(Errno::EINVAL, "the BPF program length is invalid");
+        }
+
+        for (pc, insn) in insns.iter().enumerate() {
+            if !is_valid_insn(insn, insns.len() - pc - 1) {
+                return_errno_with_message!(Errno::EINVAL, "the BPF program is invalid");
+            }
+            if is_special_load(insn) {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the BPF ancillary data loads are not supported"
+                );
+            }
+        }
+
+        let last_code = insns.last().unwrap().code;
+        if last_code != BPF_RET | BPF_K && last_code != BPF_RET | BPF_A {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the BPF program does not end with a return instruction"
+            );
+        }
+
+        Ok(Self {
+            insns: insns.into(),
+        })
+    }
+
+    /// Runs the program against the packet and returns the number of bytes to accept.
+    pub fn run(&self, packet: &[u8]) -> u32 {
+        let mut a: u32 = 0;
+        let mut x: u32 = 0;
+        let mut mem = [0u32; BPF_MEMWORDS];
+        let mut pc = 0;
+
+        // Out-of-bounds loads and divisions by zero make the program return zero.
+        loop {
+            let BpfInsn { code, jt, jf, k } = self.insns[pc];
+            pc += 1;
+
+            match code & 0x07 {
+                BPF_LD => {
+                    a = match code & 0xe0 {
+                        BPF_IMM => k,
+                        BPF_ABS => match load(packet, code & 0x18, k) {
+                            Some(val) => val,
+                            None => return 0,
+                        },
+                        BPF_IND => match load(packet, code & 0x18, x.wrapping_add(k)) {
+                            Some(val) => val,
+                            None => return 0,
+                        },
+                        BPF_MEM => mem[k as usize],
+                        BPF_LEN => packet.len() as u32,
+                        _ => unreachable!(),
+                    };
+                }
+                BPF_LDX => {
+                    x = match code & 0xe0 {
+                        BPF_IMM => k,
+                        BPF_MEM => mem[k as usize],
+                        BPF_LEN => packet.len() as u32,
+                        BPF_MSH => match packet.get(k as usize) {
+                            Some(byte) => u32::from(byte & 0xf) << 2,
+                            None => return 0,
+                        },
+                        _ => unreachable!(),
+                    };
+                }
+                BPF_ST => mem[k as usize] = a,
+                BPF_STX => mem[k as usize] = x,
+                BPF_ALU => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    a = match code & 0xf0 {
+                        BPF_ADD => a.wrapping_add(operand),
+                        BPF_SUB => a.wrapping_sub(operand),
+                        BPF_MUL => a.wrapping_mul(operand),
+                        BPF_DIV if operand == 0 => return 0,
+                        BPF_DIV => a / operand,
+                        BPF_MOD if operand == 0 => return 0,
+                        BPF_MOD => a % operand,
+                        BPF_OR => a | operand,
+                        BPF_AND => a & operand,
+                        BPF_XOR => a ^ operand,
+                        BPF_LSH => a.checked_shl(operand).unwrap_or(0),
+                        BPF_RSH => a.checked_shr(operand).unwrap_or(0),
+                        BPF_NEG => a.wrapping_neg(),
+                        _ => unreachable!(),
+                    };
+                }
+                BPF_JMP => {
+                    let operand = if code & BPF_X != 0 { x } else { k };
+                    let cond = match code & 0xf0 {
+                        BPF_JA => {
+                            pc += k as usize;
+                            continue;
+                        }
+                        BPF_JEQ => a == operand,
+                        BPF_JGT => a > operand,
+                        BPF_JGE => a >= operand,
+                        BPF_JSET => a & operand != 0,
+                        _ => unreachable!(),
+                    };
+                    pc += if cond { jt as usize } else { jf as usize };
+                }
+                BPF_RET => {
+                    return if code & 0x18 == BPF_A { a } else { k };
+                }
+                BPF_MISC => match code & 0xf8 {
+                    BPF_TAX => x = a,
+                    BPF_TXA => a = x,
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Checks whether the instruction is valid, given the number of instructions after it.
+fn is_valid_insn(insn: &BpfInsn, remaining: usize) -> bool {
+    let BpfInsn { code, jt, jf, k } = *insn;
+    let is_valid_mem = (k as usize) < BPF_MEMWORDS;
+
+    let class = code & 0x07;
+    match class {
+        BPF_LD | BPF_LDX => {
+            let size = code & 0x18;
+            let mode = code & 0xe0;
+            if code != class | size | mode {
+                return false;
+            }
+            match (class, mode, size) {
+                (_, BPF_IMM | BPF_LEN, BPF_W) => true,
+                (_, BPF_MEM, BPF_W) => is_valid_mem,
+                (BPF_LD, BPF_ABS | BPF_IND, BPF_W | BPF_H | BPF_B) => true,
+                (BPF_LDX, BPF_MSH, BPF_B) => true,
+                _ => false,
+            }
+        }
+        BPF_ST | BPF_STX => code == class && is_valid_mem,
+        BPF_ALU => {
+            let op = code & 0xf0;
+            let src = code & 0x08;
+            if code != class | op | src {
+                return false;
+            }
+            match op {
+                BPF_NEG => src == BPF_K,
+                BPF_DIV | BPF_MOD => src == BPF_X || k != 0,
+                BPF_ADD | BPF_SUB | BPF_MUL | BPF_OR | BPF_AND | BPF_LSH | BPF_RSH | BPF_XOR => {
+                    true
+                }
+                _ => false,
+            }
+        }
+        BPF_JMP => {
+            let op = code & 0xf0;
+            let src = code & 0x08;
+            if code != class | op | src {
+                return false;
+            }
+            match op {
+                BPF_JA => src == BPF_K && (k as usize) < remaining,
+                BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET => {
+                    (jt as usize) < remaining && (jf as usize) < remaining
+                }
+                _ => false,
+            }
+        }
+        BPF_RET => code == BPF_RET | BPF_K || code == BPF_RET | BPF_A,
+        BPF_MISC => code == BPF_MISC | BPF_TAX || code == BPF_MISC | BPF_TXA,
+        _ => false,
+    }
+}
+
+/// Checks whether the instruction loads from a special offset, such as `SKF_AD_OFF`.
+///
+/// Such loads do not read the packet, so running them as normal loads would silently drop every
+/// packet instead.
+fn is_special_load(insn: &BpfInsn) -> bool {
+    insn.code & 0x07 == BPF_LD
+        && insn.code & 0xe0 == BPF_ABS
+        && (SKF_LL_OFF..0).contains(&(insn.k as i32))
+}
+
+/// Loads a big-endian value of the size from the packet at the offset.
+fn load(packet: &[u8], size: u16, offset: u32) -> Option<u32> {
+    let offset = offset as usize;
+    let val = match size {
+        BPF_W => u32::from_be_bytes(
+            packet
+                .get(offset..offset.checked_add(4)?)?
+                .try_into()
+                .ok()?,
+        ),
+        BPF_H => u32::from(u16::from_be_bytes(
+            packet
+                .get(offset..offset.checked_add(2)?)?
+                .try_into()
+                .ok()?,
+        )),
+        BPF_B => u32::from(*packet.get(offset)?),
+        _ => unreachable!(),
+    };
+    Some(val)
+}
@@ -25,7 +25,7 @@ use crate::{
         process_vm::{AuxKey, AuxVec, ProcessVm},
         TermStatus,
     },
-    vdso::{vdso_vmo, VDSO_VMO_SIZE},
+    vdso::{vdso_vmo, VDSO_TEXT_OFFSET, VDSO_VMO_SIZE},
     vm::{
         perms::VmPerms,
         util::duplicate_frame,
@@ -442,7 +442,7 @@ fn map_vdso_to_vm(process_vm: &ProcessVm) -> Option<Vaddr> {
         .vmo(vdso_vmo.dup().unwrap());
 
     let vdso_data_base = options.build().unwrap();
-    let vdso_text_base = vdso_data_base + 0x4000;
+    let vdso_text_base = vdso_data_base + VDSO_TEXT_OFFSET;
 
     let data_perms = VmPerms::READ | VmPerms::WRITE;
     let text_perms = VmPerms::READ | VmPerms::EXEC;
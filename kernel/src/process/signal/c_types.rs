@@ -57,6 +57,14 @@ impl siginfo_t {
     pub fn si_addr(&self) -> Vaddr {
         read_union_field!(self, Self, siginfo_fields.sigfault.addr)
     }
+
+    pub fn set_si_pid_uid(&mut self, pid: Pid, uid: Uid) {
+        self.siginfo_fields.common.first.piduid = siginfo_piduid_t { pid, uid };
+    }
+
+    pub fn set_si_value(&mut self, value: sigval_t) {
+        self.siginfo_fields.common.second.value = value;
+    }
 }
 
 #[derive(Clone, Copy, Pod)]
@@ -118,6 +126,10 @@ pub union sigval_t {
 }
 
 impl sigval_t {
+    pub fn from_ptr(ptr: Vaddr) -> Self {
+        Self { sigval_ptr: ptr }
+    }
+
     pub fn read_int(&self) -> i32 {
         read_union_field!(self, Self, sigval_int)
     }
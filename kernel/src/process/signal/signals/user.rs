@@ -5,8 +5,8 @@
 use super::Signal;
 use crate::process::{
     signal::{
-        c_types::siginfo_t,
-        constants::{SI_QUEUE, SI_TKILL, SI_USER},
+        c_types::{siginfo_t, sigval_t},
+        constants::{SI_MESGQ, SI_QUEUE, SI_TKILL, SI_USER},
         sig_num::SigNum,
     },
     Pid, Uid,
@@ -25,6 +25,8 @@ pub enum UserSignalKind {
     Kill,
     Tkill,
     Sigqueue,
+    /// A POSIX message queue notification carrying the `sigev_value` given to `mq_notify`.
+    Mesgq(usize),
 }
 
 impl UserSignal {
@@ -56,13 +58,19 @@ impl Signal for UserSignal {
             UserSignalKind::Kill => SI_USER,
             UserSignalKind::Tkill => SI_TKILL,
             UserSignalKind::Sigqueue => SI_QUEUE,
+            UserSignalKind::Mesgq(_) => SI_MESGQ,
         };
 
-        siginfo_t::new(self.num, code)
+        let mut info = siginfo_t::new(self.num, code);
+        if let UserSignalKind::Mesgq(value) = self.kind {
+            info.set_si_pid_uid(self.pid, self.uid);
+            info.set_si_value(sigval_t::from_ptr(value));
+        }
         // info.set_si_pid(self.pid);
         // info.set_si_uid(self.uid);
         // if let UserSignalKind::Sigqueue(val) = self.kind {
         //     info.set_si_value(val);
         // }
+        info
     }
 }
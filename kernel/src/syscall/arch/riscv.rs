@@ -60,7 +60,15 @@ use crate::syscall::{
     mmap::sys_mmap,
     mount::sys_mount,
     mprotect::sys_mprotect,
+    mq_getsetattr::sys_mq_getsetattr,
+    mq_notify::sys_mq_notify,
+    mq_open::{sys_mq_open, sys_mq_unlink},
+    mq_timedsend::{sys_mq_timedreceive, sys_mq_timedsend},
     mremap::sys_mremap,
+    msgctl::sys_msgctl,
+    msgget::sys_msgget,
+    msgrcv::sys_msgrcv,
+    msgsnd::sys_msgsnd,
     msync::sys_msync,
     munmap::sys_munmap,
     nanosleep::{sys_clock_nanosleep, sys_nanosleep},
@@ -115,6 +123,10 @@ use crate::syscall::{
     setsid::sys_setsid,
     setsockopt::sys_setsockopt,
     setuid::sys_setuid,
+    shmat::sys_shmat,
+    shmctl::sys_shmctl,
+    shmdt::sys_shmdt,
+    shmget::sys_shmget,
     shutdown::sys_shutdown,
     sigaltstack::sys_sigaltstack,
     signalfd::sys_signalfd4,
@@ -261,10 +273,23 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_GETGID = 176             => sys_getgid(args[..0]);
     SYS_GETEGID = 177            => sys_getegid(args[..0]);
     SYS_GETTID = 178             => sys_gettid(args[..0]);
+    SYS_MQ_OPEN = 180            => sys_mq_open(args[..4]);
+    SYS_MQ_UNLINK = 181          => sys_mq_unlink(args[..1]);
+    SYS_MQ_TIMEDSEND = 182       => sys_mq_timedsend(args[..5]);
+    SYS_MQ_TIMEDRECEIVE = 183    => sys_mq_timedreceive(args[..5]);
+    SYS_MQ_NOTIFY = 184          => sys_mq_notify(args[..2]);
+    SYS_MQ_GETSETATTR = 185      => sys_mq_getsetattr(args[..3]);
+    SYS_MSGGET = 186             => sys_msgget(args[..2]);
+    SYS_MSGCTL = 187             => sys_msgctl(args[..3]);
+    SYS_MSGRCV = 188             => sys_msgrcv(args[..5]);
+    SYS_MSGSND = 189             => sys_msgsnd(args[..4]);
     SYS_SEMGET = 190             => sys_semget(args[..3]);
     SYS_SEMCTL = 191             => sys_semctl(args[..4]);
     SYS_SEMOP = 193              => sys_semop(args[..3]);
-    SYS_SEMGET_ = 194            => sys_semget(args[..3]);
+    SYS_SHMGET = 194             => sys_shmget(args[..3]);
+    SYS_SHMCTL = 195             => sys_shmctl(args[..3]);
+    SYS_SHMAT = 196              => sys_shmat(args[..3]);
+    SYS_SHMDT = 197              => sys_shmdt(args[..1]);
     SYS_SOCKET = 198             => sys_socket(args[..3]);
     SYS_SOCKETPAIR = 199         => sys_socketpair(args[..4]);
     SYS_BIND = 200               => sys_bind(args[..3]);
@@ -69,7 +69,15 @@ use crate::syscall::{
     mmap::sys_mmap,
     mount::sys_mount,
     mprotect::sys_mprotect,
+    mq_getsetattr::sys_mq_getsetattr,
+    mq_notify::sys_mq_notify,
+    mq_open::{sys_mq_open, sys_mq_unlink},
+    mq_timedsend::{sys_mq_timedreceive, sys_mq_timedsend},
     mremap::sys_mremap,
+    msgctl::sys_msgctl,
+    msgget::sys_msgget,
+    msgrcv::sys_msgrcv,
+    msgsnd::sys_msgsnd,
     msync::sys_msync,
     munmap::sys_munmap,
     nanosleep::{sys_clock_nanosleep, sys_nanosleep},
@@ -131,6 +139,10 @@ use crate::syscall::{
     setsockopt::sys_setsockopt,
     setuid::sys_setuid,
     setxattr::{sys_fsetxattr, sys_lsetxattr, sys_setxattr},
+    shmat::sys_shmat,
+    shmctl::sys_shmctl,
+    shmdt::sys_shmdt,
+    shmget::sys_shmget,
     shutdown::sys_shutdown,
     sigaltstack::sys_sigaltstack,
     signalfd::{sys_signalfd, sys_signalfd4},
@@ -189,6 +201,9 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_MSYNC = 26             => sys_msync(args[..3]);
     SYS_SCHED_YIELD = 24       => sys_sched_yield(args[..0]);
     SYS_MADVISE = 28           => sys_madvise(args[..3]);
+    SYS_SHMGET = 29            => sys_shmget(args[..3]);
+    SYS_SHMAT = 30             => sys_shmat(args[..3]);
+    SYS_SHMCTL = 31            => sys_shmctl(args[..3]);
     SYS_DUP = 32               => sys_dup(args[..1]);
     SYS_DUP2 = 33              => sys_dup2(args[..2]);
     SYS_PAUSE = 34             => sys_pause(args[..0]);
@@ -224,6 +239,11 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_SEMGET = 64            => sys_semget(args[..3]);
     SYS_SEMOP = 65             => sys_semop(args[..3]);
     SYS_SEMCTL = 66            => sys_semctl(args[..4]);
+    SYS_SHMDT = 67             => sys_shmdt(args[..1]);
+    SYS_MSGGET = 68            => sys_msgget(args[..2]);
+    SYS_MSGSND = 69            => sys_msgsnd(args[..4]);
+    SYS_MSGRCV = 70            => sys_msgrcv(args[..5]);
+    SYS_MSGCTL = 71            => sys_msgctl(args[..3]);
     SYS_FCNTL = 72             => sys_fcntl(args[..3]);
     SYS_FLOCK = 73             => sys_flock(args[..2]);
     SYS_FSYNC = 74             => sys_fsync(args[..1]);
@@ -330,6 +350,12 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_EPOLL_CTL = 233        => sys_epoll_ctl(args[..4]);
     SYS_TGKILL = 234           => sys_tgkill(args[..3]);
     SYS_UTIMES = 235           => sys_utimes(args[..2]);
+    SYS_MQ_OPEN = 240          => sys_mq_open(args[..4]);
+    SYS_MQ_UNLINK = 241        => sys_mq_unlink(args[..1]);
+    SYS_MQ_TIMEDSEND = 242     => sys_mq_timedsend(args[..5]);
+    SYS_MQ_TIMEDRECEIVE = 243  => sys_mq_timedreceive(args[..5]);
+    SYS_MQ_NOTIFY = 244        => sys_mq_notify(args[..2]);
+    SYS_MQ_GETSETATTR = 245    => sys_mq_getsetattr(args[..3]);
     SYS_WAITID = 247           => sys_waitid(args[..5]);
     SYS_OPENAT = 257           => sys_openat(args[..4]);
     SYS_MKDIRAT = 258          => sys_mkdirat(args[..3]);
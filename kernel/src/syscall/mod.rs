@@ -74,7 +74,15 @@ mod mknod;
 mod mmap;
 mod mount;
 mod mprotect;
+mod mq_getsetattr;
+mod mq_notify;
+mod mq_open;
+mod mq_timedsend;
 mod mremap;
+mod msgctl;
+mod msgget;
+mod msgrcv;
+mod msgsnd;
 mod msync;
 mod munmap;
 mod nanosleep;
@@ -136,6 +144,10 @@ mod setsid;
 mod setsockopt;
 mod setuid;
 mod setxattr;
+mod shmat;
+mod shmctl;
+mod shmdt;
+mod shmget;
 mod shutdown;
 mod sigaltstack;
 mod signalfd;
@@ -10,6 +10,7 @@ use crate::{
         path::Dentry,
         utils::{FileSystem, InodeType},
     },
+    ipc::mqueue::mqueue_fs,
     prelude::*,
     syscall::constants::MAX_FILENAME_LEN,
 };
@@ -186,6 +187,7 @@ fn get_fs(
             let overlay_fs = create_overlayfs(data.as_ref(), ctx)?;
             Ok(overlay_fs)
         }
+        "mqueue" => Ok(mqueue_fs().clone()),
         _ => return_errno_with_message!(Errno::EINVAL, "Invalid fs type"),
     }
 }
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FileDesc},
+        utils::StatusFlags,
+    },
+    ipc::mqueue::{MqAttr, MqueueFile},
+    prelude::*,
+};
+
+pub fn sys_mq_getsetattr(
+    mqdes: FileDesc,
+    new_attr_addr: Vaddr,
+    old_attr_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "mqdes = {}, new_attr_addr = {:#x}, old_attr_addr = {:#x}",
+        mqdes, new_attr_addr, old_attr_addr
+    );
+
+    let new_attr = if new_attr_addr == 0 {
+        None
+    } else {
+        let attr = ctx.user_space().read_val::<MqAttr>(new_attr_addr)?;
+        if attr.mq_flags & !(StatusFlags::O_NONBLOCK.bits() as i64) != 0 {
+            return_errno_with_message!(Errno::EINVAL, "only O_NONBLOCK can be set");
+        }
+        Some(attr)
+    };
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, mqdes);
+    let mqueue_file = file.downcast_ref::<MqueueFile>().ok_or_else(|| {
+        Error::with_message(Errno::EBADF, "the file is not a message queue descriptor")
+    })?;
+
+    let mut old_attr = mqueue_file.inode().queue().attr();
+    old_attr.mq_flags = mqueue_file.status_flags().bits() as i64;
+
+    // Only `mq_flags` can be changed, and the other fields are ignored.
+    if let Some(new_attr) = new_attr {
+        let flags = StatusFlags::from_bits_truncate(new_attr.mq_flags as u32);
+        mqueue_file.set_status_flags(flags)?;
+    }
+
+    if old_attr_addr != 0 {
+        ctx.user_space().write_val(old_attr_addr, &old_attr)?;
+    }
+    Ok(SyscallReturn::Return(0))
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::file_table::{get_file_fast, FileDesc},
+    ipc::mqueue::{MqNotify, MqNotifyKind, MqueueFile, NOTIFY_COOKIE_LEN},
+    net::socket::netlink::NetlinkRouteSocket,
+    prelude::*,
+    process::signal::{
+        c_types::{sigevent_t, SigNotify},
+        sig_num::SigNum,
+    },
+};
+
+pub fn sys_mq_notify(
+    mqdes: FileDesc,
+    sigevent_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("mqdes = {}, sigevent_addr = {:#x}", mqdes, sigevent_addr);
+
+    // Build the notification before borrowing the file table, since `SIGEV_THREAD` needs to
+    // look up the netlink socket in the table.
+    let kind = if sigevent_addr == 0 {
+        None
+    } else {
+        let sig_event = ctx.user_space().read_val::<sigevent_t>(sigevent_addr)?;
+        Some(read_notify_kind(&sig_event, ctx)?)
+    };
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, mqdes);
+    let mqueue_file = file.downcast_ref::<MqueueFile>().ok_or_else(|| {
+        Error::with_message(Errno::EBADF, "the file is not a message queue descriptor")
+    })?;
+    let queue = mqueue_file.inode().queue();
+
+    match kind {
+        None => queue.remove_notify(ctx.process.pid()),
+        Some(kind) => queue.set_notify(MqNotify::new(&current!(), kind))?,
+    }
+    Ok(SyscallReturn::Return(0))
+}
+
+fn read_notify_kind(sig_event: &sigevent_t, ctx: &Context) -> Result<MqNotifyKind> {
+    let kind = match SigNotify::try_from(sig_event.sigev_notify)? {
+        SigNotify::SIGEV_NONE => MqNotifyKind::None,
+        SigNotify::SIGEV_SIGNAL => {
+            let signo = u8::try_from(sig_event.sigev_signo)
+                .ok()
+                .and_then(|signo| SigNum::try_from(signo).ok())
+                .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid signal number"))?;
+            MqNotifyKind::Signal {
+                signo,
+                value: sig_event.sigev_value.read_ptr(),
+            }
+        }
+        // The C library creates a netlink socket, whose descriptor is passed in `sigev_signo`,
+        // and starts the notification thread once the cookie is received from the socket.
+        SigNotify::SIGEV_THREAD => {
+            let socket = {
+                let file_table = ctx.thread_local.borrow_file_table();
+                let file_table_locked = file_table.unwrap().read();
+                file_table_locked
+                    .get_file(sig_event.sigev_signo as FileDesc)?
+                    .clone()
+            };
+            if socket.downcast_ref::<NetlinkRouteSocket>().is_none() {
+                return_errno_with_message!(Errno::EBADF, "the file is not a netlink socket");
+            }
+
+            let mut cookie = [0u8; NOTIFY_COOKIE_LEN];
+            ctx.user_space().read_bytes(
+                sig_event.sigev_value.read_ptr(),
+                &mut cookie.as_mut_slice().into(),
+            )?;
+            MqNotifyKind::Thread { socket, cookie }
+        }
+        SigNotify::SIGEV_THREAD_ID => {
+            return_errno_with_message!(Errno::EINVAL, "SIGEV_THREAD_ID is not supported")
+        }
+    };
+    Ok(kind)
+}
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_table::FdFlags,
+        utils::{AccessMode, CreationFlags, Inode, InodeMode, Permission, StatusFlags, NAME_MAX},
+    },
+    ipc::mqueue::{
+        mqueue_fs, MessageQueue, MqAttr, MqueueFile, DFLT_MSG, DFLT_MSGMAX, DFLT_MSGSIZE,
+        DFLT_MSGSIZEMAX, HARD_MSGMAX, HARD_MSGSIZEMAX,
+    },
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    syscall::constants::MAX_FILENAME_LEN,
+};
+
+pub fn sys_mq_open(
+    name_addr: Vaddr,
+    flags: u32,
+    mode: u16,
+    attr_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let name = read_queue_name(name_addr, ctx)?;
+    let access_mode = AccessMode::from_u32(flags)?;
+    let creation_flags = CreationFlags::from_bits_truncate(flags);
+    let is_nonblocking = StatusFlags::from_bits_truncate(flags).contains(StatusFlags::O_NONBLOCK);
+    debug!(
+        "name = {:?}, flags = {:#x}, mode = {:#o}, attr_addr = {:#x}",
+        name, flags, mode, attr_addr
+    );
+
+    let fs = mqueue_fs();
+    let inode = match fs.lookup(&name) {
+        Ok(inode) => {
+            if creation_flags.contains(CreationFlags::O_CREAT | CreationFlags::O_EXCL) {
+                return_errno_with_message!(Errno::EEXIST, "the message queue already exists");
+            }
+            inode.check_permission(Permission::from(access_mode))?;
+            inode
+        }
+        Err(err) if err.error() == Errno::ENOENT => {
+            if !creation_flags.contains(CreationFlags::O_CREAT) {
+                return Err(err);
+            }
+
+            let queue = if attr_addr == 0 {
+                MessageQueue::new(DFLT_MSG, DFLT_MSGSIZE)
+            } else {
+                let attr = ctx.user_space().read_val::<MqAttr>(attr_addr)?;
+                let (max_msgs, msg_size) = check_attr(&attr, ctx)?;
+                MessageQueue::new(max_msgs, msg_size)
+            };
+            let credentials = ctx.posix_thread.credentials();
+            let mask_mode = mode & !ctx.posix_thread.fs().umask().read().get();
+            fs.create(
+                &name,
+                InodeMode::from_bits_truncate(mask_mode),
+                credentials.fsuid(),
+                credentials.fsgid(),
+                queue,
+            )?
+        }
+        Err(err) => return Err(err),
+    };
+
+    let file = Arc::new(MqueueFile::new(inode, access_mode, is_nonblocking));
+    let fd = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let mut file_table_locked = file_table.unwrap().write();
+        let fd_flags = if creation_flags.contains(CreationFlags::O_CLOEXEC) {
+            FdFlags::CLOEXEC
+        } else {
+            FdFlags::empty()
+        };
+        file_table_locked.insert(file, fd_flags)
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+pub fn sys_mq_unlink(name_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let name = read_queue_name(name_addr, ctx)?;
+    debug!("name = {:?}", name);
+
+    mqueue_fs().remove(&name)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Reads the name of a queue, which has had its leading slash removed by the C library.
+fn read_queue_name(name_addr: Vaddr, ctx: &Context) -> Result<String> {
+    let name = ctx.user_space().read_cstring(name_addr, MAX_FILENAME_LEN)?;
+    let name = name.to_string_lossy().into_owned();
+
+    if name.is_empty() {
+        return_errno_with_message!(Errno::ENOENT, "the name is empty");
+    }
+    if name.len() > NAME_MAX {
+        return_errno_with_message!(Errno::ENAMETOOLONG, "the name is too long");
+    }
+    if name.contains('/') {
+        return_errno_with_message!(Errno::EACCES, "the name contains slashes");
+    }
+    Ok(name)
+}
+
+/// Checks the attributes given to create a queue and returns the maximum number of messages
+/// and the maximum size of a message.
+fn check_attr(attr: &MqAttr, ctx: &Context) -> Result<(usize, usize)> {
+    if attr.mq_maxmsg <= 0 || attr.mq_msgsize <= 0 {
+        return_errno_with_message!(Errno::EINVAL, "the attributes must be positive");
+    }
+    let max_msgs = attr.mq_maxmsg as usize;
+    let msg_size = attr.mq_msgsize as usize;
+
+    if max_msgs > HARD_MSGMAX || msg_size > HARD_MSGSIZEMAX {
+        return_errno_with_message!(Errno::EINVAL, "the attributes exceed the hard limits");
+    }
+    let is_privileged = ctx
+        .posix_thread
+        .credentials()
+        .effective_capset()
+        .contains(CapSet::SYS_RESOURCE);
+    if !is_privileged && (max_msgs > DFLT_MSGMAX || msg_size > DFLT_MSGSIZEMAX) {
+        return_errno_with_message!(Errno::EINVAL, "the attributes exceed the limits");
+    }
+
+    Ok((max_msgs, msg_size))
+}
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FileDesc},
+    },
+    ipc::mqueue::MqueueFile,
+    prelude::*,
+    time::{clocks::RealTimeClock, timespec_t},
+};
+
+pub fn sys_mq_timedsend(
+    mqdes: FileDesc,
+    msg_addr: Vaddr,
+    msg_len: usize,
+    msg_prio: u32,
+    abs_timeout_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "mqdes = {}, msg_addr = {:#x}, msg_len = {}, msg_prio = {}, abs_timeout_addr = {:#x}",
+        mqdes, msg_addr, msg_len, msg_prio, abs_timeout_addr
+    );
+
+    let timeout = read_timeout(abs_timeout_addr, ctx)?;
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, mqdes);
+    let mqueue_file = file.downcast_ref::<MqueueFile>().ok_or_else(|| {
+        Error::with_message(Errno::EBADF, "the file is not a message queue descriptor")
+    })?;
+    if !mqueue_file.access_mode().is_writable() {
+        return_errno_with_message!(Errno::EBADF, "the queue is not opened for writing");
+    }
+
+    let queue = mqueue_file.inode().queue();
+    if msg_len > queue.attr().mq_msgsize as usize {
+        return_errno_with_message!(Errno::EMSGSIZE, "the message is too long");
+    }
+    let mut msg = vec![0u8; msg_len];
+    ctx.user_space()
+        .read_bytes(msg_addr, &mut VmWriter::from(msg.as_mut_slice()))?;
+
+    queue.send(
+        msg,
+        msg_prio,
+        mqueue_file.is_nonblocking(),
+        timeout.as_ref(),
+        ctx,
+    )?;
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_mq_timedreceive(
+    mqdes: FileDesc,
+    msg_addr: Vaddr,
+    msg_len: usize,
+    msg_prio_addr: Vaddr,
+    abs_timeout_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "mqdes = {}, msg_addr = {:#x}, msg_len = {}, msg_prio_addr = {:#x}, abs_timeout_addr = {:#x}",
+        mqdes, msg_addr, msg_len, msg_prio_addr, abs_timeout_addr
+    );
+
+    let timeout = read_timeout(abs_timeout_addr, ctx)?;
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, mqdes);
+    let mqueue_file = file.downcast_ref::<MqueueFile>().ok_or_else(|| {
+        Error::with_message(Errno::EBADF, "the file is not a message queue descriptor")
+    })?;
+    if !mqueue_file.access_mode().is_readable() {
+        return_errno_with_message!(Errno::EBADF, "the queue is not opened for reading");
+    }
+
+    let user_space = ctx.user_space();
+    let mut writer = user_space.writer(msg_addr, msg_len)?;
+    let (len, prio) = mqueue_file.inode().queue().receive(
+        &mut writer,
+        mqueue_file.is_nonblocking(),
+        timeout.as_ref(),
+    )?;
+
+    if msg_prio_addr != 0 {
+        user_space.write_val(msg_prio_addr, &prio)?;
+    }
+    Ok(SyscallReturn::Return(len as _))
+}
+
+/// Reads the absolute timeout measured against `CLOCK_REALTIME` and converts it to a relative
+/// one.
+fn read_timeout(abs_timeout_addr: Vaddr, ctx: &Context) -> Result<Option<Duration>> {
+    if abs_timeout_addr == 0 {
+        return Ok(None);
+    }
+
+    let timespec = ctx.user_space().read_val::<timespec_t>(abs_timeout_addr)?;
+    let abs_timeout = Duration::try_from(timespec)?;
+    let now = RealTimeClock::get().read_time();
+    Ok(Some(abs_timeout.saturating_sub(now)))
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{
+        msg::{
+            msg_queue::{get_msg_queue, msg_queue_ids, remove_msg_queue},
+            MsgControlCmd, MsgStat, MSGMAX, MSGMNB, MSGMNI,
+        },
+        IpcPerm64,
+    },
+    prelude::*,
+};
+
+pub fn sys_msgctl(msqid: i32, cmd: i32, buf: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    if msqid < 0 {
+        return_errno!(Errno::EINVAL);
+    }
+
+    let cmd = MsgControlCmd::try_from(cmd)?;
+    debug!(
+        "[sys_msgctl] msqid = {}, cmd = {:?}, buf = {:#x}",
+        msqid, cmd, buf
+    );
+
+    let user_space = ctx.user_space();
+    let credentials = ctx.posix_thread.credentials();
+
+    match cmd {
+        MsgControlCmd::IPC_INFO | MsgControlCmd::MSG_INFO => {
+            let mut info = MsgInfo {
+                msgpool: MSGMNI as i32 * MSGMNB as i32 / 1024,
+                msgmap: MSGMNB as i32,
+                msgmax: MSGMAX as i32,
+                msgmnb: MSGMNB as i32,
+                msgmni: MSGMNI as i32,
+                msgssz: MSGSSZ,
+                msgtql: MSGMNB as i32,
+                msgseg: MSGSEG,
+                ..Default::default()
+            };
+            // `MSG_INFO` reports the usage instead of some of the limits.
+            let ids = msg_queue_ids();
+            if matches!(cmd, MsgControlCmd::MSG_INFO) {
+                let stats = ids
+                    .iter()
+                    .filter_map(|msqid| get_msg_queue(*msqid).ok())
+                    .map(|queue| queue.stat())
+                    .collect::<Vec<_>>();
+                info.msgpool = ids.len() as i32;
+                info.msgmap = stats.iter().map(|stat| stat.qnum as i32).sum();
+                info.msgtql = stats.iter().map(|stat| stat.cbytes as i32).sum();
+            }
+            user_space.write_val(buf, &info)?;
+
+            return Ok(SyscallReturn::Return(
+                ids.last().copied().unwrap_or(0) as isize
+            ));
+        }
+        MsgControlCmd::IPC_STAT | MsgControlCmd::MSG_STAT | MsgControlCmd::MSG_STAT_ANY => {
+            let queue = get_msg_queue(msqid)?;
+            if !matches!(cmd, MsgControlCmd::MSG_STAT_ANY) {
+                queue.check_access(&credentials, 0o4)?;
+            }
+            user_space.write_val(buf, &MsqidDs::from(queue.stat()))?;
+
+            // `MSG_STAT` and `MSG_STAT_ANY` take an index and return the identifier, which are
+            // the same here.
+            if !matches!(cmd, MsgControlCmd::IPC_STAT) {
+                return Ok(SyscallReturn::Return(msqid as isize));
+            }
+        }
+        MsgControlCmd::IPC_SET => {
+            let msqid_ds = user_space.read_val::<MsqidDs>(buf)?;
+            let queue = get_msg_queue(msqid)?;
+            if !queue.is_owner(&credentials) {
+                return_errno!(Errno::EPERM);
+            }
+
+            let perm = &msqid_ds.msg_perm;
+            queue.set(
+                perm.uid,
+                perm.gid,
+                perm.mode as u16,
+                msqid_ds.msg_qbytes as usize,
+                &credentials,
+            )?;
+        }
+        MsgControlCmd::IPC_RMID => {
+            let queue = get_msg_queue(msqid)?;
+            if !queue.is_owner(&credentials) {
+                return_errno!(Errno::EPERM);
+            }
+
+            remove_msg_queue(msqid)?;
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Size of a message segment, which is only reported to the user.
+const MSGSSZ: i32 = 16;
+/// Number of message segments, which is only reported to the user.
+const MSGSEG: u16 = 0xffff;
+
+/// The status of a queue, i.e., `struct msqid64_ds`.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+struct MsqidDs {
+    msg_perm: IpcPerm64,
+    msg_stime: i64,
+    msg_rtime: i64,
+    msg_ctime: i64,
+    msg_cbytes: u64,
+    msg_qnum: u64,
+    msg_qbytes: u64,
+    msg_lspid: i32,
+    msg_lrpid: i32,
+    __unused4: u64,
+    __unused5: u64,
+}
+
+impl From<MsgStat> for MsqidDs {
+    fn from(stat: MsgStat) -> Self {
+        Self {
+            msg_perm: IpcPerm64 {
+                key: stat.key,
+                uid: stat.uid,
+                gid: stat.gid,
+                cuid: stat.cuid,
+                cgid: stat.cgid,
+                mode: stat.mode as u32,
+                ..Default::default()
+            },
+            msg_stime: stat.stime as i64,
+            msg_rtime: stat.rtime as i64,
+            msg_ctime: stat.ctime as i64,
+            msg_cbytes: stat.cbytes as u64,
+            msg_qnum: stat.qnum as u64,
+            msg_qbytes: stat.qbytes as u64,
+            msg_lspid: stat.lspid as i32,
+            msg_lrpid: stat.lrpid as i32,
+            ..Default::default()
+        }
+    }
+}
+
+/// The system-wide limits or usage, i.e., `struct msginfo`.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+struct MsgInfo {
+    msgpool: i32,
+    msgmap: i32,
+    msgmax: i32,
+    msgmnb: i32,
+    msgmni: i32,
+    msgssz: i32,
+    msgtql: i32,
+    msgseg: u16,
+    __pad: u16,
+}
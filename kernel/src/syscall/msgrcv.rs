@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::msg::{msg_queue::get_msg_queue, MsgFlags},
+    prelude::*,
+};
+
+pub fn sys_msgrcv(
+    msqid: i32,
+    msgp: Vaddr,
+    msgsz: isize,
+    msgtyp: i64,
+    msgflg: i32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "[sys_msgrcv] msqid = {}, msgp = {:#x}, msgsz = {}, msgtyp = {}, msgflg = {:#o}",
+        msqid, msgp, msgsz, msgtyp, msgflg
+    );
+
+    if msqid < 0 || msgsz < 0 {
+        return_errno!(Errno::EINVAL);
+    }
+
+    let flags = MsgFlags::from_bits_truncate(msgflg as u32);
+    if flags.contains(MsgFlags::MSG_COPY) {
+        return_errno_with_message!(Errno::ENOSYS, "MSG_COPY is not supported");
+    }
+
+    let queue = get_msg_queue(msqid)?;
+    queue.check_access(&ctx.posix_thread.credentials(), 0o4)?;
+    let message = queue.receive(msgtyp, msgsz as usize, flags, ctx.process.pid())?;
+
+    // The buffer is a `struct msgbuf`, which starts with the message type.
+    let user_space = ctx.user_space();
+    user_space.write_val(msgp, &message.mtype())?;
+    user_space.write_bytes(
+        msgp + core::mem::size_of::<i64>(),
+        &mut VmReader::from(message.data()),
+    )?;
+
+    Ok(SyscallReturn::Return(message.data().len() as isize))
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::msg::{msg_queue::get_msg_queue, Message, MsgFlags, MSGMAX},
+    prelude::*,
+};
+
+pub fn sys_msgsnd(
+    msqid: i32,
+    msgp: Vaddr,
+    msgsz: usize,
+    msgflg: i32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "[sys_msgsnd] msqid = {}, msgp = {:#x}, msgsz = {}, msgflg = {:#o}",
+        msqid, msgp, msgsz, msgflg
+    );
+
+    if msqid < 0 || msgsz > MSGMAX {
+        return_errno!(Errno::EINVAL);
+    }
+
+    // The buffer is a `struct msgbuf`, which starts with the message type.
+    let user_space = ctx.user_space();
+    let mtype = user_space.read_val::<i64>(msgp)?;
+    if mtype < 1 {
+        return_errno_with_message!(Errno::EINVAL, "the message type must be positive");
+    }
+    let mut data = vec![0u8; msgsz];
+    user_space.read_bytes(
+        msgp + core::mem::size_of::<i64>(),
+        &mut VmWriter::from(data.as_mut_slice()),
+    )?;
+
+    let flags = MsgFlags::from_bits_truncate(msgflg as u32);
+    let queue = get_msg_queue(msqid)?;
+    queue.check_access(&ctx.posix_thread.credentials(), 0o2)?;
+    queue.send(
+        Message::new(mtype, data),
+        flags.contains(MsgFlags::IPC_NOWAIT),
+        ctx.process.pid(),
+    )?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -2,7 +2,7 @@
 
 use super::SyscallReturn;
 use crate::{
-    fs::file_table::{get_file_fast, FileDesc},
+    fs::file_table::{get_file_fast, FdFlags, FileDesc},
     net::socket::util::SendRecvFlags,
     prelude::*,
     util::net::CUserMsgHdr,
@@ -14,7 +14,7 @@ pub fn sys_recvmsg(
     flags: i32,
     ctx: &Context,
 ) -> Result<SyscallReturn> {
-    let c_user_msghdr: CUserMsgHdr = ctx.user_space().read_val(user_msghdr_ptr)?;
+    let mut c_user_msghdr: CUserMsgHdr = ctx.user_space().read_val(user_msghdr_ptr)?;
     let flags = SendRecvFlags::from_bits_truncate(flags);
 
     debug!(
@@ -26,7 +26,7 @@ pub fn sys_recvmsg(
     let file = get_file_fast!(&mut file_table, sockfd);
     let socket = file.as_socket_or_err()?;
 
-    let (total_bytes, message_header) = {
+    let (total_bytes, mut message_header) = {
         let user_space = ctx.user_space();
         let mut io_vec_writer = c_user_msghdr.copy_writer_array_from_user(&user_space)?;
         socket
@@ -40,11 +40,43 @@ pub fn sys_recvmsg(
 
     if let Some(addr) = message_header.addr() {
         c_user_msghdr.write_socket_addr_to_user(addr)?;
+    } else {
+        c_user_msghdr.msg_namelen = 0;
     }
 
-    if c_user_msghdr.msg_control != 0 {
-        warn!("receiving control message is not supported");
-    }
+    let fd_flags = if flags.contains(SendRecvFlags::MSG_CMSG_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+    c_user_msghdr.msg_flags = if message_header.is_truncated() {
+        SendRecvFlags::MSG_TRUNC.bits() as u32
+    } else {
+        0
+    };
+    let file_table = &*file_table.unwrap();
+    c_user_msghdr.write_control_messages_to_user(
+        message_header.take_control_messages(),
+        |files| {
+            let mut file_table_locked = file_table.write();
+            files
+                .into_iter()
+                .map(|file| file_table_locked.insert(file, fd_flags))
+                .collect()
+        },
+        |fds| {
+            let files = {
+                let mut file_table_locked = file_table.write();
+                fds.into_iter()
+                    .filter_map(|fd| file_table_locked.close_file(fd))
+                    .collect::<Vec<_>>()
+            };
+            drop(files);
+        },
+    )?;
+
+    ctx.user_space()
+        .write_val(user_msghdr_ptr, &c_user_msghdr)?;
 
     Ok(SyscallReturn::Return(total_bytes as _))
 }
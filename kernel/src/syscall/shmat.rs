@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::{
+    ipc::shm::{segment::get_shm, ShmFlags},
+    prelude::*,
+    vm::{perms::VmPerms, vmar::is_userspace_vaddr},
+};
+
+/// The alignment of the attach address when `SHM_RND` is given.
+const SHMLBA: usize = PAGE_SIZE;
+
+pub fn sys_shmat(shmid: i32, shmaddr: Vaddr, shmflg: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = ShmFlags::from_bits_truncate(shmflg as u32);
+    debug!(
+        "[sys_shmat] shmid = {}, shmaddr = {:#x}, flags = {:?}",
+        shmid, shmaddr, flags
+    );
+
+    if shmid < 0 {
+        return_errno!(Errno::EINVAL);
+    }
+
+    let addr = if flags.contains(ShmFlags::SHM_RND) {
+        shmaddr.align_down(SHMLBA)
+    } else if shmaddr % SHMLBA != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the attach address is not aligned");
+    } else {
+        shmaddr
+    };
+    if flags.contains(ShmFlags::SHM_REMAP) && addr == 0 {
+        return_errno_with_message!(Errno::EINVAL, "SHM_REMAP requires an attach address");
+    }
+
+    let (perms, requested) = {
+        let mut perms = VmPerms::READ;
+        let mut requested = 0o4;
+        if !flags.contains(ShmFlags::SHM_RDONLY) {
+            perms |= VmPerms::WRITE;
+            requested |= 0o2;
+        }
+        if flags.contains(ShmFlags::SHM_EXEC) {
+            perms |= VmPerms::EXEC;
+            requested |= 0o1;
+        }
+        (perms, requested)
+    };
+
+    let segment = get_shm(shmid)?;
+    segment.check_access(&ctx.posix_thread.credentials(), requested)?;
+
+    let size = segment.size().align_up(PAGE_SIZE);
+    if addr != 0 && (!is_userspace_vaddr(addr) || !is_userspace_vaddr(addr + size - 1)) {
+        return_errno_with_message!(Errno::EINVAL, "the attach address is invalid");
+    }
+
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+    let mut options = root_vmar
+        .new_map(size, perms)?
+        .vmo(segment.vmo().dup()?)
+        .is_shared(true)
+        .shm_attachment(segment.attach(ctx.process.pid()));
+    if addr != 0 {
+        options = options
+            .offset(addr)
+            .can_overwrite(flags.contains(ShmFlags::SHM_REMAP));
+    }
+
+    let map_addr = options.build().map_err(|err| match err.error() {
+        // The requested region is occupied and `SHM_REMAP` is not given.
+        Errno::EACCES if addr != 0 => Error::with_message(Errno::EINVAL, "the region is in use"),
+        _ => err,
+    })?;
+    Ok(SyscallReturn::Return(map_addr as _))
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{
+        shm::{
+            segment::{get_shm, remove_shm, shm_ids, ShmStat},
+            ShmControlCmd, SHMALL, SHMMAX, SHMMIN, SHMMNI, SHMSEG, SHM_LOCKED,
+        },
+        IpcPerm64,
+    },
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+};
+
+pub fn sys_shmctl(shmid: i32, cmd: i32, buf: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    if shmid < 0 {
+        return_errno!(Errno::EINVAL);
+    }
+
+    let cmd = ShmControlCmd::try_from(cmd)?;
+    debug!(
+        "[sys_shmctl] shmid = {}, cmd = {:?}, buf = {:#x}",
+        shmid, cmd, buf
+    );
+
+    let user_space = ctx.user_space();
+    let credentials = ctx.posix_thread.credentials();
+
+    match cmd {
+        ShmControlCmd::IPC_INFO => {
+            let info = ShmInfo64 {
+                shmmax: SHMMAX as u64,
+                shmmin: SHMMIN as u64,
+                shmmni: SHMMNI as u64,
+                shmseg: SHMSEG as u64,
+                shmall: SHMALL as u64,
+                ..Default::default()
+            };
+            user_space.write_val(buf, &info)?;
+
+            return Ok(SyscallReturn::Return(max_shmid() as isize));
+        }
+        ShmControlCmd::SHM_INFO => {
+            let ids = shm_ids();
+            let shm_tot = ids
+                .iter()
+                .filter_map(|shmid| get_shm(*shmid).ok())
+                .map(|segment| segment.size().div_ceil(PAGE_SIZE) as u64)
+                .sum();
+            let info = ShmInfo {
+                used_ids: ids.len() as i32,
+                shm_tot,
+                shm_rss: shm_tot,
+                ..Default::default()
+            };
+            user_space.write_val(buf, &info)?;
+
+            return Ok(SyscallReturn::Return(max_shmid() as isize));
+        }
+        ShmControlCmd::IPC_STAT | ShmControlCmd::SHM_STAT | ShmControlCmd::SHM_STAT_ANY => {
+            let segment = get_shm(shmid)?;
+            if !matches!(cmd, ShmControlCmd::SHM_STAT_ANY) {
+                segment.check_access(&credentials, 0o4)?;
+            }
+            user_space.write_val(buf, &ShmidDs::from(segment.stat()))?;
+
+            // `SHM_STAT` and `SHM_STAT_ANY` take an index and return the identifier, which are
+            // the same here.
+            if !matches!(cmd, ShmControlCmd::IPC_STAT) {
+                return Ok(SyscallReturn::Return(shmid as isize));
+            }
+        }
+        ShmControlCmd::IPC_SET => {
+            let shmid_ds = user_space.read_val::<ShmidDs>(buf)?;
+            let segment = get_shm(shmid)?;
+            if !segment.is_owner(&credentials) {
+                return_errno!(Errno::EPERM);
+            }
+
+            let perm = &shmid_ds.shm_perm;
+            segment.set_owner_and_mode(perm.uid, perm.gid, perm.mode as u16);
+        }
+        ShmControlCmd::IPC_RMID => {
+            let segment = get_shm(shmid)?;
+            if !segment.is_owner(&credentials) {
+                return_errno!(Errno::EPERM);
+            }
+
+            remove_shm(&segment);
+        }
+        ShmControlCmd::SHM_LOCK | ShmControlCmd::SHM_UNLOCK => {
+            let segment = get_shm(shmid)?;
+            if !segment.is_owner(&credentials)
+                && !credentials.effective_capset().contains(CapSet::IPC_LOCK)
+            {
+                return_errno!(Errno::EPERM);
+            }
+
+            // The pages of segments are never swapped out, so only the flag is recorded.
+            let is_lock = matches!(cmd, ShmControlCmd::SHM_LOCK);
+            segment.set_mode_flags(SHM_LOCKED, is_lock);
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn max_shmid() -> i32 {
+    shm_ids().last().copied().unwrap_or(0)
+}
+
+/// The status of a segment, i.e., `struct shmid64_ds`.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+struct ShmidDs {
+    shm_perm: IpcPerm64,
+    shm_segsz: u64,
+    shm_atime: i64,
+    shm_dtime: i64,
+    shm_ctime: i64,
+    shm_cpid: i32,
+    shm_lpid: i32,
+    shm_nattch: u64,
+    __unused4: u64,
+    __unused5: u64,
+}
+
+impl From<ShmStat> for ShmidDs {
+    fn from(stat: ShmStat) -> Self {
+        Self {
+            shm_perm: IpcPerm64 {
+                key: stat.key,
+                uid: stat.uid,
+                gid: stat.gid,
+                cuid: stat.cuid,
+                cgid: stat.cgid,
+                mode: stat.mode as u32,
+                ..Default::default()
+            },
+            shm_segsz: stat.size as u64,
+            shm_atime: stat.atime as i64,
+            shm_dtime: stat.dtime as i64,
+            shm_ctime: stat.ctime as i64,
+            shm_cpid: stat.cpid as i32,
+            shm_lpid: stat.lpid as i32,
+            shm_nattch: stat.nattch as u64,
+            ..Default::default()
+        }
+    }
+}
+
+/// The system-wide limits, i.e., `struct shminfo64`.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+struct ShmInfo64 {
+    shmmax: u64,
+    shmmin: u64,
+    shmmni: u64,
+    shmseg: u64,
+    shmall: u64,
+    __unused1: u64,
+    __unused2: u64,
+    __unused3: u64,
+    __unused4: u64,
+}
+
+/// The system-wide usage, i.e., `struct shm_info`.
+#[derive(Debug, Clone, Copy, Pod, Default)]
+#[repr(C)]
+struct ShmInfo {
+    used_ids: i32,
+    __pad: i32,
+    shm_tot: u64,
+    shm_rss: u64,
+    shm_swp: u64,
+    swap_attempts: u64,
+    swap_successes: u64,
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::prelude::*;
+
+pub fn sys_shmdt(shmaddr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("[sys_shmdt] shmaddr = {:#x}", shmaddr);
+
+    if shmaddr % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the address is not aligned");
+    }
+
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+
+    // Find the segment attached at `shmaddr`, and then all the parts of the attachment, which
+    // may have been split by `mprotect` or partial `munmap`.
+    let (segment, ranges) = {
+        let query_guard = root_vmar.query(shmaddr..shmaddr + 1);
+        let Some(segment) = query_guard
+            .iter()
+            .find(|vm_mapping| vm_mapping.map_to_addr() == shmaddr)
+            .and_then(|vm_mapping| vm_mapping.shm_attachment())
+            .map(|attachment| attachment.segment().clone())
+        else {
+            return_errno_with_message!(Errno::EINVAL, "no segment is attached at the address");
+        };
+        drop(query_guard);
+
+        let end = shmaddr.saturating_add(segment.size());
+        let query_guard = root_vmar.query(shmaddr..end);
+        let ranges: Vec<_> = query_guard
+            .iter()
+            .filter(|vm_mapping| {
+                vm_mapping
+                    .shm_attachment()
+                    .is_some_and(|attachment| Arc::ptr_eq(attachment.segment(), &segment))
+            })
+            .map(|vm_mapping| vm_mapping.map_to_addr()..vm_mapping.map_end())
+            .collect();
+        (segment, ranges)
+    };
+
+    for range in ranges {
+        root_vmar.remove_mapping(range)?;
+    }
+    segment.record_detach(ctx.process.pid());
+
+    Ok(SyscallReturn::Return(0))
+}
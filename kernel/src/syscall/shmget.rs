@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{shm::segment::get_or_create_shm, IpcFlags},
+    prelude::*,
+};
+
+pub fn sys_shmget(key: i32, size: usize, shmflg: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = IpcFlags::from_bits_truncate(shmflg as u32);
+    let mode = (shmflg as u32 & 0o777) as u16;
+    let credentials = ctx.posix_thread.credentials();
+    let pid = ctx.process.pid();
+
+    debug!(
+        "[sys_shmget] key = {}, size = {}, flags = {:#o}",
+        key, size, shmflg
+    );
+
+    let shmid = get_or_create_shm(key, size, flags, mode, &credentials, pid)?;
+
+    Ok(SyscallReturn::Return(shmid as isize))
+}
@@ -1,14 +1,17 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_bigtcp::{socket::LinkSocketKind, wire::IpVersion};
+
 use super::SyscallReturn;
 use crate::{
     fs::{file_handle::FileLike, file_table::FdFlags},
     net::socket::{
-        ip::{DatagramSocket, StreamSocket},
+        ip::{DatagramSocket, RawSocket, RawSocketKind, StreamSocket},
         netlink::{
             is_valid_protocol, NetlinkRouteSocket, NetlinkUeventSocket, StandardNetlinkProtocol,
         },
-        unix::UnixStreamSocket,
+        packet::PacketSocket,
+        unix::{UnixDatagramSocket, UnixStreamSocket},
         vsock::VsockStreamSocket,
     },
     prelude::*,
@@ -25,30 +28,60 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
     );
     let is_nonblocking = sock_flags.contains(SockFlags::SOCK_NONBLOCK);
     let file_like = match (domain, sock_type) {
-        // FIXME: SOCK_SEQPACKET is added to run fcntl_test, not supported yet.
-        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_STREAM | SockType::SOCK_SEQPACKET) => {
-            UnixStreamSocket::new(is_nonblocking) as Arc<dyn FileLike>
+        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_STREAM) => {
+            UnixStreamSocket::new(is_nonblocking, false) as Arc<dyn FileLike>
+        }
+        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_SEQPACKET) => {
+            UnixStreamSocket::new(is_nonblocking, true) as Arc<dyn FileLike>
+        }
+        (CSocketAddrFamily::AF_UNIX, SockType::SOCK_DGRAM) => {
+            UnixDatagramSocket::new(is_nonblocking) as Arc<dyn FileLike>
         }
-        (CSocketAddrFamily::AF_INET, SockType::SOCK_STREAM) => {
+        (CSocketAddrFamily::AF_INET | CSocketAddrFamily::AF_INET6, SockType::SOCK_STREAM) => {
             let protocol = Protocol::try_from(protocol)?;
             debug!("protocol = {:?}", protocol);
             match protocol {
                 Protocol::IPPROTO_IP | Protocol::IPPROTO_TCP => {
-                    StreamSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                    StreamSocket::new(is_nonblocking, ip_version_of(domain)) as Arc<dyn FileLike>
                 }
                 _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported protocol"),
             }
         }
-        (CSocketAddrFamily::AF_INET, SockType::SOCK_DGRAM) => {
+        (CSocketAddrFamily::AF_INET | CSocketAddrFamily::AF_INET6, SockType::SOCK_DGRAM) => {
             let protocol = Protocol::try_from(protocol)?;
             debug!("protocol = {:?}", protocol);
             match protocol {
                 Protocol::IPPROTO_IP | Protocol::IPPROTO_UDP => {
-                    DatagramSocket::new(is_nonblocking) as Arc<dyn FileLike>
+                    DatagramSocket::new(is_nonblocking, ip_version_of(domain)) as Arc<dyn FileLike>
+                }
+                Protocol::IPPROTO_ICMP if domain == CSocketAddrFamily::AF_INET => {
+                    RawSocket::new(is_nonblocking, IpVersion::Ipv4, RawSocketKind::Ping)?
+                        as Arc<dyn FileLike>
+                }
+                Protocol::IPPROTO_ICMPV6 if domain == CSocketAddrFamily::AF_INET6 => {
+                    RawSocket::new(is_nonblocking, IpVersion::Ipv6, RawSocketKind::Ping)?
+                        as Arc<dyn FileLike>
                 }
                 _ => return_errno_with_message!(Errno::EAFNOSUPPORT, "unsupported protocol"),
             }
         }
+        (CSocketAddrFamily::AF_INET | CSocketAddrFamily::AF_INET6, SockType::SOCK_RAW) => {
+            debug!("protocol = {}", protocol);
+            let Ok(protocol) = u8::try_from(protocol) else {
+                return_errno_with_message!(Errno::EINVAL, "the protocol is invalid");
+            };
+            if protocol == 0 {
+                return_errno_with_message!(
+                    Errno::EPROTONOSUPPORT,
+                    "raw IP sockets must specify a protocol"
+                );
+            }
+            RawSocket::new(
+                is_nonblocking,
+                ip_version_of(domain),
+                RawSocketKind::Raw(protocol),
+            )? as Arc<dyn FileLike>
+        }
         (CSocketAddrFamily::AF_NETLINK, SockType::SOCK_RAW | SockType::SOCK_DGRAM) => {
             let netlink_family = StandardNetlinkProtocol::try_from(protocol as u32);
             debug!("netlink family = {:?}", netlink_family);
@@ -76,6 +109,16 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
                 }
             }
         }
+        (CSocketAddrFamily::AF_PACKET, SockType::SOCK_RAW | SockType::SOCK_DGRAM) => {
+            // Like Linux, the protocol is truncated to 16 bits and is in network byte order.
+            let protocol = u16::from_be(protocol as u16);
+            debug!("protocol = {:#x}", protocol);
+            let kind = match sock_type {
+                SockType::SOCK_RAW => LinkSocketKind::Raw,
+                _ => LinkSocketKind::Cooked,
+            };
+            PacketSocket::new(is_nonblocking, kind, protocol)? as Arc<dyn FileLike>
+        }
         (CSocketAddrFamily::AF_VSOCK, SockType::SOCK_STREAM) => {
             Arc::new(VsockStreamSocket::new(is_nonblocking)?) as Arc<dyn FileLike>
         }
@@ -93,3 +136,11 @@ pub fn sys_socket(domain: i32, type_: i32, protocol: i32, ctx: &Context) -> Resu
     };
     Ok(SyscallReturn::Return(fd as _))
 }
+
+fn ip_version_of(domain: CSocketAddrFamily) -> IpVersion {
+    if domain == CSocketAddrFamily::AF_INET6 {
+        IpVersion::Ipv6
+    } else {
+        IpVersion::Ipv4
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
         file_table::FileDesc,
         fs_resolver::{FsPath, AT_FDCWD},
         path::Dentry,
+        utils::InodeFlags,
     },
     prelude::*,
     time::{clocks::RealTimeCoarseClock, timespec_t, timeval_t},
@@ -110,6 +111,20 @@ struct Utimbuf {
 }
 
 fn vfs_utimes(dentry: &Dentry, times: Option<TimeSpecPair>) -> Result<SyscallReturn> {
+    let inode_flags = dentry.inode().inode_flags();
+    if inode_flags.contains(InodeFlags::FS_IMMUTABLE_FL) {
+        return_errno_with_message!(Errno::EPERM, "the file is immutable");
+    }
+    if inode_flags.contains(InodeFlags::FS_APPEND_FL)
+        && times.as_ref().is_some_and(|times| {
+            [&times.atime, &times.mtime]
+                .into_iter()
+                .any(|time| !time.is_utime_now() && !time.is_utime_omit())
+        })
+    {
+        return_errno_with_message!(Errno::EPERM, "the file is append-only");
+    }
+
     let (atime, mtime, ctime) = match times {
         Some(times) => {
             if !times.atime.is_valid() || !times.mtime.is_valid() {
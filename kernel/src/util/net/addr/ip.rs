@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::wire::{Ipv4Address, PortNum};
+use aster_bigtcp::wire::{IpAddress, Ipv4Address, Ipv6Address, PortNum};
 
 use super::family::CSocketAddrFamily;
 use crate::prelude::*;
@@ -43,6 +43,61 @@ impl From<CSocketAddrInet> for (Ipv4Address, PortNum) {
     }
 }
 
+/// IPv6 socket address.
+///
+/// See <https://www.man7.org/linux/man-pages/man7/ipv6.7.html>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CSocketAddrInet6 {
+    /// Address family (AF_INET6).
+    sin6_family: u16,
+    /// Port number.
+    sin6_port: CPortNum,
+    /// IPv6 flow information.
+    sin6_flowinfo: u32,
+    /// IPv6 address.
+    sin6_addr: CInet6Addr,
+    /// Scope ID.
+    sin6_scope_id: u32,
+}
+
+impl From<(Ipv6Address, PortNum)> for CSocketAddrInet6 {
+    fn from(value: (Ipv6Address, PortNum)) -> Self {
+        Self {
+            sin6_family: CSocketAddrFamily::AF_INET6 as u16,
+            sin6_port: value.1.into(),
+            sin6_flowinfo: 0,
+            sin6_addr: value.0.into(),
+            sin6_scope_id: 0,
+        }
+    }
+}
+
+impl TryFrom<CSocketAddrInet6> for (Ipv6Address, PortNum) {
+    type Error = Error;
+
+    fn try_from(value: CSocketAddrInet6) -> Result<Self> {
+        debug_assert_eq!(value.sin6_family, CSocketAddrFamily::AF_INET6 as u16);
+        // FIXME: The flow information is ignored. The scope ID is required to distinguish
+        // link-local addresses on different interfaces, which is not supported yet, so a non-zero
+        // scope ID is rejected instead of being silently dropped.
+        if value.sin6_scope_id != 0 {
+            return_errno_with_message!(Errno::EINVAL, "IPv6 scope IDs are not supported");
+        }
+        Ok((value.sin6_addr.into(), value.sin6_port.into()))
+    }
+}
+
+/// Converts an IP address to the bytes of the C socket address with a zero port number.
+///
+/// This is used to report the offender of an extended socket error (`SO_EE_OFFENDER` in Linux).
+pub(in crate::util::net) fn ip_addr_to_c_bytes(addr: IpAddress) -> Vec<u8> {
+    match addr {
+        IpAddress::Ipv4(addr) => CSocketAddrInet::from((addr, 0)).as_bytes().to_vec(),
+        IpAddress::Ipv6(addr) => CSocketAddrInet6::from((addr, 0)).as_bytes().to_vec(),
+    }
+}
+
 /// IPv4 4-byte address.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod)]
@@ -64,6 +119,27 @@ impl From<CInetAddr> for Ipv4Address {
     }
 }
 
+/// IPv6 16-byte address.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CInet6Addr {
+    s6_addr: [u8; 16],
+}
+
+impl From<Ipv6Address> for CInet6Addr {
+    fn from(value: Ipv6Address) -> Self {
+        Self {
+            s6_addr: value.octets(),
+        }
+    }
+}
+
+impl From<CInet6Addr> for Ipv6Address {
+    fn from(value: CInet6Addr) -> Self {
+        Self::from(value.s6_addr)
+    }
+}
+
 /// TCP/UDP port number.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod)]
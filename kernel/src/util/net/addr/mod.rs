@@ -4,9 +4,11 @@ pub use family::{
     read_socket_addr_from_user, write_socket_addr_to_user, write_socket_addr_with_max_len,
     CSocketAddrFamily,
 };
+pub(super) use ip::ip_addr_to_c_bytes;
 
 mod family;
 mod ip;
 mod netlink;
+mod packet;
 mod unix;
 mod vsock;
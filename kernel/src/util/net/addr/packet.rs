@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::family::CSocketAddrFamily;
+use crate::{net::socket::packet::PacketSocketAddr, prelude::*};
+
+/// Packet socket address.
+///
+/// See <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L14>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CSocketAddrPacket {
+    /// Address family (AF_PACKET).
+    sll_family: u16,
+    /// Link-layer protocol in network byte order.
+    sll_protocol: u16,
+    /// Interface index.
+    sll_ifindex: i32,
+    /// ARP hardware type.
+    sll_hatype: u16,
+    /// Packet type.
+    sll_pkttype: u8,
+    /// Length of the hardware address.
+    sll_halen: u8,
+    /// Hardware address.
+    sll_addr: [u8; 8],
+}
+
+impl From<PacketSocketAddr> for CSocketAddrPacket {
+    fn from(value: PacketSocketAddr) -> Self {
+        Self {
+            sll_family: CSocketAddrFamily::AF_PACKET as u16,
+            sll_protocol: value.protocol.to_be(),
+            sll_ifindex: value.ifindex as i32,
+            sll_hatype: value.hatype,
+            sll_pkttype: value.pkttype,
+            sll_halen: value.halen,
+            sll_addr: value.addr,
+        }
+    }
+}
+
+impl From<CSocketAddrPacket> for PacketSocketAddr {
+    fn from(value: CSocketAddrPacket) -> Self {
+        debug_assert_eq!(value.sll_family, CSocketAddrFamily::AF_PACKET as u16);
+        Self {
+            protocol: u16::from_be(value.sll_protocol),
+            ifindex: value.sll_ifindex as u32,
+            hatype: value.sll_hatype,
+            pkttype: value.sll_pkttype,
+            halen: value.sll_halen,
+            addr: value.sll_addr,
+        }
+    }
+}
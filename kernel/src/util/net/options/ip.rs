@@ -4,8 +4,11 @@ use int_to_c_enum::TryFromInt;
 
 use super::RawSocketOption;
 use crate::{
-    impl_raw_socket_option,
-    net::socket::ip::options::{Hdrincl, Tos, Ttl},
+    impl_raw_sock_option_set_only, impl_raw_socket_option,
+    net::socket::ip::options::{
+        AddMembership, DropMembership, Hdrincl, MulticastIf, MulticastLoop, MulticastTtl, RecvErr,
+        Tos, Ttl,
+    },
     prelude::*,
     util::net::options::SocketOption,
 };
@@ -72,6 +75,12 @@ pub fn new_ip_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
         CIpOptionName::TOS => Ok(Box::new(Tos::new())),
         CIpOptionName::TTL => Ok(Box::new(Ttl::new())),
         CIpOptionName::HDRINCL => Ok(Box::new(Hdrincl::new())),
+        CIpOptionName::RECVERR => Ok(Box::new(RecvErr::new())),
+        CIpOptionName::MULTICAST_IF => Ok(Box::new(MulticastIf::new())),
+        CIpOptionName::MULTICAST_TTL => Ok(Box::new(MulticastTtl::new())),
+        CIpOptionName::MULTICAST_LOOP => Ok(Box::new(MulticastLoop::new())),
+        CIpOptionName::ADD_MEMBERSHIP => Ok(Box::new(AddMembership::new())),
+        CIpOptionName::DROP_MEMBERSHIP => Ok(Box::new(DropMembership::new())),
         _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported ip level option"),
     }
 }
@@ -79,3 +88,9 @@ pub fn new_ip_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
 impl_raw_socket_option!(Ttl);
 impl_raw_socket_option!(Tos);
 impl_raw_socket_option!(Hdrincl);
+impl_raw_socket_option!(RecvErr);
+impl_raw_socket_option!(MulticastIf);
+impl_raw_socket_option!(MulticastTtl);
+impl_raw_socket_option!(MulticastLoop);
+impl_raw_sock_option_set_only!(AddMembership);
+impl_raw_sock_option_set_only!(DropMembership);
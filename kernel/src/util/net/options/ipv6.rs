@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use int_to_c_enum::TryFromInt;
+
+use super::RawSocketOption;
+use crate::{
+    impl_raw_socket_option,
+    net::socket::ip::options::{Ipv6RecvErr, V6Only},
+    prelude::*,
+    util::net::options::SocketOption,
+};
+
+/// Socket options for IPv6 socket.
+///
+/// The raw definitions can be found at:
+/// https://elixir.bootlin.com/linux/v6.0.19/source/include/uapi/linux/in6.h#L166
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+#[expect(clippy::upper_case_acronyms)]
+pub enum CIpv6OptionName {
+    ADDRFORM = 1,
+    RFC2292_PKTINFO = 2,
+    RFC2292_HOPOPTS = 3,
+    RFC2292_DSTOPTS = 4,
+    RFC2292_RTHDR = 5,
+    RFC2292_PKTOPTIONS = 6,
+    CHECKSUM = 7,
+    RFC2292_HOPLIMIT = 8,
+    NEXTHOP = 9,
+    AUTHHDR = 10,
+    FLOWINFO = 11,
+    UNICAST_HOPS = 16,
+    MULTICAST_IF = 17,
+    MULTICAST_HOPS = 18,
+    MULTICAST_LOOP = 19,
+    ADD_MEMBERSHIP = 20,
+    DROP_MEMBERSHIP = 21,
+    ROUTER_ALERT = 22,
+    MTU_DISCOVER = 23,
+    MTU = 24,
+    RECVERR = 25,
+    V6ONLY = 26,
+    JOIN_ANYCAST = 27,
+    LEAVE_ANYCAST = 28,
+    MULTICAST_ALL = 29,
+    ROUTER_ALERT_ISOLATE = 30,
+    RECVERR_RFC4884 = 31,
+    IPSEC_POLICY = 34,
+    XFRM_POLICY = 35,
+    HDRINCL = 36,
+    RECVPKTINFO = 49,
+    PKTINFO = 50,
+    RECVHOPLIMIT = 51,
+    HOPLIMIT = 52,
+    RECVHOPOPTS = 53,
+    HOPOPTS = 54,
+    RTHDRDSTOPTS = 55,
+    RECVRTHDR = 56,
+    RTHDR = 57,
+    RECVDSTOPTS = 58,
+    DSTOPTS = 59,
+    RECVPATHMTU = 60,
+    PATHMTU = 61,
+    DONTFRAG = 62,
+    RECVTCLASS = 66,
+    TCLASS = 67,
+    AUTOFLOWLABEL = 70,
+    ADDR_PREFERENCES = 72,
+    MINHOPCOUNT = 73,
+    ORIGDSTADDR = 74,
+    TRANSPARENT = 75,
+    UNICAST_IF = 76,
+    RECVFRAGSIZE = 77,
+    FREEBIND = 78,
+}
+
+pub fn new_ipv6_option(name: i32) -> Result<Box<dyn RawSocketOption>> {
+    let name = CIpv6OptionName::try_from(name).map_err(|_| Errno::ENOPROTOOPT)?;
+    match name {
+        CIpv6OptionName::RECVERR => Ok(Box::new(Ipv6RecvErr::new())),
+        CIpv6OptionName::V6ONLY => Ok(Box::new(V6Only::new())),
+        _ => return_errno_with_message!(Errno::ENOPROTOOPT, "unsupported ipv6 level option"),
+    }
+}
+
+impl_raw_socket_option!(V6Only);
+impl_raw_socket_option!(Ipv6RecvErr);
@@ -52,17 +52,22 @@
 //!
 
 use ip::new_ip_option;
+use ipv6::new_ipv6_option;
 use netlink::new_netlink_option;
+use packet::new_packet_option;
 
 use crate::{net::socket::options::SocketOption, prelude::*};
 
 mod ip;
+mod ipv6;
 mod netlink;
+mod packet;
 mod socket;
 mod tcp;
+mod udp;
 mod utils;
 
-use self::{socket::new_socket_option, tcp::new_tcp_option};
+use self::{socket::new_socket_option, tcp::new_tcp_option, udp::new_udp_option};
 
 pub trait RawSocketOption: SocketOption {
     fn read_from_user(&mut self, addr: Vaddr, max_len: u32) -> Result<()>;
@@ -168,7 +173,10 @@ pub fn new_raw_socket_option(
         CSocketOptionLevel::SOL_SOCKET => new_socket_option(name),
         CSocketOptionLevel::SOL_IP => new_ip_option(name),
         CSocketOptionLevel::SOL_TCP => new_tcp_option(name),
+        CSocketOptionLevel::SOL_UDP => new_udp_option(name),
+        CSocketOptionLevel::SOL_IPV6 => new_ipv6_option(name),
         CSocketOptionLevel::SOL_NETLINK => new_netlink_option(name),
+        CSocketOptionLevel::SOL_PACKET => new_packet_option(name),
         _ => return_errno_with_message!(Errno::EOPNOTSUPP, "unsupported option level"),
     }
 }
@@ -184,5 +192,6 @@ pub enum CSocketOptionLevel {
     SOL_UDP = 17,
     SOL_IPV6 = 41,
     SOL_RAW = 255,
+    SOL_PACKET = 263,
     SOL_NETLINK = 270,
 }
@@ -5,8 +5,13 @@ use core::{num::NonZeroU8, time::Duration};
 use crate::{
     current_userspace,
     net::socket::{
-        ip::{options::IpTtl, stream_options::CongestionControl},
-        util::LingerOption,
+        ip::{
+            options::{IpMreq, IpMulticastIf, IpTtl},
+            stream_options::CongestionControl,
+        },
+        packet::PacketMreq,
+        unix::UCred,
+        util::{BpfInsn, LingerOption, SocketFilter, BPF_MAXINSNS},
     },
     prelude::*,
 };
@@ -97,6 +102,24 @@ impl WriteToUser for u8 {
     }
 }
 
+impl ReadFromUser for u16 {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        let val = i32::read_from_user(addr, max_len)?;
+
+        if val < 0 || val > u16::MAX as i32 {
+            return_errno_with_message!(Errno::EINVAL, "invalid u16 value");
+        }
+
+        Ok(val as u16)
+    }
+}
+
+impl WriteToUser for u16 {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        (*self as i32).write_to_user(addr, max_len)
+    }
+}
+
 impl ReadFromUser for IpTtl {
     fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
         let val = i32::read_from_user(addr, max_len)?;
@@ -162,6 +185,125 @@ impl WriteToUser for LingerOption {
     }
 }
 
+impl WriteToUser for UCred {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        let write_len = core::mem::size_of::<UCred>();
+
+        if (max_len as usize) < write_len {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        current_userspace!().write_val(addr, self)?;
+        Ok(write_len)
+    }
+}
+
+impl ReadFromUser for SocketFilter {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        if (max_len as usize) < core::mem::size_of::<CSockFprog>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        let c_fprog = current_userspace!().read_val::<CSockFprog>(addr)?;
+
+        let len = c_fprog.len as usize;
+        if len == 0 || len > BPF_MAXINSNS {
+            return_errno_with_message!(Errno::EINVAL, "the BPF program length is invalid");
+        }
+
+        let insn_size = core::mem::size_of::<BpfInsn>();
+        let mut insns = Vec::with_capacity(len);
+        for i in 0..len {
+            let insn_addr = (c_fprog.filter as Vaddr)
+                .checked_add(i * insn_size)
+                .ok_or_else(|| Error::with_message(Errno::EFAULT, "the address overflows"))?;
+            insns.push(current_userspace!().read_val::<BpfInsn>(insn_addr)?);
+        }
+
+        SocketFilter::new(insns)
+    }
+}
+
+impl ReadFromUser for PacketMreq {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        if (max_len as usize) < core::mem::size_of::<PacketMreq>() {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        current_userspace!().read_val::<PacketMreq>(addr)
+    }
+}
+
+impl ReadFromUser for IpMulticastIf {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        // Like Linux, the value can be `struct ip_mreqn`, `struct ip_mreq`, or `struct in_addr`.
+        // See <https://elixir.bootlin.com/linux/v6.0.9/source/net/ipv4/ip_sockglue.c#L1140>.
+        let max_len = max_len as usize;
+
+        if max_len >= core::mem::size_of::<CIpMreqn>() {
+            let c_mreqn = current_userspace!().read_val::<CIpMreqn>(addr)?;
+            return Ok(IpMulticastIf {
+                address: c_mreqn.address.into(),
+                ifindex: c_mreqn.ifindex as u32,
+            });
+        }
+
+        let address_addr = if max_len >= core::mem::size_of::<CIpMreq>() {
+            // The address is the second field of `struct ip_mreq`.
+            addr + core::mem::size_of::<[u8; 4]>()
+        } else if max_len >= core::mem::size_of::<[u8; 4]>() {
+            addr
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        };
+        let address = current_userspace!().read_val::<[u8; 4]>(address_addr)?;
+
+        Ok(IpMulticastIf {
+            address: address.into(),
+            ifindex: 0,
+        })
+    }
+}
+
+impl WriteToUser for IpMulticastIf {
+    fn write_to_user(&self, addr: Vaddr, max_len: u32) -> Result<usize> {
+        let write_len = core::mem::size_of::<[u8; 4]>();
+
+        if (max_len as usize) < write_len {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        }
+
+        current_userspace!().write_val(addr, &self.address.octets())?;
+        Ok(write_len)
+    }
+}
+
+impl ReadFromUser for IpMreq {
+    fn read_from_user(addr: Vaddr, max_len: u32) -> Result<Self> {
+        // Like Linux, the value can be `struct ip_mreqn` or `struct ip_mreq`.
+        let c_mreqn = if (max_len as usize) >= core::mem::size_of::<CIpMreqn>() {
+            current_userspace!().read_val::<CIpMreqn>(addr)?
+        } else if (max_len as usize) >= core::mem::size_of::<CIpMreq>() {
+            let c_mreq = current_userspace!().read_val::<CIpMreq>(addr)?;
+            CIpMreqn {
+                multiaddr: c_mreq.multiaddr,
+                address: c_mreq.interface,
+                ifindex: 0,
+            }
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "max_len is too short");
+        };
+
+        Ok(IpMreq {
+            multiaddr: c_mreqn.multiaddr.into(),
+            interface: IpMulticastIf {
+                address: c_mreqn.address.into(),
+                ifindex: c_mreqn.ifindex as u32,
+            },
+        })
+    }
+}
+
 const TCP_CONGESTION_NAME_MAX: u32 = 16;
 
 impl ReadFromUser for CongestionControl {
@@ -225,3 +367,28 @@ impl From<CLinger> for LingerOption {
         LingerOption::new(is_on, timeout)
     }
 }
+
+/// `struct ip_mreqn` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIpMreqn {
+    multiaddr: [u8; 4], // IP multicast address of group
+    address: [u8; 4],   // local IP address of interface
+    ifindex: i32,       // interface index
+}
+
+/// `struct ip_mreq` in Linux.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CIpMreq {
+    multiaddr: [u8; 4], // IP multicast address of group
+    interface: [u8; 4], // local IP address of interface
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSockFprog {
+    len: u16, // number of filter blocks
+    _pad: [u8; 6],
+    filter: u64, // pointer to the filter blocks
+}
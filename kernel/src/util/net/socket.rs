@@ -1,8 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use super::read_socket_addr_from_user;
+use core::mem::size_of;
+
+use super::{addr::ip_addr_to_c_bytes, read_socket_addr_from_user};
 use crate::{
-    net::socket::util::SocketAddr,
+    current_userspace,
+    fs::{file_handle::FileLike, file_table::FileDesc},
+    net::socket::{
+        unix::UCred,
+        util::{ControlMessage, ErrorOrigin, ExtendedError, SendRecvFlags, SocketAddr},
+    },
     prelude::*,
     util::{net::write_socket_addr_with_max_len, VmReaderArray, VmWriterArray},
 };
@@ -28,6 +35,7 @@ pub enum Protocol {
     IPPROTO_GRE = 47,       /* Cisco GRE tunnels (rfc 1701,1702)	*/
     IPPROTO_ESP = 50,       /* Encapsulation Security Payload protocol */
     IPPROTO_AH = 51,        /* Authentication Header protocol	*/
+    IPPROTO_ICMPV6 = 58,    /* ICMPv6				*/
     IPPROTO_MTP = 92,       /* Multicast Transport Protocol		*/
     IPPROTO_BEETPH = 94,    /* IP option pseudo header for BEET	*/
     IPPROTO_ENCAP = 98,     /* Encapsulation Header			*/
@@ -84,11 +92,11 @@ pub struct CUserMsgHdr {
     /// Scatter/Gather iov array
     pub msg_iov: Vaddr,
     /// The # of elements in msg_iov
-    pub msg_iovlen: u32,
+    pub msg_iovlen: usize,
     /// Ancillary data
     pub msg_control: Vaddr,
     /// Ancillary data buffer length
-    pub msg_controllen: u32,
+    pub msg_controllen: usize,
     /// Flags on received message
     pub msg_flags: u32,
 }
@@ -103,12 +111,159 @@ impl CUserMsgHdr {
         Ok(Some(socket_addr))
     }
 
-    pub fn write_socket_addr_to_user(&self, addr: &SocketAddr) -> Result<()> {
+    /// Writes the socket address to the user space.
+    ///
+    /// The `msg_namelen` field is updated to the actual length of the socket address.
+    pub fn write_socket_addr_to_user(&mut self, addr: &SocketAddr) -> Result<()> {
         if self.msg_name == 0 {
             return Ok(());
         }
 
-        write_socket_addr_with_max_len(addr, self.msg_name, self.msg_namelen)?;
+        self.msg_namelen = write_socket_addr_with_max_len(addr, self.msg_name, self.msg_namelen)?;
+        Ok(())
+    }
+
+    /// Reads the control messages from the user space.
+    ///
+    /// File descriptors in `SCM_RIGHTS` messages are resolved to the open files in the file table
+    /// of the current thread.
+    pub fn read_control_messages_from_user(&self, ctx: &Context) -> Result<Vec<ControlMessage>> {
+        if self.msg_controllen == 0 {
+            return Ok(Vec::new());
+        }
+
+        if self.msg_controllen > MAX_CONTROL_LEN {
+            return_errno_with_message!(Errno::ENOBUFS, "the control messages are too long");
+        }
+
+        let mut buffer = vec![0u8; self.msg_controllen];
+        ctx.user_space()
+            .read_bytes(self.msg_control, &mut VmWriter::from(buffer.as_mut_slice()))?;
+
+        let mut control_messages = Vec::new();
+        let mut offset = 0;
+
+        while buffer.len() - offset >= CMSG_HDR_LEN {
+            let header = CControlMessageHeader::from_bytes(&buffer[offset..]);
+            if header.cmsg_len < CMSG_HDR_LEN || header.cmsg_len > buffer.len() - offset {
+                return_errno_with_message!(Errno::EINVAL, "the control message length is invalid");
+            }
+
+            let data = &buffer[offset + CMSG_HDR_LEN..offset + header.cmsg_len];
+            if let Some(control_message) = parse_control_message(&header, data, ctx)? {
+                control_messages.push(control_message);
+            }
+
+            offset += cmsg_align(header.cmsg_len).min(buffer.len() - offset);
+        }
+
+        Ok(control_messages)
+    }
+
+    /// Writes the control messages to the user space.
+    ///
+    /// The files in `SCM_RIGHTS` messages are installed with `install_files`, which returns the
+    /// new file descriptors. If the control messages cannot be written, the new file descriptors
+    /// are closed with `close_files`. If the buffer is not large enough, the control messages are
+    /// truncated and `MSG_CTRUNC` is set in the `msg_flags` field. The `msg_controllen` field is
+    /// updated to the length of the written control messages.
+    pub fn write_control_messages_to_user<F, G>(
+        &mut self,
+        control_messages: Vec<ControlMessage>,
+        mut install_files: F,
+        close_files: G,
+    ) -> Result<()>
+    where
+        F: FnMut(Vec<Arc<dyn FileLike>>) -> Vec<FileDesc>,
+        G: FnOnce(Vec<FileDesc>),
+    {
+        let max_len = if self.msg_control == 0 {
+            0
+        } else {
+            self.msg_controllen
+        };
+
+        let mut buffer = Vec::new();
+        let mut is_truncated = false;
+        let mut installed_fds = Vec::new();
+
+        for control_message in control_messages {
+            let avail_len = max_len - buffer.len();
+
+            let (cmsg_level, cmsg_type, data) = match control_message {
+                ControlMessage::Rights(mut files) => {
+                    let max_files = avail_len.saturating_sub(CMSG_HDR_LEN) / size_of::<FileDesc>();
+                    if files.len() > max_files {
+                        // The files that do not fit are closed, as Linux does.
+                        files.truncate(max_files);
+                        is_truncated = true;
+                    }
+                    if files.is_empty() {
+                        continue;
+                    }
+
+                    let fds = install_files(files);
+                    let data = fds
+                        .iter()
+                        .flat_map(|fd| fd.to_ne_bytes())
+                        .collect::<Vec<_>>();
+                    installed_fds.extend(fds);
+                    (SOL_SOCKET, SCM_RIGHTS, data)
+                }
+                ControlMessage::Credentials(cred) => {
+                    (SOL_SOCKET, SCM_CREDENTIALS, cred.as_bytes().to_vec())
+                }
+                ControlMessage::RecvErr(error) => {
+                    // Like Linux, `MSG_ERRQUEUE` is set if the message comes from the error queue.
+                    self.msg_flags |= SendRecvFlags::MSG_ERRQUEUE.bits() as u32;
+
+                    let (cmsg_level, cmsg_type) = match error.origin {
+                        ErrorOrigin::Icmp => (SOL_IP, IP_RECVERR),
+                        ErrorOrigin::Icmp6 => (SOL_IPV6, IPV6_RECVERR),
+                    };
+                    (cmsg_level, cmsg_type, extended_error_to_bytes(&error))
+                }
+            };
+
+            let header = CControlMessageHeader {
+                cmsg_len: CMSG_HDR_LEN + data.len(),
+                cmsg_level,
+                cmsg_type,
+            };
+
+            let mut cmsg = header.as_bytes().to_vec();
+            cmsg.extend_from_slice(&data);
+
+            if cmsg.len() > avail_len {
+                // Following Linux, the control message is truncated if the header fits, and is
+                // dropped otherwise.
+                if avail_len >= CMSG_HDR_LEN {
+                    buffer.extend_from_slice(&cmsg[..avail_len]);
+                }
+                is_truncated = true;
+                break;
+            }
+
+            let cmsg_space = cmsg_align(cmsg.len()).min(avail_len);
+            cmsg.resize(cmsg_space, 0);
+            buffer.extend_from_slice(&cmsg);
+        }
+
+        if !buffer.is_empty() {
+            let result = current_userspace!()
+                .write_bytes(self.msg_control, &mut VmReader::from(buffer.as_slice()));
+            if let Err(err) = result {
+                // The file descriptors would be leaked since the user cannot see them.
+                close_files(installed_fds);
+                return Err(err);
+            }
+        }
+
+        self.msg_controllen = buffer.len();
+        if is_truncated {
+            self.msg_flags |= SendRecvFlags::MSG_CTRUNC.bits() as u32;
+        }
+
         Ok(())
     }
 
@@ -116,13 +271,140 @@ impl CUserMsgHdr {
         &self,
         user_space: &'a CurrentUserSpace<'a>,
     ) -> Result<VmReaderArray<'a>> {
-        VmReaderArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen as usize)
+        VmReaderArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen)
     }
 
     pub fn copy_writer_array_from_user<'a>(
         &self,
         user_space: &'a CurrentUserSpace<'a>,
     ) -> Result<VmWriterArray<'a>> {
-        VmWriterArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen as usize)
+        VmWriterArray::from_user_io_vecs(user_space, self.msg_iov, self.msg_iovlen)
     }
 }
+
+/// Control message header.
+///
+/// The definition is from https://elixir.bootlin.com/linux/v6.0.9/source/include/linux/socket.h#L95.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CControlMessageHeader {
+    /// Data byte count, including the header
+    cmsg_len: usize,
+    /// Originating protocol
+    cmsg_level: i32,
+    /// Protocol-specific type
+    cmsg_type: i32,
+}
+
+const CMSG_HDR_LEN: usize = size_of::<CControlMessageHeader>();
+
+/// Rounds up the length of a control message so that the next one is properly aligned.
+const fn cmsg_align(len: usize) -> usize {
+    len.next_multiple_of(size_of::<usize>())
+}
+
+/// The maximum length of the control messages that can be sent.
+///
+/// This is the default value of `net.core.optmem_max` in Linux.
+const MAX_CONTROL_LEN: usize = 20480;
+
+/// The maximum number of file descriptors that can be sent in an `SCM_RIGHTS` message.
+const SCM_MAX_FD: usize = 253;
+
+const SOL_IP: i32 = 0;
+const SOL_SOCKET: i32 = 1;
+const SOL_IPV6: i32 = 41;
+
+const SCM_RIGHTS: i32 = 1;
+const SCM_CREDENTIALS: i32 = 2;
+
+const IP_RECVERR: i32 = 11;
+const IPV6_RECVERR: i32 = 25;
+
+/// Extended socket error.
+///
+/// The definition is from https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/errqueue.h#L8.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CSockExtendedErr {
+    ee_errno: u32,
+    ee_origin: u8,
+    ee_type: u8,
+    ee_code: u8,
+    ee_pad: u8,
+    ee_info: u32,
+    ee_data: u32,
+}
+
+/// Converts an extended socket error to the bytes of the `IP_RECVERR` or `IPV6_RECVERR` control
+/// message, which consist of the C structure followed by the offender address.
+fn extended_error_to_bytes(error: &ExtendedError) -> Vec<u8> {
+    let c_error = CSockExtendedErr {
+        ee_errno: error.errno as u32,
+        ee_origin: error.origin as u8,
+        ee_type: error.type_,
+        ee_code: error.code,
+        ee_pad: 0,
+        ee_info: error.info,
+        ee_data: 0,
+    };
+
+    let mut bytes = c_error.as_bytes().to_vec();
+    bytes.extend_from_slice(&ip_addr_to_c_bytes(error.offender));
+    bytes
+}
+
+fn parse_control_message(
+    header: &CControlMessageHeader,
+    data: &[u8],
+    ctx: &Context,
+) -> Result<Option<ControlMessage>> {
+    if header.cmsg_level != SOL_SOCKET {
+        // TODO: Support control messages at other levels (e.g., `IP_PKTINFO`).
+        warn!(
+            "control messages at level {} are not supported",
+            header.cmsg_level
+        );
+        return Ok(None);
+    }
+
+    let control_message = match header.cmsg_type {
+        SCM_RIGHTS => {
+            let num_fds = data.len() / size_of::<FileDesc>();
+            if num_fds == 0 {
+                return Ok(None);
+            }
+            if num_fds > SCM_MAX_FD {
+                return_errno_with_message!(Errno::EINVAL, "too many file descriptors are sent");
+            }
+
+            let file_table = ctx.thread_local.borrow_file_table();
+            let file_table_locked = file_table.unwrap().read();
+            let files = data
+                .chunks_exact(size_of::<FileDesc>())
+                .map(|bytes| {
+                    let fd = FileDesc::from_bytes(bytes);
+                    file_table_locked.get_file(fd).cloned()
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            ControlMessage::Rights(files)
+        }
+        SCM_CREDENTIALS => {
+            if data.len() != size_of::<UCred>() {
+                return_errno_with_message!(Errno::EINVAL, "the credentials are invalid");
+            }
+
+            let cred = UCred::from_bytes(data);
+            cred.check_sendable()?;
+
+            ControlMessage::Credentials(cred)
+        }
+        _ => return_errno_with_message!(
+            Errno::EINVAL,
+            "the socket-level control message type is invalid"
+        ),
+    };
+
+    Ok(Some(control_message))
+}
@@ -15,7 +15,11 @@
 //! use. It also hooks up the VDSO data update routine to the time management subsystem for periodic updates.
 
 use alloc::{boxed::Box, sync::Arc};
-use core::{mem::ManuallyDrop, time::Duration};
+use core::{
+    mem::{offset_of, size_of, ManuallyDrop},
+    sync::atomic::{fence, Ordering},
+    time::Duration,
+};
 
 use aster_rights::Rights;
 use aster_time::{read_monotonic_time, Instant};
@@ -27,13 +31,26 @@ use ostd::{
 };
 use spin::Once;
 
+#[cfg(target_arch = "x86_64")]
+use crate::fs::fs_resolver::{FsPath, FsResolver, AT_FDCWD};
 use crate::{
-    fs::fs_resolver::{FsPath, FsResolver, AT_FDCWD},
     syscall::ClockId,
     time::{clocks::MonotonicClock, timer::Timeout, SystemTime, START_TIME},
     vm::vmo::{Vmo, VmoOptions},
 };
 
+/// The offset of `VdsoData` in the VDSO data page, which is determined by the VDSO library of
+/// Linux.
+#[cfg(target_arch = "x86_64")]
+const VDSO_DATA_OFFSET: usize = 0x80;
+/// The offset of `VdsoData` in the VDSO data page, which is determined by the VDSO image in
+/// `arch/riscv/vdso.S`.
+#[cfg(target_arch = "riscv64")]
+const VDSO_DATA_OFFSET: usize = 0;
+
+/// The offset of the VDSO library text in the VDSO VMO.
+pub const VDSO_TEXT_OFFSET: usize = 0x4000;
+
 const CLOCK_TAI: usize = 11;
 const VDSO_BASES: usize = CLOCK_TAI + 1;
 const DEFAULT_CLOCK_MODE: VdsoClockMode = VdsoClockMode::Tsc;
@@ -80,9 +97,9 @@ struct ArchVdsoData {}
 
 /// A POD (Plain Old Data) structure maintaining timing information that required for userspace.
 ///
-/// Since currently we directly use the VDSO shared library of Linux,
+/// Since currently we directly use the VDSO shared library of Linux on x86-64,
 /// currently it aligns with the Linux VDSO shared library format and contents
-/// (Linux v6.2.10)
+/// (Linux v6.2.10). The RISC-V VDSO image follows the same layout.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod)]
 struct VdsoData {
@@ -139,6 +156,9 @@ impl VdsoData {
         let coeff = clocksource.coeff();
         self.set_clock_mode(DEFAULT_CLOCK_MODE);
         self.set_coeff(coeff);
+        // The high-resolution clocks can count every single cycle of the clocksource, which is
+        // at least as fine-grained as a nanosecond.
+        self.hrtimer_res = 1;
 
         let (last_instant, last_cycles) = clocksource.last_record();
         self.update_high_res_instant(last_instant, last_cycles);
@@ -220,18 +240,13 @@ impl Vdso {
             let vmo_options = VmoOptions::<Rights>::new(VDSO_VMO_SIZE);
             let vdso_vmo = vmo_options.alloc().unwrap();
             // Write VDSO data to VDSO VMO.
-            vdso_vmo.write_bytes(0x80, vdso_data.as_bytes()).unwrap();
+            vdso_vmo
+                .write_bytes(VDSO_DATA_OFFSET, vdso_data.as_bytes())
+                .unwrap();
 
-            let vdso_lib_vmo = {
-                let vdso_path = FsPath::new(AT_FDCWD, "/lib/x86_64-linux-gnu/vdso64.so").unwrap();
-                let fs_resolver = FsResolver::new();
-                let vdso_lib = fs_resolver.lookup(&vdso_path).unwrap();
-                vdso_lib.inode().page_cache().unwrap()
-            };
-            let mut vdso_text = Box::new([0u8; PAGE_SIZE]);
-            vdso_lib_vmo.read_bytes(0, &mut *vdso_text).unwrap();
+            let vdso_text = read_vdso_text();
             // Write VDSO library to VDSO VMO.
-            vdso_vmo.write_bytes(0x4000, &*vdso_text).unwrap();
+            vdso_vmo.write_bytes(VDSO_TEXT_OFFSET, &*vdso_text).unwrap();
 
             let data_frame = vdso_vmo.try_commit_page(0).unwrap();
             (vdso_vmo, data_frame)
@@ -249,36 +264,65 @@ impl Vdso {
             .lock()
             .update_high_res_instant(instant, instant_cycles);
 
-        // Update begins.
-        self.data_frame.write_val(0x80, &1).unwrap();
-        self.data_frame.write_val(0x88, &instant_cycles).unwrap();
+        self.begin_update();
+        self.data_frame
+            .write_val(
+                VDSO_DATA_OFFSET + offset_of!(VdsoData, last_cycles),
+                &instant_cycles,
+            )
+            .unwrap();
         for clock_id in HIGH_RES_CLOCK_IDS {
             self.update_data_frame_instant(clock_id);
         }
-
-        // Update finishes.
-        self.data_frame.write_val(0x80, &0).unwrap();
+        self.end_update();
     }
 
     fn update_coarse_res_instant(&self, instant: Instant) {
         let seq_lock = SEQ_LOCK.lock();
         self.data.lock().update_coarse_res_instant(instant);
 
-        // Update begins.
-        self.data_frame.write_val(0x80, &1).unwrap();
+        self.begin_update();
         for clock_id in COARSE_RES_CLOCK_IDS {
             self.update_data_frame_instant(clock_id);
         }
+        self.end_update();
+    }
+
+    /// Makes the `seq` field odd to tell the readers that an update begins.
+    ///
+    /// The caller must hold `SEQ_LOCK`.
+    fn begin_update(&self) {
+        self.write_seq();
+        fence(Ordering::Release);
+    }
 
-        // Update finishes.
-        self.data_frame.write_val(0x80, &0).unwrap();
+    /// Makes the `seq` field even to tell the readers that the update finishes.
+    ///
+    /// The caller must hold `SEQ_LOCK`.
+    fn end_update(&self) {
+        fence(Ordering::Release);
+        self.write_seq();
+    }
+
+    fn write_seq(&self) {
+        let seq = {
+            let mut data = self.data.lock();
+            data.seq = data.seq.wrapping_add(1);
+            data.seq
+        };
+        self.data_frame
+            .write_val(VDSO_DATA_OFFSET + offset_of!(VdsoData, seq), &seq)
+            .unwrap();
     }
 
     /// Update the requisite fields of the VDSO data in the `data_frame`.
     fn update_data_frame_instant(&self, clockid: ClockId) {
         let clock_index = clockid as usize;
-        let secs_offset = 0xA0 + clock_index * 0x10;
-        let nanos_info_offset = 0xA8 + clock_index * 0x10;
+        let instant_offset = VDSO_DATA_OFFSET
+            + offset_of!(VdsoData, basetime)
+            + clock_index * size_of::<VdsoInstant>();
+        let secs_offset = instant_offset + offset_of!(VdsoInstant, secs);
+        let nanos_info_offset = instant_offset + offset_of!(VdsoInstant, nanos_info);
         let data = self.data.lock();
         self.data_frame
             .write_val(secs_offset, &data.basetime[clock_index].secs)
@@ -289,6 +333,31 @@ impl Vdso {
     }
 }
 
+/// Reads the text of the VDSO library.
+#[cfg(target_arch = "x86_64")]
+fn read_vdso_text() -> Box<[u8; PAGE_SIZE]> {
+    let vdso_lib_vmo = {
+        let vdso_path = FsPath::new(AT_FDCWD, "/lib/x86_64-linux-gnu/vdso64.so").unwrap();
+        let fs_resolver = FsResolver::new();
+        let vdso_lib = fs_resolver.lookup(&vdso_path).unwrap();
+        vdso_lib.inode().page_cache().unwrap()
+    };
+    let mut vdso_text = Box::new([0u8; PAGE_SIZE]);
+    vdso_lib_vmo.read_bytes(0, &mut *vdso_text).unwrap();
+    vdso_text
+}
+
+/// Reads the text of the VDSO library.
+#[cfg(target_arch = "riscv64")]
+fn read_vdso_text() -> Box<[u8; PAGE_SIZE]> {
+    let vdso_image = crate::arch::vdso::vdso_image();
+    assert!(vdso_image.len() <= PAGE_SIZE);
+
+    let mut vdso_text = Box::new([0u8; PAGE_SIZE]);
+    vdso_text[..vdso_image.len()].copy_from_slice(vdso_image);
+    vdso_text
+}
+
 /// Update the `VdsoInstant` for clock IDs with high resolution in Vdso.
 fn update_vdso_high_res_instant(instant: Instant, instant_cycles: u64) {
     VDSO.get()
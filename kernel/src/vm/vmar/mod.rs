@@ -26,6 +26,7 @@ use self::{
 };
 use crate::{
     fs::utils::Inode,
+    ipc::shm::ShmAttachment,
     prelude::*,
     process::{Process, ResourceType},
     thread::exception::PageFaultInfo,
@@ -773,6 +774,7 @@ pub struct VmarMapOptions<'a, R1, R2> {
     parent: &'a Vmar<R1>,
     vmo: Option<Vmo<R2>>,
     inode: Option<Arc<dyn Inode>>,
+    shm_attachment: Option<ShmAttachment>,
     perms: VmPerms,
     vmo_offset: usize,
     vmo_limit: usize,
@@ -798,6 +800,7 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
             parent,
             vmo: None,
             inode: None,
+            shm_attachment: None,
             perms,
             vmo_offset: 0,
             vmo_limit: usize::MAX,
@@ -906,6 +909,16 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
         self.handle_page_faults_around = true;
         self
     }
+
+    /// Binds the attachment of a System V shared memory segment to the mapping.
+    ///
+    /// The attachment is kept as long as the mapping (or any part of it)
+    /// exists, so that the segment can count its attaches. The [`Self::vmo`]
+    /// should be the VMO of the segment.
+    pub fn shm_attachment(mut self, shm_attachment: ShmAttachment) -> Self {
+        self.shm_attachment = Some(shm_attachment);
+        self
+    }
 }
 
 impl<R1> VmarMapOptions<'_, R1, Rights> {
@@ -953,6 +966,7 @@ where
             parent,
             vmo,
             inode,
+            shm_attachment,
             perms,
             vmo_offset,
             vmo_limit,
@@ -1011,6 +1025,7 @@ where
             map_to_addr,
             vmo,
             inode,
+            shm_attachment,
             is_shared,
             handle_page_faults_around,
             perms,
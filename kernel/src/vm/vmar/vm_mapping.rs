@@ -17,6 +17,7 @@ use ostd::{
 use super::{interval_set::Interval, RssDelta, RssType};
 use crate::{
     fs::utils::Inode,
+    ipc::shm::ShmAttachment,
     prelude::*,
     thread::exception::PageFaultInfo,
     vm::{
@@ -62,6 +63,10 @@ pub struct VmMapping {
     /// If the inode is `Some`, it means that the mapping is file-backed.
     /// And the `vmo` field must be the page cache of the inode.
     inode: Option<Arc<dyn Inode>>,
+    /// The attachment of the System V shared memory segment that backs the mapping.
+    ///
+    /// If the attachment is `Some`, the `vmo` field must be the VMO of the segment.
+    shm_attachment: Option<ShmAttachment>,
     /// Whether the mapping is shared.
     ///
     /// The updates to a shared mapping are visible among processes, or carried
@@ -85,11 +90,13 @@ impl Interval<Vaddr> for VmMapping {
 /***************************** Basic methods *********************************/
 
 impl VmMapping {
+    #[expect(clippy::too_many_arguments)]
     pub(super) fn new(
         map_size: NonZeroUsize,
         map_to_addr: Vaddr,
         vmo: Option<MappedVmo>,
         inode: Option<Arc<dyn Inode>>,
+        shm_attachment: Option<ShmAttachment>,
         is_shared: bool,
         handle_page_faults_around: bool,
         perms: VmPerms,
@@ -99,6 +106,7 @@ impl VmMapping {
             map_to_addr,
             vmo,
             inode,
+            shm_attachment,
             is_shared,
             handle_page_faults_around,
             perms,
@@ -109,6 +117,7 @@ impl VmMapping {
         Ok(VmMapping {
             vmo: self.vmo.as_ref().map(|vmo| vmo.dup()).transpose()?,
             inode: self.inode.clone(),
+            shm_attachment: self.shm_attachment.clone(),
             ..*self
         })
     }
@@ -144,6 +153,12 @@ impl VmMapping {
         self.inode.as_ref()
     }
 
+    /// Returns the attachment of the System V shared memory segment that backs
+    /// the mapping.
+    pub fn shm_attachment(&self) -> Option<&ShmAttachment> {
+        self.shm_attachment.as_ref()
+    }
+
     /// Returns the mapping's RSS type.
     pub fn rss_type(&self) -> RssType {
         if self.vmo.is_none() {
@@ -417,6 +432,7 @@ impl VmMapping {
             map_size: NonZeroUsize::new(left_size).unwrap(),
             vmo: l_vmo,
             inode: self.inode.clone(),
+            shm_attachment: self.shm_attachment.clone(),
             ..self
         };
         let right = Self {
@@ -424,6 +440,7 @@ impl VmMapping {
             map_size: NonZeroUsize::new(right_size).unwrap(),
             vmo: r_vmo,
             inode: self.inode,
+            shm_attachment: self.shm_attachment,
             ..self
         };
 
@@ -30,17 +30,13 @@ fn parse_kernel_commandline() -> &'static str {
 }
 
 fn parse_initramfs() -> Option<&'static [u8]> {
-    const BUSYBOX_BIN: &[u8] =
-        include_bytes!("../../../../../initramfs/tmp/busybox/_install/bin/busybox");
-    Some(BUSYBOX_BIN)
-    // let Some((start, end)) = parse_initramfs_range() else {
-    //     return None;
-    // };
+    let (start, end) = parse_initramfs_range()?;
 
-    // let base_va = paddr_to_vaddr(start);
-    // let length = end - start;
+    let base_va = paddr_to_vaddr(start);
+    let length = end - start;
 
-    // Some(unsafe { core::slice::from_raw_parts(base_va as *const u8, length) })
+    // SAFETY: The initramfs is safe to read because of the contract with the loader.
+    Some(unsafe { core::slice::from_raw_parts(base_va as *const u8, length) })
 }
 
 fn parse_acpi_arg() -> BootloaderAcpiArg {
@@ -108,16 +104,6 @@ fn parse_initramfs_range() -> Option<(usize, usize)> {
     Some((initrd_start, initrd_end))
 }
 
-fn parse_embeded_data() -> [Option<&'static [u8]>; 10] {
-    let mut array = [None; 10];
-    const BUSYBOX_BIN: &[u8] =
-        include_bytes!("../../../../../initramfs/tmp/busybox/_install/bin/busybox");
-    array[0] = Some(BUSYBOX_BIN);
-    const TEST_SCRIPT: &[u8] = include_bytes!("../../../../../initramfs/test.sh");
-    array[1] = Some(TEST_SCRIPT);
-    array
-}
-
 fn clear_bss() {
     unsafe extern "C" {
         unsafe fn __bss();
@@ -133,11 +119,13 @@ fn clear_bss() {
 
 /// The entry point of the Rust code portion of Asterinas.
 #[no_mangle]
-pub extern "C" fn riscv_boot(_hart_id: usize, device_tree_paddr: usize) -> ! {
+pub extern "C" fn riscv_boot(hart_id: usize, device_tree_paddr: usize) -> ! {
     early_println!("Enter riscv_boot");
 
     clear_bss();
 
+    smp::init_bsp_hart_id(hart_id as u32);
+
     let device_tree_ptr = paddr_to_vaddr(device_tree_paddr) as *const u8;
     let fdt = unsafe { fdt::Fdt::from_ptr(device_tree_ptr).unwrap() };
     DEVICE_TREE.call_once(|| fdt);
@@ -151,7 +139,6 @@ pub extern "C" fn riscv_boot(_hart_id: usize, device_tree_paddr: usize) -> ! {
         acpi_arg: parse_acpi_arg(),
         framebuffer_arg: parse_framebuffer_info(),
         memory_regions: parse_memory_regions(),
-        embeded_data: parse_embeded_data(),
     });
 
     call_ostd_main();
@@ -1,13 +1,142 @@
 // SPDX-License-Identifier: MPL-2.0
 
 //! Multiprocessor Boot Support
+//!
+//! On RISC-V, the harts (i.e., the processors) are described in the device
+//! tree. The SBI firmware starts one of them as the boot hart (the BSP), and
+//! parks the others. The BSP wakes up the other harts (the APs) with the SBI
+//! Hart State Management (HSM) extension, which asks the firmware to start the
+//! hart at the given physical address in supervisor mode, with the MMU off.
+//!
+//! Reference: <https://github.com/riscv-non-isa/riscv-sbi-doc/blob/master/src/ext-hsm.adoc>.
 
-use crate::{boot::smp::PerApRawInfo, mm::Paddr};
+use core::sync::atomic::{fence, AtomicU32, Ordering};
 
+use crate::{
+    arch::boot::DEVICE_TREE,
+    boot::smp::PerApRawInfo,
+    mm::{kspace::kernel_loaded_offset, Paddr},
+};
+
+/// The hart ID of the BSP.
+static BSP_HART_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Records the hart ID of the BSP.
+///
+/// This function should be called by the BSP in the boot entry, after the BSS
+/// section is cleared.
+pub(super) fn init_bsp_hart_id(hart_id: u32) {
+    BSP_HART_ID.store(hart_id, Ordering::Relaxed);
+}
+
+/// Returns the hart ID of the BSP.
+pub fn bsp_hart_id() -> u32 {
+    BSP_HART_ID.load(Ordering::Relaxed)
+}
+
+/// Counts the number of processors.
+///
+/// This function needs to be called after the OS initializes the device tree.
 pub(crate) fn count_processors() -> Option<u32> {
-    Some(1)
+    let num_harts = usable_harts().count() as u32;
+    // The boot hart is always usable, even if the device tree says otherwise.
+    Some(num_harts.max(1))
 }
 
-pub(crate) fn bringup_all_aps(_info_ptr: *const PerApRawInfo, _pr_ptr: Paddr, _num_cpus: u32) {
-    unimplemented!()
+/// Returns the IDs of the harts that can run the kernel.
+///
+/// A hart is usable unless it is disabled in the device tree (e.g., a
+/// monitor core without an MMU).
+fn usable_harts() -> impl Iterator<Item = u32> {
+    DEVICE_TREE
+        .get()
+        .unwrap()
+        .cpus()
+        .filter(|cpu| {
+            cpu.property("status")
+                .and_then(|status| status.as_str())
+                .is_none_or(|status| status == "okay" || status == "ok")
+        })
+        .map(|cpu| cpu.ids().first() as u32)
+}
+
+/// Brings up all application processors.
+///
+/// The APs are assigned CPU IDs in the order in which they appear in the
+/// device tree, skipping the BSP.
+///
+/// # Safety
+///
+/// The caller must ensure that
+/// 1. we're in the boot context of the BSP,
+/// 2. all APs have not yet been booted, and
+/// 3. the arguments are valid to boot APs.
+pub(crate) unsafe fn bringup_all_aps(info_ptr: *const PerApRawInfo, pt_ptr: Paddr, num_cpus: u32) {
+    // SAFETY: The pointers are valid to write because there are no readers
+    // and we are the only writer at this point.
+    unsafe {
+        fill_boot_info_ptr(info_ptr);
+        fill_boot_pt_ptr(pt_ptr);
+    }
+
+    // Make sure that the APs see the boot information before they start.
+    fence(Ordering::SeqCst);
+
+    // The symbol is defined in `boot.S`.
+    extern "C" {
+        fn _start_ap();
+    }
+    let start_addr = _start_ap as usize - kernel_loaded_offset();
+
+    let bsp_hart_id = bsp_hart_id();
+    let ap_hart_ids = usable_harts().filter(|hart_id| *hart_id != bsp_hart_id);
+
+    for (cpu_id, hart_id) in (1..num_cpus).zip(ap_hart_ids) {
+        // The CPU ID is passed to the AP as the opaque argument.
+        let ret = sbi_rt::hart_start(hart_id as usize, start_addr, cpu_id as usize);
+        if ret.error != 0 {
+            panic!("Failed to start hart {hart_id} as CPU {cpu_id}: {ret:?}");
+        }
+    }
+}
+
+/// The Rust entry point of the APs.
+///
+/// This is called by the assembly code in `boot.S` after the paging, the
+/// stack, and the CPU-local storage have been set up.
+#[no_mangle]
+extern "C" fn riscv_ap_early_entry(hart_id: usize, cpu_id: usize) -> ! {
+    // SAFETY: This is the early boot phase of the AP, and `hart_id` is the
+    // hart ID passed by the SBI firmware.
+    unsafe { crate::arch::cpu::set_this_hart_id(hart_id as u32) };
+
+    crate::boot::smp::ap_early_entry(cpu_id as u32)
+}
+
+/// # Safety
+///
+/// The caller must ensure the pointer to be filled is valid to write.
+unsafe fn fill_boot_info_ptr(info_ptr: *const PerApRawInfo) {
+    extern "C" {
+        static mut __ap_boot_info_array_pointer: *const PerApRawInfo;
+    }
+
+    // SAFETY: The safety is upheld by the caller.
+    unsafe {
+        __ap_boot_info_array_pointer = info_ptr;
+    }
+}
+
+/// # Safety
+///
+/// The caller must ensure the pointer to be filled is valid to write.
+unsafe fn fill_boot_pt_ptr(pt_ptr: Paddr) {
+    extern "C" {
+        static mut __ap_boot_page_table_pointer: Paddr;
+    }
+
+    // SAFETY: The safety is upheld by the caller.
+    unsafe {
+        __ap_boot_page_table_pointer = pt_ptr;
+    }
 }
@@ -9,11 +9,11 @@ use riscv::register::scause::{Exception, Interrupt, Trap};
 pub use crate::arch::trap::GeneralRegs as RawGeneralRegs;
 use crate::{
     arch::{
-        kernel::plic::claim_interrupt,
+        irq::handle_ipi,
+        kernel::claim_interrupt,
         timer::handle_timer_interrupt,
         trap::{TrapFrame, UserContext as RawUserContext},
     },
-    cpu::current_cpu_racy,
     trap::call_irq_callback_functions,
     user::{ReturnReason, UserContextApi, UserContextApiInternal},
 };
@@ -135,12 +135,15 @@ impl UserContextApiInternal for UserContext {
                     handle_timer_interrupt();
                 }
                 Trap::Interrupt(Interrupt::SupervisorExternal) => {
-                    while let irq_num = claim_interrupt(current_cpu_racy().as_usize())
+                    while let irq_num = claim_interrupt()
                         && irq_num != 0
                     {
                         call_irq_callback_functions(&self.as_trap_frame(), irq_num);
                     }
                 }
+                Trap::Interrupt(Interrupt::SupervisorSoft) => {
+                    handle_ipi(&self.as_trap_frame());
+                }
                 Trap::Interrupt(_) => todo!(),
                 Trap::Exception(Exception::UserEnvCall) => {
                     self.user_context.sepc += 4;
@@ -8,6 +8,34 @@ pub mod local;
 
 pub use extension::{has_extensions, IsaExtensions};
 
+use crate::cpu_local_cell;
+
+cpu_local_cell! {
+    /// The hart ID of the current CPU.
+    static HART_ID: u32 = 0;
+}
+
+/// Returns the hart ID of the current CPU.
+///
+/// Unlike the CPU ID, the hart ID is assigned by the hardware and is not
+/// necessarily contiguous. It is used to talk to the SBI and the PLIC.
+///
+/// The caller should be pinned to the current CPU (e.g., in the interrupt
+/// context) if the result is used to perform hart-specific operations.
+pub(crate) fn this_hart_id() -> u32 {
+    HART_ID.load()
+}
+
+/// Initializes the hart ID of the current CPU.
+///
+/// # Safety
+///
+/// This function must be called on each processor during the early boot
+/// phase of the processor, with the correct value of the hart ID.
+pub(crate) unsafe fn set_this_hart_id(hart_id: u32) {
+    HART_ID.store(hart_id);
+}
+
 /// Halts the CPU.
 ///
 /// This function halts the CPU until the next interrupt is received. By
@@ -2,9 +2,18 @@
 
 //! Interrupts.
 
-use crate::cpu::PinCurrentCpu;
+use core::sync::atomic::{AtomicU64, Ordering};
 
-pub(crate) const IRQ_NUM_MIN: u8 = 0;
+use crate::{
+    arch::cpu::this_hart_id,
+    cpu::{CpuId, PinCurrentCpu},
+    cpu_local,
+    trap::{call_irq_callback_functions, TrapFrame},
+};
+
+// The IRQ numbers are used as the interrupt source numbers of the PLIC and the
+// APLIC, and the interrupt identities of the IMSIC, none of which can be zero.
+pub(crate) const IRQ_NUM_MIN: u8 = 1;
 pub(crate) const IRQ_NUM_MAX: u8 = 255;
 
 pub(crate) struct IrqRemapping {
@@ -46,13 +55,60 @@ pub(crate) fn is_local_enabled() -> bool {
 // ####### Inter-Processor Interrupts (IPIs) #######
 
 /// Hardware-specific, architecture-dependent CPU ID.
+///
+/// On RISC-V, this is the hart ID. The CPU ID is also recorded to find the
+/// pending IPIs of the target CPU.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct HwCpuId(u32);
+pub(crate) struct HwCpuId {
+    hart_id: u32,
+    cpu_id: CpuId,
+}
 
 impl HwCpuId {
     pub(crate) fn read_current(guard: &dyn PinCurrentCpu) -> Self {
-        // TODO: Support SMP in RISC-V.
-        Self(0)
+        Self {
+            hart_id: this_hart_id(),
+            cpu_id: guard.current_cpu(),
+        }
+    }
+}
+
+/// The number of 64-bit words to hold a bit for every IRQ number.
+const NR_PENDING_IPI_WORDS: usize = (IRQ_NUM_MAX as usize + 1).div_ceil(u64::BITS as usize);
+
+cpu_local! {
+    /// The IRQ numbers of the IPIs that are pending on this CPU.
+    ///
+    /// The SBI IPI is the supervisor software interrupt, which does not carry
+    /// an IRQ number. So the sender records the IRQ number here before
+    /// interrupting the target CPU.
+    static PENDING_IPIS: [AtomicU64; NR_PENDING_IPI_WORDS] =
+        [const { AtomicU64::new(0) }; NR_PENDING_IPI_WORDS];
+}
+
+/// Enables the IPIs on the current CPU.
+pub(crate) fn enable_ipi() {
+    // SAFETY: The IPIs are handled by `handle_ipi` in the trap handler.
+    unsafe { riscv::register::sie::set_ssoft() };
+}
+
+/// Handles the supervisor software interrupt, which delivers the IPIs.
+pub(crate) fn handle_ipi(trap_frame: &TrapFrame) {
+    // Clear the interrupt before taking the pending IRQ numbers, so that an
+    // IPI sent after that will interrupt this CPU again.
+    //
+    // SAFETY: Clearing the `SSIP` bit only acknowledges the software interrupt.
+    unsafe { core::arch::asm!("csrc sip, {}", in(reg) 1 << 1) };
+
+    // No races because we are in IRQs.
+    let pending_ipis = PENDING_IPIS.get_on_cpu(CpuId::current_racy());
+    for (index, pending) in pending_ipis.iter().enumerate() {
+        let mut bits = pending.swap(0, Ordering::Acquire);
+        while bits != 0 {
+            let bit = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            call_irq_callback_functions(trap_frame, index * u64::BITS as usize + bit);
+        }
     }
 }
 
@@ -63,6 +119,13 @@ impl HwCpuId {
 /// The caller must ensure that the interrupt number is valid and that
 /// the corresponding handler is configured correctly on the remote CPU.
 /// Furthermore, invoking the interrupt handler must also be safe.
-pub(crate) unsafe fn send_ipi(hw_cpu_id: HwCpuId, irq_num: u8, guard: &dyn PinCurrentCpu) {
-    unimplemented!()
+pub(crate) unsafe fn send_ipi(hw_cpu_id: HwCpuId, irq_num: u8, _guard: &dyn PinCurrentCpu) {
+    let irq_num = irq_num as usize;
+    let pending_ipis = PENDING_IPIS.get_on_cpu(hw_cpu_id.cpu_id);
+    pending_ipis[irq_num / u64::BITS as usize]
+        .fetch_or(1 << (irq_num % u64::BITS as usize), Ordering::Release);
+
+    let hart_mask = sbi_rt::HartMask::from_mask_base(1, hw_cpu_id.hart_id as usize);
+    let ret = sbi_rt::send_ipi(hart_mask);
+    debug_assert_eq!(ret.error, 0, "failed to send IPI: {:?}", ret);
 }
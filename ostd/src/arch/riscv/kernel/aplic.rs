@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! RISC-V Advanced Platform-Level Interrupt Controller (APLIC).
+//!
+//! The APLIC is part of the Advanced Interrupt Architecture (AIA). It receives
+//! the wired interrupts of the devices. We only support the APLIC domain of
+//! the supervisor mode in the MSI delivery mode, where the wired interrupts
+//! are forwarded to the IMSIC as MSIs.
+//!
+//! Reference: <https://github.com/riscv/riscv-aia>.
+
+use log::warn;
+use spin::Once;
+
+use super::imsic::IMSIC;
+use crate::{
+    arch::boot::DEVICE_TREE,
+    io::{IoMem, IoMemAllocatorBuilder},
+    mm::{CachePolicy, Paddr, PageFlags, VmIoOnce},
+};
+
+/// The APLIC domain of the supervisor mode.
+pub struct Aplic {
+    io_mem: IoMem,
+    num_sources: u32,
+}
+
+/// The source mode of an interrupt source, which determines how the wired
+/// interrupt is triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SourceMode {
+    /// Asserted on the rising edge.
+    EdgeRising = 4,
+    /// Asserted on the falling edge.
+    EdgeFalling = 5,
+    /// Asserted when high.
+    LevelHigh = 6,
+    /// Asserted when low.
+    LevelLow = 7,
+}
+
+impl Aplic {
+    const DOMAINCFG: usize = 0x0000;
+    const SOURCECFG_BASE: usize = 0x0004;
+    const SETIENUM: usize = 0x1EDC;
+    const CLRIENUM: usize = 0x1FDC;
+    const SETIPNUM_LE: usize = 0x2000;
+    const TARGET_BASE: usize = 0x3004;
+
+    /// The interrupt enable bit of `domaincfg`.
+    const DOMAINCFG_IE: u32 = 1 << 8;
+    /// The delivery mode bit of `domaincfg`, which selects the MSI delivery mode.
+    const DOMAINCFG_DM: u32 = 1 << 2;
+
+    /// Creates a new APLIC instance.
+    ///
+    /// # Arguments
+    /// * `base_paddr` - The base physical address of the APLIC domain.
+    /// * `size` - The size of the APLIC domain in bytes.
+    /// * `num_sources` - The number of the interrupt sources.
+    pub fn new(base_paddr: Paddr, size: usize, num_sources: u32) -> Self {
+        // SAFETY: We are building I/O memory using a region that is
+        // specified as APLIC I/O memory in device tree.
+        let io_mem = unsafe {
+            IoMem::new(
+                base_paddr..base_paddr + size,
+                PageFlags::RW,
+                CachePolicy::Uncacheable,
+            )
+        };
+        Self {
+            io_mem,
+            num_sources,
+        }
+    }
+
+    /// Base address of the APLIC domain.
+    pub fn address(&self) -> Paddr {
+        self.io_mem.paddr()
+    }
+
+    /// Returns the number of the interrupt sources.
+    pub fn num_sources(&self) -> u32 {
+        self.num_sources
+    }
+
+    /// Enables an interrupt source.
+    ///
+    /// The wired interrupt will be forwarded as an MSI with the same interrupt
+    /// identity as the source number to the IMSIC interrupt file at the given
+    /// index.
+    pub fn enable(&self, source: u32, mode: SourceMode, file_index: u32) {
+        assert!(
+            source != 0 && source <= self.num_sources,
+            "Invalid interrupt source"
+        );
+        let offset = (source as usize - 1) * 4;
+        self.io_mem
+            .write_once::<u32>(Self::SOURCECFG_BASE + offset, &(mode as u32))
+            .unwrap();
+        // The guest index is zero, which selects the interrupt file of the
+        // supervisor mode.
+        let target = (file_index << 18) | source;
+        self.io_mem
+            .write_once::<u32>(Self::TARGET_BASE + offset, &target)
+            .unwrap();
+        self.io_mem
+            .write_once::<u32>(Self::SETIENUM, &source)
+            .unwrap();
+    }
+
+    /// Disables an interrupt source.
+    pub fn disable(&self, source: u32) {
+        assert!(
+            source != 0 && source <= self.num_sources,
+            "Invalid interrupt source"
+        );
+        self.io_mem
+            .write_once::<u32>(Self::CLRIENUM, &source)
+            .unwrap();
+    }
+
+    /// Completes an interrupt.
+    ///
+    /// In the MSI delivery mode, the pending bit of a level-sensitive source
+    /// is cleared once the MSI is sent, even if the wired interrupt is still
+    /// asserted. So the interrupt is retriggered here, which sets the pending
+    /// bit again only if the wired interrupt is still asserted.
+    pub fn complete(&self, source: u32) {
+        if source == 0 || source > self.num_sources {
+            return;
+        }
+        let offset = (source as usize - 1) * 4;
+        let mode = self
+            .io_mem
+            .read_once::<u32>(Self::SOURCECFG_BASE + offset)
+            .unwrap();
+        if mode == SourceMode::LevelHigh as u32 || mode == SourceMode::LevelLow as u32 {
+            self.io_mem
+                .write_once::<u32>(Self::SETIPNUM_LE, &source)
+                .unwrap();
+        }
+    }
+
+    /// Enables the APLIC domain in the MSI delivery mode.
+    fn enable_domain(&self) {
+        self.io_mem
+            .write_once::<u32>(Self::DOMAINCFG, &(Self::DOMAINCFG_IE | Self::DOMAINCFG_DM))
+            .unwrap();
+    }
+}
+
+pub static APLIC: Once<Aplic> = Once::new();
+
+/// Discovers the APLIC domain of the supervisor mode from the device tree.
+///
+/// This should be called after the IMSIC is initialized, because the domain
+/// forwards the wired interrupts to the IMSIC. The address of the MSIs sent by
+/// the domain is configured by the SBI firmware.
+pub fn init(io_mem_builder: &IoMemAllocatorBuilder) {
+    let Some(imsic) = IMSIC.get() else {
+        return;
+    };
+
+    let Some(node) = DEVICE_TREE.get().unwrap().all_nodes().find(|node| {
+        node.compatible()
+            .is_some_and(|compatible| compatible.all().any(|c| c == "riscv,aplic"))
+            && node
+                .property("msi-parent")
+                .and_then(|p| p.as_usize())
+                .is_some_and(|phandle| phandle as u32 == imsic.phandle())
+    }) else {
+        return;
+    };
+
+    let Some(region) = node.reg().and_then(|mut reg| reg.next()) else {
+        warn!("APLIC node {} has no MMIO region", node.name);
+        return;
+    };
+    let num_sources = node
+        .property("riscv,num-sources")
+        .and_then(|p| p.as_usize())
+        .unwrap_or(0) as u32;
+
+    let aplic = APLIC.call_once(|| {
+        Aplic::new(
+            region.starting_address as usize,
+            region.size.unwrap(),
+            num_sources,
+        )
+    });
+    aplic.enable_domain();
+
+    io_mem_builder.remove(
+        region.starting_address as usize..region.starting_address as usize + region.size.unwrap(),
+    );
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! RISC-V Incoming Message-Signaled Interrupt Controller (IMSIC).
+//!
+//! The IMSIC is part of the Advanced Interrupt Architecture (AIA). Each hart
+//! has an interrupt file for the supervisor mode, which is a page of MMIO
+//! registers. A device signals an interrupt by writing the interrupt identity
+//! to the interrupt file of the target hart, and the hart receives the
+//! interrupt as a supervisor external interrupt.
+//!
+//! The interrupt identities are the same as the IRQ numbers, so an IRQ line
+//! can be signaled by MSIs without any further translation.
+//!
+//! Reference: <https://github.com/riscv/riscv-aia>.
+
+use alloc::collections::btree_map::BTreeMap;
+
+use log::warn;
+use spin::Once;
+
+use super::{hart_ids_of_intcs, interrupts_extended, SUPERVISOR_EXTERNAL};
+use crate::{
+    arch::{
+        boot::{smp::bsp_hart_id, DEVICE_TREE},
+        cpu::this_hart_id,
+        irq::IRQ_NUM_MAX,
+    },
+    io::IoMemAllocatorBuilder,
+    mm::{Paddr, PAGE_SIZE},
+};
+
+/// The supervisor-level interrupt file of the IMSIC.
+pub struct Imsic {
+    /// The interrupt files of the harts, indexed by the hart ID.
+    files: BTreeMap<u32, InterruptFile>,
+    /// The number of interrupt identities supported by each interrupt file.
+    num_ids: u32,
+    /// The phandle of the IMSIC node in the device tree.
+    phandle: u32,
+}
+
+/// The interrupt file of a hart.
+#[derive(Debug, Clone, Copy)]
+struct InterruptFile {
+    /// The index of the interrupt file, which is used by the APLIC to find the
+    /// target hart of its MSIs.
+    index: u32,
+    /// The physical address of the interrupt file.
+    paddr: Paddr,
+}
+
+impl Imsic {
+    /// Returns the physical address to which MSIs are written to interrupt
+    /// the hart.
+    pub fn msi_address(&self, hart_id: u32) -> Option<Paddr> {
+        self.files.get(&hart_id).map(|file| file.paddr)
+    }
+
+    /// Returns the index of the interrupt file of the hart.
+    pub fn file_index(&self, hart_id: u32) -> Option<u32> {
+        self.files.get(&hart_id).map(|file| file.index)
+    }
+
+    /// Returns the number of interrupt identities supported by each
+    /// interrupt file.
+    pub fn num_ids(&self) -> u32 {
+        self.num_ids
+    }
+
+    /// Returns the phandle of the IMSIC node in the device tree.
+    pub(super) fn phandle(&self) -> u32 {
+        self.phandle
+    }
+}
+
+// The CSRs that access the interrupt file of the supervisor mode.
+const CSR_SISELECT: usize = 0x150;
+const CSR_SIREG: usize = 0x151;
+const CSR_STOPEI: usize = 0x15C;
+
+// The registers that are accessed indirectly via `siselect` and `sireg`.
+const EIDELIVERY: usize = 0x70;
+const EITHRESHOLD: usize = 0x72;
+const EIE0: usize = 0xC0;
+
+pub static IMSIC: Once<Imsic> = Once::new();
+
+/// Discovers the IMSIC from the device tree.
+///
+/// Only the interrupt files of the supervisor mode are used. The interrupt
+/// files of the machine mode are described by a separate node, which belongs
+/// to the SBI firmware.
+pub fn init(io_mem_builder: &IoMemAllocatorBuilder) {
+    let device_tree = DEVICE_TREE.get().unwrap();
+
+    let Some(node) = device_tree.all_nodes().find(|node| {
+        node.compatible()
+            .is_some_and(|compatible| compatible.all().any(|c| c == "riscv,imsics"))
+            && interrupts_extended(node).any(|(_, cause)| cause == SUPERVISOR_EXTERNAL)
+    }) else {
+        return;
+    };
+
+    let Some(phandle) = node.property("phandle").and_then(|p| p.as_usize()) else {
+        warn!("IMSIC node {} has no phandle", node.name);
+        return;
+    };
+    let num_ids = node
+        .property("riscv,num-ids")
+        .and_then(|p| p.as_usize())
+        .unwrap_or(0) as u32;
+    if num_ids < IRQ_NUM_MAX as u32 {
+        warn!("IMSIC supports {num_ids} interrupt identities, which is fewer than the IRQ numbers");
+    }
+    let guest_index_bits = node
+        .property("riscv,guest-index-bits")
+        .and_then(|p| p.as_usize())
+        .unwrap_or(0);
+    // Each hart has one interrupt file for the supervisor mode and
+    // `1 << guest_index_bits` interrupt files for the guests.
+    let file_stride = PAGE_SIZE << guest_index_bits;
+
+    // The interrupt files are laid out in the MMIO regions in the same order
+    // as the harts in the `interrupts-extended` property.
+    let mut file_paddrs = node.reg().into_iter().flatten().flat_map(|region| {
+        let start = region.starting_address as usize;
+        let num_files = region.size.unwrap_or(0) / file_stride;
+        (0..num_files).map(move |i| start + i * file_stride)
+    });
+
+    let intc_harts = hart_ids_of_intcs();
+    let mut files = BTreeMap::new();
+    for (index, (intc, _)) in interrupts_extended(&node).enumerate() {
+        let Some(paddr) = file_paddrs.next() else {
+            warn!(
+                "IMSIC node {} has fewer interrupt files than harts",
+                node.name
+            );
+            break;
+        };
+        if let Some(hart_id) = intc_harts.get(&intc) {
+            files.insert(
+                *hart_id as u32,
+                InterruptFile {
+                    index: index as u32,
+                    paddr,
+                },
+            );
+        }
+    }
+
+    if !files.contains_key(&bsp_hart_id()) {
+        warn!("IMSIC node {} has no interrupt file for the BSP", node.name);
+        return;
+    }
+
+    for region in node.reg().into_iter().flatten() {
+        let start = region.starting_address as usize;
+        io_mem_builder.remove(start..start + region.size.unwrap());
+    }
+
+    IMSIC.call_once(|| Imsic {
+        files,
+        num_ids,
+        phandle: phandle as u32,
+    });
+}
+
+/// Initializes the interrupt file of the current hart.
+///
+/// This should be called on each CPU after [`init`] is called on the BSP.
+pub(crate) fn init_current_hart() {
+    let Some(imsic) = IMSIC.get() else {
+        return;
+    };
+    if !imsic.files.contains_key(&this_hart_id()) {
+        return;
+    }
+
+    // Enable all the interrupt identities that can be IRQ numbers. The
+    // identities that are not used by any device will never be signaled.
+    let max_id = imsic.num_ids.min(IRQ_NUM_MAX as u32);
+    for word in 0..=max_id / u64::BITS {
+        let first_id = word * u64::BITS;
+        let mut mask = u64::MAX;
+        if max_id - first_id < u64::BITS - 1 {
+            mask >>= u64::BITS - 1 - (max_id - first_id);
+        }
+        // On RV64, only the even-numbered `eie` registers exist, each of
+        // which holds the enable bits of 64 identities.
+        write_indirect(EIE0 + word as usize * 2, mask as usize);
+    }
+
+    // Signal the interrupts of all priorities.
+    write_indirect(EITHRESHOLD, 0);
+    // Deliver the interrupts from the interrupt file.
+    write_indirect(EIDELIVERY, 1);
+
+    // SAFETY: Now we can start the external interrupts.
+    unsafe { riscv::register::sie::set_sext() };
+}
+
+/// Claims the pending interrupt with the highest priority on the current
+/// hart.
+///
+/// This returns zero if no interrupt is pending.
+pub(crate) fn claim_interrupt() -> usize {
+    let value: usize;
+    // SAFETY: Claiming an interrupt only clears its pending bit in the
+    // interrupt file of the current hart.
+    unsafe {
+        core::arch::asm!(
+            "csrrw {value}, {stopei}, zero",
+            value = out(reg) value,
+            stopei = const CSR_STOPEI,
+        )
+    };
+    // The interrupt identity is in bits 26:16.
+    (value >> 16) & 0x7FF
+}
+
+fn write_indirect(reg: usize, value: usize) {
+    // SAFETY: The registers of the interrupt file only control the delivery
+    // of the external interrupts, which are handled by the trap handler.
+    unsafe {
+        core::arch::asm!(
+            "csrw {siselect}, {reg}",
+            "csrw {sireg}, {value}",
+            siselect = const CSR_SISELECT,
+            sireg = const CSR_SIREG,
+            reg = in(reg) reg,
+            value = in(reg) value,
+        )
+    };
+}
@@ -1,5 +1,83 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod aplic;
+pub mod imsic;
 pub(super) mod plic;
 
+use alloc::collections::btree_map::BTreeMap;
+
+pub use aplic::APLIC;
+use fdt::node::FdtNode;
+pub use imsic::IMSIC;
 pub use plic::PLIC;
+
+use crate::arch::{boot::DEVICE_TREE, cpu::this_hart_id};
+
+/// The interrupt cause of the supervisor external interrupts.
+const SUPERVISOR_EXTERNAL: u32 = 9;
+/// The interrupt cause of the machine external interrupts.
+const MACHINE_EXTERNAL: u32 = 11;
+
+/// Claims a pending external interrupt on the current hart.
+///
+/// The interrupts are claimed from the IMSIC if the platform supports the
+/// Advanced Interrupt Architecture (AIA), or from the PLIC otherwise.
+///
+/// This returns zero if no interrupt is pending.
+pub(crate) fn claim_interrupt() -> usize {
+    if IMSIC.is_completed() {
+        imsic::claim_interrupt()
+    } else {
+        plic::claim_interrupt(this_hart_id() as usize)
+    }
+}
+
+/// Completes an external interrupt on the current hart.
+pub(crate) fn complete_interrupt(irq_num: usize) {
+    if let Some(aplic) = APLIC.get() {
+        aplic.complete(irq_num as u32);
+    } else if PLIC.is_completed() {
+        plic::complete_interrupt(this_hart_id() as usize, irq_num);
+    }
+}
+
+/// Maps the phandles of the hart-local interrupt controllers to the hart IDs.
+fn hart_ids_of_intcs() -> BTreeMap<u32, usize> {
+    let mut intc_harts = BTreeMap::new();
+
+    let cpu_nodes = DEVICE_TREE
+        .get()
+        .unwrap()
+        .find_node("/cpus")
+        .into_iter()
+        .flat_map(|cpus| cpus.children());
+    for cpu in cpu_nodes {
+        let Some(hart_id) = cpu.reg().and_then(|mut reg| reg.next()) else {
+            continue;
+        };
+        for child in cpu.children() {
+            if let Some(phandle) = child.property("phandle").and_then(|p| p.as_usize()) {
+                intc_harts.insert(phandle as u32, hart_id.starting_address as usize);
+            }
+        }
+    }
+
+    intc_harts
+}
+
+/// Parses the `interrupts-extended` property of an interrupt controller.
+///
+/// The property lists the pairs of the phandle of a hart-local interrupt
+/// controller and the interrupt cause.
+fn interrupts_extended<'a>(node: &FdtNode<'_, 'a>) -> impl Iterator<Item = (u32, u32)> + 'a {
+    let value = node
+        .property("interrupts-extended")
+        .map_or(&[][..], |property| property.value);
+
+    value.chunks_exact(8).map(|pair| {
+        (
+            u32::from_be_bytes(pair[0..4].try_into().unwrap()),
+            u32::from_be_bytes(pair[4..8].try_into().unwrap()),
+        )
+    })
+}
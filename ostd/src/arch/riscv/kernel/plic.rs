@@ -2,12 +2,14 @@
 
 //! RISC-V Platform Level Interrupt Controller
 
-use alloc::collections::vec_deque::Iter;
+use alloc::collections::{btree_map::BTreeMap, vec_deque::Iter};
 
+use fdt::node::FdtNode;
 use spin::Once;
 
+use super::{hart_ids_of_intcs, interrupts_extended, MACHINE_EXTERNAL, SUPERVISOR_EXTERNAL};
 use crate::{
-    arch::{boot::DEVICE_TREE, device::io_port::PortRead},
+    arch::{boot::DEVICE_TREE, cpu::this_hart_id, device::io_port::PortRead},
     bus::pci::capability,
     io::{IoMem, IoMemAllocatorBuilder},
     mm::{paddr_to_vaddr, CachePolicy, Paddr, PageFlags, VmIoOnce},
@@ -17,6 +19,9 @@ use crate::{
 /// RISC-V Platform Level Interrupt Controller.
 pub struct PLIC {
     io_mem: IoMem,
+    /// The context IDs of the harts, indexed by the hart ID and whether the
+    /// context is for the supervisor mode.
+    contexts: BTreeMap<(usize, bool), usize>,
 }
 
 impl PLIC {
@@ -40,7 +45,10 @@ impl PLIC {
                 CachePolicy::Uncacheable,
             )
         };
-        Self { io_mem }
+        Self {
+            io_mem,
+            contexts: BTreeMap::new(),
+        }
     }
 
     /// Base address of the PLIC.
@@ -83,7 +91,7 @@ impl PLIC {
 
     /// Enable a specific IRQ.
     pub fn enable(&self, hart_id: usize, is_s_mode: bool, interrupt: u8) {
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let pos = context_id * Self::SOURCE_CONUT + interrupt as usize;
         let block = pos / Self::U32_BITS;
         let index = pos % Self::U32_BITS;
@@ -95,7 +103,7 @@ impl PLIC {
 
     /// Disable a specific IRQ.
     pub fn disable(&self, hart_id: usize, is_s_mode: bool, interrupt: u8) {
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let pos = context_id * Self::SOURCE_CONUT + interrupt as usize;
         let block = pos / Self::U32_BITS;
         let index = pos % Self::U32_BITS;
@@ -107,7 +115,7 @@ impl PLIC {
 
     /// Check if a specific IRQ is enabled.
     pub fn is_enabled(&self, hart_id: usize, is_s_mode: bool, interrupt: u8) -> bool {
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let pos = context_id * Self::SOURCE_CONUT + interrupt as usize;
         let block = pos / Self::U32_BITS;
         let index = pos % Self::U32_BITS;
@@ -121,7 +129,7 @@ impl PLIC {
     /// If the threshold is set to 0, all IRQs will be enabled.
     pub fn set_threshold(&self, hart_id: usize, is_s_mode: bool, threshold: u32) {
         assert!(threshold <= 7, "Threshold must be between 0 and 7");
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let offset = 0x200000 + context_id * 4096;
         self.io_mem.write_once::<u32>(offset, &threshold).unwrap();
     }
@@ -130,7 +138,7 @@ impl PLIC {
     ///
     /// Return a tuple of (Machine Mode Threshold, Supervisor Mode Threshold).
     pub fn get_threshold(&self, hart_id: usize, is_s_mode: bool) -> u32 {
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let offset = 0x200000 + context_id * 4096;
         let threshold = self.io_mem.read_once::<u32>(offset).unwrap();
         assert!(threshold <= 7, "Threshold must be between 0 and 7");
@@ -141,7 +149,7 @@ impl PLIC {
     ///
     /// Return the active interrupt number if there is an active interrupt, otherwise return None.
     pub fn claim(&self, hart_id: usize, is_s_mode: bool) -> Option<u32> {
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let offset = 0x200004 + context_id * 4096;
         let value = self.io_mem.read_once::<u32>(offset).unwrap();
         if value == 0 {
@@ -153,43 +161,95 @@ impl PLIC {
 
     /// Complete an interrupt.
     pub fn complete(&self, hart_id: usize, is_s_mode: bool, interrupt: u8) {
-        let context_id = Self::get_context_id(hart_id, is_s_mode);
+        let context_id = self.get_context_id(hart_id, is_s_mode);
         let offset = 0x200004 + context_id * 4096;
         self.io_mem
             .write_once::<u32>(offset, &(interrupt as u32))
             .unwrap();
     }
 
-    fn get_context_id(hart_id: usize, is_s_mode: bool) -> usize {
+    fn get_context_id(&self, hart_id: usize, is_s_mode: bool) -> usize {
+        if let Some(context_id) = self.contexts.get(&(hart_id, is_s_mode)) {
+            return *context_id;
+        }
+
+        // Fall back to the layout used by QEMU and most SoCs, where each hart
+        // has a machine-mode context followed by a supervisor-mode context.
         if is_s_mode {
-            hart_id + 1
+            hart_id * 2 + 1
         } else {
-            hart_id
+            hart_id * 2
         }
     }
 }
 
+/// Parses the contexts of the PLIC from the device tree.
+///
+/// The `interrupts-extended` property of the PLIC lists the contexts as pairs
+/// of the phandle of a hart-local interrupt controller and the interrupt cause
+/// of the context. The index of a pair is the context ID.
+fn parse_contexts(plic_node: &FdtNode) -> BTreeMap<(usize, bool), usize> {
+    let intc_harts = hart_ids_of_intcs();
+
+    let mut contexts = BTreeMap::new();
+    for (context_id, (phandle, cause)) in interrupts_extended(plic_node).enumerate() {
+        let Some(hart_id) = intc_harts.get(&phandle) else {
+            continue;
+        };
+        match cause {
+            SUPERVISOR_EXTERNAL => {
+                contexts.insert((*hart_id, true), context_id);
+            }
+            MACHINE_EXTERNAL => {
+                contexts.insert((*hart_id, false), context_id);
+            }
+            _ => {}
+        }
+    }
+
+    contexts
+}
+
 pub static PLIC: Once<PLIC> = Once::new();
 
 pub fn init(io_mem_builder: &IoMemAllocatorBuilder) {
-    let node = DEVICE_TREE.get().unwrap().find_node("/soc/plic").unwrap();
+    // Platforms with the Advanced Interrupt Architecture (AIA) may have no PLIC.
+    let Some(node) = DEVICE_TREE.get().unwrap().find_node("/soc/plic") else {
+        return;
+    };
     if let Some(compatible) = node.compatible()
         && compatible
             .all()
             .any(|c| c == "sifive,plic-1.0.0" || c == "riscv,plic0")
     {
         let region = node.reg().unwrap().next().unwrap();
-        PLIC.call_once(|| PLIC::new(region.starting_address as usize, region.size.unwrap()));
+        PLIC.call_once(|| {
+            let mut plic = PLIC::new(region.starting_address as usize, region.size.unwrap());
+            plic.contexts = parse_contexts(&node);
+            plic
+        });
         io_mem_builder.remove(
             region.starting_address as usize
                 ..region.starting_address as usize + region.size.unwrap(),
         );
-
-        // SAFETY: Now we can start the external interrupts.
-        unsafe { riscv::register::sie::set_sext() };
     }
 }
 
+/// Initializes the PLIC context of the current hart.
+///
+/// This should be called on each CPU after [`init`] is called on the BSP.
+pub(crate) fn init_current_hart() {
+    let Some(plic) = PLIC.get() else {
+        return;
+    };
+
+    // Accept all the interrupts enabled for this hart, regardless of their priorities.
+    plic.set_threshold(this_hart_id() as usize, true, 0);
+
+    // SAFETY: Now we can start the external interrupts.
+    unsafe { riscv::register::sie::set_sext() };
+}
+
 // pub fn handle_external_interrupt(f: &mut TrapFrame) {
 //     let plic = PLIC.get().unwrap();
 //     let hart_id = 0;
@@ -17,42 +17,63 @@ pub(crate) mod task;
 pub mod timer;
 pub mod trap;
 
-use crate::cpu::current_cpu_racy;
-
 #[cfg(feature = "cvm_guest")]
 pub(crate) fn init_cvm_guest() {
     // Unimplemented, no-op
 }
 
 pub(crate) unsafe fn late_init_on_bsp() {
+    // SAFETY: This function is called in the boot context of the BSP, and the
+    // hart ID is recorded by the boot entry.
+    unsafe { cpu::set_this_hart_id(boot::smp::bsp_hart_id()) };
+
     // SAFETY: This function is called in the boot context of the BSP.
     unsafe { trap::init() };
 
     let io_mem_builder = io::construct_io_mem_allocator_builder();
 
+    kernel::plic::init(&io_mem_builder);
+    kernel::imsic::init(&io_mem_builder);
+    kernel::aplic::init(&io_mem_builder);
+    kernel::plic::init_current_hart();
+    kernel::imsic::init_current_hart();
+    irq::enable_ipi();
+
+    // SAFETY: This function is called once and at most once at a proper timing
+    // in the boot context of the BSP, with no timer-related operations having
+    // been performed.
+    unsafe { timer::init() };
+
     // SAFETY: We're on the BSP and we're ready to boot all APs.
     unsafe { crate::boot::smp::boot_all_aps() };
 
-    kernel::plic::init(&io_mem_builder);
-
     // SAFETY:
     // 1. All the system device memory have been removed from the builder.
     // 2. RISC-V platforms does not have port I/O.
     unsafe { crate::io::init(io_mem_builder) };
 
-    // SAFETY: This function is called once and at most once at a proper timing
-    // in the boot context of the BSP, with no timer-related operations having
-    // been performed.
-    unsafe { timer::init() };
     let _ = pci::init();
 }
 
+/// Architecture-specific initialization on the application processor.
+///
+/// # Safety
+///
+/// This function must be called only once on each application processor.
+/// And it should be called after the BSP's call to [`late_init_on_bsp`].
 pub(crate) unsafe fn init_on_ap() {
-    unimplemented!()
+    kernel::plic::init_current_hart();
+    kernel::imsic::init_current_hart();
+    irq::enable_ipi();
+    timer::init_ap();
 }
 
 pub(crate) fn interrupts_ack(irq_number: usize) {
-    kernel::plic::complete_interrupt(current_cpu_racy().as_usize(), irq_number);
+    // The IPIs are not delivered by the PLIC or the APLIC. But it is fine to
+    // complete them here, because the interrupt controllers ignore the
+    // completion of the IRQ numbers that are not enabled, and the IRQ numbers
+    // of the IPIs are never enabled.
+    kernel::complete_interrupt(irq_number);
 }
 
 /// Return the frequency of TSC. The unit is Hz.
@@ -80,4 +101,8 @@ pub(crate) fn enable_cpu_features() {
         // enabled before the first FPU trap.
         riscv::register::sstatus::set_fs(riscv::register::sstatus::FS::Off);
     }
+    // SAFETY: Allowing the user space to read the `time` CSR does not affect
+    // the kernel. This is required by the vDSO to get the time without
+    // system calls.
+    unsafe { riscv::register::scounteren::set_tm() };
 }
@@ -5,7 +5,10 @@
 use log::warn;
 use spin::Once;
 
-use super::boot::DEVICE_TREE;
+use super::{
+    boot::{smp::bsp_hart_id, DEVICE_TREE},
+    kernel::IMSIC,
+};
 use crate::{bus::pci::PciDeviceLocation, io::IoMem, mm::VmIoOnce, prelude::*, Error};
 
 static PCI_BASE_ADDR: Once<IoMem> = Once::new();
@@ -60,10 +63,26 @@ pub(crate) fn init() -> Result<()> {
     Ok(())
 }
 
-pub(crate) const MSIX_DEFAULT_MSG_ADDR: u32 = 0x2400_0000;
+/// Returns the message address of the MSI-X table entries.
+///
+/// The MSIs are written to the IMSIC interrupt file of the BSP, and the
+/// message data is the IRQ number. If the platform has no IMSIC, the MSIs
+/// cannot be delivered.
+// TODO: Support the wired interrupts of PCI devices through the PLIC.
+pub(crate) fn default_msix_address() -> u64 {
+    IMSIC
+        .get()
+        .and_then(|imsic| imsic.msi_address(bsp_hart_id()))
+        .unwrap_or_else(|| {
+            warn!("MSIs cannot be delivered without an IMSIC");
+            0
+        }) as u64
+}
 
 pub(crate) fn construct_remappable_msix_address(remapping_index: u32) -> u32 {
-    unimplemented!()
+    // The IMSIC receives the MSIs directly, so there is no interrupt
+    // remapping, and `IrqRemapping::remapping_index` always returns `None`.
+    unreachable!("interrupt remapping is not supported on RISC-V")
 }
 
 /// Encodes the bus, device, and function into an address offset in the PCI MMIO region.
@@ -66,6 +66,15 @@ pub(super) unsafe fn init() {
     }
 }
 
+/// Initializes the timer on this AP.
+///
+/// This function should be called on each AP after [`init`] is called on
+/// the BSP.
+pub(super) fn init_ap() {
+    // Like the BSP, the timer interrupt is not enabled in `sie` yet.
+    set_next_timer();
+}
+
 pub(super) fn handle_timer_interrupt() {
     let irq_guard = trap::disable_local();
     if irq_guard.current_cpu() == CpuId::bsp() {
@@ -10,8 +10,10 @@ pub use trap::{GeneralRegs, TrapFrame, UserContext};
 
 use super::cpu::context::CpuExceptionInfo;
 use crate::{
-    arch::kernel::plic::claim_interrupt, cpu::current_cpu_racy, cpu_local_cell,
-    mm::MAX_USERSPACE_VADDR, trap::call_irq_callback_functions,
+    arch::{irq::handle_ipi, kernel::claim_interrupt},
+    cpu_local_cell,
+    mm::MAX_USERSPACE_VADDR,
+    trap::call_irq_callback_functions,
 };
 
 cpu_local_cell! {
@@ -43,13 +45,15 @@ extern "C" fn trap_handler(f: &mut TrapFrame) {
                     crate::arch::timer::handle_timer_interrupt();
                 }
                 Interrupt::SupervisorExternal => {
-                    while let irq_num = claim_interrupt(current_cpu_racy().as_usize())
+                    while let irq_num = claim_interrupt()
                         && irq_num != 0
                     {
                         call_irq_callback_functions(f, irq_num);
                     }
                 }
-                Interrupt::SupervisorSoft => todo!(),
+                Interrupt::SupervisorSoft => {
+                    handle_ipi(f);
+                }
                 _ => {
                     panic!(
                         "cannot handle unknown supervisor interrupt: {interrupt:?}. trapframe: {f:#x?}.",
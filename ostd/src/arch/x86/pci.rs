@@ -25,7 +25,12 @@ pub(crate) fn has_pci_bus() -> bool {
     true
 }
 
-pub(crate) const MSIX_DEFAULT_MSG_ADDR: u32 = 0xFEE0_0000;
+const MSIX_DEFAULT_MSG_ADDR: u32 = 0xFEE0_0000;
+
+/// Returns the message address of the MSI-X table entries.
+pub(crate) fn default_msix_address() -> u64 {
+    MSIX_DEFAULT_MSG_ADDR as u64
+}
 
 pub(crate) fn construct_remappable_msix_address(remapping_index: u32) -> u32 {
     // Use remappable format. The bits[4:3] should be always set to 1 according to the manual.
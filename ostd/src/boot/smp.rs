@@ -124,7 +124,7 @@ pub fn register_ap_entry(entry: fn()) {
 }
 
 #[no_mangle]
-fn ap_early_entry(cpu_id: u32) -> ! {
+pub(crate) fn ap_early_entry(cpu_id: u32) -> ! {
     // SAFETY: `cpu_id` is the correct value of the CPU ID.
     unsafe { crate::cpu::init_on_ap(cpu_id) };
 
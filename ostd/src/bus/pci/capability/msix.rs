@@ -8,7 +8,7 @@
 use alloc::{sync::Arc, vec::Vec};
 
 use crate::{
-    arch::pci::{construct_remappable_msix_address, MSIX_DEFAULT_MSG_ADDR},
+    arch::pci::{construct_remappable_msix_address, default_msix_address},
     bus::pci::{
         cfg_space::{Bar, Command, MemoryBar},
         common_device::PciCommonDevice,
@@ -90,11 +90,11 @@ impl CapabilityMsixData {
         let table_offset = (table_info & !(0b111u32)) as usize;
 
         let table_size = (dev.location().read16(cap_ptr + 2) & 0b11_1111_1111) + 1;
-        // TODO: Different architecture seems to have different, so we should set different address here.
-        let message_address = MSIX_DEFAULT_MSG_ADDR;
-        let message_upper_address = 0u32;
+        let msix_address = default_msix_address();
+        let message_address = msix_address as u32;
+        let message_upper_address = (msix_address >> 32) as u32;
 
-        // Set message address 0xFEE0_0000
+        // Set message address
         for i in 0..table_size {
             // Set message address and disable this msix entry
             table_bar
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/ip.h>
+#include <netinet/ip_icmp.h>
+#include <arpa/inet.h>
+
+#include "test.h"
+
+static struct sockaddr_in sk_loopback;
+
+#define C_ID htons(0x1234)
+
+FN_SETUP(general)
+{
+	sk_loopback.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &sk_loopback.sin_addr));
+}
+END_SETUP()
+
+static unsigned short checksum(const void *data, size_t len)
+{
+	const unsigned short *words = data;
+	unsigned int sum = 0;
+
+	for (; len > 1; len -= 2)
+		sum += *words++;
+	if (len == 1)
+		sum += *(const unsigned char *)words;
+	sum = (sum >> 16) + (sum & 0xffff);
+	sum += sum >> 16;
+
+	return ~sum;
+}
+
+static void fill_echo(struct icmphdr *hdr, unsigned short seq)
+{
+	hdr->type = ICMP_ECHO;
+	hdr->code = 0;
+	hdr->checksum = 0;
+	hdr->un.echo.id = C_ID;
+	hdr->un.echo.sequence = htons(seq);
+	hdr->checksum = checksum(hdr, sizeof(*hdr));
+}
+
+FN_TEST(raw_echo)
+{
+	int sk, i;
+	struct icmphdr req;
+	char buf[128];
+	struct iphdr *ip = (struct iphdr *)buf;
+	struct icmphdr *reply;
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_RAW, IPPROTO_ICMP));
+
+	fill_echo(&req, 1);
+	TEST_RES(sendto(sk, &req, sizeof(req), 0,
+			(struct sockaddr *)&sk_loopback, sizeof(sk_loopback)),
+		 _ret == sizeof(req));
+
+	// Raw sockets see the request itself as well as the reply, both with the IP
+	// headers.
+	for (i = 0; i < 2; ++i) {
+		TEST_RES(recv(sk, buf, sizeof(buf), 0),
+			 _ret == ip->ihl * 4 + sizeof(req) &&
+				 ip->protocol == IPPROTO_ICMP);
+		reply = (struct icmphdr *)(buf + ip->ihl * 4);
+		if (reply->type == ICMP_ECHOREPLY)
+			break;
+	}
+	TEST_RES(0, reply->type == ICMP_ECHOREPLY && reply->un.echo.id == C_ID &&
+			    reply->un.echo.sequence == htons(1));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(ping_echo)
+{
+	int sk;
+	struct icmphdr req, reply;
+	struct sockaddr_in saddr;
+	socklen_t addrlen;
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, IPPROTO_ICMP));
+
+	// The identifier and the checksum are filled by the kernel.
+	memset(&req, 0, sizeof(req));
+	req.type = ICMP_ECHO;
+	req.un.echo.sequence = htons(2);
+	TEST_RES(sendto(sk, &req, sizeof(req), 0,
+			(struct sockaddr *)&sk_loopback, sizeof(sk_loopback)),
+		 _ret == sizeof(req));
+
+	addrlen = sizeof(saddr);
+	TEST_RES(getsockname(sk, (struct sockaddr *)&saddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin_port != 0);
+
+	// Ping sockets only see the reply, without the IP header.
+	TEST_RES(recv(sk, &reply, sizeof(reply), 0),
+		 _ret == sizeof(reply) && reply.type == ICMP_ECHOREPLY &&
+			 reply.un.echo.id == saddr.sin_port &&
+			 reply.un.echo.sequence == htons(2));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(ping_invalid)
+{
+	int sk;
+	struct icmphdr req;
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, IPPROTO_ICMP));
+
+	memset(&req, 0, sizeof(req));
+	req.type = ICMP_ECHOREPLY;
+	TEST_ERRNO(sendto(sk, &req, sizeof(req), 0,
+			  (struct sockaddr *)&sk_loopback,
+			  sizeof(sk_loopback)),
+		   EINVAL);
+
+	req.type = ICMP_ECHO;
+	TEST_ERRNO(sendto(sk, &req, sizeof(req) - 1, 0,
+			  (struct sockaddr *)&sk_loopback,
+			  sizeof(sk_loopback)),
+		   EINVAL);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+
+#include "test.h"
+
+static struct sockaddr_in6 sk_loopback6;
+static struct sockaddr_in6 sk_mapped;
+static struct sockaddr_in6 sk_any6;
+static struct sockaddr_in sk_loopback;
+static struct sockaddr_in sk_any;
+
+#define C_PORT6 htons(0x2345)
+#define C_PORT_MAPPED htons(0x2346)
+
+FN_SETUP(general)
+{
+	sk_loopback6.sin6_family = AF_INET6;
+	sk_loopback6.sin6_addr = in6addr_loopback;
+
+	sk_mapped.sin6_family = AF_INET6;
+	CHECK(inet_pton(AF_INET6, "::ffff:127.0.0.1", &sk_mapped.sin6_addr));
+
+	sk_any6.sin6_family = AF_INET6;
+	sk_any6.sin6_addr = in6addr_any;
+
+	sk_loopback.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &sk_loopback.sin_addr));
+
+	sk_any.sin_family = AF_INET;
+	sk_any.sin_addr.s_addr = htonl(INADDR_ANY);
+}
+END_SETUP()
+
+FN_TEST(bind_unspecified)
+{
+	int sk, one = 1;
+	struct sockaddr_in6 saddr6;
+	struct sockaddr_in saddr;
+	socklen_t addrlen;
+
+	sk = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	TEST_SUCC(setsockopt(sk, IPPROTO_IPV6, IPV6_V6ONLY, &one, sizeof(one)));
+	TEST_SUCC(bind(sk, (struct sockaddr *)&sk_any6, sizeof(sk_any6)));
+	addrlen = sizeof(saddr6);
+	TEST_RES(getsockname(sk, (struct sockaddr *)&saddr6, &addrlen),
+		 addrlen == sizeof(saddr6) && saddr6.sin6_family == AF_INET6 &&
+			 saddr6.sin6_port != 0);
+	TEST_SUCC(close(sk));
+
+	sk = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	TEST_SUCC(bind(sk, (struct sockaddr *)&sk_any6, sizeof(sk_any6)));
+	TEST_SUCC(close(sk));
+
+	sk = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, 0));
+	TEST_SUCC(bind(sk, (struct sockaddr *)&sk_any, sizeof(sk_any)));
+	addrlen = sizeof(saddr);
+	TEST_RES(getsockname(sk, (struct sockaddr *)&saddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin_port != 0);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(bind_mapped_v6only)
+{
+	int sk, one = 1;
+
+	sk = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	TEST_SUCC(setsockopt(sk, IPPROTO_IPV6, IPV6_V6ONLY, &one, sizeof(one)));
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&sk_mapped, sizeof(sk_mapped)),
+		   EINVAL);
+	TEST_ERRNO(connect(sk, (struct sockaddr *)&sk_mapped,
+			   sizeof(sk_mapped)),
+		   ENETUNREACH);
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(udp_loopback6)
+{
+	int sk_recv, sk_send;
+	struct sockaddr_in6 saddr6;
+	socklen_t addrlen;
+	char buf[4];
+
+	sk_recv = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	sk_loopback6.sin6_port = C_PORT6;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&sk_loopback6,
+		       sizeof(sk_loopback6)));
+
+	sk_send = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	TEST_RES(sendto(sk_send, "ping", 4, 0, (struct sockaddr *)&sk_loopback6,
+			sizeof(sk_loopback6)),
+		 _ret == 4);
+
+	addrlen = sizeof(saddr6);
+	TEST_RES(recvfrom(sk_recv, buf, sizeof(buf), 0,
+			  (struct sockaddr *)&saddr6, &addrlen),
+		 _ret == 4 && memcmp(buf, "ping", 4) == 0 &&
+			 addrlen == sizeof(saddr6) &&
+			 saddr6.sin6_family == AF_INET6 &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr6.sin6_addr));
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
+
+FN_TEST(udp_mapped)
+{
+	int sk_recv, sk_send;
+	struct sockaddr_in6 saddr6;
+	socklen_t addrlen;
+	char buf[4];
+
+	sk_recv = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	sk_mapped.sin6_port = C_PORT_MAPPED;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&sk_mapped,
+		       sizeof(sk_mapped)));
+
+	addrlen = sizeof(saddr6);
+	TEST_RES(getsockname(sk_recv, (struct sockaddr *)&saddr6, &addrlen),
+		 addrlen == sizeof(saddr6) && saddr6.sin6_family == AF_INET6 &&
+			 IN6_IS_ADDR_V4MAPPED(&saddr6.sin6_addr) &&
+			 saddr6.sin6_port == C_PORT_MAPPED);
+
+	sk_send = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, 0));
+	sk_loopback.sin_port = C_PORT_MAPPED;
+	TEST_RES(sendto(sk_send, "pong", 4, 0, (struct sockaddr *)&sk_loopback,
+			sizeof(sk_loopback)),
+		 _ret == 4);
+
+	addrlen = sizeof(saddr6);
+	TEST_RES(recvfrom(sk_recv, buf, sizeof(buf), 0,
+			  (struct sockaddr *)&saddr6, &addrlen),
+		 _ret == 4 && memcmp(buf, "pong", 4) == 0 &&
+			 addrlen == sizeof(saddr6) &&
+			 saddr6.sin6_family == AF_INET6 &&
+			 IN6_IS_ADDR_V4MAPPED(&saddr6.sin6_addr));
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
+
+FN_TEST(tcp_loopback6)
+{
+	int sk_listen, sk_connect, sk_accept;
+	struct sockaddr_in6 saddr6;
+	socklen_t addrlen;
+
+	sk_listen = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	sk_loopback6.sin6_port = C_PORT6;
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&sk_loopback6,
+		       sizeof(sk_loopback6)));
+	TEST_SUCC(listen(sk_listen, 1));
+
+	sk_connect = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_loopback6,
+			  sizeof(sk_loopback6)));
+
+	addrlen = sizeof(saddr6);
+	sk_accept = TEST_RES(accept(sk_listen, (struct sockaddr *)&saddr6,
+				    &addrlen),
+			     addrlen == sizeof(saddr6) &&
+				     saddr6.sin6_family == AF_INET6 &&
+				     IN6_IS_ADDR_LOOPBACK(&saddr6.sin6_addr));
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
+
+FN_TEST(tcp_mapped)
+{
+	int sk_listen, sk_connect, sk_accept;
+	struct sockaddr_in6 saddr6;
+	socklen_t addrlen;
+
+	sk_listen = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	sk_mapped.sin6_port = C_PORT_MAPPED;
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&sk_mapped,
+		       sizeof(sk_mapped)));
+	TEST_SUCC(listen(sk_listen, 1));
+
+	sk_connect = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	sk_loopback.sin_port = C_PORT_MAPPED;
+	TEST_SUCC(connect(sk_connect, (struct sockaddr *)&sk_loopback,
+			  sizeof(sk_loopback)));
+
+	addrlen = sizeof(saddr6);
+	sk_accept = TEST_RES(accept(sk_listen, (struct sockaddr *)&saddr6,
+				    &addrlen),
+			     addrlen == sizeof(saddr6) &&
+				     saddr6.sin6_family == AF_INET6 &&
+				     IN6_IS_ADDR_V4MAPPED(&saddr6.sin6_addr));
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#include <arpa/inet.h>
+#include <linux/capability.h>
 #include <net/if.h>
 #include <netlink/route/addr.h>
+#include <sys/syscall.h>
 #include <unistd.h>
 
 #include "test.h"
@@ -252,3 +255,157 @@ FN_TEST(bufsize_msgsize)
 	TEST_SUCC(close(sock_fd));
 }
 END_TEST()
+
+struct rtnl_req {
+	struct nlmsghdr hdr;
+	union {
+		struct ifaddrmsg ifa;
+		struct rtmsg rtm;
+	};
+	char attrs[64];
+};
+
+static void add_attr(struct nlmsghdr *hdr, int type, const void *data,
+		     int len)
+{
+	struct rtattr *rta =
+		(struct rtattr *)((char *)hdr + NLMSG_ALIGN(hdr->nlmsg_len));
+
+	rta->rta_type = type;
+	rta->rta_len = RTA_LENGTH(len);
+	memcpy(RTA_DATA(rta), data, len);
+	hdr->nlmsg_len = NLMSG_ALIGN(hdr->nlmsg_len) + RTA_ALIGN(rta->rta_len);
+}
+
+static void init_addr_req(struct rtnl_req *req, int type, int index,
+			  const char *addr, int prefix_len)
+{
+	struct in_addr in_addr;
+
+	memset(req, 0, sizeof(*req));
+	req->hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct ifaddrmsg));
+	req->hdr.nlmsg_type = type;
+	req->hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK;
+	if (type == RTM_NEWADDR)
+		req->hdr.nlmsg_flags |= NLM_F_CREATE | NLM_F_EXCL;
+	req->ifa.ifa_family = AF_INET;
+	req->ifa.ifa_prefixlen = prefix_len;
+	req->ifa.ifa_index = index;
+
+	inet_pton(AF_INET, addr, &in_addr);
+	add_attr(&req->hdr, IFA_LOCAL, &in_addr, sizeof(in_addr));
+}
+
+static void init_route_req(struct rtnl_req *req, int type, int index,
+			   const char *dst, int dst_len, const char *gateway)
+{
+	struct in_addr in_addr;
+
+	memset(req, 0, sizeof(*req));
+	req->hdr.nlmsg_len = NLMSG_LENGTH(sizeof(struct rtmsg));
+	req->hdr.nlmsg_type = type;
+	req->hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_ACK;
+	if (type == RTM_NEWROUTE)
+		req->hdr.nlmsg_flags |= NLM_F_CREATE | NLM_F_EXCL;
+	req->rtm.rtm_family = AF_INET;
+	req->rtm.rtm_dst_len = dst_len;
+	req->rtm.rtm_table = RT_TABLE_MAIN;
+	req->rtm.rtm_protocol = RTPROT_BOOT;
+	req->rtm.rtm_scope = RT_SCOPE_UNIVERSE;
+	req->rtm.rtm_type = RTN_UNICAST;
+
+	inet_pton(AF_INET, dst, &in_addr);
+	add_attr(&req->hdr, RTA_DST, &in_addr, sizeof(in_addr));
+	inet_pton(AF_INET, gateway, &in_addr);
+	add_attr(&req->hdr, RTA_GATEWAY, &in_addr, sizeof(in_addr));
+	add_attr(&req->hdr, RTA_OIF, &index, sizeof(index));
+}
+
+// Sends a request and receives its acknowledgement. The error in the
+// acknowledgement is reported via `errno`.
+static int rtnl_request(int sock_fd, struct rtnl_req *req)
+{
+	struct nlmsghdr *res = (struct nlmsghdr *)buffer;
+	int error;
+
+	if (send(sock_fd, req, req->hdr.nlmsg_len, 0) < 0)
+		return -1;
+	if (recv(sock_fd, buffer, BUFFER_SIZE, 0) < 0)
+		return -1;
+
+	if (res->nlmsg_type != NLMSG_ERROR) {
+		errno = EPROTO;
+		return -1;
+	}
+	error = ((struct nlmsgerr *)NLMSG_DATA(res))->error;
+	if (error != 0) {
+		errno = -error;
+		return -1;
+	}
+
+	return 0;
+}
+
+static int set_net_admin(int enabled)
+{
+	struct __user_cap_header_struct header = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2];
+
+	if (syscall(SYS_capget, &header, data) < 0)
+		return -1;
+
+	if (enabled)
+		data[0].effective |= 1 << CAP_NET_ADMIN;
+	else
+		data[0].effective &= ~(1 << CAP_NET_ADMIN);
+
+	return syscall(SYS_capset, &header, data);
+}
+
+FN_TEST(new_addr_and_route)
+{
+	struct rtnl_req req;
+	int sock_fd;
+	int lo_index;
+
+	sock_fd = TEST_SUCC(socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE));
+	lo_index = TEST_RES(if_nametoindex(LOOPBACK_NAME), _ret > 0);
+
+	// 1. Add and remove an address and a route via the address
+	init_addr_req(&req, RTM_NEWADDR, lo_index, "10.0.3.1", 24);
+	TEST_SUCC(rtnl_request(sock_fd, &req));
+	TEST_ERRNO(rtnl_request(sock_fd, &req), EEXIST);
+
+	init_route_req(&req, RTM_NEWROUTE, lo_index, "10.0.4.0", 24,
+		       "10.0.3.2");
+	TEST_SUCC(rtnl_request(sock_fd, &req));
+	TEST_ERRNO(rtnl_request(sock_fd, &req), EEXIST);
+
+	init_route_req(&req, RTM_DELROUTE, lo_index, "10.0.4.0", 24,
+		       "10.0.3.2");
+	TEST_SUCC(rtnl_request(sock_fd, &req));
+	TEST_ERRNO(rtnl_request(sock_fd, &req), ESRCH);
+
+	// 2. Changing the configuration requires CAP_NET_ADMIN
+	TEST_SUCC(set_net_admin(0));
+
+	init_addr_req(&req, RTM_NEWADDR, lo_index, "10.0.5.1", 24);
+	TEST_ERRNO(rtnl_request(sock_fd, &req), EPERM);
+	init_addr_req(&req, RTM_DELADDR, lo_index, "10.0.3.1", 24);
+	TEST_ERRNO(rtnl_request(sock_fd, &req), EPERM);
+	init_route_req(&req, RTM_NEWROUTE, lo_index, "10.0.4.0", 24,
+		       "10.0.3.2");
+	TEST_ERRNO(rtnl_request(sock_fd, &req), EPERM);
+
+	TEST_SUCC(set_net_admin(1));
+
+	init_addr_req(&req, RTM_DELADDR, lo_index, "10.0.3.1", 24);
+	TEST_SUCC(rtnl_request(sock_fd, &req));
+	TEST_ERRNO(rtnl_request(sock_fd, &req), EADDRNOTAVAIL);
+
+	TEST_SUCC(close(sock_fd));
+}
+END_TEST()
@@ -719,3 +719,56 @@ FN_TEST(zero_sends_may_fail)
 	TEST_SUCC(close(fildes[1]));
 }
 END_TEST()
+
+FN_TEST(message_trunc)
+{
+	int fildes[2];
+	char buf[4];
+	struct iovec iov = { .iov_base = buf, .iov_len = 2 };
+	struct msghdr msg = { .msg_iov = &iov, .msg_iovlen = 1 };
+	int types[] = { SOCK_SEQPACKET, SOCK_DGRAM };
+
+	for (int i = 0; i < 2; ++i) {
+		TEST_SUCC(socketpair(AF_UNIX, types[i] | SOCK_NONBLOCK, 0,
+				     fildes));
+
+		TEST_RES(send(fildes[1], "abc", 3, 0), _ret == 3);
+		TEST_RES(recvmsg(fildes[0], &msg, 0),
+			 _ret == 2 && msg.msg_flags == MSG_TRUNC);
+
+		TEST_RES(send(fildes[1], "abc", 3, 0), _ret == 3);
+		TEST_RES(recv(fildes[0], buf, 2, MSG_TRUNC), _ret == 3);
+
+		TEST_RES(send(fildes[1], "ab", 2, 0), _ret == 2);
+		TEST_RES(recvmsg(fildes[0], &msg, 0),
+			 _ret == 2 && msg.msg_flags == 0);
+
+		TEST_ERRNO(recv(fildes[0], buf, sizeof(buf), 0), EAGAIN);
+
+		TEST_SUCC(close(fildes[0]));
+		TEST_SUCC(close(fildes[1]));
+	}
+}
+END_TEST()
+
+FN_TEST(connected_dgram_filter)
+{
+	int fildes[2];
+	int sk;
+	struct sockaddr_un addr = { .sun_family = AF_UNIX,
+				    .sun_path = "\0unix_err_dgram" };
+	socklen_t addrlen = PATH_OFFSET + 15;
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0, fildes));
+	TEST_SUCC(bind(fildes[0], (struct sockaddr *)&addr, addrlen));
+
+	sk = TEST_SUCC(socket(AF_UNIX, SOCK_DGRAM, 0));
+	TEST_ERRNO(sendto(sk, "a", 1, 0, (struct sockaddr *)&addr, addrlen),
+		   EPERM);
+	TEST_RES(send(fildes[1], "b", 1, 0), _ret == 1);
+
+	TEST_SUCC(close(sk));
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
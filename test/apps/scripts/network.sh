@@ -32,6 +32,8 @@ sleep 0.2
 ./tcp_err
 ./tcp_poll
 ./udp_err
+./ipv6_err
+./icmp_err
 ./unix_err
 
 ./netlink_route